@@ -0,0 +1,11 @@
+// Generates the gRPC server code from proto/true_ledger.proto.
+// protox parses the schema in pure Rust, so no protoc install is needed.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("cargo:rerun-if-changed=proto/true_ledger.proto");
+    let descriptors = protox::compile(["true_ledger.proto"], ["proto"])?;
+    tonic_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)?;
+    Ok(())
+}
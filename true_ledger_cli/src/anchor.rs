@@ -0,0 +1,165 @@
+//! Anchoring Checkpoints
+//! `tlc anchor submit` publishes a signed checkpoint's digest to
+//! OpenTimestamps or, through a bitcoind wallet, in an OP_RETURN output;
+//! `tlc anchor upgrade` completes a pending OpenTimestamps proof; and
+//! `tlc anchor verify` checks the proof against Bitcoin, showing the
+//! checkpoint existed no later than the block it landed in.
+
+use clap::{Args, Subcommand, ValueEnum};
+use std::fs;
+use true_ledger_core::anchor::{Anchor, AnchorBackend, AnchorStatus, OpReturn, OpenTimestamps, DEFAULT_CALENDARS, DEFAULT_ESPLORA};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::Timestamp;
+
+use crate::checkpoint::load_checkpoint;
+use crate::signing::write_json;
+
+#[derive(Subcommand, Debug)]
+pub enum AnchorCommand {
+    /// Publish a signed checkpoint's digest
+    Submit {
+        /// Signed checkpoint written by `tlc checkpoint`
+        checkpoint: String,
+
+        /// Where to publish it
+        #[arg(long, value_enum, default_value = "opentimestamps")]
+        backend: BackendKind,
+
+        /// Where to write the anchor
+        #[arg(long = "out", value_name = "FILE", default_value = "anchor.json")]
+        out_path: String,
+
+        #[command(flatten)]
+        backend_args: BackendArgs,
+    },
+
+    /// Ask the OpenTimestamps calendars to complete a pending proof (updates the file)
+    Upgrade {
+        /// Anchor written by `tlc anchor submit`
+        anchor: String,
+
+        #[command(flatten)]
+        backend_args: BackendArgs,
+    },
+
+    /// Check an anchor against Bitcoin and against the checkpoint it is for
+    Verify {
+        /// Anchor written by `tlc anchor submit`
+        anchor: String,
+
+        /// The signed checkpoint that was anchored
+        #[arg(long, value_name = "FILE")]
+        checkpoint: String,
+
+        /// DID the checkpoint must be signed by
+        #[arg(long, value_name = "DID")]
+        operator: Option<String>,
+
+        #[command(flatten)]
+        backend_args: BackendArgs,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum BackendKind {
+    /// Public OpenTimestamps calendars, aggregated into Bitcoin (free, takes hours)
+    Opentimestamps,
+    /// An OP_RETURN output in a transaction from a bitcoind wallet
+    OpReturn,
+}
+
+/// Where the backends are reached
+#[derive(Args, Debug)]
+pub struct BackendArgs {
+    /// OpenTimestamps calendar to submit to, repeatable (default: the public pool)
+    #[arg(long = "calendar", value_name = "URL")]
+    pub calendars: Vec<String>,
+
+    /// Esplora API to look up Bitcoin block headers
+    #[arg(long, value_name = "URL", default_value = DEFAULT_ESPLORA)]
+    pub esplora: String,
+
+    /// bitcoind JSON-RPC endpoint, for OP_RETURN anchors
+    #[arg(long, value_name = "URL", default_value = "http://127.0.0.1:8332")]
+    pub bitcoin_rpc: String,
+
+    /// bitcoind RPC user (the password is read from $TLC_BITCOIN_RPC_PASSWORD)
+    #[arg(long, value_name = "USER")]
+    pub rpc_user: Option<String>,
+}
+
+impl BackendArgs {
+    fn backend(&self, kind: BackendKind) -> Box<dyn AnchorBackend> {
+        match kind {
+            BackendKind::Opentimestamps => Box::new(OpenTimestamps {
+                calendars: match self.calendars.is_empty() {
+                    true => DEFAULT_CALENDARS.iter().map(|c| c.to_string()).collect(),
+                    false => self.calendars.clone(),
+                },
+                esplora: self.esplora.clone(),
+            }),
+            BackendKind::OpReturn => Box::new(OpReturn {
+                rpc_url: self.bitcoin_rpc.clone(),
+                rpc_user: self.rpc_user.clone(),
+                rpc_password: std::env::var("TLC_BITCOIN_RPC_PASSWORD").ok(),
+            }),
+        }
+    }
+
+    /// The backend an existing anchor was made with
+    fn backend_for(&self, anchor: &Anchor) -> Box<dyn AnchorBackend> {
+        match anchor {
+            Anchor::OpenTimestamps { .. } => self.backend(BackendKind::Opentimestamps),
+            Anchor::OpReturn { .. } => self.backend(BackendKind::OpReturn),
+        }
+    }
+}
+
+fn load_anchor(path: &str) -> Result<Anchor, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    serde_json::from_str(&data).map_err(|e| format!("Invalid anchor {}: {}", path, e))
+}
+
+pub fn run_anchor(command: &AnchorCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        AnchorCommand::Submit { checkpoint, backend, out_path, backend_args } => {
+            let digest = load_checkpoint(checkpoint, None, resolver)?.digest()?;
+            println!("\n⚓ Anchoring {} ({})...", checkpoint, hex::encode(digest));
+            let anchor = backend_args.backend(*backend).submit(&digest)?;
+            write_json(&anchor, out_path)?;
+            println!("💾 Anchor saved to {}", out_path);
+            if let Anchor::OpReturn { txid, .. } = &anchor {
+                println!("   Bitcoin transaction: {}", txid);
+            }
+            println!("   Run `tlc anchor verify` once it is confirmed{}",
+                if matches!(anchor, Anchor::OpenTimestamps { .. }) { " (after `tlc anchor upgrade`)" } else { "" });
+            Ok(())
+        }
+        AnchorCommand::Upgrade { anchor: path, backend_args } => {
+            let anchor = load_anchor(path)?;
+            let upgraded = backend_args.backend_for(&anchor).upgrade(&anchor)?;
+            write_json(&upgraded, path)?;
+            match backend_args.backend_for(&upgraded).verify(&upgraded)? {
+                AnchorStatus::Confirmed { block_height, .. } => println!("✅ {} is complete (Bitcoin block {})", path, block_height),
+                AnchorStatus::Pending(waiting) => println!("⏳ {} is still pending: {}", path, waiting),
+            }
+            Ok(())
+        }
+        AnchorCommand::Verify { anchor: path, checkpoint, operator, backend_args } => {
+            let anchor = load_anchor(path)?;
+            let digest = load_checkpoint(checkpoint, operator.as_deref(), resolver)?.digest()?;
+            if anchor.digest()? != digest {
+                return Err(format!("{} anchors {}, but {} has digest {}",
+                    path, hex::encode(anchor.digest()?), checkpoint, hex::encode(digest)));
+            }
+            match backend_args.backend_for(&anchor).verify(&anchor)? {
+                AnchorStatus::Confirmed { block_height, block_time } => {
+                    println!("\n🎉 **ANCHORED**: {} existed by Bitcoin block {} ({})",
+                        checkpoint, block_height, Timestamp::from(block_time).utc());
+                    Ok(())
+                }
+                AnchorStatus::Pending(waiting) => Err(format!("{} is not confirmed yet: {}", path, waiting)),
+            }
+        }
+    }
+}
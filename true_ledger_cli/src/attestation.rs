@@ -0,0 +1,60 @@
+//! Period Attestation
+//! `tlc attest <dir|ledger> --period YYYY-MM-DD|PERIOD` signs management's
+//! sign-off on a period: the digest of its closing trial balance (see the
+//! core attestation.rs). `tlc report attestations` later checks each
+//! sign-off against the books as they are then.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::attestation::Attestation;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::fiscal::{Cutoff, FiscalCalendar};
+use true_ledger_core::trial_balance::TrialBalance;
+use true_ledger_core::verify::verify_with;
+
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+use crate::store::journal_entries;
+
+/// The trial balance of the verified transactions in `journal` that fall
+/// within `cutoff`; those that fail verification are left out, with a warning
+pub fn books_through(journal: &str, cutoff: Cutoff, resolver: &dyn DidResolver) -> Result<TrialBalance, String> {
+    let mut trial_balance = TrialBalance::new();
+    for entry in journal_entries(journal)? {
+        let (name, signed_tx) = entry?;
+        if !cutoff.includes(&signed_tx.payload) {
+            continue;
+        }
+        match verify_with(&signed_tx, resolver) {
+            Ok(()) => trial_balance.post(&signed_tx.payload)?,
+            Err(e) => eprintln!("⚠️  Skipped {}: {}", name, e),
+        }
+    }
+    Ok(trial_balance)
+}
+
+/// `tlc attest JOURNAL --period DATE|PERIOD [--fiscal FILE] [--role ROLE]`
+pub fn run_attest(journal: &str, period: &str, fiscal: Option<&str>, role: Option<&str>, out_path: &str, signer_args: &SignerArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let calendar = fiscal.map(FiscalCalendar::load).transpose()?.unwrap_or_default();
+    let through = calendar.cutoff(period)?;
+    let books = books_through(journal, through, resolver)?;
+    books.check()?; // Nobody signs off on books that do not balance
+
+    let signer = signer_from_args(signer_args)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let signed = Attestation::new(signer.did(), role.map(str::to_string), period, through, &books, now.into())?
+        .sign(signer.as_ref())?;
+
+    write_json(&signed, out_path)?;
+    let attestation = &signed.attestation;
+    let (debits, _) = books.totals()?;
+    println!("\n🖋️  {} signed off on {} (through {}{})", attestation.attested_by, attestation.period, attestation.through,
+        if attestation.adjustments { ", adjustments included" } else { "" });
+    if let Some(role) = &attestation.role {
+        println!("   > As: {}", role);
+    }
+    println!("   > Trial balance: {} transaction(s), {} debits and credits", attestation.transactions, format_cents(debits));
+    println!("   > Digest: {}", attestation.trial_balance);
+    println!("💾 Signed attestation saved to {}", out_path);
+    Ok(())
+}
@@ -0,0 +1,302 @@
+//! API Authentication
+//! `tlc serve --auth FILE` requires every request to carry
+//! `Authorization: Bearer <credential>`, either a static API key or a JWT,
+//! and checks that it grants the route's scope:
+//!
+//!   read    POST /verify, GET /transactions/<hash>
+//!   submit  POST /transactions
+//!   admin   everything
+//!
+//! ```toml
+//! [[api_key]]
+//! name = "billing"             # Shown in the server log
+//! sha256 = "9f86d08..."        # Hex SHA-256 of the key (`printf %s KEY | sha256sum`); the key is not stored
+//! scopes = ["read", "submit"]
+//!
+//! [jwt]                        # HS256 tokens from your identity provider
+//! secret_env = "TLC_JWT_SECRET" # Environment variable holding the shared secret
+//! issuer = "https://auth.example"  # Required "iss", if set
+//! audience = "tlc"             # Required in "aud", if set
+//! ```
+//!
+//! A JWT must carry `exp`; its space-separated `scope` claim lists its
+//! scopes. Without `--auth` the server is open, which is only safe on
+//! localhost.
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zeroize::Zeroizing;
+
+/// What a credential may do
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Submit,
+    Admin,
+}
+
+impl Scope {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "read" => Some(Scope::Read),
+            "submit" => Some(Scope::Submit),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Submit => "submit",
+            Scope::Admin => "admin",
+        }
+    }
+}
+
+/// One `[[api_key]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApiKeyConfig {
+    pub name: String,
+    pub sha256: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// The `[jwt]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct JwtConfig {
+    pub secret_env: String,
+    pub issuer: Option<String>,
+    pub audience: Option<String>,
+}
+
+/// An --auth file
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub api_key: Vec<ApiKeyConfig>,
+    pub jwt: Option<JwtConfig>,
+}
+
+/// Who made a request, and what they may do
+pub struct Principal {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// Why a request was refused: 401 (no valid credential) or 403 (not allowed)
+pub struct Refusal {
+    pub status: u16,
+    pub message: String,
+}
+
+fn unauthorized(message: impl Into<String>) -> Refusal {
+    Refusal { status: 401, message: message.into() }
+}
+
+/// The credentials a server accepts
+pub struct Auth {
+    api_keys: Vec<ApiKeyConfig>,
+    jwt: Option<(JwtConfig, Zeroizing<Vec<u8>>)>, // With its secret
+}
+
+/// The claims of a JWT this server reads
+#[derive(Deserialize)]
+struct Claims {
+    sub: Option<String>,
+    iss: Option<String>,
+    aud: Option<Audience>,
+    exp: Option<u64>,
+    nbf: Option<u64>,
+    #[serde(default)]
+    scope: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Auth {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let config: AuthConfig = toml::from_str(&data).map_err(|e| format!("Invalid auth file {}: {}", path, e))?;
+        if config.api_key.is_empty() && config.jwt.is_none() {
+            return Err(format!("{} accepts no credentials: add an [[api_key]] or a [jwt] table", path));
+        }
+        let mut api_keys = config.api_key;
+        for key in &mut api_keys {
+            key.sha256 = key.sha256.to_lowercase();
+            if hex::decode(&key.sha256).map(|digest| digest.len()) != Ok(32) {
+                return Err(format!("API key '{}': sha256 must be 64 hex digits", key.name));
+            }
+        }
+        let jwt = match config.jwt {
+            Some(jwt) => {
+                let secret = std::env::var(&jwt.secret_env)
+                    .map_err(|_| format!("[jwt]: the environment variable {} is not set", jwt.secret_env))?;
+                if secret.len() < 32 {
+                    return Err(format!("[jwt]: the secret in {} must be at least 32 bytes", jwt.secret_env));
+                }
+                Some((jwt, Zeroizing::new(secret.into_bytes())))
+            }
+            None => None,
+        };
+        Ok(Auth { api_keys, jwt })
+    }
+
+    /// What `other` would also accept: an API key with the same digest, or a
+    /// JWT signed with the same secret
+    pub fn shared_with(&self, other: &Auth) -> Option<String> {
+        if let Some(key) = self.api_keys.iter().find(|key| other.api_keys.iter().any(|theirs| theirs.sha256 == key.sha256)) {
+            return Some(format!("API key '{}'", key.name));
+        }
+        match (&self.jwt, &other.jwt) {
+            (Some((jwt, secret)), Some((_, theirs))) if secret == theirs => Some(format!("the JWT secret in {}", jwt.secret_env)),
+            _ => None,
+        }
+    }
+
+    /// The principal behind an Authorization header, if it is allowed `scope`
+    pub fn authorize(&self, authorization: Option<&str>, scope: Scope) -> Result<Principal, Refusal> {
+        let header = authorization.ok_or_else(|| unauthorized("Missing Authorization header"))?;
+        let credential = header.strip_prefix("Bearer ").map(str::trim)
+            .ok_or_else(|| unauthorized("Expected Authorization: Bearer <API key or JWT>"))?;
+        let principal = if credential.matches('.').count() == 2 {
+            self.check_jwt(credential)?
+        } else {
+            self.check_api_key(credential)?
+        };
+        if !principal.scopes.iter().any(|granted| *granted == scope || *granted == Scope::Admin) {
+            return Err(Refusal { status: 403, message: format!("{} lacks the '{}' scope", principal.name, scope.name()) });
+        }
+        Ok(principal)
+    }
+
+    fn check_api_key(&self, key: &str) -> Result<Principal, Refusal> {
+        let digest = hex::encode(Sha256::digest(key.as_bytes()));
+        self.api_keys.iter()
+            .find(|known| known.sha256 == digest)
+            .map(|known| Principal { name: known.name.clone(), scopes: known.scopes.clone() })
+            .ok_or_else(|| unauthorized("Unknown API key"))
+    }
+
+    fn check_jwt(&self, token: &str) -> Result<Principal, Refusal> {
+        let (config, secret) = self.jwt.as_ref().ok_or_else(|| unauthorized("This server does not accept JWTs"))?;
+        let invalid = |why: &str| unauthorized(format!("Invalid token: {}", why));
+        let (signed, signature) = token.rsplit_once('.').ok_or_else(|| invalid("malformed"))?;
+        let (header, claims) = signed.split_once('.').ok_or_else(|| invalid("malformed"))?;
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| invalid("not base64url"));
+
+        // Only HS256: never "none", and never whatever algorithm the token asks for
+        let header: serde_json::Value = serde_json::from_slice(&decode(header)?).map_err(|_| invalid("unreadable header"))?;
+        if header["alg"] != "HS256" {
+            return Err(invalid("only HS256 is accepted"));
+        }
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(|_| invalid("bad secret"))?;
+        mac.update(signed.as_bytes());
+        mac.verify_slice(&decode(signature)?).map_err(|_| invalid("bad signature"))?;
+
+        let claims: Claims = serde_json::from_slice(&decode(claims)?).map_err(|_| invalid("unreadable claims"))?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        match claims.exp {
+            None => return Err(invalid("no expiry (exp)")),
+            Some(exp) if exp <= now => return Err(invalid("expired")),
+            Some(_) => {}
+        }
+        if claims.nbf.is_some_and(|nbf| nbf > now) {
+            return Err(invalid("not valid yet"));
+        }
+        if let Some(issuer) = &config.issuer {
+            if claims.iss.as_ref() != Some(issuer) {
+                return Err(invalid("wrong issuer"));
+            }
+        }
+        if let Some(audience) = &config.audience {
+            let ok = match &claims.aud {
+                Some(Audience::One(aud)) => aud == audience,
+                Some(Audience::Many(auds)) => auds.contains(audience),
+                None => false,
+            };
+            if !ok {
+                return Err(invalid("wrong audience"));
+            }
+        }
+        Ok(Principal {
+            name: claims.sub.unwrap_or_else(|| "JWT".to_string()),
+            scopes: claims.scope.split_whitespace().filter_map(Scope::parse).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const SECRET: &[u8] = b"an HS256 secret of at least 32 bytes";
+
+    fn auth() -> Auth {
+        let jwt = JwtConfig { secret_env: "UNUSED".to_string(), issuer: Some("https://auth.example".to_string()), audience: Some("tlc".to_string()) };
+        Auth {
+            api_keys: vec![ApiKeyConfig { name: "billing".to_string(), sha256: hex::encode(Sha256::digest(b"billing-key")), scopes: vec![Scope::Read] }],
+            jwt: Some((jwt, Zeroizing::new(SECRET.to_vec()))),
+        }
+    }
+
+    fn token(header: serde_json::Value, claims: serde_json::Value, secret: &[u8]) -> String {
+        let signed = format!("{}.{}", URL_SAFE_NO_PAD.encode(header.to_string()), URL_SAFE_NO_PAD.encode(claims.to_string()));
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret).unwrap();
+        mac.update(signed.as_bytes());
+        format!("Bearer {}.{}", signed, URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes()))
+    }
+
+    fn claims(exp: u64) -> serde_json::Value {
+        json!({ "sub": "ci", "iss": "https://auth.example", "aud": ["tlc"], "exp": exp, "scope": "read submit" })
+    }
+
+    fn status(result: Result<Principal, Refusal>) -> u16 {
+        result.map(|_| 200).unwrap_or_else(|refusal| refusal.status)
+    }
+
+    #[test]
+    fn api_keys_grant_only_their_scopes() {
+        let auth = auth();
+        assert_eq!(auth.authorize(Some("Bearer billing-key"), Scope::Read).ok().unwrap().name, "billing");
+        assert_eq!(status(auth.authorize(Some("Bearer billing-key"), Scope::Submit)), 403);
+        assert_eq!(status(auth.authorize(Some("Bearer other-key"), Scope::Read)), 401);
+        assert_eq!(status(auth.authorize(Some("billing-key"), Scope::Read)), 401);
+        assert_eq!(status(auth.authorize(None, Scope::Read)), 401);
+    }
+
+    #[test]
+    fn jwts_must_be_hs256_signed_current_and_addressed_to_us() {
+        let auth = auth();
+        let hs256 = json!({ "alg": "HS256", "typ": "JWT" });
+        let later = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 600;
+        let principal = auth.authorize(Some(&token(hs256.clone(), claims(later), SECRET)), Scope::Submit).ok().unwrap();
+        assert_eq!(principal.name, "ci");
+        assert_eq!(status(auth.authorize(Some(&token(hs256.clone(), claims(later), SECRET)), Scope::Admin)), 403);
+
+        assert_eq!(status(auth.authorize(Some(&token(hs256.clone(), claims(later), b"another secret of at least 32 bytes")), Scope::Read)), 401);
+        assert_eq!(status(auth.authorize(Some(&token(json!({ "alg": "none" }), claims(later), SECRET)), Scope::Read)), 401);
+        assert_eq!(status(auth.authorize(Some(&token(hs256.clone(), claims(1), SECRET)), Scope::Read)), 401);
+        let mut unaddressed = claims(later);
+        unaddressed["aud"] = json!("someone-else");
+        assert_eq!(status(auth.authorize(Some(&token(hs256.clone(), unaddressed, SECRET)), Scope::Read)), 401);
+        let mut forever = claims(later);
+        forever.as_object_mut().unwrap().remove("exp");
+        assert_eq!(status(auth.authorize(Some(&token(hs256, forever, SECRET)), Scope::Read)), 401);
+    }
+}
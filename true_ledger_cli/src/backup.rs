@@ -0,0 +1,112 @@
+//! Backup and Restore
+//! `tlc backup LEDGER --out FILE` writes the whole journal and the
+//! keystore's identities (not their keys) to one file the operator signs
+//! (see the core backup.rs); with `--encrypt` the file is sealed under a
+//! passphrase, from $TLC_BACKUP_PASSPHRASE or asked for.
+//! `tlc restore FILE --store NEW` checks the backup's signature and Merkle
+//! root, verifies every transaction, and appends them in order to a new
+//! ledger, which checks the chain as it goes. Anything wrong stops the
+//! restore and removes the half-written ledger.
+
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use true_ledger_core::backup::{Backup, BackupFile, IdentityInfo};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::storage;
+use true_ledger_core::verify::verify_with;
+use zeroize::Zeroizing;
+
+use crate::merkle::journal_tree;
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+use crate::store::journal_entries;
+
+/// The passphrase a backup is sealed under
+fn backup_passphrase(prompt: &str) -> Result<Zeroizing<String>, String> {
+    if let Ok(passphrase) = std::env::var("TLC_BACKUP_PASSPHRASE") {
+        return Ok(Zeroizing::new(passphrase));
+    }
+    rpassword::prompt_password(prompt).map(Zeroizing::new).map_err(|e| format!("Could not read passphrase: {}", e))
+}
+
+/// `tlc backup <ledger> --out FILE [--encrypt]`
+pub fn run_backup(ledger: &str, out_path: &str, encrypt: bool, signer_args: &SignerArgs) -> Result<(), String> {
+    let transactions = journal_entries(ledger)?
+        .map(|entry| entry.map(|(_, signed_tx)| signed_tx))
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| format!("{} cannot be backed up whole: {}", ledger, e))?;
+    let identities: Vec<IdentityInfo> = signer_args.keystore.open().list()?.iter().map(IdentityInfo::from).collect();
+    let signer = signer_from_args(signer_args)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let backup = Backup::create(transactions, identities, signer.as_ref(), now.into())?;
+
+    let manifest = &backup.manifest;
+    println!("\n🗄️  Backup of {}: {} transactions, root {}", ledger, manifest.tree_size, manifest.root);
+    println!("   {} keystore identit{} (names and DIDs only)", manifest.identities.len(), if manifest.identities.len() == 1 { "y" } else { "ies" });
+    let file = if encrypt {
+        let passphrase = backup_passphrase("Passphrase to seal the backup with: ")?;
+        println!("🔒 Sealed under the passphrase");
+        BackupFile::Sealed(backup.seal(&passphrase)?)
+    } else {
+        BackupFile::Plain(Box::new(backup))
+    };
+    write_json(&file, out_path)?;
+    println!("💾 Signed backup saved to {}", out_path);
+    Ok(())
+}
+
+/// `tlc restore <backup> --store NEW [--operator DID]`
+pub fn run_restore(path: &str, store: &str, operator: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    if Path::new(store).exists() {
+        return Err(format!("{} already exists; restore into a new ledger", store));
+    }
+    let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let file: BackupFile = serde_json::from_str(&data).map_err(|e| format!("Invalid backup {}: {}", path, e))?;
+    let backup = match file {
+        BackupFile::Plain(backup) => *backup,
+        BackupFile::Sealed(sealed) => sealed.open(&backup_passphrase("Passphrase the backup is sealed under: ")?)?,
+    };
+    let manifest = &backup.manifest;
+    match operator {
+        Some(operator) if operator != manifest.operator_did => {
+            return Err(format!("{} was signed by {}, not the operator {}", path, manifest.operator_did, operator));
+        }
+        Some(_) => {}
+        None => println!("⚠️  No --operator given: trusting whichever key signed {}", path),
+    }
+    backup.verify(resolver)?;
+    println!("✅ Backup signed by {} at {}: {} transactions, root {}", manifest.operator_did, manifest.created, manifest.tree_size, manifest.root);
+
+    let restored = restore_into(&backup, store, resolver);
+    if let Err(e) = restored {
+        let _ = fs::remove_file(store);
+        return Err(format!("Restore stopped, and {} removed: {}", store, e));
+    }
+    println!("✅ {} reproduces the backed-up root", store);
+    if !manifest.identities.is_empty() {
+        println!("\n🔑 Identities that signed for this ledger (restore their keys separately):");
+        for identity in &manifest.identities {
+            println!("   {:<16} {:?} {}", identity.name, identity.sig_alg, identity.did);
+        }
+    }
+    println!("\n🎉 **RESTORED** {} transactions into {}", manifest.tree_size, store);
+    Ok(())
+}
+
+/// Verifies and appends every transaction, then checks the new ledger's root
+fn restore_into(backup: &Backup, store: &str, resolver: &dyn DidResolver) -> Result<(), String> {
+    let mut ledger = storage::open(store)?;
+    for (i, signed_tx) in backup.transactions.iter().enumerate() {
+        verify_with(signed_tx, resolver)
+            .and_then(|_| ledger.append(signed_tx))
+            .map_err(|e| format!("transaction #{} ({}): {}", i, signed_tx.payload.hash_hex(), e))?;
+    }
+    drop(ledger);
+    let (tree, _) = journal_tree(store)?;
+    let root = hex::encode(tree.root());
+    if root != backup.manifest.root {
+        return Err(format!("the restored ledger has root {}, not the backed-up {}", root, backup.manifest.root));
+    }
+    Ok(())
+}
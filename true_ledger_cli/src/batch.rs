@@ -0,0 +1,88 @@
+//! Batch Verification
+//! `tlc verify-batch <paths>...` checks the signature and balance of many
+//! independent transactions in parallel, one per core.
+
+use rayon::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::SignedTransaction;
+
+/// Expands the arguments into transaction files: directories contribute
+/// their *.json files (sorted), anything else is taken as a file. Globs are
+/// left to the shell.
+fn collect_paths(paths: &[String]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = PathBuf::from(path);
+        if !path.is_dir() {
+            files.push(path);
+            continue;
+        }
+        let entries = fs::read_dir(&path).map_err(|e| format!("Could not read directory {}: {}", path.display(), e))?;
+        let mut in_dir = Vec::new();
+        for entry in entries {
+            let file = entry.map_err(|e| format!("Could not read directory {}: {}", path.display(), e))?.path();
+            if file.extension().and_then(|ext| ext.to_str()) == Some("json") {
+                in_dir.push(file);
+            }
+        }
+        in_dir.sort();
+        files.extend(in_dir);
+    }
+    Ok(files)
+}
+
+/// Reads, parses and verifies one file
+fn verify_file(path: &PathBuf, resolver: &(dyn DidResolver + Sync)) -> Result<(), String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Could not read file: {}", e))?;
+    let signed_tx = SignedTransaction::from_json(&data)?;
+    Ok(verify_with(&signed_tx, resolver)?)
+}
+
+/// `tlc verify-batch <paths>... [--jobs N] [--quiet]`: verifies every file
+/// independently (no chain links) and prints one line per file in input
+/// order, then a summary. Fails if any file fails.
+pub fn run_verify_batch(paths: &[String], jobs: Option<usize>, quiet: bool, resolver: &(dyn DidResolver + Sync)) -> Result<(), String> {
+    let files = collect_paths(paths)?;
+    if files.is_empty() {
+        return Err("No transaction files to verify".to_string());
+    }
+
+    let mut pool = rayon::ThreadPoolBuilder::new();
+    if let Some(jobs) = jobs {
+        pool = pool.num_threads(jobs);
+    }
+    let pool = pool.build().map_err(|e| format!("Could not start worker threads: {}", e))?;
+    let threads = pool.current_num_threads();
+    println!("\n🔍 Verifying {} transactions on {} thread{}...", files.len(), threads, if threads == 1 { "" } else { "s" });
+
+    let started = Instant::now();
+    let results: Vec<Result<(), String>> =
+        pool.install(|| files.par_iter().map(|path| verify_file(path, resolver)).collect());
+    let elapsed = started.elapsed();
+
+    let mut failures = 0;
+    for (path, result) in files.iter().zip(&results) {
+        match result {
+            Ok(_) if !quiet => println!("✅ {}", path.display()),
+            Ok(_) => {}
+            Err(e) => {
+                failures += 1;
+                println!("❌ {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    let passed = files.len() - failures;
+    println!("\n📊 {} passed, {} failed, {} total in {:.2?} ({:.0} tx/s)", passed, failures, files.len(),
+        elapsed, files.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON));
+    if failures == 0 {
+        println!("🎉 **ALL TRANSACTIONS VERIFIED**");
+        Ok(())
+    } else {
+        Err(format!("{} of {} transactions failed verification", failures, files.len()))
+    }
+}
@@ -0,0 +1,312 @@
+//! Server Load Test
+//! `tlc bench serve URL` drives a running `tlc serve` with concurrent
+//! clients and reports throughput and latency percentiles, for capacity
+//! planning. Each request is one of a configurable mix:
+//!
+//!   verify  POST /verify with a signed synthetic transaction
+//!   submit  POST /transactions with the next link of a synthetic chain from genesis
+//!   get     GET /transactions/<hash> of a transaction submitted earlier
+//!
+//! Submissions go one at a time and in chain order, as the server appends
+//! them one at a time anyway; a ledger that already has a genesis refuses
+//! them (409), which is reported and still timed. Every request opens its
+//! own connection, so latencies include the connect. Plain HTTP only: run
+//! it against a server without --tls-cert.
+
+use clap::{Args, Subcommand};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use true_ledger_core::{chain, Account, SignedTransaction, Transaction};
+
+use crate::generate::random_transaction;
+
+/// Distinct transactions signed for the verify requests, which cycle through them
+const VERIFY_POOL: usize = 256;
+
+/// Accounts the synthetic transactions post to
+const ACCOUNTS: [&str; 6] = ["10100", "11000", "20100", "30100", "40100", "50100"];
+
+#[derive(Subcommand, Debug)]
+pub enum BenchCommand {
+    /// Load-test a running `tlc serve`
+    Serve(ServeBenchArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ServeBenchArgs {
+    /// The server, e.g. http://127.0.0.1:8080 (a tenant: http://HOST:PORT/tenants/NAME)
+    pub url: String,
+
+    /// Requests to send in all
+    #[arg(long, short = 'n', default_value_t = 1000)]
+    pub requests: usize,
+
+    /// Clients sending at once
+    #[arg(long, short = 'c', default_value_t = 8)]
+    pub concurrency: usize,
+
+    /// Request mix, as kind=weight pairs (kinds: verify, submit, get)
+    #[arg(long, default_value = "verify=80,submit=10,get=10")]
+    pub mix: String,
+
+    /// Sent as `Authorization: Bearer KEY` (default: $TLC_API_KEY)
+    #[arg(long, value_name = "KEY")]
+    pub api_key: Option<String>,
+
+    /// Same seed => same keys, transactions and request order
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Seconds a request may take before it counts as an error
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    pub timeout: u64,
+}
+
+/// One kind of request in the mix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Verify,
+    Submit,
+    Get,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Verify => "verify",
+            Kind::Submit => "submit",
+            Kind::Get => "get",
+        }
+    }
+}
+
+/// Parses "verify=80,submit=10,get=10" into weights
+fn parse_mix(mix: &str) -> Result<Vec<(Kind, u32)>, String> {
+    let mut weights = Vec::new();
+    for pair in mix.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let (kind, weight) = pair.split_once('=').ok_or_else(|| format!("--mix: '{}' is not kind=weight", pair))?;
+        let kind = match kind.trim() {
+            "verify" => Kind::Verify,
+            "submit" => Kind::Submit,
+            "get" => Kind::Get,
+            other => return Err(format!("--mix: unknown kind '{}' (verify, submit or get)", other)),
+        };
+        let weight = weight.trim().parse::<u32>().map_err(|_| format!("--mix: '{}' is not a whole-number weight", pair))?;
+        weights.push((kind, weight));
+    }
+    if weights.iter().map(|(_, weight)| weight).sum::<u32>() == 0 {
+        return Err("--mix: the weights add up to nothing".to_string());
+    }
+    Ok(weights)
+}
+
+/// Where requests go: host:port for the connection, and the path prefix
+struct Target {
+    host: String,
+    prefix: String,
+}
+
+impl Target {
+    fn parse(url: &str) -> Result<Self, String> {
+        let rest = match url.strip_prefix("http://") {
+            Some(rest) => rest,
+            None if url.starts_with("https://") => return Err("bench serve speaks plain HTTP only: run it against a server without --tls-cert".to_string()),
+            None => url,
+        };
+        let (host, prefix) = rest.find('/').map_or((rest, ""), |slash| rest.split_at(slash));
+        if host.is_empty() {
+            return Err(format!("No host in {}", url));
+        }
+        Ok(Target { host: host.to_string(), prefix: prefix.trim_end_matches('/').to_string() })
+    }
+}
+
+/// Sends one request on a new connection; returns the response status
+fn request(target: &Target, method: &str, path: &str, body: &str, api_key: Option<&str>, timeout: Duration) -> Result<u16, String> {
+    let address = target.host.to_socket_addrs().map_err(|e| format!("{}: {}", target.host, e))?
+        .next().ok_or_else(|| format!("{} resolves to no address", target.host))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(timeout)).and_then(|_| stream.set_write_timeout(Some(timeout))).map_err(|e| e.to_string())?;
+
+    let authorization = api_key.map(|key| format!("Authorization: Bearer {}\r\n", key)).unwrap_or_default();
+    let head = format!("{} {}{} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}\r\n",
+        method, target.prefix, path, target.host, body.len(), authorization);
+    stream.write_all(head.as_bytes()).and_then(|_| stream.write_all(body.as_bytes())).map_err(|e| e.to_string())?;
+
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).map_err(|e| e.to_string())?;
+    let status_line = response.split(|byte| *byte == b'\n').next().unwrap_or_default();
+    String::from_utf8_lossy(status_line).split_whitespace().nth(1)
+        .and_then(|status| status.parse().ok())
+        .ok_or_else(|| "Not an HTTP response".to_string())
+}
+
+/// What one request came to
+struct Outcome {
+    kind: Kind,
+    status: Result<u16, String>,
+    latency: Duration,
+}
+
+/// The transactions the requests carry: a pool to verify, and a chain to submit
+fn synthetic(rng: &mut StdRng, verify_count: usize, submit_count: usize) -> (Vec<String>, Vec<SignedTransaction>) {
+    let authors: Vec<Account> = (0..3).map(|_| Account::generate_with(&mut *rng)).collect();
+    let accounts: Vec<String> = ACCOUNTS.iter().map(|account| account.to_string()).collect();
+    let mut timestamp = 1730814442;
+    let mut next = |rng: &mut StdRng| {
+        timestamp += rng.gen_range(60, 7200);
+        let a = rng.gen_range(0, authors.len());
+        (a, random_transaction(rng, &authors[a], &accounts, timestamp))
+    };
+
+    let pool = (0..verify_count.min(VERIFY_POOL))
+        .map(|_| {
+            let (a, tx) = next(rng);
+            serde_json::to_string(&authors[a].sign(tx)).unwrap_or_default()
+        })
+        .collect();
+
+    let mut chain_txs = Vec::with_capacity(submit_count);
+    let mut prev: Option<Transaction> = None;
+    let mut sequences = vec![0; authors.len()];
+    for _ in 0..submit_count {
+        let (a, mut tx) = next(rng);
+        chain::link(&mut tx, prev.as_ref());
+        tx.sequence = Some(sequences[a]);
+        sequences[a] += 1;
+        let signed_tx = authors[a].sign(tx);
+        prev = Some(signed_tx.payload.clone());
+        chain_txs.push(signed_tx);
+    }
+    (pool, chain_txs)
+}
+
+/// The latency at percentile `p` (0-100) of sorted latencies
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    match sorted.len() {
+        0 => Duration::ZERO,
+        len => sorted[((len * p).div_ceil(100)).clamp(1, len) - 1],
+    }
+}
+
+fn millis(duration: Duration) -> String {
+    format!("{:.1} ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// `tlc bench serve <url>`
+pub fn run_bench(command: &BenchCommand) -> Result<(), String> {
+    let BenchCommand::Serve(args) = command;
+    let target = Target::parse(&args.url)?;
+    let weights = parse_mix(&args.mix)?;
+    if args.requests == 0 || args.concurrency == 0 {
+        return Err("Need at least one request and one client".to_string());
+    }
+    let api_key = args.api_key.clone().or_else(|| std::env::var("TLC_API_KEY").ok());
+    let timeout = Duration::from_secs(args.timeout.max(1));
+    let mut rng = match args.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    // Which kind each request is, drawn by weight
+    let total_weight: u32 = weights.iter().map(|(_, weight)| weight).sum();
+    let schedule: Vec<Kind> = (0..args.requests)
+        .map(|_| {
+            let mut draw = rng.gen_range(0, total_weight);
+            weights.iter().find(|(_, weight)| if draw < *weight { true } else { draw -= weight; false }).map_or(Kind::Verify, |(kind, _)| *kind)
+        })
+        .collect();
+    let count = |kind: Kind| schedule.iter().filter(|k| **k == kind).count();
+    println!("\n🏋️  Signing synthetic transactions...");
+    let (pool, chain_txs) = synthetic(&mut rng, count(Kind::Verify), count(Kind::Submit));
+
+    println!("🏋️  {} requests ({}) from {} client(s) to {}...", args.requests, args.mix, args.concurrency, args.url);
+    let next_request = AtomicUsize::new(0);
+    let submissions = Mutex::new((chain_txs.into_iter(), Vec::<String>::new())); // To submit, and hashes stored so far
+    let outcomes = Mutex::new(Vec::with_capacity(args.requests));
+    let started = Instant::now();
+    thread::scope(|scope| {
+        for _ in 0..args.concurrency.min(args.requests) {
+            scope.spawn(|| loop {
+                let i = next_request.fetch_add(1, Ordering::Relaxed);
+                let Some(kind) = schedule.get(i).copied() else { break };
+                let send = |method: &str, path: &str, body: &str| {
+                    let sent = Instant::now();
+                    (request(&target, method, path, body, api_key.as_deref(), timeout), sent.elapsed())
+                };
+                let (status, latency) = match kind {
+                    Kind::Verify => send("POST", "/verify", &pool[i % pool.len()]),
+                    Kind::Submit => {
+                        // Held for the request, so the chain arrives in order
+                        let mut submissions = submissions.lock().unwrap_or_else(|e| e.into_inner());
+                        let Some(signed_tx) = submissions.0.next() else { continue };
+                        let result = send("POST", "/transactions", &serde_json::to_string(&signed_tx).unwrap_or_default());
+                        if result.0 == Ok(201) {
+                            submissions.1.push(signed_tx.payload.hash_hex());
+                        }
+                        result
+                    }
+                    Kind::Get => {
+                        let hash = {
+                            let submissions = submissions.lock().unwrap_or_else(|e| e.into_inner());
+                            match submissions.1.len() {
+                                0 => "0".repeat(64), // Nothing stored yet: a miss, still a lookup
+                                len => submissions.1[i % len].clone(),
+                            }
+                        };
+                        send("GET", &format!("/transactions/{}", hash), "")
+                    }
+                };
+                outcomes.lock().unwrap_or_else(|e| e.into_inner()).push(Outcome { kind, status, latency });
+            });
+        }
+    });
+    let elapsed = started.elapsed();
+    let outcomes = outcomes.into_inner().unwrap_or_else(|e| e.into_inner());
+    report(&outcomes, elapsed);
+    Ok(())
+}
+
+/// Prints throughput, then counts by status and latency percentiles, per kind and overall
+fn report(outcomes: &[Outcome], elapsed: Duration) {
+    println!("\n📊 {} requests in {:.2} s: {:.1} requests/s", outcomes.len(), elapsed.as_secs_f64(),
+        outcomes.len() as f64 / elapsed.as_secs_f64().max(f64::EPSILON));
+    println!("   {:<8} {:>7}  {:>10} {:>10} {:>10} {:>10}  statuses", "kind", "count", "p50", "p90", "p99", "max");
+
+    let mut by_kind: BTreeMap<Option<Kind>, Vec<&Outcome>> = BTreeMap::new();
+    for outcome in outcomes {
+        by_kind.entry(Some(outcome.kind)).or_default().push(outcome);
+        by_kind.entry(None).or_default().push(outcome);
+    }
+    for (kind, outcomes) in &by_kind {
+        let mut latencies: Vec<Duration> = outcomes.iter().map(|outcome| outcome.latency).collect();
+        latencies.sort_unstable();
+        let mut statuses: BTreeMap<String, usize> = BTreeMap::new();
+        let mut errors = BTreeMap::new();
+        for outcome in outcomes {
+            match &outcome.status {
+                Ok(status) => *statuses.entry(status.to_string()).or_default() += 1,
+                Err(e) => {
+                    *statuses.entry("error".to_string()).or_default() += 1;
+                    *errors.entry(e.clone()).or_insert(0) += 1;
+                }
+            }
+        }
+        let statuses: Vec<String> = statuses.iter().map(|(status, count)| format!("{}×{}", count, status)).collect();
+        println!("   {:<8} {:>7}  {:>10} {:>10} {:>10} {:>10}  {}", kind.map_or("all", Kind::name), outcomes.len(),
+            millis(percentile(&latencies, 50)), millis(percentile(&latencies, 90)), millis(percentile(&latencies, 99)),
+            millis(latencies.last().copied().unwrap_or_default()), statuses.join(" "));
+        if kind.is_none() {
+            for (e, count) in errors {
+                println!("   ⚠️  {}× {}", count, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! Chain Verification
+//! `tlc chain verify <dir|ledger>` checks a whole journal of linked transactions.
+
+use true_ledger_core::chain::{self, SequenceTracker};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::key_events::KeyRegistry;
+use true_ledger_core::policy::PolicyHistory;
+use true_ledger_core::reversal::ReversalTracker;
+use true_ledger_core::validation::ValidationRule;
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::Transaction;
+
+use crate::key_events::load_registry;
+use crate::policy::load_policy_history;
+use crate::store::journal_entries;
+
+/// `tlc chain verify <dir|ledger>`: checks every transaction in a journal, that each
+/// one links to its predecessor, that no author's sequence repeats or skips, and
+/// that no key signs after the journal's key events rotated it away or revoked it,
+/// that each reversal exactly negates an earlier transaction, and that each
+/// posting-policy version was adopted in turn by an administrator and each
+/// posting satisfies the version in force at its timestamp.
+/// Keeps going after a failure so every broken link is reported, not just the first.
+pub fn verify_chain_dir(dir: &str, resolver: &dyn DidResolver) -> Result<(), String> {
+    // A revocation can be backdated, so every key event is read before any posting is checked
+    let keys = load_registry(dir, resolver)?;
+    // Likewise every policy version, so each posting is judged by the one of its day
+    let in_force = load_policy_history(dir, resolver)?.compile()?;
+    // And every reversal's target, so only those transactions are kept
+    let targets: Vec<String> = journal_entries(dir)?.filter_map(|entry| entry.ok()?.1.payload.reverses).collect();
+    let journal = journal_entries(dir)?;
+    println!("\n🔗 Verifying chain in {}...", dir);
+
+    // Only the predecessor (and one counter per author) is kept, so a streamed
+    // journal is checked in near-constant memory
+    let mut failures = 0;
+    let mut total = 0;
+    let mut prev: Option<Transaction> = None;
+    let mut sequences = SequenceTracker::new();
+    let mut reversals = ReversalTracker::new(targets);
+    let mut key_events = KeyRegistry::new(); // Replayed in order, to report bad events where they occur
+    let mut policies = PolicyHistory::new();  // Likewise policy versions
+    for (position, entry) in journal.enumerate() {
+        total += 1;
+        let (name, signed_tx) = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                failures += 1;
+                println!("❌ #{} {}", position, e);
+                continue;
+            }
+        };
+        let result = verify_with(&signed_tx, resolver)
+            .and_then(|_| chain::verify_link(prev.as_ref(), &signed_tx.payload))
+            .and_then(|_| sequences.check(&signed_tx.payload))
+            .and_then(|_| reversals.check(&signed_tx.payload))
+            .and_then(|_| keys.check(&signed_tx))
+            .and_then(|_| in_force.check(&signed_tx.payload))
+            .and_then(|_| key_events.apply(&signed_tx.payload))
+            .and_then(|_| policies.apply(&signed_tx.payload));
+        match result {
+            Ok(_) => println!("✅ #{} {} {}", position, name, &signed_tx.payload.hash_hex()[..16]),
+            Err(e) => {
+                failures += 1;
+                println!("❌ #{} {}: {}", position, name, e);
+            }
+        }
+        prev = Some(signed_tx.payload);
+    }
+
+    if failures == 0 {
+        println!("\n🎉 **CHAIN IS INTACT** ({} transactions)", total);
+        Ok(())
+    } else {
+        Err(format!("{} of {} transactions failed chain verification", failures, total))
+    }
+}
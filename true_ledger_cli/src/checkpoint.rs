@@ -0,0 +1,57 @@
+//! Checkpoints
+//! `tlc checkpoint` signs the journal's current Merkle root, size and height
+//! with the operator's key; `tlc verify-checkpoint` checks the signature and,
+//! given the journal, that the journal still reproduces the root.
+
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use true_ledger_core::checkpoint::{Checkpoint, SignedCheckpoint};
+use true_ledger_core::did::DidResolver;
+
+use crate::merkle::journal_tree;
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+
+/// `tlc checkpoint <journal>`: signs a checkpoint over the whole journal
+pub fn run_checkpoint(journal: &str, out_path: &str, signer_args: &SignerArgs) -> Result<(), String> {
+    let (tree, height) = journal_tree(journal)?;
+    let signer = signer_from_args(signer_args)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let checkpoint = Checkpoint::new(&tree, height, signer.did(), now.into()).sign(signer.as_ref())?;
+
+    write_json(&checkpoint, out_path)?;
+    println!("\n🌳 Checkpoint of {}: {} transactions, root {}", journal, checkpoint.checkpoint.tree_size, checkpoint.checkpoint.root);
+    println!("💾 Signed checkpoint saved to {}", out_path);
+    Ok(())
+}
+
+/// Reads a checkpoint and checks its signature, and its operator if one is expected
+pub fn load_checkpoint(path: &str, operator: Option<&str>, resolver: &dyn DidResolver) -> Result<SignedCheckpoint, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let signed: SignedCheckpoint = serde_json::from_str(&data).map_err(|e| format!("Invalid checkpoint {}: {}", path, e))?;
+    let checkpoint = &signed.checkpoint;
+    match operator {
+        Some(operator) if operator != checkpoint.operator_did => {
+            return Err(format!("{} was signed by {}, not the operator {}", path, checkpoint.operator_did, operator));
+        }
+        Some(_) => {}
+        None => println!("⚠️  No --operator given: trusting whichever key signed {}", path),
+    }
+    signed.verify(resolver).map_err(|e| format!("Checkpoint signature is invalid: {}", e))?;
+    println!("✅ Checkpoint signed by {} at {}: {} transactions, root {}",
+        checkpoint.operator_did, checkpoint.timestamp, checkpoint.tree_size, checkpoint.root);
+    Ok(signed)
+}
+
+/// `tlc verify-checkpoint <file> [--journal LEDGER] [--operator DID]`
+pub fn run_verify_checkpoint(path: &str, journal: Option<&str>, operator: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let signed = load_checkpoint(path, operator, resolver)?;
+    if let Some(journal) = journal {
+        let (tree, _) = journal_tree(journal)?;
+        signed.checkpoint.check_tree(&tree)?;
+        println!("✅ The first {} of {}'s {} transactions still have the checkpointed root",
+            signed.checkpoint.tree_size, journal, tree.size());
+    }
+    println!("\n🎉 **CHECKPOINT VERIFIED**");
+    Ok(())
+}
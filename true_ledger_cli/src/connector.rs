@@ -0,0 +1,262 @@
+//! Cloud Accounting Connector
+//! `tlc connector push <dir|ledger> --service xero|quickbooks --org ID`
+//! posts every verified transaction not yet posted to Xero or QuickBooks
+//! Online as a journal (see the core connector.rs), recording the id the
+//! service confirms in a sync state file; `tlc connector status` lists what
+//! is posted and what is still to post.
+//!
+//! Requests carry an OAuth2 bearer token: `$TLC_CONNECTOR_TOKEN`, or one
+//! obtained from a refresh token kept in a file (`--refresh-token`) with the
+//! app's `$TLC_CONNECTOR_CLIENT_ID` and `$TLC_CONNECTOR_CLIENT_SECRET`. The
+//! service rotates refresh tokens, so the file is rewritten with each new one.
+
+use base64::Engine;
+use clap::{Args, Subcommand, ValueEnum};
+use serde_json::Value;
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use true_ledger_core::connector::{AccountMap, Confirmation, Service, SyncState};
+use true_ledger_core::did::DidResolver;
+use zeroize::Zeroizing;
+
+use crate::report::verified_payloads;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ServiceArg {
+    Xero,
+    Quickbooks,
+}
+
+impl From<ServiceArg> for Service {
+    fn from(service: ServiceArg) -> Self {
+        match service {
+            ServiceArg::Xero => Service::Xero,
+            ServiceArg::Quickbooks => Service::QuickBooks,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConnectorCommand {
+    /// Post the verified transactions not yet posted, one journal each
+    Push(PushArgs),
+
+    /// List what has been posted, and what is still to post
+    Status {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        journal: String,
+
+        #[arg(long, value_enum)]
+        service: ServiceArg,
+
+        /// Sync state file written by `tlc connector push`
+        #[arg(long, value_name = "FILE")]
+        state: String,
+    },
+}
+
+/// Options for `tlc connector push`
+#[derive(Args, Debug)]
+pub struct PushArgs {
+    /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+    pub journal: String,
+
+    #[arg(long, value_enum)]
+    pub service: ServiceArg,
+
+    /// Xero tenant id, or QuickBooks company (realm) id
+    #[arg(long, value_name = "ID", required_unless_present = "dry_run")]
+    pub org: Option<String>,
+
+    /// Sync state file: what has been posted (created if missing)
+    #[arg(long, value_name = "FILE")]
+    pub state: String,
+
+    /// TOML file mapping our account codes to the service's: [accounts] "10100" = "090"
+    #[arg(long, value_name = "FILE")]
+    pub accounts: Option<String>,
+
+    /// File holding an OAuth2 refresh token, rewritten with each rotated one
+    #[arg(long, value_name = "FILE")]
+    pub refresh_token: Option<String>,
+
+    /// API base URL (default: the service's; e.g. https://sandbox-quickbooks.api.intuit.com)
+    #[arg(long, value_name = "URL")]
+    pub api_url: Option<String>,
+
+    /// OAuth2 token endpoint (default: the service's)
+    #[arg(long, value_name = "URL")]
+    pub token_url: Option<String>,
+
+    /// Print the journals that would be posted instead of posting them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub fn run_connector(command: &ConnectorCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        ConnectorCommand::Push(args) => push(args, resolver),
+        ConnectorCommand::Status { journal, service, state } => status(journal, (*service).into(), state, resolver),
+    }
+}
+
+fn default_api_url(service: Service) -> &'static str {
+    match service {
+        Service::Xero => "https://api.xero.com/api.xro/2.0",
+        Service::QuickBooks => "https://quickbooks.api.intuit.com",
+    }
+}
+
+fn default_token_url(service: Service) -> &'static str {
+    match service {
+        Service::Xero => "https://identity.xero.com/connect/token",
+        Service::QuickBooks => "https://oauth.platform.intuit.com/oauth2/v1/tokens/bearer",
+    }
+}
+
+/// Why a request failed, and whether the rest of the run can go on
+enum PostError {
+    Rejected(String), // The service refused this journal; others may still post
+    Fatal(String),    // Bad credentials, rate limits, outages: stop here
+}
+
+/// Reads the body of an error response for the message
+fn response_error(code: u16, response: ureq::Response) -> PostError {
+    let body = response.into_string().unwrap_or_default();
+    let message = format!("HTTP {}: {}", code, body.trim());
+    match code {
+        401 | 403 | 429 => PostError::Fatal(message),
+        400..=499 => PostError::Rejected(message),
+        _ => PostError::Fatal(message),
+    }
+}
+
+/// An access token: `$TLC_CONNECTOR_TOKEN`, or exchanged for the refresh token in `refresh_token`
+fn access_token(args: &PushArgs, service: Service, agent: &ureq::Agent) -> Result<Zeroizing<String>, String> {
+    if let Ok(token) = std::env::var("TLC_CONNECTOR_TOKEN") {
+        return Ok(Zeroizing::new(token));
+    }
+    let Some(path) = &args.refresh_token else {
+        return Err("Set $TLC_CONNECTOR_TOKEN to an OAuth2 access token, or give --refresh-token".to_string());
+    };
+    let client = |name: &str| std::env::var(name).map(Zeroizing::new).map_err(|_| format!("--refresh-token needs ${} as well", name));
+    let (client_id, client_secret) = (client("TLC_CONNECTOR_CLIENT_ID")?, client("TLC_CONNECTOR_CLIENT_SECRET")?);
+    let refresh_token = Zeroizing::new(fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?);
+    let basic = Zeroizing::new(base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", client_id.as_str(), client_secret.as_str())));
+
+    let token_url = args.token_url.as_deref().unwrap_or(default_token_url(service));
+    let response = agent.post(token_url)
+        .set("Authorization", &format!("Basic {}", basic.as_str()))
+        .set("Accept", "application/json")
+        .send_form(&[("grant_type", "refresh_token"), ("refresh_token", refresh_token.trim())])
+        .map_err(|e| format!("Could not refresh the access token at {}: {}", token_url, e))?;
+    let tokens: Zeroizing<String> = Zeroizing::new(response.into_string().map_err(|e| format!("Could not read the token response: {}", e))?);
+    let tokens: Value = serde_json::from_str(&tokens).map_err(|e| format!("Invalid token response: {}", e))?;
+    if let Some(rotated) = tokens["refresh_token"].as_str() {
+        fs::write(path, rotated).map_err(|e| format!("Could not save the new refresh token to {}: {}", path, e))?;
+    }
+    tokens["access_token"].as_str().map(|token| Zeroizing::new(token.to_string()))
+        .ok_or_else(|| "The token response holds no access_token".to_string())
+}
+
+/// Sends one journal, returning the service's response
+fn post_journal(agent: &ureq::Agent, service: Service, args: &PushArgs, token: &str, key: &str, body: &Value) -> Result<Value, PostError> {
+    let base = args.api_url.as_deref().unwrap_or(default_api_url(service)).trim_end_matches('/');
+    let org = args.org.as_deref().unwrap_or_default();
+    let request = match service {
+        Service::Xero => agent.post(&format!("{}/ManualJournals", base))
+            .set("xero-tenant-id", org)
+            .set("Idempotency-Key", key),
+        Service::QuickBooks => agent.post(&format!("{}/v3/company/{}/journalentry", base, org))
+            .query("requestid", key),
+    };
+    let response = request
+        .set("Authorization", &format!("Bearer {}", token))
+        .set("Accept", "application/json")
+        .set("Content-Type", "application/json")
+        .send_string(&body.to_string());
+    match response {
+        Ok(response) => {
+            let text = response.into_string().map_err(|e| PostError::Fatal(format!("Could not read the response: {}", e)))?;
+            serde_json::from_str(&text).map_err(|e| PostError::Fatal(format!("Invalid response ({}): {}", e, text)))
+        }
+        Err(ureq::Error::Status(code, response)) => Err(response_error(code, response)),
+        Err(e) => Err(PostError::Fatal(e.to_string())),
+    }
+}
+
+fn push(args: &PushArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let service: Service = args.service.into();
+    let accounts = args.accounts.as_deref().map(AccountMap::load).transpose()?.unwrap_or_default();
+    let mut state = SyncState::load(&args.state, service)?;
+    let payloads = verified_payloads(&args.journal, resolver)?;
+    let pending: Vec<_> = payloads.iter().filter(|tx| !tx.entries.is_empty() && !state.is_posted(tx)).collect();
+    println!("\n🔗 {}: {} transaction(s) to post, {} already posted", service.name(), pending.len(), state.posted.len());
+
+    let agent = ureq::AgentBuilder::new().timeout(Duration::from_secs(30)).build();
+    let token = if args.dry_run { Zeroizing::new(String::new()) } else { access_token(args, service, &agent)? };
+    let (mut posted, mut rejected) = (0, 0);
+    for tx in pending {
+        let tx_hash = tx.hash_hex();
+        let body = match service.journal(tx, &accounts) {
+            Ok(body) => body,
+            Err(e) => {
+                rejected += 1;
+                eprintln!("⚠️  Not posted {}: {}", &tx_hash[..16], e);
+                continue;
+            }
+        };
+        if args.dry_run {
+            println!("{}", serde_json::to_string_pretty(&body).map_err(|e| e.to_string())?);
+            continue;
+        }
+        match post_journal(&agent, service, args, &token, &service.request_key(&tx_hash), &body) {
+            Ok(response) => {
+                let (remote_id, status) = service.confirmation(&response)?;
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+                println!("   ✅ {} → {} {}{}", &tx_hash[..16], service.name(), remote_id, status.as_ref().map(|s| format!(" ({})", s)).unwrap_or_default());
+                state.record(tx, Confirmation { remote_id, status, posted_at: now.into() });
+                state.save(&args.state)?; // After every journal, so a crash never forgets one
+                posted += 1;
+            }
+            Err(PostError::Rejected(e)) => {
+                rejected += 1;
+                eprintln!("⚠️  {} rejected {}: {}", service.name(), &tx_hash[..16], e);
+            }
+            Err(PostError::Fatal(e)) => {
+                return Err(format!("Stopped after posting {} journal(s): {}", posted, e));
+            }
+        }
+    }
+
+    if args.dry_run {
+        println!("\n   > Dry run: nothing was posted");
+    } else if posted == 0 && rejected == 0 {
+        println!("✅ Nothing new to post");
+    } else {
+        println!("\n💾 Posted {} journal(s); sync state saved to {}", posted, args.state);
+    }
+    if rejected > 0 {
+        return Err(format!("{} transaction(s) could not be posted; fix them (e.g. the account map) and push again", rejected));
+    }
+    Ok(())
+}
+
+fn status(journal: &str, service: Service, state: &str, resolver: &dyn DidResolver) -> Result<(), String> {
+    let state = SyncState::load(state, service)?;
+    let payloads = verified_payloads(journal, resolver)?;
+    println!("\n🔗 {} sync of {}:", service.name(), journal);
+    let mut pending = 0;
+    for tx in payloads.iter().filter(|tx| !tx.entries.is_empty()) {
+        match state.posted.get(&tx.hash_hex()) {
+            Some(confirmation) => println!("   ✅ {} {}  {} {}{}", tx.timestamp.local_date(), &tx.hash_hex()[..16], service.name(), confirmation.remote_id,
+                confirmation.status.as_ref().map(|s| format!(" ({})", s)).unwrap_or_default()),
+            None => {
+                pending += 1;
+                println!("   ⏳ {} {}  not posted: {}", tx.timestamp.local_date(), &tx.hash_hex()[..16], tx.memo);
+            }
+        }
+    }
+    println!("\n   > {} posted, {} still to post", state.posted.len(), pending);
+    Ok(())
+}
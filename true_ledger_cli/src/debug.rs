@@ -0,0 +1,56 @@
+//! Debug Tools
+//! "Signature invalid but the data looks identical" almost always means two
+//! implementations serialized the payload to different bytes. These helpers
+//! show the exact bytes we hash so the difference can be found.
+
+use std::fs;
+use true_ledger_core::hashing::Hasher;
+use true_ledger_core::Transaction;
+
+/// Offset of the first byte where the two inputs differ, if any
+fn first_difference(ours: &[u8], theirs: &[u8]) -> Option<usize> {
+    match ours.iter().zip(theirs).position(|(a, b)| a != b) {
+        Some(i) => Some(i),
+        None if ours.len() != theirs.len() => Some(ours.len().min(theirs.len())),
+        None => None,
+    }
+}
+
+/// Escaped view of up to `radius` bytes either side of `offset`
+fn context_window(bytes: &[u8], offset: usize, radius: usize) -> String {
+    let start = offset.saturating_sub(radius);
+    let end = (offset + radius).min(bytes.len());
+    format!("{:?}", String::from_utf8_lossy(&bytes[start..end]))
+}
+
+/// `debug canonical [other.bin]`: prints our canonical bytes and digest and,
+/// if given, diffs them against another implementation's signing input
+pub fn debug_canonical(tx: &Transaction, other_path: Option<&str>) {
+    let ours = tx.canonical_bytes();
+    println!("\n🔬 Canonical payload ({} bytes):", ours.len());
+    println!("{}", String::from_utf8_lossy(&ours));
+    println!("   {}: {}", tx.hash_alg(), hex::encode(tx.get_hash()));
+
+    let other_path = match other_path {
+        Some(path) => path,
+        None => return,
+    };
+    let theirs = match fs::read(other_path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("❌ Error: Could not read {}: {}", other_path, e);
+            return;
+        }
+    };
+    println!("\n🔬 Comparing against {} ({} bytes)", other_path, theirs.len());
+    println!("   Their {}: {}", tx.hash_alg(), hex::encode(tx.hash_alg().digest(&theirs)));
+
+    match first_difference(&ours, &theirs) {
+        None => println!("✅ Byte-for-byte identical."),
+        Some(offset) => {
+            println!("❌ First difference at byte offset {}", offset);
+            println!("   > ours:   {}", context_window(&ours, offset, 24));
+            println!("   > theirs: {}", context_window(&theirs, offset, 24));
+        }
+    }
+}
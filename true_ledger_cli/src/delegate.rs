@@ -0,0 +1,88 @@
+//! Delegation
+//! `tlc delegate --to DID --account PATTERN... --expires WHEN` signs a
+//! capability granting posting rights (see the core capability.rs). Signed
+//! with `--capability FILE`, it is delegated from the chain held there and
+//! may grant no more; either way the chain, ending with the new capability,
+//! is written out for its holder to sign with `--capability`.
+
+use clap::Args;
+use true_ledger_core::capability::{load_chain, Capability};
+use true_ledger_core::Timestamp;
+
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+
+/// Options for `tlc delegate`
+#[derive(Args, Debug)]
+pub struct DelegateArgs {
+    /// DID that receives the capability
+    #[arg(long = "to", value_name = "DID")]
+    pub holder: String,
+
+    /// Account it may post to: a code, a prefix ending in '*', or a range such as 61000-61999 (repeatable)
+    #[arg(long = "account", value_name = "PATTERN", required = true)]
+    pub accounts: Vec<String>,
+
+    /// Largest total debit per transaction (default: no limit, or the delegator's)
+    #[arg(long, value_name = "AMOUNT")]
+    pub max: Option<String>,
+
+    /// Valid from: YYYY-MM-DD (the start of that day, UTC), RFC 3339 or Unix seconds
+    #[arg(long, value_name = "WHEN")]
+    pub not_before: Option<String>,
+
+    /// Valid through: YYYY-MM-DD (the end of that day, UTC), RFC 3339 or Unix seconds
+    #[arg(long, value_name = "WHEN")]
+    pub expires: String,
+
+    /// Where to write the delegation chain
+    #[arg(long = "out", value_name = "FILE", default_value = "capability.json")]
+    pub out_path: String,
+
+    #[command(flatten)]
+    pub signer: SignerArgs,
+}
+
+/// A timestamp, or a day with `time` (UTC) appended
+fn parse_when(text: &str, time: &str, option: &str) -> Result<Timestamp, String> {
+    let parsed = if text.len() == 10 && text.as_bytes()[4] == b'-' {
+        Timestamp::parse_rfc3339(&format!("{}T{}Z", text, time))
+    } else {
+        text.parse()
+    };
+    parsed.map_err(|e| format!("{}: {}", option, e))
+}
+
+/// `tlc delegate`
+pub fn run_delegate(args: &DelegateArgs) -> Result<(), String> {
+    let mut chain = args.signer.capability.as_deref().map(load_chain).transpose()?.unwrap_or_default();
+    let signer = signer_from_args(&args.signer)?;
+    let parent = chain.last().map(|link| &link.capability);
+    let capability = Capability {
+        issuer: signer.did().to_string(),
+        holder: args.holder.clone(),
+        accounts: args.accounts.clone(),
+        max: args.max.clone().or_else(|| parent.and_then(|parent| parent.max.clone())),
+        not_before: match &args.not_before {
+            Some(text) => Some(parse_when(text, "00:00:00", "--not-before")?),
+            None => parent.and_then(|parent| parent.not_before.clone()),
+        },
+        expires: parse_when(&args.expires, "23:59:59", "--expires")?,
+        parent: None,
+    };
+    let signed = match chain.last() {
+        Some(parent) => parent.delegate(capability, signer.as_ref())?,
+        None => capability.sign(signer.as_ref())?,
+    };
+
+    println!("\n🎟️  {} may post to {} until {}", signed.capability.holder, signed.capability.accounts.join(", "), signed.capability.expires);
+    if let Some(max) = &signed.capability.max {
+        println!("   > Up to {} per transaction", max);
+    }
+    if !chain.is_empty() {
+        println!("   > Delegated from {} capability(ies) held by {}", chain.len(), signed.capability.issuer);
+    }
+    chain.push(signed);
+    write_json(&chain, &args.out_path)?;
+    println!("💾 Delegation chain saved to {}", args.out_path);
+    Ok(())
+}
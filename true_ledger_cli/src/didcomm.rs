@@ -0,0 +1,255 @@
+//! DIDComm Messaging
+//! `tlc didcomm send|receive|listen|key`: sends proposed and signed
+//! transactions to a counterparty's DID as encrypted DIDComm v2 messages
+//! (see the core didcomm.rs), and takes them in:
+//!
+//!   tlc didcomm send tx.json --to did:web:acme.example --identity alice
+//!   tlc didcomm listen --listen 0.0.0.0:8090 --inbox inbox/ --identity bob
+//!
+//! `send` posts to the service endpoint the recipient's DID Document names
+//! (or `--endpoint`, which a did:key needs), or with `--out` saves the
+//! message for delivery some other way. `listen` accepts messages POSTed to
+//! it over plain HTTP, since they are encrypted end to end, and saves each
+//! one that decrypts and checks out to the inbox under its message id.
+//! Signed transactions are verified before they are saved. `key` prints the
+//! `keyAgreement` method to add to a did:web's document.
+//!
+//! A JSON envelope travels as the message body; a compact JWS as a string.
+//!
+//! Built only with the `didcomm` feature.
+
+use clap::{Args, Subcommand};
+use serde_json::{json, Value};
+use std::fs;
+use std::net::TcpListener;
+use std::path::Path;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::didcomm::{self, MessageKind, Received};
+use true_ledger_core::envelope::Envelope;
+use true_ledger_core::{Account, Transaction};
+
+use crate::serve::{error, serve_connections};
+use crate::signing::{unlock_identity, write_json, KeystoreArgs};
+
+/// The identity messages are sent and received as
+#[derive(Args, Debug)]
+pub struct DidCommIdentity {
+    /// Keystore identity (Ed25519) to send or receive as (see `tlc keygen`)
+    #[arg(long, value_name = "NAME")]
+    pub identity: String,
+
+    #[command(flatten)]
+    pub keystore: KeystoreArgs,
+}
+
+impl DidCommIdentity {
+    fn unlock(&self) -> Result<Account, String> {
+        let account = unlock_identity(&self.keystore.open(), &self.identity)?;
+        println!("✅ Unlocked identity '{}'", self.identity);
+        println!("   DID: {}", account.did);
+        Ok(account)
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum DidCommCommand {
+    /// Send an unsigned (proposed) or signed transaction to a DID
+    Send {
+        /// Transaction JSON, or a signed transaction in any envelope but COSE
+        path: String,
+
+        /// Recipient DID
+        #[arg(long, value_name = "DID")]
+        to: String,
+
+        /// Post here instead of the recipient's DIDCommMessaging service
+        #[arg(long, value_name = "URL")]
+        endpoint: Option<String>,
+
+        /// Send as this DID (e.g. a did:web listing the identity's keys) rather than the identity's did:key
+        #[arg(long = "as", value_name = "DID")]
+        as_did: Option<String>,
+
+        /// Save the encrypted message here instead of posting it
+        #[arg(short, long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+
+        #[command(flatten)]
+        identity: DidCommIdentity,
+    },
+
+    /// Decrypt and check a message saved to a file
+    Receive {
+        /// Encrypted DIDComm message
+        path: String,
+
+        /// Where to save the transaction (default: received_transaction.json)
+        #[arg(short, long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+
+        #[command(flatten)]
+        identity: DidCommIdentity,
+    },
+
+    /// Accept messages over HTTP, saving each transaction to an inbox
+    Listen {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8090")]
+        listen: String,
+
+        /// Directory received transactions are saved in, as <message id>.json
+        #[arg(long, value_name = "DIR")]
+        inbox: String,
+
+        #[command(flatten)]
+        identity: DidCommIdentity,
+    },
+
+    /// Print the keyAgreement method a did:web's document lists to receive messages
+    Key {
+        /// The did:web (default: the identity's did:key)
+        #[arg(long, value_name = "DID")]
+        did: Option<String>,
+
+        #[command(flatten)]
+        identity: DidCommIdentity,
+    },
+}
+
+/// `tlc didcomm send|receive|listen|key`
+pub fn run_didcomm(command: &DidCommCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        DidCommCommand::Send { path, to, endpoint, as_did, out_path, identity } => {
+            let (kind, document) = read_document(path)?;
+            let account = identity.unlock()?;
+            let from = as_did.as_deref().unwrap_or(&account.did);
+            let packed = didcomm::pack(kind, &document, &account, from, to, resolver)?;
+            if let Some(out_path) = out_path {
+                fs::write(out_path, &packed.message).map_err(|e| format!("Failed to write {}: {}", out_path, e))?;
+                println!("\n🔒 Message for {} saved to:", to);
+                println!("   {}", out_path);
+                return Ok(());
+            }
+            let endpoint = endpoint.as_deref().or(packed.endpoint.as_deref())
+                .ok_or_else(|| format!("{} names no DIDComm endpoint: pass --endpoint URL, or --out FILE to deliver it yourself", to))?;
+            didcomm::post(&packed.message, endpoint)?;
+            println!("\n📨 {} sent to {} via {}", describe(kind), to, endpoint);
+        }
+        DidCommCommand::Receive { path, out_path, identity } => {
+            let message = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+            let account = identity.unlock()?;
+            let received = didcomm::unpack(&message, &account, resolver)?;
+            println!("\n📨 {} from {}", describe(received.kind), received.from);
+            check(&received, resolver)?;
+            let out_path = out_path.as_deref().unwrap_or("received_transaction.json");
+            save(&received.document, Path::new(out_path))?;
+            println!("\n💾 Saved to:");
+            println!("   {}", out_path);
+        }
+        DidCommCommand::Listen { listen, inbox, identity } => {
+            let account = identity.unlock()?;
+            fs::create_dir_all(inbox).map_err(|e| format!("Could not create {}: {}", inbox, e))?;
+            let listener = TcpListener::bind(listen).map_err(|e| format!("Could not listen on {}: {}", listen, e))?;
+            println!("\n📬 Accepting DIDComm messages for {} on http://{}", account.did, listen);
+            serve_connections(listener, None, |incoming, _peer| {
+                if incoming.method != "POST" {
+                    return (error(405, "Method not allowed: POST DIDComm messages"), None);
+                }
+                let message = match &incoming.body {
+                    Ok(body) => body,
+                    Err(reply) => return (reply.clone(), None),
+                };
+                match accept(message, &account, Path::new(inbox), resolver) {
+                    Ok(received) => {
+                        println!("📨 {} from {} saved as {}.json", describe(received.kind), received.from, received.id);
+                        ((202, json!({ "id": received.id })), Some(received.from))
+                    }
+                    Err(e) => {
+                        eprintln!("⚠️  Refused a message: {}", e);
+                        (error(400, e), None)
+                    }
+                }
+            })?;
+        }
+        DidCommCommand::Key { did, identity } => {
+            let account = identity.unlock()?;
+            let method = didcomm::key_agreement_method(&account, did.as_deref().unwrap_or(&account.did))?;
+            println!("\nAdd to the DID Document's \"keyAgreement\" (and a DIDCommMessaging service for the endpoint):");
+            println!("{}", serde_json::to_string_pretty(&method).map_err(|e| e.to_string())?);
+        }
+    }
+    Ok(())
+}
+
+fn describe(kind: MessageKind) -> &'static str {
+    match kind {
+        MessageKind::Proposal => "Proposed transaction",
+        MessageKind::Signed => "Signed transaction",
+    }
+}
+
+/// A file to send: a signed transaction in any text envelope, or an unsigned one to propose
+fn read_document(path: &str) -> Result<(MessageKind, Value), String> {
+    let data = fs::read(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    match Envelope::parse(&data) {
+        Ok(Envelope::Cose(_)) => Err(format!("{} is COSE_Sign1, which DIDComm does not carry: sign it in a JSON envelope or as a JWS", path)),
+        Ok(Envelope::Jws(_)) => Ok((MessageKind::Signed, Value::String(String::from_utf8_lossy(&data).trim().to_string()))),
+        Ok(_) => Ok((MessageKind::Signed, serde_json::from_slice(&data).map_err(|e| format!("Failed to parse {}: {}", path, e))?)),
+        Err(signed_error) => {
+            let tx: Transaction = serde_json::from_slice(&data)
+                .map_err(|e| format!("{} is neither a signed transaction ({}) nor a transaction ({})", path, signed_error, e))?;
+            Ok((MessageKind::Proposal, serde_json::to_value(&tx).map_err(|e| e.to_string())?))
+        }
+    }
+}
+
+/// The bytes of a received document, as they would be read from a file
+fn document_bytes(document: &Value) -> Vec<u8> {
+    match document {
+        Value::String(jws) => jws.as_bytes().to_vec(),
+        document => document.to_string().into_bytes(),
+    }
+}
+
+/// Checks a received transaction: a signed one must verify, a proposal must parse
+fn check(received: &Received, resolver: &dyn DidResolver) -> Result<(), String> {
+    let data = document_bytes(&received.document);
+    match received.kind {
+        MessageKind::Signed => {
+            let envelope = Envelope::parse(&data)?;
+            envelope.verify_signature(resolver)?;
+            println!("✅ Signed by {}", envelope.signers().join(", "));
+            if !envelope.signers().contains(&received.from.as_str()) {
+                println!("   (forwarded by {}, who is not a signer)", received.from);
+            }
+        }
+        MessageKind::Proposal => {
+            serde_json::from_slice::<Transaction>(&data)
+                .map_err(|e| format!("The proposal from {} is not a transaction: {}", received.from, e))?;
+            println!("   Unsigned: review it, then sign it with `tlc sign`");
+        }
+    }
+    Ok(())
+}
+
+fn save(document: &Value, path: &Path) -> Result<(), String> {
+    match document {
+        Value::String(jws) => fs::write(path, jws).map_err(|e| format!("Failed to write {}: {}", path.display(), e)),
+        document => write_json(document, &path.to_string_lossy()),
+    }
+}
+
+/// Decrypts, checks and files one message in `inbox`
+fn accept(message: &str, account: &Account, inbox: &Path, resolver: &dyn DidResolver) -> Result<Received, String> {
+    let received = didcomm::unpack(message, account, resolver)?;
+    if received.id.is_empty() || !received.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Message id '{}' cannot name a file", received.id));
+    }
+    check(&received, resolver)?;
+    let path = inbox.join(format!("{}.json", received.id));
+    if path.exists() {
+        return Err(format!("Message {} was already received", received.id));
+    }
+    save(&received.document, &path)?;
+    Ok(received)
+}
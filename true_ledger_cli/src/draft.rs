@@ -0,0 +1,182 @@
+//! Drafts
+//! `tlc draft save|list|show|submit|return|post <ledger> ...`: a preparer
+//! saves an unsigned transaction as a draft and submits it for review; a
+//! reviewer returns it for changes or signs and posts it, carrying the
+//! preparer's signature from submission (see approval.rs). The ledger keeps
+//! drafts outside its chain and refuses out-of-order steps (see the core
+//! lifecycle.rs).
+
+use clap::Subcommand;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::chain;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::lifecycle::{Draft, TxState};
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::{Timestamp, Transaction};
+
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+use crate::store::open_existing;
+
+#[derive(Subcommand, Debug)]
+pub enum DraftCommand {
+    /// Save an unsigned transaction as a draft, or change a draft
+    Save {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        /// Unsigned transaction JSON (author and chain fields are ignored)
+        tx_path: String,
+
+        /// Draft to replace (default: a new draft named after the transaction's hash)
+        #[arg(long)]
+        id: Option<String>,
+
+        /// Submit it for review straight away
+        #[arg(long)]
+        submit: bool,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+
+    /// List the drafts and pending transactions in a ledger
+    List {
+        /// Ledger database or .ndjson journal
+        db: String,
+    },
+
+    /// Print a draft, or write it out for editing
+    Show {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        id: String,
+
+        /// Write the unsigned transaction to a file, ready for `tlc draft save --id`
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// Submit your draft for review; it cannot be changed while it is pending
+    Submit {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        id: String,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+
+    /// Send a pending transaction back to its preparer as a draft
+    Return {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        id: String,
+    },
+
+    /// Sign a pending transaction as its reviewer and post it to the ledger
+    Post {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        id: String,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+}
+
+fn now() -> Timestamp {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0).into()
+}
+
+/// `tlc draft ...`
+pub fn run_draft(command: &DraftCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        DraftCommand::Save { db, tx_path, id, submit, signer } => {
+            let data = fs::read_to_string(tx_path).map_err(|e| format!("Could not read {}: {}", tx_path, e))?;
+            let tx: Transaction = serde_json::from_str(&data).map_err(|e| format!("Failed to parse transaction {}: {}", tx_path, e))?;
+            let signer = signer_from_args(signer)?;
+            let mut draft = Draft::new(id.as_deref(), signer.did(), tx, now());
+            if *submit {
+                draft.submit(signer.as_ref(), now())?;
+            }
+            let mut ledger = open_existing(db)?;
+            ledger.save_draft(&draft)?;
+            println!("\n📝 Saved {} {} in {}: {}", draft.state, draft.id, db, draft.payload.memo);
+            Ok(())
+        }
+        DraftCommand::List { db } => {
+            let drafts = open_existing(db)?.drafts()?;
+            println!("{:<16} {:<8} {:<10} {:<20} Memo", "Id", "State", "Updated", "Prepared by");
+            for draft in &drafts {
+                println!("{:<16} {:<8} {:<10} {:<20} {}", draft.id, draft.state.to_string(), draft.updated.local_date().to_string(),
+                    abbreviate(&draft.prepared_by), draft.payload.memo);
+            }
+            println!("\n📝 {} draft(s)", drafts.len());
+            Ok(())
+        }
+        DraftCommand::Show { db, id, out_path } => {
+            let draft = find(db, id)?;
+            match out_path {
+                Some(out_path) => {
+                    write_json(&draft.payload, out_path)?;
+                    println!("💾 Draft {} ({}) saved to {}", id, draft.state, out_path);
+                }
+                None => println!("{}", serde_json::to_string_pretty(&draft).map_err(|e| format!("Failed to serialize draft: {}", e))?),
+            }
+            Ok(())
+        }
+        DraftCommand::Submit { db, id, signer } => {
+            let mut draft = find(db, id)?;
+            let signer = signer_from_args(signer)?;
+            if draft.state != TxState::Draft {
+                return Err(format!("Draft {} is already {}", id, draft.state));
+            }
+            draft.submit(signer.as_ref(), now())?;
+            open_existing(db)?.save_draft(&draft)?;
+            println!("\n📨 Submitted {} for review: {}", id, draft.payload.memo);
+            Ok(())
+        }
+        DraftCommand::Return { db, id } => {
+            let mut draft = find(db, id)?;
+            if draft.state != TxState::Pending {
+                return Err(format!("Draft {} is not pending review", id));
+            }
+            draft.return_to_draft(now());
+            open_existing(db)?.save_draft(&draft)?;
+            println!("\n↩️  Returned {} to {} as a draft", id, draft.prepared_by);
+            Ok(())
+        }
+        DraftCommand::Post { db, id, signer } => {
+            let draft = find(db, id)?;
+            let signer = signer_from_args(signer)?;
+            let mut ledger = open_existing(db)?;
+            let mut tx = draft.payload.clone();
+            tx.author_did = signer.did().to_string();
+            draft.check_posting(&tx)?; // Before anything is signed
+            chain::link(&mut tx, ledger.head()?.as_ref().map(|head| &head.payload));
+            tx.sequence = Some(ledger.next_sequence(&tx.author_did)?);
+
+            let mut signed_tx = signer.sign_transaction(tx)?;
+            signed_tx.preparation = draft.preparation.clone();
+            verify_with(&signed_tx, resolver)?; // The ledger only holds transactions that verify
+            let hash = ledger.post_draft(id, &signed_tx)?;
+            println!("\n✅ Posted {} to {} at height {} ({})", id, db, signed_tx.payload.height.unwrap_or(0), &hash[..16]);
+            println!("   CID: {}", signed_tx.cid());
+            Ok(())
+        }
+    }
+}
+
+fn find(db: &str, id: &str) -> Result<Draft, String> {
+    open_existing(db)?.draft(id)?.ok_or_else(|| format!("No draft {} in {}", id, db))
+}
+
+/// The start and end of a long DID, for tables
+fn abbreviate(did: &str) -> String {
+    if did.len() <= 20 { did.to_string() } else { format!("{}…{}", &did[..12], &did[did.len() - 6..]) }
+}
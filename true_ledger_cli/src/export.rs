@@ -0,0 +1,36 @@
+//! Export (QuickBooks IIF)
+//! Lets clients whose accountants use QuickBooks import verified transactions
+//! as general journal entries instead of re-keying them. Only transactions
+//! that passed every check are exported.
+
+use std::fs;
+use true_ledger_core::Transaction;
+
+/// Renders the transaction as an IIF general journal entry.
+/// IIF signs amounts: debits are positive, credits negative.
+fn to_iif(tx: &Transaction) -> Result<String, String> {
+    // The day the transaction was recorded, in its own time zone (UTC for Unix timestamps)
+    let date = tx.timestamp.local_date().format("%m/%d/%Y").to_string();
+    let docnum = &hex::encode(tx.get_hash())[..8]; // Ties the QB entry back to our hash
+    let memo: String = tx.memo.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+
+    let mut iif = String::new();
+    iif.push_str("!TRNS\tTRNSTYPE\tDATE\tACCNT\tAMOUNT\tDOCNUM\tMEMO\n");
+    iif.push_str("!SPL\tTRNSTYPE\tDATE\tACCNT\tAMOUNT\tDOCNUM\tMEMO\n");
+    iif.push_str("!ENDTRNS\n");
+    for (i, entry) in tx.entries.iter().enumerate() {
+        let debit = entry.debit.parse::<f64>().map_err(|_| "Invalid debit amount format (Not a number).".to_string())?;
+        let credit = entry.credit.parse::<f64>().map_err(|_| "Invalid credit amount format (Not a number).".to_string())?;
+        let line_type = if i == 0 { "TRNS" } else { "SPL" };
+        iif.push_str(&format!("{}\tGENERAL JOURNAL\t{}\t{}\t{:.2}\t{}\t{}\n",
+            line_type, date, entry.account_id, debit - credit, docnum, memo));
+    }
+    iif.push_str("ENDTRNS\n");
+    Ok(iif)
+}
+
+/// Writes the transaction to an .iif file
+pub fn export_iif(tx: &Transaction, path: &str) -> Result<(), String> {
+    let iif = to_iif(tx)?;
+    fs::write(path, iif).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
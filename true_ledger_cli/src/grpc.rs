@@ -0,0 +1,370 @@
+//! gRPC API
+//! `tlc serve-grpc` offers the verifier and the ledger over a typed contract
+//! (proto/true_ledger.proto): VerifyTransaction, SubmitTransaction and
+//! GetTransaction. Messages convert losslessly to and from the JSON model,
+//! so a transaction verifies the same whichever way it arrives.
+
+// tonic's Status is a large error type, but every RPC handler must return it
+#![allow(clippy::result_large_err)]
+
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use tonic::{Request, Response, Status};
+use true_ledger_core::approval::Preparation;
+use true_ledger_core::capability::{Capability, SignedCapability};
+use true_ledger_core::did_web::DidWebResolver;
+use true_ledger_core::key_events::KeyEvent;
+use true_ledger_core::multisig::{Cosignature, SigningPolicy};
+use true_ledger_core::policy::Policy;
+use true_ledger_core::private_memo::{MemoOpening, PrivateMemo, Redaction, SealedMemo, SignedRedaction};
+use true_ledger_core::schema::check_signed_transaction;
+use true_ledger_core::storage::{self, Storage};
+use true_ledger_core::versioning::check_version;
+use true_ledger_core::verify::{verdict_with, Verdict};
+use true_ledger_core::{JournalEntry, LedgerError, SignedTransaction, Timestamp, Transaction};
+
+pub mod pb {
+    tonic::include_proto!("true_ledger.v1");
+}
+
+use pb::ledger_server::{Ledger, LedgerServer};
+
+/// String-valued enums (canonicalization, sig_alg, hash_alg) use their JSON names on the wire
+fn enum_from_wire<T: serde::de::DeserializeOwned>(field: &str, value: Option<String>) -> Result<Option<T>, Status> {
+    value.map(|v| serde_json::from_value(serde_json::Value::String(v.clone()))
+        .map_err(|_| Status::invalid_argument(format!("Unknown {} '{}'", field, v))))
+        .transpose()
+}
+
+fn enum_to_wire<T: serde::Serialize>(value: Option<T>) -> Option<String> {
+    value.and_then(|v| serde_json::to_value(v).ok()).and_then(|v| v.as_str().map(str::to_string))
+}
+
+impl TryFrom<pb::KeyEvent> for KeyEvent {
+    type Error = Status;
+
+    fn try_from(event: pb::KeyEvent) -> Result<Self, Status> {
+        let missing = |field: &str| Status::invalid_argument(format!("Key event '{}' needs {}", event.action, field));
+        match event.action.as_str() {
+            "rotate" => Ok(KeyEvent::Rotate { new_did: event.new_did.clone().ok_or_else(|| missing("new_did"))? }),
+            "revoke" => Ok(KeyEvent::Revoke {
+                did: event.did.clone().ok_or_else(|| missing("did"))?,
+                revoked_at: event.revoked_at.as_deref().map(str::parse::<Timestamp>).transpose()
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?,
+            }),
+            other => Err(Status::invalid_argument(format!("Unknown key event action '{}'", other))),
+        }
+    }
+}
+
+impl From<KeyEvent> for pb::KeyEvent {
+    fn from(event: KeyEvent) -> Self {
+        match event {
+            KeyEvent::Rotate { new_did } => pb::KeyEvent { action: "rotate".to_string(), new_did: Some(new_did), did: None, revoked_at: None },
+            KeyEvent::Revoke { did, revoked_at } => pb::KeyEvent {
+                action: "revoke".to_string(),
+                new_did: None,
+                did: Some(did),
+                revoked_at: revoked_at.map(|t| t.to_string()),
+            },
+        }
+    }
+}
+
+impl TryFrom<pb::Policy> for Policy {
+    type Error = Status;
+
+    fn try_from(policy: pb::Policy) -> Result<Self, Status> {
+        Ok(Policy {
+            version: policy.version,
+            effective_from: policy.effective_from.parse::<Timestamp>().map_err(|e| Status::invalid_argument(e.to_string()))?,
+            administrators: policy.administrators,
+            rules: policy.rules,
+        })
+    }
+}
+
+impl From<Policy> for pb::Policy {
+    fn from(policy: Policy) -> Self {
+        pb::Policy {
+            version: policy.version,
+            effective_from: policy.effective_from.to_string(),
+            administrators: policy.administrators,
+            rules: policy.rules,
+        }
+    }
+}
+
+impl TryFrom<pb::SignedCapability> for SignedCapability {
+    type Error = Status;
+
+    fn try_from(signed: pb::SignedCapability) -> Result<Self, Status> {
+        let capability = signed.capability.ok_or_else(|| Status::invalid_argument("Missing capability"))?;
+        let timestamp = |text: &str| text.parse::<Timestamp>().map_err(|e| Status::invalid_argument(e.to_string()));
+        Ok(SignedCapability {
+            capability: Capability {
+                issuer: capability.issuer,
+                holder: capability.holder,
+                accounts: capability.accounts,
+                max: capability.max,
+                not_before: capability.not_before.as_deref().map(timestamp).transpose()?,
+                expires: timestamp(&capability.expires)?,
+                parent: capability.parent,
+            },
+            signature: signed.signature,
+        })
+    }
+}
+
+impl From<SignedCapability> for pb::SignedCapability {
+    fn from(signed: SignedCapability) -> Self {
+        let capability = signed.capability;
+        pb::SignedCapability {
+            capability: Some(pb::Capability {
+                issuer: capability.issuer,
+                holder: capability.holder,
+                accounts: capability.accounts,
+                max: capability.max,
+                not_before: capability.not_before.map(|t| t.to_string()),
+                expires: capability.expires.to_string(),
+                parent: capability.parent,
+            }),
+            signature: signed.signature,
+        }
+    }
+}
+
+impl TryFrom<pb::PrivateMemo> for PrivateMemo {
+    type Error = Status;
+
+    fn try_from(memo: pb::PrivateMemo) -> Result<Self, Status> {
+        match memo.state.ok_or_else(|| Status::invalid_argument("Missing private memo state"))? {
+            pb::private_memo::State::Open(open) => Ok(PrivateMemo::Open(MemoOpening { salt: open.salt, text: open.text })),
+            pb::private_memo::State::Sealed(sealed) => Ok(PrivateMemo::Sealed(SealedMemo {
+                key_id: sealed.key_id,
+                nonce: sealed.nonce,
+                ciphertext: sealed.ciphertext,
+            })),
+            pb::private_memo::State::Redacted(signed) => {
+                let redaction = signed.redaction.ok_or_else(|| Status::invalid_argument("Missing redaction"))?;
+                Ok(PrivateMemo::Redacted(SignedRedaction {
+                    redaction: Redaction {
+                        payload_hash: redaction.payload_hash,
+                        commitment: redaction.commitment,
+                        redacted_by: redaction.redacted_by,
+                        timestamp: redaction.timestamp.parse().map_err(|e: LedgerError| Status::invalid_argument(e.to_string()))?,
+                        reason: redaction.reason,
+                    },
+                    signature: signed.signature,
+                }))
+            }
+        }
+    }
+}
+
+impl From<PrivateMemo> for pb::PrivateMemo {
+    fn from(memo: PrivateMemo) -> Self {
+        let state = match memo {
+            PrivateMemo::Open(open) => pb::private_memo::State::Open(pb::MemoOpening { salt: open.salt, text: open.text }),
+            PrivateMemo::Sealed(sealed) => pb::private_memo::State::Sealed(pb::SealedMemo {
+                key_id: sealed.key_id,
+                nonce: sealed.nonce,
+                ciphertext: sealed.ciphertext,
+            }),
+            PrivateMemo::Redacted(signed) => pb::private_memo::State::Redacted(pb::SignedRedaction {
+                redaction: Some(pb::Redaction {
+                    payload_hash: signed.redaction.payload_hash,
+                    commitment: signed.redaction.commitment,
+                    redacted_by: signed.redaction.redacted_by,
+                    timestamp: signed.redaction.timestamp.to_string(),
+                    reason: signed.redaction.reason,
+                }),
+                signature: signed.signature,
+            }),
+        };
+        pb::PrivateMemo { state: Some(state) }
+    }
+}
+
+impl TryFrom<pb::SignedTransaction> for SignedTransaction {
+    type Error = Status;
+
+    fn try_from(signed_tx: pb::SignedTransaction) -> Result<Self, Status> {
+        let payload = signed_tx.payload.ok_or_else(|| Status::invalid_argument("Missing payload"))?;
+        let signed_tx = SignedTransaction {
+            payload: Transaction {
+                timestamp: match payload.timestamp_rfc3339 {
+                    Some(text) => Timestamp::parse_rfc3339(&text).map_err(|e| Status::invalid_argument(e.to_string()))?,
+                    None => payload.timestamp.into(),
+                },
+                author_did: payload.author_did,
+                entries: payload.entries.into_iter()
+                    .map(|e| JournalEntry { account_id: e.account_id, debit: e.debit, credit: e.credit, currency: e.currency })
+                    .collect(),
+                memo: payload.memo,
+                prev_hash: payload.prev_hash,
+                height: payload.height,
+                sequence: payload.sequence,
+                canonicalization: enum_from_wire("canonicalization", payload.canonicalization)?,
+                signing_policy: payload.signing_policy
+                    .map(|p| SigningPolicy { threshold: p.threshold, signers: p.signers }),
+                key_event: payload.key_event.map(KeyEvent::try_from).transpose()?,
+                hash_alg: enum_from_wire("hash_alg", payload.hash_alg)?,
+                version: payload.version,
+                adjusting: payload.adjusting,
+                reverses: payload.reverses,
+                prepared_by: payload.prepared_by,
+                memo_commitment: payload.memo_commitment,
+                policy: payload.policy.map(Policy::try_from).transpose()?,
+            },
+            signature: signed_tx.signature,
+            digest: signed_tx.digest,
+            sig_alg: enum_from_wire("sig_alg", signed_tx.sig_alg)?,
+            cosignatures: signed_tx.cosignatures.into_iter()
+                .map(|c| Ok(Cosignature {
+                    signer_did: c.signer_did,
+                    signature: c.signature,
+                    sig_alg: enum_from_wire("sig_alg", c.sig_alg)?,
+                }))
+                .collect::<Result<_, Status>>()?,
+            preparation: signed_tx.preparation.map(|signature| Preparation { signature }),
+            delegation: signed_tx.delegation.into_iter().map(SignedCapability::try_from).collect::<Result<_, Status>>()?,
+            private_memo: signed_tx.private_memo.map(PrivateMemo::try_from).transpose()?,
+        };
+        // The same schema and version checks as a JSON submission, which
+        // also keep out integers that JCS cannot represent exactly
+        let document = serde_json::to_value(&signed_tx).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        check_signed_transaction(&document)
+            .and_then(|_| check_version(&signed_tx.payload))
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        Ok(signed_tx)
+    }
+}
+
+impl From<SignedTransaction> for pb::SignedTransaction {
+    fn from(signed_tx: SignedTransaction) -> Self {
+        let payload = signed_tx.payload;
+        pb::SignedTransaction {
+            payload: Some(pb::Transaction {
+                timestamp: match &payload.timestamp { Timestamp::Unix(seconds) => *seconds, Timestamp::Rfc3339(_) => 0 },
+                timestamp_rfc3339: match payload.timestamp { Timestamp::Rfc3339(text) => Some(text), Timestamp::Unix(_) => None },
+                author_did: payload.author_did,
+                entries: payload.entries.into_iter()
+                    .map(|e| pb::JournalEntry { account_id: e.account_id, debit: e.debit, credit: e.credit, currency: e.currency })
+                    .collect(),
+                memo: payload.memo,
+                prev_hash: payload.prev_hash,
+                height: payload.height,
+                sequence: payload.sequence,
+                canonicalization: enum_to_wire(payload.canonicalization),
+                signing_policy: payload.signing_policy
+                    .map(|p| pb::SigningPolicy { threshold: p.threshold, signers: p.signers }),
+                key_event: payload.key_event.map(pb::KeyEvent::from),
+                hash_alg: enum_to_wire(payload.hash_alg),
+                version: payload.version,
+                adjusting: payload.adjusting,
+                reverses: payload.reverses,
+                prepared_by: payload.prepared_by,
+                memo_commitment: payload.memo_commitment,
+                policy: payload.policy.map(pb::Policy::from),
+            }),
+            signature: signed_tx.signature,
+            digest: signed_tx.digest,
+            sig_alg: enum_to_wire(signed_tx.sig_alg),
+            cosignatures: signed_tx.cosignatures.into_iter()
+                .map(|c| pb::Cosignature { signer_did: c.signer_did, signature: c.signature, sig_alg: enum_to_wire(c.sig_alg) })
+                .collect(),
+            preparation: signed_tx.preparation.map(|p| p.signature),
+            delegation: signed_tx.delegation.into_iter().map(pb::SignedCapability::from).collect(),
+            private_memo: signed_tx.private_memo.map(pb::PrivateMemo::from),
+        }
+    }
+}
+
+impl From<Verdict> for pb::Verdict {
+    fn from(verdict: Verdict) -> Self {
+        pb::Verdict {
+            valid: verdict.valid,
+            hash: verdict.hash,
+            cid: verdict.cid,
+            checks: verdict.checks.into_iter()
+                .map(|c| pb::Check { check: c.check, ok: c.ok, error: c.error })
+                .collect(),
+        }
+    }
+}
+
+struct LedgerService {
+    ledger: Option<Mutex<Box<dyn Storage>>>,
+    resolver: DidWebResolver,
+}
+
+impl LedgerService {
+    fn ledger(&self) -> Result<std::sync::MutexGuard<'_, Box<dyn Storage>>, Status> {
+        self.ledger.as_ref()
+            .ok_or_else(|| Status::failed_precondition("No ledger configured (start the server with --store)"))?
+            .lock()
+            .map_err(|_| Status::internal("Ledger lock poisoned"))
+    }
+
+    /// Verification may fetch did:web documents, so it runs off the async workers
+    fn verdict(&self, signed_tx: &SignedTransaction) -> Verdict {
+        tokio::task::block_in_place(|| verdict_with(signed_tx, &self.resolver))
+    }
+}
+
+#[tonic::async_trait]
+impl Ledger for LedgerService {
+    async fn verify_transaction(&self, request: Request<pb::SignedTransaction>) -> Result<Response<pb::Verdict>, Status> {
+        let signed_tx = SignedTransaction::try_from(request.into_inner())?;
+        Ok(Response::new(self.verdict(&signed_tx).into()))
+    }
+
+    async fn submit_transaction(&self, request: Request<pb::SignedTransaction>)
+        -> Result<Response<pb::SubmitTransactionResponse>, Status> {
+        let signed_tx = SignedTransaction::try_from(request.into_inner())?;
+        let verdict = self.verdict(&signed_tx);
+        let stored = verdict.valid;
+        if stored {
+            let mut ledger = self.ledger()?;
+            if ledger.get_by_hash(&verdict.hash).map_err(Status::internal)?.is_some() {
+                return Err(Status::already_exists(format!("Transaction {} is already stored", verdict.hash)));
+            }
+            ledger.append(&signed_tx).map_err(|e| match e {
+                LedgerError::Chain(_) => Status::failed_precondition(e.to_string()),
+                e => Status::internal(e),
+            })?;
+        }
+        Ok(Response::new(pb::SubmitTransactionResponse { verdict: Some(verdict.into()), stored }))
+    }
+
+    async fn get_transaction(&self, request: Request<pb::GetTransactionRequest>)
+        -> Result<Response<pb::SignedTransaction>, Status> {
+        let hash = request.into_inner().hash;
+        match self.ledger()?.get_by_hash(&hash).map_err(Status::internal)? {
+            Some(signed_tx) => Ok(Response::new(signed_tx.into())),
+            None => Err(Status::not_found(format!("No transaction {}", hash))),
+        }
+    }
+}
+
+/// `tlc serve-grpc [--listen ADDR] [--store LEDGER]`: serves until killed
+pub fn serve_grpc(listen: &str, store: Option<&str>, resolver: DidWebResolver) -> Result<(), String> {
+    let addr: SocketAddr = listen.parse().map_err(|e| format!("Invalid listen address {}: {}", listen, e))?;
+    let service = LedgerService {
+        ledger: store.map(storage::open).transpose()?.map(Mutex::new),
+        resolver,
+    };
+
+    println!("🌐 gRPC ledger service (true_ledger.v1.Ledger) listening on {}", addr);
+    match store {
+        Some(db) => println!("   Ledger: {}", db),
+        None => println!("   (no --store: SubmitTransaction and GetTransaction are disabled)"),
+    }
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| format!("Could not start the async runtime: {}", e))?;
+    runtime.block_on(tonic::transport::Server::builder()
+        .add_service(LedgerServer::new(service))
+        .serve(addr))
+        .map_err(|e| format!("gRPC server failed: {}", e))
+}
@@ -0,0 +1,147 @@
+//! Opening Balance Import
+//! Takes a trial balance exported from the previous accounting system and turns
+//! it into one signed opening-balance transaction on the new chart of accounts.
+//! Both files are simple CSVs with a header row and no quoted fields:
+//!   trial balance: account,debit,credit
+//!   mapping:       old_account,new_account
+
+use clap::Args;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::amount::{format_cents, parse_cents};
+use true_ledger_core::{JournalEntry, Timestamp, Transaction};
+
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+
+/// Reads the data rows of a CSV file (header skipped, blank lines ignored)
+fn read_csv_rows(path: &str) -> Result<Vec<Vec<String>>, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    Ok(data.lines()
+        .skip(1)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.split(',').map(|f| f.trim().trim_matches('"').to_string()).collect())
+        .collect())
+}
+
+/// Builds the opening-balance transaction from a trial balance and optional mapping
+fn import_trial_balance(tb_path: &str, map_path: Option<&str>, author_did: &str, timestamp: Timestamp) -> Result<Transaction, String> {
+    // Step 1: Read the trial balance and check that it balances on its own
+    let mut rows = Vec::new();
+    let (mut total_debits, mut total_credits) = (0i64, 0i64);
+    for (i, row) in read_csv_rows(tb_path)?.into_iter().enumerate() {
+        if row.len() < 3 {
+            return Err(format!("{} row {}: expected account,debit,credit", tb_path, i + 2));
+        }
+        let debit = parse_cents(&row[1]).map_err(|e| format!("{} row {}: {}", tb_path, i + 2, e))?;
+        let credit = parse_cents(&row[2]).map_err(|e| format!("{} row {}: {}", tb_path, i + 2, e))?;
+        total_debits += debit;
+        total_credits += credit;
+        rows.push((row[0].clone(), debit, credit));
+    }
+    println!("   Step 1: Read {} trial balance lines.", rows.len());
+    if total_debits != total_credits {
+        return Err(format!("Trial balance does not balance: Debits ({}) != Credits ({})",
+            format_cents(total_debits), format_cents(total_credits)));
+    }
+    println!("   Step 2: Trial balance balances at {}.", format_cents(total_debits));
+
+    // Step 3: Map old account codes onto the new chart (identity if no map given)
+    let mapping: Option<HashMap<String, String>> = match map_path {
+        Some(path) => Some(read_csv_rows(path)?
+            .into_iter()
+            .filter(|row| row.len() >= 2)
+            .map(|row| (row[0].clone(), row[1].clone()))
+            .collect()),
+        None => None,
+    };
+    let mut net_by_account: BTreeMap<String, i64> = BTreeMap::new();
+    let mut unmapped = Vec::new();
+    for (old_account, debit, credit) in rows {
+        let new_account = match &mapping {
+            Some(map) => match map.get(&old_account) {
+                Some(new_account) => new_account.clone(),
+                None => {
+                    unmapped.push(old_account);
+                    continue;
+                }
+            },
+            None => old_account,
+        };
+        *net_by_account.entry(new_account).or_insert(0) += debit - credit;
+    }
+    if !unmapped.is_empty() {
+        return Err(format!("No mapping for old account(s): {}", unmapped.join(", ")));
+    }
+    println!("   Step 3: Mapped onto {} account(s) in the new chart.", net_by_account.len());
+
+    // Step 4: One entry per account with a non-zero net balance
+    let entries: Vec<JournalEntry> = net_by_account.into_iter()
+        .filter(|(_, net)| *net != 0)
+        .map(|(account_id, net)| JournalEntry {
+            account_id,
+            debit: format_cents(net.max(0)),
+            credit: format_cents((-net).max(0)),
+            currency: None,
+        })
+        .collect();
+
+    Ok(Transaction {
+        timestamp,
+        author_did: author_did.to_string(),
+        memo: format!("Opening balances imported from prior system ({} accounts).", entries.len()),
+        entries,
+        prev_hash: None, // Opening balances start a new chain
+        height: Some(0),
+        sequence: Some(0), // The author's first transaction in the new chain
+        canonicalization: None, // Chosen by the signer
+        signing_policy: None,
+        key_event: None,
+        hash_alg: None, // Chosen by the signer
+        version: None, // Set by the signer
+        adjusting: None,
+        reverses: None,
+        prepared_by: None,
+        memo_commitment: None,
+        policy: None,
+    })
+}
+
+
+/// Options for `tlc import-tb`
+#[derive(Args, Debug)]
+pub struct ImportArgs {
+    /// Trial balance CSV (account,debit,credit)
+    pub tb_path: String,
+
+    /// Old-to-new account mapping CSV (old_account,new_account)
+    #[arg(long = "map", value_name = "FILE")]
+    pub map_path: Option<String>,
+
+    /// Date of the opening balances: Unix seconds or RFC 3339 (default: now)
+    #[arg(long)]
+    pub timestamp: Option<Timestamp>,
+
+    /// Where to write the signed transaction
+    #[arg(long = "out", value_name = "FILE", default_value = "opening_balance.json")]
+    pub out_path: String,
+
+    #[command(flatten)]
+    pub signer: SignerArgs,
+}
+
+/// `tlc import-tb <tb.csv> [--map map.csv] [--timestamp T] [--out FILE]`
+pub fn run_import(args: &ImportArgs) -> Result<(), String> {
+    let timestamp = args.timestamp.clone()
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0).into());
+
+    println!("\n📥 Importing trial balance from {}...", args.tb_path);
+    let signer = signer_from_args(&args.signer)?;
+    let opening_tx = import_trial_balance(&args.tb_path, args.map_path.as_deref(), signer.did(), timestamp)?;
+    let signed_tx = signer.sign_transaction(opening_tx)?;
+    write_json(&signed_tx, &args.out_path)?;
+
+    println!("\n💾 Signed opening-balance transaction saved to:");
+    println!("   {}", args.out_path);
+    Ok(())
+}
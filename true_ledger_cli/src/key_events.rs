@@ -0,0 +1,118 @@
+//! Key Rotation and Revocation
+//! `tlc key rotate --to <DID>` hands the signer's authority to a new DID;
+//! `tlc key revoke <DID>` marks a key compromised. Both sign an entry-less
+//! transaction carrying the key event, which belongs in the same ledger as
+//! the postings so `tlc verify --key-events` and `tlc chain verify` see it.
+
+use clap::{Args, Subcommand};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::key_events::{KeyEvent, KeyRegistry};
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::{Timestamp, Transaction};
+
+use crate::signing::{sign_and_record, signer_from_args, SignerArgs};
+use crate::store::journal_entries;
+
+#[derive(Subcommand, Debug)]
+pub enum KeyCommand {
+    /// Hand the signer's authority over to a new DID; the old key stops signing now
+    Rotate {
+        /// The successor DID
+        #[arg(long = "to", value_name = "DID")]
+        new_did: String,
+
+        #[command(flatten)]
+        output: KeyEventArgs,
+    },
+
+    /// Revoke a compromised key, as the key itself or a key it was rotated to
+    Revoke {
+        /// The DID to revoke
+        did: String,
+
+        /// When the key was compromised (Unix seconds or RFC 3339; default: now).
+        /// Transactions it signed from then on fail verification.
+        #[arg(long, value_name = "TIMESTAMP")]
+        at: Option<Timestamp>,
+
+        #[command(flatten)]
+        output: KeyEventArgs,
+    },
+}
+
+/// Where a key event (or a policy, see policy.rs) goes, and who signs it
+#[derive(Args, Debug)]
+pub struct KeyEventArgs {
+    /// Where to write the signed key event (default: key_event.json,
+    /// or no file when --store is given)
+    #[arg(long = "out", value_name = "FILE")]
+    pub out_path: Option<String>,
+
+    /// Chain the key event onto this ledger (database or .ndjson journal) and store it there
+    #[arg(long, value_name = "DB")]
+    pub store: Option<String>,
+
+    #[command(flatten)]
+    pub signer: SignerArgs,
+}
+
+pub fn run_key(command: &KeyCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    let (event, output) = match command {
+        KeyCommand::Rotate { new_did, output } => (KeyEvent::Rotate { new_did: new_did.clone() }, output),
+        KeyCommand::Revoke { did, at, output } => (KeyEvent::Revoke { did: did.clone(), revoked_at: at.clone() }, output),
+    };
+    let signer = signer_from_args(&output.signer)?;
+    let author_did = signer.did().to_string();
+    let memo = match &event {
+        KeyEvent::Rotate { new_did } => format!("Key rotation: {} -> {}", author_did, new_did),
+        KeyEvent::Revoke { did, .. } => format!("Key revocation: {}", did),
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let tx = Transaction {
+        timestamp: now.into(),
+        author_did,
+        entries: Vec::new(),
+        memo,
+        prev_hash: None,
+        height: None,
+        sequence: None,
+        canonicalization: None,
+        signing_policy: None,
+        key_event: Some(event),
+        hash_alg: None,
+        version: None,
+        adjusting: None,
+        reverses: None,
+        prepared_by: None,
+        memo_commitment: None,
+        policy: None,
+    };
+
+    // Refuse an event the ledger's registry would not accept
+    if let Some(store) = output.store.as_deref().filter(|store| Path::new(store).exists()) {
+        load_registry(store, resolver)?.apply(&tx)?;
+    }
+
+    println!("\n🔑 Signing key event: {}...", tx.memo);
+    let store = output.store.as_deref();
+    sign_and_record(tx, signer.as_ref(), store, output.out_path.as_deref().or(store.xor(Some("key_event.json"))), resolver)?;
+    Ok(())
+}
+
+/// Replays the key events of a journal, in order. Only verified events count;
+/// those that fail, or that their author was not entitled to make, are skipped.
+pub fn load_registry(path: &str, resolver: &dyn DidResolver) -> Result<KeyRegistry, String> {
+    let mut registry = KeyRegistry::new();
+    for entry in journal_entries(path)? {
+        let (name, signed_tx) = entry?;
+        if signed_tx.payload.key_event.is_none() {
+            continue;
+        }
+        if let Err(e) = verify_with(&signed_tx, resolver).and_then(|_| registry.apply(&signed_tx.payload)) {
+            eprintln!("⚠️  Ignored key event {}: {}", name, e);
+        }
+    }
+    Ok(registry)
+}
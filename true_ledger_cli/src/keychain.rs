@@ -0,0 +1,66 @@
+//! OS Keychain Identities
+//! `tlc keychain new|import|delete`: keeps identities in the operating
+//! system's credential store rather than as key files (see the core
+//! keychain.rs). `--keychain NAME` then signs with one, wherever
+//! `--identity NAME` would.
+//!
+//! Built only with the `keychain` feature.
+
+use clap::Subcommand;
+use true_ledger_core::keychain;
+use true_ledger_core::Account;
+
+use crate::signing::{unlock_identity, KeyAlg, KeystoreArgs};
+
+#[derive(Subcommand, Debug)]
+pub enum KeychainCommand {
+    /// Create a new identity in the OS keychain
+    New {
+        /// Name to store the identity under (never overwritten)
+        name: String,
+
+        /// Signature suite of the new key
+        #[arg(long, value_enum, default_value = "ed25519")]
+        alg: KeyAlg,
+    },
+
+    /// Copy an identity from the file keystore into the OS keychain
+    Import {
+        /// Identity name, in the keystore and then in the keychain
+        name: String,
+
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+
+    /// Remove an identity from the OS keychain
+    Delete {
+        /// Identity name
+        name: String,
+    },
+}
+
+/// `tlc keychain new|import|delete`
+pub fn run_keychain(command: &KeychainCommand) -> Result<(), String> {
+    match command {
+        KeychainCommand::New { name, alg } => {
+            let account = Account::generate_for((*alg).into());
+            keychain::store(name, &account)?;
+            println!("✅ New Account Created!");
+            println!("   DID: {}", account.did);
+            println!("\n🔑 Identity '{}' stored in the OS keychain", name);
+        }
+        KeychainCommand::Import { name, keystore } => {
+            let keystore = keystore.open();
+            let account = unlock_identity(&keystore, name)?;
+            keychain::store(name, &account)?;
+            println!("✅ Identity '{}' ({}) copied into the OS keychain", name, account.did);
+            println!("   The key file is still in {}; delete it once `--keychain {}` signs.", keystore.dir().display(), name);
+        }
+        KeychainCommand::Delete { name } => {
+            keychain::delete(name)?;
+            println!("🗑️  Identity '{}' removed from the OS keychain", name);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,985 @@
+/*
+ * TRUE LEDGER CORE - THE `tlc` COMMAND-LINE TOOL
+ * One binary for the whole workflow: create an identity, sign transactions,
+ * and verify single transactions or whole chains. Every file it reads or
+ * writes is named on the command line.
+ * The ledger logic itself lives in the true_ledger_core library.
+ */
+
+mod anchor;
+mod attestation;
+mod auth;
+mod backup;
+mod batch;
+mod bench;
+mod chain;
+mod checkpoint;
+mod connector;
+mod debug;
+mod delegate;
+#[cfg(feature = "didcomm")]
+mod didcomm;
+mod draft;
+mod export;
+mod generate;
+mod grpc;
+mod import;
+mod key_events;
+#[cfg(feature = "keychain")]
+mod keychain;
+mod memo;
+mod merge;
+mod merkle;
+mod migrate;
+mod period_close;
+mod policy;
+mod presentation;
+mod raft;
+mod rebuild;
+mod recurring;
+mod report;
+mod revalue;
+mod reverse;
+mod sample;
+mod serve;
+mod signing;
+#[cfg(feature = "sigstore")]
+mod sigstore;
+mod simulate;
+mod store;
+mod sync;
+mod tenants;
+mod tls;
+mod trial_balance;
+mod vectors;
+mod verify;
+mod watch;
+
+use clap::{Parser, Subcommand};
+
+use crate::anchor::AnchorCommand;
+use crate::connector::ConnectorCommand;
+use crate::delegate::DelegateArgs;
+#[cfg(feature = "didcomm")]
+use crate::didcomm::DidCommCommand;
+use crate::draft::DraftCommand;
+use crate::generate::GeneratorConfig;
+use crate::import::ImportArgs;
+use crate::bench::BenchCommand;
+use crate::key_events::KeyCommand;
+#[cfg(feature = "keychain")]
+use crate::keychain::KeychainCommand;
+use crate::policy::PolicyCommand;
+use crate::presentation::{PresentArgs, VerifyPresentationArgs};
+use crate::memo::MemoCommand;
+use crate::merkle::TrustedRoot;
+use crate::raft::RaftArgs;
+use crate::report::ReportCommand;
+use crate::revalue::RevalueArgs;
+use crate::reverse::ReverseArgs;
+use crate::sample::MethodArg;
+use crate::signing::{KeyAlg, KeystoreArgs, KeystoreCommand, SignArgs, SignerArgs};
+use crate::simulate::SimulateArgs;
+use crate::store::StoreCommand;
+use crate::tls::TlsArgs;
+use crate::verify::{ResolverArgs, VerifyArgs};
+
+#[derive(Parser, Debug)]
+#[command(name = "tlc", version, about = "True Ledger Core: sign and verify double-entry transactions")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Create a new identity, sealed in the keystore under a passphrase
+    Keygen {
+        /// Name to store the identity under (never overwritten)
+        name: String,
+
+        /// Signature suite of the new key
+        #[arg(long, value_enum, default_value = "ed25519")]
+        alg: KeyAlg,
+
+        /// Derive the key from a new 24-word recovery phrase (printed once; Ed25519 only)
+        #[arg(long)]
+        mnemonic: bool,
+
+        /// Also write the identity's W3C DID Document here
+        #[arg(long, value_name = "FILE")]
+        did_document: Option<String>,
+
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+
+    /// Rebuild an identity from its 24-word recovery phrase
+    Recover {
+        /// Name to store the recovered identity under
+        name: String,
+
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+
+    /// Print the W3C DID Document of a did:key or did:web
+    Resolve {
+        /// The DID to resolve
+        did: String,
+
+        /// Write the document to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// List or unlock identities in the keystore
+    Keystore {
+        #[command(subcommand)]
+        command: KeystoreCommand,
+
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+    },
+
+    /// Keep identities in the OS keychain instead of key files
+    #[cfg(feature = "keychain")]
+    Keychain {
+        #[command(subcommand)]
+        command: KeychainCommand,
+    },
+
+    /// Send transactions to counterparties' DIDs as encrypted DIDComm messages, and receive them
+    #[cfg(feature = "didcomm")]
+    Didcomm {
+        #[command(subcommand)]
+        command: DidCommCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Rotate the signing key to a new DID, or revoke a compromised one
+    Key {
+        #[command(subcommand)]
+        command: KeyCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Keep the posting policy in the ledger as signed, versioned documents
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Sign an unsigned transaction
+    Sign {
+        #[command(flatten)]
+        args: SignArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Add your approval to a transaction whose signing policy needs several
+    Cosign {
+        /// Signed transaction to approve
+        path: String,
+
+        /// Where to write the cosigned transaction (default: update it in place)
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+
+    /// Verify a signed transaction (signature, timestamp, approvals, balance, red flags).
+    /// Exits 0 if valid, 1 if a check failed, 2 if it could not be checked.
+    Verify(VerifyArgs),
+
+    /// Verify many independent transactions in parallel (signature and balance)
+    VerifyBatch {
+        /// Transaction files and/or directories of them
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Worker threads (default: one per core)
+        #[arg(long, short = 'j', value_name = "N")]
+        jobs: Option<usize>,
+
+        /// Only list the files that fail
+        #[arg(long, short = 'q')]
+        quiet: bool,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Work with hash-chained directories of transactions
+    Chain {
+        #[command(subcommand)]
+        command: ChainCommand,
+    },
+
+    /// Merge replicas' journals into one verified ledger without duplicates
+    Merge {
+        /// Directories of signed transactions, or ledgers (database or .ndjson journal)
+        #[arg(required = true, num_args = 2..)]
+        journals: Vec<String>,
+
+        /// New ledger to write the union to (database or .ndjson journal)
+        #[arg(long = "out", value_name = "LEDGER", default_value = "merged.ndjson")]
+        out_path: String,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Print the Merkle root over every transaction in a journal
+    MerkleRoot {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+    },
+
+    /// Write a proof that one transaction is in a journal's Merkle tree
+    ProveInclusion {
+        /// Payload hash (hex) of the transaction to prove
+        tx_hash: String,
+
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(long, value_name = "LEDGER", default_value = ".")]
+        journal: String,
+
+        /// Where to write the proof
+        #[arg(long = "out", value_name = "FILE", default_value = "inclusion_proof.json")]
+        out_path: String,
+    },
+
+    /// Check an inclusion proof against a trusted Merkle root
+    VerifyInclusion {
+        /// Inclusion proof written by prove-inclusion
+        proof: String,
+
+        /// The root to trust (default: the proof's own)
+        #[arg(long, value_name = "HEX", conflicts_with = "checkpoint")]
+        root: Option<String>,
+
+        /// Trust the root of this signed checkpoint instead
+        #[arg(long, value_name = "FILE")]
+        checkpoint: Option<String>,
+
+        /// DID the checkpoint must be signed by
+        #[arg(long, value_name = "DID", requires = "checkpoint")]
+        operator: Option<String>,
+
+        /// Also check that the proof is for this signed transaction
+        #[arg(long = "transaction", value_name = "FILE")]
+        tx_path: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Write a proof that a journal's first transactions are unchanged in a later tree
+    ProveConsistency {
+        /// Size of the earlier tree (the tree_size of its checkpoint)
+        old_size: u64,
+
+        /// Size of the later tree (default: the whole journal)
+        #[arg(long, value_name = "N")]
+        new_size: Option<u64>,
+
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(long, value_name = "LEDGER", default_value = ".")]
+        journal: String,
+
+        /// Where to write the proof
+        #[arg(long = "out", value_name = "FILE", default_value = "consistency_proof.json")]
+        out_path: String,
+    },
+
+    /// Check that a later checkpoint's journal only appended to an earlier one's
+    VerifyConsistency {
+        /// Consistency proof written by prove-consistency
+        proof: String,
+
+        /// The earlier signed checkpoint
+        #[arg(long, value_name = "FILE")]
+        from: String,
+
+        /// The later signed checkpoint
+        #[arg(long, value_name = "FILE")]
+        to: String,
+
+        /// DID both checkpoints must be signed by
+        #[arg(long, value_name = "DID")]
+        operator: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Sign the journal's current Merkle root, size and height as its operator
+    Checkpoint {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// Where to write the signed checkpoint
+        #[arg(long = "out", value_name = "FILE", default_value = "checkpoint.json")]
+        out_path: String,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+
+    /// Write the whole journal and the keystore's identities to one signed (optionally encrypted) file
+    Backup {
+        /// Ledger to back up (database or .ndjson journal)
+        ledger: String,
+
+        /// Where to write the backup
+        #[arg(long = "out", value_name = "FILE", default_value = "backup.json")]
+        out_path: String,
+
+        /// Seal the backup under a passphrase ($TLC_BACKUP_PASSPHRASE, or asked for)
+        #[arg(long)]
+        encrypt: bool,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+
+    /// Restore a backup into a new ledger, verifying every transaction and the chain
+    Restore {
+        /// Backup written by `tlc backup`
+        path: String,
+
+        /// New ledger to restore into (database or .ndjson journal; must not exist)
+        #[arg(long, value_name = "LEDGER")]
+        store: String,
+
+        /// DID the backup must be signed by
+        #[arg(long, value_name = "DID")]
+        operator: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Rebuild a ledger's indices and balances from its journal alone, re-verifying every transaction
+    Rebuild {
+        /// Journal to rebuild from (a .ndjson journal, a database, or a directory of signed transactions)
+        journal: String,
+
+        /// Ledger to replace with the rebuilt one (database or .ndjson journal); may be the journal itself
+        #[arg(long, value_name = "LEDGER")]
+        store: String,
+
+        /// The latest signed checkpoint, whose root the journal must still reproduce
+        #[arg(long, value_name = "FILE")]
+        checkpoint: Option<String>,
+
+        /// DID the checkpoint must be signed by
+        #[arg(long, value_name = "DID", requires = "checkpoint")]
+        operator: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Close the books through a day or fiscal period: verifiers given the close reject anything dated in it
+    ClosePeriod {
+        /// Last day or fiscal period closed (FY2025, FY2025-P03; FY2025-P12 leaves period 13 open)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        through: String,
+
+        /// Fiscal calendar for --through (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        /// DID that may still post adjusting entries into the period (repeatable)
+        #[arg(long = "adjuster", value_name = "DID")]
+        adjusters: Vec<String>,
+
+        /// Where to write the signed period close
+        #[arg(long = "out", value_name = "FILE", default_value = "period_close.json")]
+        out_path: String,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+
+    /// Sign off on a period: sign the digest of its closing trial balance as management's attestation
+    Attest {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        journal: String,
+
+        /// Day or fiscal period signed off (FY2025, FY2025-P03; FY2025-P12 leaves out the year-end adjustments)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        period: String,
+
+        /// Fiscal calendar for --period (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        /// The capacity signed in, e.g. CFO
+        #[arg(long)]
+        role: Option<String>,
+
+        /// Where to write the signed attestation
+        #[arg(long = "out", value_name = "FILE", default_value = "attestation.json")]
+        out_path: String,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Delegate posting rights: sign a capability for another DID, no broader than any you hold
+    Delegate(DelegateArgs),
+
+    /// Anchor signed checkpoints in Bitcoin (OpenTimestamps or OP_RETURN)
+    Anchor {
+        #[command(subcommand)]
+        command: AnchorCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Verify a signed checkpoint, and optionally that a journal still matches it
+    VerifyCheckpoint {
+        /// Signed checkpoint written by `tlc checkpoint`
+        path: String,
+
+        /// Check that this journal's first transactions still have the checkpointed root
+        #[arg(long, value_name = "LEDGER")]
+        journal: Option<String>,
+
+        /// DID the checkpoint must be signed by
+        #[arg(long, value_name = "DID")]
+        operator: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Sign a trial balance, balance sheet or counterparty statement as a verifiable presentation, with inclusion proofs for its transactions
+    Present {
+        #[command(flatten)]
+        args: PresentArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Check a statement presentation: its proofs, and its transactions against a checkpoint or journal
+    VerifyPresentation {
+        #[command(flatten)]
+        args: VerifyPresentationArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Draw an audit sample of a checkpoint's transactions, seeded by the checkpoint, with inclusion proofs
+    Sample {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        journal: String,
+
+        /// Signed checkpoint whose transactions are sampled and whose digest seeds the draw
+        #[arg(long, value_name = "FILE")]
+        checkpoint: String,
+
+        /// DID the checkpoint must be signed by
+        #[arg(long, value_name = "DID")]
+        operator: Option<String>,
+
+        /// Transactions to sample
+        #[arg(long, value_name = "N")]
+        size: usize,
+
+        #[arg(long, value_enum, default_value_t = MethodArg::Random)]
+        method: MethodArg,
+
+        /// Where to write the sample
+        #[arg(long = "out", value_name = "FILE", default_value = "sample.json")]
+        out_path: String,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Verify an audit sample: its checkpoint, transactions and proofs, and that the seed picked them
+    VerifySample {
+        /// Sample written by `tlc sample`
+        path: String,
+
+        /// DID the sample's checkpoint must be signed by
+        #[arg(long, value_name = "DID")]
+        operator: Option<String>,
+
+        /// Also draw the sample again from this journal (needed for a monetary-unit sample)
+        #[arg(long, value_name = "LEDGER")]
+        journal: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Total every verified transaction in a journal, per account
+    TrialBalance {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        dir: String,
+
+        /// Roll each account up into its parents, shown as a tree
+        #[arg(long)]
+        rollup: bool,
+
+        /// Chart of accounts whose `parent` links shape the rollup (.json or .toml)
+        #[arg(long, value_name = "FILE", requires = "rollup")]
+        chart: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Find verified transactions by the words of their memo
+    Search {
+        /// Words that must all appear in the memo (any order, any case)
+        text: String,
+
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Financial statements over every verified transaction in a journal
+    Report {
+        #[command(subcommand)]
+        command: ReportCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Post verified transactions to Xero or QuickBooks Online as journals, once each
+    Connector {
+        #[command(subcommand)]
+        command: ConnectorCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Undo a verified transaction with a signed reversal linked to it
+    Reverse {
+        #[command(flatten)]
+        args: ReverseArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Restate foreign-currency balances at period-end rates and sign the FX gain/loss postings
+    Revalue {
+        #[command(flatten)]
+        args: RevalueArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Prepare transactions as drafts, submit them for review, and post them once a reviewer signs
+    Draft {
+        #[command(subcommand)]
+        command: DraftCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Preview proposed postings (drafts or unsigned files) against the replayed journal, without signing or storing anything
+    Simulate {
+        #[command(flatten)]
+        args: SimulateArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Keep signed transactions in a ledger: an SQLite database or an NDJSON journal
+    Store {
+        #[command(subcommand)]
+        command: StoreCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Show private memos, erase sealed ones whose retention period is over, and redact them
+    Memo {
+        #[command(subcommand)]
+        command: MemoCommand,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Serve the verifier over HTTP (POST /verify, POST /transactions)
+    Serve {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        /// Ledger that POST /transactions appends to (database or .ndjson journal)
+        #[arg(long, value_name = "LEDGER")]
+        store: Option<String>,
+
+        /// Require API keys or JWT bearer tokens with per-route scopes (see auth.rs)
+        #[arg(long, value_name = "FILE")]
+        auth: Option<String>,
+
+        /// Serve several isolated ledgers, each with its own credentials and rules, under /tenants/<name> (see tenants.rs)
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["store", "auth"])]
+        tenants: Option<String>,
+
+        #[command(flatten)]
+        tls: TlsArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Watch a directory: append valid transaction files to a ledger, quarantine the rest
+    Watch {
+        /// Directory that signed transactions (*.json) are dropped into
+        dir: String,
+
+        /// Ledger that valid transactions are appended to (database or .ndjson journal)
+        #[arg(long, value_name = "LEDGER")]
+        store: String,
+
+        /// Where appended files are moved (default: <dir>/accepted)
+        #[arg(long, value_name = "DIR")]
+        accepted: Option<String>,
+
+        /// Where invalid files and their reports are moved (default: <dir>/quarantine)
+        #[arg(long, value_name = "DIR")]
+        quarantine: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Keep this ledger identical to other nodes' over libp2p
+    Sync {
+        /// Ledger to keep in sync (database or .ndjson journal)
+        #[arg(long, value_name = "LEDGER")]
+        store: String,
+
+        /// Address to accept other nodes on
+        #[arg(long, value_name = "MULTIADDR", default_value = "/ip4/0.0.0.0/tcp/4001")]
+        listen: String,
+
+        /// Node to connect to, e.g. /ip4/10.0.0.2/tcp/4001 (repeatable)
+        #[arg(long = "peer", value_name = "MULTIADDR")]
+        peers: Vec<String>,
+
+        /// File holding this node's libp2p identity, created if missing (default: a new one each run)
+        #[arg(long, value_name = "FILE", conflicts_with = "identity")]
+        node_key: Option<String>,
+
+        /// Keystore identity (Ed25519) to use as this node's identity, so peers can admit it by DID
+        #[arg(long, value_name = "NAME")]
+        identity: Option<String>,
+
+        #[command(flatten)]
+        keystore: KeystoreArgs,
+
+        /// Admit only nodes whose identity is this DID (repeatable)
+        #[arg(long = "peer-did", value_name = "DID")]
+        peer_dids: Vec<String>,
+
+        /// Seconds between asking peers what they hold
+        #[arg(long, value_name = "SECS", default_value_t = 30)]
+        interval: u64,
+
+        /// Serve GET /status (peers, and how far behind or ahead of each this node is) on this address
+        #[arg(long, value_name = "ADDR")]
+        status: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Run one member of a Raft cluster that agrees on a single append order
+    Raft(RaftArgs),
+
+    /// Serve the ledger over gRPC (schema: proto/true_ledger.proto)
+    ServeGrpc {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:50051")]
+        listen: String,
+
+        /// Ledger that SubmitTransaction appends to (database or .ndjson journal)
+        #[arg(long, value_name = "LEDGER")]
+        store: Option<String>,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Sign the sample genesis transaction (owner's capital contribution)
+    Genesis {
+        /// Where to write the signed transaction
+        #[arg(long = "out", value_name = "FILE", default_value = "genesis_transaction.json")]
+        out_path: String,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+
+    /// Rewrite signed transaction files in the current format, keeping their signatures
+    Migrate {
+        /// Signed transaction files, rewritten in place
+        #[arg(required = true)]
+        paths: Vec<String>,
+
+        /// Also lift older payloads to the current version and re-sign them
+        /// (as their author); this changes their hashes
+        #[arg(long)]
+        resign: bool,
+
+        /// Report what would change without writing anything
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Print the JSON Schema that signed transaction files are checked against
+    Schema {
+        /// Write the schema to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// Write the known-good/known-bad test-vector suite
+    GenVectors {
+        /// Output directory
+        #[arg(default_value = "test_vectors")]
+        out_dir: String,
+    },
+
+    /// Write a synthetic, hash-chained ledger, or sign the occurrences of a recurring-transaction template
+    Generate {
+        #[command(flatten)]
+        config: GeneratorConfig,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+
+    /// Load-test a running server, reporting throughput and latency percentiles
+    Bench {
+        #[command(subcommand)]
+        command: BenchCommand,
+    },
+
+    /// Sign opening balances from a prior system's trial balance
+    ImportTb(ImportArgs),
+
+    /// Inspect the exact bytes that get signed
+    Debug {
+        #[command(subcommand)]
+        command: DebugCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum ChainCommand {
+    /// Verify every transaction in a journal and every link between them
+    Verify {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        dir: String,
+
+        #[command(flatten)]
+        resolver: ResolverArgs,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DebugCommand {
+    /// Print the canonical payload bytes, optionally diffing another implementation's
+    Canonical {
+        /// Signed transaction whose payload to show
+        path: String,
+
+        /// Another implementation's signing input to compare against
+        other: Option<String>,
+    },
+}
+
+/// A failed command: what to print (if anything) and the exit status
+pub struct Failure {
+    pub message: Option<String>,
+    pub exit_code: i32,
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure { message: Some(message), exit_code: 1 }
+    }
+}
+
+fn run(command: Command) -> Result<(), Failure> {
+    let result = match command {
+        Command::Keygen { name, alg, mnemonic, did_document, keystore } => {
+            signing::keygen(&name, alg, mnemonic, did_document.as_deref(), &keystore)
+        }
+        Command::Resolve { did, out_path, resolver } => signing::resolve(&did, out_path.as_deref(), &resolver.resolver()),
+        Command::Recover { name, keystore } => signing::recover(&name, &keystore),
+        Command::Keystore { command, keystore } => signing::run_keystore(&command, &keystore),
+        #[cfg(feature = "keychain")]
+        Command::Keychain { command } => keychain::run_keychain(&command),
+        #[cfg(feature = "didcomm")]
+        Command::Didcomm { command, resolver } => didcomm::run_didcomm(&command, &resolver.resolver()),
+        Command::Key { command, resolver } => key_events::run_key(&command, &resolver.resolver()),
+        Command::Policy { command, resolver } => policy::run_policy(&command, &resolver.resolver()),
+        Command::Sign { args, resolver } => signing::sign_file(&args, &resolver.resolver()),
+        Command::Cosign { path, out_path, signer } => signing::cosign_file(&path, out_path.as_deref(), &signer),
+        Command::Verify(args) => return verify::run_verify(&args),
+        Command::VerifyBatch { paths, jobs, quiet, resolver } => {
+            batch::run_verify_batch(&paths, jobs, quiet, &resolver.resolver())
+        }
+        Command::Chain { command: ChainCommand::Verify { dir, resolver } } => {
+            chain::verify_chain_dir(&dir, &resolver.resolver())
+        }
+        Command::Merge { journals, out_path, resolver } => merge::run_merge(&journals, &out_path, &resolver.resolver()),
+        Command::MerkleRoot { journal } => merkle::run_merkle_root(&journal),
+        Command::ProveInclusion { tx_hash, journal, out_path } => merkle::run_prove_inclusion(&tx_hash, &journal, &out_path),
+        Command::VerifyInclusion { proof, root, checkpoint, operator, tx_path, resolver } => {
+            let resolver = resolver.resolver();
+            let root = match (&root, &checkpoint) {
+                (Some(root), _) => TrustedRoot::Hex(root),
+                (None, Some(path)) => TrustedRoot::Checkpoint { path, operator: operator.as_deref(), resolver: &resolver },
+                (None, None) => TrustedRoot::FromProof,
+            };
+            merkle::run_verify_inclusion(&proof, root, tx_path.as_deref())
+        }
+        Command::ProveConsistency { old_size, new_size, journal, out_path } => {
+            merkle::run_prove_consistency(&journal, old_size, new_size, &out_path)
+        }
+        Command::VerifyConsistency { proof, from, to, operator, resolver } => {
+            merkle::run_verify_consistency(&proof, &from, &to, operator.as_deref(), &resolver.resolver())
+        }
+        Command::Checkpoint { journal, out_path, signer } => checkpoint::run_checkpoint(&journal, &out_path, &signer),
+        Command::Backup { ledger, out_path, encrypt, signer } => backup::run_backup(&ledger, &out_path, encrypt, &signer),
+        Command::Restore { path, store, operator, resolver } => backup::run_restore(&path, &store, operator.as_deref(), &resolver.resolver()),
+        Command::Rebuild { journal, store, checkpoint, operator, resolver } => {
+            rebuild::run_rebuild(&journal, &store, checkpoint.as_deref(), operator.as_deref(), &resolver.resolver())
+        }
+        Command::ClosePeriod { through, fiscal, adjusters, out_path, signer } => {
+            period_close::run_close_period(&through, fiscal.as_deref(), &adjusters, &out_path, &signer)
+        }
+        Command::Attest { journal, period, fiscal, role, out_path, signer, resolver } => {
+            attestation::run_attest(&journal, &period, fiscal.as_deref(), role.as_deref(), &out_path, &signer, &resolver.resolver())
+        }
+        Command::Delegate(args) => delegate::run_delegate(&args),
+        Command::Anchor { command, resolver } => anchor::run_anchor(&command, &resolver.resolver()),
+        Command::VerifyCheckpoint { path, journal, operator, resolver } => {
+            checkpoint::run_verify_checkpoint(&path, journal.as_deref(), operator.as_deref(), &resolver.resolver())
+        }
+        Command::Present { args, resolver } => presentation::run_present(&args, &resolver.resolver()),
+        Command::VerifyPresentation { args, resolver } => presentation::run_verify_presentation(&args, &resolver.resolver()),
+        Command::Sample { journal, checkpoint, operator, size, method, out_path, resolver } => {
+            sample::run_sample(&journal, &checkpoint, operator.as_deref(), size, method, &out_path, &resolver.resolver())
+        }
+        Command::VerifySample { path, operator, journal, resolver } => {
+            sample::run_verify_sample(&path, operator.as_deref(), journal.as_deref(), &resolver.resolver())
+        }
+        Command::TrialBalance { dir, rollup, chart, resolver } => {
+            trial_balance::run_trial_balance(&dir, rollup, chart.as_deref(), &resolver.resolver())
+        }
+        Command::Revalue { args, resolver } => revalue::run_revalue(&args, &resolver.resolver()),
+        Command::Reverse { args, resolver } => reverse::run_reverse(&args, &resolver.resolver()),
+        Command::Search { text, journal, resolver } => store::run_search(&text, &journal, &resolver.resolver()),
+        Command::Report { command, resolver } => report::run_report(&command, &resolver.resolver()),
+        Command::Connector { command, resolver } => connector::run_connector(&command, &resolver.resolver()),
+        Command::Draft { command, resolver } => draft::run_draft(&command, &resolver.resolver()),
+        Command::Simulate { args, resolver } => simulate::run_simulate(&args, &resolver.resolver()),
+        Command::Store { command, resolver } => store::run_store(&command, &resolver.resolver()),
+        Command::Memo { command, resolver } => memo::run_memo(&command, &resolver.resolver()),
+        Command::Serve { listen, store, auth, tenants, tls, resolver } => {
+            let ledgers = match &tenants {
+                Some(path) => serve::Ledgers::Tenants(path),
+                None => serve::Ledgers::Single { store: store.as_deref(), auth: auth.as_deref() },
+            };
+            serve::serve(&listen, ledgers, &tls, &resolver.resolver())
+        }
+        Command::Watch { dir, store, accepted, quarantine, resolver } => {
+            watch::run_watch(&dir, &store, accepted.as_deref(), quarantine.as_deref(), &resolver.resolver())
+        }
+        Command::Sync { store, listen, peers, node_key, identity, keystore, peer_dids, interval, status, resolver } => {
+            let identity = identity.map(|name| signing::unlock_identity(&keystore.open(), &name)).transpose()?;
+            let options = sync::SyncOptions {
+                listen: &listen, peers: &peers, node_key: node_key.as_deref(), identity: identity.as_ref(), peer_dids: &peer_dids,
+                interval, status: status.as_deref(),
+            };
+            sync::run_sync(&store, &options, &resolver.resolver())
+        }
+        Command::Raft(args) => raft::run_raft(&args),
+        Command::ServeGrpc { listen, store, resolver } => grpc::serve_grpc(&listen, store.as_deref(), resolver.resolver()),
+        Command::Genesis { out_path, signer } => signing::genesis(&out_path, &signer),
+        Command::Migrate { paths, resign, dry_run, signer, resolver } => {
+            migrate::run_migrate(&paths, resign, dry_run, &signer, &resolver.resolver())
+        }
+        Command::Schema { out_path } => verify::print_schema(out_path.as_deref()),
+        Command::GenVectors { out_dir } => vectors::generate_test_vectors(&out_dir),
+        Command::Generate { config, resolver } => match &config.recurring.template {
+            Some(template) => recurring::run_template(template, &config, &resolver.resolver()),
+            None => generate::generate_synthetic_ledger(&config),
+        },
+        Command::Bench { command } => bench::run_bench(&command),
+        Command::ImportTb(args) => import::run_import(&args),
+        Command::Debug { command: DebugCommand::Canonical { path, other } } => {
+            let signed_tx = verify::load_signed(&path)?;
+            debug::debug_canonical(&signed_tx.payload, other.as_deref());
+            Ok(())
+        }
+    };
+    result.map_err(Failure::from)
+}
+
+fn main() {
+    let cli = Cli::parse();
+    if let Err(failure) = run(cli.command) {
+        if let Some(message) = failure.message {
+            eprintln!("❌ Error: {}", message);
+        }
+        std::process::exit(failure.exit_code);
+    }
+}
@@ -0,0 +1,200 @@
+//! Private Memos
+//! `tlc sign --private-memo TEXT` signs only a salted commitment to TEXT
+//! (see the core private_memo.rs) and carries TEXT beside the payload; with
+//! `--record-keys FILE` it is sealed under a key of its own, kept for
+//! `--retain-years` from the transaction's date.
+//! `tlc memo show LEDGER HASH` prints a transaction's private memo, opening
+//! it with the record key file if it is sealed.
+//! `tlc memo erase-expired --record-keys FILE` is the retention policy:
+//! it deletes every key whose record need no longer be kept, leaving those
+//! memos unreadable in the ledger and in every copy of it, while their
+//! transactions still verify.
+//! `tlc memo redact LEDGER HASH --reason TEXT` replaces one private memo in
+//! the ledger with a redaction the signer signs, saying when and why; only
+//! the commitment is left, and the transaction still verifies.
+
+use chrono::{Months, NaiveDate};
+use clap::{Args, Subcommand};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::private_memo::{MemoOpening, PrivateMemo, RecordKeys, SignedRedaction};
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::{Timestamp, Transaction};
+
+use crate::signing::{signer_from_args, SignerArgs};
+use crate::store::open_existing;
+
+/// The private memo options of `tlc sign`
+#[derive(Args, Debug)]
+pub struct PrivateMemoArgs {
+    /// Private part of the memo: only its salted commitment is signed, so it can be erased later
+    #[arg(long, value_name = "TEXT")]
+    pub private_memo: Option<String>,
+
+    /// Seal the private memo under a new key kept in this record key file (created if missing)
+    #[arg(long, value_name = "FILE", requires = "private_memo")]
+    pub record_keys: Option<String>,
+
+    /// Years to keep a sealed private memo, from the transaction's date; its key is erased after that
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub retain_years: u32,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MemoCommand {
+    /// Print a stored transaction's private memo
+    Show {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        /// Hex payload hash (as in prev_hash)
+        hash: String,
+
+        /// Record key file, to open a sealed memo
+        #[arg(long, value_name = "FILE")]
+        record_keys: Option<String>,
+    },
+
+    /// Delete the keys of sealed memos whose retention period is over
+    EraseExpired {
+        /// Record key file
+        #[arg(long, value_name = "FILE")]
+        record_keys: String,
+
+        /// Day to apply the policy as of (YYYY-MM-DD; default: today, UTC)
+        #[arg(long, value_name = "DATE")]
+        as_of: Option<NaiveDate>,
+    },
+
+    /// Redact a stored transaction's private memo, leaving only its signed commitment
+    Redact {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        /// Hex payload hash (as in prev_hash)
+        hash: String,
+
+        /// Why it is redacted, e.g. the erasure request it honours; signed with the redaction
+        #[arg(long)]
+        reason: String,
+
+        /// Also delete a sealed memo's key from this record key file
+        #[arg(long, value_name = "FILE")]
+        record_keys: Option<String>,
+
+        #[command(flatten)]
+        signer: SignerArgs,
+    },
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn today() -> NaiveDate {
+    Timestamp::from(now()).utc().date_naive()
+}
+
+/// Commits `tx` to the private memo, if one was given, and returns what the
+/// envelope carries: the opening, or with --record-keys the opening sealed
+/// under a new key that is saved before anything is signed
+pub fn private_memo_for(tx: &mut Transaction, args: &PrivateMemoArgs) -> Result<Option<PrivateMemo>, String> {
+    let Some(text) = &args.private_memo else {
+        return Ok(None);
+    };
+    let opening = MemoOpening::commit(tx, text)?;
+    let Some(path) = &args.record_keys else {
+        return Ok(Some(PrivateMemo::Open(opening)));
+    };
+    let retain_until = tx.timestamp.utc().date_naive()
+        .checked_add_months(Months::new(args.retain_years.saturating_mul(12)))
+        .ok_or_else(|| format!("{} years from the transaction's date is out of range", args.retain_years))?;
+    let mut keys = RecordKeys::load(Path::new(path))?;
+    let sealed = keys.seal(&opening, retain_until)?;
+    keys.save(Path::new(path))?;
+    println!("🔒 Private memo sealed under key {} in {}, kept until {}", sealed.key_id, path, retain_until);
+    Ok(Some(PrivateMemo::Sealed(sealed)))
+}
+
+/// `tlc memo show|erase-expired|redact`
+pub fn run_memo(command: &MemoCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        MemoCommand::Show { db, hash, record_keys } => {
+            let signed_tx = open_existing(db)?.get_by_hash(hash)?
+                .ok_or_else(|| format!("No transaction {} in {}", hash, db))?;
+            println!("Memo: {}", signed_tx.payload.memo);
+            let Some(commitment) = &signed_tx.payload.memo_commitment else {
+                println!("No private memo");
+                return Ok(());
+            };
+            let opening = match &signed_tx.private_memo {
+                None => None,
+                Some(PrivateMemo::Open(opening)) => {
+                    opening.check(commitment)?;
+                    Some(opening.clone())
+                }
+                Some(PrivateMemo::Sealed(sealed)) => {
+                    let path = record_keys.as_deref()
+                        .ok_or_else(|| format!("The private memo is sealed under key {}; give --record-keys", sealed.key_id))?;
+                    RecordKeys::load(Path::new(path))?.open(sealed, commitment)?
+                }
+                Some(PrivateMemo::Redacted(signed)) => {
+                    signed.verify(&signed_tx, resolver)?;
+                    let redaction = &signed.redaction;
+                    println!("Private memo: redacted by {} at {}: {}", redaction.redacted_by, redaction.timestamp, redaction.reason);
+                    return Ok(());
+                }
+            };
+            match opening {
+                Some(opening) => println!("Private memo: {}", opening.text),
+                None => println!("Private memo: erased (only its commitment {} remains)", commitment),
+            }
+            Ok(())
+        }
+        MemoCommand::EraseExpired { record_keys, as_of } => {
+            let path = Path::new(record_keys);
+            if !path.is_file() {
+                return Err(format!("No record key file at {}", record_keys));
+            }
+            let today = as_of.unwrap_or_else(today);
+            let mut keys = RecordKeys::load(path)?;
+            let erased = keys.erase_expired(today);
+            keys.save(path)?;
+            for key_id in &erased {
+                println!("🗑️  Erased key {}", key_id);
+            }
+            println!("\n✅ {} key(s) past retention on {} erased; {} kept", erased.len(), today, keys.keys.len());
+            Ok(())
+        }
+        MemoCommand::Redact { db, hash, reason, record_keys, signer } => {
+            let mut ledger = open_existing(db)?;
+            let signed_tx = ledger.get_by_hash(hash)?.ok_or_else(|| format!("No transaction {} in {}", hash, db))?;
+            if let Some(PrivateMemo::Redacted(signed)) = &signed_tx.private_memo {
+                return Err(format!("The private memo of {} was already redacted by {}", hash, signed.redaction.redacted_by));
+            }
+            let signer = signer_from_args(signer)?;
+            let redaction = SignedRedaction::sign(&signed_tx, reason, signer.as_ref(), now().into())?;
+            let sealed_key = match &signed_tx.private_memo {
+                Some(PrivateMemo::Sealed(sealed)) => Some(sealed.key_id.clone()),
+                _ => None,
+            };
+
+            let mut redacted = signed_tx;
+            redacted.private_memo = Some(PrivateMemo::Redacted(redaction));
+            verify_with(&redacted, resolver)?; // Still verifies, with the redaction in place
+            ledger.replace_private_memo(hash, redacted.private_memo)?;
+            println!("🧹 Private memo of {} redacted by {}: {}", hash, signer.did(), reason);
+
+            if let (Some(key_id), Some(path)) = (sealed_key, record_keys) {
+                let mut keys = RecordKeys::load(Path::new(path))?;
+                if keys.keys.remove(&key_id).is_some() {
+                    keys.save(Path::new(path))?;
+                    println!("🗑️  Erased key {} from {}", key_id, path);
+                }
+            }
+            println!("\n✅ Only the signed commitment {} remains", redacted.payload.memo_commitment.unwrap_or_default());
+            Ok(())
+        }
+    }
+}
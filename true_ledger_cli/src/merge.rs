@@ -0,0 +1,71 @@
+//! Merging Journals
+//! `tlc merge a.ndjson b.ndjson --out merged.ndjson` joins the ledgers of
+//! replicas that appended while offline (see the core merge.rs). Every
+//! transaction is verified first; the union is written to a new ledger, in
+//! the same order whichever way round the inputs are given. Journals whose
+//! union is not one hash chain (forked, or with a sequence conflict) are
+//! refused, since the result would not verify.
+
+use std::path::Path;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::merge::merge;
+use true_ledger_core::storage;
+use true_ledger_core::verify::verify_with;
+
+use crate::store::journal_entries;
+
+/// `tlc merge <journals>... [--out LEDGER]`
+pub fn run_merge(journals: &[String], out_path: &str, resolver: &dyn DidResolver) -> Result<(), String> {
+    if Path::new(out_path).exists() {
+        return Err(format!("{} already exists; merge into a new ledger", out_path));
+    }
+
+    println!("\n🔀 Merging {} journals...", journals.len());
+    let mut inputs = Vec::new();
+    let mut failures = Vec::new();
+    for journal in journals {
+        let mut transactions = Vec::new();
+        for entry in journal_entries(journal)? {
+            match entry {
+                Ok((label, signed_tx)) => match verify_with(&signed_tx, resolver) {
+                    Ok(()) => transactions.push(signed_tx),
+                    Err(e) => failures.push(format!("{} ({}): {}", journal, label, e)),
+                },
+                Err(e) => failures.push(format!("{}: {}", journal, e)),
+            }
+        }
+        println!("   > {}: {} transaction(s)", journal, transactions.len());
+        inputs.push(transactions);
+    }
+    if !failures.is_empty() {
+        for failure in &failures {
+            println!("❌ {}", failure);
+        }
+        return Err(format!("{} transaction(s) failed verification; nothing was merged", failures.len()));
+    }
+
+    let merged = merge(inputs)?;
+    if !merged.conflicts.is_empty() || !merged.forks.is_empty() {
+        for conflict in &merged.conflicts {
+            println!("❌ {} signed {} different transactions with sequence {}: {}",
+                conflict.author, conflict.hashes.len(), conflict.sequence, conflict.hashes.join(", "));
+        }
+        for fork in &merged.forks {
+            let after = fork.prev_hash.as_deref().map_or("the ledger's start (more than one genesis)".to_string(), |prev| prev.to_string());
+            println!("❌ The chain forks after {}: {}", after, fork.hashes.join(", "));
+        }
+        return Err(format!("{} sequence conflict(s) and {} fork(s); nothing was merged", merged.conflicts.len(), merged.forks.len()));
+    }
+    // Whatever else would stop the union verifying as a chain (a missing
+    // predecessor, say) is found before anything is written
+    merged.check_chain().map_err(|e| format!("The union is not one chain, so nothing was merged: {}", e))?;
+
+    let mut ledger = storage::open(out_path)?;
+    for signed_tx in &merged.transactions {
+        ledger.append(signed_tx)
+            .map_err(|e| format!("Could not append {} to {}: {}", signed_tx.payload.hash_hex(), out_path, e))?;
+    }
+    println!("✅ {} transaction(s) in the union ({} held by more than one journal)", merged.transactions.len(), merged.duplicates);
+    println!("💾 Merged ledger saved to {}", out_path);
+    Ok(())
+}
@@ -0,0 +1,115 @@
+//! Merkle Proofs
+//! `tlc merkle-root`, `tlc prove-inclusion` and `tlc verify-inclusion`: a
+//! ledger operator publishes the root of the journal's Merkle tree and hands
+//! out a short proof per transaction, so a third party can check that one
+//! transaction belongs to the ledger without downloading the rest.
+//! `tlc prove-consistency` and `tlc verify-consistency` show an auditor that
+//! a later checkpoint's journal extends an earlier one's without rewriting it.
+
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::merkle::{parse_hash, ConsistencyProof, InclusionProof, MerkleTree};
+
+use crate::checkpoint::load_checkpoint;
+use crate::signing::write_json;
+use crate::store::journal_entries;
+use crate::verify::load_envelope;
+
+/// Builds the tree over every transaction in a journal, in chain order, and
+/// returns it with the height of the last transaction. Unreadable entries
+/// are an error: the root must cover the whole journal.
+pub fn journal_tree(journal: &str) -> Result<(MerkleTree, Option<u64>), String> {
+    let mut tree = MerkleTree::new();
+    let mut height = None;
+    for entry in journal_entries(journal)? {
+        let (_, signed_tx) = entry?;
+        tree.push(signed_tx.payload.get_hash());
+        height = signed_tx.payload.height;
+    }
+    Ok((tree, height))
+}
+
+/// `tlc merkle-root <journal>`
+pub fn run_merkle_root(journal: &str) -> Result<(), String> {
+    let (tree, _) = journal_tree(journal)?;
+    println!("🌳 Merkle root of {} ({} transactions):", journal, tree.size());
+    println!("{}", hex::encode(tree.root()));
+    Ok(())
+}
+
+/// `tlc prove-inclusion <tx-hash> --journal <journal>`
+pub fn run_prove_inclusion(tx_hash: &str, journal: &str, out_path: &str) -> Result<(), String> {
+    let proof = journal_tree(journal)?.0.prove_inclusion(tx_hash)?;
+    write_json(&proof, out_path)?;
+    println!("🌳 Transaction {} is leaf {} of {}", proof.tx_hash, proof.leaf_index, proof.tree_size);
+    println!("   Root: {}", proof.root);
+    println!("💾 Inclusion proof ({} hashes) saved to {}", proof.audit_path.len(), out_path);
+    Ok(())
+}
+
+/// Where `tlc verify-inclusion` gets the root it trusts
+pub enum TrustedRoot<'a> {
+    Hex(&'a str),
+    Checkpoint { path: &'a str, operator: Option<&'a str>, resolver: &'a dyn DidResolver },
+    FromProof,
+}
+
+/// `tlc verify-inclusion <proof> [--root HEX | --checkpoint FILE] [--transaction FILE]`
+pub fn run_verify_inclusion(proof_path: &str, root: TrustedRoot, tx_path: Option<&str>) -> Result<(), String> {
+    let data = std::fs::read_to_string(proof_path).map_err(|e| format!("Could not read {}: {}", proof_path, e))?;
+    let proof: InclusionProof = serde_json::from_str(&data).map_err(|e| format!("Invalid inclusion proof {}: {}", proof_path, e))?;
+
+    if let Some(tx_path) = tx_path {
+        let tx_hash = load_envelope(tx_path, None, false)?.payload().hash_hex();
+        if !tx_hash.eq_ignore_ascii_case(&proof.tx_hash) {
+            return Err(format!("The proof is for transaction {}, but {} is {}", proof.tx_hash, tx_path, tx_hash));
+        }
+    }
+    let trusted_root = match root {
+        TrustedRoot::Hex(root) => parse_hash(root)?,
+        TrustedRoot::Checkpoint { path, operator, resolver } => {
+            let checkpoint = load_checkpoint(path, operator, resolver)?.checkpoint;
+            if checkpoint.tree_size != proof.tree_size {
+                return Err(format!("The proof is against a tree of {} transactions but the checkpoint covers {}",
+                    proof.tree_size, checkpoint.tree_size));
+            }
+            checkpoint.root_hash()?
+        }
+        TrustedRoot::FromProof => {
+            println!("⚠️  No --root or --checkpoint given: checking against the root named in the proof itself");
+            parse_hash(&proof.root)?
+        }
+    };
+    proof.verify(&trusted_root)?;
+    println!("🎉 **INCLUDED**: transaction {} is leaf {} of the {}-transaction tree with root {}",
+        proof.tx_hash, proof.leaf_index, proof.tree_size, hex::encode(trusted_root));
+    Ok(())
+}
+
+/// `tlc prove-consistency <old-size> [--new-size N] --journal <journal>`
+pub fn run_prove_consistency(journal: &str, old_size: u64, new_size: Option<u64>, out_path: &str) -> Result<(), String> {
+    let (tree, _) = journal_tree(journal)?;
+    let new_size = new_size.map_or(tree.size(), |size| size as usize);
+    let proof = tree.prove_consistency(old_size as usize, new_size)?;
+    write_json(&proof, out_path)?;
+    println!("🌳 {} transactions (root {})", proof.old_size, proof.old_root);
+    println!("   extend to {} (root {})", proof.new_size, proof.new_root);
+    println!("💾 Consistency proof ({} hashes) saved to {}", proof.path.len(), out_path);
+    Ok(())
+}
+
+/// `tlc verify-consistency <proof> --from <checkpoint> --to <checkpoint>`:
+/// checks both checkpoints, then that the proof links their roots
+pub fn run_verify_consistency(proof_path: &str, from: &str, to: &str, operator: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let data = std::fs::read_to_string(proof_path).map_err(|e| format!("Could not read {}: {}", proof_path, e))?;
+    let proof: ConsistencyProof = serde_json::from_str(&data).map_err(|e| format!("Invalid consistency proof {}: {}", proof_path, e))?;
+    let old = load_checkpoint(from, operator, resolver)?.checkpoint;
+    let new = load_checkpoint(to, operator, resolver)?.checkpoint;
+    if (old.tree_size, new.tree_size) != (proof.old_size, proof.new_size) {
+        return Err(format!("The proof is from {} to {} transactions but the checkpoints cover {} and {}",
+            proof.old_size, proof.new_size, old.tree_size, new.tree_size));
+    }
+    proof.verify(&old.root_hash()?, &new.root_hash()?)?;
+    println!("\n🎉 **CONSISTENT**: the journal at {} only appended {} transaction(s) to the journal at {}",
+        new.timestamp, new.tree_size - old.tree_size, old.timestamp);
+    Ok(())
+}
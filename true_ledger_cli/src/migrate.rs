@@ -0,0 +1,114 @@
+//! File Migration
+//! `tlc migrate <files>...` rewrites signed transactions in the current
+//! format. The envelope is re-wrapped around the original signature: hex
+//! signatures become multibase, and a missing `digest` or `sig_alg` is
+//! filled in. The payload is left at its version, since lifting it changes
+//! the signed bytes; with `--resign` its author lifts and re-signs it.
+
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::hashing::Hasher;
+use true_ledger_core::verify::{decode_signature, verify_signature_with};
+use true_ledger_core::versioning::{upgrade, version_of, CURRENT_VERSION};
+use true_ledger_core::SignedTransaction;
+
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+use crate::verify::load_signed;
+
+/// Re-encodes a legacy hex signature as multibase; None if it already is
+fn multibase_signature(encoded: &str) -> Result<Option<String>, String> {
+    // The test decode_signature applies: 64 bytes of bare hex
+    if encoded.len() != 128 || !encoded.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    Ok(Some(multibase::encode(multibase::Base::Base58Btc, decode_signature(encoded)?)))
+}
+
+/// Rewrites the envelope around the payload and signatures it already has
+fn rewrap(signed_tx: &mut SignedTransaction, resolver: &dyn DidResolver) -> Result<Vec<String>, String> {
+    let mut changes = Vec::new();
+    if let Some(signature) = multibase_signature(&signed_tx.signature)? {
+        signed_tx.signature = signature;
+        changes.push("hex signature re-encoded as multibase".to_string());
+    }
+    for cosignature in &mut signed_tx.cosignatures {
+        if let Some(signature) = multibase_signature(&cosignature.signature)? {
+            cosignature.signature = signature;
+            changes.push(format!("hex cosignature by {} re-encoded as multibase", cosignature.signer_did));
+        }
+    }
+    if signed_tx.digest.is_none() {
+        let payload = &signed_tx.payload;
+        let multihash = payload.hash_alg().multihash(&payload.canonical_bytes());
+        signed_tx.digest = Some(multibase::encode(multibase::Base::Base58Btc, multihash));
+        changes.push("digest added".to_string());
+    }
+    if signed_tx.sig_alg.is_none() {
+        let sig_alg = resolver.resolve_public_key(&signed_tx.payload.author_did)?.sig_alg();
+        signed_tx.sig_alg = Some(sig_alg);
+        changes.push(format!("sig_alg {:?} recorded", sig_alg));
+    }
+    Ok(changes)
+}
+
+/// `tlc migrate <files>... [--resign] [--dry-run]`: rewrites each file in
+/// place, after checking its signature (and again before writing)
+pub fn run_migrate(paths: &[String], resign: bool, dry_run: bool, signer_args: &SignerArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let signer = if resign { Some(signer_from_args(signer_args)?) } else { None };
+    let mut rewritten = 0;
+
+    for path in paths {
+        let mut signed_tx = load_signed(path)?;
+        verify_signature_with(&signed_tx, resolver)
+            .map_err(|e| format!("Refusing to migrate {}: its signature does not verify: {}", path, e))?;
+
+        let version = version_of(&signed_tx.payload);
+        let mut changes = Vec::new();
+        if version < CURRENT_VERSION {
+            match &signer {
+                Some(signer) => {
+                    if signer.did() != signed_tx.payload.author_did {
+                        return Err(format!("Only the author ({}) can re-sign {}", signed_tx.payload.author_did, path));
+                    }
+                    let old_hash = signed_tx.payload.hash_hex();
+                    let (payload, steps) = upgrade(signed_tx.payload.clone());
+                    for step in steps {
+                        changes.push(format!("payload lifted to version {}: {}", step.version, step.summary));
+                    }
+                    if !signed_tx.cosignatures.is_empty() {
+                        println!("⚠️  {}: {} cosignature(s) over the old payload are dropped; collect them again with `tlc cosign`",
+                            path, signed_tx.cosignatures.len());
+                    }
+                    signed_tx = signer.sign_transaction(payload)?;
+                    changes.push(format!("re-signed by {}", signer.did()));
+                    if signed_tx.payload.height.is_some() {
+                        println!("⚠️  {}: its hash changes from {} to {}; transactions chained after it still name the old one",
+                            path, old_hash, signed_tx.payload.hash_hex());
+                    }
+                }
+                None => println!("   Payload stays at version {} (current: {}); --resign lifts it, which changes its hash",
+                    version, CURRENT_VERSION),
+            }
+        }
+        changes.extend(rewrap(&mut signed_tx, resolver)?);
+
+        if changes.is_empty() {
+            println!("✅ {} needs no changes", path);
+            continue;
+        }
+        for change in &changes {
+            println!("   • {}", change);
+        }
+        verify_signature_with(&signed_tx, resolver)
+            .map_err(|e| format!("Migrated {} no longer verifies, left unchanged: {}", path, e))?;
+        if dry_run {
+            println!("🔎 {} would be rewritten (dry run)", path);
+        } else {
+            write_json(&signed_tx, path)?;
+            println!("💾 {} rewritten", path);
+        }
+        rewritten += 1;
+    }
+
+    println!("\n📦 {} of {} file(s) {}", rewritten, paths.len(), if dry_run { "need migrating" } else { "migrated" });
+    Ok(())
+}
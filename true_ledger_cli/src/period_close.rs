@@ -0,0 +1,36 @@
+//! Period Close
+//! `tlc close-period --through YYYY-MM-DD|PERIOD [--fiscal FILE]` signs a
+//! period close; `tlc verify --period-close FILE` then rejects transactions
+//! dated in the closed period that are not authorised adjusting entries.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use true_ledger_core::fiscal::FiscalCalendar;
+use true_ledger_core::period_close::PeriodClose;
+
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+
+/// `tlc close-period --through DATE|PERIOD [--fiscal FILE] [--adjuster DID]...`
+pub fn run_close_period(through: &str, fiscal: Option<&str>, adjusters: &[String], out_path: &str, signer_args: &SignerArgs) -> Result<(), String> {
+    let calendar = fiscal.map(FiscalCalendar::load).transpose()?.unwrap_or_default();
+    let through = calendar.cutoff(through)?;
+    let signer = signer_from_args(signer_args)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let signed = PeriodClose::new(signer.did(), through, adjusters.to_vec(), now.into()).sign(signer.as_ref())?;
+
+    write_json(&signed, out_path)?;
+    println!("\n🔒 Books closed through {} by {}", signed.close.closed_through, signed.close.closed_by);
+    if signed.close.adjusting_period_open {
+        println!("   > Period 13 stays open: adjusting entries dated {} are still accepted from anyone", signed.close.closed_through);
+    }
+    if signed.close.adjusters.is_empty() && signed.close.adjusting_period_open {
+        println!("   > No adjusters: no earlier adjusting entries can be posted");
+    } else if signed.close.adjusters.is_empty() {
+        println!("   > No adjusters: nothing more can be posted into the period");
+    } else {
+        println!("   > Adjusting entries allowed from: {}", signed.close.adjusters.join(", "));
+    }
+    println!("💾 Signed period close saved to {}", out_path);
+    Ok(())
+}
+
@@ -0,0 +1,160 @@
+//! Posting Policy Documents
+//! `tlc policy adopt RULES --store LEDGER` signs a rules file into the
+//! ledger as the next version of its posting policy (see the core
+//! policy.rs); `tlc policy history LEDGER` lists the versions adopted, and
+//! `tlc policy show LEDGER` prints one version's rules, e.g. to pass to
+//! `tlc verify --rules`.
+
+use clap::Subcommand;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::policy::{Policy, PolicyHistory};
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::{Timestamp, Transaction};
+
+use crate::key_events::KeyEventArgs;
+use crate::signing::{sign_and_record, signer_from_args};
+use crate::store::journal_entries;
+
+#[derive(Subcommand, Debug)]
+pub enum PolicyCommand {
+    /// Sign a rules file into the ledger as the next version of its posting policy
+    Adopt {
+        /// The rules file (self-contained: no chart, fiscal or script files)
+        rules: String,
+
+        /// DIDs or the rules' group names that may adopt the version after this one
+        #[arg(long = "admin", value_name = "DID", required = true)]
+        administrators: Vec<String>,
+
+        /// When the version takes effect (Unix seconds or RFC 3339; default: now)
+        #[arg(long, value_name = "TIMESTAMP")]
+        effective_from: Option<Timestamp>,
+
+        #[command(flatten)]
+        output: KeyEventArgs,
+    },
+
+    /// List the policy versions a ledger adopted
+    History {
+        /// Ledger (database or .ndjson journal) or directory of signed transactions
+        ledger: String,
+    },
+
+    /// Print one version's rules (default: the latest)
+    Show {
+        /// Ledger (database or .ndjson journal) or directory of signed transactions
+        ledger: String,
+
+        /// The version to show
+        #[arg(long)]
+        version: Option<u64>,
+
+        /// Write the rules to this file instead
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+}
+
+pub fn run_policy(command: &PolicyCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        PolicyCommand::Adopt { rules, administrators, effective_from, output } => {
+            adopt(rules, administrators, effective_from.clone(), output, resolver)
+        }
+        PolicyCommand::History { ledger } => history(ledger, resolver),
+        PolicyCommand::Show { ledger, version, out_path } => show(ledger, *version, out_path.as_deref(), resolver),
+    }
+}
+
+fn adopt(rules: &str, administrators: &[String], effective_from: Option<Timestamp>, output: &KeyEventArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let text = fs::read_to_string(rules).map_err(|e| format!("Could not read {}: {}", rules, e))?;
+    let store = output.store.as_deref();
+    let mut history = match store.filter(|store| Path::new(store).exists()) {
+        Some(store) => load_policy_history(store, resolver)?,
+        None => PolicyHistory::new(),
+    };
+    let signer = signer_from_args(&output.signer)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let policy = Policy {
+        version: history.latest().map_or(1, |latest| latest.policy.version + 1),
+        effective_from: effective_from.unwrap_or_else(|| now.into()),
+        administrators: administrators.to_vec(),
+        rules: text,
+    };
+    let tx = Transaction {
+        timestamp: now.into(),
+        author_did: signer.did().to_string(),
+        entries: Vec::new(),
+        memo: format!("Posting policy version {}, effective {}", policy.version, policy.effective_from),
+        prev_hash: None,
+        height: None,
+        sequence: None,
+        canonicalization: None,
+        signing_policy: None,
+        key_event: None,
+        hash_alg: None,
+        version: None,
+        adjusting: None,
+        reverses: None,
+        prepared_by: None,
+        memo_commitment: None,
+        policy: Some(policy),
+    };
+
+    // Refuse a version the ledger's history would not accept
+    history.apply(&tx)?;
+
+    println!("\n📜 Signing {}...", tx.memo);
+    sign_and_record(tx, signer.as_ref(), store, output.out_path.as_deref().or(store.xor(Some("policy.json"))), resolver)?;
+    Ok(())
+}
+
+fn history(ledger: &str, resolver: &dyn DidResolver) -> Result<(), String> {
+    let history = load_policy_history(ledger, resolver)?;
+    if history.versions().is_empty() {
+        println!("No posting policy adopted in {}", ledger);
+        return Ok(());
+    }
+    println!("\n📜 Posting policy of {}:", ledger);
+    for adopted in history.versions() {
+        println!("   v{:<3} effective {}  adopted {} by {}  ({})", adopted.policy.version, adopted.policy.effective_from,
+            adopted.adopted_at, adopted.adopted_by, &adopted.tx_hash[..16]);
+    }
+    Ok(())
+}
+
+fn show(ledger: &str, version: Option<u64>, out_path: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let history = load_policy_history(ledger, resolver)?;
+    let adopted = match version {
+        Some(version) => history.versions().iter().find(|adopted| adopted.policy.version == version)
+            .ok_or_else(|| format!("{} adopted no policy version {}", ledger, version))?,
+        None => history.latest().ok_or_else(|| format!("No posting policy adopted in {}", ledger))?,
+    };
+    match out_path {
+        Some(out_path) => {
+            fs::write(out_path, &adopted.policy.rules).map_err(|e| format!("Could not write {}: {}", out_path, e))?;
+            println!("💾 Policy version {} saved to {}", adopted.policy.version, out_path);
+        }
+        None => print!("{}", adopted.policy.rules),
+    }
+    Ok(())
+}
+
+/// Replays the policy transactions of a journal, in order. Only verified
+/// policies count; those that fail, or that their author was not entitled
+/// to adopt, are skipped.
+pub fn load_policy_history(path: &str, resolver: &dyn DidResolver) -> Result<PolicyHistory, String> {
+    let mut history = PolicyHistory::new();
+    for entry in journal_entries(path)? {
+        let (name, signed_tx) = entry?;
+        if signed_tx.payload.policy.is_none() {
+            continue;
+        }
+        if let Err(e) = verify_with(&signed_tx, resolver).and_then(|_| history.apply(&signed_tx.payload)) {
+            eprintln!("⚠️  Ignored policy {}: {}", name, e);
+        }
+    }
+    Ok(history)
+}
@@ -0,0 +1,176 @@
+//! Statement Presentations
+//! `tlc present <dir|ledger> --statement trial-balance|balance-sheet|counterparty`
+//! signs a statement as a W3C Verifiable Presentation, with an inclusion proof
+//! for every transaction it was built from (see the core presentation.rs);
+//! `tlc verify-presentation` checks one, against a signed checkpoint's root
+//! and the journal itself when given.
+
+use chrono::NaiveDate;
+use clap::{Args, ValueEnum};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::chart::ChartOfAccounts;
+use true_ledger_core::counterparty::CounterpartyStatement;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::fiscal::{Cutoff, FiscalCalendar};
+use true_ledger_core::presentation::{StatementPresentation, StatementSubject};
+use true_ledger_core::statements::BalanceSheet;
+use true_ledger_core::trial_balance::TrialBalance;
+use true_ledger_core::Transaction;
+
+use crate::checkpoint::load_checkpoint;
+use crate::merkle::journal_tree;
+use crate::report::verified_payloads;
+use crate::signing::{signer_from_args, write_json, SignerArgs};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatementArg {
+    TrialBalance,
+    BalanceSheet,
+    Counterparty,
+}
+
+/// Options for `tlc present`
+#[derive(Args, Debug)]
+pub struct PresentArgs {
+    /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+    pub journal: String,
+
+    /// The statement to present
+    #[arg(long, value_enum)]
+    pub statement: StatementArg,
+
+    /// First day or fiscal period a counterparty statement lists; earlier movements are brought forward
+    #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+    pub from: Option<String>,
+
+    /// Last day or fiscal period the statement covers (default: everything)
+    #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+    pub as_of: Option<String>,
+
+    /// Fiscal calendar for --as-of (default: years start on 1 January)
+    #[arg(long, value_name = "FILE")]
+    pub fiscal: Option<String>,
+
+    /// Chart of accounts (required for a balance sheet)
+    #[arg(long, value_name = "FILE", required_if_eq("statement", "balance-sheet"))]
+    pub chart: Option<String>,
+
+    /// Name or DID the counterparty statement is for (see `tlc report counterparty`)
+    #[arg(long, required_if_eq("statement", "counterparty"))]
+    pub counterparty: Option<String>,
+
+    /// The counterparty's control account (repeatable; required for a counterparty statement)
+    #[arg(long = "account", value_name = "PATTERN", required_if_eq("statement", "counterparty"))]
+    pub accounts: Vec<String>,
+
+    /// The verifier's challenge (nonce), binding the presentation to their request
+    #[arg(long)]
+    pub challenge: Option<String>,
+
+    /// Where to write the presentation
+    #[arg(long = "out", value_name = "FILE", default_value = "presentation.json")]
+    pub out_path: String,
+
+    #[command(flatten)]
+    pub signer: SignerArgs,
+}
+
+/// Options for `tlc verify-presentation`
+#[derive(Args, Debug)]
+pub struct VerifyPresentationArgs {
+    /// Presentation written by `tlc present`
+    pub path: String,
+
+    /// The challenge the presentation must answer
+    #[arg(long)]
+    pub challenge: Option<String>,
+
+    /// Signed checkpoint whose root the inclusion proofs must lead to
+    #[arg(long, value_name = "FILE")]
+    pub checkpoint: Option<String>,
+
+    /// DID the checkpoint must be signed by
+    #[arg(long, value_name = "DID", requires = "checkpoint")]
+    pub operator: Option<String>,
+
+    /// Rebuild the trial balance from this journal's copies of the transactions
+    #[arg(long, value_name = "LEDGER")]
+    pub journal: Option<String>,
+}
+
+/// `tlc present JOURNAL --statement S [--as-of DATE|PERIOD] [--chart FILE] [--challenge NONCE]`
+pub fn run_present(args: &PresentArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let calendar = args.fiscal.as_deref().map(FiscalCalendar::load).transpose()?.unwrap_or_default();
+    let as_of = args.as_of.as_deref().map(|as_of| calendar.cutoff(as_of)).transpose()?.unwrap_or(Cutoff::day(NaiveDate::MAX));
+    let payloads = verified_payloads(&args.journal, resolver)?;
+    let underlying: Vec<&Transaction> = payloads.iter().filter(|tx| as_of.includes(tx)).collect();
+    let (tree, _) = journal_tree(&args.journal)?;
+    let subject = match args.statement {
+        StatementArg::TrialBalance => {
+            let books = TrialBalance::from_transactions(underlying.iter().copied())?;
+            books.check()?;
+            StatementSubject::new("TrialBalance", &books, underlying, &tree)?
+        }
+        StatementArg::BalanceSheet => {
+            let chart = ChartOfAccounts::load(args.chart.as_deref().unwrap_or_default())?;
+            let sheet = BalanceSheet::from_transactions(&payloads, &chart, as_of)?;
+            sheet.check()?;
+            StatementSubject::new("BalanceSheet", &sheet, underlying, &tree)?
+        }
+        StatementArg::Counterparty => {
+            let from = args.from.as_deref().map(|from| calendar.start(from)).transpose()?;
+            let counterparty = args.counterparty.as_deref().unwrap_or_default();
+            let statement = CounterpartyStatement::from_transactions(&payloads, counterparty, &args.accounts, from, as_of)?;
+            // Proofs for the movements brought forward too, so the opening balance is backed as well
+            let history = CounterpartyStatement::from_transactions(&payloads, counterparty, &args.accounts, None, as_of)?;
+            let theirs = history.tx_hashes();
+            let theirs = underlying.into_iter().filter(|tx| theirs.contains(&tx.hash_hex().as_str()));
+            StatementSubject::new("CounterpartyStatement", &statement, theirs, &tree)?
+        }
+    };
+
+    let signer = signer_from_args(&args.signer)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let presentation = StatementPresentation::sign(subject, signer.as_ref(), now, args.challenge.as_deref())?;
+    write_json(&presentation, &args.out_path)?;
+    let subject = &presentation.subject;
+    println!("\n📑 {} presented by {}", subject.statement, presentation.preparer());
+    println!("   > {} underlying transaction(s), proven against root {} ({} transactions)", subject.transactions.len(), subject.root, subject.tree_size);
+    println!("   > Trial balance digest: {}", subject.trial_balance);
+    println!("💾 Verifiable presentation saved to {}", args.out_path);
+    Ok(())
+}
+
+/// `tlc verify-presentation FILE [--challenge NONCE] [--checkpoint FILE [--operator DID]] [--journal LEDGER]`
+pub fn run_verify_presentation(args: &VerifyPresentationArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let data = fs::read_to_string(&args.path).map_err(|e| format!("Could not read {}: {}", args.path, e))?;
+    let document = serde_json::from_str(&data).map_err(|e| format!("Invalid presentation {}: {}", args.path, e))?;
+    let presentation = StatementPresentation::from_value(document)?;
+    if args.challenge.is_none() {
+        println!("⚠️  No --challenge given: the presentation could be a replay of one made for someone else");
+    }
+    presentation.verify(args.challenge.as_deref(), resolver)?;
+    let subject = &presentation.subject;
+    println!("✅ {} signed by its preparer {}", subject.statement, presentation.preparer());
+
+    match &args.checkpoint {
+        Some(checkpoint) => {
+            let checkpoint = load_checkpoint(checkpoint, args.operator.as_deref(), resolver)?.checkpoint;
+            if checkpoint.tree_size != subject.tree_size {
+                return Err(format!("The presentation is proven against a tree of {} transactions but the checkpoint covers {}",
+                    subject.tree_size, checkpoint.tree_size));
+            }
+            subject.check_inclusion(Some(&checkpoint.root_hash()?))?;
+            println!("✅ All {} underlying transaction(s) are in the checkpointed journal", subject.transactions.len());
+        }
+        None => println!("⚠️  No --checkpoint given: the {} underlying transaction(s) are only proven against the root the preparer stated",
+            subject.transactions.len()),
+    }
+    if let Some(journal) = &args.journal {
+        subject.check_books(&verified_payloads(journal, resolver)?)?;
+        println!("✅ The transactions in {} give the trial balance stated", journal);
+    }
+    println!("\n🎉 **STATEMENT VERIFIED**");
+    Ok(())
+}
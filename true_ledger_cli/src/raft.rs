@@ -0,0 +1,580 @@
+//! Raft Cluster Mode
+//! `tlc raft --id 1 --identity node1 --node 1=did:key:z6Mk...@10.0.0.1:7001
+//! --node 2=did:key:z6Mk...@10.0.0.2:7001 --node 3=did:key:z6Mk...@10.0.0.3:7001
+//! --store LEDGER --raft-dir DIR` runs one member of a cluster that agrees
+//! on a single append order (see the core raft.rs).
+//! Only committed entries reach the ledger, so every member's ledger holds
+//! the same transactions in the same order; nothing else should write to it.
+//! The leader verifies a submission, and checks that it follows the last
+//! transaction in the log, before proposing it. A member that cannot append
+//! a committed entry stops rather than let its ledger diverge.
+//!
+//! Each member serves an HTTP API on `--listen`:
+//!
+//!   POST /transactions        leader: verify, replicate, and reply once a
+//!                             majority holds it and it is appended (201);
+//!                             others: 421 naming the leader
+//!   GET  /transactions/<hash> a stored transaction by payload hash
+//!   GET  /status              role, term, leader and log progress
+//!
+//! It is served like `tlc serve` (see serve.rs): with `--auth FILE` every
+//! request needs an API key or token with the route's scope (submit for
+//! POST /transactions, read otherwise), and with `--api-tls-cert` it is
+//! HTTPS, with `--api-client-ca` mutual TLS, under which a client submits
+//! only transactions it is the author of.
+//!
+//! Members talk to each other over TCP, one JSON message per line. Each
+//! member signs what it sends with its keystore identity (`--identity`),
+//! whose DID every member lists for it in `--node`; messages that are not
+//! signed by the member they claim to come from are refused. With
+//! `--tls-cert FILE --tls-key FILE --tls-ca FILE` the connections are mutual
+//! TLS, each end's certificate naming its member's DID (see tls.rs), so the
+//! traffic is encrypted too and only members can connect at all.
+
+use clap::Args;
+use serde_json::json;
+use rustls::pki_types::ServerName;
+use rustls::{ClientConfig, ClientConnection, ServerConfig, ServerConnection, StreamOwned};
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc as std_mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::chain::{check_sequence, verify_link};
+use true_ledger_core::keys::VerifyingKey;
+use true_ledger_core::raft::{Envelope, NodeId, RaftLog, RaftNode, Role, SealedEnvelope};
+use true_ledger_core::storage::{self, Storage};
+use true_ledger_core::verify::verdict_with;
+use true_ledger_core::{Account, LedgerError, SignedTransaction};
+
+use crate::auth::{Auth, Scope};
+use crate::serve::{check_author, error, parse_signed, serve_connections, to_value, Incoming, Peer, Reply};
+use crate::signing::{unlock_identity, KeystoreArgs};
+use crate::tls::{cert_did, ApiTlsArgs, MemberTls, MemberTlsArgs, TlsServer};
+use crate::verify::ResolverArgs;
+
+/// How often the Raft clock ticks (an election timeout is 10-20 ticks)
+const TICK: Duration = Duration::from_millis(100);
+
+/// How long a submission waits to be committed
+const COMMIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to wait when connecting to another member
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// How long a member may take to send a message, or to complete a TLS handshake
+const IO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Options for `tlc raft`
+#[derive(Args, Debug)]
+pub struct RaftArgs {
+    /// This member's ID
+    #[arg(long, value_name = "ID")]
+    pub id: NodeId,
+
+    /// A cluster member, its DID and its Raft address, ID=DID@HOST:PORT; list every member, this one included
+    #[arg(long = "node", value_name = "ID=DID@HOST:PORT", required = true)]
+    pub nodes: Vec<String>,
+
+    /// Keystore identity this member signs its messages with; its DID must be the one --node lists for --id
+    #[arg(long, value_name = "NAME")]
+    pub identity: String,
+
+    #[command(flatten)]
+    pub keystore: KeystoreArgs,
+
+    #[command(flatten)]
+    pub tls: MemberTlsArgs,
+
+    /// Ledger that committed transactions are appended to (database or .ndjson journal)
+    #[arg(long, value_name = "LEDGER")]
+    pub store: String,
+
+    /// Directory for this member's Raft log and vote
+    #[arg(long, value_name = "DIR")]
+    pub raft_dir: String,
+
+    /// Address to serve the HTTP API on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub listen: String,
+
+    /// Require API keys or JWT bearer tokens with per-route scopes on the HTTP API (see auth.rs)
+    #[arg(long, value_name = "FILE")]
+    pub auth: Option<String>,
+
+    #[command(flatten)]
+    pub api_tls: ApiTlsArgs,
+
+    #[command(flatten)]
+    pub resolver: ResolverArgs,
+}
+
+/// What the HTTP thread asks of the node
+enum Request {
+    Submit(Box<SignedTransaction>, std_mpsc::Sender<Reply>),
+    Get(String, std_mpsc::Sender<Reply>),
+    Status(std_mpsc::Sender<Reply>),
+}
+
+/// One --node entry
+struct MemberInfo {
+    did: String,
+    address: SocketAddr,
+}
+
+fn parse_members(nodes: &[String]) -> Result<BTreeMap<NodeId, MemberInfo>, String> {
+    let mut members = BTreeMap::new();
+    for node in nodes {
+        let expected = || format!("--node {}: expected ID=DID@HOST:PORT", node);
+        let (id, rest) = node.split_once('=').ok_or_else(expected)?;
+        let (did, address) = rest.rsplit_once('@').ok_or_else(expected)?;
+        let id: NodeId = id.trim().parse().map_err(|_| format!("--node {}: the ID must be a number", node))?;
+        if !did.trim().starts_with("did:") {
+            return Err(format!("--node {}: '{}' is not a DID", node, did));
+        }
+        let address: SocketAddr = address.trim().parse().map_err(|e| format!("--node {}: {}", node, e))?;
+        if members.insert(id, MemberInfo { did: did.trim().to_string(), address }).is_some() {
+            return Err(format!("Node {} is listed twice", id));
+        }
+    }
+    Ok(members)
+}
+
+/// Serves the HTTP API on its own thread, passing requests to the node;
+/// with `auth`, each request needs a credential with the route's scope
+fn serve_api(listen: &str, tls: Option<TlsServer>, auth: Option<Auth>, requests: mpsc::Sender<Request>) -> Result<(), String> {
+    let listener = TcpListener::bind(listen).map_err(|e| format!("Could not listen on {}: {}", listen, e))?;
+    thread::spawn(move || {
+        let served = serve_connections(listener, tls, |incoming, peer| {
+            let url = incoming.url.split('?').next().unwrap_or("");
+            let mut principal = None;
+            if let Some(auth) = &auth {
+                let scope = if incoming.method == "POST" && url == "/transactions" { Scope::Submit } else { Scope::Read };
+                match auth.authorize(incoming.authorization.as_deref(), scope) {
+                    Ok(authorized) => principal = Some(authorized.name),
+                    Err(refusal) => return (error(refusal.status, refusal.message), None),
+                }
+            }
+            (ask(incoming, url, peer, &requests), principal)
+        });
+        if let Err(e) = served {
+            eprintln!("⚠️  {}", e);
+        }
+    });
+    Ok(())
+}
+
+/// Passes one API request to the node and waits for its reply
+fn ask(incoming: &Incoming, url: &str, peer: &Peer, requests: &mpsc::Sender<Request>) -> Reply {
+    let (sender, reply) = std_mpsc::channel();
+    let asked = match (incoming.method.as_str(), url) {
+        ("POST", "/transactions") => match incoming.body.clone().and_then(|body| parse_signed(&body)) {
+            Ok(signed_tx) => match check_author(peer, &signed_tx) {
+                Ok(()) => Request::Submit(Box::new(signed_tx), sender),
+                Err(reply) => return reply,
+            },
+            Err(reply) => return reply,
+        },
+        ("GET", path) if path.starts_with("/transactions/") => Request::Get(path["/transactions/".len()..].to_string(), sender),
+        ("GET", "/status") => Request::Status(sender),
+        (_, "/transactions") | (_, "/status") => return error(405, "Method not allowed"),
+        _ => return error(404, format!("No route for {}", url)),
+    };
+    match requests.blocking_send(asked) {
+        Ok(()) => reply.recv_timeout(COMMIT_TIMEOUT)
+            .unwrap_or_else(|_| error(504, "Not committed in time; it may still be, so check before resubmitting")),
+        Err(_) => error(503, "The node has stopped"),
+    }
+}
+
+/// A connection between members: TCP, or mutual TLS over TCP
+trait Link: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Link for T {}
+
+fn connect(address: SocketAddr, tls: Option<&Arc<ClientConfig>>) -> io::Result<Box<dyn Link>> {
+    let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)?;
+    tcp.set_write_timeout(Some(IO_TIMEOUT))?;
+    let Some(config) = tls else {
+        return Ok(Box::new(tcp));
+    };
+    let connection = ClientConnection::new(Arc::clone(config), ServerName::IpAddress(address.ip().into())).map_err(io::Error::other)?;
+    let mut stream = StreamOwned::new(connection, tcp);
+    // Handshake now, so a member with the wrong certificate is reported
+    stream.sock.set_read_timeout(Some(IO_TIMEOUT))?;
+    while stream.conn.is_handshaking() {
+        stream.conn.complete_io(&mut stream.sock)?;
+    }
+    Ok(Box::new(stream))
+}
+
+/// Sends one member's messages, reconnecting as needed; messages that
+/// cannot be delivered are dropped (Raft resends what matters)
+fn send_to(id: NodeId, address: SocketAddr, tls: Option<Arc<ClientConfig>>, mut outgoing: mpsc::UnboundedReceiver<SealedEnvelope>) {
+    let mut stream: Option<Box<dyn Link>> = None;
+    let mut last_failure = String::new();
+    while let Some(envelope) = outgoing.blocking_recv() {
+        if stream.is_none() {
+            match connect(address, tls.as_ref()) {
+                Ok(connection) => stream = Some(connection),
+                Err(e) => {
+                    // Reported once, not on every heartbeat
+                    let failure = e.to_string();
+                    if e.kind() == io::ErrorKind::InvalidData && failure != last_failure {
+                        eprintln!("🚫 Could not connect to member {} at {}: {}", id, address, failure);
+                    }
+                    last_failure = failure;
+                }
+            }
+        }
+        let Some(connection) = stream.as_mut() else {
+            // Unreachable: drop what queued up meanwhile rather than retry it
+            while outgoing.try_recv().is_ok() {}
+            continue;
+        };
+        let mut line = serde_json::to_vec(&envelope).expect("a Raft message serializes");
+        line.push(b'\n');
+        if connection.write_all(&line).and_then(|_| connection.flush()).is_err() {
+            stream = None;
+        }
+    }
+}
+
+/// Completes the TLS handshake on a connection from another member and
+/// returns the member its certificate names
+fn accept_tls(tcp: TcpStream, config: &Arc<ServerConfig>, dids: &BTreeMap<String, NodeId>) -> Result<(Box<dyn Link>, NodeId), String> {
+    let connection = ServerConnection::new(Arc::clone(config)).map_err(|e| e.to_string())?;
+    let mut stream = StreamOwned::new(connection, tcp);
+    stream.sock.set_read_timeout(Some(IO_TIMEOUT)).map_err(|e| e.to_string())?;
+    while stream.conn.is_handshaking() {
+        stream.conn.complete_io(&mut stream.sock).map_err(|e| e.to_string())?;
+    }
+    stream.sock.set_read_timeout(None).map_err(|e| e.to_string())?;
+    let cert = stream.conn.peer_certificates().and_then(|certs| certs.first()).ok_or("no client certificate")?;
+    let did = cert_did(cert)?.ok_or("the certificate names no DID")?;
+    let id = *dids.get(&did).ok_or_else(|| format!("{} is not a member", did))?;
+    Ok((Box::new(stream), id))
+}
+
+/// Accepts connections from other members and passes their messages on,
+/// once their signatures check out (and, over TLS, once they are seen to
+/// come from the member whose certificate was presented); a connection
+/// that sends anything else is closed
+fn receive(listener: TcpListener, keys: Arc<BTreeMap<NodeId, VerifyingKey>>, own_id: NodeId, tls: Option<(Arc<ServerConfig>, BTreeMap<String, NodeId>)>, incoming: mpsc::UnboundedSender<Envelope>) {
+    let tls = Arc::new(tls);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let from = stream.peer_addr().map(|address| address.to_string()).unwrap_or_default();
+        let (incoming, keys, tls) = (incoming.clone(), keys.clone(), tls.clone());
+        thread::spawn(move || {
+            let (link, sender): (Box<dyn Link>, Option<NodeId>) = match tls.as_ref() {
+                Some((config, dids)) => match accept_tls(stream, config, dids) {
+                    Ok((link, id)) => (link, Some(id)),
+                    Err(e) => {
+                        eprintln!("🚫 Refused a Raft connection from {}: {}", from, e);
+                        return;
+                    }
+                },
+                None => (Box::new(stream), None),
+            };
+            for line in BufReader::new(link).lines() {
+                let Ok(line) = line else { break };
+                let opened = serde_json::from_str::<SealedEnvelope>(&line)
+                    .map_err(|e| LedgerError::Serialization(e.to_string()))
+                    .and_then(|sealed| sealed.open(&keys, own_id))
+                    .and_then(|envelope| match sender {
+                        Some(id) if id != envelope.from => Err(LedgerError::Signature(format!(
+                            "a message from member {} arrived on member {}'s connection", envelope.from, id))),
+                        _ => Ok(envelope),
+                    });
+                match opened {
+                    Ok(envelope) => {
+                        if incoming.send(envelope).is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("🚫 Refused a Raft message from {} and closed the connection: {}", from, e);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// The member's state besides the protocol
+struct Member<'a> {
+    raft: RaftNode,
+    ledger: Box<dyn Storage>,
+    resolver: &'a dyn DidResolver,
+    account: Account, // Signs what this member sends
+    peers: BTreeMap<NodeId, mpsc::UnboundedSender<SealedEnvelope>>,
+    members: BTreeMap<NodeId, MemberInfo>,
+    waiting: BTreeMap<u64, (String, serde_json::Value, std_mpsc::Sender<Reply>)>, // Log index -> submitted hash, its verdict, and who to tell
+    seen: (Role, Option<NodeId>, u64),                                            // Role, leader and term last reported
+}
+
+impl Member<'_> {
+    fn submit(&mut self, signed_tx: SignedTransaction) -> Result<(String, serde_json::Value, u64), Reply> {
+        if self.raft.role() != Role::Leader {
+            let leader = self.raft.leader();
+            return Err((421, json!({
+                "error": "This node is not the leader",
+                "leader": leader,
+                "leader_raft_address": leader.and_then(|id| self.members.get(&id)).map(|member| member.address.to_string()),
+            })));
+        }
+        // Verified here, once: members append what is committed without
+        // judging it again, so they cannot disagree about it
+        let verdict = verdict_with(&signed_tx, self.resolver);
+        if !verdict.valid {
+            return Err((422, to_value(&verdict)));
+        }
+        let pending: Vec<&SignedTransaction> = self.raft.unapplied().collect();
+        if pending.iter().any(|queued| queued.payload.hash_hex() == verdict.hash) {
+            return Err(error(409, format!("Transaction {} is already being replicated", verdict.hash)));
+        }
+        match self.ledger.get_by_hash(&verdict.hash) {
+            Ok(Some(_)) => return Err(error(409, format!("Transaction {} is already stored", verdict.hash))),
+            Ok(None) => {}
+            Err(e) => return Err(error(500, e)),
+        }
+        // It must follow the last transaction in the log, so that every
+        // member can append it once it is committed
+        let tail = match pending.last() {
+            Some(queued) => Some(queued.payload.clone()),
+            None => self.ledger.head().map_err(|e| error(500, e))?.map(|head| head.payload),
+        };
+        let author = &signed_tx.payload.author_did;
+        let next_sequence = match pending.iter().rev().filter(|queued| queued.payload.author_did == *author).find_map(|queued| queued.payload.sequence) {
+            Some(sequence) => sequence + 1,
+            None => self.ledger.next_sequence(author).map_err(|e| error(500, e))?,
+        };
+        verify_link(tail.as_ref(), &signed_tx.payload)
+            .and_then(|_| check_sequence(&signed_tx.payload, next_sequence))
+            .map_err(|e| error(409, e))?;
+        let index = self.raft.propose(signed_tx).map_err(|e| error(503, e))?;
+        Ok((verdict.hash.clone(), to_value(&verdict), index))
+    }
+
+    fn handle(&mut self, request: Request) {
+        match request {
+            Request::Submit(signed_tx, reply) => match self.submit(*signed_tx) {
+                Ok((hash, verdict, index)) => {
+                    self.waiting.insert(index, (hash, verdict, reply));
+                }
+                Err(refusal) => {
+                    let _ = reply.send(refusal);
+                }
+            },
+            Request::Get(hash, reply) => {
+                let _ = reply.send(match self.ledger.get_by_hash(&hash) {
+                    Ok(Some(signed_tx)) => (200, to_value(&signed_tx)),
+                    Ok(None) => error(404, format!("No transaction {}", hash)),
+                    Err(e) => error(500, e),
+                });
+            }
+            Request::Status(reply) => {
+                let _ = reply.send((200, json!({
+                    "id": self.raft.id(),
+                    "role": format!("{:?}", self.raft.role()).to_lowercase(),
+                    "term": self.raft.term(),
+                    "leader": self.raft.leader(),
+                    "last_index": self.raft.last_index(),
+                    "commit_index": self.raft.commit_index(),
+                    "applied": self.raft.applied(),
+                    "members": self.members.iter()
+                        .map(|(id, member)| (id.to_string(), json!({ "did": member.did, "address": member.address.to_string() })))
+                        .collect::<BTreeMap<_, _>>(),
+                })));
+            }
+        }
+    }
+
+    /// Appends one committed transaction to the ledger. Every member must
+    /// append every committed transaction, or the ledgers diverge, so a
+    /// member that cannot stops instead.
+    fn apply(&mut self, index: u64, signed_tx: &SignedTransaction) -> Result<(), String> {
+        let hash = signed_tx.payload.hash_hex();
+        if self.ledger.get_by_hash(&hash)?.is_some() {
+            return Ok(()); // Appended just before a restart, before it was marked applied
+        }
+        self.ledger.append(signed_tx)
+            .map_err(|e| format!("Committed entry {} ({}) cannot be appended to this member's ledger; stopping rather than diverge: {}", index, hash, e))?;
+        println!("📥 Committed {}", hash);
+        Ok(())
+    }
+
+    /// Sends the protocol's messages, applies what it has committed, and
+    /// reports changes of leader
+    fn settle(&mut self) -> Result<(), String> {
+        for envelope in self.raft.take_messages() {
+            if let Some(peer) = self.peers.get(&envelope.to) {
+                let _ = peer.send(SealedEnvelope::seal(&envelope, &self.account)?);
+            }
+        }
+        for (index, entry) in self.raft.committed() {
+            if let Some(signed_tx) = &entry.transaction {
+                self.apply(index, signed_tx)?;
+            }
+            self.raft.mark_applied(index)?;
+            if let Some((hash, verdict, reply_to)) = self.waiting.remove(&index) {
+                let committed = entry.transaction.as_ref().is_some_and(|signed_tx| signed_tx.payload.hash_hex() == hash);
+                let _ = reply_to.send(if committed {
+                    (201, verdict)
+                } else {
+                    error(503, "Leadership changed before the transaction was committed; resubmit it")
+                });
+            }
+        }
+
+        let now = (self.raft.role(), self.raft.leader(), self.raft.term());
+        if now != self.seen {
+            match now {
+                (Role::Leader, _, term) => println!("👑 This node is the leader (term {})", term),
+                (_, Some(leader), term) => println!("🧭 Following node {} (term {})", leader, term),
+                (Role::Candidate, None, term) => println!("🗳️  Standing for election (term {})", term),
+                (Role::Follower, None, _) => {}
+            }
+            if now.0 != Role::Leader {
+                // Entries proposed by this node may yet be committed by the
+                // next leader, but nobody can promise that
+                for (_, (_, _, reply_to)) in std::mem::take(&mut self.waiting) {
+                    let _ = reply_to.send(error(503, "Leadership changed before the transaction was committed; check before resubmitting"));
+                }
+            }
+            self.seen = now;
+        }
+        Ok(())
+    }
+}
+
+/// `tlc raft`: runs until killed
+pub fn run_raft(args: &RaftArgs) -> Result<(), String> {
+    let resolver = args.resolver.resolver();
+    let members = parse_members(&args.nodes)?;
+    let own = members.get(&args.id).ok_or_else(|| format!("--id {} is not among the --node entries", args.id))?;
+    let own_address = own.address;
+    let account = unlock_identity(&args.keystore.open(), &args.identity)?;
+    if account.did != own.did {
+        return Err(format!("Identity '{}' is {}, but --node lists {} for member {}", args.identity, account.did, own.did, args.id));
+    }
+    // Every member's key, resolved once so a message is checked without a lookup
+    let mut keys = BTreeMap::new();
+    for (id, member) in &members {
+        let key = resolver.resolve_public_key(&member.did).map_err(|e| format!("Member {}: {}", id, e))?;
+        keys.insert(*id, key);
+    }
+    let ids: Vec<NodeId> = members.keys().copied().collect();
+    let raft = RaftNode::new(args.id, &ids, RaftLog::open(&args.raft_dir)?)?;
+    let tls = MemberTls::from_args(&args.tls, &account.did)?;
+    let api_tls = TlsServer::from_args(&args.api_tls.tls_args())?;
+    let auth = args.auth.as_deref().map(Auth::load).transpose()?;
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| format!("Could not start the async runtime: {}", e))?;
+    runtime.block_on(async {
+        let listener = TcpListener::bind(own_address).map_err(|e| format!("Could not listen on {}: {}", own_address, e))?;
+        let (incoming_sender, mut incoming) = mpsc::unbounded_channel();
+        let accepting = tls.as_ref().map(|tls| (tls.server.clone(), members.iter().map(|(id, member)| (member.did.clone(), *id)).collect()));
+        let (keys, own_id) = (Arc::new(keys), args.id);
+        thread::spawn(move || receive(listener, keys, own_id, accepting, incoming_sender));
+        let mut peers = BTreeMap::new();
+        for (id, member) in members.iter().filter(|(id, _)| **id != args.id) {
+            let (sender, outgoing) = mpsc::unbounded_channel();
+            let client = tls.as_ref().map(|tls| tls.client(&member.did)).transpose()?;
+            let (id, address) = (*id, member.address);
+            thread::spawn(move || send_to(id, address, client, outgoing));
+            peers.insert(id, sender);
+        }
+        let (request_sender, mut requests) = mpsc::channel(64);
+        serve_api(&args.listen, api_tls, auth, request_sender)?;
+
+        let mut member = Member {
+            raft,
+            ledger: storage::open(&args.store)?,
+            resolver: &resolver,
+            account,
+            peers,
+            members,
+            waiting: BTreeMap::new(),
+            seen: (Role::Follower, None, 0),
+        };
+        println!("🗳️  Raft member {} of {} (Raft on {}, ledger: {})", args.id, member.members.len(), own_address, args.store);
+        println!("   Signing as {}", member.account.did);
+        if tls.is_some() {
+            println!("   Members connect over mutual TLS, by certificates naming their DIDs");
+        }
+        println!("   Log: {} entries, {} applied (in {})", member.raft.last_index(), member.raft.applied(), args.raft_dir);
+        println!("   API: http://{} (POST /transactions, GET /transactions/<hash>, GET /status)", args.listen);
+        member.settle()?;
+
+        let mut ticker = tokio::time::interval(TICK);
+        loop {
+            let stepped = tokio::select! {
+                _ = ticker.tick() => member.raft.tick(),
+                Some(envelope) = incoming.recv() => member.raft.step(envelope),
+                Some(request) = requests.recv() => {
+                    member.handle(request);
+                    Ok(())
+                }
+            };
+            stepped.map_err(|e| format!("Raft: {}", e))?;
+            member.settle()?;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::fs;
+
+    /// Serves the API with one read-only API key, `reader-key`, in front of
+    /// a node that answers only status requests; returns its address
+    fn api() -> String {
+        let path = std::env::temp_dir().join(format!("tlc-raft-auth-{}.toml", std::process::id()));
+        let digest = hex::encode(Sha256::digest(b"reader-key"));
+        fs::write(&path, format!("[[api_key]]\nname = \"reader\"\nsha256 = \"{}\"\nscopes = [\"read\"]\n", digest)).unwrap();
+        let auth = Auth::load(&path.to_string_lossy()).unwrap();
+
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let (sender, mut requests) = mpsc::channel(4);
+        serve_api(&address, None, Some(auth), sender).unwrap();
+        thread::spawn(move || {
+            while let Some(request) = requests.blocking_recv() {
+                if let Request::Status(reply) = request {
+                    let _ = reply.send((200, json!({ "role": "leader" })));
+                }
+            }
+        });
+        address
+    }
+
+    fn get(address: &str, request: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn the_api_needs_a_credential_with_the_routes_scope() {
+        let address = api();
+        let anonymous = get(&address, "GET /status HTTP/1.1\r\n\r\n");
+        assert!(anonymous.starts_with("HTTP/1.1 401 "), "{}", anonymous);
+        assert!(anonymous.contains("WWW-Authenticate: Bearer"), "{}", anonymous);
+
+        let status = get(&address, "GET /status HTTP/1.1\r\nAuthorization: Bearer reader-key\r\n\r\n");
+        assert!(status.starts_with("HTTP/1.1 200 ") && status.contains("leader"), "{}", status);
+
+        let submit = get(&address, "POST /transactions HTTP/1.1\r\nAuthorization: Bearer reader-key\r\nContent-Length: 2\r\n\r\n{}");
+        assert!(submit.starts_with("HTTP/1.1 403 ") && submit.contains("'submit'"), "{}", submit);
+    }
+}
@@ -0,0 +1,102 @@
+//! Rebuild from the Journal
+//! `tlc rebuild JOURNAL --store LEDGER [--checkpoint FILE]` reconstructs a
+//! ledger's derived state (the database's postings and memo search indices,
+//! and the per-account balances) from nothing but the append-only journal.
+//! Every transaction is verified and appended again in order, so the chain
+//! is checked; the balances are totalled afresh and must balance; and with
+//! `--checkpoint` the journal must still reproduce the operator's signed
+//! root. The new ledger is written beside LEDGER and replaces it only if all
+//! of that succeeds. Drafts are not in the journal, so a database's are
+//! carried over from the one it replaces.
+//!
+//! JOURNAL may be LEDGER itself, to rebuild a database's indices from the
+//! transactions it stores.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::merkle::MerkleTree;
+use true_ledger_core::storage::{self, is_journal_path};
+use true_ledger_core::trial_balance::TrialBalance;
+use true_ledger_core::verify::verify_with;
+
+use crate::checkpoint::load_checkpoint;
+use crate::store::journal_entries;
+
+/// Where the new ledger is built: beside `store`, keeping its extension
+fn scratch_path(store: &str) -> PathBuf {
+    let path = Path::new(store);
+    let name = path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!(".rebuilding-{}", name))
+}
+
+/// `tlc rebuild <journal> --store LEDGER [--checkpoint FILE [--operator DID]]`
+pub fn run_rebuild(journal: &str, store: &str, checkpoint: Option<&str>, operator: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let checkpoint = checkpoint.map(|path| load_checkpoint(path, operator, resolver)).transpose()?;
+    let scratch = scratch_path(store);
+    let _ = fs::remove_file(&scratch); // Left over from an interrupted rebuild
+    println!("\n🔧 Rebuilding {} from {}...", store, journal);
+
+    let rebuilt = rebuild_into(journal, &scratch, resolver).and_then(|(tree, trial_balance)| {
+        if let Some(signed) = &checkpoint {
+            signed.checkpoint.check_tree(&tree)?;
+            println!("✅ The journal reproduces the checkpoint's root over its first {} transactions", signed.checkpoint.tree_size);
+        }
+        Ok((tree, trial_balance))
+    }).and_then(|rebuilt| {
+        carry_drafts(store, &scratch)?;
+        Ok(rebuilt)
+    });
+    let (tree, trial_balance) = match rebuilt {
+        Ok(rebuilt) => rebuilt,
+        Err(e) => {
+            let _ = fs::remove_file(&scratch);
+            return Err(format!("Rebuild stopped, and {} left as it was: {}", store, e));
+        }
+    };
+    fs::rename(&scratch, store).map_err(|e| format!("Could not replace {}: {}", store, e))?;
+
+    let (debits, credits) = trial_balance.totals()?;
+    println!("✅ {} transactions re-verified and re-indexed; Merkle root {}", tree.size(), hex::encode(tree.root()));
+    println!("✅ {} accounts; debits {} = credits {}", trial_balance.accounts.len(), format_cents(debits), format_cents(credits));
+    println!("\n🎉 **REBUILT** {}", store);
+    Ok(())
+}
+
+/// Verifies and appends every transaction of `journal` to a new ledger at
+/// `scratch`, totalling them as it goes
+fn rebuild_into(journal: &str, scratch: &Path, resolver: &dyn DidResolver) -> Result<(MerkleTree, TrialBalance), String> {
+    let mut ledger = storage::open(scratch)?;
+    let mut tree = MerkleTree::new();
+    let mut trial_balance = TrialBalance::new();
+    for entry in journal_entries(journal)? {
+        let (name, signed_tx) = entry?;
+        verify_with(&signed_tx, resolver)
+            .and_then(|_| ledger.append(&signed_tx))
+            .and_then(|_| trial_balance.post(&signed_tx.payload))
+            .map_err(|e| format!("{}: {}", name, e))?;
+        tree.push(signed_tx.payload.get_hash());
+    }
+    trial_balance.check()?;
+    Ok((tree, trial_balance))
+}
+
+/// Copies the drafts of the database being replaced, if it exists, into the
+/// new one. A journal's drafts are kept beside it and stay where they are.
+fn carry_drafts(store: &str, scratch: &Path) -> Result<(), String> {
+    if !Path::new(store).is_file() || is_journal_path(Path::new(store)) {
+        return Ok(());
+    }
+    let drafts = storage::open(store)?.drafts()?;
+    if drafts.is_empty() {
+        return Ok(());
+    }
+    let mut ledger = storage::open(scratch)?;
+    for draft in &drafts {
+        ledger.put_draft(draft)?;
+    }
+    println!("✅ {} draft(s) carried over", drafts.len());
+    Ok(())
+}
@@ -0,0 +1,796 @@
+//! Financial Reports
+//! `tlc report <balance-sheet|cash-flow> <dir|ledger> ...`: financial
+//! statements over the verified transactions in a journal, as plain text,
+//! JSON or CSV. `tlc report anomalies` screens the same transactions for
+//! amounts that deviate from Benford's law or their group's distribution
+//! (see the core analytics.rs), and `tlc report duplicates` lists payments
+//! that look like one payment made twice (see the core duplicates.rs).
+//! `tlc report counterparty` is a statement of account for one customer or
+//! supplier to confirm (see the core counterparty.rs), `tlc report invoices`
+//! follows invoices from issue to settlement (see the core invoices.rs), and
+//! `tlc report attestations` shows, period by period, whether management's
+//! signed sign-offs (see attestation.rs) still match the books. Dates may
+//! also be fiscal periods, counted by the calendar given with `--fiscal`
+//! (see the core fiscal.rs).
+
+use chrono::NaiveDate;
+use clap::{Subcommand, ValueEnum};
+use serde::Serialize;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::analytics::{AnomalyReport, GroupBy};
+use true_ledger_core::attestation::{AttestationStatus, SignedAttestation};
+use true_ledger_core::chart::ChartOfAccounts;
+use true_ledger_core::counterparty::CounterpartyStatement;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::duplicates::{DuplicateCheck, DuplicateGroup, DuplicateReason};
+use true_ledger_core::fiscal::{Cutoff, FiscalCalendar};
+use true_ledger_core::invoices::InvoiceLedger;
+use true_ledger_core::statements::{BalanceSheet, CashFlowLine, CashFlowStatement, StatementSection};
+use true_ledger_core::timestamp::Timestamp;
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::Transaction;
+
+use crate::attestation::books_through;
+use crate::store::journal_entries;
+
+#[derive(Subcommand, Debug)]
+pub enum ReportCommand {
+    /// Assets, liabilities and equity at the close of a day
+    BalanceSheet {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// Include transactions dated on or before this day, or through this fiscal period (FY2025, FY2025-P12)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        as_of: String,
+
+        /// Fiscal calendar for periods (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        /// Chart of accounts that classifies every account (.json or .toml)
+        #[arg(long, value_name = "FILE")]
+        chart: String,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write the statement to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// Cash in and out over a period by activity, reconciled from net income (indirect method)
+    CashFlow {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// First day or fiscal period of the statement (default: the start of the books)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        from: Option<String>,
+
+        /// Last day or fiscal period of the statement (FY2025, FY2025-P12)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        to: String,
+
+        /// Fiscal calendar for periods (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        /// Chart of accounts that marks the cash accounts and maps the rest to activities
+        #[arg(long, value_name = "FILE")]
+        chart: String,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write the statement to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// Benford first-digit tests and amount statistics per account or author, flagging deviations
+    Anomalies {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// First day or fiscal period to analyse (default: the start of the books)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        from: Option<String>,
+
+        /// Last day or fiscal period to analyse (default: everything)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        to: Option<String>,
+
+        /// Fiscal calendar for periods (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        /// Group the amounts by account or by signing author
+        #[arg(long, value_enum, default_value = "account")]
+        by: GroupByArg,
+
+        /// List only the flagged groups
+        #[arg(long)]
+        flagged: bool,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write the report to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// A review worklist of likely duplicate payments: one invoice paid twice, or one amount to one counterparty
+    Duplicates {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// First day or fiscal period to check (default: the start of the books)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        from: Option<String>,
+
+        /// Last day or fiscal period to check (default: everything)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        to: Option<String>,
+
+        /// Fiscal calendar for periods (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        /// Flag the same amount to the same counterparty this many days apart or fewer
+        #[arg(long, value_name = "DAYS", default_value_t = 14)]
+        window_days: i64,
+
+        /// Only transactions crediting this account are payments, e.g. the bank (repeatable; default: every transaction)
+        #[arg(long = "payment-account", value_name = "CODE")]
+        payment_accounts: Vec<String>,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write the worklist to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// A statement of account for one customer or supplier, for them to confirm its balance
+    Counterparty {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// Name or DID in the memos' counterparty=, payee= or vendor= field
+        #[arg(long)]
+        counterparty: String,
+
+        /// Their control account: a code, a prefix ending in '*', or a range such as 11000-11999 (repeatable)
+        #[arg(long = "account", value_name = "PATTERN", required = true)]
+        accounts: Vec<String>,
+
+        /// First day or fiscal period listed; earlier movements are brought forward (default: the start of the books)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        from: Option<String>,
+
+        /// Last day or fiscal period listed (default: everything)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        to: Option<String>,
+
+        /// Fiscal calendar for periods (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write the statement to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// Invoices from issue to settlement or write-off, with the payments no invoice could be matched to
+    Invoices {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// Control account invoices sit in: a code, a prefix ending in '*', or a range (repeatable)
+        #[arg(long = "account", value_name = "PATTERN", required = true)]
+        accounts: Vec<String>,
+
+        /// Account whose postings write an invoice off, e.g. bad debts (repeatable)
+        #[arg(long = "write-off", value_name = "PATTERN")]
+        write_off: Vec<String>,
+
+        /// Last day or fiscal period to follow (default: everything)
+        #[arg(long, value_name = "YYYY-MM-DD|PERIOD")]
+        as_of: Option<String>,
+
+        /// Fiscal calendar for periods (default: years start on 1 January)
+        #[arg(long, value_name = "FILE")]
+        fiscal: Option<String>,
+
+        /// Only the invoices still owed
+        #[arg(long)]
+        open: bool,
+
+        /// Only this counterparty's invoices (name or DID)
+        #[arg(long)]
+        counterparty: Option<String>,
+
+        /// Only this invoice, with every change to it
+        #[arg(long, value_name = "REFERENCE")]
+        invoice: Option<String>,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write the report to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// Management's sign-offs, period by period: do the books still match what was attested?
+    Attestations {
+        /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+        #[arg(default_value = ".")]
+        journal: String,
+
+        /// A signed attestation from `tlc attest` (repeatable)
+        #[arg(long = "attestation", value_name = "FILE", required = true)]
+        attestations: Vec<String>,
+
+        /// DID designated to sign off, e.g. the CFO's (repeatable; default: anyone)
+        #[arg(long = "attester", value_name = "DID")]
+        attesters: Vec<String>,
+
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write the report to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupByArg {
+    Account,
+    Author,
+}
+
+impl From<GroupByArg> for GroupBy {
+    fn from(by: GroupByArg) -> Self {
+        match by {
+            GroupByArg::Account => GroupBy::Account,
+            GroupByArg::Author => GroupBy::Author,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+pub fn run_report(command: &ReportCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        ReportCommand::BalanceSheet { journal, as_of, fiscal, chart, format, out_path } => {
+            let as_of = calendar(fiscal.as_deref())?.cutoff(as_of)?;
+            let chart = ChartOfAccounts::load(chart)?;
+            let payloads = verified_payloads(journal, resolver)?;
+            let sheet = BalanceSheet::from_transactions(&payloads, &chart, as_of)?;
+            let rendered = match format {
+                ReportFormat::Text => balance_sheet_text(&sheet),
+                ReportFormat::Json => serde_json::to_string_pretty(&sheet)
+                    .map_err(|e| format!("Failed to serialize the balance sheet: {}", e))? + "\n",
+                ReportFormat::Csv => balance_sheet_csv(&sheet),
+            };
+            output(&rendered, out_path.as_deref())?;
+            sheet.check()?;
+            Ok(())
+        }
+        ReportCommand::CashFlow { journal, from, to, fiscal, chart, format, out_path } => {
+            let calendar = calendar(fiscal.as_deref())?;
+            let from = from.as_deref().map(|from| calendar.start(from)).transpose()?;
+            let to = calendar.cutoff(to)?;
+            let chart = ChartOfAccounts::load(chart)?;
+            let payloads = verified_payloads(journal, resolver)?;
+            let statement = CashFlowStatement::from_transactions(&payloads, &chart, from, to)?;
+            let rendered = match format {
+                ReportFormat::Text => cash_flow_text(&statement),
+                ReportFormat::Json => serde_json::to_string_pretty(&statement)
+                    .map_err(|e| format!("Failed to serialize the cash flow statement: {}", e))? + "\n",
+                ReportFormat::Csv => cash_flow_csv(&statement),
+            };
+            output(&rendered, out_path.as_deref())?;
+            statement.check()?;
+            Ok(())
+        }
+        ReportCommand::Duplicates { journal, from, to, fiscal, window_days, payment_accounts, format, out_path } => {
+            let calendar = calendar(fiscal.as_deref())?;
+            let from = from.as_deref().map(|from| calendar.start(from)).transpose()?;
+            let to = to.as_deref().map(|to| calendar.cutoff(to)).transpose()?.unwrap_or(Cutoff::day(NaiveDate::MAX));
+            let payloads = verified_payloads(journal, resolver)?;
+            let in_period = payloads.iter().filter(|tx| from.is_none_or(|from| tx.timestamp.local_date() >= from) && to.includes(tx));
+            let check = DuplicateCheck { window_days: *window_days, payment_accounts: payment_accounts.clone() };
+            let worklist = check.run(in_period)?;
+            let rendered = match format {
+                ReportFormat::Text => duplicates_text(&worklist),
+                ReportFormat::Json => serde_json::to_string_pretty(&worklist)
+                    .map_err(|e| format!("Failed to serialize the worklist: {}", e))? + "\n",
+                ReportFormat::Csv => duplicates_csv(&worklist),
+            };
+            output(&rendered, out_path.as_deref())?;
+            let at_risk: i64 = worklist.iter().map(|group| group.at_risk_cents).sum();
+            eprintln!("{} {} group(s) of likely duplicate payments, {} at risk", if worklist.is_empty() { "✅" } else { "🚩" }, worklist.len(), format_cents(at_risk));
+            Ok(())
+        }
+        ReportCommand::Counterparty { journal, counterparty, accounts, from, to, fiscal, format, out_path } => {
+            let calendar = calendar(fiscal.as_deref())?;
+            let from = from.as_deref().map(|from| calendar.start(from)).transpose()?;
+            let to = to.as_deref().map(|to| calendar.cutoff(to)).transpose()?.unwrap_or(Cutoff::day(NaiveDate::MAX));
+            let payloads = verified_payloads(journal, resolver)?;
+            let statement = CounterpartyStatement::from_transactions(&payloads, counterparty, accounts, from, to)?;
+            let rendered = match format {
+                ReportFormat::Text => counterparty_text(&statement),
+                ReportFormat::Json => serde_json::to_string_pretty(&statement)
+                    .map_err(|e| format!("Failed to serialize the statement: {}", e))? + "\n",
+                ReportFormat::Csv => counterparty_csv(&statement),
+            };
+            output(&rendered, out_path.as_deref())?;
+            if statement.lines.is_empty() && statement.opening_cents == 0 {
+                eprintln!("⚠️  No movements for {} on {}: check the memos name them", counterparty, accounts.join(", "));
+            }
+            Ok(())
+        }
+        ReportCommand::Invoices { journal, accounts, write_off, as_of, fiscal, open, counterparty, invoice, format, out_path } => {
+            let calendar = calendar(fiscal.as_deref())?;
+            let to = as_of.as_deref().map(|as_of| calendar.cutoff(as_of)).transpose()?.unwrap_or(Cutoff::day(NaiveDate::MAX));
+            let payloads = verified_payloads(journal, resolver)?;
+            let mut ledger = InvoiceLedger::from_transactions(&payloads, accounts, write_off, to)?;
+            if let Some(reference) = invoice {
+                if ledger.get(reference).is_none() {
+                    return Err(format!("No invoice {} on {}", reference, accounts.join(", ")));
+                }
+                ledger.invoices.retain(|found| found.reference.eq_ignore_ascii_case(reference));
+                ledger.unlinked.clear();
+            }
+            if let Some(counterparty) = counterparty {
+                ledger.invoices.retain(|found| found.counterparty.as_deref().is_some_and(|value| value.eq_ignore_ascii_case(counterparty)));
+                ledger.unlinked.retain(|unlinked| unlinked.counterparty.as_deref().is_some_and(|value| value.eq_ignore_ascii_case(counterparty)));
+            }
+            if *open {
+                ledger.invoices.retain(|found| found.status.is_open());
+            }
+            let rendered = match format {
+                ReportFormat::Text => invoices_text(&ledger, invoice.is_some()),
+                ReportFormat::Json => serde_json::to_string_pretty(&ledger)
+                    .map_err(|e| format!("Failed to serialize the invoices: {}", e))? + "\n",
+                ReportFormat::Csv => invoices_csv(&ledger),
+            };
+            output(&rendered, out_path.as_deref())?;
+            let outstanding: i64 = ledger.open().map(|found| found.outstanding_cents).sum();
+            eprintln!("📄 {} open invoice(s), {} outstanding (net); {} unlinked movement(s)", ledger.open().count(), format_cents(outstanding), ledger.unlinked.len());
+            Ok(())
+        }
+        ReportCommand::Attestations { journal, attestations, attesters, format, out_path } => {
+            if attesters.is_empty() {
+                eprintln!("⚠️  No --attester given: a sign-off by anyone counts");
+            }
+            let mut rows = Vec::new();
+            for path in attestations {
+                let signed = SignedAttestation::load(path)?;
+                let books = books_through(journal, signed.attestation.cutoff(), resolver)?;
+                rows.push(AttestationRow::new(path, &signed, signed.status(&books, attesters, resolver)?));
+            }
+            rows.sort_by_key(|row| (row.through, row.adjustments));
+            let rendered = match format {
+                ReportFormat::Text => attestations_text(&rows),
+                ReportFormat::Json => serde_json::to_string_pretty(&rows)
+                    .map_err(|e| format!("Failed to serialize the attestations: {}", e))? + "\n",
+                ReportFormat::Csv => attestations_csv(&rows),
+            };
+            output(&rendered, out_path.as_deref())?;
+            let attested = rows.iter().filter(|row| row.status == "attested").count();
+            if attested < rows.len() {
+                return Err(format!("{} of {} sign-off(s) do not hold", rows.len() - attested, rows.len()));
+            }
+            eprintln!("✅ All {} sign-off(s) match the books", rows.len());
+            Ok(())
+        }
+        ReportCommand::Anomalies { journal, from, to, fiscal, by, flagged, format, out_path } => {
+            let calendar = calendar(fiscal.as_deref())?;
+            let from = from.as_deref().map(|from| calendar.start(from)).transpose()?;
+            let to = to.as_deref().map(|to| calendar.cutoff(to)).transpose()?.unwrap_or(Cutoff::day(NaiveDate::MAX));
+            let payloads = verified_payloads(journal, resolver)?;
+            let mut report = AnomalyReport::from_transactions(&payloads, (*by).into(), from, to)?;
+            let (flagged_groups, groups) = (report.flagged().count(), report.groups.len());
+            if *flagged {
+                report.groups.retain(|group| group.flagged);
+            }
+            let rendered = match format {
+                ReportFormat::Text => anomalies_text(&report),
+                ReportFormat::Json => serde_json::to_string_pretty(&report)
+                    .map_err(|e| format!("Failed to serialize the anomaly report: {}", e))? + "\n",
+                ReportFormat::Csv => anomalies_csv(&report),
+            };
+            output(&rendered, out_path.as_deref())?;
+            eprintln!("{} {} of {} group(s) flagged for review", if flagged_groups == 0 { "✅" } else { "🚩" }, flagged_groups, groups);
+            Ok(())
+        }
+    }
+}
+
+fn calendar(fiscal: Option<&str>) -> Result<FiscalCalendar, String> {
+    Ok(fiscal.map(FiscalCalendar::load).transpose()?.unwrap_or_default())
+}
+
+/// Every transaction in the journal that verifies, in order; the rest are
+/// reported on stderr, so that stdout holds only the statement
+pub fn verified_payloads(journal: &str, resolver: &dyn DidResolver) -> Result<Vec<Transaction>, String> {
+    let mut payloads = Vec::new();
+    for entry in journal_entries(journal)? {
+        let (name, signed_tx) = entry?;
+        match verify_with(&signed_tx, resolver) {
+            Ok(()) => payloads.push(signed_tx.payload),
+            Err(e) => eprintln!("⚠️  Skipped {}: {}", name, e),
+        }
+    }
+    Ok(payloads)
+}
+
+fn output(rendered: &str, out_path: Option<&str>) -> Result<(), String> {
+    match out_path {
+        Some(path) => {
+            fs::write(path, rendered).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            eprintln!("💾 Report saved to {}", path);
+        }
+        None => print!("{}", rendered),
+    }
+    Ok(())
+}
+
+fn before_adjustments(adjustments: bool) -> &'static str {
+    if adjustments { "" } else { " before year-end adjustments" }
+}
+
+fn text_section(out: &mut String, title: &str, section: &StatementSection) {
+    out.push_str(&format!("\n{}\n", title));
+    for line in &section.lines {
+        let label = format!("{}{} {}", "  ".repeat(line.depth + 1), line.account_id, line.name.as_deref().unwrap_or(""));
+        out.push_str(&format!("{:<48} {:>16}\n", label.trim_end(), format_cents(line.balance_cents)));
+    }
+}
+
+fn balance_sheet_text(sheet: &BalanceSheet) -> String {
+    let mut out = format!("BALANCE SHEET as of {}{} ({} transactions)\n", sheet.as_of, before_adjustments(sheet.adjustments), sheet.transactions);
+    text_section(&mut out, "Assets", &sheet.assets);
+    out.push_str(&format!("{:<48} {:>16}\n", "Total assets", format_cents(sheet.assets.total_cents)));
+    text_section(&mut out, "Liabilities", &sheet.liabilities);
+    out.push_str(&format!("{:<48} {:>16}\n", "Total liabilities", format_cents(sheet.liabilities.total_cents)));
+    text_section(&mut out, "Equity", &sheet.equity);
+    out.push_str(&format!("{:<48} {:>16}\n", "  Net income", format_cents(sheet.net_income_cents)));
+    out.push_str(&format!("{:<48} {:>16}\n", "Total equity", format_cents(sheet.total_equity_cents())));
+    out.push_str(&format!("\n{:<48} {:>16}\n", "Total liabilities and equity",
+        format_cents(sheet.liabilities.total_cents + sheet.total_equity_cents())));
+    out
+}
+
+/// Quotes a CSV field if it needs it (RFC 4180)
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// One row per account, then a total per section; total rows have no account
+fn balance_sheet_csv(sheet: &BalanceSheet) -> String {
+    let mut out = String::from("section,account_id,name,depth,balance\n");
+    let mut row = |section: &str, account_id: &str, name: &str, depth: usize, cents: i64| {
+        out.push_str(&format!("{},{},{},{},{}\n", section, csv_field(account_id), csv_field(name), depth, format_cents(cents)));
+    };
+    for (title, section) in [("assets", &sheet.assets), ("liabilities", &sheet.liabilities), ("equity", &sheet.equity)] {
+        for line in &section.lines {
+            row(title, &line.account_id, line.name.as_deref().unwrap_or(""), line.depth, line.balance_cents);
+        }
+        if title == "equity" {
+            row(title, "", "Net income", 0, sheet.net_income_cents);
+            row(title, "", "Total equity", 0, sheet.total_equity_cents());
+        } else {
+            row(title, "", &format!("Total {}", title), 0, section.total_cents);
+        }
+    }
+    out
+}
+
+fn text_cash_flow_lines(out: &mut String, lines: &[CashFlowLine]) {
+    for line in lines {
+        out.push_str(&format!("{:<48} {:>16}\n", format!("  {} {}", line.account_id, line.name), format_cents(line.amount_cents)));
+    }
+}
+
+fn cash_flow_text(statement: &CashFlowStatement) -> String {
+    let period = match statement.from {
+        Some(from) => format!("{} to {}{}", from, statement.to, before_adjustments(statement.adjustments)),
+        None => format!("through {}{}", statement.to, before_adjustments(statement.adjustments)),
+    };
+    let mut out = format!("CASH FLOW STATEMENT {} ({} transactions)\n", period, statement.transactions);
+    out.push_str("\nOperating activities\n");
+    out.push_str(&format!("{:<48} {:>16}\n", "  Net income", format_cents(statement.net_income_cents)));
+    text_cash_flow_lines(&mut out, &statement.operating);
+    out.push_str(&format!("{:<48} {:>16}\n", "Net cash from operating activities", format_cents(statement.operating_cents())));
+    out.push_str("\nInvesting activities\n");
+    text_cash_flow_lines(&mut out, &statement.investing);
+    out.push_str(&format!("{:<48} {:>16}\n", "Net cash from investing activities", format_cents(statement.investing_cents())));
+    out.push_str("\nFinancing activities\n");
+    text_cash_flow_lines(&mut out, &statement.financing);
+    out.push_str(&format!("{:<48} {:>16}\n", "Net cash from financing activities", format_cents(statement.financing_cents())));
+    out.push_str(&format!("\n{:<48} {:>16}\n", "Net change in cash", format_cents(statement.net_change_cents())));
+    out.push_str(&format!("{:<48} {:>16}\n", "Cash at beginning of period", format_cents(statement.opening_cash_cents)));
+    out.push_str(&format!("{:<48} {:>16}\n", "Cash at end of period", format_cents(statement.closing_cash_cents)));
+    out
+}
+
+/// One row per adjustment, then a total per activity; summary rows have no account
+fn cash_flow_csv(statement: &CashFlowStatement) -> String {
+    let mut out = String::from("section,account_id,name,amount\n");
+    let mut row = |section: &str, account_id: &str, name: &str, cents: i64| {
+        out.push_str(&format!("{},{},{},{}\n", section, csv_field(account_id), csv_field(name), format_cents(cents)));
+    };
+    row("operating", "", "Net income", statement.net_income_cents);
+    let activities = [
+        ("operating", &statement.operating, statement.operating_cents()),
+        ("investing", &statement.investing, statement.investing_cents()),
+        ("financing", &statement.financing, statement.financing_cents()),
+    ];
+    for (section, lines, total) in activities {
+        for line in lines {
+            row(section, &line.account_id, &line.name, line.amount_cents);
+        }
+        row(section, "", &format!("Net cash from {} activities", section), total);
+    }
+    row("cash", "", "Net change in cash", statement.net_change_cents());
+    row("cash", "", "Cash at beginning of period", statement.opening_cash_cents);
+    row("cash", "", "Cash at end of period", statement.closing_cash_cents);
+    out
+}
+
+fn anomalies_text(report: &AnomalyReport) -> String {
+    let period = match report.from {
+        Some(from) if report.to == NaiveDate::MAX => format!("from {}", from),
+        Some(from) => format!("{} to {}", from, report.to),
+        None if report.to == NaiveDate::MAX => "over the whole journal".to_string(),
+        None => format!("through {}", report.to),
+    };
+    let by = match report.by {
+        GroupBy::Account => "account",
+        GroupBy::Author => "author",
+    };
+    let mut out = format!("ANOMALY REPORT by {} {} ({} transactions)\n", by, period, report.transactions);
+    for group in &report.groups {
+        let stats = &group.stats;
+        out.push_str(&format!("\n{} {}\n", if group.flagged { "🚩" } else { "  " }, group.key));
+        out.push_str(&format!("   {} amounts, total {}, mean {}, median {}, std dev {}, max {}, {:.0}% round\n",
+            stats.count, format_cents(stats.total_cents), format_cents(stats.mean_cents.round() as i64),
+            format_cents(stats.median_cents), format_cents(stats.std_dev_cents.round() as i64), format_cents(stats.max_cents),
+            stats.round_share * 100.0));
+        match &group.benford {
+            Some(test) => {
+                out.push_str(&format!("   Benford: {:?} (MAD {:.4}, chi-square {:.2}{}, {} amounts)\n", test.conformity, test.mad,
+                    test.chi_square, if test.chi_square_exceeded { " > 15.51" } else { "" }, test.amounts));
+                for line in test.digits.iter().filter(|line| line.significant) {
+                    out.push_str(&format!("     digit {}: {:.1}% vs {:.1}% expected (z {:.2})\n",
+                        line.digit, line.observed * 100.0, line.expected * 100.0, line.z));
+                }
+            }
+            None => out.push_str("   Benford: too few amounts of 10.00 or more to test\n"),
+        }
+        for outlier in &group.outliers {
+            out.push_str(&format!("   Outlier {} on {}: {} (z {:.1})\n", outlier.tx_hash, outlier.date, format_cents(outlier.amount_cents), outlier.z));
+        }
+    }
+    out
+}
+
+/// One row per group; the Benford columns are empty for groups too small to test
+fn anomalies_csv(report: &AnomalyReport) -> String {
+    let mut out = String::from("key,flagged,count,total,mean,median,std_dev,max,round_share,benford_amounts,mad,conformity,chi_square,significant_digits,outliers\n");
+    for group in &report.groups {
+        let stats = &group.stats;
+        let benford = match &group.benford {
+            Some(test) => {
+                let digits: Vec<String> = test.digits.iter().filter(|line| line.significant).map(|line| line.digit.to_string()).collect();
+                format!("{},{:.4},{:?},{:.2},{}", test.amounts, test.mad, test.conformity, test.chi_square, digits.join(" "))
+            }
+            None => ",,,,".to_string(),
+        };
+        let outliers: Vec<&str> = group.outliers.iter().map(|outlier| outlier.tx_hash.as_str()).collect();
+        out.push_str(&format!("{},{},{},{},{},{},{},{},{:.4},{},{}\n", csv_field(&group.key), group.flagged, stats.count,
+            format_cents(stats.total_cents), format_cents(stats.mean_cents.round() as i64), format_cents(stats.median_cents),
+            format_cents(stats.std_dev_cents.round() as i64), format_cents(stats.max_cents), stats.round_share, benford, outliers.join(" ")));
+    }
+    out
+}
+
+fn duplicate_reason(reason: &DuplicateReason) -> String {
+    match reason {
+        DuplicateReason::SameInvoice { invoice } => format!("Invoice {} paid more than once", invoice),
+        DuplicateReason::SameCounterpartyAmount { counterparty, amount_cents, days_apart } => {
+            format!("{} paid to {} more than once within {} day(s)", format_cents(*amount_cents), counterparty, days_apart)
+        }
+    }
+}
+
+fn duplicates_text(worklist: &[DuplicateGroup]) -> String {
+    let mut out = format!("DUPLICATE PAYMENT WORKLIST ({} groups)\n", worklist.len());
+    for (i, group) in worklist.iter().enumerate() {
+        out.push_str(&format!("\n{}. {} ({} at risk)\n", i + 1, duplicate_reason(&group.reason), format_cents(group.at_risk_cents)));
+        for payment in &group.payments {
+            out.push_str(&format!("   {} {} {:>14} {}\n", payment.date, &payment.tx_hash[..16], format_cents(payment.amount_cents), payment.memo));
+        }
+    }
+    out
+}
+
+/// One row per payment, numbered by the group it is in
+fn duplicates_csv(worklist: &[DuplicateGroup]) -> String {
+    let mut out = String::from("group,reason,at_risk,tx_hash,date,amount,counterparty,memo\n");
+    for (i, group) in worklist.iter().enumerate() {
+        for payment in &group.payments {
+            out.push_str(&format!("{},{},{},{},{},{},{},{}\n", i + 1, csv_field(&duplicate_reason(&group.reason)), format_cents(group.at_risk_cents),
+                payment.tx_hash, payment.date, format_cents(payment.amount_cents), csv_field(&payment.counterparty), csv_field(&payment.memo)));
+        }
+    }
+    out
+}
+
+fn counterparty_text(statement: &CounterpartyStatement) -> String {
+    let mut out = format!("STATEMENT OF ACCOUNT: {}\n", statement.counterparty);
+    let through = if statement.to.date == NaiveDate::MAX { "today".to_string() } else { statement.to.date.to_string() };
+    match statement.from {
+        Some(from) => out.push_str(&format!("Accounts {}, {} to {}\n\n", statement.accounts.join(", "), from, through)),
+        None => out.push_str(&format!("Accounts {}, through {}\n\n", statement.accounts.join(", "), through)),
+    }
+    out.push_str(&format!("{:<10} {:<16} {:<36} {:>14} {:>14} {:>14}\n", "Date", "Transaction", "Memo", "Debit", "Credit", "Balance"));
+    out.push_str(&format!("{:<10} {:<16} {:<36} {:>14} {:>14} {:>14}\n", "", "", "Balance brought forward", "", "", format_cents(statement.opening_cents)));
+    for line in &statement.lines {
+        let memo: String = line.memo.chars().take(36).collect();
+        let amount = |cents: i64| if cents == 0 { String::new() } else { format_cents(cents) };
+        out.push_str(&format!("{:<10} {:<16} {:<36} {:>14} {:>14} {:>14}\n", line.date, &line.tx_hash[..16], memo,
+            amount(line.debit_cents), amount(line.credit_cents), format_cents(line.balance_cents)));
+    }
+    out.push_str(&format!("{:<10} {:<16} {:<36} {:>14} {:>14} {:>14}\n", "", "", "Balance carried down", "", "", format_cents(statement.closing_cents)));
+    out
+}
+
+/// The movements, between a brought-forward and a carried-down row
+fn counterparty_csv(statement: &CounterpartyStatement) -> String {
+    let mut out = String::from("date,tx_hash,account,memo,debit,credit,balance\n");
+    out.push_str(&format!(",,,Balance brought forward,,,{}\n", format_cents(statement.opening_cents)));
+    for line in &statement.lines {
+        out.push_str(&format!("{},{},{},{},{},{},{}\n", line.date, line.tx_hash, csv_field(&line.account_id), csv_field(&line.memo),
+            format_cents(line.debit_cents), format_cents(line.credit_cents), format_cents(line.balance_cents)));
+    }
+    out.push_str(&format!(",,,Balance carried down,,,{}\n", format_cents(statement.closing_cents)));
+    out
+}
+
+/// The day invoices are aged to: the cutoff, or today
+fn aged_to(to: &Cutoff) -> NaiveDate {
+    if to.date != NaiveDate::MAX {
+        return to.date;
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    Timestamp::from(now).utc().date_naive()
+}
+
+fn invoices_text(ledger: &InvoiceLedger, history: bool) -> String {
+    let today = aged_to(&ledger.to);
+    let mut out = format!("INVOICES on {} as of {} ({} shown)\n\n", ledger.accounts.join(", "), today, ledger.invoices.len());
+    out.push_str(&format!("{:<16} {:<20} {:<10} {:>14} {:>14} {:<14} {:>5}\n", "Reference", "Counterparty", "Issued", "Amount", "Outstanding", "Status", "Age"));
+    for invoice in &ledger.invoices {
+        let counterparty: String = invoice.counterparty.as_deref().unwrap_or("").chars().take(20).collect();
+        let age = if invoice.status.is_open() { invoice.age_days(today).to_string() } else { String::new() };
+        out.push_str(&format!("{:<16} {:<20} {:<10} {:>14} {:>14} {:<14} {:>5}\n", invoice.reference, counterparty, invoice.issued,
+            format_cents(invoice.amount_cents), format_cents(invoice.outstanding_cents), invoice.status.name(), age));
+        if history {
+            for event in &invoice.events {
+                out.push_str(&format!("   {} {} {:<10} {:>14}  linked {}\n", event.date, &event.tx_hash[..16], event.kind.name(),
+                    format_cents(event.cents), event.link.name()));
+            }
+        }
+    }
+    if !ledger.unlinked.is_empty() {
+        out.push_str(&format!("\nUNLINKED MOVEMENTS ({}): no invoice reference or open invoice of the counterparty\n", ledger.unlinked.len()));
+        for unlinked in &ledger.unlinked {
+            out.push_str(&format!("   {} {} {:>14} {}\n", unlinked.date, &unlinked.tx_hash[..16], format_cents(unlinked.cents), unlinked.memo));
+        }
+    }
+    out
+}
+
+/// One row per invoice
+fn invoices_csv(ledger: &InvoiceLedger) -> String {
+    let today = aged_to(&ledger.to);
+    let mut out = String::from("reference,counterparty,issued,amount,outstanding,status,age_days,issue_tx_hash\n");
+    for invoice in &ledger.invoices {
+        let age = if invoice.status.is_open() { invoice.age_days(today).to_string() } else { String::new() };
+        out.push_str(&format!("{},{},{},{},{},{},{},{}\n", csv_field(&invoice.reference), csv_field(invoice.counterparty.as_deref().unwrap_or("")),
+            invoice.issued, format_cents(invoice.amount_cents), format_cents(invoice.outstanding_cents), invoice.status.name(), age,
+            invoice.events[0].tx_hash));
+    }
+    out
+}
+
+/// One sign-off and where it stands
+#[derive(Serialize, Debug)]
+struct AttestationRow {
+    file: String,
+    period: String,
+    through: NaiveDate,
+    adjustments: bool,
+    attested_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    status: &'static str, // attested, changed, not_designated or invalid
+    detail: String,
+}
+
+impl AttestationRow {
+    fn new(file: &str, signed: &SignedAttestation, status: AttestationStatus) -> Self {
+        let attestation = &signed.attestation;
+        let (status, detail) = match status {
+            AttestationStatus::Attested => ("attested", format!("Books match the trial balance signed at {}", attestation.timestamp)),
+            AttestationStatus::Changed { trial_balance } => ("changed", format!("Books changed since sign-off: trial balance {} was signed, the books now give {}",
+                &attestation.trial_balance[..16], &trial_balance[..16])),
+            AttestationStatus::NotDesignated => ("not_designated", format!("{} is not a designated attester", attestation.attested_by)),
+            AttestationStatus::Invalid(e) => ("invalid", format!("Invalid signature: {}", e)),
+        };
+        AttestationRow {
+            file: file.to_string(),
+            period: attestation.period.clone(),
+            through: attestation.through,
+            adjustments: attestation.adjustments,
+            attested_by: attestation.attested_by.clone(),
+            role: attestation.role.clone(),
+            status,
+            detail,
+        }
+    }
+}
+
+fn attestations_text(rows: &[AttestationRow]) -> String {
+    let mut out = format!("PERIOD ATTESTATIONS ({} sign-offs)\n", rows.len());
+    for row in rows {
+        let icon = match row.status {
+            "attested" => "✅",
+            "not_designated" => "⚠️ ",
+            _ => "❌",
+        };
+        out.push_str(&format!("\n{} {} (through {}{})\n", icon, row.period, row.through, before_adjustments(row.adjustments)));
+        out.push_str(&format!("   Signed off by {}{}\n", row.attested_by, row.role.as_ref().map(|role| format!(" as {}", role)).unwrap_or_default()));
+        out.push_str(&format!("   {}\n", row.detail));
+    }
+    out
+}
+
+fn attestations_csv(rows: &[AttestationRow]) -> String {
+    let mut out = String::from("period,through,adjustments,attested_by,role,status,detail,file\n");
+    for row in rows {
+        out.push_str(&format!("{},{},{},{},{},{},{},{}\n", csv_field(&row.period), row.through, row.adjustments, row.attested_by,
+            csv_field(row.role.as_deref().unwrap_or("")), row.status, csv_field(&row.detail), csv_field(&row.file)));
+    }
+    out
+}
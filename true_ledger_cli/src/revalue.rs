@@ -0,0 +1,82 @@
+//! FX Revaluation
+//! `tlc revalue <dir|ledger> --rates rates.json --as-of YYYY-MM-DD`: restates
+//! every foreign-currency balance at the closing rate and signs the balancing
+//! unrealised FX gain/loss postings as the system identity.
+
+use clap::Args;
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::fx::{revaluation_transaction, revalue, ExchangeRates};
+use true_ledger_core::verify::verify_with;
+
+use crate::signing::{sign_and_record, signer_from_args, SignerArgs};
+use crate::store::journal_entries;
+
+/// Options for `tlc revalue`
+#[derive(Args, Debug)]
+pub struct RevalueArgs {
+    /// Directory of signed transactions, or a ledger (database or .ndjson journal)
+    pub journal: String,
+
+    /// Exchange-rate table (JSON: base currency, then date -> pair -> rate)
+    #[arg(long, value_name = "FILE")]
+    pub rates: String,
+
+    /// Period end: balances are taken and restated at the close of this date (UTC)
+    #[arg(long, value_name = "YYYY-MM-DD")]
+    pub as_of: String,
+
+    /// Account that takes the unrealised FX gain or loss
+    #[arg(long, value_name = "CODE")]
+    pub fx_account: String,
+
+    /// Where to write the signed transaction (default: revaluation.json,
+    /// or no file when --store is given)
+    #[arg(long = "out", value_name = "FILE")]
+    pub out_path: Option<String>,
+
+    /// Chain the transaction onto this ledger (database or .ndjson journal) and store it there
+    #[arg(long, value_name = "DB")]
+    pub store: Option<String>,
+
+    #[command(flatten)]
+    pub signer: SignerArgs,
+}
+
+/// Prints the revaluation table, then signs (and optionally stores) the postings
+pub fn run_revalue(args: &RevalueArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    // Postings nobody can trace back to a known identity are worthless at audit
+    if args.signer.identity.is_none() && !args.signer.ssh_agent {
+        return Err("Revaluations are signed by the system identity: pass --identity or --ssh-agent".to_string());
+    }
+    let rates = ExchangeRates::load(&args.rates)?;
+    println!("\n💱 Revaluing {} at {} closing rates (base {})...", args.journal, args.as_of, rates.base);
+
+    // Only verified transactions belong in the books
+    let mut payloads = Vec::new();
+    for entry in journal_entries(&args.journal)? {
+        let (name, signed_tx) = entry?;
+        match verify_with(&signed_tx, resolver) {
+            Ok(()) => payloads.push(signed_tx.payload),
+            Err(e) => println!("⚠️  Skipped {}: {}", name, e),
+        }
+    }
+    let revaluations = revalue(&payloads, &rates, &args.as_of)?;
+
+    println!("\n{:<12} {:<4} {:>16} {:>16} {:>16} {:>16}", "Account", "Cur", "Balance", "Carrying", "Revalued", "Adjustment");
+    for r in &revaluations {
+        println!("{:<12} {:<4} {:>16} {:>16} {:>16} {:>16}", r.account_id, r.currency, format_cents(r.balance_cents),
+            format_cents(r.carrying_cents), format_cents(r.revalued_cents), format_cents(r.adjustment_cents));
+    }
+    let Some(mut tx) = revaluation_transaction(&revaluations, &rates, &args.fx_account, &args.as_of)? else {
+        println!("\n✅ Every foreign-currency balance is already carried at the closing rate; nothing to post.");
+        return Ok(());
+    };
+
+    let signer = signer_from_args(&args.signer)?;
+    tx.author_did = signer.did().to_string();
+    println!("\n📝 Signing FX revaluation ({} entries)...", tx.entries.len());
+    let store = args.store.as_deref();
+    sign_and_record(tx, signer.as_ref(), store, args.out_path.as_deref().or(store.xor(Some("revaluation.json"))), resolver)?;
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Reversals
+//! `tlc reverse <hash> --journal LEDGER` signs the reversal of a verified
+//! transaction: its entries with debits and credits swapped, linked to it by
+//! `reverses`. `tlc chain verify` and `tlc verify --ledger` check the link.
+
+use clap::Args;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::reversal::reversal_of;
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::SignedTransaction;
+
+use crate::signing::{sign_and_record, signer_from_args, SignerArgs};
+use crate::store::journal_entries;
+
+/// Options for `tlc reverse`
+#[derive(Args, Debug)]
+pub struct ReverseArgs {
+    /// Hex payload hash of the transaction to reverse
+    pub hash: String,
+
+    /// Where the transaction is: a directory of signed transactions, or a
+    /// ledger (database or .ndjson journal)
+    #[arg(long, value_name = "LEDGER")]
+    pub journal: String,
+
+    /// Memo for the reversal (default: "Reversal of <hash>: <original memo>")
+    #[arg(long)]
+    pub memo: Option<String>,
+
+    /// Where to write the signed reversal (default: reversal.json, or no
+    /// file when --store is given)
+    #[arg(long = "out", value_name = "FILE")]
+    pub out_path: Option<String>,
+
+    /// Chain the reversal onto this ledger (database or .ndjson journal) and store it there
+    #[arg(long, value_name = "DB")]
+    pub store: Option<String>,
+
+    #[command(flatten)]
+    pub signer: SignerArgs,
+}
+
+pub fn run_reverse(args: &ReverseArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    // One pass finds the original and anything that already reverses it
+    let mut original: Option<SignedTransaction> = None;
+    for entry in journal_entries(&args.journal)? {
+        let (name, signed_tx) = entry?;
+        if signed_tx.payload.reverses.as_deref() == Some(args.hash.as_str()) {
+            return Err(format!("{} is already reversed by {}", args.hash, name));
+        }
+        if signed_tx.payload.hash_hex() == args.hash {
+            original = Some(signed_tx);
+        }
+    }
+    let original = original.ok_or_else(|| format!("No transaction {} in {}", args.hash, args.journal))?;
+    verify_with(&original, resolver).map_err(|e| format!("Refusing to reverse {}: it does not verify: {}", args.hash, e))?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let mut tx = reversal_of(&original.payload, now.into());
+    if let Some(memo) = &args.memo {
+        tx.memo = memo.clone();
+    }
+    let signer = signer_from_args(&args.signer)?;
+    tx.author_did = signer.did().to_string();
+    println!("\n↩️  Reversing {} ({} entries): {}", &args.hash[..16.min(args.hash.len())], tx.entries.len(), original.payload.memo);
+    let store = args.store.as_deref();
+    sign_and_record(tx, signer.as_ref(), store, args.out_path.as_deref().or(store.xor(Some("reversal.json"))), resolver)?;
+    Ok(())
+}
@@ -0,0 +1,92 @@
+//! Audit Samples
+//! `tlc sample JOURNAL --checkpoint FILE --size N` draws a sample of the
+//! transactions a signed checkpoint covers, seeded with the checkpoint's
+//! digest so the operator could not have steered it (see the core
+//! sampling.rs), and writes the sampled transactions with their inclusion
+//! proofs and the checkpoint to one file.
+//! `tlc verify-sample FILE` checks that file on its own: the checkpoint's
+//! signature, every transaction and proof, and for a random sample that the
+//! seed picks exactly these transactions. With `--journal` it also draws
+//! the sample again, which a monetary-unit sample needs.
+
+use clap::ValueEnum;
+use std::fs;
+
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::model::SignedTransaction;
+use true_ledger_core::sampling::{AuditSample, SampleMethod};
+
+use crate::checkpoint::load_checkpoint;
+use crate::signing::write_json;
+use crate::store::journal_entries;
+
+/// How `tlc sample` picks transactions
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum MethodArg {
+    /// Every transaction equally likely
+    Random,
+    /// Every cent of the debits equally likely, so larger transactions more often
+    MonetaryUnit,
+}
+
+impl From<MethodArg> for SampleMethod {
+    fn from(method: MethodArg) -> Self {
+        match method {
+            MethodArg::Random => SampleMethod::Random,
+            MethodArg::MonetaryUnit => SampleMethod::MonetaryUnit,
+        }
+    }
+}
+
+/// Every transaction of a journal, in chain order
+fn read_journal(journal: &str) -> Result<Vec<SignedTransaction>, String> {
+    journal_entries(journal)?
+        .map(|entry| entry.map(|(_, signed_tx)| signed_tx))
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| format!("{} cannot be sampled whole: {}", journal, e))
+}
+
+/// `tlc sample <journal> --checkpoint FILE [--operator DID] --size N [--method M] --out FILE`
+pub fn run_sample(journal: &str, checkpoint: &str, operator: Option<&str>, size: usize, method: MethodArg, out_path: &str, resolver: &dyn DidResolver) -> Result<(), String> {
+    let checkpoint = load_checkpoint(checkpoint, operator, resolver)?;
+    let sample = AuditSample::draw(checkpoint, &read_journal(journal)?, method.into(), size)?;
+
+    println!("\n🎲 Seed {} (the checkpoint's digest)", sample.seed);
+    for item in &sample.items {
+        let payload = &item.transaction.payload;
+        println!("   #{:<6} {} {}", item.proof.leaf_index, payload.hash_hex(), payload.memo);
+    }
+    write_json(&sample, out_path)?;
+    println!("💾 {} of {} transactions sampled, with proofs, saved to {}", sample.items.len(), sample.checkpoint.checkpoint.tree_size, out_path);
+    Ok(())
+}
+
+/// `tlc verify-sample <file> [--operator DID] [--journal LEDGER]`
+pub fn run_verify_sample(path: &str, operator: Option<&str>, journal: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let sample: AuditSample = serde_json::from_str(&data).map_err(|e| format!("Invalid sample {}: {}", path, e))?;
+    let checkpoint = &sample.checkpoint.checkpoint;
+    match operator {
+        Some(operator) if operator != checkpoint.operator_did => {
+            return Err(format!("{}'s checkpoint was signed by {}, not the operator {}", path, checkpoint.operator_did, operator));
+        }
+        Some(_) => {}
+        None => println!("⚠️  No --operator given: trusting whichever key signed {}'s checkpoint", path),
+    }
+    sample.verify(resolver)?;
+    println!("✅ Checkpoint signed by {}: {} transactions, root {}", checkpoint.operator_did, checkpoint.tree_size, checkpoint.root);
+    println!("✅ {} sampled transactions verify and are proven against the root", sample.items.len());
+
+    match (journal, sample.method) {
+        (Some(journal), _) => {
+            sample.check_selection(&read_journal(journal)?)?;
+            println!("✅ {} and the seed pick exactly these transactions", journal);
+        }
+        (None, SampleMethod::Random) => println!("✅ The seed picks exactly these transactions"),
+        (None, SampleMethod::MonetaryUnit) => {
+            println!("⚠️  A monetary-unit selection depends on every amount: give --journal to draw it again");
+        }
+    }
+    println!("\n🎉 **SAMPLE VERIFIED**");
+    Ok(())
+}
@@ -0,0 +1,519 @@
+//! HTTP Verification Server
+//! `tlc serve` exposes the verifier to services written in other languages:
+//!
+//!   POST /verify              verdict for a signed transaction (nothing stored)
+//!   POST /transactions        verify, then append to the ledger (--store)
+//!   GET  /transactions/<hash> a stored transaction by payload hash
+//!
+//! Requests are answered one at a time, which keeps the ledger's appends in
+//! order without any locking. Each connection gets its own thread for the
+//! TLS handshake (if any) and for reading the request and writing the reply,
+//! so a slow client holds up nobody else, and must send its whole request
+//! within a fixed deadline.
+//!
+//! With `--auth` every request needs an API key or token with the route's
+//! scope (see auth.rs). With `--tls-cert` it serves HTTPS, and with `--client-ca` mutual TLS
+//! (see tls.rs): each connection is then authenticated as a DID, and
+//! POST /transactions only accepts transactions whose author it is.
+//!
+//! With `--tenants FILE` it serves several isolated ledgers instead, each
+//! under /tenants/<name>/ with its own credentials and posting rules (see
+//! tenants.rs).
+
+use serde::Serialize;
+use serde_json::json;
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+use rustls::{ServerConnection, StreamOwned};
+use true_ledger_core::did::DidResolver;
+use std::collections::BTreeMap;
+use true_ledger_core::storage;
+use true_ledger_core::{LedgerError, SignedTransaction};
+
+use crate::auth::{Auth, Scope};
+use crate::tenants::{load_tenants, verdict, Tenant};
+use crate::tls::{TlsArgs, TlsServer};
+
+/// Request bodies larger than this are rejected
+const MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+/// Request heads (request line and headers) larger than this are rejected
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+
+/// How long a client has, in all, to send its request (and over TLS, to complete the handshake)
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+
+/// How long it has to take the reply
+const REPLY_DEADLINE: Duration = Duration::from_secs(10);
+
+/// Most connections served at once; later ones wait to be accepted
+const MAX_CONNECTIONS: usize = 64;
+
+/// The reply to a ledger route when the server has no ledger
+const NO_LEDGER: &str = "No ledger configured (start the server with --store)";
+
+/// A JSON response: status code plus body
+pub type Reply = (u16, serde_json::Value);
+
+pub fn error(status: u16, message: impl Into<String>) -> Reply {
+    (status, json!({ "error": message.into() }))
+}
+
+pub fn to_value<T: Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_else(|e| json!({ "error": e.to_string() }))
+}
+
+/// Who is connecting, as far as the transport can tell
+#[derive(Clone)]
+pub enum Peer {
+    Anonymous,       // Plain HTTP, or TLS without client certificates
+    Did(String),     // Mutual TLS: the DID the client certificate is bound to
+    Unbound(String), // Mutual TLS, but the certificate names no DID (why not)
+}
+
+/// Reads a request body of at most MAX_BODY_BYTES
+pub fn read_body(reader: impl Read) -> Result<String, Reply> {
+    let mut body = String::new();
+    reader.take(MAX_BODY_BYTES + 1).read_to_string(&mut body)
+        .map_err(|e| error(400, format!("Could not read request body: {}", e)))?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(error(413, format!("Request body exceeds {} bytes", MAX_BODY_BYTES)));
+    }
+    Ok(body)
+}
+
+/// Refuses a submission whose author is not the DID the client's
+/// certificate is bound to, under mutual TLS
+pub fn check_author(peer: &Peer, signed_tx: &SignedTransaction) -> Result<(), Reply> {
+    match peer {
+        Peer::Anonymous => Ok(()),
+        Peer::Did(did) if *did == signed_tx.payload.author_did => Ok(()),
+        Peer::Did(did) => Err(error(403, format!("Connected as {}, but the transaction's author is {}", did, signed_tx.payload.author_did))),
+        Peer::Unbound(why) => Err(error(403, why.clone())),
+    }
+}
+
+/// Parses a signed transaction from a request body, with the same schema
+/// and version checks as a file read from disk
+pub fn parse_signed(body: &str) -> Result<SignedTransaction, Reply> {
+    SignedTransaction::from_json(body).map_err(|e| error(400, e))
+}
+
+/// Routes one request to a tenant; `url` is the path within the tenant
+fn handle(method: &str, url: &str, body: &str, peer: &Peer, tenant: &mut Tenant, resolver: &dyn DidResolver) -> Reply {
+    match (method, url) {
+        ("POST", "/verify") => match parse_signed(body) {
+            Ok(signed_tx) => {
+                let verdict = verdict(&signed_tx, tenant, resolver);
+                (if verdict.valid { 200 } else { 422 }, to_value(&verdict))
+            }
+            Err(reply) => reply,
+        },
+        ("POST", "/transactions") => {
+            if tenant.ledger.is_none() {
+                return error(404, NO_LEDGER);
+            }
+            let signed_tx = match parse_signed(body) {
+                Ok(signed_tx) => signed_tx,
+                Err(reply) => return reply,
+            };
+            if let Err(reply) = check_author(peer, &signed_tx) {
+                return reply;
+            }
+            let verdict = verdict(&signed_tx, tenant, resolver);
+            if !verdict.valid {
+                return (422, to_value(&verdict));
+            }
+            let Some(ledger) = &mut tenant.ledger else { return error(404, NO_LEDGER) };
+            match ledger.get_by_hash(&verdict.hash) {
+                Ok(Some(_)) => return error(409, format!("Transaction {} is already stored", verdict.hash)),
+                Ok(None) => {}
+                Err(e) => return error(500, e),
+            }
+            match ledger.append(&signed_tx) {
+                Ok(_) => (201, to_value(&verdict)),
+                // Not linked to the head, or a sequence out of turn
+                Err(e @ LedgerError::Chain(_)) => error(409, e),
+                Err(e) => error(500, e),
+            }
+        }
+        ("GET", path) if path.starts_with("/transactions/") => {
+            let Some(ledger) = &tenant.ledger else {
+                return error(404, NO_LEDGER);
+            };
+            let hash = &path["/transactions/".len()..];
+            match ledger.get_by_hash(hash) {
+                Ok(Some(signed_tx)) => (200, to_value(&signed_tx)),
+                Ok(None) => error(404, format!("No transaction {}", hash)),
+                Err(e) => error(500, e),
+            }
+        }
+        (_, "/verify") | (_, "/transactions") => error(405, "Method not allowed"),
+        _ => error(404, format!("No route for {}", url)),
+    }
+}
+
+/// A request read in full, from either transport
+pub struct Incoming {
+    pub method: String,
+    pub url: String,
+    pub authorization: Option<String>, // The Authorization header
+    pub body: Result<String, Reply>,   // Or the reply refusing it
+}
+
+/// The ledgers a server serves
+enum Tenancy {
+    Single(Box<Tenant>),              // --store and --auth: routes at the root
+    Multi(BTreeMap<String, Tenant>), // --tenants: routes under /tenants/<name>
+}
+
+/// What every request is served with
+struct Context<'a> {
+    tenancy: Tenancy,
+    resolver: &'a dyn DidResolver,
+}
+
+impl Context<'_> {
+    /// The tenant a path is for, and the path within it
+    fn route<'u>(&mut self, path: &'u str) -> Result<(&mut Tenant, &'u str), Reply> {
+        match &mut self.tenancy {
+            Tenancy::Single(tenant) => Ok((tenant, path)),
+            Tenancy::Multi(tenants) => {
+                let rest = path.strip_prefix("/tenants/").ok_or_else(|| error(404, format!("No route for {} (routes are under /tenants/<name>/)", path)))?;
+                let (name, rest) = rest.find('/').map_or((rest, ""), |slash| rest.split_at(slash));
+                let tenant = tenants.get_mut(name).ok_or_else(|| error(404, format!("No tenant '{}'", name)))?;
+                Ok((tenant, rest))
+            }
+        }
+    }
+
+    /// Finds the tenant, authenticates with its credentials, then routes one
+    /// request; also returns who made it, if known
+    fn respond(&mut self, incoming: &Incoming, peer: &Peer) -> (Reply, Option<String>) {
+        let (resolver, multi) = (self.resolver, matches!(self.tenancy, Tenancy::Multi(_)));
+        let (tenant, path) = match self.route(incoming.url.split('?').next().unwrap_or("")) {
+            Ok(routed) => routed,
+            Err(reply) => return (reply, None),
+        };
+        let mut principal = None;
+        if let Some(auth) = &tenant.auth {
+            let scope = if incoming.method == "POST" && path == "/transactions" { Scope::Submit } else { Scope::Read };
+            match auth.authorize(incoming.authorization.as_deref(), scope) {
+                Ok(authorized) if multi => principal = Some(format!("{}@{}", authorized.name, tenant.name)),
+                Ok(authorized) => principal = Some(authorized.name),
+                Err(refusal) => return (error(refusal.status, refusal.message), None),
+            }
+        }
+        let reply = match &incoming.body {
+            Ok(body) => handle(&incoming.method, path, body, peer, tenant, resolver),
+            Err(reply) => reply.clone(),
+        };
+        (reply, principal)
+    }
+}
+
+/// Where `tlc serve` keeps its ledgers
+pub enum Ledgers<'a> {
+    Single { store: Option<&'a str>, auth: Option<&'a str> },
+    Tenants(&'a str),
+}
+
+/// `tlc serve [--listen ADDR] [--store LEDGER] [--auth FILE] [--tenants FILE] [--tls-cert FILE --tls-key FILE [--client-ca FILE]]`: serves until killed
+pub fn serve(listen: &str, ledgers: Ledgers, tls: &TlsArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let tls = TlsServer::from_args(tls)?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    let tenancy = match ledgers {
+        Ledgers::Single { store, auth } => {
+            let tenant = Tenant {
+                name: "default".to_string(),
+                ledger: store.map(storage::open).transpose()?,
+                auth: auth.map(Auth::load).transpose()?,
+                rules: None,
+                workflow: None,
+                delegation: None,
+            };
+            println!("🌐 Verifier listening on {}://{}", scheme, listen);
+            println!("   POST /verify");
+            match store {
+                Some(db) => println!("   POST /transactions, GET /transactions/<hash> (ledger: {})", db),
+                None => println!("   (no --store: /transactions is disabled)"),
+            }
+            match auth {
+                Some(path) => println!("   Requests need a bearer API key or JWT with the route's scope (see {})", path),
+                None => println!("   ⚠️  No --auth: anyone who can connect may use every route"),
+            }
+            Tenancy::Single(Box::new(tenant))
+        }
+        Ledgers::Tenants(path) => {
+            let tenants = load_tenants(path)?;
+            println!("🌐 Verifier listening on {}://{}", scheme, listen);
+            println!("   POST /tenants/<name>/verify, POST /tenants/<name>/transactions, GET /tenants/<name>/transactions/<hash>");
+            println!("   {} tenant(s) from {}, each with its own ledger, credentials, rules and authors:", tenants.len(), path);
+            for tenant in tenants.values() {
+                println!("     {}", tenant.name);
+            }
+            Tenancy::Multi(tenants)
+        }
+    };
+    let mut context = Context { tenancy, resolver };
+    match &tls {
+        Some(tls) if tls.mutual => println!("   Mutual TLS: clients are authenticated by certificate, and submit only as their own DID"),
+        Some(_) => println!("   TLS without client certificates: submissions are not authenticated by DID"),
+        None => {}
+    }
+
+    let listener = TcpListener::bind(listen).map_err(|e| format!("Could not listen on {}: {}", listen, e))?;
+    serve_connections(listener, tls, |incoming, peer| context.respond(incoming, peer))
+}
+
+/// A request read by a connection's thread, for the main thread to answer
+struct Job {
+    incoming: Incoming,
+    peer: Peer,
+    reply: mpsc::Sender<(Reply, Option<String>)>,
+}
+
+/// Accepts connections, plain or over TLS, each on its own thread, and
+/// answers their requests one at a time on this one with `respond`, which
+/// also returns who made the request, if known
+pub fn serve_connections(listener: TcpListener, tls: Option<TlsServer>, mut respond: impl FnMut(&Incoming, &Peer) -> (Reply, Option<String>)) -> Result<(), String> {
+    let tls = tls.map(Arc::new);
+    let (jobs, queue) = mpsc::channel::<Job>();
+
+    // One permit per connection being served
+    let (release, permits) = mpsc::sync_channel::<()>(MAX_CONNECTIONS);
+    for _ in 0..MAX_CONNECTIONS {
+        release.send(()).expect("the channel has room for every permit");
+    }
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("⚠️  {}", e);
+                    continue;
+                }
+            };
+            if permits.recv().is_err() {
+                return;
+            }
+            let (tls, jobs, release) = (tls.clone(), jobs.clone(), release.clone());
+            thread::spawn(move || {
+                if let Err(e) = serve_connection(stream, tls.as_deref(), &jobs) {
+                    eprintln!("⚠️  {}", e);
+                }
+                let _ = release.send(());
+            });
+        }
+    });
+
+    for job in queue {
+        let _ = job.reply.send(respond(&job.incoming, &job.peer));
+    }
+    Err("The listener stopped".to_string())
+}
+
+/// A TCP stream that fails reads and writes once `until` has passed, however
+/// slowly the client trickles its bytes
+struct Deadline {
+    tcp: TcpStream,
+    until: Instant,
+}
+
+fn timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "client too slow; connection closed at the deadline")
+}
+
+impl Deadline {
+    fn remaining(&self) -> io::Result<Duration> {
+        let remaining = self.until.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(timed_out());
+        }
+        Ok(remaining)
+    }
+}
+
+/// A socket timeout is reported as WouldBlock on Unix and TimedOut on Windows
+fn or_timed_out(error: io::Error) -> io::Error {
+    match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => timed_out(),
+        _ => error,
+    }
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.tcp.set_read_timeout(Some(self.remaining()?))?;
+        self.tcp.read(buf).map_err(or_timed_out)
+    }
+}
+
+impl Write for Deadline {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.tcp.set_write_timeout(Some(self.remaining()?))?;
+        self.tcp.write(buf).map_err(or_timed_out)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.tcp.flush()
+    }
+}
+
+/// Serves one request on a new connection, then closes it
+fn serve_connection(tcp: TcpStream, tls: Option<&TlsServer>, jobs: &mpsc::Sender<Job>) -> Result<(), String> {
+    let peer_addr = tcp.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+    let mut deadline = Deadline { tcp, until: Instant::now() + REQUEST_DEADLINE };
+    let Some(tls) = tls else {
+        let incoming = read_request(&mut deadline).map_err(|e| format!("{}: {}", peer_addr, e))?;
+        let response = answer(incoming, Peer::Anonymous, peer_addr, jobs)?;
+        deadline.until = Instant::now() + REPLY_DEADLINE;
+        deadline.write_all(response.as_bytes()).map_err(|e| format!("Could not send response: {}", e))?;
+        return deadline.flush().map_err(|e| format!("Could not send response: {}", e));
+    };
+    let connection = ServerConnection::new(Arc::clone(&tls.config)).map_err(|e| e.to_string())?;
+    let mut stream = StreamOwned::new(connection, deadline);
+
+    // The handshake happens on the first read, so a client without a
+    // trusted certificate fails here
+    let incoming = read_request(&mut stream).map_err(|e| format!("{}: {}", peer_addr, e))?;
+    let peer = match stream.conn.peer_certificates().and_then(|certs| certs.first()) {
+        Some(cert) => match tls.client_did(cert) {
+            Ok(did) => Peer::Did(did),
+            Err(why) => Peer::Unbound(why),
+        },
+        None => Peer::Anonymous,
+    };
+    let response = answer(incoming, peer, peer_addr, jobs)?;
+    stream.sock.until = Instant::now() + REPLY_DEADLINE;
+    stream.write_all(response.as_bytes()).map_err(|e| format!("Could not send response: {}", e))?;
+    stream.conn.send_close_notify();
+    stream.flush().map_err(|e| format!("Could not send response: {}", e))
+}
+
+/// Has the main thread answer a request, logs it, and returns the HTTP response
+fn answer(incoming: Incoming, peer: Peer, peer_addr: String, jobs: &mpsc::Sender<Job>) -> Result<String, String> {
+    let (reply, replied) = mpsc::channel();
+    let (method, url) = (incoming.method.clone(), incoming.url.clone());
+    jobs.send(Job { incoming, peer: peer.clone(), reply }).map_err(|_| "The server is shutting down".to_string())?;
+    let ((status, reply), principal) = replied.recv().map_err(|_| "The server is shutting down".to_string())?;
+    let who = match (&peer, &principal) {
+        (Peer::Did(did), Some(name)) => format!("{}, {}", did, name),
+        (Peer::Did(did), None) => did.clone(),
+        (_, Some(name)) => name.clone(),
+        (_, None) => peer_addr,
+    };
+    println!("{} {} ({}) -> {}", method, url, who, status);
+
+    let reply = reply.to_string();
+    let challenge = if status == 401 { "WWW-Authenticate: Bearer\r\n" } else { "" };
+    Ok(format!("HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n{}",
+        status, reason(status), reply.len(), challenge, reply))
+}
+
+/// Reads one HTTP/1.1 request
+fn read_request(stream: &mut impl Read) -> Result<Incoming, String> {
+    let mut head = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        if let Some(end) = head.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if head.len() > MAX_HEAD_BYTES {
+            return Err(format!("Request head exceeds {} bytes", MAX_HEAD_BYTES));
+        }
+        let read = stream.read(&mut chunk).map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Err("Connection closed before a complete request".to_string());
+        }
+        head.extend_from_slice(&chunk[..read]);
+    };
+
+    let mut headers = [httparse::EMPTY_HEADER; 32];
+    let mut request = httparse::Request::new(&mut headers);
+    request.parse(&head[..head_end]).map_err(|e| format!("Malformed request: {}", e))?;
+    let header = |name: &str| request.headers.iter()
+        .find(|header| header.name.eq_ignore_ascii_case(name))
+        .map(|header| String::from_utf8_lossy(header.value).trim().to_string());
+    let content_length = header("Content-Length").map(|value| value.parse::<u64>().ok());
+
+    let body = match content_length {
+        None => Ok(String::new()),
+        Some(None) => Err(error(400, "Invalid Content-Length")),
+        Some(Some(length)) if length > MAX_BODY_BYTES => Err(error(413, format!("Request body exceeds {} bytes", MAX_BODY_BYTES))),
+        Some(Some(length)) => {
+            let received = &head[head_end..];
+            let received = &received[..received.len().min(length as usize)];
+            let rest = length - received.len() as u64;
+            read_body(received.chain(stream.take(rest)))
+        }
+    };
+    Ok(Incoming {
+        method: request.method.unwrap_or_default().to_string(),
+        url: request.path.unwrap_or_default().to_string(),
+        authorization: header("Authorization"),
+        body,
+    })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use true_ledger_core::did::DidKeyResolver;
+
+    /// Serves a ledgerless default tenant over plain HTTP, returning its address
+    fn plain_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let tenant = Tenant { name: "default".to_string(), ledger: None, auth: None, rules: None, workflow: None, delegation: None };
+            let mut context = Context { tenancy: Tenancy::Single(Box::new(tenant)), resolver: &DidKeyResolver };
+            let _ = serve_connections(listener, None, |incoming, peer| context.respond(incoming, peer));
+        });
+        address
+    }
+
+    #[test]
+    fn a_silent_plain_client_holds_up_nobody() {
+        let address = plain_server();
+        let _silent = TcpStream::connect(&address).unwrap();
+
+        let mut client = TcpStream::connect(&address).unwrap();
+        client.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        client.write_all(b"GET /transactions/abc HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"), "{}", response);
+        assert!(response.contains(NO_LEDGER), "{}", response);
+    }
+
+    #[test]
+    fn plain_requests_are_read_with_limits() {
+        let too_big = format!("POST /verify HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_BYTES + 1);
+        let incoming = read_request(&mut too_big.as_bytes()).unwrap();
+        assert_eq!(incoming.body.err().unwrap().0, 413);
+
+        let head = format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n", "x".repeat(MAX_HEAD_BYTES + 1));
+        assert!(read_request(&mut head.as_bytes()).err().unwrap().contains("exceeds"));
+        assert!(read_request(&mut &b"GET / HTTP/1.1\r\n"[..]).err().unwrap().contains("closed"));
+    }
+}
@@ -0,0 +1,566 @@
+//! Identity & Signing
+//! `tlc keygen`, `tlc keystore`, `tlc sign` and `tlc genesis`, plus the signer
+//! flags shared by every command that signs something.
+
+use clap::{Args, Subcommand, ValueEnum};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use true_ledger_core::capability::{load_chain, DelegatedSigner};
+use true_ledger_core::cose::sign_cose;
+use true_ledger_core::data_integrity;
+use true_ledger_core::jws::sign_jws;
+use true_ledger_core::did::{did_to_verifying_key, DidResolver};
+use true_ledger_core::hashing::HashAlg;
+use true_ledger_core::chain;
+use true_ledger_core::keys::SigAlg;
+use true_ledger_core::keystore::Keystore;
+use true_ledger_core::multisig::cosign;
+#[cfg(feature = "openpgp")]
+use true_ledger_core::openpgp;
+#[cfg(feature = "sigstore")]
+use crate::sigstore::{sign_keyless_file, SigstoreArgs};
+use true_ledger_core::private_memo::PrivateMemo;
+use true_ledger_core::ssh_agent::SshAgentSigner;
+use true_ledger_core::storage;
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::{Account, JournalEntry, SignedTransaction, Transaction, TransactionSigner};
+use zeroize::Zeroizing;
+
+use crate::memo::{private_memo_for, PrivateMemoArgs};
+use crate::verify::load_signed;
+
+/// Which keystore directory to use
+#[derive(Args, Debug)]
+pub struct KeystoreArgs {
+    /// Keystore directory (default: $TLC_KEYSTORE, then ~/.tlc/keystore)
+    #[arg(long, value_name = "DIR", global = true)]
+    pub keystore: Option<String>,
+}
+
+impl KeystoreArgs {
+    pub fn open(&self) -> Keystore {
+        match &self.keystore {
+            Some(dir) => Keystore::open(dir),
+            None => Keystore::open(Keystore::default_dir()),
+        }
+    }
+}
+
+/// Where the signing key comes from. With none of --identity, --keychain or
+/// --ssh-agent a throwaway key is generated for this run only.
+#[derive(Args, Debug)]
+pub struct SignerArgs {
+    /// Sign with this named identity from the keystore (see `tlc keygen`)
+    #[arg(long, value_name = "NAME", conflicts_with = "ssh_agent")]
+    pub identity: Option<String>,
+
+    #[command(flatten)]
+    pub keystore: KeystoreArgs,
+
+    /// Sign with this named identity from the OS keychain (see `tlc keychain`)
+    #[cfg(feature = "keychain")]
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["identity", "ssh_agent"])]
+    pub keychain: Option<String>,
+
+    /// Sign with an Ed25519 key held by the running ssh-agent
+    #[arg(long)]
+    pub ssh_agent: bool,
+
+    /// Pick the ssh-agent key with this comment (default: the first Ed25519 key)
+    #[arg(long, value_name = "COMMENT", requires = "ssh_agent")]
+    pub ssh_key: Option<String>,
+
+    /// Capability chain you hold (see `tlc delegate`), attached to every transaction you sign
+    #[arg(long, value_name = "FILE")]
+    pub capability: Option<String>,
+}
+
+/// Generates a fresh identity and announces its DID
+fn create_account() -> Account {
+    let account = Account::generate();
+
+    println!("✅ New Account Created!");
+    println!("   DID: {}", account.did);
+
+    account
+}
+
+/// The keystore passphrase: `$TLC_PASSPHRASE` if set (for scripts), otherwise
+/// read from the terminal without echo. Wiped from memory when dropped.
+fn read_passphrase(prompt: &str) -> Result<Zeroizing<String>, String> {
+    if let Ok(passphrase) = std::env::var("TLC_PASSPHRASE") {
+        return Ok(Zeroizing::new(passphrase));
+    }
+    rpassword::prompt_password(prompt).map(Zeroizing::new).map_err(|e| format!("Could not read passphrase: {}", e))
+}
+
+/// Unlocks a named identity, prompting for its passphrase
+pub fn unlock_identity(keystore: &Keystore, name: &str) -> Result<Account, String> {
+    let key_file = keystore.get(name)?; // Fail on a missing name before prompting
+    let passphrase = read_passphrase(&format!("Passphrase for '{}': ", name))?;
+    Ok(key_file.unlock(&passphrase)?)
+}
+
+/// Picks the signing backend: a keystore or OS keychain identity, an
+/// ssh-agent key or a new Account, attaching the --capability chain to what it signs
+pub fn signer_from_args(args: &SignerArgs) -> Result<Box<dyn TransactionSigner>, String> {
+    let signer = key_from_args(args)?;
+    match &args.capability {
+        Some(path) => {
+            let chain = load_chain(path)?;
+            println!("   Posting under {} capability(ies) from {}", chain.len(), path);
+            Ok(Box::new(DelegatedSigner::new(signer, chain)?))
+        }
+        None => Ok(signer),
+    }
+}
+
+fn key_from_args(args: &SignerArgs) -> Result<Box<dyn TransactionSigner>, String> {
+    #[cfg(feature = "keychain")]
+    if let Some(name) = &args.keychain {
+        let account = true_ledger_core::keychain::load(name)?;
+        println!("✅ Using OS keychain identity '{}'", name);
+        println!("   DID: {}", account.did);
+        return Ok(Box::new(account));
+    }
+    if let Some(name) = &args.identity {
+        let account = unlock_identity(&args.keystore.open(), name)?;
+        println!("✅ Unlocked identity '{}'", name);
+        println!("   DID: {}", account.did);
+        Ok(Box::new(account))
+    } else if args.ssh_agent {
+        let signer = SshAgentSigner::connect(args.ssh_key.as_deref())?;
+        println!("✅ Using ssh-agent key");
+        println!("   DID: {}", signer.did());
+        Ok(Box::new(signer))
+    } else {
+        println!("⚠️  No --identity given: signing with a throwaway identity.");
+        Ok(Box::new(create_account()))
+    }
+}
+
+/// Writes any serializable value as pretty JSON
+pub fn write_json<T: serde::Serialize>(value: &T, path: &str) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize {}: {}", path, e))?;
+    fs::write(path, data).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Asks for (and confirms) the passphrase a new identity will be sealed under
+fn new_passphrase(name: &str) -> Result<Zeroizing<String>, String> {
+    let passphrase = read_passphrase(&format!("New passphrase for '{}': ", name))?;
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".to_string());
+    }
+    if std::env::var("TLC_PASSPHRASE").is_err()
+        && read_passphrase("Repeat passphrase: ")? != passphrase {
+        return Err("Passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
+
+/// Signature suite for a new identity
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum KeyAlg {
+    Ed25519,
+    Secp256k1,
+}
+
+/// How a signed transaction is written out
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvelopeFormat {
+    /// Our own format: the payload plus a multibase signature (what ledgers store)
+    Signature,
+    /// A W3C Verifiable-Credential-style document with an eddsa-jcs-2022 proof (Ed25519 only)
+    DataIntegrity,
+    /// JWS compact serialization (EdDSA or ES256K), for JWT middleware
+    Jws,
+    /// Binary COSE_Sign1 over a deterministic CBOR payload, for constrained devices
+    Cose,
+    /// An armored OpenPGP signature by a GPG-managed Ed25519 key (needs --openpgp-key)
+    #[cfg(feature = "openpgp")]
+    #[value(name = "openpgp")]
+    OpenPgp,
+    /// Keyless: a short-lived Fulcio certificate for an SSO identity, logged in Rekor (experimental)
+    #[cfg(feature = "sigstore")]
+    Sigstore,
+}
+
+impl From<KeyAlg> for SigAlg {
+    fn from(alg: KeyAlg) -> Self {
+        match alg {
+            KeyAlg::Ed25519 => SigAlg::Ed25519,
+            KeyAlg::Secp256k1 => SigAlg::Secp256k1,
+        }
+    }
+}
+
+/// `tlc keygen <name> [--alg A] [--mnemonic]`: creates an identity and seals
+/// it in the keystore, optionally backed by a 24-word recovery phrase
+pub fn keygen(name: &str, alg: KeyAlg, with_mnemonic: bool, did_document: Option<&str>, keystore_args: &KeystoreArgs) -> Result<(), String> {
+    let keystore = keystore_args.open();
+    if keystore.get(name).is_ok() {
+        return Err(format!("An identity named '{}' already exists", name));
+    }
+    if with_mnemonic && !matches!(alg, KeyAlg::Ed25519) {
+        return Err("Recovery phrases are only supported for Ed25519 identities".to_string());
+    }
+    let passphrase = new_passphrase(name)?;
+
+    let account = if with_mnemonic {
+        let (account, phrase) = Account::generate_with_mnemonic();
+        keystore.insert(name, &account, &passphrase)?;
+        println!("📜 Recovery phrase (write it down; it is shown only once):");
+        println!("\n   {}\n", phrase.as_str());
+        println!("   Anyone with these words can sign as this identity.");
+        println!("   `tlc recover {}` rebuilds it from the phrase.", name);
+        account
+    } else {
+        keystore.keygen(name, alg.into(), &passphrase)?
+    };
+    println!("✅ New Account Created!");
+    println!("   DID: {}", account.did);
+    if let Some(path) = did_document {
+        write_json(&account.did_document(), path)?;
+        println!("   DID Document: {}", path);
+    }
+    println!("\n🔑 Identity '{}' sealed in {}", name, keystore.dir().display());
+    Ok(())
+}
+
+/// `tlc resolve <did>`: prints (or saves) the DID's W3C DID Document
+pub fn resolve(did: &str, out_path: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let document = resolver.resolve_document(did)?;
+    match out_path {
+        Some(out_path) => {
+            write_json(&document, out_path)?;
+            println!("💾 DID Document for {} saved to {}", did, out_path);
+        }
+        None => println!("{}", serde_json::to_string_pretty(&document)
+            .map_err(|e| format!("Failed to serialize DID Document: {}", e))?),
+    }
+    Ok(())
+}
+
+/// `tlc recover <name>`: rebuilds an identity from its recovery phrase
+/// (`$TLC_MNEMONIC`, or typed without echo) and seals it in the keystore
+pub fn recover(name: &str, keystore_args: &KeystoreArgs) -> Result<(), String> {
+    let keystore = keystore_args.open();
+    if keystore.get(name).is_ok() {
+        return Err(format!("An identity named '{}' already exists", name));
+    }
+    let phrase = match std::env::var("TLC_MNEMONIC") {
+        Ok(phrase) => Zeroizing::new(phrase),
+        Err(_) => Zeroizing::new(rpassword::prompt_password("Recovery phrase (24 words): ")
+            .map_err(|e| format!("Could not read recovery phrase: {}", e))?),
+    };
+    let account = Account::from_mnemonic(phrase.trim())?;
+    println!("✅ Account Recovered!");
+    println!("   DID: {}", account.did);
+
+    let passphrase = new_passphrase(name)?;
+    keystore.insert(name, &account, &passphrase)?;
+    println!("\n🔑 Identity '{}' sealed in {}", name, keystore.dir().display());
+    Ok(())
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeystoreCommand {
+    /// List the identities in the keystore (no passphrase needed)
+    List,
+
+    /// Check a passphrase by unlocking an identity
+    Unlock {
+        /// Identity name
+        name: String,
+    },
+}
+
+/// `tlc keystore list|unlock`
+pub fn run_keystore(command: &KeystoreCommand, keystore_args: &KeystoreArgs) -> Result<(), String> {
+    let keystore = keystore_args.open();
+    match command {
+        KeystoreCommand::List => {
+            let key_files = keystore.list()?;
+            println!("🔑 {} identit{} in {}", key_files.len(),
+                if key_files.len() == 1 { "y" } else { "ies" }, keystore.dir().display());
+            for key_file in key_files {
+                println!("   {:<20} {:<10} {}", key_file.name, format!("{:?}", key_file.sig_alg), key_file.did);
+            }
+        }
+        KeystoreCommand::Unlock { name } => {
+            let account = unlock_identity(&keystore, name)?;
+            println!("✅ Unlocked identity '{}'", name);
+            println!("   DID: {}", account.did);
+        }
+    }
+    Ok(())
+}
+
+/// Options for `tlc sign`
+#[derive(Args, Debug)]
+pub struct SignArgs {
+    /// Unsigned transaction JSON (author_did may be left out)
+    pub tx_path: String,
+
+    /// Envelope to write the signed transaction in
+    #[arg(long, value_enum, default_value = "signature")]
+    pub envelope: EnvelopeFormat,
+
+    /// Hash to sign over: sha2-256, sha3-256 or blake3 (default: the one the
+    /// transaction names, else sha2-256)
+    #[arg(long, value_name = "ALG")]
+    pub hash_alg: Option<HashAlg>,
+
+    /// Where to write the signed transaction (default: signed_transaction.json,
+    /// or no file when --store is given)
+    #[arg(long = "out", value_name = "FILE")]
+    pub out_path: Option<String>,
+
+    /// Chain the transaction onto this ledger (database or .ndjson journal) and store it there
+    #[arg(long, value_name = "DB")]
+    pub store: Option<String>,
+
+    #[command(flatten)]
+    pub private_memo: PrivateMemoArgs,
+
+    /// OpenPGP secret key file to sign the openpgp envelope with
+    /// (`gpg --export-secret-keys --armor`); its passphrase is asked for if set
+    #[cfg(feature = "openpgp")]
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["identity", "ssh_agent"])]
+    pub openpgp_key: Option<String>,
+
+    #[cfg(feature = "sigstore")]
+    #[command(flatten)]
+    pub sigstore: SigstoreArgs,
+
+    #[command(flatten)]
+    pub signer: SignerArgs,
+}
+
+/// `tlc sign <tx.json> [--out FILE] [--store DB]`: signs an unsigned transaction.
+/// An empty `author_did` is filled in with the signer's DID. Any other value
+/// must be that DID, or a did:web whose document lists the signer's key,
+/// since the signature is checked against the author's key.
+/// With a ledger database, a transaction without a height is linked to the
+/// ledger's head before signing and stored once signed.
+/// With `--private-memo`, the payload signs only a commitment to it (see memo.rs).
+pub fn sign_file(args: &SignArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let SignArgs { tx_path, envelope, hash_alg, out_path, store, private_memo: private_memo_args, signer: signer_args, .. } = args;
+    let (envelope, hash_alg, out_path, store) = (*envelope, *hash_alg, out_path.as_deref(), store.as_deref());
+    if envelope != EnvelopeFormat::Signature && store.is_some() {
+        return Err("Ledgers only store the signature envelope: drop --store or --envelope".to_string());
+    }
+    if envelope != EnvelopeFormat::Signature && private_memo_args.private_memo.is_some() {
+        return Err("Only the signature envelope carries a private memo: drop --private-memo or --envelope".to_string());
+    }
+    let data = fs::read_to_string(tx_path).map_err(|e| format!("Could not read {}: {}", tx_path, e))?;
+    let mut tx: Transaction = serde_json::from_str(&data)
+        .map_err(|e| format!("Failed to parse transaction {}: {}", tx_path, e))?;
+
+    if let Some(policy) = &tx.signing_policy {
+        policy.validate()?;
+    }
+    if hash_alg.is_some() {
+        tx.hash_alg = hash_alg;
+    }
+
+    #[cfg(feature = "openpgp")]
+    match (envelope, &args.openpgp_key) {
+        (EnvelopeFormat::OpenPgp, Some(key_path)) => return sign_openpgp_file(tx, tx_path, key_path, out_path),
+        (EnvelopeFormat::OpenPgp, None) => return Err("The openpgp envelope is signed with --openpgp-key FILE".to_string()),
+        (_, Some(_)) => return Err("--openpgp-key only signs the openpgp envelope: add --envelope openpgp".to_string()),
+        _ => {}
+    }
+    #[cfg(feature = "sigstore")]
+    if envelope == EnvelopeFormat::Sigstore {
+        return sign_keyless_file(tx, tx_path, &args.sigstore, out_path);
+    }
+
+    let signer = signer_from_args(signer_args)?;
+    if tx.author_did.is_empty() {
+        tx.author_did = signer.did().to_string();
+    } else if tx.author_did != signer.did() {
+        let author_key = resolver.resolve_public_key(&tx.author_did)?;
+        if author_key != did_to_verifying_key(signer.did())? {
+            return Err(format!("{} names author {} but the signing key is {}", tx_path, tx.author_did, signer.did()));
+        }
+    }
+
+    let private_memo = private_memo_for(&mut tx, private_memo_args)?;
+
+    println!("\n📝 Signing {}...", tx_path);
+    match envelope {
+        EnvelopeFormat::Signature => {
+            let out_path = out_path.or(store.xor(Some("signed_transaction.json")));
+            sign_and_record_with(tx, private_memo, signer.as_ref(), store, out_path, resolver)?;
+        }
+        EnvelopeFormat::DataIntegrity => {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+            let credential = data_integrity::issue(tx, signer.as_ref(), now)?;
+            let out_path = out_path.unwrap_or("signed_transaction.json");
+            write_json(&credential, out_path)?;
+            println!("\n💾 Signed credential ({}) saved to:", data_integrity::CRYPTOSUITE);
+            println!("   {}", out_path);
+        }
+        EnvelopeFormat::Jws => {
+            let jws = sign_jws(tx, signer.as_ref())?;
+            let out_path = out_path.unwrap_or("signed_transaction.jws");
+            fs::write(out_path, format!("{}\n", jws.compact)).map_err(|e| format!("Failed to write {}: {}", out_path, e))?;
+            println!("\n💾 Signed JWS ({}) saved to:", jws.header.alg);
+            println!("   {}", out_path);
+        }
+        EnvelopeFormat::Cose => {
+            let cose = sign_cose(tx, signer.as_ref())?;
+            let out_path = out_path.unwrap_or("signed_transaction.cose");
+            fs::write(out_path, &cose.bytes).map_err(|e| format!("Failed to write {}: {}", out_path, e))?;
+            println!("\n💾 Signed COSE_Sign1 ({} bytes) saved to:", cose.bytes.len());
+            println!("   {}", out_path);
+        }
+        #[cfg(feature = "openpgp")]
+        EnvelopeFormat::OpenPgp => unreachable!("signed above with --openpgp-key"),
+        #[cfg(feature = "sigstore")]
+        EnvelopeFormat::Sigstore => unreachable!("signed above, keyless"),
+    }
+    Ok(())
+}
+
+/// `tlc sign --envelope openpgp --openpgp-key FILE`: the author is the
+/// did:key of the file's Ed25519 signing key (filled in when left empty)
+#[cfg(feature = "openpgp")]
+fn sign_openpgp_file(mut tx: Transaction, tx_path: &str, key_path: &str, out_path: Option<&str>) -> Result<(), String> {
+    let data = fs::read(key_path).map_err(|e| format!("Could not read {}: {}", key_path, e))?;
+    let tsk = openpgp::load_tsk(&data)?;
+    let (key, did) = openpgp::signing_key(&tsk)?;
+    if tx.author_did.is_empty() {
+        tx.author_did = did.clone();
+    }
+    println!("✅ Using OpenPGP key {}", key.fingerprint());
+    println!("   DID: {}", did);
+    let password = match openpgp::needs_password(&tsk)? {
+        true => Some(openpgp::Password::from(read_passphrase(&format!("Passphrase for OpenPGP key {}: ", key.fingerprint()))?.as_str())),
+        false => None,
+    };
+
+    println!("\n📝 Signing {}...", tx_path);
+    let signed = openpgp::sign_openpgp(tx, &tsk, password.as_ref())?;
+    let out_path = out_path.unwrap_or("signed_transaction.json");
+    write_json(&signed, out_path)?;
+    println!("\n💾 Signed OpenPGP transaction saved to:");
+    println!("   {}", out_path);
+    Ok(())
+}
+
+/// Signs `tx`, then records it: with a ledger, a transaction without a
+/// height or sequence number gets them from the ledger before signing and is
+/// stored once signed; with `out_path`, it is written there.
+pub fn sign_and_record(tx: Transaction, signer: &dyn TransactionSigner, store: Option<&str>, out_path: Option<&str>,
+    resolver: &dyn DidResolver) -> Result<SignedTransaction, String> {
+    sign_and_record_with(tx, None, signer, store, out_path, resolver)
+}
+
+/// [`sign_and_record`], carrying the opening of the payload's memo commitment
+/// beside the signed payload
+fn sign_and_record_with(mut tx: Transaction, private_memo: Option<PrivateMemo>, signer: &dyn TransactionSigner, store: Option<&str>,
+    out_path: Option<&str>, resolver: &dyn DidResolver) -> Result<SignedTransaction, String> {
+    let mut ledger = match store {
+        Some(db) => Some((db, storage::open(db)?)),
+        None => None,
+    };
+    if let Some((_, storage)) = &ledger {
+        if tx.height.is_none() {
+            chain::link(&mut tx, storage.head()?.as_ref().map(|head| &head.payload));
+        }
+        if tx.sequence.is_none() {
+            tx.sequence = Some(storage.next_sequence(&tx.author_did)?);
+        }
+    }
+
+    let mut signed_tx = signer.sign_transaction(tx)?;
+    signed_tx.private_memo = private_memo;
+    println!("   CID: {}", signed_tx.cid());
+
+    if let Some((db, storage)) = &mut ledger {
+        verify_with(&signed_tx, resolver)?; // The ledger only holds transactions that verify
+        let hash = storage.append(&signed_tx)?;
+        println!("\n💾 Stored in {} at height {} ({})", db, signed_tx.payload.height.unwrap_or(0), &hash[..16]);
+    }
+    if let Some(out_path) = out_path {
+        write_json(&signed_tx, out_path)?;
+        println!("\n💾 Signed transaction saved to:");
+        println!("   {}", out_path);
+    }
+    Ok(signed_tx)
+}
+
+/// `tlc cosign <signed.json> [--out FILE]`: adds the signer's approval to a
+/// transaction that needs more than one (see the payload's signing_policy)
+pub fn cosign_file(path: &str, out_path: Option<&str>, signer_args: &SignerArgs) -> Result<(), String> {
+    let mut signed_tx = load_signed(path)?;
+    let signer = signer_from_args(signer_args)?;
+
+    println!("\n✍️  Approving {}...", path);
+    cosign(signer.as_ref(), &mut signed_tx)?;
+    if let Some(policy) = &signed_tx.payload.signing_policy {
+        // The author's signature counts only if they are a listed signer
+        let author = policy.signers.contains(&signed_tx.payload.author_did) as usize;
+        println!("   Approvals: {} of {} required", author + signed_tx.cosignatures.len(), policy.threshold);
+    }
+
+    let out_path = out_path.unwrap_or(path);
+    write_json(&signed_tx, out_path)?;
+    println!("\n💾 Cosigned transaction saved to:");
+    println!("   {}", out_path);
+    Ok(())
+}
+
+/// Owner's initial capital contribution (the genesis transaction)
+pub fn genesis_transaction(author_did: &str) -> Transaction {
+    Transaction {
+        timestamp: 1730814442.into(), // Example timestamp
+        author_did: author_did.to_string(),
+        memo: "Initial capital contribution by owner.".to_string(),
+        prev_hash: None, // The genesis transaction starts the chain
+        height: Some(0),
+        sequence: Some(0), // The author's first transaction
+        canonicalization: None, // Chosen by the signer
+        signing_policy: None,
+        key_event: None,
+        hash_alg: None, // Chosen by the signer
+        version: None, // Set by the signer
+        adjusting: None,
+        reverses: None,
+        prepared_by: None,
+        memo_commitment: None,
+        policy: None,
+        entries: vec![
+            JournalEntry {
+                account_id: "10100".to_string(), // Assets:Cash (Debit)
+                debit: "10000.00".to_string(),
+                credit: "0.00".to_string(),
+                currency: None,
+            },
+            JournalEntry {
+                account_id: "30100".to_string(), // Equity:Owner's Capital (Credit)
+                debit: "0.00".to_string(),
+                credit: "10000.00".to_string(),
+                currency: None,
+            },
+        ],
+    }
+}
+
+/// `tlc genesis [--out FILE]`: signs the sample genesis transaction
+pub fn genesis(out_path: &str, signer_args: &SignerArgs) -> Result<(), String> {
+    let signer = signer_from_args(signer_args)?;
+
+    println!("\n📝 Creating Genesis Transaction...");
+    let signed_tx = signer.sign_transaction(genesis_transaction(signer.did()))?;
+    println!("\n🔐 Transaction Signed!");
+    println!("   CID: {}", signed_tx.cid());
+
+    write_json(&signed_tx, out_path)?;
+    println!("\n💾 Success! Verifiable transaction saved to:");
+    println!("   {}", out_path);
+    Ok(())
+}
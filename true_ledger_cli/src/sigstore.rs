@@ -0,0 +1,51 @@
+//! Keyless Signing (experimental)
+//! `tlc sign --envelope sigstore`: signs with a short-lived Fulcio
+//! certificate for the signer's SSO identity and logs the signature in Rekor
+//! (see the core sigstore.rs). The identity token comes from `--oidc-token`
+//! or `$SIGSTORE_ID_TOKEN`, as CI systems provide it; the result is checked
+//! against the same trust root `tlc verify` uses.
+//!
+//! Built only with the `sigstore` feature.
+
+use clap::Args;
+use true_ledger_core::sigstore::{sign_keyless, SigstoreServers, SigstoreTrust};
+use true_ledger_core::Transaction;
+
+use crate::signing::write_json;
+
+/// Options for `tlc sign --envelope sigstore`
+#[derive(Args, Debug)]
+pub struct SigstoreArgs {
+    /// OIDC identity token to get the signing certificate with
+    #[arg(long, value_name = "JWT", env = "SIGSTORE_ID_TOKEN", hide_env_values = true)]
+    pub oidc_token: Option<String>,
+
+    /// Fulcio certificate authority
+    #[arg(long, value_name = "URL", default_value = "https://fulcio.sigstore.dev")]
+    pub fulcio_url: String,
+
+    /// Rekor transparency log
+    #[arg(long, value_name = "URL", default_value = "https://rekor.sigstore.dev")]
+    pub rekor_url: String,
+}
+
+/// Signs `tx` keyless; an empty `author_did` becomes the certificate's identity
+pub fn sign_keyless_file(tx: Transaction, tx_path: &str, args: &SigstoreArgs, out_path: Option<&str>) -> Result<(), String> {
+    let id_token = args.oidc_token.as_deref()
+        .ok_or("Keyless signing needs an OIDC identity token: pass --oidc-token or set SIGSTORE_ID_TOKEN")?;
+    let trust_path = SigstoreTrust::default_path();
+    let trust = SigstoreTrust::load(&trust_path)
+        .map_err(|e| format!("{} (signatures are checked against the trust root before they are saved)", e))?;
+    let servers = SigstoreServers { fulcio_url: args.fulcio_url.clone(), rekor_url: args.rekor_url.clone() };
+
+    println!("\n📝 Signing {} keyless...", tx_path);
+    let signed = sign_keyless(tx, id_token, &servers, &trust)?;
+    let (identity, issuer) = signed.identity()?;
+    println!("✅ Certificate issued to {} (vouched for by {})", identity, issuer);
+    println!("   Rekor entry {} (log index {})", signed.sigstore.rekor.uuid, signed.sigstore.rekor.log_index);
+    let out_path = out_path.unwrap_or("signed_transaction.json");
+    write_json(&signed, out_path)?;
+    println!("\n💾 Signed Sigstore transaction saved to:");
+    println!("   {}", out_path);
+    Ok(())
+}
@@ -0,0 +1,142 @@
+//! Simulation
+//! `tlc simulate <ledger> --draft ID --tx FILE ...` previews a batch of
+//! proposed postings: the verified journal is replayed in memory, then the
+//! ledger's drafts and unsigned transaction files are applied in turn and
+//! judged by the ledger's policy and any rules given (see the core
+//! simulation.rs). It reports each proposal's violations and the balances
+//! the accepted ones would leave. Nothing is signed or stored.
+
+use clap::Args;
+use std::fs;
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::period_close::SignedPeriodClose;
+use true_ledger_core::simulation::{Proposal, Simulation, SimulationReport};
+use true_ledger_core::validation::{RulesConfig, ValidationPipeline};
+use true_ledger_core::Transaction;
+
+use crate::report::verified_payloads;
+use crate::store::open_existing;
+use crate::verify::OutputFormat;
+
+/// Options for `tlc simulate`
+#[derive(Args, Debug)]
+pub struct SimulateArgs {
+    /// Ledger database or .ndjson journal
+    pub db: String,
+
+    /// A draft in the ledger to apply (repeatable; applied first, in the order given)
+    #[arg(long = "draft", value_name = "ID")]
+    pub drafts: Vec<String>,
+
+    /// Apply every draft and pending transaction in the ledger
+    #[arg(long, conflicts_with = "drafts")]
+    pub all_drafts: bool,
+
+    /// An unsigned transaction JSON file to apply (repeatable; applied after the drafts)
+    #[arg(long = "tx", value_name = "FILE")]
+    pub tx_paths: Vec<String>,
+
+    /// DID the proposals would be posted by (default: a file's author, or a draft's preparer)
+    #[arg(long = "as", value_name = "DID")]
+    pub author: Option<String>,
+
+    /// Rules file to check the proposals against, on top of the ledger's own policy
+    #[arg(long, value_name = "FILE")]
+    pub rules: Option<String>,
+
+    /// A signed period close the proposals must respect (repeatable)
+    #[arg(long = "period-close", value_name = "FILE")]
+    pub period_closes: Vec<String>,
+
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+}
+
+/// The drafts and files to apply, in order, each posted by `--as` if given
+fn proposals(args: &SimulateArgs) -> Result<Vec<Proposal>, String> {
+    let ledger = open_existing(&args.db)?;
+    let drafts = if args.all_drafts {
+        ledger.drafts()?
+    } else {
+        args.drafts.iter()
+            .map(|id| ledger.draft(id)?.ok_or_else(|| format!("No draft {} in {}", id, args.db)))
+            .collect::<Result<_, String>>()?
+    };
+    let mut proposals: Vec<Proposal> = drafts.into_iter()
+        .map(|draft| {
+            let mut tx = draft.payload;
+            tx.author_did = draft.prepared_by;
+            Proposal { label: draft.id, tx }
+        })
+        .collect();
+    for path in &args.tx_paths {
+        let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let tx: Transaction = serde_json::from_str(&data).map_err(|e| format!("Failed to parse transaction {}: {}", path, e))?;
+        proposals.push(Proposal { label: path.clone(), tx });
+    }
+    if let Some(author) = &args.author {
+        for proposal in &mut proposals {
+            proposal.tx.author_did = author.clone();
+        }
+    }
+    Ok(proposals)
+}
+
+/// The --rules pipeline, with a rule for each --period-close
+fn extra_rules(args: &SimulateArgs, resolver: &dyn DidResolver) -> Result<ValidationPipeline, String> {
+    let mut rules = match &args.rules {
+        Some(path) => ValidationPipeline::from_config(&RulesConfig::load(path)?)?,
+        None => ValidationPipeline::new(),
+    };
+    for path in &args.period_closes {
+        let close = SignedPeriodClose::load(path)?;
+        close.verify(resolver).map_err(|e| format!("Period close {} has an invalid signature: {}", path, e))?;
+        rules.push(close);
+    }
+    Ok(rules)
+}
+
+/// `tlc simulate LEDGER [--draft ID]... [--all-drafts] [--tx FILE]... [--as DID] [--rules FILE] [--period-close FILE]...`
+pub fn run_simulate(args: &SimulateArgs, resolver: &dyn DidResolver) -> Result<(), String> {
+    let proposals = proposals(args)?;
+    if proposals.is_empty() {
+        return Err("Nothing to simulate: give --draft, --all-drafts or --tx".to_string());
+    }
+    let payloads = verified_payloads(&args.db, resolver)?;
+    let report = Simulation::replay(&payloads, extra_rules(args, resolver)?)?.run(&proposals)?;
+    match args.format {
+        OutputFormat::Text => print_report(&report)?,
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report).map_err(|e| format!("Failed to serialize the simulation: {}", e))?),
+    }
+    if report.refused() > 0 {
+        return Err(format!("{} of {} proposed transaction(s) would be refused", report.refused(), report.proposals.len()));
+    }
+    Ok(())
+}
+
+fn print_report(report: &SimulationReport) -> Result<(), String> {
+    println!("\n🧪 Simulated {} proposal(s) on {} replayed transaction(s); nothing signed or stored", report.proposals.len(), report.replayed);
+    for outcome in &report.proposals {
+        if outcome.accepted {
+            println!("   ✅ {}: {}", outcome.label, outcome.memo);
+            continue;
+        }
+        println!("   ❌ {}: {}", outcome.label, outcome.memo);
+        for violation in &outcome.violations {
+            println!("      [{}] {}", violation.rule, violation.message);
+        }
+    }
+    if report.changes.is_empty() {
+        println!("\n   > No balance would change");
+        return Ok(());
+    }
+    println!("\n{:<16} {:>16} {:>16} {:>16}", "Account", "Before", "After", "Change");
+    for change in &report.changes {
+        println!("{:<16} {:>16} {:>16} {:>16}", change.account_id, format_cents(change.before_cents), format_cents(change.after_cents),
+            format_cents(change.after_cents - change.before_cents));
+    }
+    let (debits, credits) = report.after.totals()?;
+    println!("\n   > Books after: debits {}, credits {}", format_cents(debits), format_cents(credits));
+    Ok(())
+}
@@ -0,0 +1,247 @@
+//! Ledger Database
+//! `tlc store import|get|query|ledger`: moves signed transactions into a
+//! ledger (an SQLite database, or an NDJSON journal for *.ndjson / *.jsonl
+//! paths), searches it and shows account histories. `tlc chain verify` and `tlc trial-balance` read a ledger
+//! wherever they accept a directory, streaming journals line by line.
+
+use clap::{Args, Subcommand};
+use std::collections::BTreeSet;
+use std::path::Path;
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::chain;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::journal::NdjsonJournal;
+use true_ledger_core::storage::{self, is_journal_path, memo_terms, Query, Storage};
+use true_ledger_core::verify::verify_with;
+use true_ledger_core::{SignedTransaction, Timestamp};
+
+use crate::signing::write_json;
+
+#[derive(Subcommand, Debug)]
+pub enum StoreCommand {
+    /// Verify transaction files (or directories of them) and add them to a ledger
+    Import {
+        /// Ledger database or .ndjson journal (created if missing)
+        db: String,
+
+        /// Transaction files and/or directories
+        #[arg(required = true)]
+        paths: Vec<String>,
+    },
+
+    /// Print one stored transaction by its payload hash
+    Get {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        /// Hex payload hash (as in prev_hash)
+        hash: String,
+
+        /// Write it to a file instead of printing it
+        #[arg(long = "out", value_name = "FILE")]
+        out_path: Option<String>,
+    },
+
+    /// List stored transactions by author, account and/or time range
+    Query {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        #[command(flatten)]
+        query: QueryArgs,
+    },
+
+    /// Show one account's postings with its running balance
+    Ledger {
+        /// Ledger database or .ndjson journal
+        db: String,
+
+        /// Account code
+        #[arg(long, value_name = "CODE")]
+        account: String,
+
+        /// Only postings at or after this time (Unix seconds or RFC 3339)
+        #[arg(long, value_name = "TIMESTAMP")]
+        from: Option<Timestamp>,
+
+        /// Only postings at or before this time (Unix seconds or RFC 3339)
+        #[arg(long, value_name = "TIMESTAMP")]
+        until: Option<Timestamp>,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct QueryArgs {
+    /// Only transactions by this author DID
+    #[arg(long, value_name = "DID")]
+    pub author: Option<String>,
+
+    /// Only transactions with an entry on this account
+    #[arg(long, value_name = "CODE")]
+    pub account: Option<String>,
+
+    /// Only transactions at or after this time (Unix seconds or RFC 3339)
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub from: Option<Timestamp>,
+
+    /// Only transactions at or before this time (Unix seconds or RFC 3339)
+    #[arg(long, value_name = "TIMESTAMP")]
+    pub until: Option<Timestamp>,
+
+    /// Only transactions whose memo has every one of these words
+    #[arg(long, value_name = "WORDS")]
+    pub memo: Option<String>,
+}
+
+/// Opens a ledger that must already exist (SQLite would silently create an
+/// empty one)
+pub fn open_existing(db: &str) -> Result<Box<dyn Storage>, String> {
+    if !Path::new(db).is_file() {
+        return Err(format!("No ledger at {}", db));
+    }
+    Ok(storage::open(db)?)
+}
+
+/// A journal's transactions in chain order, each labelled by file name,
+/// journal line or CID. A bad line is yielded as an error and reading goes on.
+pub type JournalEntries = Box<dyn Iterator<Item = Result<(String, SignedTransaction), String>>>;
+
+/// Reads a journal for commands that accept a directory of JSON files or a
+/// ledger. NDJSON journals are streamed in file order; the rest are loaded
+/// and sorted by height.
+pub fn journal_entries(path: &str) -> Result<JournalEntries, String> {
+    if Path::new(path).is_dir() {
+        return Ok(Box::new(chain::load_dir(path)?.into_iter()
+            .map(|(file, signed_tx)| Ok((file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default(), signed_tx)))));
+    }
+    let ledger = open_existing(path)?;
+    if is_journal_path(Path::new(path)) {
+        let lines = NdjsonJournal::open(path).iter()?.into_iter().flatten();
+        return Ok(Box::new(lines.map(|item| item.map(|(line_no, signed_tx)| (format!("line {}", line_no), signed_tx)).map_err(String::from))));
+    }
+    Ok(Box::new(ledger.query(&Query::default())?.into_iter()
+        .map(|signed_tx| Ok((signed_tx.cid(), signed_tx)))))
+}
+
+/// `tlc store import|get|query|ledger`
+pub fn run_store(command: &StoreCommand, resolver: &dyn DidResolver) -> Result<(), String> {
+    match command {
+        StoreCommand::Import { db, paths } => import(db, paths, resolver),
+        StoreCommand::Get { db, hash, out_path } => {
+            let signed_tx = open_existing(db)?.get_by_hash(hash)?
+                .ok_or_else(|| format!("No transaction {} in {}", hash, db))?;
+            match out_path {
+                Some(out_path) => {
+                    write_json(&signed_tx, out_path)?;
+                    println!("💾 Transaction {} saved to {}", hash, out_path);
+                }
+                None => println!("{}", serde_json::to_string_pretty(&signed_tx)
+                    .map_err(|e| format!("Failed to serialize transaction: {}", e))?),
+            }
+            Ok(())
+        }
+        StoreCommand::Query { db, query } => {
+            let query = Query {
+                author: query.author.clone(),
+                account: query.account.clone(),
+                from: query.from.as_ref().map(Timestamp::unix),
+                until: query.until.as_ref().map(Timestamp::unix),
+                memo: query.memo.clone(),
+            };
+            let found = open_existing(db)?.query(&query)?;
+            println!("{:>6} {:>25} {:<16} Memo", "Height", "Timestamp", "Hash");
+            for signed_tx in &found {
+                let tx = &signed_tx.payload;
+                let height = tx.height.map(|h| h.to_string()).unwrap_or_else(|| "-".to_string());
+                println!("{:>6} {:>25} {:<16} {}", height, tx.timestamp, &tx.hash_hex()[..16], tx.memo);
+            }
+            println!("\n📒 {} transaction(s)", found.len());
+            Ok(())
+        }
+        StoreCommand::Ledger { db, account, from, until } => {
+            let ledger = open_existing(db)?;
+            let mut query = ledger.postings().account(account);
+            if let Some(from) = from {
+                query = query.from(from.unix());
+            }
+            if let Some(until) = until {
+                query = query.until(until.unix());
+            }
+            println!("{:>6} {:<10} {:<16} {:>14} {:>14} {:>14} {:<4} Memo", "Height", "Date", "Hash", "Debit", "Credit", "Balance", "Cur");
+            let mut count = 0;
+            for posting in query.iter()? {
+                count += 1;
+                let height = posting.height.map(|h| h.to_string()).unwrap_or_else(|| "-".to_string());
+                println!("{:>6} {:<10} {:<16} {:>14} {:>14} {:>14} {:<4} {}", height, posting.timestamp.local_date(), &posting.hash[..16],
+                    format_cents(posting.debit_cents), format_cents(posting.credit_cents), format_cents(posting.balance_cents),
+                    posting.currency.as_deref().unwrap_or(""), posting.memo);
+            }
+            println!("\n📒 {} posting(s) to {}", count, account);
+            Ok(())
+        }
+    }
+}
+
+/// `tlc search <words> [dir|ledger]`: verified transactions whose memo has
+/// every word. Ledgers are searched through their index; directories are read through.
+pub fn run_search(text: &str, path: &str, resolver: &dyn DidResolver) -> Result<(), String> {
+    if memo_terms(text).is_empty() {
+        return Err(format!("'{}' has no words to search for", text));
+    }
+    let query = Query { memo: Some(text.to_string()), ..Query::default() };
+    let found: Vec<SignedTransaction> = if Path::new(path).is_dir() {
+        let mut found = Vec::new();
+        for entry in journal_entries(path)? {
+            let (_, signed_tx) = entry?;
+            if query.matches(&signed_tx) {
+                found.push(signed_tx);
+            }
+        }
+        found
+    } else {
+        open_existing(path)?.query(&query)?
+    };
+
+    let mut shown = 0;
+    for signed_tx in &found {
+        let tx = &signed_tx.payload;
+        let hash = tx.hash_hex();
+        if let Err(e) = verify_with(signed_tx, resolver) {
+            println!("⚠️  Skipped {}: {}", &hash[..16], e);
+            continue;
+        }
+        shown += 1;
+        let accounts: BTreeSet<&str> = tx.entries.iter().map(|e| e.account_id.as_str()).collect();
+        println!("{}  {}  {}", hash, tx.timestamp.local_date(), tx.memo);
+        println!("   Accounts: {}", accounts.into_iter().collect::<Vec<_>>().join(", "));
+    }
+    println!("\n🔎 {} verified transaction(s) match \"{}\"", shown, text);
+    Ok(())
+}
+
+/// Only transactions that verify are stored; the rest are reported and skipped
+fn import(db: &str, paths: &[String], resolver: &dyn DidResolver) -> Result<(), String> {
+    let mut storage = storage::open(db)?;
+    let mut files = Vec::new();
+    for path in paths {
+        if Path::new(path).is_dir() {
+            files.extend(chain::load_dir(path)?.into_iter().map(|(file, signed_tx)| (file.display().to_string(), signed_tx)));
+        } else {
+            files.push((path.clone(), crate::verify::load_signed(path)?));
+        }
+    }
+
+    println!("\n📥 Importing {} transaction(s) into {}...", files.len(), db);
+    let mut skipped = 0;
+    for (name, signed_tx) in &files {
+        match verify_with(signed_tx, resolver).and_then(|_| storage.append(signed_tx)) {
+            Ok(hash) => println!("✅ {} {}", &hash[..16], name),
+            Err(e) => {
+                skipped += 1;
+                println!("⚠️  Skipped {}: {}", name, e);
+            }
+        }
+    }
+    println!("\n💾 Stored {} of {} transaction(s) in {}", files.len() - skipped, files.len(), db);
+    Ok(())
+}
@@ -0,0 +1,587 @@
+//! Ledger Synchronization
+//! `tlc sync --store LEDGER --peer MULTIADDR...` keeps ledgers on several
+//! nodes (one per office, say) identical over libp2p. Nodes talk two
+//! protocols over Noise-encrypted TCP:
+//!
+//!   /true-ledger/sync/1   request/response: "which hashes do you hold?"
+//!                         and "send me these transactions"
+//!   true-ledger/tx/1      gossip: each transaction a node has just
+//!                         appended (or only its hash, if too large)
+//!
+//! A node asks every peer for its hashes when they connect and again every
+//! `--interval` seconds, fetches what it lacks, and verifies each
+//! transaction (as `tlc serve` does) before appending it. Nothing a peer
+//! sends is trusted: an invalid transaction is dropped and logged.
+//!
+//! Transactions appended to the ledger by anything else (`tlc serve`,
+//! `tlc watch`, `tlc store import`) are noticed within a second or two and
+//! broadcast, so replicas converge without waiting for the next round: each
+//! second the node compares the ledger's head with the one it last saw, and
+//! only when it moved walks back along `prev_hash` to what it already had.
+//! Hashes are answered and checked from that in-memory set.
+//! `--status ADDR` serves `GET /status`: the node's peers, and for each how
+//! many transactions it holds that this node lacks (`behind`) and the
+//! reverse (`ahead`), as of the last exchange of hashes.
+//!
+//! `--node-key FILE` keeps the node's libp2p identity (and so its peer ID)
+//! across restarts; it is created on first use.
+//!
+//! For trust that rests on DIDs alone, with no certificates: `--identity
+//! NAME` makes a keystore account's Ed25519 key the node's identity, so the
+//! Noise XX handshake proves the node holds its DID's key, and each
+//! `--peer-did DID` admits the node with that identity. With any
+//! `--peer-did`, connections from or to anyone else are refused before a
+//! message is exchanged.
+
+use libp2p::core::ConnectedPoint;
+use libp2p::futures::StreamExt;
+use libp2p::gossipsub::{self, IdentTopic, MessageAuthenticity};
+use libp2p::identity::{self, Keypair};
+use libp2p::request_response::{self, OutboundRequestId, ProtocolSupport};
+use libp2p::swarm::behaviour::toggle::Toggle;
+use libp2p::swarm::{DialError, ListenError, NetworkBehaviour, SwarmEvent};
+use libp2p::{allow_block_list, noise, tcp, yamux, Multiaddr, PeerId, StreamProtocol, Swarm};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tiny_http::{Header, Method, Response, Server};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::keys::SigAlg;
+use true_ledger_core::storage::{self, Query, Storage};
+use true_ledger_core::verify::verdict_with;
+use true_ledger_core::{Account, SignedTransaction};
+
+/// The request/response protocol
+const SYNC_PROTOCOL: &str = "/true-ledger/sync/1";
+
+/// The gossip topic new transaction hashes are announced on
+const TX_TOPIC: &str = "true-ledger/tx/1";
+
+/// Most transactions asked for, or sent, in one response
+const MAX_BATCH: usize = 256;
+
+/// Transactions larger than this are announced by hash alone (gossipsub's
+/// default limit is 64 KiB per message)
+const MAX_GOSSIP_BYTES: usize = 60 * 1024;
+
+/// How often the ledger is checked for transactions appended by other programs
+const POLL: Duration = Duration::from_secs(1);
+
+/// A question one node asks another
+#[derive(Serialize, Deserialize, Debug)]
+enum SyncRequest {
+    Inventory,        // Every payload hash you hold
+    Get(Vec<String>), // The transactions with these hashes
+}
+
+/// The answer
+#[derive(Serialize, Deserialize, Debug)]
+enum SyncResponse {
+    Inventory(Vec<String>),    // Payload hashes
+    Transactions(Vec<String>), // Signed transactions, as JSON
+    Failed(String),            // The node could not read its ledger
+}
+
+/// A gossiped announcement
+#[derive(Serialize, Deserialize, Debug)]
+struct Announcement {
+    hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transaction: Option<String>, // The signed transaction, as JSON, unless too large
+}
+
+/// What this node knows of one peer
+#[derive(Debug, Default)]
+struct PeerStatus {
+    address: String,
+    connected: bool,
+    behind: Option<usize>,          // Transactions the peer holds that this node lacks
+    ahead: Option<usize>,           // Transactions this node holds that the peer lacks
+    checked: Option<Instant>,       // When the peer last sent its hashes
+    last_received: Option<Instant>, // When the peer last sent a transaction this node appended
+}
+
+/// What `GET /status` reports
+#[derive(Debug, Default)]
+struct Status {
+    node: String,
+    transactions: usize,
+    peers: BTreeMap<PeerId, PeerStatus>,
+}
+
+impl Status {
+    fn to_json(&self) -> serde_json::Value {
+        let ago = |at: Option<Instant>| at.map(|at| at.elapsed().as_secs());
+        let peers: Vec<serde_json::Value> = self.peers.iter().map(|(peer, status)| json!({
+            "peer": peer.to_string(),
+            "address": status.address,
+            "connected": status.connected,
+            "behind": status.behind,
+            "ahead": status.ahead,
+            "in_sync": status.behind == Some(0) && status.ahead == Some(0),
+            "checked_secs_ago": ago(status.checked),
+            "last_received_secs_ago": ago(status.last_received),
+        })).collect();
+        json!({ "node": self.node, "transactions": self.transactions, "peers": peers })
+    }
+}
+
+/// Serves `GET /status` on its own thread
+fn serve_status(listen: &str, status: Arc<Mutex<Status>>) -> Result<(), String> {
+    let server = Server::http(listen).map_err(|e| format!("Could not serve status on {}: {}", listen, e))?;
+    let content_type = Header::from_bytes("Content-Type", "application/json").expect("static header is valid");
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let (code, body) = match (request.method(), request.url()) {
+                (Method::Get, "/status") => (200, status.lock().map(|status| status.to_json()).unwrap_or_default()),
+                _ => (404, json!({ "error": "Only GET /status is served" })),
+            };
+            let response = Response::from_string(body.to_string()).with_status_code(code).with_header(content_type.clone());
+            let _ = request.respond(response);
+        }
+    });
+    Ok(())
+}
+
+#[derive(NetworkBehaviour)]
+struct Behaviour {
+    allowed: Toggle<allow_block_list::Behaviour<allow_block_list::AllowedPeers>>, // The --peer-did nodes, if any are given
+    sync: request_response::json::Behaviour<SyncRequest, SyncResponse>,
+    gossip: gossipsub::Behaviour,
+}
+
+/// The node identity of a keystore account: the same Ed25519 key, so the
+/// Noise handshake proves the node holds its DID's key
+fn account_keypair(account: &Account) -> Result<Keypair, String> {
+    if account.sig_alg() != SigAlg::Ed25519 {
+        return Err(format!("{} is not an Ed25519 key; a node identity must be", account.did));
+    }
+    let mut secret = account.secret_bytes();
+    Keypair::ed25519_from_bytes(&mut *secret).map_err(|e| format!("Invalid node key: {}", e))
+}
+
+/// The peer ID a node whose identity is `did` has
+fn did_peer_id(did: &str, resolver: &dyn DidResolver) -> Result<PeerId, String> {
+    let key = resolver.resolve_public_key(did).map_err(|e| format!("--peer-did {}: {}", did, e))?;
+    if key.sig_alg() != SigAlg::Ed25519 {
+        return Err(format!("--peer-did {}: not an Ed25519 key, so it cannot be a node identity", did));
+    }
+    let key = identity::ed25519::PublicKey::try_from_bytes(&key.to_bytes()).map_err(|e| format!("--peer-did {}: {}", did, e))?;
+    Ok(identity::PublicKey::from(key).to_peer_id())
+}
+
+/// Loads the node's identity from `path`, or creates it there
+fn load_node_key(path: &str) -> Result<Keypair, String> {
+    if Path::new(path).exists() {
+        let bytes = fs::read(path).map_err(|e| format!("Could not read node key {}: {}", path, e))?;
+        return Keypair::from_protobuf_encoding(&bytes).map_err(|e| format!("Invalid node key {}: {}", path, e));
+    }
+    let keypair = Keypair::generate_ed25519();
+    let bytes = keypair.to_protobuf_encoding().map_err(|e| format!("Could not encode node key: {}", e))?;
+    fs::write(path, bytes).map_err(|e| format!("Could not write node key {}: {}", path, e))?;
+    println!("🔑 New node key saved to {}", path);
+    Ok(keypair)
+}
+
+/// One node's view of the ledger and of the requests it has sent
+struct Node<'a> {
+    ledger: Box<dyn Storage>,
+    resolver: &'a dyn DidResolver,
+    topic: IdentTopic,
+    in_flight: HashMap<OutboundRequestId, Vec<String>>, // Get requests awaiting transactions
+    rejected: HashSet<String>,                          // Hashes that failed verification; not fetched again
+    known: HashSet<String>,                             // Hashes in the ledger when last looked at
+    seen: Option<(String, u64)>,                        // Hash and height of the head when last looked at
+    status: Arc<Mutex<Status>>,
+}
+
+impl Node<'_> {
+    fn hashes(&self) -> Result<Vec<String>, String> {
+        Ok(self.ledger.query(&Query::default())?.iter().map(|signed_tx| signed_tx.payload.hash_hex()).collect())
+    }
+
+    fn update_status(&self, update: impl FnOnce(&mut Status)) {
+        if let Ok(mut status) = self.status.lock() {
+            status.transactions = self.known.len();
+            update(&mut status);
+        }
+    }
+
+    /// Transactions appended since the last look, by this node or another
+    /// program; returns those not yet counted. The first look reads the
+    /// whole ledger; after that only a moved head costs more than one read.
+    fn poll_ledger(&mut self) -> Result<Vec<String>, String> {
+        let head = self.ledger.head()?;
+        let head_seen = head.as_ref().map(|head| (head.payload.hash_hex(), head.payload.height.unwrap_or_default()));
+        if head_seen == self.seen {
+            return Ok(Vec::new());
+        }
+        let mut fresh = Vec::new();
+        match &self.seen {
+            None => fresh.extend(self.hashes()?.into_iter().filter(|hash| !self.known.contains(hash))),
+            Some((_, seen_height)) => {
+                // Everything above the height last seen is new, though this
+                // node may have appended some of it itself
+                let mut next = head;
+                while let Some(signed_tx) = next.filter(|signed_tx| signed_tx.payload.height.is_some_and(|height| height > *seen_height)) {
+                    let hash = signed_tx.payload.hash_hex();
+                    if !self.known.contains(&hash) {
+                        fresh.push(hash);
+                    }
+                    next = match &signed_tx.payload.prev_hash {
+                        Some(prev_hash) => self.ledger.get_by_hash(prev_hash)?,
+                        None => None,
+                    };
+                }
+                fresh.reverse();
+            }
+        }
+        self.known.extend(fresh.iter().cloned());
+        self.seen = head_seen;
+        self.update_status(|_| {});
+        Ok(fresh)
+    }
+
+    fn is_wanted(&self, hash: &str) -> bool {
+        let requested = self.in_flight.values().any(|hashes| hashes.iter().any(|h| h == hash));
+        !requested && !self.rejected.contains(hash) && !self.known.contains(hash)
+    }
+
+    /// Asks `peer` for whichever of `hashes` this node lacks
+    fn fetch(&mut self, swarm: &mut Swarm<Behaviour>, peer: PeerId, hashes: Vec<String>) -> Result<(), String> {
+        let wanted: Vec<String> = hashes.into_iter().filter(|hash| self.is_wanted(hash)).collect();
+        for batch in wanted.chunks(MAX_BATCH) {
+            let id = swarm.behaviour_mut().sync.send_request(&peer, SyncRequest::Get(batch.to_vec()));
+            self.in_flight.insert(id, batch.to_vec());
+        }
+        Ok(())
+    }
+
+    fn answer(&self, request: SyncRequest) -> SyncResponse {
+        let result = match request {
+            SyncRequest::Inventory => Ok(SyncResponse::Inventory(self.known.iter().cloned().collect())),
+            SyncRequest::Get(hashes) => hashes.iter().take(MAX_BATCH)
+                .filter_map(|hash| self.ledger.get_by_hash(hash).transpose())
+                .map(|found| found.map_err(String::from).and_then(|signed_tx| serde_json::to_string(&signed_tx).map_err(|e| e.to_string())))
+                .collect::<Result<Vec<String>, String>>()
+                .map(SyncResponse::Transactions),
+        };
+        result.unwrap_or_else(SyncResponse::Failed)
+    }
+
+    /// Verifies and appends the transactions a peer sent for `requested`;
+    /// returns the hashes appended
+    fn receive(&mut self, peer: PeerId, requested: &[String], documents: Vec<String>) -> Vec<String> {
+        let mut valid = Vec::new();
+        for document in documents {
+            let signed_tx = match SignedTransaction::from_json(&document) {
+                Ok(signed_tx) => signed_tx,
+                Err(e) => {
+                    println!("🚫 {} sent an unreadable transaction: {}", peer, e);
+                    continue;
+                }
+            };
+            let verdict = verdict_with(&signed_tx, self.resolver);
+            if !requested.contains(&verdict.hash) {
+                println!("🚫 {} sent {}, which was not asked for", peer, verdict.hash);
+            } else if !verdict.valid {
+                let failed: Vec<&str> = verdict.checks.iter().filter(|c| !c.ok).map(|c| c.check.as_str()).collect();
+                println!("🚫 {} sent {}, which failed {}", peer, verdict.hash, failed.join(", "));
+                self.rejected.insert(verdict.hash);
+            } else {
+                valid.push(signed_tx);
+            }
+        }
+
+        // Each author's transactions must be appended in sequence; keep
+        // retrying those out of turn while others go in
+        valid.sort_by_key(|signed_tx| (signed_tx.payload.height, signed_tx.payload.sequence, signed_tx.payload.timestamp.unix()));
+        let mut appended = Vec::new();
+        loop {
+            let before = valid.len();
+            let mut last_error = None;
+            valid.retain(|signed_tx| match self.ledger.get_by_hash(&signed_tx.payload.hash_hex()) {
+                Ok(Some(_)) => false,
+                _ => match self.ledger.append(signed_tx) {
+                    Ok(hash) => {
+                        appended.push(hash);
+                        false
+                    }
+                    Err(e) => {
+                        last_error = Some(e);
+                        true
+                    }
+                },
+            });
+            if valid.is_empty() || valid.len() == before {
+                if let Some(e) = last_error.filter(|_| !valid.is_empty()) {
+                    println!("⏳ {} transaction(s) from {} could not be appended yet: {}", valid.len(), peer, e);
+                }
+                break;
+            }
+        }
+        for hash in &appended {
+            println!("📥 {} from {}", hash, peer);
+            self.known.insert(hash.clone());
+        }
+        if !appended.is_empty() {
+            self.update_status(|status| {
+                let entry = status.peers.entry(peer).or_default();
+                entry.behind = entry.behind.map(|behind| behind.saturating_sub(appended.len()));
+                entry.last_received = Some(Instant::now());
+            });
+        }
+        appended
+    }
+
+    /// Tells the other nodes about transactions just appended, sending each
+    /// along if it fits in a gossip message
+    fn announce(&self, swarm: &mut Swarm<Behaviour>, hashes: &[String]) -> Result<(), String> {
+        for hash in hashes {
+            let transaction = match self.ledger.get_by_hash(hash)? {
+                Some(signed_tx) => serde_json::to_string(&signed_tx).ok().filter(|json| json.len() <= MAX_GOSSIP_BYTES),
+                None => None,
+            };
+            let message = serde_json::to_vec(&Announcement { hash: hash.clone(), transaction }).expect("an announcement serializes");
+            // With no peers subscribed yet there is nobody to tell; they
+            // will find it in our inventory
+            let _ = swarm.behaviour_mut().gossip.publish(self.topic.clone(), message);
+        }
+        Ok(())
+    }
+
+    /// Records how far this node and `peer` differ
+    fn compare(&self, peer: PeerId, theirs: &[String]) {
+        let theirs: HashSet<&String> = theirs.iter().collect();
+        let behind = theirs.iter().filter(|hash| !self.known.contains(**hash)).count();
+        let ahead = self.known.iter().filter(|hash| !theirs.contains(hash)).count();
+        self.update_status(|status| {
+            let entry = status.peers.entry(peer).or_default();
+            entry.behind = Some(behind);
+            entry.ahead = Some(ahead);
+            entry.checked = Some(Instant::now());
+        });
+    }
+
+    fn handle(&mut self, swarm: &mut Swarm<Behaviour>, event: BehaviourEvent) -> Result<(), String> {
+        match event {
+            BehaviourEvent::Sync(request_response::Event::Message { peer, message, .. }) => match message {
+                request_response::Message::Request { request, channel, .. } => {
+                    let response = self.answer(request);
+                    let _ = swarm.behaviour_mut().sync.send_response(channel, response);
+                }
+                request_response::Message::Response { request_id, response } => {
+                    let requested = self.in_flight.remove(&request_id).unwrap_or_default();
+                    match response {
+                        SyncResponse::Inventory(hashes) => {
+                            self.compare(peer, &hashes);
+                            self.fetch(swarm, peer, hashes)?;
+                        }
+                        SyncResponse::Transactions(documents) => {
+                            let appended = self.receive(peer, &requested, documents);
+                            self.announce(swarm, &appended)?;
+                        }
+                        SyncResponse::Failed(e) => println!("⚠️  {} could not answer: {}", peer, e),
+                    }
+                }
+            },
+            BehaviourEvent::Sync(request_response::Event::OutboundFailure { peer, request_id, error, .. }) => {
+                self.in_flight.remove(&request_id);
+                println!("⚠️  Request to {} failed: {}", peer, error);
+            }
+            BehaviourEvent::Sync(_) => {}
+            BehaviourEvent::Gossip(gossipsub::Event::Message { propagation_source, message, .. }) => {
+                match serde_json::from_slice::<Announcement>(&message.data) {
+                    // Gossip has already carried it to the other nodes, so
+                    // it is not announced again. If it cannot be appended
+                    // yet, the next exchange of hashes fetches it along
+                    // with whatever it waits for.
+                    Ok(Announcement { hash, transaction: Some(document) }) => {
+                        if self.is_wanted(&hash) {
+                            self.receive(propagation_source, &[hash], vec![document]);
+                        }
+                    }
+                    Ok(Announcement { hash, transaction: None }) => self.fetch(swarm, propagation_source, vec![hash])?,
+                    Err(e) => println!("⚠️  Unreadable announcement from {}: {}", propagation_source, e),
+                }
+            }
+            BehaviourEvent::Gossip(_) => {}
+            BehaviourEvent::Allowed(never) => match never {},
+        }
+        Ok(())
+    }
+}
+
+fn build_swarm(keypair: Keypair, allowed: Option<&[PeerId]>) -> Result<Swarm<Behaviour>, String> {
+    let swarm = libp2p::SwarmBuilder::with_existing_identity(keypair)
+        .with_tokio()
+        .with_tcp(tcp::Config::default(), noise::Config::new, yamux::Config::default)
+        .map_err(|e| format!("Could not set up TCP: {}", e))?
+        .with_behaviour(|key| {
+            let gossip_config = gossipsub::ConfigBuilder::default()
+                .validation_mode(gossipsub::ValidationMode::Strict)
+                .build()?;
+            let allowed = allowed.map(|peers| {
+                let mut list = allow_block_list::Behaviour::<allow_block_list::AllowedPeers>::default();
+                for peer in peers {
+                    list.allow_peer(*peer);
+                }
+                list
+            });
+            Ok(Behaviour {
+                allowed: allowed.into(),
+                sync: request_response::json::Behaviour::new(
+                    [(StreamProtocol::new(SYNC_PROTOCOL), ProtocolSupport::Full)],
+                    request_response::Config::default(),
+                ),
+                gossip: gossipsub::Behaviour::new(MessageAuthenticity::Signed(key.clone()), gossip_config)?,
+            })
+        })
+        .map_err(|e| format!("Could not set up the sync protocols: {}", e))?
+        .with_swarm_config(|config| config.with_idle_connection_timeout(Duration::from_secs(300)))
+        .build();
+    Ok(swarm)
+}
+
+/// Options for `tlc sync` besides the ledger
+pub struct SyncOptions<'a> {
+    pub listen: &'a str,
+    pub peers: &'a [String],
+    pub node_key: Option<&'a str>,
+    pub identity: Option<&'a Account>, // Used as the node key instead
+    pub peer_dids: &'a [String],      // Admit only these nodes (none: anyone)
+    pub interval: u64,           // Seconds between exchanges of hashes
+    pub status: Option<&'a str>, // Address to serve GET /status on
+}
+
+/// `tlc sync --store LEDGER [--listen MULTIADDR] [--peer MULTIADDR]... [--node-key FILE | --identity NAME] [--peer-did DID]... [--interval SECS] [--status ADDR]`: runs until killed
+pub fn run_sync(store: &str, options: &SyncOptions, resolver: &dyn DidResolver) -> Result<(), String> {
+    let SyncOptions { listen, peers, node_key, identity, peer_dids, interval, status: status_listen } = *options;
+    let listen: Multiaddr = listen.parse().map_err(|e| format!("Invalid --listen address {}: {}", listen, e))?;
+    let peers = peers.iter()
+        .map(|peer| peer.parse::<Multiaddr>().map_err(|e| format!("Invalid --peer address {}: {}", peer, e)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let keypair = match (identity, node_key) {
+        (Some(account), _) => account_keypair(account)?,
+        (None, Some(path)) => load_node_key(path)?,
+        (None, None) => Keypair::generate_ed25519(),
+    };
+    let allowed = peer_dids.iter().map(|did| Ok((did_peer_id(did, resolver)?, did.clone()))).collect::<Result<BTreeMap<_, _>, String>>()?;
+    let mut node = Node {
+        ledger: storage::open(store)?,
+        resolver,
+        topic: IdentTopic::new(TX_TOPIC),
+        in_flight: HashMap::new(),
+        rejected: HashSet::new(),
+        known: HashSet::new(),
+        seen: None,
+        status: Arc::new(Mutex::new(Status::default())),
+    };
+    node.poll_ledger()?;
+
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| format!("Could not start the async runtime: {}", e))?;
+    runtime.block_on(async {
+        let peer_ids: Vec<PeerId> = allowed.keys().copied().collect();
+        let mut swarm = build_swarm(keypair, (!allowed.is_empty()).then_some(&peer_ids[..]))?;
+        swarm.behaviour_mut().gossip.subscribe(&node.topic).map_err(|e| format!("Could not join {}: {}", TX_TOPIC, e))?;
+        swarm.listen_on(listen.clone()).map_err(|e| format!("Could not listen on {}: {}", listen, e))?;
+        for peer in &peers {
+            swarm.dial(peer.clone()).map_err(|e| format!("Could not dial {}: {}", peer, e))?;
+        }
+
+        let local_peer_id = *swarm.local_peer_id();
+        node.update_status(|status| status.node = local_peer_id.to_string());
+        if let Some(address) = status_listen {
+            serve_status(address, Arc::clone(&node.status))?;
+        }
+
+        println!("🔄 Syncing {} as node {}", store, local_peer_id);
+        if let Some(account) = identity {
+            println!("   Node identity: {}", account.did);
+        }
+        if !allowed.is_empty() {
+            println!("   Admitting only: {}", peer_dids.join(", "));
+        }
+        println!("   Holding {} transaction(s); re-checking peers every {}s", node.known.len(), interval);
+        if let Some(address) = status_listen {
+            println!("   Status: http://{}/status", address);
+        }
+
+        let mut dialed: HashMap<Multiaddr, PeerId> = HashMap::new(); // --peer address -> the node there
+        let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+        let mut poll = tokio::time::interval(POLL);
+        loop {
+            tokio::select! {
+                _ = poll.tick() => {
+                    let appended = node.poll_ledger().and_then(|fresh| {
+                        for hash in &fresh {
+                            println!("📤 {} appended locally; broadcasting", hash);
+                        }
+                        node.announce(&mut swarm, &fresh)
+                    });
+                    if let Err(e) = appended {
+                        eprintln!("⚠️  {}", e);
+                    }
+                }
+                _ = ticker.tick() => {
+                    let connected: BTreeSet<PeerId> = swarm.connected_peers().copied().collect();
+                    // Reconnect to --peer nodes that have gone away
+                    for peer in &peers {
+                        if !dialed.get(peer).is_some_and(|id| connected.contains(id)) {
+                            let _ = swarm.dial(peer.clone());
+                        }
+                    }
+                    for peer in connected {
+                        swarm.behaviour_mut().sync.send_request(&peer, SyncRequest::Inventory);
+                    }
+                }
+                event = swarm.select_next_some() => match event {
+                    SwarmEvent::NewListenAddr { address, .. } => {
+                        println!("   Listening on {}/p2p/{}", address, swarm.local_peer_id());
+                    }
+                    SwarmEvent::ConnectionEstablished { peer_id, endpoint, num_established, .. } => {
+                        if let ConnectedPoint::Dialer { address, .. } = &endpoint {
+                            dialed.insert(address.clone(), peer_id);
+                        }
+                        if num_established.get() == 1 {
+                            match allowed.get(&peer_id) {
+                                Some(did) => println!("🤝 Connected to {} ({}, {})", peer_id, did, endpoint.get_remote_address()),
+                                None => println!("🤝 Connected to {} ({})", peer_id, endpoint.get_remote_address()),
+                            }
+                            node.update_status(|status| {
+                                let entry = status.peers.entry(peer_id).or_default();
+                                entry.address = endpoint.get_remote_address().to_string();
+                                entry.connected = true;
+                            });
+                            swarm.behaviour_mut().sync.send_request(&peer_id, SyncRequest::Inventory);
+                        }
+                    }
+                    SwarmEvent::ConnectionClosed { peer_id, num_established: 0, .. } => {
+                        println!("👋 Disconnected from {}", peer_id);
+                        node.update_status(|status| status.peers.entry(peer_id).or_default().connected = false);
+                    }
+                    SwarmEvent::OutgoingConnectionError { error: DialError::Denied { .. }, peer_id, .. } => {
+                        println!("🚫 Refused to connect to {}: not a --peer-did node", peer_id.map(|p| p.to_string()).unwrap_or_default());
+                    }
+                    SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                        println!("⚠️  Could not connect to {}: {}", peer_id.map(|p| p.to_string()).unwrap_or_default(), error);
+                    }
+                    SwarmEvent::IncomingConnectionError { send_back_addr, error: ListenError::Denied { .. }, .. } => {
+                        println!("🚫 Refused a connection from {}: not a --peer-did node", send_back_addr);
+                    }
+                    SwarmEvent::Behaviour(event) => {
+                        if let Err(e) = node.handle(&mut swarm, event) {
+                            eprintln!("⚠️  {}", e);
+                        }
+                    }
+                    _ => {}
+                },
+            }
+        }
+    })
+}
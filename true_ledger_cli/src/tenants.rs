@@ -0,0 +1,308 @@
+//! Tenants
+//! `tlc serve --tenants FILE` hosts several isolated ledgers in one server,
+//! so a bookkeeping bureau can serve all its clients from one deployment.
+//! Each tenant has its own ledger, its own API keys and tokens, and its own
+//! posting policy and chart of accounts, and requests name it in the path:
+//!
+//!   POST /tenants/<name>/verify
+//!   POST /tenants/<name>/transactions
+//!   GET  /tenants/<name>/transactions/<hash>
+//!
+//! ```toml
+//! [[tenant]]
+//! name = "acme"                 # Letters, digits, '-' and '_'
+//! store = "acme/ledger.db"      # Its ledger (database or .ndjson journal); no two tenants share one
+//! auth = "acme/auth.toml"       # Its API keys and JWT settings (see auth.rs), which open no other tenant
+//! rules = "acme/rules.toml"     # Its posting policy, chart and `authors` (see the core validation.rs)
+//! keystore = "acme/keystore"    # Optional: the keystore its staff sign with (see `tlc keygen --keystore`)
+//! ```
+//!
+//! Paths are relative to the tenants file. A transaction submitted to a
+//! tenant must satisfy its rules before it is stored; /verify reports each
+//! rule as a "rule:<name>" check. A rules file's `[approval]` and
+//! `[delegation]` tables are enforced too, as the "approval" and
+//! "delegation" checks. Every tenant, the single ledger of a plain server
+//! included, also checks the timestamp (not in the future) and that no
+//! signing key was revoked or rotated away in its ledger's key events.
+//!
+//! Each tenant's rules must list its `authors`, the DIDs trusted to post to
+//! it, and no DID may be registered with two tenants: a DID authorised at
+//! one client's ledger is refused at every other, by the server and by
+//! `tlc verify --rules` with that tenant's rules file alike. Every identity
+//! in a tenant's keystore must be one of its authors, and no two tenants
+//! share a keystore, an API key or a JWT secret.
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use true_ledger_core::approval::ApprovalWorkflow;
+use true_ledger_core::capability::DelegationPolicy;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::keystore::Keystore;
+use true_ledger_core::multisig::approvers;
+use true_ledger_core::storage::{self, check_keys, Storage};
+use true_ledger_core::timestamp::TimestampPolicy;
+use true_ledger_core::validation::{RulesConfig, ValidationPipeline};
+use true_ledger_core::verify::{verdict_with, CheckResult, Verdict};
+use true_ledger_core::{LedgerError, SignedTransaction};
+
+use crate::auth::Auth;
+
+/// One `[[tenant]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TenantConfig {
+    pub name: String,
+    pub store: String,
+    pub auth: String,
+    pub rules: String,
+    pub keystore: Option<String>,
+}
+
+/// A --tenants file
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TenantsConfig {
+    pub tenant: Vec<TenantConfig>,
+}
+
+/// One ledger a server serves, with its own credentials and policy. A
+/// single-ledger server has one, named "default", whose auth is optional.
+pub struct Tenant {
+    pub name: String,
+    pub ledger: Option<Box<dyn Storage>>,
+    pub auth: Option<Auth>,
+    pub rules: Option<ValidationPipeline>,
+    pub workflow: Option<ApprovalWorkflow>,   // The rules file's [approval]
+    pub delegation: Option<DelegationPolicy>, // The rules file's [delegation]
+}
+
+/// The verdict on a transaction at a tenant: the timestamp, the signers'
+/// keys against its ledger's key events, its approval workflow and
+/// delegation policy, and a "rule:<name>" check for each of its rules
+pub fn verdict(signed_tx: &SignedTransaction, tenant: &Tenant, resolver: &dyn DidResolver) -> Verdict {
+    let mut verdict = verdict_with(signed_tx, resolver);
+    let mut push = |check: String, result: Result<(), LedgerError>| verdict.checks.push(CheckResult {
+        check,
+        ok: result.is_ok(),
+        code: result.as_ref().err().map(|e| e.code()),
+        error: result.err().map(|e| e.to_string()),
+    });
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    push("timestamp".to_string(), TimestampPolicy::default().check(&signed_tx.payload, now));
+    if let Some(ledger) = &tenant.ledger {
+        push("key_events".to_string(), check_keys(ledger.as_ref(), signed_tx));
+    }
+    if let Some(workflow) = &tenant.workflow {
+        let result = approvers(signed_tx, resolver).and_then(|signers| {
+            let signers: Vec<&str> = signers.iter().map(String::as_str).collect();
+            workflow.check(&signed_tx.payload, &signers).map(|_| ())
+        });
+        push("approval".to_string(), result);
+    }
+    if let Some(delegation) = &tenant.delegation {
+        push("delegation".to_string(), delegation.check(signed_tx, resolver).map(|_| ()));
+    }
+    for (name, result) in tenant.rules.iter().flat_map(|rules| rules.check(&signed_tx.payload)) {
+        push(format!("rule:{}", name), result);
+    }
+    verdict.valid = verdict.checks.iter().all(|check| check.ok);
+    verdict
+}
+
+/// Where a ledger file is, however it was named: a journal is only created
+/// on its first append
+fn canonical_store(store: &str) -> Result<PathBuf, String> {
+    let path = Path::new(store);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let dir = fs::canonicalize(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    Ok(dir.join(path.file_name().unwrap_or_default()))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The canonical directory of a tenant's keystore, after checking that
+/// each identity in it is one of the tenant's `authors`
+fn check_keystore(dir: &str, authors: &[String]) -> Result<PathBuf, String> {
+    let canonical = fs::canonicalize(dir).map_err(|e| format!("keystore {}: {}", dir, e))?;
+    for key_file in Keystore::open(&canonical).list()? {
+        if !authors.contains(&key_file.did) {
+            return Err(format!("keystore identity '{}' ({}) is not one of its authors", key_file.name, key_file.did));
+        }
+    }
+    Ok(canonical)
+}
+
+/// Reads a tenants file and opens every tenant's ledger, credentials and rules
+pub fn load_tenants(path: &str) -> Result<BTreeMap<String, Tenant>, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    let config: TenantsConfig = toml::from_str(&data).map_err(|e| format!("Invalid tenants file {}: {}", path, e))?;
+    let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+    let relative = |file: &str| dir.join(file).to_string_lossy().into_owned();
+
+    let mut tenants: BTreeMap<String, Tenant> = BTreeMap::new();
+    let mut stores = BTreeMap::new();    // Canonical ledger path -> tenant
+    let mut keystores = BTreeMap::new(); // Canonical keystore directory -> tenant
+    let mut registered = BTreeMap::new(); // Author DID -> tenant
+    for tenant in config.tenant {
+        let name = tenant.name.clone();
+        let fail = |e: String| format!("Tenant '{}': {}", name, e);
+        if !valid_name(&name) {
+            return Err(fail("a name is letters, digits, '-' and '_'".to_string()));
+        }
+        if tenants.contains_key(&name) {
+            return Err(format!("Tenant '{}' is listed twice", name));
+        }
+        let store = relative(&tenant.store);
+        let ledger = storage::open(&store).map_err(|e| fail(e.to_string()))?;
+        if let Some(other) = stores.insert(canonical_store(&store).map_err(fail)?, name.clone()) {
+            return Err(format!("Tenants '{}' and '{}' share the ledger {}", other, name, store));
+        }
+        let auth = Auth::load(&relative(&tenant.auth)).map_err(fail)?;
+        for other in tenants.values() {
+            if let Some(shared) = other.auth.as_ref().and_then(|theirs| auth.shared_with(theirs)) {
+                return Err(format!("Tenants '{}' and '{}' share {}, which would open both", other.name, name, shared));
+            }
+        }
+
+        let rules_config = RulesConfig::load(&relative(&tenant.rules)).map_err(|e| fail(e.to_string()))?;
+        let registry = rules_config.author_registry().map_err(|e| fail(e.to_string()))?
+            .ok_or_else(|| fail(format!("{} lists no authors", tenant.rules)))?;
+        for did in &registry.authors {
+            match registered.insert(did.clone(), name.clone()) {
+                Some(other) if other != name => return Err(format!("{} is an author of both '{}' and '{}'", did, other, name)),
+                _ => {}
+            }
+        }
+        if let Some(keystore) = &tenant.keystore {
+            let canonical = check_keystore(&relative(keystore), &registry.authors).map_err(fail)?;
+            if let Some(other) = keystores.insert(canonical, name.clone()) {
+                return Err(format!("Tenants '{}' and '{}' share the keystore {}", other, name, keystore));
+            }
+        }
+        let rules = ValidationPipeline::from_config(&rules_config).map_err(|e| fail(e.to_string()))?;
+        let workflow = rules_config.approval_workflow().map_err(|e| fail(e.to_string()))?;
+        let delegation = rules_config.delegation_policy().map_err(|e| fail(e.to_string()))?;
+        tenants.insert(name.clone(), Tenant { name, ledger: Some(ledger), auth: Some(auth), rules: Some(rules), workflow, delegation });
+    }
+    if tenants.is_empty() {
+        return Err(format!("{} lists no [[tenant]]", path));
+    }
+    Ok(tenants)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use true_ledger_core::builder::TransactionBuilder;
+    use true_ledger_core::did::DidKeyResolver;
+    use true_ledger_core::identity::Account;
+    use true_ledger_core::journal::NdjsonJournal;
+    use true_ledger_core::key_events::KeyEvent;
+
+    /// A fresh directory for one test
+    fn scratch(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tlc-tenants-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A tenants file for tenants "a" and "b", with the given auth files
+    fn tenants_file(dir: &Path, auth_a: &str, auth_b: &str) -> String {
+        for (name, auth) in [("a", auth_a), ("b", auth_b)] {
+            fs::write(dir.join(format!("{}-auth.toml", name)), auth).unwrap();
+            fs::write(dir.join(format!("{}-rules.toml", name)), format!("authors = [\"{}\"]\n", Account::generate().did)).unwrap();
+        }
+        let tenants = "[[tenant]]\nname = \"a\"\nstore = \"a.ndjson\"\nauth = \"a-auth.toml\"\nrules = \"a-rules.toml\"\n\n\
+            [[tenant]]\nname = \"b\"\nstore = \"b.ndjson\"\nauth = \"b-auth.toml\"\nrules = \"b-rules.toml\"\n";
+        let path = dir.join("tenants.toml");
+        fs::write(&path, tenants).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    fn api_key(digest: char) -> String {
+        format!("[[api_key]]\nname = \"clerk\"\nsha256 = \"{}\"\nscopes = [\"read\"]\n", digest.to_string().repeat(64))
+    }
+
+    #[test]
+    fn tenants_may_not_share_credentials() {
+        let dir = scratch("credentials");
+        assert!(load_tenants(&tenants_file(&dir, &api_key('a'), &api_key('b'))).is_ok());
+        let shared = load_tenants(&tenants_file(&dir, &api_key('a'), &api_key('A'))).err().unwrap();
+        assert!(shared.contains("share API key 'clerk'"), "{}", shared);
+
+        std::env::set_var("TLC_TEST_TENANT_SECRET", "s".repeat(32));
+        let jwt = "[jwt]\nsecret_env = \"TLC_TEST_TENANT_SECRET\"\n";
+        let shared = load_tenants(&tenants_file(&dir, jwt, jwt)).err().unwrap();
+        assert!(shared.contains("JWT secret"), "{}", shared);
+    }
+
+    fn tenant(dir: &Path, rules: &str) -> Tenant {
+        let config = RulesConfig::parse(rules).unwrap();
+        Tenant {
+            name: "a".to_string(),
+            ledger: Some(Box::new(NdjsonJournal::open(dir.join("ledger.ndjson")))),
+            auth: None,
+            rules: Some(ValidationPipeline::from_config(&config).unwrap()),
+            workflow: config.approval_workflow().unwrap(),
+            delegation: config.delegation_policy().unwrap(),
+        }
+    }
+
+    fn failed(verdict: &Verdict) -> Vec<&str> {
+        verdict.checks.iter().filter(|check| !check.ok).map(|check| check.check.as_str()).collect()
+    }
+
+    fn posting(account: &Account, at: u64, prev: Option<&SignedTransaction>) -> SignedTransaction {
+        let builder = TransactionBuilder::new()
+            .timestamp(at)
+            .entry("68100", "10.00", "0.00")
+            .entry("10100", "0.00", "10.00")
+            .memo("Supplies");
+        match prev {
+            Some(prev) => builder.follows(&prev.payload),
+            None => builder.genesis(),
+        }.sign(account).unwrap()
+    }
+
+    #[test]
+    fn tenant_verdicts_apply_the_approval_and_delegation_tables() {
+        let (clerk, approver) = (Account::generate(), Account::generate());
+        let dir = scratch("approval");
+        let rules = format!("[approval]\napprovers = [\"{}\"]\n\n[delegation]\nroots = [\"{}\"]\n", approver.did, approver.did);
+        let verdict = verdict(&posting(&clerk, 1_700_000_000, None), &tenant(&dir, &rules), &DidKeyResolver);
+        assert!(!verdict.valid);
+        assert_eq!(failed(&verdict), ["approval", "delegation"]);
+    }
+
+    #[test]
+    fn tenant_verdicts_check_the_timestamp_and_key_events() {
+        let clerk = Account::generate();
+        let dir = scratch("keys");
+        let mut tenant = tenant(&dir, "");
+        let genesis = posting(&clerk, 1_700_000_000, None);
+        assert!(verdict(&genesis, &tenant, &DidKeyResolver).valid);
+        let ledger = tenant.ledger.as_mut().unwrap();
+        ledger.append(&genesis).unwrap();
+
+        let mut revocation = posting(&clerk, 1_700_000_001, Some(&genesis)).payload;
+        revocation.entries.clear();
+        revocation.key_event = Some(KeyEvent::Revoke { did: clerk.did.clone(), revoked_at: None });
+        let revocation = clerk.sign(revocation);
+        ledger.append(&revocation).unwrap();
+
+        let late = posting(&clerk, 1_700_000_002, Some(&revocation));
+        assert_eq!(failed(&verdict(&late, &tenant, &DidKeyResolver)), ["key_events"]);
+        let future = posting(&clerk, 4_000_000_000, Some(&revocation));
+        assert!(failed(&verdict(&future, &tenant, &DidKeyResolver)).contains(&"timestamp"));
+    }
+}
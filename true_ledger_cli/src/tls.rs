@@ -0,0 +1,263 @@
+//! TLS for the Verification Server
+//! `tlc serve --tls-cert FILE --tls-key FILE` serves HTTPS. With
+//! `--client-ca FILE` it is mutual TLS: every client presents a certificate
+//! issued by that CA, and the server knows which DID is connecting. A
+//! certificate names its DID in a URI subject alternative name, e.g.
+//!
+//!   openssl req ... -addext "subjectAltName=URI:did:key:z6Mk..."
+//!
+//! or `--client-dids FILE` maps certificates to DIDs by SHA-256 fingerprint
+//! (of the DER encoding), for certificates that cannot be reissued:
+//!
+//! ```toml
+//! "3f0c...e1" = "did:key:z6Mk..."
+//! ```
+//!
+//! Raft cluster members (`tlc raft --tls-cert FILE --tls-key FILE --tls-ca FILE`)
+//! talk to each other over mutual TLS the same way: every member's
+//! certificate is issued by the cluster's CA and names the member's DID, and
+//! a connection is accepted only from, or made only to, the member whose
+//! DID its certificate names. Member certificates are not checked against
+//! host names; the DID is what identifies a member. Their HTTP API is
+//! served like `tlc serve`'s, with `--api-tls-cert`, `--api-tls-key`,
+//! `--api-client-ca` and `--api-client-dids`.
+
+use clap::Args;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::client::verify_server_cert_signed_by_trust_anchor;
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::server::{ParsedCertificate, WebPkiClientVerifier};
+use rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, ServerConfig, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// TLS options for `tlc serve`
+#[derive(Args, Debug)]
+pub struct TlsArgs {
+    /// Serve HTTPS with this certificate chain (PEM)
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    pub tls_cert: Option<String>,
+
+    /// Private key for --tls-cert (PEM)
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    pub tls_key: Option<String>,
+
+    /// Require client certificates issued by this CA (PEM): mutual TLS
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    pub client_ca: Option<String>,
+
+    /// TOML table mapping client-certificate SHA-256 fingerprints (hex) to DIDs
+    #[arg(long, value_name = "FILE", requires = "client_ca")]
+    pub client_dids: Option<String>,
+}
+
+/// TLS options for the HTTP API of `tlc raft`, whose --tls-* options are
+/// for the members' own connections
+#[derive(Args, Debug)]
+pub struct ApiTlsArgs {
+    /// Serve the HTTP API over HTTPS with this certificate chain (PEM)
+    #[arg(long, value_name = "FILE", requires = "api_tls_key")]
+    pub api_tls_cert: Option<String>,
+
+    /// Private key for --api-tls-cert (PEM)
+    #[arg(long, value_name = "FILE", requires = "api_tls_cert")]
+    pub api_tls_key: Option<String>,
+
+    /// Require API clients to present certificates issued by this CA (PEM): mutual TLS
+    #[arg(long, value_name = "FILE", requires = "api_tls_cert")]
+    pub api_client_ca: Option<String>,
+
+    /// TOML table mapping API client-certificate SHA-256 fingerprints (hex) to DIDs
+    #[arg(long, value_name = "FILE", requires = "api_client_ca")]
+    pub api_client_dids: Option<String>,
+}
+
+impl ApiTlsArgs {
+    /// The same settings as `tlc serve` takes them
+    pub fn tls_args(&self) -> TlsArgs {
+        TlsArgs {
+            tls_cert: self.api_tls_cert.clone(),
+            tls_key: self.api_tls_key.clone(),
+            client_ca: self.api_client_ca.clone(),
+            client_dids: self.api_client_dids.clone(),
+        }
+    }
+}
+
+/// How the server knows who is connecting
+pub struct TlsServer {
+    pub config: Arc<ServerConfig>,
+    pub mutual: bool,                     // Clients must present a certificate
+    pub client_dids: BTreeMap<String, String>, // Fingerprint -> DID
+}
+
+/// Options for `tlc raft`: mutual TLS between cluster members
+#[derive(Args, Debug)]
+pub struct MemberTlsArgs {
+    /// Talk to other members over mutual TLS with this certificate chain (PEM); it must name this member's DID
+    #[arg(long, value_name = "FILE", requires_all = ["tls_key", "tls_ca"])]
+    pub tls_cert: Option<String>,
+
+    /// Private key for --tls-cert (PEM)
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    pub tls_key: Option<String>,
+
+    /// The CA (PEM) that issued every member's certificate
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    pub tls_ca: Option<String>,
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certs = CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Could not read certificates from {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("{} holds no PEM certificate", path));
+    }
+    Ok(certs)
+}
+
+impl TlsServer {
+    /// The server's TLS settings, or None when --tls-cert is not given
+    pub fn from_args(args: &TlsArgs) -> Result<Option<Self>, String> {
+        let (Some(cert_path), Some(key_path)) = (&args.tls_cert, &args.tls_key) else {
+            return Ok(None);
+        };
+        let certs = load_certs(cert_path)?;
+        let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| format!("Could not read the private key from {}: {}", key_path, e))?;
+        let builder = ServerConfig::builder();
+        let builder = match &args.client_ca {
+            Some(ca_path) => {
+                let verifier = WebPkiClientVerifier::builder(Arc::new(load_roots(ca_path)?)).build()
+                    .map_err(|e| format!("Invalid client CA {}: {}", ca_path, e))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder.with_single_cert(certs, key).map_err(|e| format!("Invalid certificate or key: {}", e))?;
+        let client_dids = match &args.client_dids {
+            Some(path) => {
+                let data = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+                let map: BTreeMap<String, String> = toml::from_str(&data).map_err(|e| format!("Invalid client DID map {}: {}", path, e))?;
+                map.into_iter().map(|(fingerprint, did)| (fingerprint.to_lowercase().replace(':', ""), did)).collect()
+            }
+            None => BTreeMap::new(),
+        };
+        Ok(Some(TlsServer { config: Arc::new(config), mutual: args.client_ca.is_some(), client_dids }))
+    }
+
+    /// The DID a verified client certificate is bound to: the --client-dids
+    /// entry for its fingerprint, else a `did:` URI in its subject alternative names
+    pub fn client_did(&self, cert: &CertificateDer) -> Result<String, String> {
+        let fingerprint = hex::encode(Sha256::digest(cert.as_ref()));
+        if let Some(did) = self.client_dids.get(&fingerprint) {
+            return Ok(did.clone());
+        }
+        cert_did(cert)?.ok_or_else(|| format!("The client certificate {} names no DID", &fingerprint[..16]))
+    }
+}
+
+/// The DID a certificate names in a `did:` URI subject alternative name
+pub fn cert_did(cert: &CertificateDer) -> Result<Option<String>, String> {
+    let (_, parsed) = X509Certificate::from_der(cert.as_ref()).map_err(|e| format!("Unreadable certificate: {}", e))?;
+    let san = parsed.subject_alternative_name().map_err(|e| format!("Invalid subjectAltName: {}", e))?;
+    Ok(san.into_iter()
+        .flat_map(|san| san.value.general_names.iter())
+        .find_map(|name| match name {
+            GeneralName::URI(uri) if uri.starts_with("did:") => Some(uri.to_string()),
+            _ => None,
+        }))
+}
+
+fn load_roots(path: &str) -> Result<RootCertStore, String> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(cert).map_err(|e| format!("Invalid CA certificate in {}: {}", path, e))?;
+    }
+    Ok(roots)
+}
+
+/// One cluster member's side of mutual TLS
+pub struct MemberTls {
+    roots: Arc<RootCertStore>,
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+    pub server: Arc<ServerConfig>, // Accepts connections from certificates the CA issued
+}
+
+impl MemberTls {
+    /// The member's TLS settings, or None when --tls-cert is not given. The
+    /// certificate must name `own_did`, as other members will expect.
+    pub fn from_args(args: &MemberTlsArgs, own_did: &str) -> Result<Option<Self>, String> {
+        let (Some(cert_path), Some(key_path), Some(ca_path)) = (&args.tls_cert, &args.tls_key, &args.tls_ca) else {
+            return Ok(None);
+        };
+        let certs = load_certs(cert_path)?;
+        match cert_did(&certs[0])? {
+            Some(did) if did == own_did => {}
+            Some(did) => return Err(format!("{} names {}, but this member is {}", cert_path, did, own_did)),
+            None => return Err(format!("{} names no DID; add subjectAltName=URI:{}", cert_path, own_did)),
+        }
+        let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| format!("Could not read the private key from {}: {}", key_path, e))?;
+        let roots = Arc::new(load_roots(ca_path)?);
+        let verifier = WebPkiClientVerifier::builder(roots.clone()).build().map_err(|e| format!("Invalid CA {}: {}", ca_path, e))?;
+        let server = ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs.clone(), key.clone_key())
+            .map_err(|e| format!("Invalid certificate or key: {}", e))?;
+        Ok(Some(MemberTls { roots, certs, key, server: Arc::new(server) }))
+    }
+
+    /// Settings for connecting to the member `did`: its certificate must be
+    /// issued by the CA and name that DID
+    pub fn client(&self, did: &str) -> Result<Arc<ClientConfig>, String> {
+        let algorithms = ClientConfig::builder().crypto_provider().signature_verification_algorithms;
+        let verifier = MemberVerifier { roots: self.roots.clone(), did: did.to_string(), algorithms };
+        let config = ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(verifier))
+            .with_client_auth_cert(self.certs.clone(), self.key.clone_key())
+            .map_err(|e| format!("Invalid certificate or key: {}", e))?;
+        Ok(Arc::new(config))
+    }
+}
+
+/// Checks the certificate of the member being connected to: issued by the
+/// cluster's CA, for that member's DID, whatever address it is reached at
+#[derive(Debug)]
+struct MemberVerifier {
+    roots: Arc<RootCertStore>,
+    did: String,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ServerCertVerifier for MemberVerifier {
+    fn verify_server_cert(&self, end_entity: &CertificateDer<'_>, intermediates: &[CertificateDer<'_>], _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8], now: UnixTime) -> Result<ServerCertVerified, rustls::Error> {
+        let parsed = ParsedCertificate::try_from(end_entity)?;
+        verify_server_cert_signed_by_trust_anchor(&parsed, &self.roots, intermediates, now, self.algorithms.all)?;
+        match cert_did(end_entity).map_err(rustls::Error::General)? {
+            Some(did) if did == self.did => Ok(ServerCertVerified::assertion()),
+            Some(did) => Err(rustls::Error::General(format!("the certificate names {}, not {}", did, self.did))),
+            None => Err(rustls::Error::General("the certificate names no DID".to_string())),
+        }
+    }
+
+    fn verify_tls12_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(&self, message: &[u8], cert: &CertificateDer<'_>, dss: &DigitallySignedStruct) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
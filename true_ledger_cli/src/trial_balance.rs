@@ -0,0 +1,59 @@
+//! Trial Balance
+//! `tlc trial-balance <dir|ledger>`: verifies every transaction in a
+//! journal and totals the ones that pass, per account. With `--rollup`,
+//! each account also carries everything under it.
+
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::chart::ChartOfAccounts;
+use true_ledger_core::trial_balance::{AccountTotals, TrialBalance};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::verify::verify_with;
+
+use crate::store::journal_entries;
+
+/// Prints the per-account totals and fails if the books do not balance
+pub fn run_trial_balance(dir: &str, rollup: bool, chart: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let chart = chart.map(ChartOfAccounts::load).transpose()?.unwrap_or_default();
+    let journal = journal_entries(dir)?;
+    println!("\n📒 Building trial balance from {}...", dir);
+
+    let mut trial_balance = TrialBalance::new();
+    let mut skipped = 0;
+    let mut total = 0;
+    for entry in journal {
+        total += 1;
+        // Only verified transactions belong in the books
+        let result = entry.and_then(|(name, signed_tx)| verify_with(&signed_tx, resolver)
+            .and_then(|_| trial_balance.post(&signed_tx.payload))
+            .map_err(|e| format!("{}: {}", name, e)));
+        if let Err(e) = result {
+            skipped += 1;
+            println!("⚠️  Skipped {}", e);
+        }
+    }
+
+    // Rolled-up lines are indented under their parents
+    let rows: Vec<(String, AccountTotals)> = if rollup {
+        trial_balance.rollup(&chart)?.into_iter()
+            .map(|line| (format!("{}{}", "  ".repeat(line.depth), line.account_id), line.totals))
+            .collect()
+    } else {
+        trial_balance.accounts.iter().map(|(account_id, totals)| (account_id.clone(), totals.clone())).collect()
+    };
+    let width = rows.iter().map(|(account, _)| account.len()).max().unwrap_or(0).max(16);
+
+    println!("\n{:<width$} {:>16} {:>16} {:>16}", "Account", "Debit", "Credit", "Net");
+    for (account, totals) in &rows {
+        println!("{:<width$} {:>16} {:>16} {:>16}", account,
+            format_cents(totals.debit_cents), format_cents(totals.credit_cents), format_cents(totals.net_cents()));
+    }
+    let (debits, credits) = trial_balance.totals()?;
+    println!("{:<width$} {:>16} {:>16} {:>16}", "TOTAL", format_cents(debits), format_cents(credits), format_cents(debits - credits));
+
+    if skipped > 0 {
+        println!("\n   > {} of {} transaction(s) failed verification and were left out.", skipped, total);
+    }
+    trial_balance.check()?;
+    println!("\n🎉 **TRIAL BALANCE BALANCES** ({} transactions)", trial_balance.transactions);
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! Test Vectors
+//! Known-good and known-bad signed transactions with their expected verdicts,
+//! so other implementations can check themselves against ours.
+
+use serde::Serialize;
+use std::fs;
+use true_ledger_core::keys::SigAlg;
+use true_ledger_core::{Account, SignedTransaction};
+
+use crate::signing::genesis_transaction;
+
+/// One entry in the vector manifest
+#[derive(Serialize)]
+struct TestVector {
+    file: String,
+    description: String,
+    expected: String, // "valid", "invalid_signature", "imbalance", "invalid_amount" or "invalid_did"
+}
+
+/// Writes the vector suite and a vectors.json manifest into `out_dir`
+pub fn generate_test_vectors(out_dir: &str) -> Result<(), String> {
+    // Fixed keys so the suite is byte-for-byte reproducible
+    let alice = Account::from_secret_bytes(&[0x01; 32]);
+    let mallory = Account::from_secret_bytes(&[0x02; 32]);
+    let satoshi = Account::from_secret_bytes_with(SigAlg::Secp256k1, &[0x01; 32])?;
+
+    let mut cases: Vec<(&str, &str, &str, SignedTransaction)> = Vec::new();
+
+    cases.push(("valid.json", "Well-formed, balanced, correctly signed.", "valid",
+        alice.sign(genesis_transaction(&alice.did))));
+
+    let mut tampered = alice.sign(genesis_transaction(&alice.did));
+    tampered.payload.entries[0].debit = "1000000.00".to_string();
+    tampered.payload.entries[1].credit = "1000000.00".to_string();
+    cases.push(("tampered_payload.json", "Amounts changed after signing (still balanced).", "invalid_signature", tampered));
+
+    cases.push(("wrong_key.json", "Author DID is Alice but Mallory signed it.", "invalid_signature",
+        mallory.sign(genesis_transaction(&alice.did))));
+
+    let mut imbalanced = genesis_transaction(&alice.did);
+    imbalanced.entries[1].credit = "9999.99".to_string();
+    cases.push(("imbalance.json", "Correctly signed but debits exceed credits by 0.01.", "imbalance",
+        alice.sign(imbalanced)));
+
+    let mut not_a_number = genesis_transaction(&alice.did);
+    not_a_number.entries[0].debit = "ten thousand".to_string();
+    cases.push(("invalid_amount.json", "Correctly signed but an amount is not a number.", "invalid_amount",
+        alice.sign(not_a_number)));
+
+    cases.push(("valid_secp256k1.json", "Well-formed, balanced, signed with a secp256k1 did:key (zQ3s...).", "valid",
+        satoshi.sign(genesis_transaction(&satoshi.did))));
+
+    let mut mislabelled = satoshi.sign(genesis_transaction(&satoshi.did));
+    mislabelled.sig_alg = Some(SigAlg::Ed25519);
+    cases.push(("sig_alg_mismatch.json", "Valid secp256k1 signature but the envelope claims Ed25519.", "invalid_signature",
+        mislabelled));
+
+    // Same key bytes, but tagged with the secp256k1 multicodec (0xe7 0x01)
+    let mut bad_prefix_bytes = vec![0xe7, 0x01];
+    bad_prefix_bytes.extend_from_slice(&alice.public_key().to_bytes());
+    let bad_did = format!("did:key:{}", multibase::encode(multibase::Base::Base58Btc, bad_prefix_bytes));
+    cases.push(("bad_multicodec_prefix.json", "Ed25519 key wrapped in a secp256k1 multicodec prefix.", "invalid_did",
+        alice.sign(genesis_transaction(&bad_did))));
+
+    fs::create_dir_all(out_dir).map_err(|e| format!("Failed to create {}: {}", out_dir, e))?;
+
+    let mut manifest = Vec::new();
+    for (file, description, expected, signed_tx) in cases {
+        let data = serde_json::to_string_pretty(&signed_tx)
+            .map_err(|e| format!("Failed to serialize {}: {}", file, e))?;
+        fs::write(format!("{}/{}", out_dir, file), data)
+            .map_err(|e| format!("Failed to write {}: {}", file, e))?;
+        manifest.push(TestVector {
+            file: file.to_string(),
+            description: description.to_string(),
+            expected: expected.to_string(),
+        });
+    }
+
+    let data = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+    fs::write(format!("{}/vectors.json", out_dir), data)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    println!("🧪 Wrote {} test vectors to {}/", manifest.len(), out_dir);
+    Ok(())
+}
@@ -0,0 +1,182 @@
+//! Watch Mode
+//! `tlc watch <dir> --store LEDGER` runs the verifier as a long-lived
+//! service: every signed transaction (*.json) that lands in the directory is
+//! verified and, if valid and linked to the ledger's head, appended to the
+//! ledger and moved to `accepted/`. Anything else is moved to `quarantine/`
+//! next to a `<name>.report.json` saying why. Files already there when it
+//! starts are handled first.
+//!
+//! Writers should create files under another name (a leading '.' or any
+//! extension but .json) and rename them into place; as a fallback, a file
+//! is only read once it has gone unchanged for a moment.
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::storage::{self, check_append, Storage};
+use true_ledger_core::verify::verdict_with;
+use true_ledger_core::SignedTransaction;
+
+use crate::signing::write_json;
+
+/// How long a file must go unchanged before it is read
+const SETTLE: Duration = Duration::from_millis(500);
+
+/// What became of one file
+enum Outcome {
+    Appended(String),                       // Payload hash
+    Duplicate(String),                      // Payload hash, already in the ledger
+    Quarantined(String, serde_json::Value), // Why, and the report to write
+}
+
+/// Where files go once handled, and the ledger valid ones are appended to
+struct Watched<'a> {
+    accepted: PathBuf,
+    quarantine: PathBuf,
+    ledger: Box<dyn Storage>,
+    resolver: &'a dyn DidResolver,
+}
+
+/// A file the watcher should pick up: *.json directly in the directory, not hidden
+fn is_candidate(path: &Path, dir: &Path) -> bool {
+    let visible = path.file_name().and_then(|name| name.to_str()).is_some_and(|name| !name.starts_with('.'));
+    visible && path.parent() == Some(dir) && path.extension().and_then(|ext| ext.to_str()) == Some("json")
+}
+
+/// Moves a file into `to`, keeping its name unless that is taken
+fn move_into(path: &Path, to: &Path) -> Result<PathBuf, String> {
+    let name = path.file_name().ok_or_else(|| format!("{} has no file name", path.display()))?;
+    let mut target = to.join(name);
+    let mut n = 1;
+    while target.exists() {
+        let stem = Path::new(name).file_stem().unwrap_or_default().to_string_lossy();
+        target = to.join(format!("{}.{}.json", stem, n));
+        n += 1;
+    }
+    fs::rename(path, &target).map_err(|e| format!("Could not move {} to {}: {}", path.display(), to.display(), e))?;
+    Ok(target)
+}
+
+impl Watched<'_> {
+    /// Verifies one file and appends it, or says why not
+    fn check(&mut self, path: &Path) -> Result<String, Outcome> {
+        let quarantine = |why: String, report: serde_json::Value| Outcome::Quarantined(why, report);
+        let data = fs::read_to_string(path)
+            .map_err(|e| quarantine(format!("Could not read file: {}", e), json!({ "error": e.to_string() })))?;
+        let signed_tx = SignedTransaction::from_json(&data)
+            .map_err(|e| quarantine(e.to_string(), json!({ "error": e.to_string() })))?;
+        let verdict = verdict_with(&signed_tx, self.resolver);
+        let report = serde_json::to_value(&verdict).unwrap_or_default();
+        if !verdict.valid {
+            let failed: Vec<&str> = verdict.checks.iter().filter(|c| !c.ok).map(|c| c.check.as_str()).collect();
+            return Err(quarantine(format!("failed {}", failed.join(", ")), report));
+        }
+        match self.ledger.get_by_hash(&verdict.hash) {
+            Ok(Some(_)) => return Err(Outcome::Duplicate(verdict.hash)),
+            Ok(None) => {}
+            Err(e) => return Err(quarantine(e.to_string(), json!({ "verdict": report, "error": e.to_string() }))),
+        }
+        // A stale prev_hash or height would fork the ledger
+        check_append(self.ledger.as_ref(), &signed_tx)
+            .map_err(|e| quarantine(format!("Does not extend the ledger: {}", e), json!({ "verdict": report, "error": e.to_string() })))?;
+        self.ledger.append(&signed_tx)
+            .map_err(|e| quarantine(format!("Could not append: {}", e), json!({ "verdict": report, "error": e.to_string() })))?;
+        Ok(verdict.hash)
+    }
+
+    /// Handles one file and moves it out of the watched directory
+    fn process(&mut self, path: &Path) -> Result<(), String> {
+        if !path.is_file() {
+            return Ok(()); // Renamed away or deleted before it settled
+        }
+        let outcome = match self.check(path) {
+            Ok(hash) => Outcome::Appended(hash),
+            Err(outcome) => outcome,
+        };
+        let name = path.display();
+        match outcome {
+            Outcome::Appended(hash) => {
+                move_into(path, &self.accepted)?;
+                println!("✅ {}: appended {}", name, hash);
+            }
+            Outcome::Duplicate(hash) => {
+                move_into(path, &self.accepted)?;
+                println!("♻️  {}: {} is already stored", name, hash);
+            }
+            Outcome::Quarantined(why, report) => {
+                let moved = move_into(path, &self.quarantine)?;
+                let report_path = moved.with_extension("report.json");
+                let report = json!({ "file": path.file_name().map(|n| n.to_string_lossy()), "reason": why, "report": report });
+                write_json(&report, &report_path.to_string_lossy())?;
+                println!("🚫 {}: {} (quarantined, see {})", name, why, report_path.display());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `tlc watch <dir> --store LEDGER [--accepted DIR] [--quarantine DIR]`: runs until killed
+pub fn run_watch(dir: &str, store: &str, accepted: Option<&str>, quarantine: Option<&str>, resolver: &dyn DidResolver) -> Result<(), String> {
+    let dir = fs::canonicalize(dir).map_err(|e| format!("Cannot watch {}: {}", dir, e))?;
+    let subdir = |given: Option<&str>, default: &str| -> Result<PathBuf, String> {
+        let path = given.map(PathBuf::from).unwrap_or_else(|| dir.join(default));
+        fs::create_dir_all(&path).map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+        Ok(path)
+    };
+    let mut watched = Watched {
+        accepted: subdir(accepted, "accepted")?,
+        quarantine: subdir(quarantine, "quarantine")?,
+        ledger: storage::open(store)?,
+        resolver,
+    };
+
+    // Start watching before the first scan, so nothing that arrives in
+    // between is missed
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(|e| format!("Could not start watching: {}", e))?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive).map_err(|e| format!("Could not watch {}: {}", dir.display(), e))?;
+
+    println!("👀 Watching {} (ledger: {})", dir.display(), store);
+    println!("   Valid transactions are appended and moved to {}", watched.accepted.display());
+    println!("   Invalid ones are moved to {}, with a report", watched.quarantine.display());
+
+    let mut existing: Vec<PathBuf> = fs::read_dir(&dir).map_err(|e| format!("Could not read directory {}: {}", dir.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_candidate(path, &dir))
+        .collect();
+    existing.sort();
+    for path in existing {
+        watched.process(&path).unwrap_or_else(|e| eprintln!("⚠️  {}", e));
+    }
+
+    // Files wait here until they have settled
+    let mut pending: BTreeMap<PathBuf, Instant> = BTreeMap::new();
+    loop {
+        let timeout = pending.values().min().map(|last| SETTLE.saturating_sub(last.elapsed())).unwrap_or(Duration::from_secs(3600));
+        match events.recv_timeout(timeout) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths.into_iter().filter(|path| is_candidate(path, &dir)) {
+                        pending.insert(path, Instant::now());
+                    }
+                }
+            }
+            Ok(Err(e)) => eprintln!("⚠️  Watch error: {}", e),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Err("The file watcher stopped".to_string()),
+        }
+        let settled: Vec<PathBuf> = pending.iter()
+            .filter(|(_, last)| last.elapsed() >= SETTLE)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            pending.remove(&path);
+            watched.process(&path).unwrap_or_else(|e| eprintln!("⚠️  {}", e));
+        }
+    }
+}
@@ -0,0 +1,271 @@
+//! Account Permissions
+//! Which identities may post to which accounts. Each `[[acl]]` table in a
+//! rules file (see validation.rs) reserves a set of accounts, on one side or
+//! both, for the DIDs it lists; an entry posting there by any other author
+//! fails verification. An entry covered by several tables must satisfy each.
+//! Accounts outside every table stay open to everyone.
+//!
+//! ```toml
+//! [groups]
+//! payroll = ["did:key:z6Mk..."]
+//!
+//! [[acl]]
+//! accounts = ["61000-61999", "2150*"]   # Codes, prefixes ending in '*', or inclusive ranges
+//! side = "debit"                        # Only debits to them (default: both sides)
+//! allow = ["payroll"]                   # DIDs or [groups] names
+//! ```
+//!
+//! Ranges compare the account code (what comes before any ':'), so both
+//! ends should have as many digits as the codes in the chart.
+//!
+//! A top-level `authors` list is the ledger's registry of trusted DIDs:
+//! with one, a transaction by anyone else fails verification whatever it
+//! posts to. A server hosting several ledgers gives each its own (see the
+//! CLI's tenants.rs).
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+use crate::amount::parse_cents;
+use crate::error::LedgerError;
+use crate::model::{JournalEntry, Transaction};
+use crate::validation::ValidationRule;
+
+/// Which side of an entry a permission covers
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Debit,
+    Credit,
+    #[default]
+    Both,
+}
+
+/// A set of accounts: an exact code, a prefix, or an inclusive range
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccountPattern {
+    Exact(String),
+    Prefix(String),
+    Range(String, String),
+}
+
+impl AccountPattern {
+    pub fn parse(pattern: &str) -> Result<Self, LedgerError> {
+        if let Some(prefix) = pattern.strip_suffix('*') {
+            return Ok(AccountPattern::Prefix(prefix.to_string()));
+        }
+        match pattern.split_once('-') {
+            Some((low, high)) if !low.is_empty() && low.len() == high.len() && low <= high => {
+                Ok(AccountPattern::Range(low.to_string(), high.to_string()))
+            }
+            Some(_) => Err(LedgerError::Config(format!("'{}' is not a range of codes of the same length, low to high", pattern))),
+            None => Ok(AccountPattern::Exact(pattern.to_string())),
+        }
+    }
+
+    pub fn matches(&self, account_id: &str) -> bool {
+        let code = account_id.split(':').next().unwrap_or(account_id);
+        match self {
+            AccountPattern::Exact(exact) => account_id == exact || code == exact,
+            AccountPattern::Prefix(prefix) => account_id.starts_with(prefix.as_str()),
+            AccountPattern::Range(low, high) => code.len() == low.len() && low.as_str() <= code && code <= high.as_str(),
+        }
+    }
+
+    /// Whether every account this pattern matches is also matched by
+    /// `outer` (conservatively: a prefix is never within a range)
+    pub fn within(&self, outer: &AccountPattern) -> bool {
+        match (self, outer) {
+            (AccountPattern::Exact(code), _) => outer.matches(code),
+            (AccountPattern::Prefix(prefix), AccountPattern::Prefix(outer)) => prefix.starts_with(outer.as_str()),
+            (AccountPattern::Range(low, high), AccountPattern::Prefix(outer)) => low.starts_with(outer.as_str()) && high.starts_with(outer.as_str()),
+            (AccountPattern::Range(low, high), AccountPattern::Range(outer_low, outer_high)) => {
+                low.len() == outer_low.len() && outer_low <= low && high <= outer_high
+            }
+            _ => false,
+        }
+    }
+}
+
+/// One `[[acl]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AclConfig {
+    pub accounts: Vec<String>,
+    #[serde(default)]
+    pub side: Side,
+    pub allow: Vec<String>, // DIDs or group names
+}
+
+/// Accounts reserved for the listed identities
+#[derive(Debug, Clone)]
+pub struct AccountAcl {
+    pub accounts: Vec<AccountPattern>,
+    pub side: Side,
+    pub allowed: Vec<String>, // DIDs
+}
+
+impl AccountAcl {
+    pub fn from_config(config: &AclConfig, groups: &BTreeMap<String, Vec<String>>) -> Result<Self, LedgerError> {
+        if config.accounts.is_empty() {
+            return Err(LedgerError::Config("acl: no accounts listed".to_string()));
+        }
+        Ok(AccountAcl {
+            accounts: config.accounts.iter().map(|pattern| AccountPattern::parse(pattern)).collect::<Result<_, _>>().map_err(|e| e.context("acl"))?,
+            side: config.side,
+            allowed: expand_groups(&config.allow, groups).map_err(|e| e.context("acl"))?,
+        })
+    }
+
+    /// Whether this permission covers `entry`
+    fn covers(&self, entry: &JournalEntry) -> Result<bool, LedgerError> {
+        let on_side = match self.side {
+            Side::Both => true,
+            Side::Debit => parse_cents(&entry.debit)? != 0,
+            Side::Credit => parse_cents(&entry.credit)? != 0,
+        };
+        Ok(on_side && self.accounts.iter().any(|pattern| pattern.matches(&entry.account_id)))
+    }
+}
+
+impl ValidationRule for AccountAcl {
+    fn name(&self) -> &str {
+        "acl"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        if self.allowed.contains(&tx.author_did) {
+            return Ok(());
+        }
+        for (i, entry) in tx.entries.iter().enumerate() {
+            if self.covers(entry)? {
+                let verb = match self.side {
+                    Side::Debit => "debit",
+                    Side::Credit => "credit",
+                    Side::Both => "post to",
+                };
+                return Err(LedgerError::Config(format!("Entry #{}: {} may not {} {}", i, tx.author_did, verb, entry.account_id)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The only DIDs that may author transactions on a ledger
+#[derive(Debug, Clone)]
+pub struct AuthorRegistry {
+    pub authors: Vec<String>, // DIDs
+}
+
+impl AuthorRegistry {
+    pub fn new(authors: &[String], groups: &BTreeMap<String, Vec<String>>) -> Result<Self, LedgerError> {
+        Ok(AuthorRegistry { authors: expand_groups(authors, groups).map_err(|e| e.context("authors"))? })
+    }
+}
+
+impl ValidationRule for AuthorRegistry {
+    fn name(&self) -> &str {
+        "authors"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        if self.authors.contains(&tx.author_did) {
+            Ok(())
+        } else {
+            Err(LedgerError::Config(format!("{} is not a registered author of this ledger", tx.author_did)))
+        }
+    }
+}
+
+/// `names` as DIDs, each name a DID or one of `groups`
+pub fn expand_groups(names: &[String], groups: &BTreeMap<String, Vec<String>>) -> Result<Vec<String>, LedgerError> {
+    let mut dids = Vec::new();
+    for name in names {
+        match groups.get(name) {
+            Some(members) => dids.extend(members.iter().cloned()),
+            None if name.starts_with("did:") => dids.push(name.clone()),
+            None => return Err(LedgerError::Config(format!("'{}' is neither a DID nor a group", name))),
+        }
+    }
+    if dids.is_empty() {
+        return Err(LedgerError::Config("nobody is listed".to_string()));
+    }
+    Ok(dids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+
+    fn posting(author: &str, debit: &str, credit: &str) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .author(author)
+            .entry(debit, "10.00", "0.00")
+            .entry(credit, "0.00", "10.00")
+            .memo("Posting")
+            .genesis()
+            .build()
+            .unwrap()
+    }
+
+    fn acl(accounts: &[&str], side: Side, allow: &[&str], groups: &BTreeMap<String, Vec<String>>) -> Result<AccountAcl, LedgerError> {
+        let config = AclConfig {
+            accounts: accounts.iter().map(|account| account.to_string()).collect(),
+            side,
+            allow: allow.iter().map(|name| name.to_string()).collect(),
+        };
+        AccountAcl::from_config(&config, groups)
+    }
+
+    #[test]
+    fn patterns_match_codes_prefixes_and_ranges() {
+        let range = AccountPattern::parse("61000-61999").unwrap();
+        assert!(range.matches("61500") && range.matches("61999:payroll"));
+        assert!(!range.matches("62000") && !range.matches("615000"));
+        assert!(AccountPattern::parse("2150*").unwrap().matches("21501"));
+        assert!(AccountPattern::parse("10100").unwrap().matches("10100:main"));
+
+        for invalid in ["61999-61000", "100-1000", "-100"] {
+            assert!(AccountPattern::parse(invalid).is_err(), "{}", invalid);
+        }
+
+        assert!(AccountPattern::parse("61500-61600").unwrap().within(&range));
+        assert!(AccountPattern::parse("615*").unwrap().within(&AccountPattern::parse("61*").unwrap()));
+        assert!(!AccountPattern::parse("615*").unwrap().within(&range));
+        assert!(!range.within(&AccountPattern::parse("61500-61600").unwrap()));
+    }
+
+    #[test]
+    fn reserved_accounts_refuse_other_authors_on_the_reserved_side() {
+        let (payroll, clerk) = (Account::generate(), Account::generate());
+        let groups = BTreeMap::from([("payroll".to_string(), vec![payroll.did.clone()])]);
+        let debits = acl(&["61000-61999"], Side::Debit, &["payroll"], &groups).unwrap();
+
+        assert!(debits.check(&posting(&payroll.did, "61100", "10100")).is_ok());
+        let refused = debits.check(&posting(&clerk.did, "61100", "10100")).unwrap_err().to_string();
+        assert!(refused.contains("may not debit 61100"), "{}", refused);
+        // Crediting the reserved accounts is still open
+        assert!(debits.check(&posting(&clerk.did, "10100", "61100")).is_ok());
+        assert!(acl(&["61000-61999"], Side::Both, &["payroll"], &groups).unwrap().check(&posting(&clerk.did, "10100", "61100")).is_err());
+    }
+
+    #[test]
+    fn unknown_names_and_empty_lists_are_config_errors() {
+        let groups = BTreeMap::from([("nobody".to_string(), Vec::new())]);
+        assert!(acl(&[], Side::Both, &["did:key:z6Mk"], &groups).is_err());
+        assert!(acl(&["10100"], Side::Both, &["payroll"], &groups).is_err());
+        assert!(acl(&["10100"], Side::Both, &["nobody"], &groups).is_err());
+        assert!(AuthorRegistry::new(&[], &groups).is_err());
+    }
+
+    #[test]
+    fn only_registered_authors_may_post() {
+        let (member, stranger) = (Account::generate(), Account::generate());
+        let registry = AuthorRegistry::new(std::slice::from_ref(&member.did), &BTreeMap::new()).unwrap();
+        assert!(registry.check(&posting(&member.did, "10100", "30100")).is_ok());
+        assert!(registry.check(&posting(&stranger.did, "10100", "30100")).is_err());
+    }
+}
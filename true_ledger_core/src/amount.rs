@@ -0,0 +1,67 @@
+//! Amounts in Cents
+//! Journal amounts are decimal strings. Anything that adds many of them up
+//! (imports, trial balances) works in integer cents so totals are exact.
+
+use crate::error::LedgerError;
+
+/// Parses a decimal amount ("1234.5", "-0.01", "") into integer cents
+pub fn parse_cents(amount: &str) -> Result<i64, LedgerError> {
+    let amount = amount.trim().trim_matches('"');
+    if amount.is_empty() {
+        return Ok(0);
+    }
+    let (negative, digits) = match amount.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, amount),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if whole.is_empty() || fraction.len() > 2
+        || !whole.chars().all(|c| c.is_ascii_digit())
+        || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return Err(LedgerError::Amount(format!("'{}' is not an amount with at most 2 decimals", amount)));
+    }
+    let whole: i64 = whole.parse().map_err(|_| LedgerError::Amount(format!("'{}' is too large", amount)))?;
+    let fraction: i64 = format!("{:0<2}", fraction).parse().unwrap_or(0);
+    let cents = whole.checked_mul(100).and_then(|c| c.checked_add(fraction))
+        .ok_or_else(|| LedgerError::Amount(format!("'{}' is too large", amount)))?;
+    Ok(if negative { -cents } else { cents })
+}
+
+/// Formats an amount in cents as a "1234.56" string ("-0.01" when negative)
+pub fn format_cents(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_parse_to_exact_cents() {
+        assert_eq!(parse_cents("1234.5").unwrap(), 123450);
+        assert_eq!(parse_cents("-0.01").unwrap(), -1);
+        assert_eq!(parse_cents(" \"7\" ").unwrap(), 700);
+        assert_eq!(parse_cents("").unwrap(), 0);
+        assert_eq!(parse_cents("92233720368547758.07").unwrap(), i64::MAX);
+    }
+
+    #[test]
+    fn malformed_or_overflowing_amounts_are_refused() {
+        for amount in ["1.234", ".50", "-", "1,000.00", "1e3", "+5", "12.-3", "92233720368547758.08", "99999999999999999999"] {
+            assert!(parse_cents(amount).is_err(), "{}", amount);
+        }
+    }
+
+    #[test]
+    fn cents_format_back() {
+        assert_eq!(format_cents(123450), "1234.50");
+        assert_eq!(format_cents(-1), "-0.01");
+        assert_eq!(format_cents(0), "0.00");
+        assert_eq!(format_cents(i64::MIN), "-92233720368547758.08");
+        for cents in [1, -99, 100, 987654321] {
+            assert_eq!(parse_cents(&format_cents(cents)).unwrap(), cents);
+        }
+    }
+}
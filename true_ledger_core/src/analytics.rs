@@ -0,0 +1,335 @@
+//! Distribution Analytics
+//! A cheap fraud-screening layer over verified transactions: the amounts
+//! posted to each account (or signed by each author) over a period are
+//! tested against Benford's law and summarised, and whatever stands out is
+//! flagged for a person to look at. A flag is a reason to ask, not a finding.
+//!
+//! The unit of analysis is one entry's amount, debit or credit. Benford's
+//! law says the first digit d of naturally occurring amounts appears with
+//! probability log10(1 + 1/d); invented amounts rarely follow it. The test
+//! leaves out amounts under 10.00, whose first digits are set by prices and
+//! fees, and needs at least 50 amounts. Conformity is judged by the mean
+//! absolute deviation (Nigrini's first-digit thresholds), since chi-square
+//! rejects almost any large ledger; chi-square and each digit's z-statistic
+//! are reported beside it. Amounts more than 3 standard deviations from
+//! their group's mean are flagged as outliers.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::error::LedgerError;
+use crate::fiscal::Cutoff;
+use crate::model::Transaction;
+use crate::verify::entry_cents;
+
+/// Amounts below this are left out of the first-digit test
+const BENFORD_MIN_CENTS: i64 = 1000;
+
+/// Fewer amounts than this are too few to test
+const BENFORD_MIN_AMOUNTS: usize = 50;
+
+/// 8 degrees of freedom at 5% significance
+const CHI_SQUARE_CRITICAL: f64 = 15.507;
+
+/// Two-tailed 5% significance
+const Z_CRITICAL: f64 = 1.96;
+
+/// Standard deviations from the mean that make an amount an outlier
+const OUTLIER_Z: f64 = 3.0;
+
+/// Whose amounts are grouped together
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Account,
+    Author,
+}
+
+/// How closely first digits follow Benford's law, by mean absolute deviation
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Conformity {
+    Close,         // MAD up to 0.006
+    Acceptable,    // Up to 0.012
+    Marginal,      // Up to 0.015
+    Nonconformity, // Above
+}
+
+impl Conformity {
+    fn from_mad(mad: f64) -> Self {
+        match mad {
+            mad if mad <= 0.006 => Conformity::Close,
+            mad if mad <= 0.012 => Conformity::Acceptable,
+            mad if mad <= 0.015 => Conformity::Marginal,
+            _ => Conformity::Nonconformity,
+        }
+    }
+}
+
+/// One first digit's share of the amounts
+#[derive(Serialize, Debug, Clone)]
+pub struct DigitLine {
+    pub digit: u8,
+    pub count: u64,
+    pub observed: f64, // Share of the tested amounts
+    pub expected: f64, // Benford's log10(1 + 1/d)
+    pub z: f64,
+    pub significant: bool, // z above 1.96
+}
+
+/// The first-digit test of one group's amounts
+#[derive(Serialize, Debug, Clone)]
+pub struct BenfordTest {
+    pub amounts: usize, // Amounts of 10.00 or more, the ones tested
+    pub digits: Vec<DigitLine>,
+    pub chi_square: f64,
+    pub chi_square_exceeded: bool, // Above the 5% critical value for 8 degrees of freedom
+    pub mad: f64,
+    pub conformity: Conformity,
+}
+
+/// The first significant digit of a non-zero amount
+fn first_digit(cents: i64) -> u8 {
+    let mut n = cents.unsigned_abs();
+    while n >= 10 {
+        n /= 10;
+    }
+    n as u8
+}
+
+impl BenfordTest {
+    /// Tests the amounts, or returns None when too few are large enough
+    pub fn run(cents: &[i64]) -> Option<Self> {
+        let tested: Vec<i64> = cents.iter().copied().filter(|&c| c >= BENFORD_MIN_CENTS).collect();
+        if tested.len() < BENFORD_MIN_AMOUNTS {
+            return None;
+        }
+        let mut counts = [0u64; 9];
+        for &c in &tested {
+            counts[first_digit(c) as usize - 1] += 1;
+        }
+        let n = tested.len() as f64;
+        let digits: Vec<DigitLine> = (1..=9u8).map(|digit| {
+            let count = counts[digit as usize - 1];
+            let observed = count as f64 / n;
+            let expected = (1.0 + 1.0 / digit as f64).log10();
+            // With the continuity correction, which keeps small deviations from counting
+            let z = ((observed - expected).abs() - 1.0 / (2.0 * n)).max(0.0) / (expected * (1.0 - expected) / n).sqrt();
+            DigitLine { digit, count, observed, expected, z, significant: z > Z_CRITICAL }
+        }).collect();
+        let chi_square = digits.iter().map(|line| (line.count as f64 - n * line.expected).powi(2) / (n * line.expected)).sum();
+        let mad = digits.iter().map(|line| (line.observed - line.expected).abs()).sum::<f64>() / 9.0;
+        Some(BenfordTest {
+            amounts: tested.len(),
+            digits,
+            chi_square,
+            chi_square_exceeded: chi_square > CHI_SQUARE_CRITICAL,
+            mad,
+            conformity: Conformity::from_mad(mad),
+        })
+    }
+}
+
+/// One amount far from its group's mean
+#[derive(Serialize, Debug, Clone)]
+pub struct Outlier {
+    pub tx_hash: String,
+    pub date: NaiveDate,
+    pub amount_cents: i64,
+    pub z: f64,
+}
+
+/// Summary statistics of one group's amounts
+#[derive(Serialize, Debug, Clone)]
+pub struct AmountStats {
+    pub count: usize,
+    pub total_cents: i64,
+    pub mean_cents: f64,
+    pub median_cents: i64,
+    pub std_dev_cents: f64, // Population standard deviation
+    pub max_cents: i64,
+    pub round_share: f64, // Share of amounts that are whole hundreds (100.00, 2500.00, ...)
+}
+
+/// One account's or author's amounts over the period
+#[derive(Serialize, Debug, Clone)]
+pub struct GroupReport {
+    pub key: String, // Account code or author DID
+    pub stats: AmountStats,
+    pub benford: Option<BenfordTest>, // None: too few amounts of 10.00 or more
+    pub outliers: Vec<Outlier>,
+    pub flagged: bool, // First digits do not conform, or there are outliers
+}
+
+/// One amount and where it came from
+struct Observation {
+    tx_hash: String,
+    date: NaiveDate,
+    cents: i64,
+}
+
+impl GroupReport {
+    fn new(key: String, observations: &[Observation]) -> Self {
+        let mut sorted: Vec<i64> = observations.iter().map(|o| o.cents).collect();
+        sorted.sort_unstable();
+        let count = sorted.len();
+        let total_cents = sorted.iter().sum();
+        let mean = total_cents as f64 / count as f64;
+        let std_dev = (sorted.iter().map(|&c| (c as f64 - mean).powi(2)).sum::<f64>() / count as f64).sqrt();
+        let stats = AmountStats {
+            count,
+            total_cents,
+            mean_cents: mean,
+            median_cents: sorted[count / 2],
+            std_dev_cents: std_dev,
+            max_cents: sorted[count - 1],
+            round_share: sorted.iter().filter(|&&c| c % 10_000 == 0).count() as f64 / count as f64,
+        };
+        let outliers: Vec<Outlier> = if std_dev > 0.0 {
+            observations.iter()
+                .map(|o| (o, (o.cents as f64 - mean) / std_dev))
+                .filter(|(_, z)| z.abs() > OUTLIER_Z)
+                .map(|(o, z)| Outlier { tx_hash: o.tx_hash.clone(), date: o.date, amount_cents: o.cents, z })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let benford = BenfordTest::run(&sorted);
+        let flagged = benford.as_ref().is_some_and(|test| test.conformity == Conformity::Nonconformity) || !outliers.is_empty();
+        GroupReport { key, stats, benford, outliers, flagged }
+    }
+}
+
+/// The distribution report over a period
+#[derive(Serialize, Debug, Clone)]
+pub struct AnomalyReport {
+    pub from: Option<NaiveDate>, // None: from the first transaction
+    pub to: NaiveDate,
+    pub by: GroupBy,
+    pub transactions: usize, // How many transactions fall in the period
+    pub groups: Vec<GroupReport>,
+}
+
+impl AnomalyReport {
+    /// Analyses the entries of verified transactions dated from `from` (or
+    /// the start of the books) through the `to` cutoff
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I, by: GroupBy, from: Option<NaiveDate>, to: Cutoff) -> Result<Self, LedgerError> {
+        if from.is_some_and(|from| from > to.date) {
+            return Err(LedgerError::Config(format!("The period ends ({}) before it starts", to.date)));
+        }
+        let mut groups: BTreeMap<String, Vec<Observation>> = BTreeMap::new();
+        let mut transactions = 0;
+        for tx in txs {
+            let date = tx.timestamp.local_date();
+            if from.is_some_and(|from| date < from) || !to.includes(tx) {
+                continue;
+            }
+            transactions += 1;
+            let tx_hash = tx.hash_hex();
+            for entry in &tx.entries {
+                let cents = entry_cents(&entry.debit, "debit")? + entry_cents(&entry.credit, "credit")?;
+                if cents == 0 {
+                    continue;
+                }
+                let key = match by {
+                    GroupBy::Account => entry.account_id.clone(),
+                    GroupBy::Author => tx.author_did.clone(),
+                };
+                groups.entry(key).or_default().push(Observation { tx_hash: tx_hash.clone(), date, cents });
+            }
+        }
+        Ok(AnomalyReport {
+            from,
+            to: to.date,
+            by,
+            transactions,
+            groups: groups.into_iter().map(|(key, observations)| GroupReport::new(key, &observations)).collect(),
+        })
+    }
+
+    pub fn flagged(&self) -> impl Iterator<Item = &GroupReport> {
+        self.groups.iter().filter(|group| group.flagged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::format_cents;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+
+    /// A sale of `cents` on `day` of January 2025
+    fn sale(author: &Account, day: u32, cents: i64) -> Transaction {
+        let amount = format_cents(cents);
+        let date = NaiveDate::from_ymd_opt(2025, 1, day).unwrap();
+        TransactionBuilder::new()
+            .timestamp(date.and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp() as u64)
+            .entry("10100", amount.as_str(), "0.00")
+            .entry("40100", "0.00", amount.as_str())
+            .memo("Sale")
+            .genesis()
+            .sign(author)
+            .unwrap()
+            .payload
+    }
+
+    /// Amounts spread evenly on a log scale, which follow Benford's law
+    fn benford_amounts(n: usize) -> Vec<i64> {
+        (0..n).map(|i| (1000.0 * 10f64.powf(3.0 * i as f64 / n as f64)) as i64).collect()
+    }
+
+    #[test]
+    fn first_digits_of_natural_amounts_conform() {
+        let test = BenfordTest::run(&benford_amounts(900)).unwrap();
+        assert_eq!(test.conformity, Conformity::Close);
+        assert!(!test.chi_square_exceeded);
+        assert!(test.digits.iter().all(|line| !line.significant));
+    }
+
+    #[test]
+    fn invented_amounts_do_not_conform() {
+        let invented: Vec<i64> = (0..200).map(|i| 490_000 + i * 7).collect(); // Just under an approval limit of 5000.00
+        let test = BenfordTest::run(&invented).unwrap();
+        assert_eq!(test.conformity, Conformity::Nonconformity);
+        assert!(test.chi_square_exceeded);
+        assert!(test.digits[3].significant);
+        assert!(BenfordTest::run(&invented[..49]).is_none());
+        assert!(BenfordTest::run(&[999; 100]).is_none());
+    }
+
+    #[test]
+    fn amounts_are_grouped_and_filtered_to_the_period() {
+        let alice = Account::generate();
+        let bob = Account::generate();
+        let txs = vec![sale(&alice, 2, 10_000), sale(&alice, 10, 20_000), sale(&bob, 20, 30_000)];
+        let to = Cutoff::day(NaiveDate::from_ymd_opt(2025, 1, 15).unwrap());
+
+        let report = AnomalyReport::from_transactions(&txs, GroupBy::Account, NaiveDate::from_ymd_opt(2025, 1, 5), to).unwrap();
+        assert_eq!(report.transactions, 1);
+        assert_eq!(report.groups.iter().map(|g| g.key.as_str()).collect::<Vec<_>>(), ["10100", "40100"]);
+        assert_eq!(report.groups[0].stats.total_cents, 20_000);
+
+        let report = AnomalyReport::from_transactions(&txs, GroupBy::Author, None, Cutoff::day(NaiveDate::MAX)).unwrap();
+        let alice_group = report.groups.iter().find(|g| g.key == alice.did).unwrap();
+        assert_eq!(alice_group.stats.count, 4);
+        assert_eq!(alice_group.stats.round_share, 1.0);
+        assert!(AnomalyReport::from_transactions(&txs, GroupBy::Author, NaiveDate::from_ymd_opt(2025, 2, 1), to).is_err());
+    }
+
+    #[test]
+    fn an_outlying_amount_is_flagged() {
+        let author = Account::generate();
+        let mut txs: Vec<Transaction> = (0..30).map(|i| sale(&author, 1 + i % 28, 5_000 + i as i64 * 13)).collect();
+        txs.push(sale(&author, 28, 900_000));
+        let report = AnomalyReport::from_transactions(&txs, GroupBy::Account, None, Cutoff::day(NaiveDate::MAX)).unwrap();
+        let cash = &report.groups[0];
+        assert!(cash.flagged);
+        assert!(cash.benford.is_none());
+        assert_eq!(cash.outliers.len(), 1);
+        assert_eq!(cash.outliers[0].amount_cents, 900_000);
+        assert_eq!(cash.outliers[0].tx_hash, txs[30].hash_hex());
+        assert_eq!(report.flagged().count(), 2);
+    }
+}
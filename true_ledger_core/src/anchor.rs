@@ -0,0 +1,561 @@
+//! External Anchoring
+//! A signed checkpoint proves what the operator claimed, not when. Anchoring
+//! publishes the checkpoint's digest somewhere the operator cannot backdate:
+//!
+//! * OpenTimestamps: the digest goes to public calendar servers, which
+//!   aggregate many digests into one Bitcoin transaction. The proof starts
+//!   out pending and is upgraded, hours later, to a path from the digest to
+//!   a Bitcoin block's Merkle root. Stored as a standard detached `.ots` file.
+//! * OP_RETURN: the digest is written directly into a Bitcoin transaction
+//!   through a bitcoind wallet (JSON-RPC), at the operator's expense.
+//!
+//! Either proof is checked against Bitcoin itself (a block explorer, or the
+//! bitcoind node), never against the party that made it.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::error::LedgerError;
+use crate::merkle::{parse_hash, Hash};
+
+/// Public calendars a new timestamp is submitted to
+pub const DEFAULT_CALENDARS: &[&str] = &["https://a.pool.opentimestamps.org", "https://b.pool.opentimestamps.org"];
+/// Esplora API used to look up Bitcoin block headers
+pub const DEFAULT_ESPLORA: &str = "https://blockstream.info/api";
+/// Calendars a pending proof may be upgraded from, besides the ones configured
+const TRUSTED_CALENDAR_DOMAINS: &[&str] = &["opentimestamps.org", "eternitywall.com", "catallaxy.com"];
+
+/// An anchored digest and the backend's evidence for it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "backend", rename_all = "kebab-case")]
+pub enum Anchor {
+    #[serde(rename = "opentimestamps")]
+    OpenTimestamps {
+        digest: String, // SHA-256 (hex) of what was anchored
+        ots: String,    // Detached .ots timestamp file, base64
+    },
+    OpReturn {
+        digest: String,
+        txid: String,   // Bitcoin transaction whose OP_RETURN output carries the digest
+    },
+}
+
+/// What a verified anchor proves
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnchorStatus {
+    /// Submitted, but not yet in a block (the message says what it waits on)
+    Pending(String),
+    /// The digest existed no later than this block
+    Confirmed { block_height: u64, block_time: u64 },
+}
+
+impl Anchor {
+    /// The anchored digest
+    pub fn digest(&self) -> Result<Hash, LedgerError> {
+        match self {
+            Anchor::OpenTimestamps { digest, .. } | Anchor::OpReturn { digest, .. } => parse_hash(digest),
+        }
+    }
+}
+
+/// Where digests get published and how the resulting anchors are checked
+pub trait AnchorBackend {
+    /// Publishes `digest` and returns the (possibly still pending) anchor
+    fn submit(&self, digest: &Hash) -> Result<Anchor, LedgerError>;
+
+    /// Completes a pending anchor where the backend allows it
+    fn upgrade(&self, anchor: &Anchor) -> Result<Anchor, LedgerError> {
+        Ok(anchor.clone())
+    }
+
+    /// Checks the anchor against the blockchain
+    fn verify(&self, anchor: &Anchor) -> Result<AnchorStatus, LedgerError>;
+}
+
+fn invalid(message: String) -> LedgerError {
+    LedgerError::Timestamp(message)
+}
+
+// The OpenTimestamps proof format, as python-opentimestamps writes it
+const OTS_MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const OTS_VERSION: u64 = 1;
+const OTS_MAX_DEPTH: usize = 256;
+const OTS_MAX_MESSAGE: usize = 4096;
+
+const TAG_ATTESTATION: u8 = 0x00;
+const TAG_FORK: u8 = 0xff;
+const OP_SHA256: u8 = 0x08;
+const OP_APPEND: u8 = 0xf0;
+const OP_PREPEND: u8 = 0xf1;
+const OP_REVERSE: u8 = 0xf2;
+const OP_HEXLIFY: u8 = 0xf3;
+
+const PENDING_TAG: [u8; 8] = [0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e];
+const BITCOIN_TAG: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Sha256,
+    Append(Vec<u8>),
+    Prepend(Vec<u8>),
+    Reverse,
+    Hexlify,
+}
+
+impl Op {
+    fn apply(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Op::Sha256 => Sha256::digest(message).to_vec(),
+            Op::Append(suffix) => [message, suffix.as_slice()].concat(),
+            Op::Prepend(prefix) => [prefix.as_slice(), message].concat(),
+            Op::Reverse => message.iter().rev().copied().collect(),
+            Op::Hexlify => hex::encode(message).into_bytes(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Attestation {
+    Pending(String), // Calendar URL to ask for the upgrade
+    Bitcoin(u64),    // Block height whose Merkle root the message is
+    Unknown([u8; 8], Vec<u8>),
+}
+
+/// The commitments made from one message: attestations of it, and operations
+/// that lead to further messages
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Stamp {
+    attestations: Vec<Attestation>,
+    ops: Vec<(Op, Stamp)>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], LedgerError> {
+        let bytes = self.data.get(self.pos..self.pos + n)
+            .ok_or_else(|| invalid("Timestamp proof is truncated".to_string()))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, LedgerError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varuint(&mut self) -> Result<u64, LedgerError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(invalid("Timestamp proof has an oversized integer".to_string()))
+    }
+
+    fn varbytes(&mut self, max: usize) -> Result<&'a [u8], LedgerError> {
+        let len = self.varuint()? as usize;
+        if len > max {
+            return Err(invalid(format!("Timestamp proof field of {} bytes exceeds {}", len, max)));
+        }
+        self.bytes(len)
+    }
+}
+
+fn write_varuint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_varbytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varuint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+impl Stamp {
+    fn read(reader: &mut Reader, depth: usize) -> Result<Self, LedgerError> {
+        if depth > OTS_MAX_DEPTH {
+            return Err(invalid("Timestamp proof is nested too deeply".to_string()));
+        }
+        let mut stamp = Stamp::default();
+        loop {
+            let tag = reader.byte()?;
+            let more = tag == TAG_FORK;
+            let tag = if more { reader.byte()? } else { tag };
+            if tag == TAG_ATTESTATION {
+                stamp.attestations.push(Self::read_attestation(reader)?);
+            } else {
+                let op = match tag {
+                    OP_SHA256 => Op::Sha256,
+                    OP_APPEND => Op::Append(reader.varbytes(OTS_MAX_MESSAGE)?.to_vec()),
+                    OP_PREPEND => Op::Prepend(reader.varbytes(OTS_MAX_MESSAGE)?.to_vec()),
+                    OP_REVERSE => Op::Reverse,
+                    OP_HEXLIFY => Op::Hexlify,
+                    other => return Err(invalid(format!("Unsupported timestamp operation 0x{:02x}", other))),
+                };
+                stamp.ops.push((op, Self::read(reader, depth + 1)?));
+            }
+            if !more {
+                return Ok(stamp);
+            }
+        }
+    }
+
+    fn read_attestation(reader: &mut Reader) -> Result<Attestation, LedgerError> {
+        let tag: [u8; 8] = reader.bytes(8)?.try_into().expect("eight bytes");
+        let mut payload = Reader { data: reader.varbytes(8192)?, pos: 0 };
+        Ok(match tag {
+            PENDING_TAG => {
+                let uri = payload.varbytes(1000)?;
+                Attestation::Pending(String::from_utf8(uri.to_vec())
+                    .map_err(|_| invalid("Pending attestation has a non-UTF-8 URL".to_string()))?)
+            }
+            BITCOIN_TAG => Attestation::Bitcoin(payload.varuint()?),
+            tag => Attestation::Unknown(tag, payload.data.to_vec()),
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let count = self.attestations.len() + self.ops.len();
+        let mut written = 0;
+        let mut separator = |out: &mut Vec<u8>| {
+            written += 1;
+            if written < count {
+                out.push(TAG_FORK);
+            }
+        };
+        for attestation in &self.attestations {
+            separator(out);
+            out.push(TAG_ATTESTATION);
+            let (tag, payload) = match attestation {
+                Attestation::Pending(uri) => {
+                    let mut payload = Vec::new();
+                    write_varbytes(&mut payload, uri.as_bytes());
+                    (PENDING_TAG, payload)
+                }
+                Attestation::Bitcoin(height) => {
+                    let mut payload = Vec::new();
+                    write_varuint(&mut payload, *height);
+                    (BITCOIN_TAG, payload)
+                }
+                Attestation::Unknown(tag, payload) => (*tag, payload.clone()),
+            };
+            out.extend_from_slice(&tag);
+            write_varbytes(out, &payload);
+        }
+        for (op, child) in &self.ops {
+            separator(out);
+            match op {
+                Op::Sha256 => out.push(OP_SHA256),
+                Op::Append(suffix) => {
+                    out.push(OP_APPEND);
+                    write_varbytes(out, suffix);
+                }
+                Op::Prepend(prefix) => {
+                    out.push(OP_PREPEND);
+                    write_varbytes(out, prefix);
+                }
+                Op::Reverse => out.push(OP_REVERSE),
+                Op::Hexlify => out.push(OP_HEXLIFY),
+            }
+            child.write(out);
+        }
+    }
+
+    /// Adds everything `other` commits to (from the same message)
+    fn merge(&mut self, other: Stamp) {
+        for attestation in other.attestations {
+            if !self.attestations.contains(&attestation) {
+                self.attestations.push(attestation);
+            }
+        }
+        for (op, child) in other.ops {
+            match self.ops.iter_mut().find(|(existing, _)| *existing == op) {
+                Some((_, existing)) => existing.merge(child),
+                None => self.ops.push((op, child)),
+            }
+        }
+    }
+
+    /// Every attestation in the tree, with the message it attests
+    fn attestations_from(&self, message: &[u8], found: &mut Vec<(Vec<u8>, Attestation)>) -> Result<(), LedgerError> {
+        found.extend(self.attestations.iter().map(|a| (message.to_vec(), a.clone())));
+        for (op, child) in &self.ops {
+            let next = op.apply(message);
+            if next.len() > OTS_MAX_MESSAGE {
+                return Err(invalid("Timestamp proof builds an oversized message".to_string()));
+            }
+            child.attestations_from(&next, found)?;
+        }
+        Ok(())
+    }
+}
+
+/// A detached timestamp: the SHA-256 digest it is for, and its proof tree
+fn parse_ots(data: &[u8]) -> Result<(Hash, Stamp), LedgerError> {
+    let mut reader = Reader { data, pos: 0 };
+    if reader.bytes(OTS_MAGIC.len()).ok() != Some(OTS_MAGIC) {
+        return Err(invalid("Not an OpenTimestamps proof".to_string()));
+    }
+    if reader.varuint()? != OTS_VERSION {
+        return Err(invalid("Unsupported OpenTimestamps proof version".to_string()));
+    }
+    if reader.byte()? != OP_SHA256 {
+        return Err(invalid("Only SHA-256 timestamps are supported".to_string()));
+    }
+    let digest: Hash = reader.bytes(32)?.try_into().expect("32 bytes");
+    let stamp = Stamp::read(&mut reader, 0)?;
+    if reader.pos != data.len() {
+        return Err(invalid("Timestamp proof has trailing bytes".to_string()));
+    }
+    Ok((digest, stamp))
+}
+
+fn write_ots(digest: &Hash, stamp: &Stamp) -> Vec<u8> {
+    let mut out = OTS_MAGIC.to_vec();
+    write_varuint(&mut out, OTS_VERSION);
+    out.push(OP_SHA256);
+    out.extend_from_slice(digest);
+    stamp.write(&mut out);
+    out
+}
+
+/// Status code and body; error statuses are returned, not raised. Needs the
+/// `network` feature.
+#[cfg(feature = "network")]
+fn http(method: &str, url: &str, headers: &[(&str, &str)], body: Option<&[u8]>) -> Result<(u16, Vec<u8>), LedgerError> {
+    use std::io::Read;
+    let agent = ureq::AgentBuilder::new().timeout(std::time::Duration::from_secs(30)).build();
+    let mut request = agent.request(method, url).set("User-Agent", "true-ledger-core");
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    let response = match body {
+        Some(body) => request.send_bytes(body),
+        None => request.call(),
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(e) => return Err(LedgerError::Io(format!("Could not reach {}: {}", url, e))),
+    };
+    let status = response.status();
+    let mut data = Vec::new();
+    response.into_reader().take(1 << 20).read_to_end(&mut data)
+        .map_err(|e| LedgerError::Io(format!("Could not read the response from {}: {}", url, e)))?;
+    Ok((status, data))
+}
+
+#[cfg(not(feature = "network"))]
+fn http(_method: &str, url: &str, _headers: &[(&str, &str)], _body: Option<&[u8]>) -> Result<(u16, Vec<u8>), LedgerError> {
+    Err(LedgerError::Io(format!("Cannot reach {}: this build has no network support", url)))
+}
+
+fn http_ok(method: &str, url: &str, headers: &[(&str, &str)], body: Option<&[u8]>) -> Result<Vec<u8>, LedgerError> {
+    match http(method, url, headers, body)? {
+        (200, data) => Ok(data),
+        (status, data) => Err(LedgerError::Io(format!("{} answered {}: {}", url, status, String::from_utf8_lossy(&data).trim()))),
+    }
+}
+
+/// Timestamps through OpenTimestamps calendars; verifies Bitcoin attestations
+/// against block headers from an Esplora API
+pub struct OpenTimestamps {
+    pub calendars: Vec<String>,
+    pub esplora: String,
+}
+
+impl Default for OpenTimestamps {
+    fn default() -> Self {
+        OpenTimestamps {
+            calendars: DEFAULT_CALENDARS.iter().map(|c| c.to_string()).collect(),
+            esplora: DEFAULT_ESPLORA.to_string(),
+        }
+    }
+}
+
+const OTS_ACCEPT: (&str, &str) = ("Accept", "application/vnd.opentimestamps.v1");
+
+impl OpenTimestamps {
+    fn stamp_of(anchor: &Anchor) -> Result<(Hash, Stamp), LedgerError> {
+        let Anchor::OpenTimestamps { digest, ots } = anchor else {
+            return Err(LedgerError::Config("Not an OpenTimestamps anchor".to_string()));
+        };
+        let data = STANDARD.decode(ots).map_err(|e| invalid(format!("Invalid base64 timestamp proof: {}", e)))?;
+        let (stamped, stamp) = parse_ots(&data)?;
+        if stamped != parse_hash(digest)? {
+            return Err(invalid(format!("The timestamp proof is for {}, not {}", hex::encode(stamped), digest)));
+        }
+        Ok((stamped, stamp))
+    }
+
+    /// Pending proofs name the calendar to ask; only well-known ones (and
+    /// those configured) are contacted, so a proof cannot direct requests anywhere
+    fn trusts(&self, calendar: &str) -> bool {
+        if self.calendars.iter().any(|c| c.trim_end_matches('/') == calendar.trim_end_matches('/')) {
+            return true;
+        }
+        let Some(host) = calendar.strip_prefix("https://").map(|rest| rest.split(['/', ':']).next().unwrap_or_default()) else {
+            return false;
+        };
+        TRUSTED_CALENDAR_DOMAINS.iter().any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+    }
+
+    /// Asks the calendars of every pending attestation for their upgrade
+    fn upgrade_stamp(&self, stamp: &mut Stamp, message: &[u8]) -> Result<(), LedgerError> {
+        let pending: Vec<String> = stamp.attestations.iter()
+            .filter_map(|a| match a { Attestation::Pending(uri) => Some(uri.clone()), _ => None })
+            .collect();
+        for calendar in pending.iter().filter(|c| self.trusts(c)) {
+            let url = format!("{}/timestamp/{}", calendar.trim_end_matches('/'), hex::encode(message));
+            if let Ok((200, data)) = http("GET", &url, &[OTS_ACCEPT], None) {
+                stamp.merge(Stamp::read(&mut Reader { data: &data, pos: 0 }, 0)?);
+            }
+        }
+        for (op, child) in &mut stamp.ops {
+            self.upgrade_stamp(child, &op.apply(message))?;
+        }
+        Ok(())
+    }
+
+    /// The Merkle root (internal byte order) and time of the block at `height`
+    fn block(&self, height: u64) -> Result<(Vec<u8>, u64), LedgerError> {
+        let base = self.esplora.trim_end_matches('/');
+        let hash = http_ok("GET", &format!("{}/block-height/{}", base, height), &[], None)?;
+        let hash = String::from_utf8_lossy(&hash).trim().to_string();
+        let header: Value = serde_json::from_slice(&http_ok("GET", &format!("{}/block/{}", base, hash), &[], None)?)
+            .map_err(|e| LedgerError::Serialization(format!("Invalid block {}: {}", hash, e)))?;
+        let mut merkle_root = header["merkle_root"].as_str().and_then(|root| hex::decode(root).ok())
+            .ok_or_else(|| LedgerError::Serialization(format!("Block {} has no merkle_root", hash)))?;
+        merkle_root.reverse(); // Explorers show it byte-reversed
+        Ok((merkle_root, header["timestamp"].as_u64().unwrap_or_default()))
+    }
+}
+
+impl AnchorBackend for OpenTimestamps {
+    fn submit(&self, digest: &Hash) -> Result<Anchor, LedgerError> {
+        let mut stamp = Stamp::default();
+        let mut errors = Vec::new();
+        for calendar in &self.calendars {
+            let url = format!("{}/digest", calendar.trim_end_matches('/'));
+            match http_ok("POST", &url, &[OTS_ACCEPT], Some(digest)) {
+                Ok(data) => stamp.merge(Stamp::read(&mut Reader { data: &data, pos: 0 }, 0)?),
+                Err(e) => errors.push(e.to_string()),
+            }
+        }
+        if stamp == Stamp::default() {
+            return Err(LedgerError::Io(format!("No calendar accepted the digest: {}", errors.join("; "))));
+        }
+        Ok(Anchor::OpenTimestamps { digest: hex::encode(digest), ots: STANDARD.encode(write_ots(digest, &stamp)) })
+    }
+
+    fn upgrade(&self, anchor: &Anchor) -> Result<Anchor, LedgerError> {
+        let (digest, mut stamp) = Self::stamp_of(anchor)?;
+        self.upgrade_stamp(&mut stamp, &digest)?;
+        Ok(Anchor::OpenTimestamps { digest: hex::encode(digest), ots: STANDARD.encode(write_ots(&digest, &stamp)) })
+    }
+
+    /// Confirmed by the earliest Bitcoin attestation; any attestation whose
+    /// block does not have the claimed Merkle root makes the proof invalid
+    fn verify(&self, anchor: &Anchor) -> Result<AnchorStatus, LedgerError> {
+        let (digest, stamp) = Self::stamp_of(anchor)?;
+        let mut attestations = Vec::new();
+        stamp.attestations_from(&digest, &mut attestations)?;
+
+        let mut earliest: Option<AnchorStatus> = None;
+        let mut pending = Vec::new();
+        for (message, attestation) in attestations {
+            match attestation {
+                Attestation::Bitcoin(height) => {
+                    let (merkle_root, block_time) = self.block(height)?;
+                    if message != merkle_root {
+                        return Err(invalid(format!("Bitcoin block {} does not commit to the timestamp", height)));
+                    }
+                    if !matches!(earliest, Some(AnchorStatus::Confirmed { block_height, .. }) if block_height <= height) {
+                        earliest = Some(AnchorStatus::Confirmed { block_height: height, block_time });
+                    }
+                }
+                Attestation::Pending(calendar) => pending.push(calendar),
+                Attestation::Unknown(..) => {}
+            }
+        }
+        Ok(earliest.unwrap_or_else(|| AnchorStatus::Pending(format!("waiting on {}", pending.join(", ")))))
+    }
+}
+
+/// Writes the digest in an OP_RETURN output through a bitcoind wallet, and
+/// verifies it with the same node (which needs -txindex for old transactions)
+pub struct OpReturn {
+    pub rpc_url: String,
+    pub rpc_user: Option<String>,
+    pub rpc_password: Option<String>,
+}
+
+impl OpReturn {
+    fn call(&self, method: &str, params: Value) -> Result<Value, LedgerError> {
+        let body = json!({ "jsonrpc": "1.0", "id": "tlc", "method": method, "params": params }).to_string();
+        let auth = match (&self.rpc_user, &self.rpc_password) {
+            (Some(user), password) => Some(format!("Basic {}",
+                STANDARD.encode(format!("{}:{}", user, password.as_deref().unwrap_or_default())))),
+            _ => None,
+        };
+        let mut headers = vec![("Content-Type", "application/json")];
+        if let Some(auth) = &auth {
+            headers.push(("Authorization", auth));
+        }
+        let (status, data) = http("POST", &self.rpc_url, &headers, Some(body.as_bytes()))?;
+        let response: Value = serde_json::from_slice(&data).map_err(|_| LedgerError::Io(format!(
+            "bitcoind answered {} to {}: {}", status, method, String::from_utf8_lossy(&data).trim())))?;
+        match &response["error"] {
+            Value::Null => Ok(response["result"].clone()),
+            error => Err(LedgerError::Io(format!("bitcoind {} failed: {}", method, error["message"].as_str().unwrap_or(&error.to_string())))),
+        }
+    }
+}
+
+impl AnchorBackend for OpReturn {
+    fn submit(&self, digest: &Hash) -> Result<Anchor, LedgerError> {
+        let raw = self.call("createrawtransaction", json!([[], [{ "data": hex::encode(digest) }]]))?;
+        let funded = self.call("fundrawtransaction", json!([raw]))?;
+        let signed = self.call("signrawtransactionwithwallet", json!([funded["hex"]]))?;
+        if signed["complete"] != Value::Bool(true) {
+            return Err(LedgerError::Key("The bitcoind wallet could not sign the anchoring transaction".to_string()));
+        }
+        let txid = self.call("sendrawtransaction", json!([signed["hex"]]))?;
+        Ok(Anchor::OpReturn {
+            digest: hex::encode(digest),
+            txid: txid.as_str().unwrap_or_default().to_string(),
+        })
+    }
+
+    fn verify(&self, anchor: &Anchor) -> Result<AnchorStatus, LedgerError> {
+        let Anchor::OpReturn { digest, txid } = anchor else {
+            return Err(LedgerError::Config("Not an OP_RETURN anchor".to_string()));
+        };
+        let tx = self.call("getrawtransaction", json!([txid, true]))?;
+        let script = format!("6a20{}", parse_hash(digest).map(hex::encode)?); // OP_RETURN, push 32 bytes
+        let outputs = tx["vout"].as_array().cloned().unwrap_or_default();
+        if !outputs.iter().any(|output| output["scriptPubKey"]["hex"].as_str() == Some(&script)) {
+            return Err(invalid(format!("Transaction {} has no OP_RETURN output with {}", txid, digest)));
+        }
+        let Some(block_hash) = tx["blockhash"].as_str() else {
+            return Ok(AnchorStatus::Pending(format!("transaction {} is not yet in a block", txid)));
+        };
+        let header = self.call("getblockheader", json!([block_hash]))?;
+        Ok(AnchorStatus::Confirmed {
+            block_height: header["height"].as_u64().unwrap_or_default(),
+            block_time: header["time"].as_u64().unwrap_or_default(),
+        })
+    }
+}
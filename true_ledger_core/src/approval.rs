@@ -0,0 +1,161 @@
+//! Preparer and Approver
+//! Under the two-role workflow a transaction is posted only when the person
+//! who prepared it and at least one approver have both signed it. The
+//! preparer signs when submitting a draft (see lifecycle.rs), before the
+//! transaction has a chain position, so their signature covers its content:
+//! the payload without author, chain position, sequence or signing format,
+//! behind a context string of its own. The approver then posts it as the
+//! author, and further approvers may cosign; `prepared_by` is part of the
+//! payload, so every approver signs who prepared it.
+//!
+//! A preparation signature is checked wherever the author's is. Whether it
+//! is required, and who may approve, is the organisation's rule:
+//!
+//! ```toml
+//! [approval]
+//! approvers = ["did:key:z6Mk...", "controllers"]   # DIDs or [groups] names
+//! ```
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
+
+use crate::acl::expand_groups;
+use crate::canonical::to_jcs;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::lifecycle::content;
+use crate::model::{SignedTransaction, Transaction};
+use crate::signer::TransactionSigner;
+use crate::verify::decode_signature;
+
+/// Prefixed to the signed bytes so a preparation signature can never be
+/// replayed as a transaction signature, or the other way round
+const SIGNING_CONTEXT: &[u8] = b"true-ledger preparation v1\n";
+
+/// The preparer's signature over a transaction's content
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Preparation {
+    pub signature: String, // Multibase (base58btc)
+}
+
+/// The exact bytes the preparer signs
+fn signing_input(tx: &Transaction) -> Result<Vec<u8>, LedgerError> {
+    let mut input = SIGNING_CONTEXT.to_vec();
+    input.extend_from_slice(to_jcs(&content(tx)?)?.as_bytes());
+    Ok(input)
+}
+
+/// Signs `tx` as its preparer; `prepared_by` must be the signer's DID
+pub fn prepare(signer: &dyn TransactionSigner, tx: &Transaction) -> Result<Preparation, LedgerError> {
+    if tx.prepared_by.as_deref() != Some(signer.did()) {
+        return Err(LedgerError::Key(format!("The transaction is not prepared by {}", signer.did())));
+    }
+    let signature = signer.sign_bytes(&signing_input(tx)?)?;
+    Ok(Preparation { signature: multibase::encode(multibase::Base::Base58Btc, signature) })
+}
+
+/// Checks the preparer's signature, if the payload names a preparer. One
+/// without the other is an error.
+pub fn verify_preparation(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+    match (&signed_tx.payload.prepared_by, &signed_tx.preparation) {
+        (None, None) => Ok(()),
+        (Some(preparer), None) => Err(LedgerError::Approval(format!("Prepared by {}, but their signature is missing", preparer))),
+        (None, Some(_)) => Err(LedgerError::Approval("Carries a preparation signature but names no preparer".to_string())),
+        (Some(preparer), Some(preparation)) => {
+            let key = resolver.resolve_public_key(preparer)?;
+            key.verify_bytes(&signing_input(&signed_tx.payload)?, &decode_signature(&preparation.signature)?)
+                .map_err(|e| LedgerError::Approval(format!("Preparation signature by {}: {}", preparer, e)))
+        }
+    }
+}
+
+/// Who may approve under the two-role workflow
+#[derive(Debug, Clone, Default)]
+pub struct ApprovalWorkflow {
+    pub approvers: Vec<String>, // DIDs
+}
+
+impl ApprovalWorkflow {
+    /// The workflow for `approvers`, each a DID or the name of one of `groups`
+    pub fn new(approvers: &[String], groups: &BTreeMap<String, Vec<String>>) -> Result<Self, LedgerError> {
+        Ok(ApprovalWorkflow { approvers: expand_groups(approvers, groups).map_err(|e| e.context("approval"))? })
+    }
+
+    /// Checks that `tx` names a preparer and that a listed approver other
+    /// than the preparer is among `signers` (the DIDs whose signatures
+    /// verified). Returns the approvers found.
+    pub fn check(&self, tx: &Transaction, signers: &[&str]) -> Result<Vec<String>, LedgerError> {
+        let preparer = tx.prepared_by.as_ref()
+            .ok_or_else(|| LedgerError::Approval("No preparer: the transaction must be prepared and approved by different people".to_string()))?;
+        let approvals: Vec<String> = signers.iter()
+            .filter(|did| **did != preparer.as_str() && self.approvers.iter().any(|approver| approver == *did))
+            .map(|did| did.to_string())
+            .collect();
+        if approvals.is_empty() {
+            return Err(LedgerError::Approval(format!("Prepared by {}, but not signed by any approver", preparer)));
+        }
+        Ok(approvals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+
+    fn prepared(preparer: Option<&Account>, approver: &Account) -> Transaction {
+        let mut tx = TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .author(&approver.did)
+            .entry("68100", "250.00", "0.00")
+            .entry("10100", "0.00", "250.00")
+            .memo("Supplier invoice")
+            .genesis()
+            .build()
+            .unwrap();
+        tx.prepared_by = preparer.map(|preparer| preparer.did.clone());
+        tx
+    }
+
+    #[test]
+    fn the_preparation_signature_covers_the_content() {
+        let (preparer, approver, other) = (Account::generate(), Account::generate(), Account::generate());
+        let tx = prepared(Some(&preparer), &approver);
+        assert!(prepare(&other, &tx).is_err());
+        let preparation = prepare(&preparer, &tx).unwrap();
+
+        let mut signed_tx = approver.sign(tx.clone());
+        assert!(verify_preparation(&signed_tx, &DidKeyResolver).is_err());
+        signed_tx.preparation = Some(preparation.clone());
+        verify_preparation(&signed_tx, &DidKeyResolver).unwrap();
+
+        // Changing what was prepared, or who prepared it, breaks the signature
+        let mut tampered = signed_tx.clone();
+        tampered.payload.entries[0].debit = "2500.00".to_string();
+        assert!(verify_preparation(&tampered, &DidKeyResolver).is_err());
+        tampered = signed_tx.clone();
+        tampered.payload.prepared_by = Some(other.did.clone());
+        assert!(verify_preparation(&tampered, &DidKeyResolver).is_err());
+
+        // A preparation signature without a preparer is refused
+        let mut unnamed = approver.sign(prepared(None, &approver));
+        unnamed.preparation = Some(preparation);
+        assert!(verify_preparation(&unnamed, &DidKeyResolver).is_err());
+    }
+
+    #[test]
+    fn a_preparer_cannot_approve_their_own_transaction() {
+        let (preparer, approver) = (Account::generate(), Account::generate());
+        let groups = BTreeMap::from([("controllers".to_string(), vec![preparer.did.clone(), approver.did.clone()])]);
+        let workflow = ApprovalWorkflow::new(&["controllers".to_string()], &groups).unwrap();
+        let tx = prepared(Some(&preparer), &approver);
+
+        assert!(workflow.check(&tx, &[&preparer.did]).is_err());
+        assert_eq!(workflow.check(&tx, &[&preparer.did, &approver.did]).unwrap(), vec![approver.did.clone()]);
+        assert!(workflow.check(&prepared(None, &approver), &[&approver.did]).is_err());
+        assert!(ApprovalWorkflow::new(&["auditors".to_string()], &groups).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! Period Attestation
+//! Management's sign-off on a period: a designated signer (e.g. the CFO)
+//! signs the digest of the closing trial balance (see trial_balance.rs),
+//! stating that these are the books as of the period's end. Anyone holding
+//! the journal can rebuild the trial balance and see whether the books
+//! still match what was signed off, or were changed after it.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::canonical::to_jcs;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::fiscal::Cutoff;
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+use crate::trial_balance::TrialBalance;
+use crate::verify::decode_signature;
+
+/// Prefixed to the signed bytes so an attestation signature can never be
+/// replayed as any other kind of signature
+const SIGNING_CONTEXT: &[u8] = b"true-ledger period attestation v1\n";
+
+/// What the attester signs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Attestation {
+    pub attested_by: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,  // The capacity signed in, e.g. "CFO"
+    pub period: String,        // As named when signed: a day or a fiscal period
+    pub through: NaiveDate,    // Last day of the period, in each transaction's own time zone
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub adjustments: bool,     // Adjusting entries dated `through` (period 13) are included
+    pub trial_balance: String, // Hex digest of the closing trial balance
+    pub transactions: usize,   // How many transactions it covers
+    pub timestamp: Timestamp,  // When it was signed
+}
+
+/// An attestation and the attester's signature over it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedAttestation {
+    pub attestation: Attestation,
+    pub signature: String, // Multibase (base58btc)
+}
+
+/// Where an attestation stands against the books as they are now
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AttestationStatus {
+    Attested,                          // Signed by a designated attester; the books still match
+    Changed { trial_balance: String }, // The books now have this digest instead
+    NotDesignated,                     // Validly signed, but not by a designated attester
+    Invalid(String),                   // The signature does not verify
+}
+
+impl Attestation {
+    /// An attestation of `trial_balance` as the books through `through`
+    pub fn new(attested_by: &str, role: Option<String>, period: &str, through: Cutoff, trial_balance: &TrialBalance, timestamp: Timestamp) -> Result<Self, LedgerError> {
+        Ok(Attestation {
+            attested_by: attested_by.to_string(),
+            role,
+            period: period.to_string(),
+            through: through.date,
+            adjustments: through.adjustments,
+            trial_balance: trial_balance.digest()?,
+            transactions: trial_balance.transactions,
+            timestamp,
+        })
+    }
+
+    /// Which transactions the attested books include
+    pub fn cutoff(&self) -> Cutoff {
+        Cutoff { date: self.through, adjustments: self.adjustments }
+    }
+
+    /// The exact bytes the attester signs: a context string, then the JCS form
+    fn signing_input(&self) -> Result<Vec<u8>, LedgerError> {
+        let mut input = SIGNING_CONTEXT.to_vec();
+        input.extend_from_slice(to_jcs(self)?.as_bytes());
+        Ok(input)
+    }
+
+    /// Signs the attestation. `attested_by` must be the signer's own DID.
+    pub fn sign(self, signer: &dyn TransactionSigner) -> Result<SignedAttestation, LedgerError> {
+        if self.attested_by != signer.did() {
+            return Err(LedgerError::Key(format!("The attestation names {} but the signing key is {}", self.attested_by, signer.did())));
+        }
+        let signature = signer.sign_bytes(&self.signing_input()?)?;
+        Ok(SignedAttestation {
+            attestation: self,
+            signature: multibase::encode(multibase::Base::Base58Btc, signature),
+        })
+    }
+}
+
+impl SignedAttestation {
+    /// Reads a signed attestation from a JSON file (its signature is not checked)
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read attestation {}: {}", path, e)))?;
+        serde_json::from_str(&data).map_err(|e| LedgerError::Serialization(format!("Invalid attestation {}: {}", path, e)))
+    }
+
+    /// Checks the attester's signature
+    pub fn verify(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let key = resolver.resolve_public_key(&self.attestation.attested_by)?;
+        key.verify_bytes(&self.attestation.signing_input()?, &decode_signature(&self.signature)?)
+    }
+
+    /// The attestation's standing against `books`, the trial balance of the
+    /// journal through [`Attestation::cutoff`]. With no `attesters`, any signer counts.
+    pub fn status(&self, books: &TrialBalance, attesters: &[String], resolver: &dyn DidResolver) -> Result<AttestationStatus, LedgerError> {
+        if let Err(e) = self.verify(resolver) {
+            return Ok(AttestationStatus::Invalid(e.to_string()));
+        }
+        if !attesters.is_empty() && !attesters.contains(&self.attestation.attested_by) {
+            return Ok(AttestationStatus::NotDesignated);
+        }
+        let trial_balance = books.digest()?;
+        if trial_balance != self.attestation.trial_balance {
+            return Ok(AttestationStatus::Changed { trial_balance });
+        }
+        Ok(AttestationStatus::Attested)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::chain;
+
+    fn books(len: usize, clerk: &Account) -> TrialBalance {
+        TrialBalance::from_transactions(chain(clerk, len).iter().map(|signed_tx| &signed_tx.payload)).unwrap()
+    }
+
+    #[test]
+    fn books_changed_after_sign_off_no_longer_match() {
+        let cfo = Account::generate();
+        let clerk = Account::generate();
+        let through = Cutoff::day(NaiveDate::from_ymd_opt(2023, 11, 30).unwrap());
+        let attestation = Attestation::new(&cfo.did, Some("CFO".to_string()), "2023-11-30", through, &books(2, &clerk), 1_701_400_000.into())
+            .unwrap().sign(&cfo).unwrap();
+        let resolver = DidKeyResolver;
+        assert_eq!(attestation.status(&books(2, &clerk), std::slice::from_ref(&cfo.did), &resolver).unwrap(), AttestationStatus::Attested);
+        assert_eq!(attestation.status(&books(2, &clerk), std::slice::from_ref(&clerk.did), &resolver).unwrap(), AttestationStatus::NotDesignated);
+        assert!(matches!(attestation.status(&books(3, &clerk), &[], &resolver).unwrap(), AttestationStatus::Changed { .. }));
+
+        let mut forged = attestation.clone();
+        forged.attestation.transactions = 3;
+        assert!(matches!(forged.status(&books(2, &clerk), &[], &resolver).unwrap(), AttestationStatus::Invalid(_)));
+        assert!(attestation.attestation.clone().sign(&clerk).is_err(), "signed only by the DID it names");
+    }
+}
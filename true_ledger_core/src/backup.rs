@@ -0,0 +1,243 @@
+//! Backups
+//! A backup is one file holding a ledger's whole journal, in chain order,
+//! and the metadata of the keystore that signs for it (each identity's name,
+//! DID and key type; never a sealed key). The operator signs a manifest
+//! naming the Merkle root over the transactions, as a checkpoint does, so a
+//! restore can show it got back exactly what was backed up. Indices are not
+//! stored: a ledger rebuilds them as the transactions are appended again.
+//!
+//! A backup can also be sealed under a passphrase, with Argon2id and
+//! AES-256-GCM as key files are; the signed backup is then the plaintext.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+use crate::canonical::to_jcs;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::keys::SigAlg;
+use crate::keystore::{derive_key, KdfParams, KeyFile};
+use crate::merkle::MerkleTree;
+use crate::model::SignedTransaction;
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+use crate::verify::decode_signature;
+
+/// Prefixed to the signed bytes so a backup signature can never be replayed
+/// as any other signature
+const SIGNING_CONTEXT: &[u8] = b"true-ledger backup v1\n";
+
+/// Bound to a sealed backup's ciphertext as associated data
+const SEALING_CONTEXT: &[u8] = b"true-ledger sealed backup v1";
+
+/// A keystore identity, without its key
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IdentityInfo {
+    pub name: String,
+    pub did: String,
+    pub sig_alg: SigAlg,
+}
+
+impl From<&KeyFile> for IdentityInfo {
+    fn from(key_file: &KeyFile) -> Self {
+        IdentityInfo { name: key_file.name.clone(), did: key_file.did.clone(), sig_alg: key_file.sig_alg }
+    }
+}
+
+/// What the operator signs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupManifest {
+    pub operator_did: String,
+    pub created: Timestamp,
+    pub tree_size: u64,      // Transactions in the backup
+    pub height: Option<u64>, // Chain height of the last of them (None for an empty ledger)
+    pub root: String,        // Merkle root (hex) over them, in order
+    pub identities: Vec<IdentityInfo>,
+}
+
+/// A signed backup
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Backup {
+    pub manifest: BackupManifest,
+    pub signature: String, // Multibase (base58btc), over the manifest
+    pub transactions: Vec<SignedTransaction>,
+}
+
+/// A backup sealed under a passphrase
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SealedBackup {
+    pub kdf: String, // Always "argon2id"
+    pub kdf_params: KdfParams,
+    pub salt: String,       // Hex, 16 bytes
+    pub cipher: String,     // Always "aes-256-gcm"
+    pub nonce: String,      // Hex, 12 bytes
+    pub ciphertext: String, // Base64: the signed backup's JSON + 16-byte tag
+}
+
+/// A backup file, sealed or not
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum BackupFile {
+    Sealed(SealedBackup),
+    Plain(Box<Backup>),
+}
+
+fn tree_of(transactions: &[SignedTransaction]) -> MerkleTree {
+    let mut tree = MerkleTree::new();
+    for signed_tx in transactions {
+        tree.push(signed_tx.payload.get_hash());
+    }
+    tree
+}
+
+impl BackupManifest {
+    /// The exact bytes the operator signs: a context string, then the JCS form
+    fn signing_input(&self) -> Result<Vec<u8>, LedgerError> {
+        let mut input = SIGNING_CONTEXT.to_vec();
+        input.extend_from_slice(to_jcs(self)?.as_bytes());
+        Ok(input)
+    }
+}
+
+impl Backup {
+    /// Signs a backup of `transactions` (the whole journal, in chain order)
+    /// and the keystore identities as the signer's own DID
+    pub fn create(transactions: Vec<SignedTransaction>, identities: Vec<IdentityInfo>, signer: &dyn TransactionSigner, created: Timestamp) -> Result<Self, LedgerError> {
+        let manifest = BackupManifest {
+            operator_did: signer.did().to_string(),
+            created,
+            tree_size: transactions.len() as u64,
+            height: transactions.last().and_then(|signed_tx| signed_tx.payload.height),
+            root: hex::encode(tree_of(&transactions).root()),
+            identities,
+        };
+        let signature = signer.sign_bytes(&manifest.signing_input()?)?;
+        Ok(Backup { manifest, signature: multibase::encode(multibase::Base::Base58Btc, signature), transactions })
+    }
+
+    /// Checks the operator's signature and that the transactions are the
+    /// ones the manifest names. Each transaction is not verified here.
+    pub fn verify(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let manifest = &self.manifest;
+        let key = resolver.resolve_public_key(&manifest.operator_did)?;
+        key.verify_bytes(&manifest.signing_input()?, &decode_signature(&self.signature)?)
+            .map_err(|e| e.context("The backup's signature is invalid"))?;
+        if self.transactions.len() as u64 != manifest.tree_size {
+            return Err(LedgerError::Chain(format!("The backup holds {} transactions, but its manifest names {}", self.transactions.len(), manifest.tree_size)));
+        }
+        let root = hex::encode(tree_of(&self.transactions).root());
+        if root != manifest.root {
+            return Err(LedgerError::Chain(format!("The backup's transactions have root {}, not the signed {}", root, manifest.root)));
+        }
+        Ok(())
+    }
+
+    /// Seals the backup under a passphrase
+    pub fn seal(&self, passphrase: &str) -> Result<SealedBackup, LedgerError> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let kdf_params = KdfParams::default();
+        let key = derive_key(passphrase, &salt, &kdf_params)?;
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| LedgerError::Key(format!("Invalid key: {}", e)))?;
+        let plaintext = serde_json::to_vec(self).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: &plaintext, aad: SEALING_CONTEXT })
+            .map_err(|_| LedgerError::Key("Encryption failed".to_string()))?;
+
+        Ok(SealedBackup {
+            kdf: "argon2id".to_string(),
+            kdf_params,
+            salt: hex::encode(salt),
+            cipher: "aes-256-gcm".to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: base64::engine::general_purpose::STANDARD.encode(ciphertext),
+        })
+    }
+}
+
+impl SealedBackup {
+    /// Opens the sealed backup. Its signature is checked separately, by `Backup::verify`.
+    pub fn open(&self, passphrase: &str) -> Result<Backup, LedgerError> {
+        if self.kdf != "argon2id" || self.cipher != "aes-256-gcm" {
+            return Err(LedgerError::Key(format!("Unsupported backup encryption ({} / {})", self.kdf, self.cipher)));
+        }
+        let salt = hex::decode(&self.salt).map_err(|e| LedgerError::Key(format!("Invalid salt: {:?}", e)))?;
+        let nonce = hex::decode(&self.nonce).map_err(|e| LedgerError::Key(format!("Invalid nonce: {:?}", e)))?;
+        let ciphertext = base64::engine::general_purpose::STANDARD.decode(&self.ciphertext)
+            .map_err(|e| LedgerError::Key(format!("Invalid ciphertext: {}", e)))?;
+        if nonce.len() != 12 {
+            return Err(LedgerError::Key("Invalid nonce: must be 12 bytes".to_string()));
+        }
+
+        let key = derive_key(passphrase, &salt, &self.kdf_params)?;
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| LedgerError::Key(format!("Invalid key: {}", e)))?;
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: SEALING_CONTEXT })
+            .map_err(|_| LedgerError::Key("Could not open the backup: wrong passphrase or corrupted file".to_string()))?;
+        serde_json::from_slice(&plaintext).map_err(|e| LedgerError::Serialization(format!("Invalid backup: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::chain;
+
+    fn backup(operator: &Account) -> Backup {
+        let transactions = chain(&Account::generate(), 3);
+        let identities = vec![IdentityInfo { name: "operator".to_string(), did: operator.did.clone(), sig_alg: SigAlg::Ed25519 }];
+        Backup::create(transactions, identities, operator, 1_700_000_100.into()).unwrap()
+    }
+
+    #[test]
+    fn a_backup_verifies_and_survives_a_round_trip() {
+        let operator = Account::generate();
+        let backup = backup(&operator);
+        assert_eq!(backup.manifest.tree_size, 3);
+        assert_eq!(backup.manifest.height, backup.transactions[2].payload.height);
+        let json = serde_json::to_string(&BackupFile::Plain(Box::new(backup))).unwrap();
+        match serde_json::from_str(&json).unwrap() {
+            BackupFile::Plain(read) => read.verify(&DidKeyResolver).unwrap(),
+            BackupFile::Sealed(_) => panic!("a plain backup was read as sealed"),
+        }
+    }
+
+    #[test]
+    fn a_changed_backup_is_refused() {
+        let operator = Account::generate();
+
+        let mut dropped = backup(&operator);
+        dropped.transactions.pop();
+        assert!(dropped.verify(&DidKeyResolver).is_err());
+
+        let mut reordered = backup(&operator);
+        reordered.transactions.swap(1, 2);
+        assert!(reordered.verify(&DidKeyResolver).unwrap_err().to_string().contains("not the signed"));
+
+        let mut renamed = backup(&operator);
+        renamed.manifest.identities[0].name = "someone".to_string();
+        assert!(renamed.verify(&DidKeyResolver).unwrap_err().to_string().contains("signature is invalid"));
+    }
+
+    #[test]
+    fn a_sealed_backup_opens_only_with_its_passphrase() {
+        let operator = Account::generate();
+        let sealed = backup(&operator).seal("correct horse").unwrap();
+        let json = serde_json::to_string(&BackupFile::Sealed(sealed)).unwrap();
+        assert!(!json.contains(&operator.did), "the sealed backup leaks its manifest");
+        let BackupFile::Sealed(sealed) = serde_json::from_str(&json).unwrap() else {
+            panic!("a sealed backup was read as plain");
+        };
+        assert!(sealed.open("wrong").is_err());
+        sealed.open("correct horse").unwrap().verify(&DidKeyResolver).unwrap();
+    }
+}
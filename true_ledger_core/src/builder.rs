@@ -0,0 +1,244 @@
+//! Transaction Builder
+//! Puts a transaction together one call at a time and checks it before it
+//! is signed, so a mistake surfaces where it was made rather than as a
+//! failed verification later:
+//!
+//! ```ignore
+//! let tx = TransactionBuilder::new()
+//!     .timestamp(1730814442)
+//!     .author(&account.did)
+//!     .entry("10100", "10000.00", "0.00")
+//!     .entry("30100", "0.00", "10000.00")
+//!     .memo("Initial capital contribution by owner.")
+//!     .build()?;
+//! ```
+//!
+//! Amounts are checked in integer cents, so "0.1" + "0.2" balances "0.3",
+//! and written out with two decimals.
+
+use std::collections::BTreeMap;
+
+use crate::amount::{format_cents, parse_cents};
+use crate::chart::ChartOfAccounts;
+use crate::did::did_to_verifying_key;
+use crate::error::LedgerError;
+use crate::hashing::HashAlg;
+use crate::model::{JournalEntry, SignedTransaction, Transaction};
+use crate::multisig::SigningPolicy;
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+use crate::verify::check_currency;
+
+/// Builds a [`Transaction`]; see the module docs
+#[derive(Debug, Clone, Default)]
+pub struct TransactionBuilder {
+    timestamp: Option<Timestamp>,
+    author_did: Option<String>,
+    entries: Vec<JournalEntry>,
+    memo: Option<String>,
+    prev_hash: Option<String>,
+    height: Option<u64>,
+    sequence: Option<u64>,
+    signing_policy: Option<SigningPolicy>,
+    hash_alg: Option<HashAlg>,
+    adjusting: Option<bool>,
+    reverses: Option<String>,
+    chart: Option<ChartOfAccounts>, // When set, every account must be in it
+}
+
+impl TransactionBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn timestamp(mut self, timestamp: impl Into<Timestamp>) -> Self {
+        self.timestamp = Some(timestamp.into());
+        self
+    }
+
+    /// The author's DID; left out, the signer's own is used
+    pub fn author(mut self, did: impl Into<String>) -> Self {
+        self.author_did = Some(did.into());
+        self
+    }
+
+    /// Adds a line in the transaction's single currency
+    pub fn entry(mut self, account_id: impl Into<String>, debit: impl Into<String>, credit: impl Into<String>) -> Self {
+        self.entries.push(JournalEntry { account_id: account_id.into(), debit: debit.into(), credit: credit.into(), currency: None });
+        self
+    }
+
+    /// Adds a line in an ISO 4217 currency; each currency must balance on its own
+    pub fn entry_in(mut self, currency: impl Into<String>, account_id: impl Into<String>, debit: impl Into<String>, credit: impl Into<String>) -> Self {
+        self.entries.push(JournalEntry {
+            account_id: account_id.into(),
+            debit: debit.into(),
+            credit: credit.into(),
+            currency: Some(currency.into()),
+        });
+        self
+    }
+
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = Some(memo.into());
+        self
+    }
+
+    /// Chains onto `previous`: its hash becomes `prev_hash` and the height follows on
+    pub fn follows(mut self, previous: &Transaction) -> Self {
+        self.prev_hash = Some(previous.hash_hex());
+        self.height = Some(previous.height.map_or(1, |height| height + 1));
+        self
+    }
+
+    /// Starts a chain (height 0, no `prev_hash`)
+    pub fn genesis(mut self) -> Self {
+        self.prev_hash = None;
+        self.height = Some(0);
+        self
+    }
+
+    /// The author's replay counter: 0 for their first transaction in a ledger
+    pub fn sequence(mut self, sequence: u64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    pub fn signing_policy(mut self, policy: SigningPolicy) -> Self {
+        self.signing_policy = Some(policy);
+        self
+    }
+
+    pub fn hash_alg(mut self, hash_alg: HashAlg) -> Self {
+        self.hash_alg = Some(hash_alg);
+        self
+    }
+
+    /// Marks an adjusting entry into a closed period (see period_close)
+    pub fn adjusting(mut self) -> Self {
+        self.adjusting = Some(true);
+        self
+    }
+
+    /// Marks the transaction as undoing `target` (see reversal)
+    pub fn reverses(mut self, target: &Transaction) -> Self {
+        self.reverses = Some(target.hash_hex());
+        self
+    }
+
+    /// Rejects accounts that are not in this chart
+    pub fn chart(mut self, chart: &ChartOfAccounts) -> Self {
+        self.chart = Some(chart.clone());
+        self
+    }
+
+    /// Checks the transaction and returns it, ready to sign
+    pub fn build(self) -> Result<Transaction, LedgerError> {
+        let timestamp = self.timestamp
+            .ok_or_else(|| LedgerError::Timestamp("The transaction has no timestamp".to_string()))?;
+        if timestamp.unix() == 0 {
+            return Err(LedgerError::Timestamp("The timestamp is unset (0)".to_string()));
+        }
+        if let Some(did) = &self.author_did {
+            check_did(did)?;
+        }
+        let memo = self.memo.filter(|memo| !memo.trim().is_empty())
+            .ok_or_else(|| LedgerError::Config("The transaction has no memo; say why it was posted".to_string()))?;
+        if self.entries.len() < 2 {
+            return Err(LedgerError::Imbalance(format!("A transaction needs at least two entries, not {}", self.entries.len())));
+        }
+
+        let mut entries = self.entries;
+        let mut totals: BTreeMap<Option<String>, (i64, i64)> = BTreeMap::new();
+        for (i, entry) in entries.iter_mut().enumerate() {
+            let in_entry = |e: LedgerError| e.context(format!("Entry #{} ({})", i, entry.account_id));
+            check_account_id(&entry.account_id).map_err(in_entry)?;
+            if let Some(currency) = &entry.currency {
+                check_currency(currency).map_err(in_entry)?;
+            }
+            let debit = parse_cents(&entry.debit).map_err(in_entry)?;
+            let credit = parse_cents(&entry.credit).map_err(in_entry)?;
+            if debit < 0 || credit < 0 {
+                return Err(in_entry(LedgerError::Amount("Amounts are never negative; post the other side instead".to_string())));
+            }
+            if (debit == 0) == (credit == 0) {
+                return Err(in_entry(LedgerError::Amount("An entry is either a debit or a credit".to_string())));
+            }
+            let (debits, credits) = totals.entry(entry.currency.clone()).or_default();
+            *debits += debit;
+            *credits += credit;
+            // Written out in full ("" and "0.1" become "0.00" and "0.10"), as verifiers expect
+            entry.debit = format_cents(debit);
+            entry.credit = format_cents(credit);
+        }
+        for (currency, (debits, credits)) in totals {
+            if debits != credits {
+                let scope = currency.map(|c| format!(" in {}", c)).unwrap_or_default();
+                return Err(LedgerError::Imbalance(format!("Debits ({}) != Credits ({}){}", format_cents(debits), format_cents(credits), scope)));
+            }
+        }
+
+        let tx = Transaction {
+            timestamp,
+            author_did: self.author_did.unwrap_or_default(),
+            entries,
+            memo,
+            prev_hash: self.prev_hash,
+            height: self.height,
+            sequence: self.sequence,
+            canonicalization: None, // Chosen by the signer
+            signing_policy: self.signing_policy,
+            key_event: None,
+            hash_alg: self.hash_alg,
+            version: None, // Set by the signer
+            adjusting: self.adjusting,
+            reverses: self.reverses,
+            prepared_by: None, // Set when a draft is submitted (see lifecycle)
+            memo_commitment: None, // Set by private_memo::MemoOpening::commit
+            policy: None,
+        };
+        if let Some(policy) = &tx.signing_policy {
+            policy.validate()?;
+        }
+        if let Some(chart) = &self.chart {
+            if let Some(problem) = chart.validate(&tx, false).into_iter().next() {
+                return Err(LedgerError::Config(problem));
+            }
+        }
+        Ok(tx)
+    }
+
+    /// Builds the transaction and signs it; the author defaults to the signer
+    pub fn sign(mut self, signer: &dyn TransactionSigner) -> Result<SignedTransaction, LedgerError> {
+        match &self.author_did {
+            Some(did) if did != signer.did() => {
+                return Err(LedgerError::Signature(format!("The author is {} but the signer is {}", did, signer.did())));
+            }
+            Some(_) => {}
+            None => self.author_did = Some(signer.did().to_string()),
+        }
+        signer.sign_transaction(self.build()?)
+    }
+}
+
+/// A did:key must decode; other methods are resolved when verifying
+fn check_did(did: &str) -> Result<(), LedgerError> {
+    if did.starts_with("did:key:") {
+        did_to_verifying_key(did).map(|_| ())
+    } else if did.strip_prefix("did:").is_some_and(|rest| rest.contains(':')) {
+        Ok(())
+    } else {
+        Err(LedgerError::Did(format!("{} is not a DID", did)))
+    }
+}
+
+/// Account codes are one token: letters, digits and `.` `-` `:` `_`
+fn check_account_id(account_id: &str) -> Result<(), LedgerError> {
+    if account_id.is_empty() {
+        return Err(LedgerError::Config("The account code is empty".to_string()));
+    }
+    match account_id.chars().find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':' | '_'))) {
+        Some(c) => Err(LedgerError::Config(format!("{:?} is not allowed in an account code", c))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,228 @@
+//! Canonical JSON (RFC 8785 JCS)
+//! The signing input must be byte-for-byte reproducible by any implementation
+//! in any language. JCS pins down everything serde_json leaves open: object
+//! keys sorted by UTF-16 code units, no whitespace, minimal string escaping
+//! and ECMAScript number formatting.
+//!
+//! JCS numbers are IEEE 754 doubles, so integers beyond ±(2^53 - 1) have no
+//! exact canonical form: two different values would produce the same bytes.
+//! Such integers are refused rather than rounded.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::LedgerError;
+
+/// The largest integer every JCS implementation represents exactly (2^53 - 1)
+pub const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+/// Serializes any value to its JCS canonical form
+pub fn to_jcs<T: Serialize>(value: &T) -> Result<String, LedgerError> {
+    let value = serde_json::to_value(value).map_err(|e| LedgerError::Serialization(format!("Failed to serialize for canonicalization: {}", e)))?;
+    let mut out = String::new();
+    write_value(&value, &mut out)?;
+    Ok(out)
+}
+
+fn write_value(value: &Value, out: &mut String) -> Result<(), LedgerError> {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => {
+            let outside = n.as_u64().is_some_and(|n| n > MAX_SAFE_INTEGER) || n.as_i64().is_some_and(|n| n.unsigned_abs() > MAX_SAFE_INTEGER);
+            if outside {
+                return Err(LedgerError::Serialization(format!("Integer {} is outside ±(2^53 - 1), so it has no exact JCS form", n)));
+            }
+            out.push_str(&format_number(n.as_f64().unwrap_or(0.0)))
+        }
+        Value::String(s) => write_string(s, out),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out)?;
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, item)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_string(key, out);
+                out.push(':');
+                write_value(item, out)?;
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+/// Escapes only what JSON requires; everything else is emitted as-is
+fn write_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\u{08}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{0C}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// ECMAScript Number::toString: shortest round-trip digits, plain notation
+/// for decimal exponents in [-6, 21), exponent notation otherwise
+fn format_number(n: f64) -> String {
+    if n == 0.0 {
+        return "0".to_string(); // Also covers -0
+    }
+    let sign = if n < 0.0 { "-" } else { "" };
+
+    // Rust's {:e} gives the shortest round-trip digits, e.g. "3.3333333333333335e8"
+    let scientific = format!("{:e}", n.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let digits = even_on_tie(n.abs(), digits, exponent);
+    let k = digits.len() as i32;
+    let point = exponent + 1; // Position of the decimal point relative to the digits
+
+    let body = if k <= point && point <= 21 {
+        format!("{}{}", digits, "0".repeat((point - k) as usize))
+    } else if 0 < point && point <= 21 {
+        format!("{}.{}", &digits[..point as usize], &digits[point as usize..])
+    } else if -6 < point && point <= 0 {
+        format!("0.{}{}", "0".repeat((-point) as usize), digits)
+    } else {
+        let fraction = if k > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}e{}{}", &digits[..1], fraction, if point - 1 > 0 { "+" } else { "-" }, (point - 1).abs())
+    };
+    format!("{}{}", sign, body)
+}
+
+/// When two shortest digit strings are equally close ("1424953923781206.25"
+/// as ...6.2 or ...6.3), ECMAScript takes the even one; Rust rounds up
+fn even_on_tie(n: f64, digits: String, exponent: i32) -> String {
+    let k = digits.len();
+    if (digits.as_bytes()[k - 1] - b'0').is_multiple_of(2) {
+        return digits;
+    }
+    // Precision is exact in Rust, and every f64 has fewer than 1100 significant digits
+    let exact = format!("{:.1100e}", n);
+    let (mantissa, exact_exponent) = exact.split_once('e').unwrap_or((&exact, "0"));
+    let exact_digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    if exact_exponent.parse() != Ok(exponent) || exact_digits.len() <= k {
+        return digits;
+    }
+    let (prefix, tail) = exact_digits.split_at(k);
+    let is_tie = tail.starts_with('5') && tail[1..].bytes().all(|b| b == b'0');
+    let truncated_round_trips = format!("{}.{}e{}", &prefix[..1], &prefix[1..], exponent).parse::<f64>() == Ok(n);
+    if is_tie && truncated_round_trips {
+        prefix.to_string()
+    } else {
+        digits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jcs(json: &str) -> String {
+        to_jcs(&serde_json::from_str::<Value>(json).unwrap()).unwrap()
+    }
+
+    /// RFC 8785 Appendix B: IEEE 754 bit patterns and their canonical text
+    #[test]
+    fn rfc8785_number_samples() {
+        let samples: [(u64, &str); 24] = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in samples {
+            assert_eq!(format_number(f64::from_bits(bits)), expected, "bits {:016x}", bits);
+        }
+    }
+
+    /// RFC 8785 section 3.2.2 example
+    #[test]
+    fn rfc8785_primitive_example() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let expected = r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#;
+        assert_eq!(jcs(input), expected);
+    }
+
+    /// RFC 8785 section 3.2.3: keys sort by UTF-16 code units, not code points
+    #[test]
+    fn rfc8785_sorting_example() {
+        let input = r#"{
+            "€": "Euro Sign",
+            "\r": "Carriage Return",
+            "דּ": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "😀": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "ö": "Latin Small Letter O With Diaeresis"
+        }"#;
+        let expected = "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\"\u{f6}\":\"Latin Small Letter O With Diaeresis\",\
+            \"\u{20ac}\":\"Euro Sign\",\"\u{1f600}\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}";
+        assert_eq!(jcs(input), expected);
+    }
+
+    #[test]
+    fn whitespace_and_nesting_are_removed() {
+        assert_eq!(jcs(r#"{ "b" : [ 1 , { "d":4, "c":3 } ], "a": "x" }"#), r#"{"a":"x","b":[1,{"c":3,"d":4}]}"#);
+    }
+
+    #[test]
+    fn safe_integers_are_exact() {
+        assert_eq!(jcs("[9007199254740991, -9007199254740991, 1730814442]"), "[9007199254740991,-9007199254740991,1730814442]");
+    }
+
+    #[test]
+    fn integers_beyond_2_pow_53_are_refused() {
+        for json in ["9007199254740992", "9007199254740993", "-9007199254740992", "18446744073709551615", r#"{"a":[1,{"b":12345678901234567890}]}"#] {
+            let value: Value = serde_json::from_str(json).unwrap();
+            assert!(matches!(to_jcs(&value), Err(LedgerError::Serialization(_))), "{} was accepted", json);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+//! Capability Delegation
+//! Posting rights can be handed down instead of listed in a rules file. An
+//! admin signs a capability granting another DID the right to post to some
+//! accounts, up to an amount, until it expires; the holder may delegate a
+//! narrower capability in turn. The author of a transaction attaches the
+//! whole chain, root first, beside their signature (`delegation`), and a
+//! verifier that trusts the root checks every link:
+//!
+//! - the root is issued by one of the verifier's `roots`, each later link by
+//!   the holder of the one before, and every signature verifies;
+//! - each link names the one before by hash, and grants no more than it:
+//!   its accounts within the parent's, its limit no higher, its validity no
+//!   longer;
+//! - the last link is held by the author, and every link allows every entry,
+//!   the total debit and the transaction's timestamp.
+//!
+//! The model follows ZCAP-LD and UCAN (signed, attenuated delegation by
+//! hash link) but the tokens are this crate's own JCS-signed JSON, not
+//! either wire format. The rules file names who may issue root capabilities:
+//!
+//! ```toml
+//! [delegation]
+//! roots = ["admins"]   # DIDs or [groups] names; their own postings need no capability
+//! ```
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::acl::{expand_groups, AccountPattern};
+use crate::amount::{format_cents, parse_cents};
+use crate::canonical::to_jcs;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::model::{SignedTransaction, Transaction};
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+use crate::verify::{balance_totals, decode_signature};
+
+/// Prefixed to the signed bytes so a capability signature can never be
+/// replayed as any other kind of signature
+const SIGNING_CONTEXT: &[u8] = b"true-ledger capability v1\n";
+
+/// What the issuer signs: the right to post, granted to `holder`
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Capability {
+    pub issuer: String,
+    pub holder: String,
+    pub accounts: Vec<String>,      // Codes, prefixes ending in '*', or inclusive ranges (see acl.rs)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<String>,        // Largest total debit per transaction
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before: Option<Timestamp>,
+    pub expires: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,     // Hex SHA-256 of the signed capability this one is delegated from
+}
+
+/// A capability and its issuer's signature
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SignedCapability {
+    pub capability: Capability,
+    pub signature: String, // Multibase (base58btc)
+}
+
+impl Capability {
+    /// The exact bytes the issuer signs: a context string, then the JCS form
+    fn signing_input(&self) -> Result<Vec<u8>, LedgerError> {
+        let mut input = SIGNING_CONTEXT.to_vec();
+        input.extend_from_slice(to_jcs(self)?.as_bytes());
+        Ok(input)
+    }
+
+    /// Signs the capability. `issuer` must be the signer's own DID.
+    pub fn sign(self, signer: &dyn TransactionSigner) -> Result<SignedCapability, LedgerError> {
+        if self.issuer != signer.did() {
+            return Err(LedgerError::Key(format!("The capability names issuer {} but the signing key is {}", self.issuer, signer.did())));
+        }
+        self.check_shape()?;
+        let signature = signer.sign_bytes(&self.signing_input()?)?;
+        Ok(SignedCapability { capability: self, signature: multibase::encode(multibase::Base::Base58Btc, signature) })
+    }
+
+    fn patterns(&self) -> Result<Vec<AccountPattern>, LedgerError> {
+        self.accounts.iter().map(|pattern| AccountPattern::parse(pattern)).collect()
+    }
+
+    fn max_cents(&self) -> Result<Option<i64>, LedgerError> {
+        self.max.as_deref().map(parse_cents).transpose().map_err(|e| e.context("Capability max"))
+    }
+
+    /// Checks what can be checked without the rest of the chain
+    fn check_shape(&self) -> Result<(), LedgerError> {
+        if self.accounts.is_empty() {
+            return Err(LedgerError::Config("The capability grants no accounts".to_string()));
+        }
+        self.patterns()?;
+        self.max_cents()?;
+        if self.not_before.as_ref().is_some_and(|from| from.unix() > self.expires.unix()) {
+            return Err(LedgerError::Config("The capability expires before it is valid".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Checks that this capability grants no more than `parent`
+    pub fn check_attenuates(&self, parent: &Capability) -> Result<(), LedgerError> {
+        let narrower = |what: String| Err(LedgerError::Approval(format!("Delegated to {}: {}", self.holder, what)));
+        let parent_patterns = parent.patterns()?;
+        for (pattern, text) in self.patterns()?.iter().zip(&self.accounts) {
+            if !parent_patterns.iter().any(|outer| pattern.within(outer)) {
+                return narrower(format!("{} is not within {}", text, parent.accounts.join(", ")));
+            }
+        }
+        match (self.max_cents()?, parent.max_cents()?) {
+            (None, Some(limit)) => return narrower(format!("no limit, but its parent's is {}", format_cents(limit))),
+            (Some(max), Some(limit)) if max > limit => {
+                return narrower(format!("a limit of {}, above its parent's {}", format_cents(max), format_cents(limit)));
+            }
+            _ => {}
+        }
+        if self.expires.unix() > parent.expires.unix() {
+            return narrower(format!("it expires at {}, after its parent ({})", self.expires, parent.expires));
+        }
+        if let Some(from) = &parent.not_before {
+            if self.not_before.as_ref().is_none_or(|own| own.unix() < from.unix()) {
+                return narrower(format!("it is valid before its parent ({})", from));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that this capability allows `tx`
+    pub fn check_allows(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let denied = |what: String| Err(LedgerError::Approval(format!("The capability {} → {} {}", self.issuer, self.holder, what)));
+        let at = tx.timestamp.unix();
+        if self.not_before.as_ref().is_some_and(|from| at < from.unix()) || at > self.expires.unix() {
+            return denied(format!("is not valid at {}", tx.timestamp));
+        }
+        let patterns = self.patterns()?;
+        for (i, entry) in tx.entries.iter().enumerate() {
+            if !patterns.iter().any(|pattern| pattern.matches(&entry.account_id)) {
+                return denied(format!("does not cover entry #{} on {}", i, entry.account_id));
+            }
+        }
+        if let Some(max) = self.max_cents()? {
+            let (total, _) = balance_totals(tx)?;
+            if total > max {
+                return denied(format!("allows up to {}, but the total debit is {}", format_cents(max), format_cents(total)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SignedCapability {
+    /// Hex SHA-256 of the JCS form, which a delegated capability names as its parent
+    pub fn hash_hex(&self) -> Result<String, LedgerError> {
+        Ok(hex::encode(Sha256::digest(to_jcs(self)?.as_bytes())))
+    }
+
+    /// Checks the issuer's signature
+    pub fn verify(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let key = resolver.resolve_public_key(&self.capability.issuer)?;
+        key.verify_bytes(&self.capability.signing_input()?, &decode_signature(&self.signature)?)
+            .map_err(|e| e.context(format!("Capability issued by {}", self.capability.issuer)))
+    }
+
+    /// `capability`, delegated from this one: it must be issued by this
+    /// one's holder and grant no more
+    pub fn delegate(&self, mut capability: Capability, signer: &dyn TransactionSigner) -> Result<SignedCapability, LedgerError> {
+        if capability.issuer != self.capability.holder {
+            return Err(LedgerError::Approval(format!("The capability is held by {}; only they can delegate it", self.capability.holder)));
+        }
+        capability.parent = Some(self.hash_hex()?);
+        capability.check_attenuates(&self.capability)?;
+        capability.sign(signer)
+    }
+}
+
+/// Reads a delegation chain (a JSON array, root first) from a file; nothing is checked
+pub fn load_chain(path: &str) -> Result<Vec<SignedCapability>, LedgerError> {
+    let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read capability {}: {}", path, e)))?;
+    serde_json::from_str(&data).map_err(|e| LedgerError::Serialization(format!("Invalid capability {}: {}", path, e)))
+}
+
+/// Checks every link of `chain` on its own and against the one before; the
+/// first must be issued by one of `roots`
+pub fn verify_chain(chain: &[SignedCapability], roots: &[String], resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+    let Some(root) = chain.first() else {
+        return Err(LedgerError::Approval("The delegation chain is empty".to_string()));
+    };
+    if !roots.contains(&root.capability.issuer) {
+        return Err(LedgerError::Approval(format!("The delegation starts at {}, which is not a trusted root", root.capability.issuer)));
+    }
+    if root.capability.parent.is_some() {
+        return Err(LedgerError::Approval("The first capability in the chain names a parent".to_string()));
+    }
+    for (i, link) in chain.iter().enumerate() {
+        link.capability.check_shape()?;
+        link.verify(resolver)?;
+        if i == 0 {
+            continue;
+        }
+        let parent = &chain[i - 1];
+        if link.capability.issuer != parent.capability.holder {
+            return Err(LedgerError::Approval(format!("Capability #{} is issued by {}, but #{} is held by {}",
+                i, link.capability.issuer, i - 1, parent.capability.holder)));
+        }
+        if link.capability.parent.as_deref() != Some(parent.hash_hex()?.as_str()) {
+            return Err(LedgerError::Approval(format!("Capability #{} is not delegated from #{}", i, i - 1)));
+        }
+        link.capability.check_attenuates(&parent.capability)?;
+    }
+    Ok(())
+}
+
+/// Who may post under the delegation model
+#[derive(Debug, Clone)]
+pub struct DelegationPolicy {
+    pub roots: Vec<String>, // DIDs
+}
+
+impl DelegationPolicy {
+    /// The policy trusting `roots`, each a DID or the name of one of `groups`
+    pub fn new(roots: &[String], groups: &BTreeMap<String, Vec<String>>) -> Result<Self, LedgerError> {
+        Ok(DelegationPolicy { roots: expand_groups(roots, groups).map_err(|e| e.context("delegation"))? })
+    }
+
+    /// Checks that the author of `signed_tx` is a root, or holds a valid
+    /// chain from one that allows the transaction. Returns the chain's length.
+    pub fn check(&self, signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<usize, LedgerError> {
+        let tx = &signed_tx.payload;
+        if self.roots.contains(&tx.author_did) {
+            return Ok(0);
+        }
+        let chain = &signed_tx.delegation;
+        if chain.is_empty() {
+            return Err(LedgerError::Approval(format!("{} holds no capability to post", tx.author_did)));
+        }
+        verify_chain(chain, &self.roots, resolver)?;
+        let leaf = &chain[chain.len() - 1].capability;
+        if leaf.holder != tx.author_did {
+            return Err(LedgerError::Approval(format!("The capability is held by {}, not the author {}", leaf.holder, tx.author_did)));
+        }
+        for link in chain {
+            link.capability.check_allows(tx)?;
+        }
+        Ok(chain.len())
+    }
+}
+
+/// A signer that attaches its delegation chain to every transaction it signs
+pub struct DelegatedSigner {
+    signer: Box<dyn TransactionSigner>,
+    chain: Vec<SignedCapability>,
+}
+
+impl DelegatedSigner {
+    /// `signer` posting under `chain`, whose last capability it must hold
+    pub fn new(signer: Box<dyn TransactionSigner>, chain: Vec<SignedCapability>) -> Result<Self, LedgerError> {
+        match chain.last() {
+            Some(leaf) if leaf.capability.holder == signer.did() => Ok(DelegatedSigner { signer, chain }),
+            Some(leaf) => Err(LedgerError::Approval(format!("The capability is held by {}, not {}", leaf.capability.holder, signer.did()))),
+            None => Err(LedgerError::Approval("The delegation chain is empty".to_string())),
+        }
+    }
+}
+
+impl TransactionSigner for DelegatedSigner {
+    fn did(&self) -> &str {
+        self.signer.did()
+    }
+
+    fn sign_transaction(&self, tx: Transaction) -> Result<SignedTransaction, LedgerError> {
+        let mut signed_tx = self.signer.sign_transaction(tx)?;
+        signed_tx.delegation = self.chain.clone();
+        Ok(signed_tx)
+    }
+
+    fn sign_bytes(&self, message: &[u8]) -> Result<Vec<u8>, LedgerError> {
+        self.signer.sign_bytes(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+
+    const NOW: u64 = 1_700_000_000;
+
+    struct Setup {
+        admin: Account,
+        manager: Account,
+        clerk: Account,
+        root: SignedCapability, // admin -> manager: 5*, up to 1000.00
+        leaf: SignedCapability, // manager -> clerk: 51*, up to 100.00
+    }
+
+    fn capability(issuer: &Account, holder: &Account, accounts: &[&str], max: &str, expires: u64) -> Capability {
+        Capability {
+            issuer: issuer.did.clone(),
+            holder: holder.did.clone(),
+            accounts: accounts.iter().map(|a| a.to_string()).collect(),
+            max: Some(max.to_string()),
+            not_before: Some(Timestamp::from(NOW - 3600)),
+            expires: Timestamp::from(expires),
+            parent: None,
+        }
+    }
+
+    fn setup() -> Setup {
+        let (admin, manager, clerk) = (Account::generate(), Account::generate(), Account::generate());
+        let root = capability(&admin, &manager, &["5*"], "1000.00", NOW + 7200).sign(&admin).unwrap();
+        let leaf = root.delegate(capability(&manager, &clerk, &["51*"], "100.00", NOW + 3600), &manager).unwrap();
+        Setup { admin, manager, clerk, root, leaf }
+    }
+
+    fn posting(author: &Account, chain: &[SignedCapability], amount: &str, timestamp: u64) -> SignedTransaction {
+        let tx = TransactionBuilder::new()
+            .timestamp(timestamp)
+            .entry("51000", amount, "0.00")
+            .entry("51900", "0.00", amount)
+            .memo("Supplies")
+            .genesis()
+            .author(&author.did)
+            .build()
+            .unwrap();
+        let mut signed_tx = author.sign(tx);
+        signed_tx.delegation = chain.to_vec();
+        signed_tx
+    }
+
+    fn policy(setup: &Setup) -> DelegationPolicy {
+        DelegationPolicy { roots: vec![setup.admin.did.clone()] }
+    }
+
+    #[test]
+    fn a_delegated_chain_allows_a_covered_posting() {
+        let s = setup();
+        let chain = [s.root.clone(), s.leaf.clone()];
+        assert_eq!(policy(&s).check(&posting(&s.clerk, &chain, "50.00", NOW), &DidKeyResolver).unwrap(), 2);
+        assert_eq!(policy(&s).check(&posting(&s.admin, &[], "5000.00", NOW), &DidKeyResolver).unwrap(), 0);
+        let signer = DelegatedSigner::new(Box::new(Account::from_secret_bytes(&s.clerk.secret_bytes())), chain.to_vec()).unwrap();
+        assert_eq!(signer.sign_transaction(posting(&s.clerk, &[], "1.00", NOW).payload).unwrap().delegation.len(), 2);
+    }
+
+    #[test]
+    fn postings_outside_the_grant_are_refused() {
+        let s = setup();
+        let chain = [s.root.clone(), s.leaf.clone()];
+        let refused = |signed_tx: SignedTransaction| policy(&s).check(&signed_tx, &DidKeyResolver).unwrap_err();
+        assert!(matches!(refused(posting(&s.clerk, &chain, "100.01", NOW)), LedgerError::Approval(_)));
+        assert!(matches!(refused(posting(&s.clerk, &chain, "50.00", NOW + 3601)), LedgerError::Approval(_)));
+        assert!(matches!(refused(posting(&s.clerk, &chain, "50.00", NOW - 3601)), LedgerError::Approval(_)));
+        assert!(matches!(refused(posting(&s.clerk, &[], "1.00", NOW)), LedgerError::Approval(_)));
+        assert!(matches!(refused(posting(&s.manager, &chain, "1.00", NOW)), LedgerError::Approval(_)));
+        let mut other_accounts = posting(&s.clerk, &chain, "1.00", NOW);
+        other_accounts.payload.entries[1].account_id = "40100".to_string();
+        assert!(policy(&s).check(&other_accounts, &DidKeyResolver).is_err());
+    }
+
+    #[test]
+    fn negative_or_overflowing_debits_cannot_slip_under_the_max() {
+        let s = setup();
+        let mut negative = posting(&s.clerk, &[], "1000.00", NOW).payload;
+        negative.entries.push(negative.entries[0].clone());
+        negative.entries[1].debit = "-999.50".to_string();
+        negative.entries[1].credit = "0.00".to_string();
+        assert!(matches!(s.leaf.capability.check_allows(&negative), Err(LedgerError::Amount(_))));
+
+        let mut overflow = posting(&s.clerk, &[], "92233720368547758.07", NOW).payload;
+        overflow.entries[1] = overflow.entries[0].clone();
+        assert!(matches!(s.leaf.capability.check_allows(&overflow), Err(LedgerError::Amount(_))));
+    }
+
+    #[test]
+    fn delegation_can_only_narrow() {
+        let s = setup();
+        let wider = [
+            capability(&s.manager, &s.clerk, &["4*"], "100.00", NOW + 3600),
+            capability(&s.manager, &s.clerk, &["51*"], "1000.01", NOW + 3600),
+            capability(&s.manager, &s.clerk, &["51*"], "100.00", NOW + 7201),
+            Capability { max: None, ..capability(&s.manager, &s.clerk, &["51*"], "0", NOW + 3600) },
+            Capability { not_before: None, ..capability(&s.manager, &s.clerk, &["51*"], "1.00", NOW + 3600) },
+        ];
+        for capability in wider {
+            assert!(s.root.delegate(capability.clone(), &s.manager).is_err(), "{:?}", capability);
+            // Signed without the check, it still fails in the chain
+            let link = Capability { parent: Some(s.root.hash_hex().unwrap()), ..capability }.sign(&s.manager).unwrap();
+            assert!(verify_chain(&[s.root.clone(), link], &policy(&s).roots, &DidKeyResolver).is_err());
+        }
+        // Only the holder may delegate
+        assert!(s.root.delegate(capability(&s.clerk, &s.clerk, &["51*"], "1.00", NOW), &s.clerk).is_err());
+    }
+
+    #[test]
+    fn broken_chains_are_refused() {
+        let s = setup();
+        let roots = policy(&s).roots;
+        let verify = |chain: &[SignedCapability]| verify_chain(chain, &roots, &DidKeyResolver);
+        verify(&[s.root.clone(), s.leaf.clone()]).unwrap();
+
+        assert!(verify(&[]).is_err());
+        assert!(verify(std::slice::from_ref(&s.leaf)).is_err());
+        assert!(verify(&[s.leaf.clone(), s.root.clone()]).is_err());
+        assert!(verify_chain(&[s.root.clone(), s.leaf.clone()], std::slice::from_ref(&s.manager.did), &DidKeyResolver).is_err());
+
+        // Changed after signing
+        let mut forged = s.leaf.clone();
+        forged.capability.max = Some("99.00".to_string());
+        assert!(matches!(verify(&[s.root.clone(), forged]), Err(LedgerError::Signature(_))));
+
+        // A leaf delegated from another root capability
+        let other_root = capability(&s.admin, &s.manager, &["5*"], "900.00", NOW + 7200).sign(&s.admin).unwrap();
+        let stray = other_root.delegate(capability(&s.manager, &s.clerk, &["51*"], "100.00", NOW + 3600), &s.manager).unwrap();
+        assert!(verify(&[s.root.clone(), stray]).is_err());
+    }
+
+    #[test]
+    fn malformed_capabilities_cannot_be_signed() {
+        let admin = Account::generate();
+        let holder = Account::generate();
+        assert!(capability(&admin, &holder, &[], "1.00", NOW).sign(&admin).is_err());
+        assert!(capability(&admin, &holder, &["5*"], "NaN", NOW).sign(&admin).is_err());
+        assert!(capability(&admin, &holder, &["5*"], "1.00", NOW - 7200).sign(&admin).is_err());
+        assert!(capability(&admin, &holder, &["5*"], "1.00", NOW).sign(&holder).is_err());
+    }
+}
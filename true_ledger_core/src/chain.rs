@@ -0,0 +1,124 @@
+//! Hash-Chained Transaction Log
+//! Each transaction names its predecessor's payload hash (`prev_hash`) and its
+//! position (`height`). Because both are inside the signed payload, removing,
+//! reordering or editing any transaction breaks every link after it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::LedgerError;
+use crate::model::{SignedTransaction, Transaction};
+
+/// Sets `tx`'s chain fields so it follows `prev` (or starts a chain if None).
+/// Must be called before signing.
+pub fn link(tx: &mut Transaction, prev: Option<&Transaction>) {
+    match prev {
+        Some(prev) => {
+            tx.prev_hash = Some(prev.hash_hex());
+            tx.height = Some(prev.height.unwrap_or(0) + 1);
+        }
+        None => {
+            tx.prev_hash = None;
+            tx.height = Some(0);
+        }
+    }
+}
+
+/// Checks that `tx` directly follows `prev`: one height above it, pointing at
+/// its hash. With no `prev`, `tx` must be a genesis transaction at height 0.
+pub fn verify_link(prev: Option<&Transaction>, tx: &Transaction) -> Result<(), LedgerError> {
+    let expected_height = match prev {
+        Some(prev) => prev.height.map(|h| h + 1),
+        None => Some(0),
+    };
+    match (tx.height, expected_height) {
+        (None, _) => return Err(LedgerError::Chain("Transaction has no height (not part of a chain)".to_string())),
+        (Some(height), Some(expected)) if height != expected => {
+            return Err(LedgerError::Chain(format!("Expected height {} but found {}", expected, height)));
+        }
+        _ => {}
+    }
+
+    match (prev, &tx.prev_hash) {
+        (None, None) => Ok(()),
+        (None, Some(_)) => Err(LedgerError::Chain("Genesis transaction must not have a prev_hash".to_string())),
+        (Some(_), None) => Err(LedgerError::Chain("Missing prev_hash".to_string())),
+        (Some(prev), Some(prev_hash)) => {
+            let expected = prev.hash_hex();
+            if *prev_hash == expected {
+                Ok(())
+            } else {
+                Err(LedgerError::Chain(format!("prev_hash {} does not match predecessor {}", prev_hash, expected)))
+            }
+        }
+    }
+}
+
+/// Checks that a sequenced transaction carries the number its author's next
+/// one must have. Transactions without a sequence (older files) pass.
+pub fn check_sequence(tx: &Transaction, expected: u64) -> Result<(), LedgerError> {
+    match tx.sequence {
+        Some(sequence) if sequence < expected => Err(LedgerError::Chain(format!(
+            "Replayed sequence {} for {} (expected {})", sequence, tx.author_did, expected))),
+        Some(sequence) if sequence > expected => Err(LedgerError::Chain(format!(
+            "Gap in sequence for {}: expected {} but found {}", tx.author_did, expected, sequence))),
+        _ => Ok(()),
+    }
+}
+
+/// Each author's next expected sequence number while walking a chain in order
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    next: HashMap<String, u64>,
+}
+
+impl SequenceTracker {
+    pub fn new() -> Self {
+        SequenceTracker::default()
+    }
+
+    /// Checks `tx` against its author's counter, then advances it
+    pub fn check(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
+        let Some(sequence) = tx.sequence else { return Ok(()) };
+        let next = self.next.entry(tx.author_did.clone()).or_insert(0);
+        check_sequence(tx, *next)?;
+        *next = sequence + 1;
+        Ok(())
+    }
+}
+
+/// Loads every *.json signed transaction in `dir`, ordered by height
+pub fn load_dir(dir: &str) -> Result<Vec<(PathBuf, SignedTransaction)>, LedgerError> {
+    let entries = fs::read_dir(dir).map_err(|e| LedgerError::Io(format!("Could not read directory {}: {}", dir, e)))?;
+
+    let mut chain = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| LedgerError::Io(format!("Could not read directory {}: {}", dir, e)))?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+            continue;
+        }
+        let data = fs::read_to_string(&path)
+            .map_err(|e| LedgerError::Io(format!("Could not read {}: {}", path.display(), e)))?;
+        let signed_tx = SignedTransaction::from_json(&data).map_err(|e| e.context(path.display()))?;
+        chain.push((path, signed_tx));
+    }
+
+    // Unchained files (no height) sort first and are then rejected by verify_link
+    chain.sort_by_key(|(path, tx)| (tx.payload.height, path.clone()));
+    Ok(chain)
+}
+
+/// Verifies a whole chain: every signature, every balance, every link and
+/// every author's sequence
+pub fn verify_chain(chain: &[SignedTransaction]) -> Result<(), LedgerError> {
+    let mut prev: Option<&Transaction> = None;
+    let mut sequences = SequenceTracker::new();
+    for (position, signed_tx) in chain.iter().enumerate() {
+        crate::verify::verify(signed_tx).map_err(|e| e.context(format!("Transaction #{}", position)))?;
+        verify_link(prev, &signed_tx.payload).map_err(|e| e.context(format!("Transaction #{}", position)))?;
+        sequences.check(&signed_tx.payload).map_err(|e| e.context(format!("Transaction #{}", position)))?;
+        prev = Some(&signed_tx.payload);
+    }
+    Ok(())
+}
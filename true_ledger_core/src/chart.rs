@@ -0,0 +1,268 @@
+//! Chart of Accounts
+//! Defines which account codes exist, what they are called and what type they
+//! are. Loaded from a JSON or TOML file (chosen by extension):
+//!   { "accounts": [ { "code": "10100", "name": "Cash", "type": "asset" } ] }
+//!
+//! Accounts form a hierarchy for reporting. A code like `10100:Cash:Operating`
+//! rolls up into `10100:Cash`, and that into `10100`; an account's `parent`
+//! overrides this, for charts with flat numeric codes. Parents need not be
+//! posted to, or even be in the chart, to appear in a rolled-up report.
+//!
+//! For the cash flow statement, cash accounts are marked `cash_flow = "cash"`;
+//! every other account's movements are reported under the activity it names
+//! (by default equity is financing, everything else operating).
+//!
+//! An account is retired with `retired_from = "YYYY-MM-DD"`: nothing may be
+//! posted to it dated on or after that day, but it stays in the chart, so
+//! reports over earlier periods still name and roll it up as before. To
+//! retire accounts by a signed change instead, a rules file's `[[retired]]`
+//! table can be adopted as a ledger policy (see validation.rs and policy.rs).
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::error::LedgerError;
+use crate::model::Transaction;
+
+/// The five fundamental account types
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AccountType {
+    Asset,
+    Liability,
+    Equity,
+    Income,
+    Expense,
+}
+
+/// Which side increases an account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalBalance {
+    Debit,
+    Credit,
+}
+
+impl AccountType {
+    /// Assets and expenses grow with debits; everything else with credits
+    pub fn normal_balance(&self) -> NormalBalance {
+        match self {
+            AccountType::Asset | AccountType::Expense => NormalBalance::Debit,
+            AccountType::Liability | AccountType::Equity | AccountType::Income => NormalBalance::Credit,
+        }
+    }
+}
+
+/// Where an account's movements appear on the cash flow statement
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CashFlow {
+    Cash, // The account holds cash; its change is what the statement explains
+    Operating,
+    Investing,
+    Financing,
+}
+
+/// One account in the chart
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountDef {
+    pub code: String, // Matches JournalEntry::account_id
+    pub name: String,
+    #[serde(rename = "type")]
+    pub account_type: AccountType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>, // Rolls up into this account instead of the code's own prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cash_flow: Option<CashFlow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired_from: Option<NaiveDate>, // Closed to postings dated on or after this day
+}
+
+impl AccountDef {
+    /// The account's cash flow activity, defaulted from its type
+    pub fn cash_flow(&self) -> CashFlow {
+        self.cash_flow.unwrap_or(match self.account_type {
+            AccountType::Equity => CashFlow::Financing,
+            _ => CashFlow::Operating,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ChartFile {
+    accounts: Vec<AccountDef>,
+}
+
+/// All known accounts, keyed by code
+#[derive(Debug, Clone, Default)]
+pub struct ChartOfAccounts {
+    accounts: BTreeMap<String, AccountDef>,
+}
+
+impl ChartOfAccounts {
+    /// Builds a chart, rejecting duplicate account codes and broken hierarchies
+    pub fn new(accounts: Vec<AccountDef>) -> Result<Self, LedgerError> {
+        let mut by_code = BTreeMap::new();
+        for account in accounts {
+            if account.cash_flow == Some(CashFlow::Cash) && account.account_type != AccountType::Asset {
+                return Err(LedgerError::Config(format!("Account {} is marked as cash but is not an asset", account.code)));
+            }
+            if let Some(existing) = by_code.insert(account.code.clone(), account) {
+                return Err(LedgerError::Config(format!("Account code {} is defined twice", existing.code)));
+            }
+        }
+        let chart = ChartOfAccounts { accounts: by_code };
+        chart.check_hierarchy()?;
+        Ok(chart)
+    }
+
+    /// Explicit parents must exist, share their children's type and never loop
+    fn check_hierarchy(&self) -> Result<(), LedgerError> {
+        for account in self.accounts() {
+            if let Some(parent_code) = &account.parent {
+                let parent = self.get(parent_code).ok_or_else(|| LedgerError::Config(format!(
+                    "Account {} rolls up into {}, which is not in the chart", account.code, parent_code)))?;
+                if parent.account_type != account.account_type {
+                    return Err(LedgerError::Config(format!("Account {} ({:?}) cannot roll up into {} ({:?})",
+                        account.code, account.account_type, parent.code, parent.account_type)));
+                }
+            }
+            if self.ancestors(&account.code).contains(&account.code) {
+                return Err(LedgerError::Config(format!("Account {} rolls up into itself", account.code)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads the chart from a .toml file, or JSON for any other extension
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read chart of accounts {}: {}", path, e)))?;
+        let file: ChartFile = if path.ends_with(".toml") {
+            toml::from_str(&data).map_err(|e| LedgerError::Config(format!("Invalid chart of accounts {}: {}", path, e)))?
+        } else {
+            serde_json::from_str(&data).map_err(|e| LedgerError::Config(format!("Invalid chart of accounts {}: {}", path, e)))?
+        };
+        ChartOfAccounts::new(file.accounts)
+    }
+
+    pub fn get(&self, code: &str) -> Option<&AccountDef> {
+        self.accounts.get(code)
+    }
+
+    pub fn accounts(&self) -> impl Iterator<Item = &AccountDef> {
+        self.accounts.values()
+    }
+
+    /// The account a code rolls up into: its `parent` in the chart, or else
+    /// the code up to its last ':'
+    pub fn parent(&self, code: &str) -> Option<String> {
+        match self.get(code).and_then(|account| account.parent.clone()) {
+            Some(parent) => Some(parent),
+            None => code.rsplit_once(':').map(|(prefix, _)| prefix.to_string()),
+        }
+    }
+
+    /// Every account a code rolls up into, nearest first. Stops at the first
+    /// repeat, so a loop shows up as the code among its own ancestors.
+    pub fn ancestors(&self, code: &str) -> Vec<String> {
+        let mut ancestors: Vec<String> = Vec::new();
+        let mut current = self.parent(code);
+        while let Some(parent) = current {
+            if ancestors.contains(&parent) {
+                break;
+            }
+            current = if parent == code { None } else { self.parent(&parent) };
+            ancestors.push(parent);
+        }
+        ancestors
+    }
+
+    /// Checks every entry against the chart and returns one message per problem.
+    /// Unknown account codes are always reported, and so are retired ones if
+    /// the transaction is dated from their retirement on. With `normal_balance`, so is
+    /// any entry posted against its account's normal balance (e.g. a debit to an
+    /// income account), which is legitimate for corrections but worth blocking
+    /// in books that post those through dedicated contra accounts.
+    pub fn validate(&self, tx: &Transaction, normal_balance: bool) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (i, entry) in tx.entries.iter().enumerate() {
+            let account = match self.get(&entry.account_id) {
+                Some(account) => account,
+                None => {
+                    problems.push(format!("Entry #{}: unknown account {}", i, entry.account_id));
+                    continue;
+                }
+            };
+            if let Some(retired_from) = account.retired_from.filter(|retired_from| tx.timestamp.local_date() >= *retired_from) {
+                problems.push(format!("Entry #{}: account {} {} is retired from {}", i, entry.account_id, account.name, retired_from));
+            }
+            if !normal_balance {
+                continue;
+            }
+            let is_posted = |amount: &str| amount.parse::<f64>().map(|a| a != 0.0).unwrap_or(false);
+            let wrong_side = match account.account_type.normal_balance() {
+                NormalBalance::Debit if is_posted(&entry.credit) => Some("credited"),
+                NormalBalance::Credit if is_posted(&entry.debit) => Some("debited"),
+                _ => None,
+            };
+            if let Some(side) = wrong_side {
+                problems.push(format!("Entry #{}: {} {} ({:?}) is {} against its normal balance",
+                    i, entry.account_id, account.name, account.account_type, side));
+            }
+        }
+        problems
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+
+    fn chart() -> ChartOfAccounts {
+        let account = |code: &str, name: &str, account_type| AccountDef {
+            code: code.to_string(), name: name.to_string(), account_type, parent: None, cash_flow: None, retired_from: None,
+        };
+        let mut petty_cash = account("10200", "Petty cash", AccountType::Asset);
+        petty_cash.retired_from = NaiveDate::from_ymd_opt(2023, 11, 14); // The day of the 1_700_000_000 timestamp
+        ChartOfAccounts::new(vec![account("10100", "Cash", AccountType::Asset), account("40100", "Sales", AccountType::Income), petty_cash]).unwrap()
+    }
+
+    fn posting(debit_account: &str, credit_account: &str) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .entry(debit_account, "5.00", "0.00")
+            .entry(credit_account, "0.00", "5.00")
+            .memo("Posting")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn unknown_accounts_are_always_reported() {
+        let problems = chart().validate(&posting("10100", "99999"), false);
+        assert_eq!(problems, ["Entry #1: unknown account 99999"]);
+    }
+
+    #[test]
+    fn retired_accounts_are_closed_from_their_retirement_on() {
+        let problems = chart().validate(&posting("10200", "40100"), false);
+        assert_eq!(problems, ["Entry #0: account 10200 Petty cash is retired from 2023-11-14"]);
+        let mut earlier = posting("10200", "40100");
+        earlier.timestamp = (1_700_000_000 - 86_400).into();
+        assert!(chart().validate(&earlier, false).is_empty());
+        assert_eq!(chart().get("10200").unwrap().name, "Petty cash", "still in the chart for reports");
+    }
+
+    #[test]
+    fn the_normal_balance_is_checked_only_when_asked() {
+        let refund = posting("40100", "10100"); // Debits income, credits cash
+        assert!(chart().validate(&refund, false).is_empty());
+        let problems = chart().validate(&refund, true);
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        assert!(problems[0].contains("debited against its normal balance"));
+        assert!(problems[1].contains("credited against its normal balance"));
+        assert!(chart().validate(&posting("10100", "40100"), true).is_empty());
+    }
+}
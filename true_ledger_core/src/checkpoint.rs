@@ -0,0 +1,101 @@
+//! Signed Checkpoints
+//! A checkpoint (a "signed tree head") is the ledger operator's signed
+//! statement that, at `timestamp`, the journal held `tree_size` transactions
+//! whose Merkle root was `root`. Auditors archive checkpoints: an inclusion
+//! proof checked against an archived root shows a transaction was in the
+//! ledger at that time, and a journal that no longer reproduces an archived
+//! root has had its history rewritten.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::canonical::to_jcs;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::merkle::{parse_hash, Hash, MerkleTree};
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+use crate::verify::decode_signature;
+
+/// Prefixed to the signed bytes so a checkpoint signature can never be
+/// replayed as a transaction signature, or the other way round
+const SIGNING_CONTEXT: &[u8] = b"true-ledger checkpoint v1\n";
+
+/// What the operator signs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Checkpoint {
+    pub operator_did: String,
+    pub tree_size: u64,      // Transactions covered: the first tree_size of the journal
+    pub height: Option<u64>, // Chain height of the last of them (None for an empty journal)
+    pub root: String,        // Merkle root (hex) over those transactions
+    pub timestamp: Timestamp,
+}
+
+/// A checkpoint and the operator's signature over it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedCheckpoint {
+    pub checkpoint: Checkpoint,
+    pub signature: String, // Multibase (base58btc)
+}
+
+impl Checkpoint {
+    /// A checkpoint over the whole of `tree`
+    pub fn new(tree: &MerkleTree, height: Option<u64>, operator_did: &str, timestamp: Timestamp) -> Self {
+        Checkpoint {
+            operator_did: operator_did.to_string(),
+            tree_size: tree.size() as u64,
+            height,
+            root: hex::encode(tree.root()),
+            timestamp,
+        }
+    }
+
+    /// The exact bytes the operator signs: a context string, then the JCS form
+    fn signing_input(&self) -> Result<Vec<u8>, LedgerError> {
+        let mut input = SIGNING_CONTEXT.to_vec();
+        input.extend_from_slice(to_jcs(self)?.as_bytes());
+        Ok(input)
+    }
+
+    /// Signs the checkpoint. The operator must be the signer's own DID.
+    pub fn sign(self, signer: &dyn TransactionSigner) -> Result<SignedCheckpoint, LedgerError> {
+        if self.operator_did != signer.did() {
+            return Err(LedgerError::Key(format!("Checkpoint names operator {} but the signing key is {}", self.operator_did, signer.did())));
+        }
+        let signature = signer.sign_bytes(&self.signing_input()?)?;
+        Ok(SignedCheckpoint {
+            checkpoint: self,
+            signature: multibase::encode(multibase::Base::Base58Btc, signature),
+        })
+    }
+
+    pub fn root_hash(&self) -> Result<Hash, LedgerError> {
+        parse_hash(&self.root)
+    }
+
+    /// Checks that the first `tree_size` transactions of `tree` still have
+    /// this checkpoint's root, i.e. the journal was only appended to since
+    pub fn check_tree(&self, tree: &MerkleTree) -> Result<(), LedgerError> {
+        let root = tree.root_at(self.tree_size as usize).ok_or_else(|| LedgerError::Chain(format!(
+            "The checkpoint covers {} transactions but the journal only has {}", self.tree_size, tree.size())))?;
+        if root != self.root_hash()? {
+            return Err(LedgerError::Chain(format!("The journal's first {} transactions have root {}, not the checkpointed {}",
+                self.tree_size, hex::encode(root), self.root)));
+        }
+        Ok(())
+    }
+}
+
+impl SignedCheckpoint {
+    /// What gets anchored externally: the SHA-256 of the signed checkpoint's
+    /// JCS form, so reformatting the file does not change it
+    pub fn digest(&self) -> Result<Hash, LedgerError> {
+        Ok(Sha256::digest(to_jcs(self)?.as_bytes()).into())
+    }
+
+    /// Checks the operator's signature
+    pub fn verify(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let key = resolver.resolve_public_key(&self.checkpoint.operator_did)?;
+        key.verify_bytes(&self.checkpoint.signing_input()?, &decode_signature(&self.signature)?)
+    }
+}
@@ -0,0 +1,254 @@
+//! Cloud Accounting Connector (Xero / QuickBooks Online)
+//! Verified transactions leave the ledger as journals in a bookkeeping
+//! service: a Xero ManualJournal or a QuickBooks Online JournalEntry, one
+//! per transaction, each line the net of an entry (debits positive). The
+//! service's account for each of our codes comes from an account map; codes
+//! it does not name are sent as they are.
+//!
+//! A [`SyncState`] records what was posted, by payload hash, with the id and
+//! status the service confirmed, so a sync only sends what is new. The same
+//! hash also keys each request with the service's own idempotency control
+//! (Xero's Idempotency-Key header, QuickBooks' requestid), so a request
+//! retried after a lost response is not posted twice. The HTTP side lives
+//! with the command that pushes (see the CLI's connector.rs).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::amount::{format_cents, parse_cents};
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::timestamp::Timestamp;
+
+/// The bookkeeping service
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Service {
+    Xero,
+    QuickBooks,
+}
+
+impl Service {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Service::Xero => "Xero",
+            Service::QuickBooks => "QuickBooks Online",
+        }
+    }
+
+    /// The idempotency key for a transaction (QuickBooks caps requestid at 50 characters)
+    pub fn request_key(&self, tx_hash: &str) -> String {
+        match self {
+            Service::Xero => tx_hash.to_string(),
+            Service::QuickBooks => tx_hash[..32.min(tx_hash.len())].to_string(),
+        }
+    }
+
+    /// The request body that posts `tx`
+    pub fn journal(&self, tx: &Transaction, accounts: &AccountMap) -> Result<Value, LedgerError> {
+        let lines = journal_lines(tx, accounts)?;
+        let date = tx.timestamp.local_date().format("%Y-%m-%d").to_string();
+        let reference = format!("tlc-{}", &tx.hash_hex()[..16]); // Ties the journal back to our hash
+        let memo: String = tx.memo.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+        let body = match self {
+            Service::Xero => json!({
+                "ManualJournals": [{
+                    "Narration": format!("{} ({})", memo, reference),
+                    "Date": date,
+                    "Status": "POSTED",
+                    "JournalLines": lines.iter().map(|(account, net)| json!({
+                        "AccountCode": account,
+                        "LineAmount": amount(*net),
+                        "Description": memo,
+                    })).collect::<Vec<_>>(),
+                }]
+            }),
+            Service::QuickBooks => json!({
+                "TxnDate": date,
+                "DocNumber": reference,
+                "PrivateNote": memo,
+                "Line": lines.iter().map(|(account, net)| json!({
+                    "DetailType": "JournalEntryLineDetail",
+                    "Amount": amount(net.abs()),
+                    "Description": memo,
+                    "JournalEntryLineDetail": {
+                        "PostingType": if *net > 0 { "Debit" } else { "Credit" },
+                        "AccountRef": { "value": account },
+                    },
+                })).collect::<Vec<_>>(),
+            }),
+        };
+        Ok(body)
+    }
+
+    /// The service's id and status for the journal a successful response created
+    pub fn confirmation(&self, response: &Value) -> Result<(String, Option<String>), LedgerError> {
+        let (journal, id_field, status_field) = match self {
+            Service::Xero => (&response["ManualJournals"][0], "ManualJournalID", "Status"),
+            Service::QuickBooks => (&response["JournalEntry"], "Id", "SyncToken"),
+        };
+        let id = journal[id_field].as_str()
+            .ok_or_else(|| LedgerError::Serialization(format!("{} confirmed no {}: {}", self.name(), id_field, response)))?;
+        let status = match (self, &journal[status_field]) {
+            (Service::QuickBooks, Value::String(sync_token)) => Some(format!("SyncToken {}", sync_token)),
+            (_, Value::String(status)) => Some(status.clone()),
+            _ => None,
+        };
+        Ok((id.to_string(), status))
+    }
+}
+
+/// A JSON number with two decimals
+fn amount(cents: i64) -> Value {
+    format_cents(cents).parse::<serde_json::Number>().map(Value::Number).unwrap_or(Value::Null)
+}
+
+/// (Service account, net cents) per entry, skipping entries that net to zero
+fn journal_lines(tx: &Transaction, accounts: &AccountMap) -> Result<Vec<(String, i64)>, LedgerError> {
+    if tx.entries.is_empty() {
+        return Err(LedgerError::Config("Nothing to post: the transaction has no entries".to_string()));
+    }
+    let mut lines = Vec::with_capacity(tx.entries.len());
+    for (i, entry) in tx.entries.iter().enumerate() {
+        if let Some(currency) = &entry.currency {
+            return Err(LedgerError::Config(format!("Entry #{} is in {}: only base-currency transactions can be posted", i, currency)));
+        }
+        let net = parse_cents(&entry.debit).map_err(|e| e.context(format!("Entry #{} debit", i)))?
+            - parse_cents(&entry.credit).map_err(|e| e.context(format!("Entry #{} credit", i)))?;
+        if net != 0 {
+            lines.push((accounts.get(&entry.account_id).to_string(), net));
+        }
+    }
+    Ok(lines)
+}
+
+/// Our account codes to the service's (Xero account codes, QuickBooks account ids)
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct AccountMap {
+    #[serde(default)]
+    accounts: BTreeMap<String, String>,
+}
+
+impl AccountMap {
+    /// Reads a TOML file with an `[accounts]` table, e.g. `"10100" = "090"`
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read account map {}: {}", path, e)))?;
+        toml::from_str(&data).map_err(|e| LedgerError::Config(format!("Invalid account map {}: {}", path, e)))
+    }
+
+    /// The service's account for `code`: as mapped, else the code itself
+    pub fn get<'a>(&'a self, code: &'a str) -> &'a str {
+        self.accounts.get(code).map_or(code, String::as_str)
+    }
+}
+
+/// What the service confirmed for one posted transaction
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Confirmation {
+    pub remote_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    pub posted_at: Timestamp,
+}
+
+/// What has been posted to one service, by payload hash
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncState {
+    pub service: Service,
+    #[serde(default)]
+    pub posted: BTreeMap<String, Confirmation>,
+}
+
+impl SyncState {
+    pub fn new(service: Service) -> Self {
+        SyncState { service, posted: BTreeMap::new() }
+    }
+
+    /// Reads the state file, or starts afresh if there is none. The state
+    /// must belong to `service`: ids from one service mean nothing to another.
+    pub fn load(path: &str, service: Service) -> Result<Self, LedgerError> {
+        let state: SyncState = match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| LedgerError::Serialization(format!("Invalid sync state {}: {}", path, e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(SyncState::new(service)),
+            Err(e) => return Err(LedgerError::Io(format!("Could not read sync state {}: {}", path, e))),
+        };
+        if state.service != service {
+            return Err(LedgerError::Config(format!("{} tracks a sync with {}, not {}", path, state.service.name(), service.name())));
+        }
+        Ok(state)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), LedgerError> {
+        let data = serde_json::to_string_pretty(self).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        fs::write(path, data).map_err(|e| LedgerError::Io(format!("Could not write sync state {}: {}", path, e)))
+    }
+
+    /// Whether `tx` has been posted already
+    pub fn is_posted(&self, tx: &Transaction) -> bool {
+        self.posted.contains_key(&tx.hash_hex())
+    }
+
+    pub fn record(&mut self, tx: &Transaction, confirmation: Confirmation) {
+        self.posted.insert(tx.hash_hex(), confirmation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+
+    fn invoice() -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .entry("11000", "1200.50", "0.00")
+            .entry("40100", "0.00", "1000.00")
+            .entry("20300", "0.00", "200.50")
+            .memo("Invoice 1042")
+            .genesis()
+            .sign(&Account::generate())
+            .unwrap()
+            .payload
+    }
+
+    #[test]
+    fn journals_carry_signed_lines_in_the_services_accounts() {
+        let accounts: AccountMap = toml::from_str("[accounts]\n\"11000\" = \"610\"\n").unwrap();
+        let tx = invoice();
+        let xero = Service::Xero.journal(&tx, &accounts).unwrap();
+        let lines = &xero["ManualJournals"][0]["JournalLines"];
+        assert_eq!(lines[0]["AccountCode"], "610");
+        assert_eq!(lines[0]["LineAmount"].to_string(), "1200.5");
+        assert_eq!(lines[2]["AccountCode"], "20300", "unmapped codes are sent as they are");
+        assert_eq!(lines[2]["LineAmount"].to_string(), "-200.5");
+
+        let quickbooks = Service::QuickBooks.journal(&tx, &accounts).unwrap();
+        assert_eq!(quickbooks["Line"][1]["JournalEntryLineDetail"]["PostingType"], "Credit");
+        assert_eq!(quickbooks["Line"][1]["Amount"].to_string(), "1000.0");
+        assert_eq!(quickbooks["DocNumber"].as_str().unwrap().len(), 20, "within QuickBooks' 21 characters");
+        assert_eq!(Service::QuickBooks.request_key(&tx.hash_hex()).len(), 32);
+    }
+
+    #[test]
+    fn confirmations_are_tracked_per_service() {
+        let response = json!({ "ManualJournals": [{ "ManualJournalID": "8f5e", "Status": "POSTED" }] });
+        assert_eq!(Service::Xero.confirmation(&response).unwrap(), ("8f5e".to_string(), Some("POSTED".to_string())));
+        assert!(Service::QuickBooks.confirmation(&response).is_err());
+
+        let tx = invoice();
+        let mut state = SyncState::new(Service::Xero);
+        assert!(!state.is_posted(&tx));
+        state.record(&tx, Confirmation { remote_id: "8f5e".to_string(), status: None, posted_at: 1_700_000_100.into() });
+        assert!(state.is_posted(&tx));
+
+        let path = crate::test_util::scratch_dir("connector").join("xero.json");
+        let path = path.to_str().unwrap();
+        state.save(path).unwrap();
+        assert!(SyncState::load(path, Service::Xero).unwrap().is_posted(&tx));
+        assert!(SyncState::load(path, Service::QuickBooks).is_err());
+    }
+}
@@ -0,0 +1,393 @@
+//! COSE_Sign1 Envelope (RFC 9052)
+//! For constrained devices a signed transaction can be binary: a tagged
+//! COSE_Sign1 structure whose payload is the transaction as deterministic
+//! CBOR (the DAG-CBOR encoding used for CIDs). The protected header names
+//! the algorithm (EdDSA -8, ES256K -47); the unprotected header carries the
+//! author's verification method as `kid`. The signature covers the
+//! Sig_structure ["Signature1", protected, h'', payload], as the RFC requires.
+
+use serde_json::{Map, Number, Value};
+
+use crate::dag_cbor::to_dag_cbor;
+use crate::data_integrity::verification_method;
+use crate::did::{did_to_verifying_key, DidResolver};
+use crate::error::LedgerError;
+use crate::keys::SigAlg;
+use crate::model::Transaction;
+use crate::signer::TransactionSigner;
+
+/// CBOR tag of a COSE_Sign1 structure
+pub const COSE_SIGN1_TAG: u64 = 18;
+const HEADER_ALG: i64 = 1;
+const HEADER_KID: i64 = 4;
+
+/// The COSE algorithm identifier for a signature suite
+pub fn cose_alg(sig_alg: SigAlg) -> i64 {
+    match sig_alg {
+        SigAlg::Ed25519 => -8,    // EdDSA
+        SigAlg::Secp256k1 => -47, // ES256K
+    }
+}
+
+/// One CBOR data item, as much of CBOR as COSE and our payloads use
+#[derive(Debug, Clone, PartialEq)]
+enum Cbor {
+    Int(i128),
+    Bytes(Vec<u8>),
+    Text(String),
+    Array(Vec<Cbor>),
+    Map(Vec<(Cbor, Cbor)>),
+    Tag(u64, Box<Cbor>),
+    Bool(bool),
+    Null,
+    Float(f64),
+}
+
+fn write_head(major: u8, arg: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match arg {
+        0..=23 => out.push(major | arg as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, arg as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(arg as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(arg as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&arg.to_be_bytes());
+        }
+    }
+}
+
+impl Cbor {
+    /// Encodes with shortest heads and definite lengths; maps keep their order
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Cbor::Int(i) if *i >= 0 => write_head(0, *i as u64, out),
+            Cbor::Int(i) => write_head(1, (-1 - *i) as u64, out),
+            Cbor::Bytes(bytes) => {
+                write_head(2, bytes.len() as u64, out);
+                out.extend_from_slice(bytes);
+            }
+            Cbor::Text(text) => {
+                write_head(3, text.len() as u64, out);
+                out.extend_from_slice(text.as_bytes());
+            }
+            Cbor::Array(items) => {
+                write_head(4, items.len() as u64, out);
+                items.iter().for_each(|item| item.encode(out));
+            }
+            Cbor::Map(entries) => {
+                write_head(5, entries.len() as u64, out);
+                for (key, value) in entries {
+                    key.encode(out);
+                    value.encode(out);
+                }
+            }
+            Cbor::Tag(tag, item) => {
+                write_head(6, *tag, out);
+                item.encode(out);
+            }
+            Cbor::Bool(b) => out.push(if *b { 0xf5 } else { 0xf4 }),
+            Cbor::Null => out.push(0xf6),
+            Cbor::Float(f) => {
+                out.push(0xfb);
+                out.extend_from_slice(&f.to_be_bytes());
+            }
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+
+    /// Decodes exactly one item filling all of `bytes`
+    fn decode(bytes: &[u8]) -> Result<Cbor, LedgerError> {
+        let mut pos = 0;
+        let item = Cbor::decode_item(bytes, &mut pos, 0)?;
+        if pos != bytes.len() {
+            return Err(malformed("trailing bytes after the CBOR item"));
+        }
+        Ok(item)
+    }
+
+    fn decode_item(bytes: &[u8], pos: &mut usize, depth: usize) -> Result<Cbor, LedgerError> {
+        if depth > 64 {
+            return Err(malformed("nested too deeply"));
+        }
+        let initial = *bytes.get(*pos).ok_or_else(|| malformed("truncated"))?;
+        *pos += 1;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        if major == 7 {
+            return match info {
+                20 => Ok(Cbor::Bool(false)),
+                21 => Ok(Cbor::Bool(true)),
+                22 => Ok(Cbor::Null),
+                27 => Ok(Cbor::Float(f64::from_be_bytes(take(bytes, pos, 8)?.try_into().expect("8 bytes")))),
+                _ => Err(malformed("unsupported simple value or float width")),
+            };
+        }
+        let arg = match info {
+            0..=23 => info as u64,
+            24 => take(bytes, pos, 1)?[0] as u64,
+            25 => u16::from_be_bytes(take(bytes, pos, 2)?.try_into().expect("2 bytes")) as u64,
+            26 => u32::from_be_bytes(take(bytes, pos, 4)?.try_into().expect("4 bytes")) as u64,
+            27 => u64::from_be_bytes(take(bytes, pos, 8)?.try_into().expect("8 bytes")),
+            _ => return Err(malformed("indefinite lengths are not deterministic CBOR")),
+        };
+        let len = |arg: u64| usize::try_from(arg).map_err(|_| malformed("length out of range"));
+        match major {
+            0 => Ok(Cbor::Int(arg as i128)),
+            1 => Ok(Cbor::Int(-1 - arg as i128)),
+            2 => Ok(Cbor::Bytes(take(bytes, pos, len(arg)?)?.to_vec())),
+            3 => String::from_utf8(take(bytes, pos, len(arg)?)?.to_vec())
+                .map(Cbor::Text)
+                .map_err(|_| malformed("text string is not UTF-8")),
+            4 => (0..arg).map(|_| Cbor::decode_item(bytes, pos, depth + 1)).collect::<Result<_, _>>().map(Cbor::Array),
+            5 => (0..arg)
+                .map(|_| Ok((Cbor::decode_item(bytes, pos, depth + 1)?, Cbor::decode_item(bytes, pos, depth + 1)?)))
+                .collect::<Result<_, LedgerError>>()
+                .map(Cbor::Map),
+            _ => Ok(Cbor::Tag(arg, Box::new(Cbor::decode_item(bytes, pos, depth + 1)?))),
+        }
+    }
+
+    /// The JSON a payload item stands for (text keys only, no byte strings)
+    fn into_json(self) -> Result<Value, LedgerError> {
+        Ok(match self {
+            Cbor::Int(i) => match (u64::try_from(i), i64::try_from(i)) {
+                (Ok(u), _) => Value::Number(u.into()),
+                (_, Ok(i)) => Value::Number(i.into()),
+                _ => return Err(malformed("integer out of range")),
+            },
+            Cbor::Text(text) => Value::String(text),
+            Cbor::Array(items) => Value::Array(items.into_iter().map(Cbor::into_json).collect::<Result<_, _>>()?),
+            Cbor::Map(entries) => {
+                let mut map = Map::new();
+                for (key, value) in entries {
+                    let Cbor::Text(key) = key else { return Err(malformed("payload map keys must be text")) };
+                    map.insert(key, value.into_json()?);
+                }
+                Value::Object(map)
+            }
+            Cbor::Bool(b) => Value::Bool(b),
+            Cbor::Null => Value::Null,
+            Cbor::Float(f) => Number::from_f64(f).map(Value::Number).ok_or_else(|| malformed("non-finite float"))?,
+            Cbor::Bytes(_) | Cbor::Tag(..) => return Err(malformed("byte strings and tags have no JSON form")),
+        })
+    }
+
+    /// Looks up an integer label in a header map
+    fn label(&self, label: i64) -> Option<&Cbor> {
+        match self {
+            Cbor::Map(entries) => entries.iter().find(|(key, _)| *key == Cbor::Int(label as i128)).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+fn malformed(what: &str) -> LedgerError {
+    LedgerError::Serialization(format!("Malformed COSE_Sign1: {}", what))
+}
+
+fn take<'a>(bytes: &'a [u8], pos: &mut usize, n: usize) -> Result<&'a [u8], LedgerError> {
+    let end = pos.checked_add(n).filter(|end| *end <= bytes.len()).ok_or_else(|| malformed("truncated"))?;
+    let slice = &bytes[*pos..end];
+    *pos = end;
+    Ok(slice)
+}
+
+/// The bytes a COSE_Sign1 signature covers
+fn sig_structure(protected: &[u8], payload: &[u8]) -> Vec<u8> {
+    Cbor::Array(vec![
+        Cbor::Text("Signature1".to_string()),
+        Cbor::Bytes(protected.to_vec()),
+        Cbor::Bytes(Vec::new()), // No external additional authenticated data
+        Cbor::Bytes(payload.to_vec()),
+    ]).to_bytes()
+}
+
+/// A transaction in a COSE_Sign1 envelope
+#[derive(Debug, Clone)]
+pub struct CoseTransaction {
+    pub bytes: Vec<u8>,       // The tagged COSE_Sign1, exactly as signed
+    pub alg: i64,
+    pub kid: String,
+    pub payload: Transaction,
+    protected: Vec<u8>,
+    payload_bytes: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+/// Signs `tx` as a tagged COSE_Sign1. The author must be the signer's own DID.
+pub fn sign_cose(tx: Transaction, signer: &dyn TransactionSigner) -> Result<CoseTransaction, LedgerError> {
+    let tx = tx.prepare_for_signing();
+    if tx.author_did != signer.did() {
+        return Err(LedgerError::Key(format!("Transaction names author {} but the signing key is {}", tx.author_did, signer.did())));
+    }
+    let alg = cose_alg(did_to_verifying_key(signer.did())?.sig_alg());
+    let kid = verification_method(signer.did());
+    let protected = Cbor::Map(vec![(Cbor::Int(HEADER_ALG as i128), Cbor::Int(alg as i128))]).to_bytes();
+    let payload_bytes = to_dag_cbor(&tx)?;
+    let signature = signer.sign_bytes(&sig_structure(&protected, &payload_bytes))?;
+
+    let bytes = Cbor::Tag(COSE_SIGN1_TAG, Box::new(Cbor::Array(vec![
+        Cbor::Bytes(protected.clone()),
+        Cbor::Map(vec![(Cbor::Int(HEADER_KID as i128), Cbor::Bytes(kid.as_bytes().to_vec()))]),
+        Cbor::Bytes(payload_bytes.clone()),
+        Cbor::Bytes(signature.clone()),
+    ]))).to_bytes();
+    Ok(CoseTransaction { bytes, alg, kid, payload: tx, protected, payload_bytes, signature })
+}
+
+impl CoseTransaction {
+    /// Whether `bytes` look like a COSE_Sign1 (tagged, or a bare four-item array)
+    pub fn detect(bytes: &[u8]) -> bool {
+        matches!(bytes.first(), Some(0xd2) | Some(0x84))
+    }
+
+    /// Parses a COSE_Sign1, tagged or not
+    pub fn parse(bytes: &[u8]) -> Result<Self, LedgerError> {
+        let item = match Cbor::decode(bytes)? {
+            Cbor::Tag(COSE_SIGN1_TAG, item) => *item,
+            Cbor::Tag(tag, _) => return Err(malformed(&format!("tag {} is not COSE_Sign1", tag))),
+            item => item,
+        };
+        let Cbor::Array(parts) = item else { return Err(malformed("not an array")) };
+        let [Cbor::Bytes(protected), unprotected, Cbor::Bytes(payload_bytes), Cbor::Bytes(signature)] = &parts[..] else {
+            return Err(malformed("expected [protected, unprotected, payload, signature]"));
+        };
+
+        let protected_map = if protected.is_empty() { Cbor::Map(Vec::new()) } else { Cbor::decode(protected)? };
+        let alg = match protected_map.label(HEADER_ALG) {
+            Some(Cbor::Int(alg)) => i64::try_from(*alg).map_err(|_| malformed("alg out of range"))?,
+            _ => return Err(malformed("no alg in the protected header")),
+        };
+        let kid = match protected_map.label(HEADER_KID).or_else(|| unprotected.label(HEADER_KID)) {
+            Some(Cbor::Bytes(kid)) => String::from_utf8(kid.clone()).map_err(|_| malformed("kid is not UTF-8"))?,
+            _ => return Err(malformed("no kid header")),
+        };
+        let payload = serde_json::from_value(Cbor::decode(payload_bytes)?.into_json()?)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e)))?;
+        Ok(CoseTransaction {
+            bytes: bytes.to_vec(),
+            alg,
+            kid,
+            payload,
+            protected: protected.clone(),
+            payload_bytes: payload_bytes.clone(),
+            signature: signature.clone(),
+        })
+    }
+
+    /// The payload as it was signed, every field kept
+    pub fn payload_json(&self) -> Result<Value, LedgerError> {
+        Cbor::decode(&self.payload_bytes)?.into_json()
+    }
+
+    /// The DID the `kid` belongs to
+    pub fn signer_did(&self) -> &str {
+        self.kid.split('#').next().unwrap_or_default()
+    }
+
+    /// Checks that the `kid` is the author and the signature is theirs under `alg`
+    pub fn verify_signature(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        if self.signer_did() != self.payload.author_did {
+            return Err(LedgerError::Signature(format!("COSE kid {} does not belong to the author {}", self.kid, self.payload.author_did)));
+        }
+        let key = resolver.resolve_public_key(&self.payload.author_did)?;
+        if self.alg != cose_alg(key.sig_alg()) {
+            return Err(LedgerError::Signature(format!("COSE alg {} does not match the author's key (expected {})",
+                self.alg, cose_alg(key.sig_alg()))));
+        }
+        key.verify_bytes(&sig_structure(&self.protected, &self.payload_bytes), &self.signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::signed;
+
+    /// RFC 8949 Appendix A items, which must decode and re-encode unchanged
+    #[test]
+    fn rfc8949_examples_round_trip() {
+        let examples = [
+            "00", "17", "1818", "1b000000e8d4a51000", "1bffffffffffffffff", "3bffffffffffffffff", "3903e7",
+            "fb7e37e43c8800759c", "f4", "f5", "f6", "40", "4401020304", "60", "63e6b0b4",
+            "80", "8301820203820405", "a0", "a201020304", "a26161016162820203", "c11a514b67b0",
+            "d74401020304", "d818456449455446",
+        ];
+        for example in examples {
+            let bytes = hex::decode(example).unwrap();
+            assert_eq!(hex::encode(Cbor::decode(&bytes).unwrap().to_bytes()), example);
+        }
+        assert_eq!(Cbor::decode(&hex::decode("3bffffffffffffffff").unwrap()).unwrap(), Cbor::Int(-18446744073709551616));
+    }
+
+    #[test]
+    fn non_deterministic_or_broken_cbor_is_refused() {
+        for bad in ["5f42010243030405ff", "9fff", "0000", "1903", "f93c00", "62c3", "a1", ""] {
+            assert!(Cbor::decode(&hex::decode(bad).unwrap()).is_err(), "{} was accepted", bad);
+        }
+        let deep = [vec![0x81; 100], vec![0x00]].concat();
+        assert!(Cbor::decode(&deep).is_err());
+    }
+
+    /// RFC 9052 section 4.4: ["Signature1", protected, external_aad, payload]
+    #[test]
+    fn the_sig_structure() {
+        let protected = hex::decode("a10127").unwrap(); // {1: -8}
+        assert_eq!(hex::encode(sig_structure(&protected, b"hi")), "846a5369676e61747572653143a1012740426869");
+    }
+
+    #[test]
+    fn signed_transactions_round_trip() {
+        for account in [Account::generate(), Account::generate_for(SigAlg::Secp256k1)] {
+            let tx = signed(&account, None, 0, "Opening").payload;
+            let cose = sign_cose(tx.clone(), &account).unwrap();
+            assert!(CoseTransaction::detect(&cose.bytes));
+            let parsed = CoseTransaction::parse(&cose.bytes).unwrap();
+            assert_eq!(parsed.alg, cose_alg(account.sig_alg()));
+            assert_eq!(parsed.signer_did(), account.did);
+            assert_eq!(parsed.payload.hash_hex(), tx.hash_hex());
+            parsed.verify_signature(&DidKeyResolver).unwrap();
+            // Untagged, as some libraries write it
+            assert_eq!(cose.bytes[0], 0xd2);
+            CoseTransaction::parse(&cose.bytes[1..]).unwrap().verify_signature(&DidKeyResolver).unwrap();
+        }
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let account = Account::generate();
+        let cose = sign_cose(signed(&account, None, 0, "Opening").payload, &account).unwrap();
+
+        let mut tampered = CoseTransaction::parse(&cose.bytes).unwrap();
+        let at = tampered.payload_bytes.windows(7).position(|w| w == b"Opening").unwrap();
+        tampered.payload_bytes[at] = b'X';
+        assert!(matches!(tampered.verify_signature(&DidKeyResolver), Err(LedgerError::Signature(_))));
+
+        let mut wrong_alg = CoseTransaction::parse(&cose.bytes).unwrap();
+        wrong_alg.alg = -47;
+        assert!(wrong_alg.verify_signature(&DidKeyResolver).is_err());
+
+        let mut foreign_kid = CoseTransaction::parse(&cose.bytes).unwrap();
+        foreign_kid.kid = verification_method(&Account::generate().did);
+        assert!(foreign_kid.verify_signature(&DidKeyResolver).is_err());
+
+        let mut truncated = cose.bytes.clone();
+        truncated.pop();
+        assert!(CoseTransaction::parse(&truncated).is_err());
+        let retagged = [&[0xd1][..], &cose.bytes[1..]].concat(); // COSE_Mac0, not COSE_Sign1
+        assert!(CoseTransaction::parse(&retagged).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+//! Counterparty Statements
+//! What we hold against one customer or supplier: every movement on their
+//! control accounts (receivables, payables) over a period, with the balance
+//! brought forward and carried down, for the counterparty to confirm.
+//!
+//! A transaction is theirs if its memo names them, by name or DID, in a
+//! `counterparty=`, `payee=` or `vendor=` field (see duplicates.rs), or if
+//! it reverses one that does: a reversal undoes their movement whatever its
+//! own memo says. Balances are debit minus credit, so a customer who owes
+//! us shows a positive balance and a supplier we owe a negative one.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::acl::AccountPattern;
+use crate::amount::parse_cents;
+use crate::duplicates::{memo_fields, named_counterparty};
+use crate::error::LedgerError;
+use crate::fiscal::Cutoff;
+use crate::model::Transaction;
+
+/// One movement on a control account
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct CounterpartyLine {
+    pub date: NaiveDate,
+    pub tx_hash: String,
+    pub account_id: String,
+    pub memo: String,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+    pub balance_cents: i64, // Running balance after this line
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct CounterpartyStatement {
+    pub counterparty: String,
+    pub accounts: Vec<String>,     // The control account patterns, as given
+    pub from: Option<NaiveDate>,   // None: from the start of the books
+    pub to: Cutoff,
+    pub opening_cents: i64,        // Brought forward from before `from`
+    pub lines: Vec<CounterpartyLine>,
+    pub closing_cents: i64,        // Carried down at `to`
+}
+
+/// Whether a memo field names `counterparty`: DIDs exactly, names in any case
+pub(crate) fn names(value: &str, counterparty: &str) -> bool {
+    if counterparty.starts_with("did:") {
+        value == counterparty
+    } else {
+        value.eq_ignore_ascii_case(counterparty)
+    }
+}
+
+impl CounterpartyStatement {
+    /// The statement over verified transactions, in chain order
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I, counterparty: &str, accounts: &[String], from: Option<NaiveDate>, to: Cutoff) -> Result<Self, LedgerError> {
+        let patterns = accounts.iter().map(|pattern| AccountPattern::parse(pattern)).collect::<Result<Vec<_>, _>>()?;
+        let txs: Vec<&Transaction> = txs.into_iter().collect();
+        let tagged: HashSet<String> = txs.iter()
+            .filter(|tx| named_counterparty(&memo_fields(&tx.memo)).is_some_and(|value| names(value, counterparty)))
+            .map(|tx| tx.hash_hex())
+            .collect();
+
+        let mut statement = CounterpartyStatement {
+            counterparty: counterparty.to_string(),
+            accounts: accounts.to_vec(),
+            from,
+            to,
+            opening_cents: 0,
+            lines: Vec::new(),
+            closing_cents: 0,
+        };
+        for tx in txs {
+            let tx_hash = tx.hash_hex();
+            let theirs = tagged.contains(&tx_hash) || tx.reverses.as_ref().is_some_and(|reversed| tagged.contains(reversed));
+            if !theirs || !to.includes(tx) {
+                continue;
+            }
+            let date = tx.timestamp.local_date();
+            for (i, entry) in tx.entries.iter().enumerate() {
+                if !patterns.iter().any(|pattern| pattern.matches(&entry.account_id)) {
+                    continue;
+                }
+                let debit_cents = parse_cents(&entry.debit).map_err(|e| e.context(format!("Entry #{} debit", i)))?;
+                let credit_cents = parse_cents(&entry.credit).map_err(|e| e.context(format!("Entry #{} credit", i)))?;
+                statement.closing_cents += debit_cents - credit_cents;
+                if from.is_some_and(|from| date < from) {
+                    statement.opening_cents += debit_cents - credit_cents;
+                    continue;
+                }
+                statement.lines.push(CounterpartyLine {
+                    date,
+                    tx_hash: tx_hash.clone(),
+                    account_id: entry.account_id.clone(),
+                    memo: tx.memo.clone(),
+                    debit_cents,
+                    credit_cents,
+                    balance_cents: statement.closing_cents,
+                });
+            }
+        }
+        Ok(statement)
+    }
+
+    /// The transactions the statement lists, each once, in order
+    pub fn tx_hashes(&self) -> Vec<&str> {
+        let mut hashes: Vec<&str> = self.lines.iter().map(|line| line.tx_hash.as_str()).collect();
+        hashes.dedup();
+        hashes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+
+    fn posting(author: &Account, day: u64, debit: &str, credit: &str, amount: &str, memo: &str) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(1_700_000_000 + day * 86_400)
+            .entry(debit, amount, "0.00")
+            .entry(credit, "0.00", amount)
+            .memo(memo)
+            .genesis()
+            .sign(author)
+            .unwrap()
+            .payload
+    }
+
+    #[test]
+    fn a_statement_brings_forward_and_follows_reversals() {
+        let clerk = Account::generate();
+        let old_invoice = posting(&clerk, 0, "11000", "40100", "300.00", "Invoice 7 counterparty=Acme");
+        let invoice = posting(&clerk, 20, "11000", "40100", "500.00", "Invoice 9 counterparty=acme");
+        let receipt = posting(&clerk, 25, "10100", "11000", "300.00", "Receipt payee=ACME");
+        let other = posting(&clerk, 26, "11000", "40100", "80.00", "Invoice 10 counterparty=globex");
+        let mut reversal = posting(&clerk, 27, "40100", "11000", "500.00", "Reversal of invoice 9");
+        reversal.reverses = Some(invoice.hash_hex());
+        let txs = [old_invoice, invoice, receipt, other, reversal];
+
+        let from = NaiveDate::from_ymd_opt(2023, 11, 24);
+        let to = Cutoff::day(NaiveDate::from_ymd_opt(2023, 12, 31).unwrap());
+        let statement = CounterpartyStatement::from_transactions(&txs, "Acme", &["11000".to_string()], from, to).unwrap();
+        assert_eq!(statement.opening_cents, 30_000);
+        assert_eq!(statement.lines.iter().map(|line| line.balance_cents).collect::<Vec<_>>(), [80_000, 50_000, 0]);
+        assert_eq!(statement.closing_cents, 0);
+        assert_eq!(statement.tx_hashes().len(), 3);
+    }
+}
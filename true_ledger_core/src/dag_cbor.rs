@@ -0,0 +1,165 @@
+//! DAG-CBOR and CIDs (Content Addressing)
+//! A signed transaction encoded as deterministic DAG-CBOR and hashed gives a
+//! CIDv1 that IPFS and other content-addressed stores accept as its address.
+//! DAG-CBOR fixes the choices plain CBOR leaves open: definite lengths,
+//! shortest integer heads, 64-bit floats only, and map keys sorted by length
+//! and then bytewise.
+
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::LedgerError;
+use crate::hashing::MULTIHASH_SHA2_256;
+
+/// Multicodec code for DAG-CBOR
+pub const MULTICODEC_DAG_CBOR: u8 = 0x71;
+/// CID version byte
+pub const CID_V1: u8 = 0x01;
+
+/// Serializes any value to deterministic DAG-CBOR
+pub fn to_dag_cbor<T: Serialize>(value: &T) -> Result<Vec<u8>, LedgerError> {
+    let value = serde_json::to_value(value).map_err(|e| LedgerError::Serialization(format!("Failed to serialize for DAG-CBOR: {}", e)))?;
+    let mut out = Vec::new();
+    write_value(&value, &mut out);
+    Ok(out)
+}
+
+/// CIDv1 (dag-cbor, sha2-256) of the given DAG-CBOR bytes, as multibase
+/// base32 ("bafyrei..."), the form IPFS prints
+pub fn cid_v1(dag_cbor: &[u8]) -> String {
+    // Both codes are below 0x80, so each varint is a single byte
+    let mut cid = vec![CID_V1, MULTICODEC_DAG_CBOR, MULTIHASH_SHA2_256, 32];
+    cid.extend_from_slice(&Sha256::digest(dag_cbor));
+    multibase::encode(multibase::Base::Base32Lower, cid)
+}
+
+/// A CBOR head: major type plus argument in the shortest form
+fn write_head(major: u8, arg: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+    match arg {
+        0..=23 => out.push(major | arg as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, arg as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(arg as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(arg as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&arg.to_be_bytes());
+        }
+    }
+}
+
+fn write_text(s: &str, out: &mut Vec<u8>) {
+    write_head(3, s.len() as u64, out);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_value(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xf6),
+        Value::Bool(b) => out.push(if *b { 0xf5 } else { 0xf4 }),
+        Value::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                write_head(0, u, out);
+            } else if let Some(i) = n.as_i64() {
+                write_head(1, !(i as u64), out); // -1 - i
+            } else {
+                out.push(0xfb);
+                out.extend_from_slice(&n.as_f64().unwrap_or(0.0).to_be_bytes());
+            }
+        }
+        Value::String(s) => write_text(s, out),
+        Value::Array(items) => {
+            write_head(4, items.len() as u64, out);
+            for item in items {
+                write_value(item, out);
+            }
+        }
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.len().cmp(&b.len()).then_with(|| a.as_bytes().cmp(b.as_bytes())));
+            write_head(5, entries.len() as u64, out);
+            for (key, item) in entries {
+                write_text(key, out);
+                write_value(item, out);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn encoded(value: Value) -> String {
+        hex::encode(to_dag_cbor(&value).unwrap())
+    }
+
+    /// RFC 8949 Appendix A, for the items JSON can express
+    #[test]
+    fn rfc8949_examples() {
+        let examples = [
+            (json!(0), "00"),
+            (json!(23), "17"),
+            (json!(24), "1818"),
+            (json!(100), "1864"),
+            (json!(1000), "1903e8"),
+            (json!(1000000), "1a000f4240"),
+            (json!(1000000000000u64), "1b000000e8d4a51000"),
+            (json!(18446744073709551615u64), "1bffffffffffffffff"),
+            (json!(-1), "20"),
+            (json!(-10), "29"),
+            (json!(-100), "3863"),
+            (json!(-1000), "3903e7"),
+            (json!(1.1), "fb3ff199999999999a"),
+            (json!(1.0e+300), "fb7e37e43c8800759c"),
+            (json!(false), "f4"),
+            (json!(true), "f5"),
+            (json!(null), "f6"),
+            (json!(""), "60"),
+            (json!("a"), "6161"),
+            (json!("IETF"), "6449455446"),
+            (json!("\"\\"), "62225c"),
+            (json!("\u{fc}"), "62c3bc"),
+            (json!("\u{6c34}"), "63e6b0b4"),
+            (json!([]), "80"),
+            (json!([1, 2, 3]), "83010203"),
+            (json!([1, [2, 3], [4, 5]]), "8301820203820405"),
+            (json!({}), "a0"),
+            (json!({ "a": 1, "b": [2, 3] }), "a26161016162820203"),
+            (json!(["a", { "b": "c" }]), "826161a161626163"),
+        ];
+        for (value, expected) in examples {
+            assert_eq!(encoded(value.clone()), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    fn map_keys_sort_by_length_then_bytes() {
+        // Plain CBOR's canonical order would put "aa" before "b"
+        assert_eq!(encoded(json!({ "aa": 1, "b": 2, "c": 3 })), "a361620261630362616101");
+    }
+
+    #[test]
+    fn cid_of_the_empty_map() {
+        // The CID IPFS gives `{}` stored as dag-cbor
+        assert_eq!(cid_v1(&to_dag_cbor(&json!({})).unwrap()), "bafyreigbtj4x7ip5legnfznufuopl4sg4knzc2cof6duas4b3q2fy6swua");
+    }
+
+    #[test]
+    fn the_cid_changes_with_any_field() {
+        let a = cid_v1(&to_dag_cbor(&json!({ "memo": "Rent", "amount": "10.00" })).unwrap());
+        let b = cid_v1(&to_dag_cbor(&json!({ "amount": "10.00", "memo": "Rent" })).unwrap());
+        let c = cid_v1(&to_dag_cbor(&json!({ "memo": "Rent", "amount": "10.01" })).unwrap());
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert!(a.starts_with("bafyrei"));
+    }
+}
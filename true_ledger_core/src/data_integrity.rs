@@ -0,0 +1,190 @@
+//! W3C Data Integrity Envelope (eddsa-jcs-2022)
+//! Instead of our own `signature` string, a transaction can travel as a
+//! Verifiable-Credential-style document: the payload is the
+//! `credentialSubject`, the author is the `issuer`, and a `proof` block
+//! carries an Ed25519 signature that any VC verifier supporting the
+//! eddsa-jcs-2022 cryptosuite can check
+//! (https://www.w3.org/TR/vc-di-eddsa/#eddsa-jcs-2022).
+//!
+//! The signed input is SHA-256(JCS(proof options)) || SHA-256(JCS(document
+//! without its proof)), so the document is kept verbatim: fields added by
+//! other tooling are covered by the proof and must survive untouched.
+
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::canonical::to_jcs;
+use crate::did::{did_to_verifying_key, DidResolver};
+use crate::error::LedgerError;
+use crate::keys::SigAlg;
+use crate::model::Transaction;
+use crate::signer::TransactionSigner;
+
+pub const PROOF_TYPE: &str = "DataIntegrityProof";
+pub const CRYPTOSUITE: &str = "eddsa-jcs-2022";
+pub const PROOF_PURPOSE: &str = "assertionMethod";
+pub const CREDENTIALS_CONTEXT: &str = "https://www.w3.org/ns/credentials/v2";
+pub const CREDENTIAL_TYPE: &str = "LedgerTransaction";
+
+/// A transaction issued as a credential with a Data Integrity proof
+#[derive(Debug, Clone)]
+pub struct TransactionCredential {
+    pub document: Value,      // The whole credential, proof included, exactly as signed
+    pub payload: Transaction, // Its credentialSubject
+}
+
+impl Serialize for TransactionCredential {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.document.serialize(serializer)
+    }
+}
+
+/// The verification method a did:key signs with (`did#<multibase key>`);
+/// other DIDs name their first key
+pub fn verification_method(did: &str) -> String {
+    match did.strip_prefix("did:key:") {
+        Some(key) => format!("{}#{}", did, key),
+        None => format!("{}#key-1", did),
+    }
+}
+
+/// The bytes an eddsa-jcs-2022 proof signs: the hashes of the proof options
+/// (the proof without its proofValue) and of the document without its proof
+fn hash_data(proof_options: &Value, unsecured: &Value) -> Result<Vec<u8>, LedgerError> {
+    let mut data = Sha256::digest(to_jcs(proof_options)?.as_bytes()).to_vec();
+    data.extend_from_slice(&Sha256::digest(to_jcs(unsecured)?.as_bytes()));
+    Ok(data)
+}
+
+/// Signs any JSON-LD document: returns it with an eddsa-jcs-2022 `proof`
+/// by `signer` for `purpose`, dated `created` (Unix seconds). `challenge`,
+/// if given, binds an authentication proof to one verifier's request.
+pub fn add_proof(unsecured: Value, signer: &dyn TransactionSigner, created: u64, purpose: &str, challenge: Option<&str>) -> Result<Value, LedgerError> {
+    if did_to_verifying_key(signer.did())?.sig_alg() != SigAlg::Ed25519 {
+        return Err(LedgerError::Key(format!("{} requires an Ed25519 key", CRYPTOSUITE)));
+    }
+    let created = chrono::DateTime::from_timestamp(created as i64, 0)
+        .ok_or_else(|| LedgerError::Timestamp(format!("Proof creation time {} is out of range", created)))?
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let mut proof = json!({
+        "@context": unsecured["@context"],
+        "type": PROOF_TYPE,
+        "cryptosuite": CRYPTOSUITE,
+        "created": created,
+        "verificationMethod": verification_method(signer.did()),
+        "proofPurpose": purpose,
+    });
+    if let Some(challenge) = challenge {
+        proof["challenge"] = Value::String(challenge.to_string());
+    }
+    let signature = signer.sign_bytes(&hash_data(&proof, &unsecured)?)?;
+    proof["proofValue"] = Value::String(multibase::encode(multibase::Base::Base58Btc, signature));
+
+    let mut document = unsecured;
+    document["proof"] = proof;
+    Ok(document)
+}
+
+/// Wraps `tx` in a credential and signs it. `created` (Unix seconds) dates
+/// the proof. Only Ed25519 keys can sign, as the cryptosuite requires.
+pub fn issue(tx: Transaction, signer: &dyn TransactionSigner, created: u64) -> Result<TransactionCredential, LedgerError> {
+    let tx = tx.prepare_for_signing();
+    if tx.author_did != signer.did() {
+        return Err(LedgerError::Key(format!("Transaction names author {} but the signing key is {}", tx.author_did, signer.did())));
+    }
+    let unsecured = json!({
+        "@context": [CREDENTIALS_CONTEXT],
+        "type": ["VerifiableCredential", CREDENTIAL_TYPE],
+        "issuer": tx.author_did,
+        "credentialSubject": tx,
+    });
+    let document = add_proof(unsecured, signer, created, PROOF_PURPOSE, None)?;
+    Ok(TransactionCredential { document, payload: tx })
+}
+
+/// The DID whose verification method signed a document's proof
+pub fn proof_signer(document: &Value) -> &str {
+    let method = document["proof"]["verificationMethod"].as_str().unwrap_or_default();
+    method.split('#').next().unwrap_or_default()
+}
+
+/// Checks a document's eddsa-jcs-2022 proof: made for `purpose` (and
+/// `challenge`, if one is expected) with the key of [`proof_signer`]
+pub fn verify_document(document: &Value, purpose: &str, challenge: Option<&str>, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+    let invalid = |message: String| LedgerError::Signature(message);
+    let proof = document.get("proof").and_then(Value::as_object)
+        .ok_or_else(|| invalid("Document has no proof".to_string()))?;
+    for (field, expected) in [("type", PROOF_TYPE), ("cryptosuite", CRYPTOSUITE), ("proofPurpose", purpose)] {
+        if proof.get(field).and_then(Value::as_str) != Some(expected) {
+            return Err(invalid(format!("Proof {} must be {}, found {}", field, expected, proof.get(field).unwrap_or(&Value::Null))));
+        }
+    }
+    if let Some(challenge) = challenge {
+        if proof.get("challenge").and_then(Value::as_str) != Some(challenge) {
+            return Err(invalid(format!("Proof was not made for challenge {}", challenge)));
+        }
+    }
+
+    let proof_value = proof.get("proofValue").and_then(Value::as_str)
+        .ok_or_else(|| invalid("Proof has no proofValue".to_string()))?;
+    let (base, signature) = multibase::decode(proof_value)
+        .map_err(|e| invalid(format!("Invalid proofValue: {:?}", e)))?;
+    if base != multibase::Base::Base58Btc {
+        return Err(invalid("proofValue must be base58btc encoded".to_string()));
+    }
+
+    let mut proof_options = Value::Object(proof.clone());
+    if let Some(options) = proof_options.as_object_mut() {
+        options.remove("proofValue");
+    }
+    let mut unsecured = document.clone();
+    if let Some(document) = unsecured.as_object_mut() {
+        document.remove("proof");
+    }
+    // A proof scoped to a context is only valid on documents that start with it
+    match proof_options.get("@context").and_then(Value::as_array) {
+        Some(context) => {
+            let document_context = unsecured["@context"].as_array().cloned().unwrap_or_default();
+            if !document_context.starts_with(context) {
+                return Err(invalid("The document's @context does not match the proof's".to_string()));
+            }
+        }
+        None => {
+            if let Some(context) = unsecured.get("@context") {
+                proof_options["@context"] = context.clone();
+            }
+        }
+    }
+
+    let key = resolver.resolve_public_key(proof_signer(document))?;
+    if key.sig_alg() != SigAlg::Ed25519 {
+        return Err(invalid(format!("{} requires an Ed25519 key", CRYPTOSUITE)));
+    }
+    key.verify_bytes(&hash_data(&proof_options, &unsecured)?, &signature)
+}
+
+impl TransactionCredential {
+    /// Reads a credential, keeping every field; the payload is its credentialSubject
+    pub fn from_value(document: Value) -> Result<Self, LedgerError> {
+        let payload = serde_json::from_value(document["credentialSubject"].clone())
+            .map_err(|e| LedgerError::Serialization(format!("Invalid credentialSubject: {}", e)))?;
+        Ok(TransactionCredential { document, payload })
+    }
+
+    /// The DID the proof's verification method belongs to
+    pub fn signer_did(&self) -> &str {
+        proof_signer(&self.document)
+    }
+
+    /// Checks the proof: an eddsa-jcs-2022 assertion by the issuer, who must
+    /// also be the transaction's author
+    pub fn verify_proof(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let issuer = self.document["issuer"].as_str().unwrap_or_default();
+        if issuer != self.payload.author_did || self.signer_did() != issuer {
+            return Err(LedgerError::Signature(format!("Issuer {}, proof signer {} and author {} must be the same DID",
+                issuer, self.signer_did(), self.payload.author_did)));
+        }
+        verify_document(&self.document, PROOF_PURPOSE, None, resolver)
+    }
+}
@@ -0,0 +1,91 @@
+//! did:key encoding and decoding for Ed25519 and secp256k1 public keys, the
+//! W3C DID Documents they expand to, and the resolver interface that lets
+//! other DID methods (see did_web) supply keys and documents too
+
+use ed25519_dalek::PublicKey;
+use serde_json::{json, Value};
+
+use crate::error::LedgerError;
+use crate::keys::VerifyingKey;
+
+/// Convert a public key to 'did:key:z6Mk...' format (The DID)
+pub fn did_from_public_key(public: &PublicKey) -> String {
+    let mut did_key_bytes = vec![0xed, 0x01]; // Ed25519 multicodec prefix
+    did_key_bytes.extend_from_slice(&public.to_bytes());
+    format!("did:key:{}", multibase::encode(multibase::Base::Base58Btc, did_key_bytes))
+}
+
+/// Convert a public key of any supported algorithm to its did:key
+pub fn did_from_verifying_key(key: &VerifyingKey) -> String {
+    format!("did:key:{}", multibase::encode(multibase::Base::Base58Btc, key.to_multicodec_bytes()))
+}
+
+/// Helper to parse a did:key and extract the Ed25519 public key
+pub fn did_to_public_key(did: &str) -> Result<PublicKey, LedgerError> {
+    match did_to_verifying_key(did)? {
+        VerifyingKey::Ed25519(public_key) => Ok(public_key),
+        _ => Err(LedgerError::Did("Not an Ed25519 did:key".to_string())),
+    }
+}
+
+/// Parses a did:key of any supported algorithm; the multicodec prefix
+/// ('z6Mk...' Ed25519, 'zQ3s...' secp256k1) decides which
+pub fn did_to_verifying_key(did: &str) -> Result<VerifyingKey, LedgerError> {
+    let key_str = did.strip_prefix("did:key:").ok_or_else(|| LedgerError::Did("Not a did:key".to_string()))?;
+    multikey_to_verifying_key(key_str)
+}
+
+/// Decodes a multibase, multicodec-tagged key ('z6Mk...', 'zQ3s...'), the form
+/// used both inside did:key and as `publicKeyMultibase` in DID Documents
+pub fn multikey_to_verifying_key(key_str: &str) -> Result<VerifyingKey, LedgerError> {
+    // Decode from Base58btc (the leading 'z' is the multibase prefix)
+    let (base, decoded) = multibase::decode(key_str)
+        .map_err(|e| LedgerError::Did(format!("Multibase decode error: {:?}", e)))?;
+    if base != multibase::Base::Base58Btc {
+        return Err(LedgerError::Did("did:key must be Base58btc encoded".to_string()));
+    }
+
+    // The multicodec prefix says which algorithm the key belongs to
+    VerifyingKey::from_multicodec_bytes(&decoded).map_err(|e| LedgerError::Did(e.message().to_string()))
+}
+
+/// The DID Document a did:key expands to: one Multikey verification method,
+/// named by the key's multibase form and usable for authentication and assertions
+/// (https://w3c-ccg.github.io/did-method-key/)
+pub fn did_key_document(did: &str) -> Result<Value, LedgerError> {
+    did_to_verifying_key(did)?; // Only supported keys get a document
+    let multibase = &did["did:key:".len()..];
+    let method_id = format!("{}#{}", did, multibase);
+    Ok(json!({
+        "@context": ["https://www.w3.org/ns/did/v1", "https://w3id.org/security/multikey/v1"],
+        "id": did,
+        "verificationMethod": [{
+            "id": method_id,
+            "type": "Multikey",
+            "controller": did,
+            "publicKeyMultibase": multibase,
+        }],
+        "authentication": [method_id],
+        "assertionMethod": [method_id],
+    }))
+}
+
+/// Turns an author DID into the public key its signatures are checked against,
+/// and into its full DID Document for other SSI tooling
+pub trait DidResolver {
+    fn resolve_public_key(&self, did: &str) -> Result<VerifyingKey, LedgerError>;
+
+    /// Only did:key unless the resolver knows other methods
+    fn resolve_document(&self, did: &str) -> Result<Value, LedgerError> {
+        did_key_document(did)
+    }
+}
+
+/// Resolves did:key only: the key is in the DID itself, so no I/O is needed
+pub struct DidKeyResolver;
+
+impl DidResolver for DidKeyResolver {
+    fn resolve_public_key(&self, did: &str) -> Result<VerifyingKey, LedgerError> {
+        did_to_verifying_key(did)
+    }
+}
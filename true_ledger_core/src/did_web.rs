@@ -0,0 +1,202 @@
+//! did:web Resolution
+//! A did:web author publishes a DID Document at a well-known HTTPS URL
+//! (did:web:example.com -> https://example.com/.well-known/did.json,
+//! did:web:example.com:users:alice -> https://example.com/users/alice/did.json).
+//! Fetched documents are cached on disk so verification keeps working
+//! offline; with the network disabled only the cache is consulted.
+
+use base64::Engine;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::did::{did_key_document, did_to_verifying_key, multikey_to_verifying_key, DidResolver};
+use crate::error::LedgerError;
+use crate::keys::VerifyingKey;
+
+/// Resolves did:key locally and did:web via HTTPS plus an on-disk cache
+pub struct DidWebResolver {
+    cache_dir: Option<PathBuf>, // None = no cache
+    allow_network: bool,
+}
+
+impl DidWebResolver {
+    pub fn new(cache_dir: Option<PathBuf>, allow_network: bool) -> Self {
+        DidWebResolver { cache_dir, allow_network }
+    }
+
+    /// `$TLC_DID_CACHE` if set, otherwise `~/.tlc/did-cache`
+    pub fn default_cache_dir() -> PathBuf {
+        match std::env::var_os("TLC_DID_CACHE") {
+            Some(dir) => PathBuf::from(dir),
+            None => crate::tlc_home().join("did-cache"),
+        }
+    }
+
+    /// One file per DID, named by its SHA-256 so any DID is a safe file name
+    fn cache_path(&self, did: &str) -> Option<PathBuf> {
+        self.cache_dir.as_ref().map(|dir| dir.join(format!("{}.json", hex::encode(Sha256::digest(did.as_bytes())))))
+    }
+
+    fn read_cache(&self, did: &str) -> Option<String> {
+        self.cache_path(did).and_then(|path| fs::read_to_string(path).ok())
+    }
+
+    /// Best effort: a cache that cannot be written only costs a refetch
+    #[cfg(feature = "network")]
+    fn write_cache(&self, did: &str, document: &str) {
+        if let (Some(dir), Some(path)) = (&self.cache_dir, self.cache_path(did)) {
+            let _ = fs::create_dir_all(dir).and_then(|_| fs::write(path, document));
+        }
+    }
+
+    /// The DID Document as JSON text: fetched fresh when the network is
+    /// allowed (falling back to the cache if the fetch fails), else from cache
+    pub fn fetch_document(&self, did: &str) -> Result<String, LedgerError> {
+        let url = did_web_url(did)?;
+        if !self.allow_network {
+            return self.read_cache(did)
+                .ok_or_else(|| LedgerError::Did(format!("{} is not in the DID cache and network access is disabled", did)));
+        }
+        self.fetch_url(did, &url)
+    }
+
+    /// Built without the `network` feature: the cache is all there is
+    #[cfg(not(feature = "network"))]
+    fn fetch_url(&self, did: &str, _url: &str) -> Result<String, LedgerError> {
+        self.read_cache(did)
+            .ok_or_else(|| LedgerError::Did(format!("{} is not in the DID cache and this build cannot fetch it", did)))
+    }
+
+    #[cfg(feature = "network")]
+    fn fetch_url(&self, did: &str, url: &str) -> Result<String, LedgerError> {
+        let agent = ureq::AgentBuilder::new().timeout(std::time::Duration::from_secs(10)).build();
+        let fetched = agent.get(url).call()
+            .map_err(|e| LedgerError::Did(format!("Could not fetch {}: {}", url, e)))
+            .and_then(|response| response.into_string().map_err(|e| LedgerError::Did(format!("Could not read {}: {}", url, e))));
+        match fetched {
+            Ok(document) => {
+                self.write_cache(did, &document);
+                Ok(document)
+            }
+            Err(e) => self.read_cache(did).ok_or(e),
+        }
+    }
+}
+
+impl DidResolver for DidWebResolver {
+    fn resolve_public_key(&self, did: &str) -> Result<VerifyingKey, LedgerError> {
+        if did.starts_with("did:web:") {
+            assertion_key_from_document(&self.resolve_document(did)?, did)
+        } else {
+            did_to_verifying_key(did)
+        }
+    }
+
+    fn resolve_document(&self, did: &str) -> Result<Value, LedgerError> {
+        if !did.starts_with("did:web:") {
+            return did_key_document(did);
+        }
+        let document: Value = serde_json::from_str(&self.fetch_document(did)?)
+            .map_err(|e| LedgerError::Did(format!("Invalid DID Document for {}: {}", did, e)))?;
+        if document["id"] != did {
+            return Err(LedgerError::Did(format!("DID Document id {} does not match {}", document["id"], did)));
+        }
+        Ok(document)
+    }
+}
+
+/// The HTTPS URL of a did:web's DID Document
+pub fn did_web_url(did: &str) -> Result<String, LedgerError> {
+    let id = did.strip_prefix("did:web:").ok_or_else(|| LedgerError::Did("Not a did:web".to_string()))?;
+    let mut segments = id.split(':');
+    let domain = segments.next().unwrap_or_default().replace("%3A", ":").replace("%3a", ":");
+    let path: Vec<&str> = segments.collect();
+
+    let valid_domain = !domain.is_empty()
+        && domain.chars().all(|c| c.is_ascii_alphanumeric() || "-.:".contains(c));
+    let valid_path = path.iter().all(|p| !p.is_empty() && *p != "." && *p != ".."
+        && p.chars().all(|c| c.is_ascii_alphanumeric() || "-._~%".contains(c)));
+    if !valid_domain || !valid_path {
+        return Err(LedgerError::Did(format!("Malformed did:web {}", did)));
+    }
+
+    if path.is_empty() {
+        Ok(format!("https://{}/.well-known/did.json", domain))
+    } else {
+        Ok(format!("https://{}/{}/did.json", domain, path.join("/")))
+    }
+}
+
+/// Decodes the public key of a single verification method, if it has one
+fn method_public_key(method: &Value) -> Option<Result<VerifyingKey, LedgerError>> {
+    if let Some(multibase) = method["publicKeyMultibase"].as_str() {
+        // Ed25519VerificationKey2020 and Multikey (Ed25519 or secp256k1)
+        return Some(multikey_to_verifying_key(multibase));
+    }
+    if let Some(base58) = method["publicKeyBase58"].as_str() {
+        // Ed25519VerificationKey2018: the raw key in plain base58btc
+        return Some(multibase::decode(format!("z{}", base58))
+            .map_err(|e| LedgerError::Did(format!("Invalid publicKeyBase58: {:?}", e)))
+            .and_then(|(_, bytes)| ed25519_dalek::PublicKey::from_bytes(&bytes)
+                .map(VerifyingKey::Ed25519)
+                .map_err(|e| LedgerError::Did(format!("Invalid public key bytes: {:?}", e)))));
+    }
+    let jwk = &method["publicKeyJwk"];
+    let b64 = |field: &str| base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(jwk[field].as_str().unwrap_or_default())
+        .map_err(|e| LedgerError::Did(format!("Invalid JWK {}: {}", field, e)));
+    if jwk["kty"] == "OKP" && jwk["crv"] == "Ed25519" {
+        // JsonWebKey2020 with an Ed25519 OKP key
+        return Some(b64("x").and_then(|x| ed25519_dalek::PublicKey::from_bytes(&x)
+            .map(VerifyingKey::Ed25519)
+            .map_err(|e| LedgerError::Did(format!("Invalid public key bytes: {:?}", e)))));
+    }
+    if jwk["kty"] == "EC" && jwk["crv"] == "secp256k1" {
+        // EcdsaSecp256k1VerificationKey2019 / JsonWebKey2020: uncompressed point from x and y
+        return Some(b64("x").and_then(|x| b64("y").map(|y| (x, y))).and_then(|(x, y)| {
+            let mut point = vec![0x04];
+            point.extend_from_slice(&x);
+            point.extend_from_slice(&y);
+            k256::ecdsa::VerifyingKey::from_sec1_bytes(&point)
+                .map(VerifyingKey::Secp256k1)
+                .map_err(|_| LedgerError::Did("Invalid secp256k1 JWK".to_string()))
+        }));
+    }
+    None
+}
+
+/// Picks the key authorised to sign for `did`: the first supported
+/// (Ed25519 or secp256k1) method listed under `assertionMethod`, or under
+/// `verificationMethod` when the document has no `assertionMethod`
+pub fn assertion_key_from_document(document: &Value, did: &str) -> Result<VerifyingKey, LedgerError> {
+    if document["id"] != did {
+        return Err(LedgerError::Did(format!("DID Document id {} does not match {}", document["id"], did)));
+    }
+    let methods = document["verificationMethod"].as_array().cloned().unwrap_or_default();
+    let find_method = |reference: &str| -> Option<Value> {
+        methods.iter().find(|m| {
+            let id = m["id"].as_str().unwrap_or_default();
+            id == reference || (reference.starts_with('#') && id == format!("{}{}", did, reference))
+        }).cloned()
+    };
+
+    let candidates: Vec<Value> = match document["assertionMethod"].as_array() {
+        // Entries are either references to verificationMethod ids or embedded methods
+        Some(assertion) => assertion.iter()
+            .filter_map(|entry| match entry.as_str() {
+                Some(reference) => find_method(reference),
+                None => Some(entry.clone()),
+            })
+            .collect(),
+        None => methods.clone(),
+    };
+
+    for method in &candidates {
+        if let Some(public_key) = method_public_key(method) {
+            return public_key.map_err(|e| e.context(format!("{} ({})", did, method["id"])));
+        }
+    }
+    Err(LedgerError::Did(format!("DID Document for {} has no supported assertion key", did)))
+}
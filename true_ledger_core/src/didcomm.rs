@@ -0,0 +1,404 @@
+//! DIDComm Messaging
+//! Counterparties send each other proposed and co-signed transactions as
+//! DIDComm v2 messages (https://identity.foundation/didcomm-messaging/spec/)
+//! rather than emailing JSON files around. A message is signed by the
+//! sender, encrypted to the recipient's key agreement keys (authcrypt, so the
+//! recipient also learns who sent it), wrapped in a forward message for each
+//! mediator the recipient routes through, and posted to the endpoint of the
+//! recipient's DIDCommMessaging service.
+//!
+//! The body is the transaction document itself; the message type says what
+//! it is ([`MessageKind`]): an unsigned transaction proposed for the
+//! recipient to review and sign, or a signed envelope in any format.
+//!
+//! An Ed25519 identity agrees keys with the X25519 key derived from it, as
+//! did:key does (https://w3c-ccg.github.io/did-method-key/), so a did:key can
+//! be written to as `did:key:z6Mk...#z6LS...`. A did:key has no services,
+//! so its endpoint is given separately; a did:web lists the key under
+//! `keyAgreement` (see [`key_agreement_method`]) beside a DIDCommMessaging
+//! service. Collecting messages from a mediator (message pickup) is not
+//! done here: the mediator is expected to deliver to the recipient's own
+//! endpoint.
+//!
+//! Built only with the `didcomm` feature.
+
+use askar_crypto::alg::ed25519::Ed25519KeyPair;
+use askar_crypto::alg::x25519::X25519KeyPair;
+use askar_crypto::jwk::ToJwk;
+use askar_crypto::repr::{KeyPublicBytes, KeySecretBytes};
+use async_trait::async_trait;
+use didcomm::did::{
+    DIDCommMessagingService, DIDDoc, DIDResolver, Service, ServiceKind, VerificationMaterial, VerificationMethod,
+    VerificationMethodType,
+};
+use didcomm::error::{Error as DidCommError, ErrorKind};
+use didcomm::secrets::{Secret, SecretMaterial, SecretType, SecretsResolver};
+use didcomm::{Message, PackEncryptedOptions, UnpackOptions};
+use futures_executor::block_on;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde_json::{json, Value};
+
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::identity::Account;
+use crate::keys::{SigAlg, MULTICODEC_ED25519_PUB};
+
+/// Multicodec prefix of an X25519 public key ('z6LS...' in multibase)
+const MULTICODEC_X25519_PUB: [u8; 2] = [0xec, 0x01];
+
+/// Media type of an encrypted DIDComm message
+pub const ENCRYPTED_MEDIA_TYPE: &str = "application/didcomm-encrypted+json";
+
+/// What a message carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageKind {
+    /// An unsigned transaction, proposed for the recipient to sign
+    Proposal,
+    /// A signed (or co-signed) transaction envelope
+    Signed,
+}
+
+impl MessageKind {
+    /// The DIDComm message type URI
+    pub fn type_uri(self) -> &'static str {
+        match self {
+            MessageKind::Proposal => "https://github.com/fokenpi/true-ledger-core-genesis/protocols/transaction/1.0/proposal",
+            MessageKind::Signed => "https://github.com/fokenpi/true-ledger-core-genesis/protocols/transaction/1.0/signed",
+        }
+    }
+
+    fn from_type_uri(uri: &str) -> Option<Self> {
+        [MessageKind::Proposal, MessageKind::Signed].into_iter().find(|kind| kind.type_uri() == uri)
+    }
+}
+
+/// An encrypted message and where to post it
+pub struct Packed {
+    pub message: String,
+    pub endpoint: Option<String>, // The recipient's DIDCommMessaging service, if its document has one
+}
+
+/// A message that decrypted and authenticated
+pub struct Received {
+    pub id: String,
+    pub from: String, // The sender's DID, proven by authcrypt and the sender's signature
+    pub kind: MessageKind,
+    pub document: Value,
+}
+
+fn didcomm_error(e: DidCommError) -> LedgerError {
+    match e.kind() {
+        ErrorKind::DIDNotResolved | ErrorKind::DIDUrlNotFound => LedgerError::Did(format!("DIDComm: {}", e)),
+        ErrorKind::SecretNotFound | ErrorKind::NoCompatibleCrypto | ErrorKind::Unsupported => LedgerError::Key(format!("DIDComm: {}", e)),
+        ErrorKind::IoError => LedgerError::Io(format!("DIDComm: {}", e)),
+        _ => LedgerError::Serialization(format!("DIDComm: {}", e)),
+    }
+}
+
+/// `key` as a did:key-style fragment or Multikey: multibase of the multicodec-tagged bytes
+fn multikey(prefix: [u8; 2], key: &[u8]) -> String {
+    let mut bytes = prefix.to_vec();
+    bytes.extend_from_slice(key);
+    multibase::encode(multibase::Base::Base58Btc, bytes)
+}
+
+fn public_jwk(key: &impl ToJwk) -> Result<Value, LedgerError> {
+    let jwk = key.to_jwk_public(None).map_err(|e| LedgerError::Key(format!("Could not encode a JWK: {}", e)))?;
+    serde_json::from_str(&jwk).map_err(|e| LedgerError::Key(format!("Could not encode a JWK: {}", e)))
+}
+
+fn secret_jwk(key: &impl ToJwk) -> Result<Value, LedgerError> {
+    let jwk = key.to_jwk_secret(None).map_err(|e| LedgerError::Key(format!("Could not encode a JWK: {}", e)))?;
+    serde_json::from_slice(&jwk).map_err(|e| LedgerError::Key(format!("Could not encode a JWK: {}", e)))
+}
+
+/// The Ed25519 key pair of `account` and the X25519 one derived from it
+fn account_keys(account: &Account) -> Result<(Ed25519KeyPair, X25519KeyPair), LedgerError> {
+    if account.sig_alg() != SigAlg::Ed25519 {
+        return Err(LedgerError::Key(format!("DIDComm needs an Ed25519 identity; {} is {:?}", account.did, account.sig_alg())));
+    }
+    let ed25519 = Ed25519KeyPair::from_secret_bytes(&account.secret_bytes()[..])
+        .map_err(|e| LedgerError::Key(format!("Unusable Ed25519 key: {}", e)))?;
+    let x25519 = ed25519.to_x25519_keypair();
+    Ok((ed25519, x25519))
+}
+
+/// The `keyAgreement` verification method a did:web's document lists so
+/// `account` can receive messages for it
+pub fn key_agreement_method(account: &Account, did: &str) -> Result<Value, LedgerError> {
+    let (_, x25519) = account_keys(account)?;
+    let key = multikey(MULTICODEC_X25519_PUB, &x25519.with_public_bytes(|bytes| bytes.to_vec()));
+    Ok(json!({
+        "id": format!("{}#{}", did, key),
+        "type": "Multikey",
+        "controller": did,
+        "publicKeyMultibase": key,
+    }))
+}
+
+/// A `publicKeyMultibase` as a JWK, for the Ed25519 and X25519 keys DIDComm uses
+fn multikey_jwk(key: &str) -> Option<Value> {
+    let (base, bytes) = multibase::decode(key).ok()?;
+    if base != multibase::Base::Base58Btc || bytes.len() != 34 {
+        return None;
+    }
+    match [bytes[0], bytes[1]] {
+        MULTICODEC_ED25519_PUB => public_jwk(&Ed25519KeyPair::from_public_bytes(&bytes[2..]).ok()?).ok(),
+        MULTICODEC_X25519_PUB => public_jwk(&X25519KeyPair::from_public_bytes(&bytes[2..]).ok()?).ok(),
+        _ => None,
+    }
+}
+
+/// A verification method reference ("#key-1", a full DID URL, or an embedded method) as a full DID URL
+fn method_id(did: &str, reference: &Value) -> Option<String> {
+    let id = reference.as_str().or_else(|| reference["id"].as_str())?;
+    Some(if id.starts_with('#') { format!("{}{}", did, id) } else { id.to_string() })
+}
+
+/// A DID Document as the didcomm crate reads it: verification methods as
+/// JsonWebKey2020 with absolute ids, the X25519 key a did:key derives, and
+/// the DIDCommMessaging services. Methods it cannot use are left out.
+fn didcomm_document(did: &str, document: &Value) -> Result<DIDDoc, LedgerError> {
+    let mut methods: Vec<&Value> = document["verificationMethod"].as_array().map(|m| m.iter().collect()).unwrap_or_default();
+    for relation in ["authentication", "keyAgreement"] {
+        // Methods can also be embedded in the relationship itself
+        let embedded = document[relation].as_array().into_iter().flatten().filter(|reference| reference.is_object());
+        methods.extend(embedded);
+    }
+    let mut verification_method: Vec<VerificationMethod> = methods.into_iter().filter_map(|method| {
+        let jwk = match (method.get("publicKeyJwk"), method["publicKeyMultibase"].as_str()) {
+            (Some(jwk), _) => jwk.clone(),
+            (None, Some(key)) => multikey_jwk(key)?,
+            (None, None) => return None,
+        };
+        Some(VerificationMethod {
+            id: method_id(did, method)?,
+            type_: VerificationMethodType::JsonWebKey2020,
+            controller: did.to_string(),
+            verification_material: VerificationMaterial::JWK { public_key_jwk: jwk },
+        })
+    }).collect();
+    let references = |relation: &str| -> Vec<String> {
+        document[relation].as_array().into_iter().flatten().filter_map(|reference| method_id(did, reference)).collect()
+    };
+    let authentication = references("authentication");
+    let mut key_agreement = references("keyAgreement");
+
+    if let Some(key) = did.strip_prefix("did:key:") {
+        let ed25519 = multibase::decode(key).ok()
+            .filter(|(_, bytes)| bytes.len() == 34 && bytes[..2] == MULTICODEC_ED25519_PUB)
+            .and_then(|(_, bytes)| Ed25519KeyPair::from_public_bytes(&bytes[2..]).ok());
+        if let Some(ed25519) = ed25519 {
+            let x25519 = ed25519.to_x25519_keypair();
+            let id = format!("{}#{}", did, multikey(MULTICODEC_X25519_PUB, &x25519.with_public_bytes(|bytes| bytes.to_vec())));
+            verification_method.push(VerificationMethod {
+                id: id.clone(),
+                type_: VerificationMethodType::JsonWebKey2020,
+                controller: did.to_string(),
+                verification_material: VerificationMaterial::JWK { public_key_jwk: public_jwk(&x25519)? },
+            });
+            key_agreement.push(id);
+        }
+    }
+
+    let service = document["service"].as_array().into_iter().flatten().filter(|service| service["type"] == "DIDCommMessaging").filter_map(|service| {
+        // The endpoint is a URI, a {uri, accept, routingKeys} object, or a list of them
+        let endpoint = match &service["serviceEndpoint"] {
+            Value::Array(endpoints) => endpoints.first()?.clone(),
+            endpoint => endpoint.clone(),
+        };
+        let value = match endpoint {
+            Value::String(uri) => DIDCommMessagingService { uri, accept: None, routing_keys: Vec::new() },
+            endpoint => serde_json::from_value(endpoint).ok()?,
+        };
+        Some(Service { id: method_id(did, service)?, service_endpoint: ServiceKind::DIDCommMessaging { value } })
+    }).collect();
+
+    Ok(DIDDoc { id: did.to_string(), key_agreement, authentication, verification_method, service })
+}
+
+/// The ledger's DID resolver, as the didcomm crate asks for documents
+struct Documents<'a> {
+    resolver: &'a dyn DidResolver,
+}
+
+impl Documents<'_> {
+    fn resolve_now(&self, did: &str) -> Result<DIDDoc, LedgerError> {
+        didcomm_document(did, &self.resolver.resolve_document(did)?)
+    }
+}
+
+#[async_trait(?Send)]
+impl DIDResolver for Documents<'_> {
+    async fn resolve(&self, did: &str) -> didcomm::error::Result<Option<DIDDoc>> {
+        self.resolve_now(did).map(Some).map_err(|e| DidCommError::msg(ErrorKind::DIDNotResolved, e.to_string()))
+    }
+}
+
+/// The secret keys of one account, offered for any verification method (in
+/// whichever document) whose public key is the account's Ed25519 key or the
+/// X25519 key derived from it
+struct AccountSecrets<'a> {
+    documents: &'a Documents<'a>,
+    keys: [(Value, Value); 2], // (public JWK, private JWK)
+}
+
+impl<'a> AccountSecrets<'a> {
+    fn new(account: &Account, documents: &'a Documents<'a>) -> Result<Self, LedgerError> {
+        let (ed25519, x25519) = account_keys(account)?;
+        Ok(AccountSecrets {
+            documents,
+            keys: [(public_jwk(&ed25519)?, secret_jwk(&ed25519)?), (public_jwk(&x25519)?, secret_jwk(&x25519)?)],
+        })
+    }
+
+    fn secret(&self, kid: &str) -> Option<Secret> {
+        let did = kid.split('#').next()?;
+        let document = self.documents.resolve_now(did).ok()?;
+        let method = document.verification_method.iter().find(|method| method.id == kid)?;
+        let VerificationMaterial::JWK { public_key_jwk } = &method.verification_material else { return None };
+        let (_, private) = self.keys.iter()
+            .find(|(public, _)| public["crv"] == public_key_jwk["crv"] && public["x"] == public_key_jwk["x"])?;
+        Some(Secret {
+            id: kid.to_string(),
+            type_: SecretType::JsonWebKey2020,
+            secret_material: SecretMaterial::JWK { private_key_jwk: private.clone() },
+        })
+    }
+}
+
+#[async_trait(?Send)]
+impl SecretsResolver for AccountSecrets<'_> {
+    async fn get_secret(&self, secret_id: &str) -> didcomm::error::Result<Option<Secret>> {
+        Ok(self.secret(secret_id))
+    }
+
+    async fn find_secrets<'a>(&self, secret_ids: &'a [&'a str]) -> didcomm::error::Result<Vec<&'a str>> {
+        Ok(secret_ids.iter().copied().filter(|id| self.secret(id).is_some()).collect())
+    }
+}
+
+fn message_id() -> String {
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    hex::encode(id)
+}
+
+/// Signs `document` as `from` (the account's did:key, or a DID whose
+/// document lists its keys), encrypts it to `to` and wraps it for the
+/// mediators `to` routes through
+pub fn pack(kind: MessageKind, document: &Value, account: &Account, from: &str, to: &str, resolver: &dyn DidResolver) -> Result<Packed, LedgerError> {
+    let documents = Documents { resolver };
+    let secrets = AccountSecrets::new(account, &documents)?;
+    let sender = documents.resolve_now(from)?;
+    if !sender.key_agreement.iter().any(|kid| secrets.secret(kid).is_some()) {
+        return Err(LedgerError::Key(format!("{} lists no key agreement key of this identity ({})", from, account.did)));
+    }
+    let message = Message::build(message_id(), kind.type_uri().to_string(), document.clone())
+        .from(from.to_string())
+        .to(to.to_string())
+        .finalize();
+    let (message, metadata) = block_on(message.pack_encrypted(to, Some(from), Some(from), &documents, &secrets, &PackEncryptedOptions::default()))
+        .map_err(didcomm_error)?;
+    let endpoint = match metadata.messaging_service {
+        Some(service) => Some(service.service_endpoint), // Its first mediator's
+        None => documents.resolve_now(to)?.service.into_iter().find_map(|service| match service.service_endpoint {
+            ServiceKind::DIDCommMessaging { value } if !value.uri.starts_with("did:") => Some(value.uri),
+            _ => None,
+        }),
+    };
+    Ok(Packed { message, endpoint })
+}
+
+/// Decrypts a message sent to `account` and checks who sent it: only
+/// authenticated, signed messages of the two transaction types are accepted
+pub fn unpack(message: &str, account: &Account, resolver: &dyn DidResolver) -> Result<Received, LedgerError> {
+    let documents = Documents { resolver };
+    let secrets = AccountSecrets::new(account, &documents)?;
+    let (message, metadata) = block_on(Message::unpack(message, &documents, &secrets, &UnpackOptions::default()))
+        .map_err(didcomm_error)?;
+    if !metadata.encrypted || !metadata.authenticated || !metadata.non_repudiation {
+        return Err(LedgerError::Signature("DIDComm: the message is not encrypted, authenticated and signed by its sender".to_string()));
+    }
+    let from = message.from.clone().ok_or_else(|| LedgerError::Signature("DIDComm: the message does not name its sender".to_string()))?;
+    let signer = metadata.sign_from.as_deref().and_then(|kid| kid.split('#').next());
+    if signer != Some(from.as_str()) {
+        return Err(LedgerError::Signature(format!("DIDComm: the message from {} is signed by someone else", from)));
+    }
+    let kind = MessageKind::from_type_uri(&message.type_)
+        .ok_or_else(|| LedgerError::Serialization(format!("DIDComm: unexpected message type {}", message.type_)))?;
+    Ok(Received { id: message.id, from, kind, document: message.body })
+}
+
+/// Delivers a packed message to an endpoint over HTTP(S)
+pub fn post(message: &str, endpoint: &str) -> Result<(), LedgerError> {
+    let agent = ureq::AgentBuilder::new().timeout(std::time::Duration::from_secs(30)).build();
+    agent.post(endpoint).set("Content-Type", ENCRYPTED_MEDIA_TYPE).send_string(message)
+        .map_err(|e| LedgerError::Io(format!("{}: {}", endpoint, e)))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::DidKeyResolver;
+    use crate::test_util::signed;
+
+    #[test]
+    fn a_signed_transaction_reaches_its_recipient() {
+        let (alice, bob) = (Account::generate(), Account::generate());
+        let tx = serde_json::to_value(signed(&alice, None, 0, "Invoice 42")).unwrap();
+        let packed = pack(MessageKind::Signed, &tx, &alice, &alice.did, &bob.did, &DidKeyResolver).unwrap();
+        assert!(packed.endpoint.is_none()); // did:key has no services
+        assert!(!packed.message.contains("Invoice 42"));
+
+        let received = unpack(&packed.message, &bob, &DidKeyResolver).unwrap();
+        assert_eq!(received.from, alice.did);
+        assert_eq!(received.kind, MessageKind::Signed);
+        assert_eq!(received.document, tx);
+    }
+
+    #[test]
+    fn only_the_recipient_can_read_it() {
+        let (alice, bob) = (Account::generate(), Account::generate());
+        let packed = pack(MessageKind::Proposal, &json!({ "memo": "Lunch" }), &alice, &alice.did, &bob.did, &DidKeyResolver).unwrap();
+        assert!(unpack(&packed.message, &Account::generate(), &DidKeyResolver).is_err());
+        assert!(unpack(&packed.message, &alice, &DidKeyResolver).is_err());
+
+        let mut tampered: Value = serde_json::from_str(&packed.message).unwrap();
+        let ciphertext = tampered["ciphertext"].as_str().unwrap().to_string();
+        let flipped = if ciphertext.starts_with('A') { "B" } else { "A" };
+        tampered["ciphertext"] = json!(format!("{}{}", flipped, &ciphertext[1..]));
+        assert!(unpack(&tampered.to_string(), &bob, &DidKeyResolver).is_err());
+    }
+
+    #[test]
+    fn a_sender_must_hold_the_keys_of_its_did() {
+        let (alice, bob, mallory) = (Account::generate(), Account::generate(), Account::generate());
+        assert!(pack(MessageKind::Proposal, &json!({}), &mallory, &alice.did, &bob.did, &DidKeyResolver).is_err());
+        assert!(pack(MessageKind::Proposal, &json!({}), &Account::generate_for(SigAlg::Secp256k1), &alice.did, &bob.did, &DidKeyResolver).is_err());
+    }
+
+    #[test]
+    fn messages_go_to_the_did_web_service_endpoint() {
+        struct WebResolver(Value);
+        impl DidResolver for WebResolver {
+            fn resolve_public_key(&self, did: &str) -> Result<crate::keys::VerifyingKey, LedgerError> {
+                crate::did::did_to_verifying_key(did)
+            }
+            fn resolve_document(&self, did: &str) -> Result<Value, LedgerError> {
+                if did == "did:web:bob.example" { Ok(self.0.clone()) } else { crate::did::did_key_document(did) }
+            }
+        }
+        let (alice, bob) = (Account::generate(), Account::generate());
+        let resolver = WebResolver(json!({
+            "id": "did:web:bob.example",
+            "keyAgreement": [key_agreement_method(&bob, "did:web:bob.example").unwrap()],
+            "service": [{ "id": "#didcomm", "type": "DIDCommMessaging", "serviceEndpoint": "https://bob.example/didcomm" }],
+        }));
+        let packed = pack(MessageKind::Proposal, &json!({ "memo": "Rent" }), &alice, &alice.did, "did:web:bob.example", &resolver).unwrap();
+        assert_eq!(packed.endpoint.as_deref(), Some("https://bob.example/didcomm"));
+        assert_eq!(unpack(&packed.message, &bob, &resolver).unwrap().document["memo"], "Rent");
+    }
+}
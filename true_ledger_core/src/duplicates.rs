@@ -0,0 +1,258 @@
+//! Duplicate Payment Detection
+//! Paying the same bill twice is the most common accounts-payable loss, and
+//! each payment on its own verifies. This pass looks across the verified
+//! journal for payments that are likely the same one made again, and
+//! returns a worklist for a reviewer, largest amounts first. Two heuristics:
+//! - the same invoice reference, in the memo's `invoice=` field, paid twice
+//! - the same amount paid to the same counterparty within a window of days
+//!
+//! Memos may carry structured fields as `key=value` words, e.g.
+//! "Office chairs invoice=INV-1042 counterparty=acme". The counterparty is
+//! the `counterparty=` field (or `payee=` or `vendor=`); without one it is
+//! the accounts the payment debits, such as a supplier's payable account.
+//! A transaction that was reversed, and its reversal, are not payments.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+use crate::amount::parse_cents;
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::verify::balance_totals;
+
+/// Memo fields naming who was paid, in order of preference
+const COUNTERPARTY_FIELDS: [&str; 3] = ["counterparty", "payee", "vendor"];
+
+/// The `key=value` words of a memo, keys lowercased. A key given twice keeps
+/// its first value.
+pub fn memo_fields(memo: &str) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    for word in memo.split_whitespace() {
+        if let Some((key, value)) = word.split_once('=') {
+            let value = value.trim_end_matches([',', ';', '.']);
+            if !key.is_empty() && !value.is_empty() {
+                fields.entry(key.to_lowercase()).or_insert_with(|| value.to_string());
+            }
+        }
+    }
+    fields
+}
+
+/// Why a group of payments looks duplicated
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DuplicateReason {
+    SameInvoice { invoice: String },
+    SameCounterpartyAmount { counterparty: String, amount_cents: i64, days_apart: i64 },
+}
+
+/// One payment on the worklist
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Payment {
+    pub tx_hash: String,
+    pub date: NaiveDate,
+    pub amount_cents: i64, // The transaction's debits
+    pub counterparty: String,
+    pub memo: String,
+}
+
+/// Payments that are likely one payment made more than once
+#[derive(Serialize, Debug, Clone)]
+pub struct DuplicateGroup {
+    pub reason: DuplicateReason,
+    pub payments: Vec<Payment>, // Oldest first
+    pub at_risk_cents: i64,     // Paid beyond the first payment
+}
+
+/// What counts as a payment, and how close is close
+#[derive(Debug, Clone)]
+pub struct DuplicateCheck {
+    pub window_days: i64,              // Same counterparty and amount this many days apart or fewer
+    pub payment_accounts: Vec<String>, // Only transactions crediting one of these (e.g. the bank); empty: every transaction
+}
+
+impl Default for DuplicateCheck {
+    fn default() -> Self {
+        DuplicateCheck { window_days: 14, payment_accounts: Vec::new() }
+    }
+}
+
+fn nonzero(amount: &str) -> bool {
+    parse_cents(amount).is_ok_and(|cents| cents != 0)
+}
+
+fn credits_any(tx: &Transaction, accounts: &[String]) -> bool {
+    tx.entries.iter().any(|entry| accounts.contains(&entry.account_id) && nonzero(&entry.credit))
+}
+
+/// The counterparty a memo's fields name, if any
+pub fn named_counterparty(fields: &BTreeMap<String, String>) -> Option<&str> {
+    COUNTERPARTY_FIELDS.iter().find_map(|key| fields.get(*key)).map(String::as_str)
+}
+
+/// Who a payment went to: a memo field, or else the accounts it debits
+fn counterparty(tx: &Transaction, fields: &BTreeMap<String, String>) -> String {
+    if let Some(value) = named_counterparty(fields) {
+        return value.to_lowercase();
+    }
+    let mut debited: Vec<&str> = tx.entries.iter()
+        .filter(|entry| nonzero(&entry.debit))
+        .map(|entry| entry.account_id.as_str())
+        .collect();
+    debited.sort_unstable();
+    debited.dedup();
+    debited.join("+")
+}
+
+impl DuplicateCheck {
+    /// The worklist over verified transactions, largest amount at risk first
+    pub fn run<'a, I: IntoIterator<Item = &'a Transaction>>(&self, txs: I) -> Result<Vec<DuplicateGroup>, LedgerError> {
+        let txs: Vec<&Transaction> = txs.into_iter().collect();
+        let reversed: HashSet<&str> = txs.iter().filter_map(|tx| tx.reverses.as_deref()).collect();
+
+        let mut by_invoice: BTreeMap<String, Vec<Payment>> = BTreeMap::new();
+        let mut by_counterparty_amount: BTreeMap<(String, i64), Vec<Payment>> = BTreeMap::new();
+        for tx in txs {
+            if tx.reverses.is_some() || (!self.payment_accounts.is_empty() && !credits_any(tx, &self.payment_accounts)) {
+                continue;
+            }
+            let tx_hash = tx.hash_hex();
+            if reversed.contains(tx_hash.as_str()) {
+                continue;
+            }
+            let fields = memo_fields(&tx.memo);
+            let payment = Payment {
+                tx_hash,
+                date: tx.timestamp.local_date(),
+                amount_cents: balance_totals(tx)?.0,
+                counterparty: counterparty(tx, &fields),
+                memo: tx.memo.clone(),
+            };
+            if let Some(invoice) = fields.get("invoice") {
+                by_invoice.entry(invoice.to_uppercase()).or_default().push(payment.clone());
+            }
+            by_counterparty_amount.entry((payment.counterparty.clone(), payment.amount_cents)).or_default().push(payment);
+        }
+
+        let mut groups = Vec::new();
+        let mut invoiced: HashSet<Vec<String>> = HashSet::new(); // Groups already listed under their invoice
+        for (invoice, mut payments) in by_invoice {
+            if payments.len() > 1 {
+                payments.sort_by_key(|payment| payment.date);
+                invoiced.insert(payments.iter().map(|payment| payment.tx_hash.clone()).collect());
+                groups.push(DuplicateGroup::new(DuplicateReason::SameInvoice { invoice }, payments));
+            }
+        }
+        for ((counterparty, amount_cents), mut payments) in by_counterparty_amount {
+            payments.sort_by_key(|payment| payment.date);
+            for cluster in self.clusters(payments) {
+                if invoiced.contains(&cluster.iter().map(|payment| payment.tx_hash.clone()).collect::<Vec<_>>()) {
+                    continue;
+                }
+                let days_apart = (cluster[cluster.len() - 1].date - cluster[0].date).num_days();
+                let reason = DuplicateReason::SameCounterpartyAmount { counterparty: counterparty.clone(), amount_cents, days_apart };
+                groups.push(DuplicateGroup::new(reason, cluster));
+            }
+        }
+        groups.sort_by_key(|group| std::cmp::Reverse(group.at_risk_cents));
+        Ok(groups)
+    }
+
+    /// Splits payments, oldest first, into runs where each follows the last
+    /// within the window; runs of one are dropped
+    fn clusters(&self, payments: Vec<Payment>) -> Vec<Vec<Payment>> {
+        let mut clusters: Vec<Vec<Payment>> = Vec::new();
+        for payment in payments {
+            match clusters.last_mut() {
+                Some(cluster) if (payment.date - cluster[cluster.len() - 1].date).num_days() <= self.window_days => cluster.push(payment),
+                _ => clusters.push(vec![payment]),
+            }
+        }
+        clusters.retain(|cluster| cluster.len() > 1);
+        clusters
+    }
+}
+
+impl DuplicateGroup {
+    fn new(reason: DuplicateReason, payments: Vec<Payment>) -> Self {
+        let at_risk_cents = payments.iter().skip(1).map(|payment| payment.amount_cents).sum();
+        DuplicateGroup { reason, payments, at_risk_cents }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::amount::format_cents;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+
+    /// A payment from the bank (10100) to `payable` on `day` of March 2025
+    fn payment(day: u32, payable: &str, cents: i64, memo: &str) -> Transaction {
+        let amount = format_cents(cents);
+        let date = NaiveDate::from_ymd_opt(2025, 3, day).unwrap();
+        TransactionBuilder::new()
+            .timestamp(date.and_hms_opt(10, 0, 0).unwrap().and_utc().timestamp() as u64)
+            .entry(payable, amount.as_str(), "0.00")
+            .entry("10100", "0.00", amount.as_str())
+            .memo(memo)
+            .genesis()
+            .sign(&Account::generate())
+            .unwrap()
+            .payload
+    }
+
+    #[test]
+    fn memo_fields_are_key_value_words() {
+        let fields = memo_fields("Chairs Invoice=INV-7, payee=Acme vendor=other invoice=INV-8 = a= =b");
+        assert_eq!(fields.get("invoice").map(String::as_str), Some("INV-7"));
+        assert_eq!(fields.get("payee").map(String::as_str), Some("Acme"));
+        assert_eq!(fields.len(), 3);
+    }
+
+    #[test]
+    fn the_same_invoice_paid_twice_is_flagged() {
+        let txs = vec![
+            payment(1, "20100", 50_000, "Chairs invoice=INV-7 counterparty=acme"),
+            payment(28, "20100", 49_000, "Chairs (resent) invoice=inv-7"),
+            payment(2, "20100", 10_000, "Desk invoice=INV-8"),
+        ];
+        let groups = DuplicateCheck::default().run(&txs).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].reason, DuplicateReason::SameInvoice { invoice: "INV-7".to_string() });
+        assert_eq!(groups[0].at_risk_cents, 49_000);
+        assert_eq!(groups[0].payments[0].tx_hash, txs[0].hash_hex());
+    }
+
+    #[test]
+    fn the_same_amount_to_the_same_counterparty_within_the_window_is_flagged() {
+        let txs = vec![
+            payment(1, "20100", 75_000, "Rent payee=Landlord"),
+            payment(5, "20100", 75_000, "Rent again payee=landlord"),
+            payment(3, "20200", 75_000, "Same amount, another supplier"),
+            payment(25, "20100", 75_000, "Next month's rent payee=landlord"),
+            payment(6, "20100", 75_001, "Not quite the same payee=landlord"),
+        ];
+        let groups = DuplicateCheck { window_days: 14, payment_accounts: vec!["10100".to_string()] }.run(&txs).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].reason, DuplicateReason::SameCounterpartyAmount { counterparty: "landlord".to_string(), amount_cents: 75_000, days_apart: 4 });
+        assert_eq!(groups[0].payments.len(), 2);
+
+        let wide = DuplicateCheck { window_days: 30, payment_accounts: Vec::new() }.run(&txs).unwrap();
+        assert_eq!(wide[0].payments.len(), 3);
+        assert!(DuplicateCheck { window_days: 14, payment_accounts: vec!["10200".to_string()] }.run(&txs).unwrap().is_empty());
+    }
+
+    #[test]
+    fn a_reversed_payment_is_not_a_duplicate() {
+        let first = payment(1, "20100", 75_000, "Rent invoice=R-3");
+        let second = payment(2, "20100", 75_000, "Rent invoice=R-3");
+        let mut reversal = payment(3, "10100", 75_000, "Reverses the second rent payment");
+        reversal.reverses = Some(second.hash_hex());
+        assert!(DuplicateCheck::default().run(&[first.clone(), second.clone(), reversal]).unwrap().is_empty());
+
+        let groups = DuplicateCheck::default().run(&[first, second]).unwrap();
+        assert_eq!(groups.len(), 1, "an invoice paid twice is listed once, not again by amount");
+    }
+}
@@ -0,0 +1,190 @@
+//! Transaction Envelopes
+//! The payload is always a [`Transaction`]; what differs is how the
+//! signatures over it travel. Verifiers accept every envelope and run the
+//! same checks on the payload inside.
+
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::cose::CoseTransaction;
+use crate::data_integrity::TransactionCredential;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::jws::JwsTransaction;
+use crate::lifecycle::TxState;
+use crate::model::{SignedTransaction, Transaction};
+use crate::multisig::{check_policy, verify_quorum};
+#[cfg(feature = "openpgp")]
+use crate::openpgp::OpenPgpTransaction;
+#[cfg(feature = "sigstore")]
+use crate::sigstore::SigstoreTransaction;
+use crate::verify::verify_signature_with;
+use crate::versioning::check_version;
+
+/// A signed transaction in any supported envelope
+#[derive(Debug, Clone)]
+pub enum Envelope {
+    Signed(SignedTransaction),            // Our own: a signature string plus cosignatures
+    DataIntegrity(TransactionCredential), // A W3C credential with an eddsa-jcs-2022 proof
+    Jws(JwsTransaction),                  // JWS compact serialization
+    Cose(CoseTransaction),                // Binary COSE_Sign1 over a CBOR payload
+    #[cfg(feature = "openpgp")]
+    OpenPgp(OpenPgpTransaction),          // An armored OpenPGP signature beside the payload
+    #[cfg(feature = "sigstore")]
+    Sigstore(SigstoreTransaction),        // A keyless signature with its Fulcio chain and Rekor entry
+}
+
+impl Envelope {
+    /// Parses a transaction file in any envelope: COSE_Sign1, JSON
+    /// (credentials are told apart by their `proof`, OpenPGP and Sigstore
+    /// transactions by their `openpgp` and `sigstore`) or a compact JWS
+    pub fn parse(data: &[u8]) -> Result<Self, LedgerError> {
+        let envelope = if CoseTransaction::detect(data) {
+            Envelope::Cose(CoseTransaction::parse(data)?)
+        } else {
+            let text = std::str::from_utf8(data)
+                .map_err(|_| LedgerError::Serialization("Transaction file is neither UTF-8 text nor COSE_Sign1".to_string()))?;
+            if text.trim_start().starts_with('{') { Self::from_json(text)? } else { Envelope::Jws(JwsTransaction::parse(text)?) }
+        };
+        // Whatever the envelope, the payload must be a version this release reads
+        check_version(envelope.payload())?;
+        Ok(envelope)
+    }
+
+    /// Like [`Envelope::parse`], but a field the payload does not define is
+    /// an error rather than silently skipped. In our own envelope the
+    /// signature covers only the fields we know, so an unknown one could
+    /// claim anything (`"approved_by": "CFO"`) without invalidating it; in
+    /// the others it is signed but no check ever looks at it.
+    pub fn parse_strict(data: &[u8]) -> Result<Self, LedgerError> {
+        let envelope = Self::parse(data)?;
+        match &envelope {
+            Envelope::Signed(_) => {
+                let document = serde_json::from_slice(data)
+                    .map_err(|e| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e)))?;
+                reject_unknown_fields::<SignedTransaction>(&document, "", "not covered by the signature")?
+            }
+            Envelope::DataIntegrity(credential) => reject_unknown_fields::<Transaction>(
+                &credential.document["credentialSubject"], "credentialSubject.", "signed, but not read by any check")?,
+            Envelope::Jws(jws) => reject_unknown_fields::<Transaction>(&jws.payload_json()?, "payload.", "signed, but not read by any check")?,
+            Envelope::Cose(cose) => reject_unknown_fields::<Transaction>(&cose.payload_json()?, "payload.", "signed, but not read by any check")?,
+            #[cfg(feature = "openpgp")]
+            Envelope::OpenPgp(_) => {
+                let document: Value = serde_json::from_slice(data)
+                    .map_err(|e| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e)))?;
+                reject_unknown_fields::<OpenPgpTransaction>(&document, "", "not covered by the signature")?
+            }
+            #[cfg(feature = "sigstore")]
+            Envelope::Sigstore(_) => {
+                let document: Value = serde_json::from_slice(data)
+                    .map_err(|e| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e)))?;
+                reject_unknown_fields::<SigstoreTransaction>(&document, "", "not covered by the signature")?
+            }
+        }
+        Ok(envelope)
+    }
+
+    fn from_json(json_data: &str) -> Result<Self, LedgerError> {
+        #[derive(Deserialize)]
+        struct Probe {
+            proof: Option<IgnoredAny>,
+            openpgp: Option<IgnoredAny>,
+            sigstore: Option<IgnoredAny>,
+            state: Option<TxState>, // Only drafts have one
+        }
+        let parse_error = |e: serde_json::Error| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e));
+        let probe: Probe = serde_json::from_str(json_data).map_err(parse_error)?;
+        if let Some(state) = probe.state {
+            return Err(LedgerError::Approval(format!("This is a {} transaction, not a posted one; it is not signed until it is posted", state)));
+        }
+        match (probe.proof, probe.openpgp, probe.sigstore) {
+            (Some(_), _, _) => Ok(Envelope::DataIntegrity(TransactionCredential::from_value(
+                serde_json::from_str(json_data).map_err(parse_error)?)?)),
+            #[cfg(feature = "openpgp")]
+            (None, Some(_), _) => Ok(Envelope::OpenPgp(OpenPgpTransaction::from_value(
+                serde_json::from_str(json_data).map_err(parse_error)?)?)),
+            #[cfg(not(feature = "openpgp"))]
+            (None, Some(_), _) => Err(LedgerError::Serialization(
+                "This transaction is in the OpenPGP envelope, which this build does not read (see the openpgp feature)".to_string())),
+            #[cfg(feature = "sigstore")]
+            (None, None, Some(_)) => Ok(Envelope::Sigstore(SigstoreTransaction::from_value(
+                serde_json::from_str(json_data).map_err(parse_error)?)?)),
+            #[cfg(not(feature = "sigstore"))]
+            (None, None, Some(_)) => Err(LedgerError::Serialization(
+                "This transaction is in the Sigstore envelope, which this build does not read (see the sigstore feature)".to_string())),
+            (None, None, None) => Ok(Envelope::Signed(SignedTransaction::from_json(json_data)?)),
+        }
+    }
+
+    pub fn payload(&self) -> &Transaction {
+        match self {
+            Envelope::Signed(signed_tx) => &signed_tx.payload,
+            Envelope::DataIntegrity(credential) => &credential.payload,
+            Envelope::Jws(jws) => &jws.payload,
+            Envelope::Cose(cose) => &cose.payload,
+            #[cfg(feature = "openpgp")]
+            Envelope::OpenPgp(openpgp) => &openpgp.payload,
+            #[cfg(feature = "sigstore")]
+            Envelope::Sigstore(sigstore) => &sigstore.payload,
+        }
+    }
+
+    /// Our own envelope, the only one that carries cosignatures and gets stored
+    pub fn as_signed(&self) -> Option<&SignedTransaction> {
+        match self {
+            Envelope::Signed(signed_tx) => Some(signed_tx),
+            _ => None,
+        }
+    }
+
+    /// Checks the author's signature (or proof) over the payload
+    pub fn verify_signature(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        match self {
+            Envelope::Signed(signed_tx) => verify_signature_with(signed_tx, resolver),
+            Envelope::DataIntegrity(credential) => credential.verify_proof(resolver),
+            Envelope::Jws(jws) => jws.verify_signature(resolver),
+            Envelope::Cose(cose) => cose.verify_signature(resolver),
+            #[cfg(feature = "openpgp")]
+            Envelope::OpenPgp(openpgp) => openpgp.verify_signature(resolver),
+            #[cfg(feature = "sigstore")]
+            Envelope::Sigstore(sigstore) => sigstore.verify_signature(), // Against the configured trust root, not a DID
+        }
+    }
+
+    /// Checks the payload's signing policy against every valid signature.
+    /// Only our own envelope carries cosignatures; the others count the author alone.
+    pub fn verify_approvals(&self, resolver: &dyn DidResolver) -> Result<usize, LedgerError> {
+        match self {
+            Envelope::Signed(signed_tx) => verify_quorum(signed_tx, resolver),
+            _ => check_policy(self.payload(), std::slice::from_ref(&self.payload().author_did)),
+        }
+    }
+
+    /// Every DID that signed: the author first, then any cosigners
+    pub fn signers(&self) -> Vec<&str> {
+        let mut signers = vec![self.payload().author_did.as_str()];
+        if let Envelope::Signed(signed_tx) = self {
+            signers.extend(signed_tx.cosignatures.iter().map(|c| c.signer_did.as_str()));
+        }
+        signers
+    }
+
+    /// The content identifier of our own envelope; None for the others
+    pub fn cid(&self) -> Option<String> {
+        self.as_signed().map(SignedTransaction::cid)
+    }
+}
+
+/// Fails if `value` has fields `T` would skip, naming each by its path
+fn reject_unknown_fields<T: DeserializeOwned>(value: &Value, prefix: &str, why: &str) -> Result<(), LedgerError> {
+    let mut unknown = Vec::new();
+    serde_ignored::deserialize(value, |path| unknown.push(format!("`{}{}`", prefix, path)))
+        .map(|_: T| ())
+        .map_err(|e| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e)))?;
+    match unknown.len() {
+        0 => Ok(()),
+        1 => Err(LedgerError::Serialization(format!("Unknown field {}: {}", unknown[0], why))),
+        _ => Err(LedgerError::Serialization(format!("Unknown fields {}: {}", unknown.join(", "), why))),
+    }
+}
@@ -0,0 +1,121 @@
+//! Errors
+//! Every fallible function in the crate returns a [`LedgerError`]. The
+//! variant says what kind of failure it was, so callers can branch on it;
+//! the message says what exactly went wrong and is what gets shown to users.
+
+use thiserror::Error;
+
+/// A failure, by cause. Each variant carries the human-readable message.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LedgerError {
+    /// Data that could not be encoded or decoded (JSON, TOML, CBOR, ...)
+    #[error("{0}")]
+    Serialization(String),
+    /// Reading or writing a file, directory or socket failed
+    #[error("{0}")]
+    Io(String),
+    /// A malformed DID, or one whose key could not be resolved
+    #[error("{0}")]
+    Did(String),
+    /// An invalid key, keystore, recovery phrase or signing backend
+    #[error("{0}")]
+    Key(String),
+    /// A signature or digest that does not match the payload
+    #[error("{0}")]
+    Signature(String),
+    /// Missing or invalid approvals under a signing policy
+    #[error("{0}")]
+    Approval(String),
+    /// Debits that do not equal credits
+    #[error("{0}")]
+    Imbalance(String),
+    /// An amount that is not a valid decimal
+    #[error("{0}")]
+    Amount(String),
+    /// A transaction that does not link to its predecessor
+    #[error("{0}")]
+    Chain(String),
+    /// A timestamp in the future or before the open accounting period
+    #[error("{0}")]
+    Timestamp(String),
+    /// A ledger store (SQLite database, journal) that rejected an operation
+    #[error("{0}")]
+    Storage(String),
+    /// A chart of accounts, rule or other setting that is invalid or violated
+    #[error("{0}")]
+    Config(String),
+}
+
+impl LedgerError {
+    // Stable numeric codes, one per variant. Never reused or renumbered, so
+    // callers in other languages can switch on them.
+    pub const SERIALIZATION: u16 = 1;
+    pub const IO: u16 = 2;
+    pub const DID: u16 = 10;
+    pub const KEY: u16 = 11;
+    pub const SIGNATURE: u16 = 20;
+    pub const APPROVAL: u16 = 21;
+    pub const IMBALANCE: u16 = 30;
+    pub const AMOUNT: u16 = 31;
+    pub const CHAIN: u16 = 40;
+    pub const TIMESTAMP: u16 = 41;
+    pub const STORAGE: u16 = 50;
+    pub const CONFIG: u16 = 60;
+
+    /// The variant's stable numeric code
+    pub fn code(&self) -> u16 {
+        match self {
+            LedgerError::Serialization(_) => Self::SERIALIZATION,
+            LedgerError::Io(_) => Self::IO,
+            LedgerError::Did(_) => Self::DID,
+            LedgerError::Key(_) => Self::KEY,
+            LedgerError::Signature(_) => Self::SIGNATURE,
+            LedgerError::Approval(_) => Self::APPROVAL,
+            LedgerError::Imbalance(_) => Self::IMBALANCE,
+            LedgerError::Amount(_) => Self::AMOUNT,
+            LedgerError::Chain(_) => Self::CHAIN,
+            LedgerError::Timestamp(_) => Self::TIMESTAMP,
+            LedgerError::Storage(_) => Self::STORAGE,
+            LedgerError::Config(_) => Self::CONFIG,
+        }
+    }
+
+    /// The message without the variant
+    pub fn message(&self) -> &str {
+        match self {
+            LedgerError::Serialization(m) | LedgerError::Io(m) | LedgerError::Did(m) | LedgerError::Key(m)
+            | LedgerError::Signature(m) | LedgerError::Approval(m) | LedgerError::Imbalance(m)
+            | LedgerError::Amount(m) | LedgerError::Chain(m) | LedgerError::Timestamp(m) | LedgerError::Storage(m)
+            | LedgerError::Config(m) => m,
+        }
+    }
+
+    /// Prefixes the message ("Transaction #3: ...") and keeps the variant
+    pub fn context(self, prefix: impl std::fmt::Display) -> Self {
+        self.map_message(|m| format!("{}: {}", prefix, m))
+    }
+
+    fn map_message(self, f: impl FnOnce(String) -> String) -> Self {
+        match self {
+            LedgerError::Serialization(m) => LedgerError::Serialization(f(m)),
+            LedgerError::Io(m) => LedgerError::Io(f(m)),
+            LedgerError::Did(m) => LedgerError::Did(f(m)),
+            LedgerError::Key(m) => LedgerError::Key(f(m)),
+            LedgerError::Signature(m) => LedgerError::Signature(f(m)),
+            LedgerError::Approval(m) => LedgerError::Approval(f(m)),
+            LedgerError::Imbalance(m) => LedgerError::Imbalance(f(m)),
+            LedgerError::Amount(m) => LedgerError::Amount(f(m)),
+            LedgerError::Chain(m) => LedgerError::Chain(f(m)),
+            LedgerError::Timestamp(m) => LedgerError::Timestamp(f(m)),
+            LedgerError::Storage(m) => LedgerError::Storage(f(m)),
+            LedgerError::Config(m) => LedgerError::Config(f(m)),
+        }
+    }
+}
+
+/// Lets code that still reports plain strings (e.g. the CLI) use `?` on core calls
+impl From<LedgerError> for String {
+    fn from(error: LedgerError) -> Self {
+        error.to_string()
+    }
+}
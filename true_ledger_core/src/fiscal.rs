@@ -0,0 +1,163 @@
+//! Fiscal Calendar
+//! Books that do not close on 31 December configure when their year starts:
+//!
+//! ```toml
+//! year_start = "04-01"   # MM-DD; FY2025 runs 2024-04-01 to 2025-03-31
+//! ```
+//!
+//! A fiscal year is named after the calendar year it ends in and has twelve
+//! monthly periods, P01 to P12, counted from its start. Period 13 holds the
+//! year-end adjusting entries: transactions marked `"adjusting": true` and
+//! dated on the year's last day. It shares that day with P12 but comes after
+//! it, so reports "through FY2025-P12" show the books before adjustments.
+//! Adjusting entries dated any other day belong to that day's period.
+//!
+//! Wherever a report or a period lock takes a day, it also takes a period:
+//! `2025-03-31`, `FY2025` (the whole year, adjustments included),
+//! `FY2025-P03` or `FY2025-P13`.
+
+use chrono::{Datelike, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::error::LedgerError;
+use crate::model::Transaction;
+
+/// Where a report or lock ends: everything dated before `date`, and on
+/// `date` itself unless it is an adjusting entry that was left out
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cutoff {
+    pub date: NaiveDate,
+    pub adjustments: bool, // Whether adjusting entries dated `date` (period 13) are included
+}
+
+impl Cutoff {
+    /// Through the end of a day, adjustments included
+    pub fn day(date: NaiveDate) -> Self {
+        Cutoff { date, adjustments: true }
+    }
+
+    pub fn includes(&self, tx: &Transaction) -> bool {
+        let date = tx.timestamp.local_date();
+        date < self.date || (date == self.date && (self.adjustments || tx.adjusting != Some(true)))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CalendarFile {
+    year_start: String,
+}
+
+/// When the fiscal year starts; the default is 1 January
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FiscalCalendar {
+    start_month: u32,
+    start_day: u32,
+}
+
+impl Default for FiscalCalendar {
+    fn default() -> Self {
+        FiscalCalendar { start_month: 1, start_day: 1 }
+    }
+}
+
+impl FiscalCalendar {
+    /// A year starting on `year_start` (MM-DD). Days after the 28th are
+    /// refused, since not every month has them.
+    pub fn new(year_start: &str) -> Result<Self, LedgerError> {
+        let invalid = || LedgerError::Config(format!("year_start '{}' is not a month and day (MM-DD, day 1-28)", year_start));
+        let (month, day) = year_start.split_once('-').ok_or_else(invalid)?;
+        let (start_month, start_day) = (month.parse::<u32>().map_err(|_| invalid())?, day.parse::<u32>().map_err(|_| invalid())?);
+        if !(1..=12).contains(&start_month) || !(1..=28).contains(&start_day) {
+            return Err(invalid());
+        }
+        Ok(FiscalCalendar { start_month, start_day })
+    }
+
+    /// Loads `year_start` from a TOML file
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read fiscal calendar {}: {}", path, e)))?;
+        let file: CalendarFile = toml::from_str(&data)
+            .map_err(|e| LedgerError::Config(format!("Invalid fiscal calendar {}: {}", path, e)))?;
+        Self::new(&file.year_start).map_err(|e| e.context(path))
+    }
+
+    /// First and last day of fiscal year `year`
+    pub fn year_bounds(&self, year: i32) -> (NaiveDate, NaiveDate) {
+        let start = self.year_start(year);
+        (start, self.year_start(year + 1).pred_opt().unwrap_or(start))
+    }
+
+    /// The day FY`year` starts: in the previous calendar year, unless the
+    /// year starts on 1 January
+    fn year_start(&self, year: i32) -> NaiveDate {
+        let calendar_year = if (self.start_month, self.start_day) == (1, 1) { year } else { year - 1 };
+        NaiveDate::from_ymd_opt(calendar_year, self.start_month, self.start_day).unwrap_or_default()
+    }
+
+    /// First and last day of a period (1-12; 13 is the year's last day)
+    pub fn period_bounds(&self, year: i32, period: u32) -> (NaiveDate, NaiveDate) {
+        let (year_start, year_end) = self.year_bounds(year);
+        if period >= 13 {
+            return (year_end, year_end);
+        }
+        let start = year_start + Months::new(period - 1);
+        (start, (year_start + Months::new(period)).pred_opt().unwrap_or(start))
+    }
+
+    /// The fiscal year a day falls in
+    pub fn year_of(&self, date: NaiveDate) -> i32 {
+        let year = if (self.start_month, self.start_day) == (1, 1) { date.year() } else { date.year() + 1 };
+        if date < self.year_start(year) { year - 1 } else { year }
+    }
+
+    /// The fiscal year and period (1-13) a transaction is posted in
+    pub fn period_of(&self, tx: &Transaction) -> (i32, u32) {
+        let date = tx.timestamp.local_date();
+        let year = self.year_of(date);
+        let (_, year_end) = self.year_bounds(year);
+        if date == year_end && tx.adjusting == Some(true) {
+            return (year, 13);
+        }
+        let period = (1..=12).find(|p| date <= self.period_bounds(year, *p).1).unwrap_or(12);
+        (year, period)
+    }
+
+    /// The end of a day or period (see the module docs)
+    pub fn cutoff(&self, spec: &str) -> Result<Cutoff, LedgerError> {
+        match parse_period(spec)? {
+            None => Ok(Cutoff::day(parse_day(spec)?)),
+            Some((year, None)) => Ok(Cutoff::day(self.year_bounds(year).1)),
+            Some((year, Some(period))) => Ok(Cutoff { date: self.period_bounds(year, period).1, adjustments: period != 12 }),
+        }
+    }
+
+    /// The first day of a day or period. Period 13 has no days of its own,
+    /// so a range cannot start there.
+    pub fn start(&self, spec: &str) -> Result<NaiveDate, LedgerError> {
+        match parse_period(spec)? {
+            None => parse_day(spec),
+            Some((year, None)) => Ok(self.year_bounds(year).0),
+            Some((_, Some(13))) => Err(LedgerError::Config(format!("{}: period 13 can only end a range", spec))),
+            Some((year, Some(period))) => Ok(self.period_bounds(year, period).0),
+        }
+    }
+}
+
+fn parse_day(date: &str) -> Result<NaiveDate, LedgerError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| LedgerError::Config(format!("'{}' is not a date (YYYY-MM-DD) or fiscal period (FY2025, FY2025-P03)", date)))
+}
+
+/// `FY2025` or `FY2025-P03` as (year, period); None for anything else
+fn parse_period(spec: &str) -> Result<Option<(i32, Option<u32>)>, LedgerError> {
+    let Some(rest) = spec.strip_prefix("FY") else { return Ok(None) };
+    let invalid = || LedgerError::Config(format!("'{}' is not a fiscal year (FY2025) or period (FY2025-P01 to FY2025-P13)", spec));
+    let (year, period) = match rest.split_once("-P") {
+        Some((year, period)) => (year, Some(period.parse::<u32>().ok().filter(|p| (1..=13).contains(p)).ok_or_else(invalid)?)),
+        None => (rest, None),
+    };
+    let year = year.parse::<i32>().ok().filter(|y| (1970..=9999).contains(y)).ok_or_else(invalid)?;
+    Ok(Some((year, period)))
+}
@@ -0,0 +1,337 @@
+//! Foreign Exchange
+//! Exchange rates by date, and the period-end revaluation that restates
+//! foreign-currency balances in the base currency at the closing rate.
+//!
+//! Convention: an account with foreign-currency entries holds that one
+//! currency, and base-currency entries on it are earlier revaluations. Its
+//! carrying value is every foreign entry converted at the rate of its own date
+//! plus those adjustments, so revaluing twice at the same rates posts nothing.
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::amount::format_cents;
+use crate::error::LedgerError;
+use crate::model::{JournalEntry, Transaction};
+use crate::timestamp::Timestamp;
+use crate::verify::{add_cents, check_currency, entry_cents};
+
+/// A rate as an exact fraction: one unit of the foreign currency is
+/// `numerator / denominator` units of the base currency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rate {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rate {
+    /// Parses a positive decimal such as "1.0389"
+    pub fn parse(rate: &str) -> Result<Self, LedgerError> {
+        let invalid = || LedgerError::Config(format!("'{}' is not a positive exchange rate", rate));
+        let (whole, fraction) = rate.trim().split_once('.').unwrap_or((rate.trim(), ""));
+        if whole.is_empty() || fraction.len() > 12
+            || !whole.chars().all(|c| c.is_ascii_digit()) || !fraction.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let numerator: i128 = format!("{}{}", whole, fraction).parse().map_err(|_| invalid())?;
+        if numerator == 0 {
+            return Err(invalid());
+        }
+        Ok(Rate { numerator, denominator: 10i128.pow(fraction.len() as u32) })
+    }
+
+    /// The rate the other way round (base into foreign)
+    pub fn inverse(self) -> Self {
+        Rate { numerator: self.denominator, denominator: self.numerator }
+    }
+
+    /// Converts an amount in cents, rounding half away from zero
+    pub fn convert(self, cents: i64) -> Result<i64, LedgerError> {
+        let too_large = || LedgerError::Amount(format!("{} converted at {}/{} is more than a ledger can hold",
+            format_cents(cents), self.numerator, self.denominator));
+        let rounded = (cents as i128).checked_mul(self.numerator)
+            .and_then(|scaled| scaled.abs().checked_mul(2)?.checked_add(self.denominator).map(|doubled| (doubled, scaled.signum())))
+            .map(|(doubled, sign)| doubled / (self.denominator * 2) * sign)
+            .ok_or_else(too_large)?;
+        i64::try_from(rounded).map_err(|_| too_large())
+    }
+}
+
+/// Exchange-rate table, loaded from JSON:
+/// `{"base": "USD", "rates": {"2024-12-31": {"EUR/USD": "1.0389"}}}`.
+/// "EUR/USD" is the price of one EUR in USD; "USD/EUR" is used inverted.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ExchangeRates {
+    pub base: String, // ISO 4217 code of the reporting currency
+    #[serde(default)]
+    pub rates: BTreeMap<String, BTreeMap<String, String>>, // Date (YYYY-MM-DD) -> pair -> rate
+}
+
+impl ExchangeRates {
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read {}: {}", path, e)))?;
+        let rates: ExchangeRates = serde_json::from_str(&data)
+            .map_err(|e| LedgerError::Config(format!("Invalid exchange rates {}: {}", path, e)))?;
+        rates.validate().map_err(|e| e.context(path))?;
+        Ok(rates)
+    }
+
+    /// Checks every date, currency pair and rate
+    pub fn validate(&self) -> Result<(), LedgerError> {
+        check_currency(&self.base)?;
+        for (date, pairs) in &self.rates {
+            parse_date(date)?;
+            for (pair, rate) in pairs {
+                let (from, to) = split_pair(pair)?;
+                check_currency(from)?;
+                check_currency(to)?;
+                Rate::parse(rate).map_err(|e| e.context(format!("{} {}", date, pair)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The rate from `currency` into the base currency in force on `date`:
+    /// the latest one quoted on or before it
+    pub fn rate(&self, currency: &str, date: &str) -> Result<Rate, LedgerError> {
+        if currency == self.base {
+            return Ok(Rate { numerator: 1, denominator: 1 });
+        }
+        let direct = format!("{}/{}", currency, self.base);
+        let inverse = format!("{}/{}", self.base, currency);
+        for (_, pairs) in self.rates.range(..=date.to_string()).rev() {
+            if let Some(rate) = pairs.get(&direct) {
+                return Rate::parse(rate);
+            }
+            if let Some(rate) = pairs.get(&inverse) {
+                return Rate::parse(rate).map(Rate::inverse);
+            }
+        }
+        Err(LedgerError::Config(format!("No {} rate on or before {}", direct, date)))
+    }
+}
+
+fn split_pair(pair: &str) -> Result<(&str, &str), LedgerError> {
+    pair.split_once('/').ok_or_else(|| LedgerError::Config(format!("'{}' is not a currency pair like EUR/USD", pair)))
+}
+
+/// Days since 1970-01-01 of a YYYY-MM-DD date
+pub fn parse_date(date: &str) -> Result<i64, LedgerError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+        .filter(|parsed| parsed.format("%Y-%m-%d").to_string() == date) // Zero-padded, as written
+        .map(|parsed| (parsed - NaiveDate::default()).num_days()) // NaiveDate::default() is 1970-01-01
+        .filter(|days| *days >= 0)
+        .ok_or_else(|| LedgerError::Config(format!("'{}' is not a date (YYYY-MM-DD) since 1970", date)))
+}
+
+/// The UTC date (YYYY-MM-DD) of a Unix timestamp
+pub fn format_date(timestamp: u64) -> String {
+    Timestamp::Unix(timestamp).utc().format("%Y-%m-%d").to_string()
+}
+
+/// The last second (23:59:59 UTC) of a YYYY-MM-DD date
+pub fn end_of_day(date: &str) -> Result<u64, LedgerError> {
+    Ok(parse_date(date)? as u64 * 86_400 + 86_399)
+}
+
+/// One foreign-currency balance restated at the closing rate
+#[derive(Debug, Clone)]
+pub struct Revaluation {
+    pub account_id: String,
+    pub currency: String,
+    pub balance_cents: i64,  // Net debit balance in the foreign currency
+    pub carrying_cents: i64, // What the books hold it at, in the base currency
+    pub revalued_cents: i64, // The balance at the closing rate
+    pub adjustment_cents: i64, // Revalued less carrying: positive is a gain on a debit balance
+}
+
+/// Foreign-currency balances as of the end of `as_of` (YYYY-MM-DD), from
+/// transactions that have already been verified. Later transactions are
+/// ignored. Each transaction is dated, and converted, by its own local day.
+pub fn revalue<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I, rates: &ExchangeRates, as_of: &str) -> Result<Vec<Revaluation>, LedgerError> {
+    parse_date(as_of)?;
+    let mut foreign: BTreeMap<(String, String), (i64, i64)> = BTreeMap::new(); // (account, currency) -> (balance, carrying)
+    let mut adjustments: BTreeMap<String, i64> = BTreeMap::new(); // account -> base-currency net
+    for tx in txs {
+        let date = tx.timestamp.local_date().format("%Y-%m-%d").to_string();
+        if *date > *as_of {
+            continue;
+        }
+        for (i, entry) in tx.entries.iter().enumerate() {
+            let net = entry_net_cents(entry).map_err(|e| e.context(format!("{} entry #{}", tx.hash_hex(), i)))?;
+            // An unstated currency is the base currency
+            match entry.currency.as_deref().filter(|c| *c != rates.base) {
+                None => add_cents(adjustments.entry(entry.account_id.clone()).or_default(), net)?,
+                Some(currency) => {
+                    let converted = rates.rate(currency, &date)?.convert(net)?;
+                    let (balance, carrying) = foreign.entry((entry.account_id.clone(), currency.to_string())).or_default();
+                    add_cents(balance, net)?;
+                    add_cents(carrying, converted)?;
+                }
+            }
+        }
+    }
+
+    let mut revaluations: Vec<Revaluation> = Vec::new();
+    for ((account_id, currency), (balance_cents, carrying_cents)) in foreign {
+        if let Some(previous) = revaluations.last().filter(|r| r.account_id == account_id) {
+            return Err(LedgerError::Config(format!("Account {} holds both {} and {}; revaluation needs one foreign currency per account",
+                account_id, previous.currency, currency)));
+        }
+        let mut carrying_cents = carrying_cents;
+        add_cents(&mut carrying_cents, adjustments.get(&account_id).copied().unwrap_or(0))?;
+        let revalued_cents = rates.rate(&currency, as_of)?.convert(balance_cents)?;
+        // Negatable too, as it may be posted as a credit
+        let adjustment_cents = revalued_cents.checked_sub(carrying_cents).filter(|cents| cents.checked_neg().is_some())
+            .ok_or_else(|| LedgerError::Amount(format!("The revaluation of {} is more than a ledger can hold", account_id)))?;
+        revaluations.push(Revaluation {
+            carrying_cents,
+            revalued_cents,
+            adjustment_cents,
+            account_id,
+            currency,
+            balance_cents,
+        });
+    }
+    Ok(revaluations)
+}
+
+/// Debit less credit; neither is negative, so this cannot overflow
+fn entry_net_cents(entry: &JournalEntry) -> Result<i64, LedgerError> {
+    Ok(entry_cents(&entry.debit, "debit")? - entry_cents(&entry.credit, "credit")?)
+}
+
+/// The base-currency postings that bring every balance to its revalued
+/// amount, with the difference going to `fx_account` (unrealised FX gain or
+/// loss). None if nothing needs adjusting.
+pub fn revaluation_transaction(revaluations: &[Revaluation], rates: &ExchangeRates, fx_account: &str, as_of: &str) -> Result<Option<Transaction>, LedgerError> {
+    let base = Some(rates.base.clone());
+    let posting = |account_id: &str, net: i64| JournalEntry {
+        account_id: account_id.to_string(),
+        debit: format_cents(net.max(0)),
+        credit: format_cents((-net).max(0)),
+        currency: base.clone(),
+    };
+    let mut entries: Vec<JournalEntry> = revaluations.iter()
+        .filter(|r| r.adjustment_cents != 0)
+        .map(|r| posting(&r.account_id, r.adjustment_cents))
+        .collect();
+    if entries.is_empty() {
+        return Ok(None);
+    }
+    let mut total = 0;
+    for r in revaluations {
+        add_cents(&mut total, r.adjustment_cents)?;
+    }
+    if total != 0 {
+        let total = total.checked_neg().ok_or_else(|| LedgerError::Amount("The revaluation total is more than a ledger can hold".to_string()))?;
+        entries.push(posting(fx_account, total));
+    }
+    Ok(Some(Transaction {
+        timestamp: end_of_day(as_of)?.into(),
+        author_did: String::new(), // Filled in by the signer
+        memo: format!("FX revaluation at {} closing rates ({} balances).", as_of, entries.len() - usize::from(total != 0)),
+        entries,
+        prev_hash: None,
+        height: None,
+        sequence: None, // Assigned when it is stored
+        canonicalization: None,
+        signing_policy: None,
+        key_event: None,
+        hash_alg: None,
+        version: None,
+        adjusting: None,
+        reverses: None,
+        prepared_by: None,
+        memo_commitment: None,
+        policy: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+
+    fn rates() -> ExchangeRates {
+        serde_json::from_str(r#"{"base": "USD", "rates": {
+            "2024-01-01": {"EUR/USD": "1.10"},
+            "2024-06-30": {"USD/EUR": "0.8"},
+            "2024-12-31": {"EUR/USD": "1.20"}
+        }}"#).unwrap()
+    }
+
+    /// A purchase of EUR with USD on `date`; balanced only at the rate, so
+    /// the entries are set after building
+    fn purchase(date: &str, eur: &str, usd: &str) -> Transaction {
+        let mut tx = TransactionBuilder::new()
+            .timestamp(end_of_day(date).unwrap())
+            .entry("10200", "0.01", "0.00")
+            .entry("10100", "0.00", "0.01")
+            .memo("EUR purchase")
+            .genesis()
+            .build()
+            .unwrap();
+        tx.entries[0] = JournalEntry { account_id: "10200".to_string(), debit: eur.to_string(), credit: "0.00".to_string(), currency: Some("EUR".to_string()) };
+        tx.entries[1] = JournalEntry { account_id: "10100".to_string(), debit: "0.00".to_string(), credit: usd.to_string(), currency: Some("USD".to_string()) };
+        tx
+    }
+
+    #[test]
+    fn rates_are_exact_and_round_half_away_from_zero() {
+        let rate = Rate::parse("1.5").unwrap();
+        assert_eq!(rate.convert(1).unwrap(), 2);
+        assert_eq!(rate.convert(-1).unwrap(), -2);
+        assert_eq!(rate.convert(3).unwrap(), 5);
+        assert_eq!(rate.inverse().convert(300).unwrap(), 200);
+        for invalid in ["0", "0.000", "-1.1", "1.2.3", "", "1.0000000000001"] {
+            assert!(Rate::parse(invalid).is_err(), "{}", invalid);
+        }
+        assert!(Rate::parse("2").unwrap().convert(i64::MAX).is_err());
+        assert!(Rate::parse("99999999999999999999999999").unwrap().convert(i64::MIN).is_err());
+    }
+
+    #[test]
+    fn the_latest_rate_on_or_before_the_date_applies() {
+        let rates = rates();
+        rates.validate().unwrap();
+        assert_eq!(rates.rate("EUR", "2024-03-01").unwrap(), Rate::parse("1.10").unwrap());
+        assert_eq!(rates.rate("EUR", "2024-07-01").unwrap(), Rate::parse("0.8").unwrap().inverse());
+        assert_eq!(rates.rate("USD", "1999-01-01").unwrap().convert(123).unwrap(), 123);
+        assert!(rates.rate("EUR", "2023-12-31").is_err());
+        assert!(rates.rate("GBP", "2024-12-31").is_err());
+        for invalid in ["2024-1-01", "2024-02-30", "1969-12-31"] {
+            assert!(parse_date(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn revaluation_posts_the_difference_once() {
+        let rates = rates();
+        let bought = purchase("2024-01-15", "1000.00", "1100.00");
+        let revaluations = revalue([&bought], &rates, "2024-12-31").unwrap();
+        assert_eq!(revaluations.len(), 1);
+        assert_eq!((revaluations[0].carrying_cents, revaluations[0].revalued_cents, revaluations[0].adjustment_cents), (110_000, 120_000, 10_000));
+
+        let adjustment = revaluation_transaction(&revaluations, &rates, "79100", "2024-12-31").unwrap().unwrap();
+        assert_eq!(adjustment.entries[0].debit, "100.00");
+        assert_eq!(adjustment.entries[1].credit, "100.00");
+
+        let again = revalue([&bought, &adjustment], &rates, "2024-12-31").unwrap();
+        assert!(revaluation_transaction(&again, &rates, "79100", "2024-12-31").unwrap().is_none());
+        // Later transactions are not part of the balance
+        assert!(revalue([&bought], &rates, "2024-01-14").unwrap().is_empty());
+    }
+
+    #[test]
+    fn negative_and_overflowing_balances_are_refused() {
+        let rates = rates();
+        assert!(revalue([&purchase("2024-01-15", "-1000.00", "-1100.00")], &rates, "2024-12-31").is_err());
+        let max = "92233720368547758.07";
+        assert!(revalue([&purchase("2024-01-15", max, max)], &rates, "2024-12-31").is_err());
+        let half = purchase("2024-01-15", "50000000000000000.00", "55000000000000000.00");
+        assert!(revalue([&half, &half], &rates, "2024-12-31").is_err());
+    }
+}
@@ -0,0 +1,114 @@
+//! General Ledger
+//! Account views over a [`Storage`]: every posting to an account, in chain
+//! order, with the account's running balance after it:
+//!
+//! ```ignore
+//! for posting in ledger.postings().account("10100").between(t0, t1).iter()? {
+//!     println!("{} {} {}", posting.timestamp, posting.memo, format_cents(posting.balance_cents));
+//! }
+//! ```
+//!
+//! Balances run from the start of the books, so the first posting in a range
+//! already carries everything posted before it. They are kept per account
+//! and currency, as debits minus credits.
+
+use std::collections::BTreeMap;
+
+use crate::amount::parse_cents;
+use crate::error::LedgerError;
+use crate::storage::{Query, Storage};
+use crate::timestamp::Timestamp;
+
+/// One entry of a stored transaction
+#[derive(Debug, Clone)]
+pub struct Posting {
+    pub hash: String, // Payload hash of the transaction it belongs to
+    pub height: Option<u64>,
+    pub timestamp: Timestamp,
+    pub memo: String,
+    pub account_id: String,
+    pub currency: Option<String>,
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+    pub balance_cents: i64, // The account's balance in this currency after this posting
+}
+
+/// Which postings to return; see the module docs
+pub struct PostingQuery<'a> {
+    storage: &'a dyn Storage,
+    account: Option<String>,
+    from: Option<u64>,
+    until: Option<u64>,
+}
+
+impl<'a> PostingQuery<'a> {
+    pub fn new(storage: &'a dyn Storage) -> Self {
+        PostingQuery { storage, account: None, from: None, until: None }
+    }
+
+    /// Only postings to this account; left out, every account's
+    pub fn account(mut self, account_id: impl Into<String>) -> Self {
+        self.account = Some(account_id.into());
+        self
+    }
+
+    /// Only postings from `from` through `until` (Unix seconds, inclusive)
+    pub fn between(mut self, from: u64, until: u64) -> Self {
+        self.from = Some(from);
+        self.until = Some(until);
+        self
+    }
+
+    pub fn from(mut self, from: u64) -> Self {
+        self.from = Some(from);
+        self
+    }
+
+    pub fn until(mut self, until: u64) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    /// Runs the query. Earlier postings are read too, for the opening balances.
+    pub fn iter(self) -> Result<std::vec::IntoIter<Posting>, LedgerError> {
+        let query = Query { account: self.account.clone(), until: self.until, ..Query::default() };
+        let mut balances: BTreeMap<(String, Option<String>), i64> = BTreeMap::new();
+        let mut postings = Vec::new();
+        for signed_tx in self.storage.query(&query)? {
+            let tx = &signed_tx.payload;
+            let in_range = self.from.is_none_or(|from| tx.timestamp.unix() >= from);
+            let hash = tx.hash_hex();
+            for (i, entry) in tx.entries.iter().enumerate() {
+                if self.account.as_ref().is_some_and(|account| entry.account_id != *account) {
+                    continue;
+                }
+                let in_entry = |e: LedgerError| e.context(format!("{} entry #{}", hash, i));
+                let debit_cents = parse_cents(&entry.debit).map_err(in_entry)?;
+                let credit_cents = parse_cents(&entry.credit).map_err(in_entry)?;
+                let balance = balances.entry((entry.account_id.clone(), entry.currency.clone())).or_default();
+                *balance += debit_cents - credit_cents;
+                if in_range {
+                    postings.push(Posting {
+                        hash: hash.clone(),
+                        height: tx.height,
+                        timestamp: tx.timestamp.clone(),
+                        memo: tx.memo.clone(),
+                        account_id: entry.account_id.clone(),
+                        currency: entry.currency.clone(),
+                        debit_cents,
+                        credit_cents,
+                        balance_cents: *balance,
+                    });
+                }
+            }
+        }
+        Ok(postings.into_iter())
+    }
+}
+
+impl dyn Storage + '_ {
+    /// Starts a general-ledger query over this store
+    pub fn postings(&self) -> PostingQuery<'_> {
+        PostingQuery::new(self)
+    }
+}
@@ -0,0 +1,102 @@
+//! Payload Hash Algorithms
+//! What gets signed is a hash of the payload's canonical bytes. The payload
+//! names its algorithm in `hash_alg` (so the choice is itself signed; absent
+//! means SHA-256, as on every transaction signed before the field existed),
+//! and the envelope's `digest` carries the hash as a multihash whose prefix
+//! tells verifiers which one to recompute. BLAKE3 is several times faster
+//! than SHA-256 on large batches; SHA3-256 is there for policies that ask for it.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::LedgerError;
+
+/// Multihash codes (https://github.com/multiformats/multicodec)
+pub const MULTIHASH_SHA2_256: u8 = 0x12;
+pub const MULTIHASH_SHA3_256: u8 = 0x16;
+pub const MULTIHASH_BLAKE3: u8 = 0x1e;
+
+/// A digest function that can be named in a multihash
+pub trait Hasher {
+    /// The multihash code that prefixes its digests
+    fn multihash_code(&self) -> u8;
+
+    /// The digest of `data`
+    fn digest(&self, data: &[u8]) -> Vec<u8>;
+
+    /// `<code><length><digest>`
+    fn multihash(&self, data: &[u8]) -> Vec<u8> {
+        let digest = self.digest(data);
+        let mut multihash = vec![self.multihash_code(), digest.len() as u8];
+        multihash.extend_from_slice(&digest);
+        multihash
+    }
+}
+
+/// The hash algorithms a payload may name; all produce 32 bytes
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HashAlg {
+    #[default]
+    #[serde(rename = "sha2-256")]
+    Sha2_256,
+    #[serde(rename = "sha3-256")]
+    Sha3_256,
+    #[serde(rename = "blake3")]
+    Blake3,
+}
+
+impl HashAlg {
+    pub const ALL: [HashAlg; 3] = [HashAlg::Sha2_256, HashAlg::Sha3_256, HashAlg::Blake3];
+
+    /// The algorithm a multihash code names
+    pub fn from_multihash_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|alg| alg.multihash_code() == code)
+    }
+
+    /// Its multicodec name, as written in `hash_alg`
+    pub fn name(self) -> &'static str {
+        match self {
+            HashAlg::Sha2_256 => "sha2-256",
+            HashAlg::Sha3_256 => "sha3-256",
+            HashAlg::Blake3 => "blake3",
+        }
+    }
+}
+
+impl Hasher for HashAlg {
+    fn multihash_code(&self) -> u8 {
+        match self {
+            HashAlg::Sha2_256 => MULTIHASH_SHA2_256,
+            HashAlg::Sha3_256 => MULTIHASH_SHA3_256,
+            HashAlg::Blake3 => MULTIHASH_BLAKE3,
+        }
+    }
+
+    fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlg::Sha2_256 => Sha256::digest(data).to_vec(),
+            HashAlg::Sha3_256 => sha3::Sha3_256::digest(data).to_vec(),
+            HashAlg::Blake3 => blake3::hash(data).as_bytes().to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for HashAlg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// By multicodec name: "sha2-256", "sha3-256" or "blake3"
+impl FromStr for HashAlg {
+    type Err = LedgerError;
+
+    fn from_str(name: &str) -> Result<Self, LedgerError> {
+        Self::ALL.into_iter().find(|alg| alg.name() == name).ok_or_else(|| LedgerError::Config(format!(
+            "Unknown hash algorithm '{}' (expected one of: {})", name,
+            Self::ALL.map(HashAlg::name).join(", "))))
+    }
+}
@@ -0,0 +1,159 @@
+//! Identity Model (The Account)
+//! This holds our keys and the public DID. Accounts are Ed25519 unless created
+//! for the secp256k1 suite (see keys).
+
+use ed25519_dalek::{Keypair, PublicKey, SecretKey, Signer};
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use rand::{CryptoRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::did::{did_from_verifying_key, did_key_document};
+use crate::error::LedgerError;
+use crate::keys::{SigAlg, VerifyingKey};
+use crate::mnemonic;
+use crate::model::{SignedTransaction, Transaction};
+
+/// The secret half of an account, per signature suite
+enum KeyPair {
+    Ed25519(Keypair),
+    Secp256k1(k256::ecdsa::SigningKey),
+}
+
+pub struct Account {
+    keypair: KeyPair,
+    pub did: String,
+}
+
+impl Account {
+    /// Generates a new user account and their 'did:key'
+    pub fn generate() -> Self {
+        let mut csprng = OsRng {};
+        Account::generate_with(&mut csprng)
+    }
+
+    /// Generates an account from the given RNG (e.g. a seeded one for fixtures)
+    pub fn generate_with<R: CryptoRng + RngCore>(rng: &mut R) -> Self {
+        Account::from_keypair(KeyPair::Ed25519(Keypair::generate(rng)))
+    }
+
+    /// Generates a new account for the given signature suite
+    pub fn generate_for(sig_alg: SigAlg) -> Self {
+        match sig_alg {
+            SigAlg::Ed25519 => Account::generate(),
+            SigAlg::Secp256k1 => loop {
+                // Almost every 32-byte string is a valid scalar; retry on the rare miss
+                let mut secret = Zeroizing::new([0u8; 32]);
+                OsRng.fill_bytes(secret.as_mut());
+                if let Ok(account) = Account::from_secret_bytes_with(SigAlg::Secp256k1, &secret) {
+                    break account;
+                }
+            },
+        }
+    }
+
+    fn from_keypair(keypair: KeyPair) -> Self {
+        let mut account = Account { keypair, did: String::new() };
+        account.did = did_from_verifying_key(&account.public_key());
+        account
+    }
+
+    /// Generates an account backed by a new 24-word recovery phrase.
+    /// The phrase is the only way to recover the account: show it to the user once.
+    pub fn generate_with_mnemonic() -> (Self, Zeroizing<String>) {
+        let phrase = mnemonic::generate_mnemonic();
+        let account = Account::from_mnemonic(&phrase).expect("a freshly generated phrase is valid");
+        (account, phrase)
+    }
+
+    /// Rebuilds the account a recovery phrase was generated for
+    pub fn from_mnemonic(phrase: &str) -> Result<Self, LedgerError> {
+        let seed = mnemonic::mnemonic_to_seed(phrase)?;
+        let secret = mnemonic::derive_ed25519(seed.as_ref(), mnemonic::DERIVATION_PATH);
+        Ok(Account::from_secret_bytes(&secret))
+    }
+
+    /// Rebuilds an account from a fixed 32-byte secret key.
+    /// Used for key files and reproducible fixtures (never use a *known* secret for real books!)
+    pub fn from_secret_bytes(bytes: &[u8; 32]) -> Self {
+        let secret = SecretKey::from_bytes(bytes).expect("32 bytes is always a valid secret key");
+        let public = PublicKey::from(&secret);
+        Account::from_keypair(KeyPair::Ed25519(Keypair { secret, public }))
+    }
+
+    /// Rebuilds an account of the given suite from its 32-byte secret key.
+    /// Fails for secp256k1 if the bytes are not a valid scalar.
+    pub fn from_secret_bytes_with(sig_alg: SigAlg, bytes: &[u8; 32]) -> Result<Self, LedgerError> {
+        match sig_alg {
+            SigAlg::Ed25519 => Ok(Account::from_secret_bytes(bytes)),
+            SigAlg::Secp256k1 => k256::ecdsa::SigningKey::from_slice(bytes)
+                .map(|key| Account::from_keypair(KeyPair::Secp256k1(key)))
+                .map_err(|_| LedgerError::Key("Invalid secp256k1 secret key".to_string())),
+        }
+    }
+
+    /// The raw 32-byte secret key, for writing the account to a key file.
+    /// Wiped from memory when dropped, like the account's own copy.
+    pub fn secret_bytes(&self) -> Zeroizing<[u8; 32]> {
+        let mut secret = Zeroizing::new([0u8; 32]);
+        match &self.keypair {
+            KeyPair::Ed25519(keypair) => secret.copy_from_slice(keypair.secret.as_bytes()),
+            KeyPair::Secp256k1(key) => {
+                let mut bytes = key.to_bytes();
+                secret.copy_from_slice(&bytes);
+                bytes.zeroize();
+            }
+        }
+        secret
+    }
+
+    /// Which signature suite this account signs with
+    pub fn sig_alg(&self) -> SigAlg {
+        match &self.keypair {
+            KeyPair::Ed25519(_) => SigAlg::Ed25519,
+            KeyPair::Secp256k1(_) => SigAlg::Secp256k1,
+        }
+    }
+
+    /// The account's public key
+    pub fn public_key(&self) -> VerifyingKey {
+        match &self.keypair {
+            KeyPair::Ed25519(keypair) => VerifyingKey::Ed25519(keypair.public),
+            KeyPair::Secp256k1(key) => VerifyingKey::Secp256k1(*key.verifying_key()),
+        }
+    }
+
+    /// The W3C DID Document for the account's did:key, as JSON
+    pub fn did_document(&self) -> serde_json::Value {
+        did_key_document(&self.did).expect("an account's own did:key always resolves")
+    }
+
+    /// Signs the *hash* of the transaction data
+    pub fn sign(&self, tx: Transaction) -> SignedTransaction {
+        let tx = tx.prepare_for_signing();
+        let tx_hash = tx.get_hash();
+        let signature = match &self.keypair {
+            KeyPair::Ed25519(keypair) => keypair.sign(&tx_hash).to_bytes().to_vec(),
+            KeyPair::Secp256k1(key) => {
+                // Deterministic (RFC 6979), low-S ECDSA over the same SHA-256
+                let signature: k256::ecdsa::Signature = key.sign_prehash(&tx_hash)
+                    .expect("a 32-byte hash is always a valid prehash");
+                signature.to_bytes().to_vec()
+            }
+        };
+        SignedTransaction::new(tx, self.sig_alg(), &signature)
+    }
+
+    /// Signs arbitrary bytes: Ed25519 over the message itself, ECDSA over its SHA-256
+    pub fn sign_bytes(&self, message: &[u8]) -> Vec<u8> {
+        match &self.keypair {
+            KeyPair::Ed25519(keypair) => keypair.sign(message).to_bytes().to_vec(),
+            KeyPair::Secp256k1(key) => {
+                let signature: k256::ecdsa::Signature = key.sign_prehash(&Sha256::digest(message))
+                    .expect("a 32-byte hash is always a valid prehash");
+                signature.to_bytes().to_vec()
+            }
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! Invoice Sub-Ledger
+//! Invoices as first-class objects, followed through their life (issued,
+//! partially paid, settled, written off) on the control accounts they sit in
+//! (receivables, payables). The ledger holds no invoice records of its own:
+//! every state is derived from signed transactions, and every change of
+//! state names the transaction behind it.
+//!
+//! An invoice is issued by the first transaction, other than a write-off,
+//! whose memo gives its reference in an `invoice=` field (see duplicates.rs);
+//! its amount is that transaction's movement on the control accounts,
+//! positive for a customer who owes us and negative for a supplier we owe.
+//! Later transactions with the same reference pay it; those that also post
+//! to a write-off account (e.g. bad debts) write it off, and a reversal
+//! undoes whatever the transaction it reverses did.
+//!
+//! Reconciliation links the rest automatically: a movement that gives no
+//! reference but names a counterparty (`counterparty=`, `payee=` or
+//! `vendor=`) is applied to that counterparty's open invoices, oldest first.
+//! What cannot be linked is left unlinked for a reviewer.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::acl::AccountPattern;
+use crate::counterparty::names;
+use crate::duplicates::{memo_fields, named_counterparty};
+use crate::error::LedgerError;
+use crate::fiscal::Cutoff;
+use crate::model::Transaction;
+use crate::verify::entry_cents;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Issued,
+    PartiallyPaid,
+    Settled,
+    WrittenOff,
+    Voided, // Its issue was reversed
+}
+
+impl InvoiceStatus {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InvoiceStatus::Issued => "issued",
+            InvoiceStatus::PartiallyPaid => "partially paid",
+            InvoiceStatus::Settled => "settled",
+            InvoiceStatus::WrittenOff => "written off",
+            InvoiceStatus::Voided => "voided",
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        matches!(self, InvoiceStatus::Issued | InvoiceStatus::PartiallyPaid)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    Issue,
+    Payment,
+    WriteOff,
+    Reversal, // Undoes a payment or write-off
+    Void,     // Undoes the issue
+}
+
+impl EventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventKind::Issue => "issue",
+            EventKind::Payment => "payment",
+            EventKind::WriteOff => "write-off",
+            EventKind::Reversal => "reversal",
+            EventKind::Void => "void",
+        }
+    }
+}
+
+/// How a movement was tied to the invoice
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Link {
+    Reference,    // Its memo gives the invoice's reference
+    Counterparty, // Applied to the counterparty's oldest open invoice
+    Reversed,     // It reverses a transaction linked to the invoice
+}
+
+impl Link {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Link::Reference => "by reference",
+            Link::Counterparty => "by counterparty",
+            Link::Reversed => "by reversal",
+        }
+    }
+}
+
+/// One change to an invoice, and the signed transaction behind it
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct InvoiceEvent {
+    pub date: NaiveDate,
+    pub tx_hash: String,
+    pub kind: EventKind,
+    pub link: Link,
+    pub cents: i64, // Movement on the control accounts applied to the invoice
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct Invoice {
+    pub reference: String,
+    pub counterparty: Option<String>,
+    pub issued: NaiveDate,
+    pub amount_cents: i64,      // Positive: receivable; negative: payable
+    pub outstanding_cents: i64, // Same sign as the amount while open
+    pub status: InvoiceStatus,
+    pub events: Vec<InvoiceEvent>,
+}
+
+impl Invoice {
+    /// Days since issue as of `date`
+    pub fn age_days(&self, date: NaiveDate) -> i64 {
+        (date - self.issued).num_days()
+    }
+
+    fn apply(&mut self, event: InvoiceEvent) -> Result<(), LedgerError> {
+        self.outstanding_cents = self.outstanding_cents.checked_add(event.cents)
+            .ok_or_else(|| LedgerError::Amount(format!("Invoice {} owes more than a ledger can hold", self.reference)))?;
+        self.events.push(event);
+        self.status = self.derive_status();
+        Ok(())
+    }
+
+    fn derive_status(&self) -> InvoiceStatus {
+        let sign = self.amount_cents.signum();
+        if self.outstanding_cents * sign > 0 {
+            return if self.outstanding_cents == self.amount_cents { InvoiceStatus::Issued } else { InvoiceStatus::PartiallyPaid };
+        }
+        // Closed, by whatever last reduced it
+        match self.events.iter().rev().find(|event| event.cents * sign < 0).map(|event| event.kind) {
+            Some(EventKind::WriteOff) => InvoiceStatus::WrittenOff,
+            Some(EventKind::Void) => InvoiceStatus::Voided,
+            _ => InvoiceStatus::Settled,
+        }
+    }
+}
+
+/// A control-account movement no invoice could be found for
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Unlinked {
+    pub date: NaiveDate,
+    pub tx_hash: String,
+    pub counterparty: Option<String>,
+    pub memo: String,
+    pub cents: i64,
+}
+
+/// Every invoice on the control accounts, in order of issue
+#[derive(Serialize, Debug, Clone)]
+pub struct InvoiceLedger {
+    pub accounts: Vec<String>,   // The control account patterns, as given
+    pub write_off: Vec<String>,  // Accounts whose postings write an invoice off
+    pub to: Cutoff,
+    pub invoices: Vec<Invoice>,
+    pub unlinked: Vec<Unlinked>, // For a reviewer to match by hand
+}
+
+/// Net movement (debit minus credit) of `tx` on accounts matching `patterns`
+fn movement(tx: &Transaction, patterns: &[AccountPattern]) -> Result<i64, LedgerError> {
+    let mut net: i64 = 0;
+    for (i, entry) in tx.entries.iter().enumerate() {
+        if patterns.iter().any(|pattern| pattern.matches(&entry.account_id)) {
+            let in_entry = |e: LedgerError| e.context(format!("Entry #{} ({})", i, entry.account_id));
+            let debit = entry_cents(&entry.debit, "debit").map_err(in_entry)?;
+            let credit = entry_cents(&entry.credit, "credit").map_err(in_entry)?;
+            net = net.checked_add(debit).and_then(|net| net.checked_sub(credit))
+                .ok_or_else(|| in_entry(LedgerError::Amount("Amounts add up to more than a ledger can hold".to_string())))?;
+        }
+    }
+    Ok(net)
+}
+
+impl InvoiceLedger {
+    /// The sub-ledger over verified transactions, in chain order, through `to`
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I, accounts: &[String], write_off: &[String], to: Cutoff) -> Result<Self, LedgerError> {
+        let parse = |patterns: &[String]| patterns.iter().map(|pattern| AccountPattern::parse(pattern)).collect::<Result<Vec<_>, _>>();
+        let (control, write_off_patterns) = (parse(accounts)?, parse(write_off)?);
+        let mut ledger = InvoiceLedger {
+            accounts: accounts.to_vec(),
+            write_off: write_off.to_vec(),
+            to,
+            invoices: Vec::new(),
+            unlinked: Vec::new(),
+        };
+        let mut by_reference: HashMap<String, usize> = HashMap::new();
+        let mut applied: HashMap<String, Vec<(usize, i64)>> = HashMap::new(); // Per transaction: (invoice, cents)
+
+        for tx in txs.into_iter().filter(|tx| to.includes(tx)) {
+            let net = movement(tx, &control)?;
+            if net == 0 {
+                continue;
+            }
+            let (tx_hash, date) = (tx.hash_hex(), tx.timestamp.local_date());
+            let event = |kind, link, cents| InvoiceEvent { date, tx_hash: tx_hash.clone(), kind, link, cents };
+
+            if let Some(reversed) = tx.reverses.as_ref().and_then(|reversed| applied.get(reversed)).cloned() {
+                for &(index, cents) in &reversed {
+                    let invoice = &mut ledger.invoices[index];
+                    let kind = if tx.reverses.as_ref() == Some(&invoice.events[0].tx_hash) { EventKind::Void } else { EventKind::Reversal };
+                    invoice.apply(event(kind, Link::Reversed, -cents))?;
+                }
+                applied.insert(tx_hash.clone(), reversed.iter().map(|&(index, cents)| (index, -cents)).collect());
+                continue;
+            }
+            if let Some(reversed) = &tx.reverses {
+                if ledger.unlinked.iter().any(|unlinked| &unlinked.tx_hash == reversed) {
+                    ledger.unlinked.retain(|unlinked| &unlinked.tx_hash != reversed);
+                    continue;
+                }
+            }
+
+            let fields = memo_fields(&tx.memo);
+            let counterparty = named_counterparty(&fields).map(str::to_string);
+            let kind = if tx.entries.iter().any(|entry| write_off_patterns.iter().any(|pattern| pattern.matches(&entry.account_id))) {
+                EventKind::WriteOff
+            } else {
+                EventKind::Payment
+            };
+            if let Some(reference) = fields.get("invoice") {
+                let key = reference.to_uppercase();
+                match by_reference.get(&key) {
+                    Some(&index) => {
+                        ledger.invoices[index].apply(event(kind, Link::Reference, net))?;
+                        applied.insert(tx_hash.clone(), vec![(index, net)]);
+                        continue;
+                    }
+                    None if kind != EventKind::WriteOff => {
+                        by_reference.insert(key, ledger.invoices.len());
+                        applied.insert(tx_hash.clone(), vec![(ledger.invoices.len(), net)]);
+                        ledger.invoices.push(Invoice {
+                            reference: reference.clone(),
+                            counterparty,
+                            issued: date,
+                            amount_cents: net,
+                            outstanding_cents: net,
+                            status: InvoiceStatus::Issued,
+                            events: vec![event(EventKind::Issue, Link::Reference, net)],
+                        });
+                        continue;
+                    }
+                    None => {} // Writing off an invoice we never issued: link by counterparty, if at all
+                }
+            }
+
+            // No reference: apply to the counterparty's open invoices it reduces, oldest first
+            let mut left = net;
+            let mut links = Vec::new();
+            if let Some(named) = &counterparty {
+                for (index, invoice) in ledger.invoices.iter_mut().enumerate() {
+                    let theirs = invoice.counterparty.as_deref().is_some_and(|value| names(value, named));
+                    if left == 0 || !theirs || !invoice.status.is_open() || invoice.outstanding_cents.signum() == left.signum() {
+                        continue;
+                    }
+                    let cents = if left.abs() < invoice.outstanding_cents.abs() { left } else { -invoice.outstanding_cents };
+                    invoice.apply(event(kind, Link::Counterparty, cents))?;
+                    links.push((index, cents));
+                    left -= cents;
+                }
+            }
+            if !links.is_empty() {
+                applied.insert(tx_hash.clone(), links);
+            }
+            if left != 0 {
+                ledger.unlinked.push(Unlinked { date, tx_hash: tx_hash.clone(), counterparty, memo: tx.memo.clone(), cents: left });
+            }
+        }
+        Ok(ledger)
+    }
+
+    /// Invoices still owed, in order of issue
+    pub fn open(&self) -> impl Iterator<Item = &Invoice> {
+        self.invoices.iter().filter(|invoice| invoice.status.is_open())
+    }
+
+    /// The invoice with `reference`, in any case
+    pub fn get(&self, reference: &str) -> Option<&Invoice> {
+        self.invoices.iter().find(|invoice| invoice.reference.eq_ignore_ascii_case(reference))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+
+    fn posting(author: &Account, day: u64, debit: &str, credit: &str, amount: &str, memo: &str) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(1_700_000_000 + day * 86_400)
+            .entry(debit, amount, "0.00")
+            .entry(credit, "0.00", amount)
+            .memo(memo)
+            .genesis()
+            .sign(author)
+            .unwrap()
+            .payload
+    }
+
+    #[test]
+    fn invoices_move_through_their_states_on_signed_transactions() {
+        let clerk = Account::generate();
+        let txs = [
+            posting(&clerk, 0, "11000", "40100", "500.00", "Invoice invoice=INV-1 counterparty=acme"),
+            posting(&clerk, 1, "11000", "40100", "200.00", "Invoice invoice=INV-2 counterparty=acme"),
+            posting(&clerk, 2, "11000", "40100", "90.00", "Invoice invoice=INV-3 counterparty=globex"),
+            posting(&clerk, 10, "10100", "11000", "200.00", "Part payment invoice=inv-1"),
+            posting(&clerk, 20, "10100", "11000", "400.00", "Receipt payee=Acme"), // Settles INV-1, pays half of INV-2
+            posting(&clerk, 30, "68100", "11000", "90.00", "Bad debt invoice=INV-3"),
+            posting(&clerk, 31, "10100", "11000", "25.00", "Receipt from someone"),
+        ];
+        let to = Cutoff::day(NaiveDate::MAX);
+        let ledger = InvoiceLedger::from_transactions(&txs, &["11000".to_string()], &["68*".to_string()], to).unwrap();
+        let status = |reference| ledger.get(reference).map(|invoice| (invoice.status, invoice.outstanding_cents));
+        assert_eq!(status("INV-1"), Some((InvoiceStatus::Settled, 0)));
+        assert_eq!(status("inv-2"), Some((InvoiceStatus::PartiallyPaid, 10_000)));
+        assert_eq!(status("INV-3"), Some((InvoiceStatus::WrittenOff, 0)));
+        assert_eq!(ledger.get("INV-2").unwrap().events[1].link, Link::Counterparty);
+        assert_eq!(ledger.open().count(), 1);
+        assert_eq!(ledger.unlinked.len(), 1);
+        assert_eq!(ledger.unlinked[0].cents, -2_500);
+
+        // Reversing the receipt reopens both invoices it paid
+        let mut reversal = posting(&clerk, 40, "11000", "10100", "400.00", "Receipt bounced");
+        reversal.reverses = Some(txs[4].hash_hex());
+        let mut reversed = txs.to_vec();
+        reversed.push(reversal);
+        let ledger = InvoiceLedger::from_transactions(&reversed, &["11000".to_string()], &["68*".to_string()], to).unwrap();
+        assert_eq!(ledger.get("INV-1").map(|invoice| invoice.status), Some(InvoiceStatus::PartiallyPaid));
+        assert_eq!(ledger.get("INV-2").map(|invoice| invoice.outstanding_cents), Some(20_000));
+
+        let mut void = posting(&clerk, 41, "40100", "11000", "200.00", "Issued in error");
+        void.reverses = Some(txs[1].hash_hex());
+        reversed.push(void);
+        let ledger = InvoiceLedger::from_transactions(&reversed, &["11000".to_string()], &["68*".to_string()], to).unwrap();
+        assert_eq!(ledger.get("INV-2").map(|invoice| invoice.status), Some(InvoiceStatus::Voided));
+    }
+
+    #[test]
+    fn negative_or_overflowing_movements_are_errors() {
+        let clerk = Account::generate();
+        let to = Cutoff::day(NaiveDate::MAX);
+        let control = ["11000".to_string()];
+        let mut negative = posting(&clerk, 0, "11000", "40100", "500.00", "Invoice invoice=INV-1");
+        negative.entries[0].debit = "-500.00".to_string();
+        assert!(matches!(InvoiceLedger::from_transactions(&[negative], &control, &[], to), Err(LedgerError::Amount(_))));
+
+        let mut doubled = posting(&clerk, 0, "11000", "40100", "92233720368547758.07", "Invoice invoice=INV-1");
+        doubled.entries[1] = doubled.entries[0].clone();
+        assert!(matches!(InvoiceLedger::from_transactions(&[doubled], &control, &[], to), Err(LedgerError::Amount(_))));
+
+        let txs = [
+            posting(&clerk, 0, "11000", "40100", "92233720368547758.07", "Invoice invoice=INV-1"),
+            posting(&clerk, 1, "11000", "40100", "0.01", "More invoice=INV-1"),
+        ];
+        assert!(matches!(InvoiceLedger::from_transactions(&txs, &control, &[], to), Err(LedgerError::Amount(_))));
+    }
+}
@@ -0,0 +1,354 @@
+//! NDJSON Journal
+//! The whole ledger as one append-only file: one signed transaction per
+//! line, in the order they were appended. Readers stream it line by line,
+//! so a journal of any size is verified in constant memory. Drafts, which
+//! are rewritten as they move through review, are kept beside it in
+//! `<journal>.drafts.json`.
+//!
+//! Lookups by hash, the head, each author's sequence and the key events
+//! come from an index kept in memory. It remembers how much of the file it
+//! has read and reads only what was added since, whether by this handle or
+//! another writer, so appending and checking a link do not reparse the
+//! whole journal.
+//!
+//! The one rewrite is of a private memo (see private_memo): the journal is
+//! copied with that line replaced and renamed over the old one, so it must
+//! not happen while another process appends.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Lines, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use crate::error::LedgerError;
+use crate::key_events::KeyRegistry;
+use crate::lifecycle::Draft;
+use crate::model::SignedTransaction;
+use crate::private_memo::PrivateMemo;
+use crate::storage::{check_append, Query, Storage};
+
+/// A journal file; created on the first append
+pub struct NdjsonJournal {
+    path: PathBuf,
+    index: Mutex<Index>,
+}
+
+/// Where to find things in the part of the journal read so far
+#[derive(Default)]
+struct Index {
+    indexed: u64,                    // Bytes read: always the end of a complete line
+    line_no: usize,
+    offsets: HashMap<String, u64>,   // Payload hash -> start of its line
+    head: Option<(u64, u64)>,        // Greatest height, and the start of its line
+    sequences: HashMap<String, u64>, // Author DID -> greatest sequence number
+    key_events: Vec<u64>,            // Start of each key event's line, in file order
+}
+
+impl Index {
+    fn add(&mut self, offset: u64, signed_tx: &SignedTransaction) {
+        let tx = &signed_tx.payload;
+        self.offsets.insert(tx.hash_hex(), offset);
+        if let Some(height) = tx.height {
+            if self.head.is_none_or(|(head, _)| height >= head) {
+                self.head = Some((height, offset));
+            }
+        }
+        if let Some(sequence) = tx.sequence {
+            let last = self.sequences.entry(tx.author_did.clone()).or_insert(sequence);
+            *last = (*last).max(sequence);
+        }
+        if tx.key_event.is_some() {
+            self.key_events.push(offset);
+        }
+    }
+}
+
+/// Streams a journal's transactions with their 1-based line numbers
+pub struct JournalLines {
+    lines: Lines<BufReader<File>>,
+    line_no: usize,
+}
+
+impl Iterator for JournalLines {
+    type Item = Result<(usize, SignedTransaction), LedgerError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = self.lines.next()?;
+            self.line_no += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(LedgerError::Io(format!("line {}: {}", self.line_no, e)))),
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Some(serde_json::from_str(&line)
+                .map(|signed_tx| (self.line_no, signed_tx))
+                .map_err(|e| LedgerError::Serialization(format!("line {}: Failed to parse transaction: {}", self.line_no, e))));
+        }
+    }
+}
+
+impl NdjsonJournal {
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        NdjsonJournal { path: path.as_ref().to_path_buf(), index: Mutex::new(Index::default()) }
+    }
+
+    /// Streams every transaction in file order. A missing file is an empty journal.
+    pub fn iter(&self) -> Result<Option<JournalLines>, LedgerError> {
+        match File::open(&self.path) {
+            Ok(file) => Ok(Some(JournalLines { lines: BufReader::new(file).lines(), line_no: 0 })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(LedgerError::Io(format!("Could not read journal {}: {}", self.path.display(), e))),
+        }
+    }
+
+    fn drafts_path(&self) -> PathBuf {
+        self.path.with_extension("drafts.json")
+    }
+
+    /// Replaces the drafts file in one rename, so readers see the old or the new list
+    fn write_drafts(&self, drafts: &[Draft]) -> Result<(), LedgerError> {
+        let path = self.drafts_path();
+        let data = serde_json::to_string_pretty(drafts).map_err(|e| LedgerError::Serialization(format!("Failed to serialize drafts: {}", e)))?;
+        let staging = path.with_extension("json.tmp");
+        fs::write(&staging, data).and_then(|_| fs::rename(&staging, &path))
+            .map_err(|e| LedgerError::Io(format!("Could not write drafts {}: {}", path.display(), e)))
+    }
+
+    /// The index, after reading whatever complete lines were added since
+    /// last time. A journal that shrank was replaced, so it is read afresh.
+    fn index(&self) -> Result<MutexGuard<'_, Index>, LedgerError> {
+        let mut index = self.index.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let len = match fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(LedgerError::Io(format!("Could not read journal {}: {}", self.path.display(), e))),
+        };
+        if len < index.indexed {
+            *index = Index::default();
+        }
+        if len == index.indexed {
+            return Ok(index);
+        }
+
+        let read_error = |e: std::io::Error| LedgerError::Io(format!("Could not read journal {}: {}", self.path.display(), e));
+        let mut file = File::open(&self.path).map_err(read_error)?;
+        file.seek(SeekFrom::Start(index.indexed)).map_err(read_error)?;
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line).map_err(read_error)?;
+            if read == 0 || !line.ends_with('\n') {
+                break; // A partial last line is still being written, or was cut short
+            }
+            let offset = index.indexed;
+            index.line_no += 1;
+            if !line.trim().is_empty() {
+                let signed_tx: SignedTransaction = serde_json::from_str(&line)
+                    .map_err(|e| LedgerError::Serialization(format!("line {}: Failed to parse transaction: {}", index.line_no, e)).context(self.path.display()))?;
+                index.add(offset, &signed_tx);
+            }
+            index.indexed += read as u64;
+        }
+        Ok(index)
+    }
+
+    /// The transaction whose line starts at `offset`
+    fn read_at(&self, offset: u64) -> Result<SignedTransaction, LedgerError> {
+        let read_error = |e: std::io::Error| LedgerError::Io(format!("Could not read journal {}: {}", self.path.display(), e));
+        let mut file = File::open(&self.path).map_err(read_error)?;
+        file.seek(SeekFrom::Start(offset)).map_err(read_error)?;
+        let mut line = String::new();
+        BufReader::new(file).read_line(&mut line).map_err(read_error)?;
+        serde_json::from_str(&line)
+            .map_err(|e| LedgerError::Serialization(format!("{}: Failed to parse transaction at byte {}: {}", self.path.display(), offset, e)))
+    }
+
+    /// Every transaction, stopping at the first unreadable line
+    fn scan(&self) -> Result<Vec<SignedTransaction>, LedgerError> {
+        let mut transactions = Vec::new();
+        for item in self.iter()?.into_iter().flatten() {
+            transactions.push(item.map_err(|e| e.context(self.path.display()))?.1);
+        }
+        Ok(transactions)
+    }
+}
+
+impl Storage for NdjsonJournal {
+    fn append(&mut self, signed_tx: &SignedTransaction) -> Result<String, LedgerError> {
+        let hash = check_append(self, signed_tx)?;
+        let mut line = serde_json::to_string(signed_tx).map_err(|e| LedgerError::Serialization(format!("Failed to serialize transaction: {}", e)))?;
+        line.push('\n');
+
+        let mut file = OpenOptions::new().create(true).append(true).read(true).open(&self.path)
+            .map_err(|e| LedgerError::Io(format!("Could not open journal {}: {}", self.path.display(), e)))?;
+        // A crash mid-write leaves a partial last line; never append after one
+        if fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0) > 0 {
+            let mut last = [0u8; 1];
+            file.seek(SeekFrom::End(-1)).and_then(|_| file.read_exact(&mut last))
+                .map_err(|e| LedgerError::Io(format!("Could not read journal {}: {}", self.path.display(), e)))?;
+            if last[0] != b'\n' {
+                return Err(LedgerError::Storage(format!("Journal {} ends with a partial line; repair it before appending", self.path.display())));
+            }
+        }
+        // One write per line so concurrent appenders cannot interleave within it
+        file.write_all(line.as_bytes()).and_then(|_| file.sync_data())
+            .map_err(|e| LedgerError::Io(format!("Could not append to journal {}: {}", self.path.display(), e)))?;
+        drop(self.index()?); // Reads just the line written
+        Ok(hash)
+    }
+
+    fn get_by_hash(&self, hash: &str) -> Result<Option<SignedTransaction>, LedgerError> {
+        let offset = self.index()?.offsets.get(hash).copied();
+        offset.map(|offset| self.read_at(offset)).transpose()
+    }
+
+    fn query(&self, query: &Query) -> Result<Vec<SignedTransaction>, LedgerError> {
+        let mut found: Vec<SignedTransaction> = self.scan()?.into_iter().filter(|signed_tx| query.matches(signed_tx)).collect();
+        found.sort_by_key(|signed_tx| (signed_tx.payload.height, signed_tx.payload.timestamp.unix())); // Stable: file order breaks ties
+        Ok(found)
+    }
+
+    fn head(&self) -> Result<Option<SignedTransaction>, LedgerError> {
+        let offset = self.index()?.head.map(|(_, offset)| offset);
+        offset.map(|offset| self.read_at(offset)).transpose()
+    }
+
+    fn next_sequence(&self, author_did: &str) -> Result<u64, LedgerError> {
+        Ok(self.index()?.sequences.get(author_did).map_or(0, |last| last + 1))
+    }
+
+    fn key_registry(&self) -> Result<KeyRegistry, LedgerError> {
+        let offsets = self.index()?.key_events.clone();
+        let mut key_events = offsets.into_iter().map(|offset| self.read_at(offset)).collect::<Result<Vec<_>, _>>()?;
+        key_events.sort_by_key(|signed_tx| (signed_tx.payload.height, signed_tx.payload.timestamp.unix())); // As query orders them
+        KeyRegistry::from_transactions(key_events.iter().map(|signed_tx| &signed_tx.payload))
+    }
+
+    fn drafts(&self) -> Result<Vec<Draft>, LedgerError> {
+        let path = self.drafts_path();
+        let data = match fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(LedgerError::Io(format!("Could not read drafts {}: {}", path.display(), e))),
+        };
+        let mut drafts: Vec<Draft> = serde_json::from_str(&data)
+            .map_err(|e| LedgerError::Serialization(format!("Invalid drafts {}: {}", path.display(), e)))?;
+        drafts.sort_by_key(|draft| draft.updated.unix()); // Stable: file order breaks ties
+        Ok(drafts)
+    }
+
+    fn put_draft(&mut self, draft: &Draft) -> Result<(), LedgerError> {
+        let mut drafts = self.drafts()?;
+        drafts.retain(|other| other.id != draft.id);
+        drafts.push(draft.clone());
+        self.write_drafts(&drafts)
+    }
+
+    fn delete_draft(&mut self, id: &str) -> Result<(), LedgerError> {
+        let mut drafts = self.drafts()?;
+        drafts.retain(|draft| draft.id != id);
+        self.write_drafts(&drafts)
+    }
+
+    fn replace_private_memo(&mut self, hash: &str, private_memo: Option<PrivateMemo>) -> Result<(), LedgerError> {
+        let mut index = self.index()?;
+        let offset = *index.offsets.get(hash)
+            .ok_or_else(|| LedgerError::Storage(format!("No transaction {} in {}", hash, self.path.display())))?;
+        let mut signed_tx = self.read_at(offset)?;
+        signed_tx.private_memo = private_memo;
+        let mut line = serde_json::to_string(&signed_tx).map_err(|e| LedgerError::Serialization(format!("Failed to serialize transaction: {}", e)))?;
+        line.push('\n');
+
+        // Every other line is copied byte for byte
+        let mut staging = self.path.clone().into_os_string();
+        staging.push(".tmp");
+        let rewrite = || -> std::io::Result<()> {
+            let mut reader = BufReader::new(File::open(&self.path)?);
+            let mut out = File::create(&staging)?;
+            std::io::copy(&mut (&mut reader).take(offset), &mut out)?;
+            reader.read_line(&mut String::new())?;
+            out.write_all(line.as_bytes())?;
+            std::io::copy(&mut reader, &mut out)?;
+            out.sync_data()?;
+            fs::rename(&staging, &self.path)
+        };
+        rewrite().map_err(|e| LedgerError::Io(format!("Could not rewrite journal {}: {}", self.path.display(), e)))?;
+        *index = Index::default(); // The lines after it have moved
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+    use crate::test_util::{chain, scratch_dir};
+
+    fn hash_of(signed_tx: Option<SignedTransaction>) -> Option<String> {
+        signed_tx.map(|signed_tx| signed_tx.payload.hash_hex())
+    }
+
+    fn journal(name: &str) -> NdjsonJournal {
+        NdjsonJournal::open(scratch_dir(name).join("ledger.ndjson"))
+    }
+
+    #[test]
+    fn lookups_follow_appends() {
+        let account = Account::generate();
+        let mut journal = journal("lookups");
+        assert!(journal.head().unwrap().is_none());
+        for signed_tx in chain(&account, 3) {
+            let hash = journal.append(&signed_tx).unwrap();
+            assert_eq!(hash_of(journal.get_by_hash(&hash).unwrap()), Some(signed_tx.payload.hash_hex()));
+            assert_eq!(hash_of(journal.head().unwrap()), Some(signed_tx.payload.hash_hex()));
+        }
+        assert_eq!(journal.next_sequence(&account.did).unwrap(), 3);
+        assert_eq!(journal.next_sequence("did:key:nobody").unwrap(), 0);
+        assert!(journal.get_by_hash("00").unwrap().is_none());
+    }
+
+    #[test]
+    fn another_writers_lines_are_picked_up() {
+        let account = Account::generate();
+        let transactions = chain(&account, 2);
+        let mut writer = journal("other-writer");
+        let reader = NdjsonJournal::open(&writer.path);
+        writer.append(&transactions[0]).unwrap();
+        assert_eq!(hash_of(reader.head().unwrap()), Some(transactions[0].payload.hash_hex()));
+        writer.append(&transactions[1]).unwrap();
+        assert_eq!(hash_of(reader.head().unwrap()), Some(transactions[1].payload.hash_hex()));
+        assert_eq!(reader.next_sequence(&account.did).unwrap(), 2);
+    }
+
+    #[test]
+    fn a_replaced_journal_is_read_afresh() {
+        let account = Account::generate();
+        let transactions = chain(&account, 2);
+        let mut journal = journal("replaced");
+        for signed_tx in &transactions {
+            journal.append(signed_tx).unwrap();
+        }
+        let first_line = format!("{}\n", serde_json::to_string(&transactions[0]).unwrap());
+        fs::write(&journal.path, first_line).unwrap();
+        assert_eq!(hash_of(journal.head().unwrap()), Some(transactions[0].payload.hash_hex()));
+        assert!(journal.get_by_hash(&transactions[1].payload.hash_hex()).unwrap().is_none());
+        journal.append(&transactions[1]).unwrap();
+    }
+
+    #[test]
+    fn a_partial_last_line_is_not_indexed() {
+        let account = Account::generate();
+        let transactions = chain(&account, 2);
+        let mut journal = journal("partial");
+        journal.append(&transactions[0]).unwrap();
+        let line = serde_json::to_string(&transactions[1]).unwrap();
+        OpenOptions::new().append(true).open(&journal.path).unwrap().write_all(&line.as_bytes()[..20]).unwrap();
+        assert_eq!(hash_of(journal.head().unwrap()), Some(transactions[0].payload.hash_hex()));
+        assert!(matches!(journal.append(&transactions[1]), Err(LedgerError::Storage(_))));
+    }
+}
@@ -0,0 +1,188 @@
+//! JWS Compact Envelope (RFC 7515)
+//! A signed transaction as `header.payload.signature`, so it can pass
+//! through JWT middleware and be checked by any JOSE library. The payload is
+//! the transaction's JCS form; the protected header names the algorithm
+//! ("EdDSA" for Ed25519, "ES256K" for secp256k1) and, as `kid`, the author's
+//! verification method (`did:key:z...#z...`).
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::canonical::to_jcs;
+use crate::data_integrity::verification_method;
+use crate::did::{did_to_verifying_key, DidResolver};
+use crate::error::LedgerError;
+use crate::keys::SigAlg;
+use crate::model::Transaction;
+use crate::signer::TransactionSigner;
+
+/// The JOSE `alg` for a signature suite
+pub fn jose_alg(sig_alg: SigAlg) -> &'static str {
+    match sig_alg {
+        SigAlg::Ed25519 => "EdDSA",
+        SigAlg::Secp256k1 => "ES256K",
+    }
+}
+
+/// The protected header fields we read
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JwsHeader {
+    pub alg: String,
+    pub kid: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
+}
+
+/// A transaction in JWS compact serialization
+#[derive(Debug, Clone)]
+pub struct JwsTransaction {
+    pub compact: String,      // header.payload.signature, exactly as signed
+    pub header: JwsHeader,
+    pub payload: Transaction,
+}
+
+fn decode_part(part: &str, name: &str) -> Result<Vec<u8>, LedgerError> {
+    URL_SAFE_NO_PAD.decode(part).map_err(|e| LedgerError::Serialization(format!("Invalid JWS {}: {}", name, e)))
+}
+
+/// Signs `tx` as a compact JWS. The author must be the signer's own DID.
+pub fn sign_jws(tx: Transaction, signer: &dyn TransactionSigner) -> Result<JwsTransaction, LedgerError> {
+    let tx = tx.prepare_for_signing();
+    if tx.author_did != signer.did() {
+        return Err(LedgerError::Key(format!("Transaction names author {} but the signing key is {}", tx.author_did, signer.did())));
+    }
+    let header = JwsHeader {
+        alg: jose_alg(did_to_verifying_key(signer.did())?.sig_alg()).to_string(),
+        kid: verification_method(signer.did()),
+        typ: Some("JWT".to_string()), // Any JSON object is a claim set, so JWT middleware accepts it
+    };
+    let signing_input = format!("{}.{}",
+        URL_SAFE_NO_PAD.encode(to_jcs(&header)?),
+        URL_SAFE_NO_PAD.encode(to_jcs(&tx)?));
+    let signature = signer.sign_bytes(signing_input.as_bytes())?;
+    Ok(JwsTransaction {
+        compact: format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature)),
+        header,
+        payload: tx,
+    })
+}
+
+impl JwsTransaction {
+    /// Parses `header.payload.signature` (surrounding whitespace is ignored)
+    pub fn parse(compact: &str) -> Result<Self, LedgerError> {
+        let compact = compact.trim();
+        let parts: Vec<&str> = compact.split('.').collect();
+        let [header, payload, _signature] = parts[..] else {
+            return Err(LedgerError::Serialization("A compact JWS has exactly three dot-separated parts".to_string()));
+        };
+        let header: JwsHeader = serde_json::from_slice(&decode_part(header, "header")?)
+            .map_err(|e| LedgerError::Serialization(format!("Invalid JWS header: {}", e)))?;
+        let payload: Transaction = serde_json::from_slice(&decode_part(payload, "payload")?)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e)))?;
+        Ok(JwsTransaction { compact: compact.to_string(), header, payload })
+    }
+
+    /// The payload as it was signed, every field kept
+    pub fn payload_json(&self) -> Result<serde_json::Value, LedgerError> {
+        let payload = self.compact.split('.').nth(1).unwrap_or_default();
+        serde_json::from_slice(&decode_part(payload, "payload")?)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e)))
+    }
+
+    /// The DID the `kid` belongs to
+    pub fn signer_did(&self) -> &str {
+        self.header.kid.split('#').next().unwrap_or_default()
+    }
+
+    /// Checks that the `kid` is the author and the signature is theirs under `alg`
+    pub fn verify_signature(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        if self.signer_did() != self.payload.author_did {
+            return Err(LedgerError::Signature(format!("JWS kid {} does not belong to the author {}", self.header.kid, self.payload.author_did)));
+        }
+        let key = resolver.resolve_public_key(&self.payload.author_did)?;
+        if self.header.alg != jose_alg(key.sig_alg()) {
+            return Err(LedgerError::Signature(format!("JWS alg {} does not match the author's {} key",
+                self.header.alg, jose_alg(key.sig_alg()))));
+        }
+        let (signing_input, signature) = self.compact.rsplit_once('.').unwrap_or_default();
+        key.verify_bytes(signing_input.as_bytes(), &decode_part(signature, "signature")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::signed;
+
+    /// RFC 8037 appendix A: the Ed25519 key of RFC 8032 test 1
+    const RFC8037_D: &str = "nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A";
+    const RFC8037_X: &str = "11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo";
+    const RFC8037_JWS: &str = "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.\
+        hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg";
+
+    fn rfc8037_account() -> Account {
+        Account::from_secret_bytes(&URL_SAFE_NO_PAD.decode(RFC8037_D).unwrap().try_into().unwrap())
+    }
+
+    #[test]
+    fn rfc8037_ed25519_example() {
+        let account = rfc8037_account();
+        assert_eq!(URL_SAFE_NO_PAD.encode(account.public_key().to_bytes()), RFC8037_X);
+        let (signing_input, signature) = RFC8037_JWS.rsplit_once('.').unwrap();
+        assert_eq!(URL_SAFE_NO_PAD.encode(account.sign_bytes(signing_input.as_bytes())), signature);
+        let signature = URL_SAFE_NO_PAD.decode(signature).unwrap();
+        account.public_key().verify_bytes(signing_input.as_bytes(), &signature).unwrap();
+        assert!(account.public_key().verify_bytes(b"eyJhbGciOiJFZERTQSJ9.e30", &signature).is_err());
+    }
+
+    #[test]
+    fn signed_transactions_round_trip() {
+        for account in [Account::generate(), Account::generate_for(SigAlg::Secp256k1)] {
+            let jws = sign_jws(signed(&account, None, 0, "Opening").payload, &account).unwrap();
+            let parsed = JwsTransaction::parse(&format!("  {}\n", jws.compact)).unwrap();
+            assert_eq!(parsed.header.alg, jose_alg(account.sig_alg()));
+            assert_eq!(parsed.signer_did(), account.did);
+            parsed.verify_signature(&DidKeyResolver).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_foreign_author_is_refused_at_signing() {
+        let author = Account::generate();
+        let tx = signed(&author, None, 0, "Opening").payload;
+        assert!(matches!(sign_jws(tx, &Account::generate()), Err(LedgerError::Key(_))));
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let account = Account::generate();
+        let jws = sign_jws(signed(&account, None, 0, "Opening").payload, &account).unwrap();
+        let [header, payload, signature]: [&str; 3] = jws.compact.split('.').collect::<Vec<_>>().try_into().unwrap();
+
+        // Another payload under the same signature
+        let mut other = jws.payload.clone();
+        other.memo = "Opening balance".to_string();
+        let forged = format!("{}.{}.{}", header, URL_SAFE_NO_PAD.encode(to_jcs(&other).unwrap()), signature);
+        assert!(matches!(JwsTransaction::parse(&forged).unwrap().verify_signature(&DidKeyResolver), Err(LedgerError::Signature(_))));
+
+        // A kid that is not the author's
+        let stranger = Account::generate();
+        let mut header_json: JwsHeader = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(header).unwrap()).unwrap();
+        header_json.kid = verification_method(&stranger.did);
+        let foreign = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(to_jcs(&header_json).unwrap()), payload, signature);
+        assert!(JwsTransaction::parse(&foreign).unwrap().verify_signature(&DidKeyResolver).is_err());
+
+        // An alg other than the key's
+        header_json.kid = verification_method(&account.did);
+        header_json.alg = "ES256K".to_string();
+        let wrong_alg = format!("{}.{}.{}", URL_SAFE_NO_PAD.encode(to_jcs(&header_json).unwrap()), payload, signature);
+        assert!(JwsTransaction::parse(&wrong_alg).unwrap().verify_signature(&DidKeyResolver).is_err());
+
+        assert!(JwsTransaction::parse(&format!("{}.{}", header, payload)).is_err());
+        assert!(JwsTransaction::parse(&format!("{}.{}.{}.x", header, payload, signature)).is_err());
+        assert!(JwsTransaction::parse(&format!("{}.!!.{}", header, signature)).is_err());
+    }
+}
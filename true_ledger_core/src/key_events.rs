@@ -0,0 +1,231 @@
+//! Key Rotation and Revocation
+//! A key event is an ordinary signed transaction (no entries) whose payload
+//! carries a `key_event`, so it is chained, sequenced and stored like any
+//! other. Replaying a ledger's key events into a [`KeyRegistry`] says which
+//! DIDs may still sign, and from when they may not:
+//!
+//! - `rotate`: the author hands over to `new_did`; the author's own key is
+//!   retired from the event's timestamp on.
+//! - `revoke`: `did` is compromised from `revoked_at` (default: the event's
+//!   timestamp), which may be earlier than the event itself. The author must
+//!   be `did` or a DID it was rotated to, so a successor key can revoke a lost one.
+//!
+//! A key that has been rotated away or revoked makes no further key events,
+//! whatever their timestamps, so neither can be repeated or undone.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::error::LedgerError;
+use crate::model::{SignedTransaction, Transaction};
+use crate::timestamp::Timestamp;
+
+/// What a key event does, tagged by `action` ("rotate" or "revoke")
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum KeyEvent {
+    Rotate {
+        new_did: String,
+    },
+    Revoke {
+        did: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        revoked_at: Option<Timestamp>,
+    },
+}
+
+/// What the registry knows about one DID
+#[derive(Debug, Clone, Default)]
+pub struct KeyStatus {
+    pub rotated_to: Option<(String, u64)>, // Successor DID, from this Unix time
+    pub rotated_from: Option<String>,      // Predecessor DID
+    pub revoked_at: Option<u64>,
+}
+
+/// Every DID's rotations and revocations, built from verified key events
+#[derive(Debug, Default)]
+pub struct KeyRegistry {
+    keys: HashMap<String, KeyStatus>,
+}
+
+impl KeyRegistry {
+    pub fn new() -> Self {
+        KeyRegistry::default()
+    }
+
+    /// Builds a registry from transactions that have already been verified;
+    /// those without a key event are skipped
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I) -> Result<Self, LedgerError> {
+        let mut registry = KeyRegistry::new();
+        for tx in txs {
+            registry.apply(tx)?;
+        }
+        Ok(registry)
+    }
+
+    pub fn status(&self, did: &str) -> Option<&KeyStatus> {
+        self.keys.get(did)
+    }
+
+    /// Records `tx`'s key event, if it has one. Its signature must already
+    /// have been verified; this checks the author was entitled to make it.
+    pub fn apply(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
+        let Some(event) = &tx.key_event else { return Ok(()) };
+        if !tx.entries.is_empty() {
+            return Err(LedgerError::Key("A key event must not post entries".to_string()));
+        }
+        // A key that was rotated away or revoked makes no more events, whatever
+        // the event's own timestamp says: timestamps are the author's word
+        let at = tx.timestamp.unix();
+        if let Some(status) = self.keys.get(&tx.author_did) {
+            if let Some((new_did, _)) = &status.rotated_to {
+                return Err(LedgerError::Key(format!("{} was already rotated to {} and can make no more key events", tx.author_did, new_did)));
+            }
+            if status.revoked_at.is_some() {
+                return Err(LedgerError::Key(format!("{} was revoked and can make no more key events", tx.author_did)));
+            }
+        }
+        match event {
+            KeyEvent::Rotate { new_did } => {
+                if *new_did == tx.author_did {
+                    return Err(LedgerError::Key(format!("{} cannot rotate to itself", new_did)));
+                }
+                if self.keys.get(new_did).is_some_and(|s| s.rotated_from.is_some() || s.rotated_to.is_some()) {
+                    return Err(LedgerError::Key(format!("{} already belongs to another rotation", new_did)));
+                }
+                self.keys.entry(tx.author_did.clone()).or_default().rotated_to = Some((new_did.clone(), at));
+                self.keys.entry(new_did.clone()).or_default().rotated_from = Some(tx.author_did.clone());
+            }
+            KeyEvent::Revoke { did, revoked_at } => {
+                if !self.is_same_or_successor(did, &tx.author_did) {
+                    return Err(LedgerError::Key(format!("{} may not revoke {}: only the key itself or a key it was rotated to can", tx.author_did, did)));
+                }
+                let revoked_at = revoked_at.as_ref().map_or(at, Timestamp::unix);
+                let status = self.keys.entry(did.clone()).or_default();
+                // A second revocation can only move the cut-off earlier
+                status.revoked_at = Some(status.revoked_at.map_or(revoked_at, |earlier| earlier.min(revoked_at)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether `candidate` is `did` or reached from it by rotations
+    fn is_same_or_successor(&self, did: &str, candidate: &str) -> bool {
+        let mut current = did;
+        for _ in 0..=self.keys.len() {
+            if current == candidate {
+                return true;
+            }
+            match self.keys.get(current).and_then(|s| s.rotated_to.as_ref()) {
+                Some((next, _)) => current = next,
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// Fails if `did` was revoked or rotated away at or before `at` (Unix seconds)
+    pub fn check_did(&self, did: &str, at: u64) -> Result<(), LedgerError> {
+        let Some(status) = self.keys.get(did) else { return Ok(()) };
+        if let Some(revoked_at) = status.revoked_at.filter(|revoked_at| at >= *revoked_at) {
+            return Err(LedgerError::Key(format!("Key {} was revoked at {} and cannot sign at {}", did, revoked_at, at)));
+        }
+        if let Some((new_did, rotated_at)) = status.rotated_to.as_ref().filter(|(_, rotated_at)| at >= *rotated_at) {
+            return Err(LedgerError::Key(format!("Key {} was rotated to {} at {} and cannot sign at {}", did, new_did, rotated_at, at)));
+        }
+        Ok(())
+    }
+
+    /// Checks the author and every cosigner of a transaction at its timestamp.
+    /// Key events pass: [`KeyRegistry::apply`] judges them against the registry
+    /// as it stood before them, which a finished registry no longer shows.
+    pub fn check(&self, signed_tx: &SignedTransaction) -> Result<(), LedgerError> {
+        let cosigners = signed_tx.cosignatures.iter().map(|c| c.signer_did.as_str());
+        let signers: Vec<&str> = std::iter::once(signed_tx.payload.author_did.as_str()).chain(cosigners).collect();
+        self.check_signers(&signed_tx.payload, &signers)
+    }
+
+    /// [`KeyRegistry::check`] for a payload signed by `signers`, whatever its envelope
+    pub fn check_signers(&self, tx: &Transaction, signers: &[&str]) -> Result<(), LedgerError> {
+        if tx.key_event.is_some() {
+            return Ok(());
+        }
+        let at = tx.timestamp.unix();
+        for did in signers {
+            self.check_did(did, at)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+    use crate::test_util::signed;
+
+    /// A key event by `author` at Unix time `at`
+    fn event(author: &Account, at: u64, event: KeyEvent) -> Transaction {
+        let mut tx = signed(author, None, 0, "Key event").payload;
+        tx.entries.clear();
+        tx.timestamp = at.into();
+        tx.key_event = Some(event);
+        tx
+    }
+
+    fn rotate(author: &Account, at: u64, new_did: &str) -> Transaction {
+        event(author, at, KeyEvent::Rotate { new_did: new_did.to_string() })
+    }
+
+    fn revoke(author: &Account, at: u64, did: &str) -> Transaction {
+        event(author, at, KeyEvent::Revoke { did: did.to_string(), revoked_at: None })
+    }
+
+    #[test]
+    fn a_rotated_key_signs_only_before_the_rotation() {
+        let (old, new) = (Account::generate(), Account::generate());
+        let registry = KeyRegistry::from_transactions([&rotate(&old, 2_000, &new.did)]).unwrap();
+        registry.check_did(&old.did, 1_999).unwrap();
+        assert!(registry.check_did(&old.did, 2_000).is_err());
+        registry.check_did(&new.did, 2_000).unwrap();
+    }
+
+    #[test]
+    fn a_revoked_key_cannot_rotate_with_a_backdated_event() {
+        let (lost, thief) = (Account::generate(), Account::generate());
+        let mut registry = KeyRegistry::from_transactions([&revoke(&lost, 2_000, &lost.did)]).unwrap();
+        let backdated = rotate(&lost, 1_000, &thief.did);
+        assert!(registry.apply(&backdated).unwrap_err().to_string().contains("revoked"));
+        assert!(registry.status(&thief.did).is_none());
+    }
+
+    #[test]
+    fn a_key_rotates_once() {
+        let (old, first, second) = (Account::generate(), Account::generate(), Account::generate());
+        let mut registry = KeyRegistry::from_transactions([&rotate(&old, 2_000, &first.did)]).unwrap();
+        assert!(registry.apply(&rotate(&old, 1_000, &second.did)).is_err());
+        assert!(registry.apply(&rotate(&old, 3_000, &second.did)).is_err());
+        assert_eq!(registry.status(&old.did).unwrap().rotated_to, Some((first.did.clone(), 2_000)));
+    }
+
+    #[test]
+    fn a_successor_revokes_its_predecessor_but_no_one_else_can() {
+        let (old, new, stranger) = (Account::generate(), Account::generate(), Account::generate());
+        let mut registry = KeyRegistry::from_transactions([&rotate(&old, 2_000, &new.did)]).unwrap();
+        assert!(registry.apply(&revoke(&stranger, 3_000, &old.did)).is_err());
+        let mut earlier = revoke(&new, 3_000, &old.did);
+        earlier.key_event = Some(KeyEvent::Revoke { did: old.did.clone(), revoked_at: Some(1_500.into()) });
+        registry.apply(&earlier).unwrap();
+        assert!(registry.check_did(&old.did, 1_500).is_err());
+        registry.check_did(&old.did, 1_499).unwrap();
+    }
+
+    #[test]
+    fn a_key_event_posts_no_entries() {
+        let account = Account::generate();
+        let mut tx = revoke(&account, 2_000, &account.did);
+        tx.entries = signed(&account, None, 0, "Posting").payload.entries;
+        assert!(KeyRegistry::new().apply(&tx).is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! OS Keychain
+//! An alternative to the file keystore (see keystore.rs): an identity's
+//! secret key is kept in the operating system's credential store (the macOS
+//! Keychain, the Windows Credential Manager, or the Secret Service on
+//! Linux), which unlocks with the user's login, so no key file is left on
+//! the workstation. Each identity is one item under the service name
+//! "true-ledger", holding its DID, suite and secret key; the DID is checked
+//! against the key whenever the item is read.
+//!
+//! Built only with the `keychain` feature.
+
+use keyring::Entry;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::error::LedgerError;
+use crate::identity::Account;
+use crate::keys::SigAlg;
+
+/// The service name every identity is stored under
+pub const SERVICE: &str = "true-ledger";
+
+/// One identity as stored in the keychain
+#[derive(Serialize, Deserialize)]
+struct KeychainItem {
+    did: String,
+    #[serde(default)]
+    sig_alg: SigAlg,
+    secret_key: String, // Hex, 32 bytes
+}
+
+fn keychain_error(name: &str, e: keyring::Error) -> LedgerError {
+    match e {
+        keyring::Error::NoEntry => LedgerError::Key(format!("No identity named '{}' in the OS keychain", name)),
+        e => LedgerError::Key(format!("OS keychain ({}): {}", name, e)),
+    }
+}
+
+fn entry(name: &str) -> Result<Entry, LedgerError> {
+    Entry::new(SERVICE, name).map_err(|e| keychain_error(name, e))
+}
+
+/// Stores `account` under `name`, refusing to overwrite an identity
+pub fn store(name: &str, account: &Account) -> Result<(), LedgerError> {
+    store_in(&entry(name)?, name, account)
+}
+
+/// Reads the identity stored under `name`
+pub fn load(name: &str) -> Result<Account, LedgerError> {
+    load_from(&entry(name)?, name)
+}
+
+/// Removes the identity stored under `name`
+pub fn delete(name: &str) -> Result<(), LedgerError> {
+    entry(name)?.delete_credential().map_err(|e| keychain_error(name, e))
+}
+
+fn store_in(entry: &Entry, name: &str, account: &Account) -> Result<(), LedgerError> {
+    match entry.get_secret() {
+        Ok(_) => return Err(LedgerError::Key(format!("An identity named '{}' is already in the OS keychain", name))),
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(keychain_error(name, e)),
+    }
+    let item = KeychainItem {
+        did: account.did.clone(),
+        sig_alg: account.sig_alg(),
+        secret_key: hex::encode(&account.secret_bytes()[..]),
+    };
+    let secret = Zeroizing::new(serde_json::to_vec(&item)
+        .map_err(|e| LedgerError::Serialization(format!("Failed to serialize keychain item: {}", e)))?);
+    let _wipe = Zeroizing::new(item.secret_key);
+    entry.set_secret(&secret).map_err(|e| keychain_error(name, e))
+}
+
+fn load_from(entry: &Entry, name: &str) -> Result<Account, LedgerError> {
+    let secret = Zeroizing::new(entry.get_secret().map_err(|e| keychain_error(name, e))?);
+    let item: KeychainItem = serde_json::from_slice(&secret)
+        .map_err(|_| LedgerError::Key(format!("The OS keychain item '{}' is not a true-ledger identity", name)))?;
+    let secret_key = Zeroizing::new(item.secret_key);
+    let bytes = Zeroizing::new(hex::decode(secret_key.as_str())
+        .map_err(|_| LedgerError::Key(format!("The OS keychain item '{}' holds an unreadable key", name)))?);
+    let bytes: Zeroizing<[u8; 32]> = Zeroizing::new(bytes.as_slice().try_into()
+        .map_err(|_| LedgerError::Key(format!("The OS keychain item '{}' holds a key of the wrong length", name)))?);
+    let account = Account::from_secret_bytes_with(item.sig_alg, &bytes)?;
+    if account.did != item.did {
+        return Err(LedgerError::Key(format!("The OS keychain item '{}' is corrupt: its key does not belong to {}", name, item.did)));
+    }
+    Ok(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An in-memory entry: mock entries keep what is set on them, and nothing else
+    fn mock_entry() -> Entry {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder());
+        Entry::new(SERVICE, "test").unwrap()
+    }
+
+    #[test]
+    fn an_identity_comes_back_with_its_did() {
+        let entry = mock_entry();
+        assert!(load_from(&entry, "alice").is_err());
+        let account = Account::generate_for(SigAlg::Secp256k1);
+        store_in(&entry, "alice", &account).unwrap();
+        assert_eq!(load_from(&entry, "alice").unwrap().did, account.did);
+        assert!(store_in(&entry, "alice", &Account::generate()).is_err());
+    }
+
+    #[test]
+    fn a_swapped_did_is_refused() {
+        let entry = mock_entry();
+        let item = KeychainItem {
+            did: Account::generate().did,
+            sig_alg: SigAlg::Ed25519,
+            secret_key: hex::encode(&Account::generate().secret_bytes()[..]),
+        };
+        entry.set_secret(&serde_json::to_vec(&item).unwrap()).unwrap();
+        assert!(load_from(&entry, "alice").err().unwrap().to_string().contains("corrupt"));
+        entry.set_secret(b"a password someone else stored").unwrap();
+        assert!(load_from(&entry, "alice").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Signature Suites
+//! Transactions can be signed with Ed25519 (the default) or with secp256k1
+//! ECDSA for users whose keys live in Ethereum/Bitcoin tooling. Both sign the
+//! same 32-byte SHA-256 of the payload; which algorithm applies is decided by
+//! the author's did:key multicodec, never by the (unsigned) envelope.
+
+use ed25519_dalek::Verifier;
+use k256::ecdsa::signature::hazmat::PrehashVerifier;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::LedgerError;
+
+/// Multicodec prefix of an Ed25519 public key (did:key 'z6Mk...')
+pub const MULTICODEC_ED25519_PUB: [u8; 2] = [0xed, 0x01];
+/// Multicodec prefix of a compressed secp256k1 public key (did:key 'zQ3s...')
+pub const MULTICODEC_SECP256K1_PUB: [u8; 2] = [0xe7, 0x01];
+
+/// The signature algorithm recorded in a SignedTransaction's `sig_alg`
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SigAlg {
+    #[default]
+    #[serde(rename = "Ed25519")]
+    Ed25519,
+    #[serde(rename = "ES256K")]
+    Secp256k1, // ECDSA over secp256k1 with SHA-256, 64-byte r||s (low-S)
+}
+
+/// A public key of any supported algorithm
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyingKey {
+    Ed25519(ed25519_dalek::PublicKey),
+    Secp256k1(k256::ecdsa::VerifyingKey),
+}
+
+impl VerifyingKey {
+    pub fn sig_alg(&self) -> SigAlg {
+        match self {
+            VerifyingKey::Ed25519(_) => SigAlg::Ed25519,
+            VerifyingKey::Secp256k1(_) => SigAlg::Secp256k1,
+        }
+    }
+
+    /// Raw key bytes: 32 for Ed25519, 33 (compressed point) for secp256k1
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            VerifyingKey::Ed25519(key) => key.to_bytes().to_vec(),
+            VerifyingKey::Secp256k1(key) => key.to_encoded_point(true).as_bytes().to_vec(),
+        }
+    }
+
+    /// The key with its multicodec prefix, as embedded in a did:key
+    pub fn to_multicodec_bytes(&self) -> Vec<u8> {
+        let prefix = match self {
+            VerifyingKey::Ed25519(_) => MULTICODEC_ED25519_PUB,
+            VerifyingKey::Secp256k1(_) => MULTICODEC_SECP256K1_PUB,
+        };
+        let mut bytes = prefix.to_vec();
+        bytes.extend_from_slice(&self.to_bytes());
+        bytes
+    }
+
+    /// Parses multicodec-prefixed key bytes
+    pub fn from_multicodec_bytes(bytes: &[u8]) -> Result<Self, LedgerError> {
+        match bytes {
+            [0xed, 0x01, key @ ..] => ed25519_dalek::PublicKey::from_bytes(key)
+                .map(VerifyingKey::Ed25519)
+                .map_err(|e| LedgerError::Key(format!("Invalid public key bytes: {:?}", e))),
+            [0xe7, 0x01, key @ ..] => k256::ecdsa::VerifyingKey::from_sec1_bytes(key)
+                .map(VerifyingKey::Secp256k1)
+                .map_err(|_| LedgerError::Key("Invalid secp256k1 public key bytes".to_string())),
+            _ => Err(LedgerError::Key("Unsupported multicodec prefix (expected Ed25519 or secp256k1)".to_string())),
+        }
+    }
+
+    /// Checks a [`sign_bytes`](crate::signer::TransactionSigner::sign_bytes)
+    /// signature: Ed25519 over the message itself, ECDSA over its SHA-256
+    pub fn verify_bytes(&self, message: &[u8], signature: &[u8]) -> Result<(), LedgerError> {
+        match self {
+            VerifyingKey::Ed25519(_) => self.verify(message, signature),
+            VerifyingKey::Secp256k1(_) => self.verify(&Sha256::digest(message), signature),
+        }
+    }
+
+    /// Checks a signature over the 32-byte transaction hash
+    pub fn verify(&self, tx_hash: &[u8], signature: &[u8]) -> Result<(), LedgerError> {
+        let valid = match self {
+            VerifyingKey::Ed25519(key) => {
+                let signature = ed25519_dalek::Signature::from_bytes(signature)
+                    .map_err(|e| LedgerError::Signature(format!("Invalid signature format: {:?}", e)))?;
+                key.verify(tx_hash, &signature).is_ok()
+            }
+            VerifyingKey::Secp256k1(key) => {
+                let signature = k256::ecdsa::Signature::from_slice(signature)
+                    .map_err(|e| LedgerError::Signature(format!("Invalid signature format: {:?}", e)))?;
+                key.verify_prehash(tx_hash, &signature).is_ok()
+            }
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(LedgerError::Signature("Signature verification failed: Tampering detected or wrong key.".to_string()))
+        }
+    }
+}
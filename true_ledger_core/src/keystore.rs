@@ -0,0 +1,263 @@
+//! Encrypted Keystore
+//! Persists Accounts between runs so the same did:key can sign many
+//! transactions. A keystore is a directory with one JSON file per named
+//! identity. The secret key is sealed with AES-256-GCM under a key derived
+//! from the user's passphrase with Argon2id; the DID stays readable (so
+//! identities can be listed without a passphrase) but is bound to the
+//! ciphertext as associated data, so it cannot be swapped undetected.
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use zeroize::Zeroizing;
+
+use crate::error::LedgerError;
+use crate::identity::Account;
+use crate::keys::SigAlg;
+
+/// Argon2id cost parameters, stored per file so they can be raised later
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct KdfParams {
+    pub m_cost: u32, // Memory in KiB
+    pub t_cost: u32, // Iterations
+    pub p_cost: u32, // Parallelism
+}
+
+impl Default for KdfParams {
+    /// OWASP's recommended Argon2id baseline (19 MiB, 2 passes, 1 lane)
+    fn default() -> Self {
+        KdfParams { m_cost: 19 * 1024, t_cost: 2, p_cost: 1 }
+    }
+}
+
+/// One identity as stored on disk
+#[derive(Serialize, Deserialize, Debug)]
+pub struct KeyFile {
+    pub name: String,
+    pub did: String,
+    #[serde(default)]
+    pub sig_alg: SigAlg, // Which suite the sealed secret belongs to
+    pub kdf: String, // Always "argon2id"
+    pub kdf_params: KdfParams,
+    pub salt: String,       // Hex, 16 bytes
+    pub cipher: String,     // Always "aes-256-gcm"
+    pub nonce: String,      // Hex, 12 bytes
+    pub ciphertext: String, // Hex: sealed 32-byte Ed25519 secret key + 16-byte tag
+}
+
+/// Derives the 32-byte AES key from the passphrase
+pub(crate) fn derive_key(passphrase: &str, salt: &[u8], params: &KdfParams) -> Result<Zeroizing<[u8; 32]>, LedgerError> {
+    let params = Params::new(params.m_cost, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| LedgerError::Key(format!("Invalid Argon2 parameters: {}", e)))?;
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| LedgerError::Key(format!("Key derivation failed: {}", e)))?;
+    Ok(key)
+}
+
+impl KeyFile {
+    /// Seals the account's secret key under the passphrase
+    pub fn seal(name: &str, account: &Account, passphrase: &str) -> Result<Self, LedgerError> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let kdf_params = KdfParams::default();
+        let key = derive_key(passphrase, &salt, &kdf_params)?;
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| LedgerError::Key(format!("Invalid key: {}", e)))?;
+        let secret = account.secret_bytes();
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: secret.as_ref(), aad: account.did.as_bytes() })
+            .map_err(|_| LedgerError::Key("Encryption failed".to_string()))?;
+
+        Ok(KeyFile {
+            name: name.to_string(),
+            did: account.did.clone(),
+            sig_alg: account.sig_alg(),
+            kdf: "argon2id".to_string(),
+            kdf_params,
+            salt: hex::encode(salt),
+            cipher: "aes-256-gcm".to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Opens the sealed secret key and checks it belongs to the recorded DID
+    pub fn unlock(&self, passphrase: &str) -> Result<Account, LedgerError> {
+        if self.kdf != "argon2id" || self.cipher != "aes-256-gcm" {
+            return Err(LedgerError::Key(format!("Unsupported key file format ({} / {})", self.kdf, self.cipher)));
+        }
+        let salt = hex::decode(&self.salt).map_err(|e| LedgerError::Key(format!("Invalid salt: {:?}", e)))?;
+        let nonce = hex::decode(&self.nonce).map_err(|e| LedgerError::Key(format!("Invalid nonce: {:?}", e)))?;
+        let ciphertext = hex::decode(&self.ciphertext).map_err(|e| LedgerError::Key(format!("Invalid ciphertext: {:?}", e)))?;
+        if nonce.len() != 12 {
+            return Err(LedgerError::Key("Invalid nonce: must be 12 bytes".to_string()));
+        }
+
+        let key = derive_key(passphrase, &salt, &self.kdf_params)?;
+        let cipher = Aes256Gcm::new_from_slice(key.as_ref()).map_err(|e| LedgerError::Key(format!("Invalid key: {}", e)))?;
+        let secret = Zeroizing::new(cipher
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: self.did.as_bytes() })
+            .map_err(|_| LedgerError::Key(format!("Could not unlock '{}': wrong passphrase or corrupted key file", self.name)))?);
+        let secret: Zeroizing<[u8; 32]> = Zeroizing::new(secret.as_slice().try_into()
+            .map_err(|_| LedgerError::Key(format!("Key file '{}' holds a secret of the wrong length", self.name)))?);
+
+        let account = Account::from_secret_bytes_with(self.sig_alg, &secret)?;
+        if account.did != self.did {
+            return Err(LedgerError::Key(format!("Key file '{}' is corrupt: its secret key does not belong to {}", self.name, self.did)));
+        }
+        Ok(account)
+    }
+}
+
+/// A directory of named, encrypted identities
+pub struct Keystore {
+    dir: PathBuf,
+}
+
+impl Keystore {
+    /// `$TLC_KEYSTORE` if set, otherwise `~/.tlc/keystore`
+    pub fn default_dir() -> PathBuf {
+        if let Some(dir) = std::env::var_os("TLC_KEYSTORE") {
+            return PathBuf::from(dir);
+        }
+        crate::tlc_home().join("keystore")
+    }
+
+    pub fn open<P: Into<PathBuf>>(dir: P) -> Self {
+        Keystore { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Identity names are file names, so keep them to a safe character set
+    fn path_for(&self, name: &str) -> Result<PathBuf, LedgerError> {
+        let valid = !name.is_empty() && !name.starts_with('.')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+        if !valid {
+            return Err(LedgerError::Key(format!("Invalid identity name '{}' (use letters, digits, '-', '_' and '.')", name)));
+        }
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    /// Generates a new identity, seals it and stores it under `name`
+    pub fn keygen(&self, name: &str, sig_alg: SigAlg, passphrase: &str) -> Result<Account, LedgerError> {
+        let account = Account::generate_for(sig_alg);
+        self.insert(name, &account, passphrase)?;
+        Ok(account)
+    }
+
+    /// Stores an existing account under `name`, refusing to overwrite one
+    pub fn insert(&self, name: &str, account: &Account, passphrase: &str) -> Result<(), LedgerError> {
+        let path = self.path_for(name)?;
+        let key_file = KeyFile::seal(name, account, passphrase)?;
+        let data = serde_json::to_string_pretty(&key_file)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to serialize key file: {}", e)))?;
+
+        fs::create_dir_all(&self.dir).map_err(|e| LedgerError::Io(format!("Failed to create {}: {}", self.dir.display(), e)))?;
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => LedgerError::Key(format!("An identity named '{}' already exists", name)),
+            _ => LedgerError::Io(format!("Failed to create {}: {}", path.display(), e)),
+        })?;
+        std::io::Write::write_all(&mut file, data.as_bytes())
+            .map_err(|e| LedgerError::Io(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    /// Reads a key file without unlocking it
+    pub fn get(&self, name: &str) -> Result<KeyFile, LedgerError> {
+        let path = self.path_for(name)?;
+        let data = fs::read_to_string(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => LedgerError::Key(format!("No identity named '{}' in {}", name, self.dir.display())),
+            _ => LedgerError::Io(format!("Could not read {}: {}", path.display(), e)),
+        })?;
+        serde_json::from_str(&data).map_err(|e| LedgerError::Serialization(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+
+    /// Every identity in the keystore, sorted by name
+    pub fn list(&self) -> Result<Vec<KeyFile>, LedgerError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(LedgerError::Io(format!("Could not read {}: {}", self.dir.display(), e))),
+        };
+        let mut key_files = Vec::new();
+        for entry in entries {
+            let path = entry.map_err(|e| LedgerError::Io(format!("Could not read {}: {}", self.dir.display(), e)))?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let data = fs::read_to_string(&path).map_err(|e| LedgerError::Io(format!("Could not read {}: {}", path.display(), e)))?;
+            let key_file: KeyFile = serde_json::from_str(&data)
+                .map_err(|e| LedgerError::Serialization(format!("Failed to parse {}: {}", path.display(), e)))?;
+            key_files.push(key_file);
+        }
+        key_files.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(key_files)
+    }
+
+    /// Loads and decrypts the named identity
+    pub fn unlock(&self, name: &str, passphrase: &str) -> Result<Account, LedgerError> {
+        self.get(name)?.unlock(passphrase)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    #[test]
+    fn identities_unlock_only_with_their_passphrase() {
+        let keystore = Keystore::open(scratch_dir("keystore-unlock"));
+        let account = keystore.keygen("alice", SigAlg::Secp256k1, "correct horse").unwrap();
+        assert_eq!(keystore.unlock("alice", "correct horse").unwrap().did, account.did);
+        let wrong = keystore.unlock("alice", "battery staple").err().unwrap().to_string();
+        assert!(wrong.contains("wrong passphrase"), "{}", wrong);
+        assert!(keystore.unlock("bob", "correct horse").is_err());
+    }
+
+    #[test]
+    fn a_sealed_key_is_bound_to_its_did() {
+        let (account, other) = (Account::generate(), Account::generate());
+        let mut key_file = KeyFile::seal("alice", &account, "passphrase").unwrap();
+        key_file.did = other.did.clone();
+        assert!(key_file.unlock("passphrase").is_err());
+
+        let mut key_file = KeyFile::seal("alice", &account, "passphrase").unwrap();
+        key_file.nonce = "00".to_string();
+        assert!(key_file.unlock("passphrase").is_err());
+    }
+
+    #[test]
+    fn names_are_safe_unique_file_names() {
+        let keystore = Keystore::open(scratch_dir("keystore-names"));
+        for name in ["", ".hidden", "../escape", "a/b", "semi;colon"] {
+            assert!(keystore.insert(name, &Account::generate(), "passphrase").is_err(), "{}", name);
+        }
+        keystore.insert("b-2", &Account::generate(), "passphrase").unwrap();
+        keystore.insert("a_1", &Account::generate(), "passphrase").unwrap();
+        let duplicate = keystore.insert("a_1", &Account::generate(), "passphrase").err().unwrap().to_string();
+        assert!(duplicate.contains("already exists"), "{}", duplicate);
+
+        let names: Vec<String> = keystore.list().unwrap().into_iter().map(|key_file| key_file.name).collect();
+        assert_eq!(names, vec!["a_1", "b-2"]);
+        assert!(Keystore::open(keystore.dir().join("missing")).list().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,102 @@
+//! TRUE LEDGER CORE - SHARED LIBRARY
+//!
+//! The ledger logic shared by Segment 1 (signing) and Segment 2 (verification):
+//! the transaction data model, did:key identities, signing backends, and the
+//! cryptographic and financial checks. Embed this crate to sign or verify
+//! transactions without shelling out to the binaries.
+//!
+//! The three entry points most callers need are [`sign`], [`verify`] and
+//! [`balance_check`]; [`TransactionBuilder`] puts new transactions together.
+
+pub mod acl;
+pub mod amount;
+pub mod analytics;
+pub mod anchor;
+pub mod approval;
+pub mod attestation;
+pub mod backup;
+pub mod builder;
+pub mod canonical;
+pub mod capability;
+pub mod chain;
+pub mod chart;
+pub mod checkpoint;
+pub mod connector;
+pub mod cose;
+pub mod counterparty;
+pub mod dag_cbor;
+pub mod data_integrity;
+pub mod did;
+#[cfg(feature = "didcomm")]
+pub mod didcomm;
+pub mod did_web;
+pub mod duplicates;
+pub mod envelope;
+pub mod error;
+pub mod fiscal;
+pub mod fx;
+pub mod general_ledger;
+pub mod hashing;
+pub mod identity;
+pub mod invoices;
+pub mod journal;
+pub mod jws;
+pub mod key_events;
+pub mod merkle;
+pub mod keys;
+#[cfg(feature = "keychain")]
+pub mod keychain;
+pub mod keystore;
+pub mod lifecycle;
+pub mod materiality;
+pub mod merge;
+pub mod mnemonic;
+pub mod model;
+pub mod multisig;
+#[cfg(feature = "openpgp")]
+pub mod openpgp;
+pub mod period_close;
+pub mod policy;
+pub mod presentation;
+pub mod private_memo;
+pub mod raft;
+pub mod recurring;
+pub mod reversal;
+pub mod rules;
+pub mod sampling;
+#[cfg(feature = "sigstore")]
+pub mod sigstore;
+pub mod schema;
+#[cfg(feature = "scripting")]
+pub mod script;
+pub mod signer;
+pub mod simulation;
+pub mod ssh_agent;
+pub mod statements;
+pub mod storage;
+#[cfg(test)]
+mod test_util;
+pub mod timestamp;
+pub mod trial_balance;
+pub mod validation;
+pub mod verify;
+pub mod versioning;
+
+pub use builder::TransactionBuilder;
+pub use error::LedgerError;
+pub use identity::Account;
+pub use model::{Canonicalization, JournalEntry, SignedTransaction, Transaction};
+pub use signer::TransactionSigner;
+pub use timestamp::Timestamp;
+pub use verify::{balance_check, verify, verify_signature};
+
+/// Signs a transaction with any signing backend (an Account, an ssh-agent key, ...)
+pub fn sign(signer: &dyn TransactionSigner, tx: Transaction) -> Result<SignedTransaction, LedgerError> {
+    signer.sign_transaction(tx)
+}
+
+/// Per-user state directory (keystore, caches): `~/.tlc`
+pub fn tlc_home() -> std::path::PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).unwrap_or_default();
+    std::path::Path::new(&home).join(".tlc")
+}
@@ -0,0 +1,132 @@
+//! Transaction Lifecycle
+//! A transaction can be prepared by one person and posted by another:
+//!
+//! - **draft**: saved unsigned by its preparer, who may still change it;
+//! - **pending**: submitted for review and signed by its preparer, and frozen
+//!   until the reviewer posts it or returns it to draft;
+//! - **posted**: signed by the reviewer, chained and appended to the ledger.
+//!
+//! Drafts and pending transactions are kept by the ledger outside its chain
+//! (see [`Storage::save_draft`](crate::storage::Storage::save_draft)), which
+//! refuses any other transition. Only posted transactions verify, so a draft
+//! file never does. Chain position and sequence are set when
+//! the reviewer signs, so a draft can wait while other postings go ahead.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::approval::{prepare, Preparation};
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+
+/// Where a transaction is in its life
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TxState {
+    Draft,
+    Pending,
+    Posted,
+}
+
+impl fmt::Display for TxState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TxState::Draft => "draft",
+            TxState::Pending => "pending",
+            TxState::Posted => "posted",
+        })
+    }
+}
+
+/// A transaction that is not posted yet
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Draft {
+    pub id: String,
+    pub state: TxState,       // Draft or Pending; posted drafts leave the draft area
+    pub prepared_by: String,  // The preparer's DID, as recorded by the ledger (drafts are unsigned)
+    pub payload: Transaction, // No author, chain position or sequence until it is posted
+    pub updated: Timestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preparation: Option<Preparation>, // The preparer's signature, made when it was submitted
+}
+
+/// `tx` without what the reviewer's signing adds: what the preparer signs
+/// (see approval.rs)
+pub(crate) fn content(tx: &Transaction) -> Result<serde_json::Value, LedgerError> {
+    let mut tx = tx.clone();
+    tx.author_did = String::new();
+    tx.prev_hash = None;
+    tx.height = None;
+    tx.sequence = None;
+    tx.canonicalization = None;
+    tx.version = None;
+    serde_json::to_value(&tx).map_err(|e| LedgerError::Serialization(e.to_string()))
+}
+
+impl Draft {
+    /// A new draft of `payload`, named after its hash unless `id` is given
+    pub fn new(id: Option<&str>, prepared_by: &str, mut payload: Transaction, now: Timestamp) -> Self {
+        payload.author_did = String::new();
+        payload.prev_hash = None;
+        payload.height = None;
+        payload.sequence = None;
+        payload.prepared_by = None;
+        let id = id.map_or_else(|| payload.hash_hex()[..16].to_string(), str::to_string);
+        Draft { id, state: TxState::Draft, prepared_by: prepared_by.to_string(), payload, updated: now, preparation: None }
+    }
+
+    /// Submits the draft for review, signed by its preparer
+    pub fn submit(&mut self, preparer: &dyn TransactionSigner, now: Timestamp) -> Result<(), LedgerError> {
+        if preparer.did() != self.prepared_by {
+            return Err(LedgerError::Approval(format!("Draft {} was prepared by {}; only they can submit it", self.id, self.prepared_by)));
+        }
+        self.payload.prepared_by = Some(self.prepared_by.clone());
+        self.preparation = Some(prepare(preparer, &self.payload)?);
+        self.state = TxState::Pending;
+        self.updated = now;
+        Ok(())
+    }
+
+    /// Sends a pending draft back to its preparer, who signs it again when
+    /// they resubmit it
+    pub fn return_to_draft(&mut self, now: Timestamp) {
+        self.preparation = None;
+        self.state = TxState::Draft;
+        self.updated = now;
+    }
+
+    /// Checks that `next` may replace `previous` (None: a new draft)
+    pub fn check_transition(previous: Option<&Draft>, next: &Draft) -> Result<(), LedgerError> {
+        if next.state == TxState::Posted {
+            return Err(LedgerError::Approval(format!("Draft {} is posted by signing it, not by saving it", next.id)));
+        }
+        if next.state == TxState::Pending && (next.preparation.is_none() || next.payload.prepared_by.as_ref() != Some(&next.prepared_by)) {
+            return Err(LedgerError::Approval(format!("Draft {} must be signed by its preparer when it is submitted", next.id)));
+        }
+        let Some(previous) = previous else { return Ok(()) };
+        if previous.prepared_by != next.prepared_by {
+            return Err(LedgerError::Approval(format!("Draft {} was prepared by {}", previous.id, previous.prepared_by)));
+        }
+        if previous.state == TxState::Pending && content(&previous.payload)? != content(&next.payload)? {
+            return Err(LedgerError::Approval(format!("Draft {} is pending review; return it to draft before changing it", previous.id)));
+        }
+        Ok(())
+    }
+
+    /// Checks that `posted` is this draft, signed by a reviewer other than
+    /// its preparer after it was submitted
+    pub fn check_posting(&self, posted: &Transaction) -> Result<(), LedgerError> {
+        if self.state != TxState::Pending {
+            return Err(LedgerError::Approval(format!("Draft {} has not been submitted for review", self.id)));
+        }
+        if posted.author_did == self.prepared_by {
+            return Err(LedgerError::Approval(format!("{} prepared draft {} and may not also post it", self.prepared_by, self.id)));
+        }
+        if content(posted)? != content(&self.payload)? {
+            return Err(LedgerError::Approval(format!("The transaction is not draft {} as submitted", self.id)));
+        }
+        Ok(())
+    }
+}
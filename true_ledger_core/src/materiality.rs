@@ -0,0 +1,74 @@
+//! Materiality Thresholds
+//! Large ledgers produce a lot of rounding-level noise. Materiality lets each
+//! entity (identified by its author DID) decide what is worth a reviewer's time.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+
+use crate::error::LedgerError;
+
+/// Thresholds for a single entity
+#[derive(Deserialize, Debug, Clone)]
+pub struct Materiality {
+    pub threshold: f64, // Amounts at or above this are material
+}
+
+impl Materiality {
+    pub fn is_material(&self, amount: f64) -> bool {
+        amount >= self.threshold
+    }
+}
+
+/// Materiality configuration: a default plus per-entity overrides
+#[derive(Deserialize, Debug)]
+pub struct MaterialityConfig {
+    pub default: Materiality,
+    #[serde(default)]
+    pub entities: HashMap<String, Materiality>, // Keyed by author DID
+}
+
+impl MaterialityConfig {
+    /// Loads the config from a JSON file, falling back to a built-in default
+    /// only if there is no such file: one that cannot be read or parsed is an error
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| LedgerError::Config(format!("Invalid materiality config {}: {}", path, e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(MaterialityConfig {
+                default: Materiality { threshold: 1000.0 },
+                entities: HashMap::new(),
+            }),
+            Err(e) => Err(LedgerError::Io(format!("Could not read materiality config {}: {}", path, e))),
+        }
+    }
+
+    /// Thresholds for the given entity, or the default if none are configured
+    pub fn for_entity(&self, did: &str) -> &Materiality {
+        self.entities.get(did).unwrap_or(&self.default)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::scratch_dir;
+
+    #[test]
+    fn only_a_missing_file_falls_back_to_the_default() {
+        let dir = scratch_dir("materiality");
+        let missing = MaterialityConfig::load(&dir.join("missing.json").to_string_lossy()).unwrap();
+        assert!(missing.default.is_material(1000.0) && !missing.default.is_material(999.99));
+
+        let path = dir.join("materiality.json");
+        fs::write(&path, r#"{"default": {"threshold": 50.0}, "entities": {"did:key:a": {"threshold": 5.0}}}"#).unwrap();
+        let config = MaterialityConfig::load(&path.to_string_lossy()).unwrap();
+        assert!(config.for_entity("did:key:a").is_material(5.0));
+        assert!(!config.for_entity("did:key:b").is_material(49.99));
+
+        fs::write(&path, "{ not json").unwrap();
+        assert!(matches!(MaterialityConfig::load(&path.to_string_lossy()), Err(LedgerError::Config(_))));
+        // A directory exists but cannot be read as a file
+        assert!(matches!(MaterialityConfig::load(&dir.to_string_lossy()), Err(LedgerError::Io(_))));
+    }
+}
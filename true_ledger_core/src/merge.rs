@@ -0,0 +1,231 @@
+//! Merging Replicas
+//! Two replicas that appended while apart hold different transactions. Their
+//! journals merge as a grow-only set keyed by payload hash, so the union is
+//! the same whichever order replicas are merged in, and merging twice
+//! changes nothing. The set is written out in one deterministic order: by
+//! timestamp, then author, sequence and hash, except that a transaction
+//! never comes before its `prev_hash` or its author's previous sequence
+//! number, when the set holds those.
+//!
+//! The union is only a ledger if it is still one hash chain. Concurrent
+//! appends fork it (two transactions naming the same `prev_hash`), and since
+//! links are signed nothing can be re-linked, so forks are reported rather
+//! than written out; so is an author signing two different transactions with
+//! the same sequence number. [`Merged::check_chain`] confirms that the
+//! ordered union verifies as a chain.
+
+use std::cmp::Reverse;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BinaryHeap, HashMap};
+
+use crate::chain::{verify_link, SequenceTracker};
+use crate::error::LedgerError;
+use crate::model::SignedTransaction;
+
+/// Two or more transactions one author signed with the same sequence number
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub author: String,
+    pub sequence: u64,
+    pub hashes: Vec<String>,
+}
+
+/// Two or more transactions that follow the same predecessor
+#[derive(Debug, Clone)]
+pub struct Fork {
+    pub prev_hash: Option<String>, // None: more than one genesis
+    pub hashes: Vec<String>,
+}
+
+/// The union of several journals
+#[derive(Debug, Default)]
+pub struct Merged {
+    pub transactions: Vec<SignedTransaction>, // In merge order
+    pub duplicates: usize,                    // Copies held by more than one journal
+    pub conflicts: Vec<Conflict>,
+    pub forks: Vec<Fork>,
+}
+
+impl Merged {
+    /// Checks that the transactions, in merge order, form one hash chain
+    /// with every author's sequence in turn, as a ledger requires
+    pub fn check_chain(&self) -> Result<(), LedgerError> {
+        let mut prev = None;
+        let mut sequences = SequenceTracker::new();
+        for (position, signed_tx) in self.transactions.iter().enumerate() {
+            let tx = &signed_tx.payload;
+            verify_link(prev, tx).and_then(|_| sequences.check(tx))
+                .map_err(|e| e.context(format!("Transaction #{} ({})", position, tx.hash_hex())))?;
+            prev = Some(tx);
+        }
+        Ok(())
+    }
+}
+
+/// Where a transaction goes when nothing holds it back
+type OrderKey = (u64, String, u64, String); // Timestamp, author, sequence, hash
+
+fn order_key(hash: &str, signed_tx: &SignedTransaction) -> OrderKey {
+    let tx = &signed_tx.payload;
+    (tx.timestamp.unix(), tx.author_did.clone(), tx.sequence.unwrap_or(0), hash.to_string())
+}
+
+/// Merges journals into one set, ordered as described above. Fails only if
+/// the transactions' links cannot all be honoured (a cycle, which honest
+/// signers cannot produce).
+pub fn merge(journals: Vec<Vec<SignedTransaction>>) -> Result<Merged, LedgerError> {
+    let mut set: BTreeMap<String, SignedTransaction> = BTreeMap::new();
+    let mut duplicates = 0;
+    for signed_tx in journals.into_iter().flatten() {
+        match set.entry(signed_tx.payload.hash_hex()) {
+            Entry::Occupied(_) => duplicates += 1,
+            Entry::Vacant(slot) => {
+                slot.insert(signed_tx);
+            }
+        }
+    }
+
+    let mut by_sequence: BTreeMap<(&str, u64), Vec<&str>> = BTreeMap::new();
+    for (hash, signed_tx) in &set {
+        if let Some(sequence) = signed_tx.payload.sequence {
+            by_sequence.entry((signed_tx.payload.author_did.as_str(), sequence)).or_default().push(hash);
+        }
+    }
+    let conflicts = by_sequence.iter()
+        .filter(|(_, hashes)| hashes.len() > 1)
+        .map(|((author, sequence), hashes)| Conflict {
+            author: author.to_string(),
+            sequence: *sequence,
+            hashes: hashes.iter().map(|hash| hash.to_string()).collect(),
+        })
+        .collect();
+
+    let mut by_prev: BTreeMap<Option<&str>, Vec<&str>> = BTreeMap::new();
+    for (hash, signed_tx) in &set {
+        if signed_tx.payload.height.is_some() {
+            by_prev.entry(signed_tx.payload.prev_hash.as_deref()).or_default().push(hash);
+        }
+    }
+    let forks = by_prev.into_iter()
+        .filter(|(_, hashes)| hashes.len() > 1)
+        .map(|(prev_hash, hashes)| Fork {
+            prev_hash: prev_hash.map(str::to_string),
+            hashes: hashes.into_iter().map(str::to_string).collect(),
+        })
+        .collect();
+
+    // What each transaction waits for, among those in the set
+    let mut waiting: HashMap<&str, usize> = HashMap::new();
+    let mut unblocks: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (hash, signed_tx) in &set {
+        let tx = &signed_tx.payload;
+        let mut after: Vec<&str> = tx.prev_hash.as_deref().filter(|prev| set.contains_key(*prev)).into_iter().collect();
+        if let Some(previous) = tx.sequence.and_then(|sequence| sequence.checked_sub(1)) {
+            after.extend(by_sequence.get(&(tx.author_did.as_str(), previous)).into_iter().flatten());
+        }
+        waiting.insert(hash, after.len());
+        for parent in after {
+            unblocks.entry(parent).or_default().push(hash);
+        }
+    }
+
+    let mut ready: BinaryHeap<Reverse<(OrderKey, &str)>> = set.iter()
+        .filter(|(hash, _)| waiting[hash.as_str()] == 0)
+        .map(|(hash, signed_tx)| Reverse((order_key(hash, signed_tx), hash.as_str())))
+        .collect();
+    let mut order = Vec::with_capacity(set.len());
+    while let Some(Reverse((_, hash))) = ready.pop() {
+        order.push(hash.to_string());
+        for child in unblocks.get(hash).into_iter().flatten() {
+            let count = waiting.get_mut(child).expect("every transaction has a count");
+            *count -= 1;
+            if *count == 0 {
+                ready.push(Reverse((order_key(child, &set[*child]), child)));
+            }
+        }
+    }
+    if order.len() < set.len() {
+        let mut stuck: Vec<&str> = waiting.iter().filter(|(_, count)| **count > 0).map(|(hash, _)| *hash).collect();
+        stuck.sort();
+        return Err(LedgerError::Chain(format!("{} transaction(s) wait on each other and cannot be ordered, e.g. {}",
+            stuck.len(), stuck.first().copied().unwrap_or_default())));
+    }
+
+    let transactions = order.iter().map(|hash| set[hash].clone()).collect();
+    Ok(Merged { transactions, duplicates, conflicts, forks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+    use crate::test_util::{chain, signed};
+
+    fn hashes(merged: &Merged) -> Vec<String> {
+        merged.transactions.iter().map(|signed_tx| signed_tx.payload.hash_hex()).collect()
+    }
+
+    #[test]
+    fn the_union_is_the_same_in_any_order() {
+        let account = Account::generate();
+        let full = chain(&account, 5);
+        let (a, b) = (full[..3].to_vec(), vec![full[0].clone(), full[3].clone(), full[4].clone()]);
+        let ab = merge(vec![a.clone(), b.clone()]).unwrap();
+        let ba = merge(vec![b, a]).unwrap();
+        assert_eq!(hashes(&ab), hashes(&ba));
+        assert_eq!(hashes(&ab), full.iter().map(|signed_tx| signed_tx.payload.hash_hex()).collect::<Vec<_>>());
+        assert_eq!(ab.duplicates, 1);
+        assert!(ab.conflicts.is_empty() && ab.forks.is_empty());
+        ab.check_chain().unwrap();
+    }
+
+    #[test]
+    fn merging_twice_changes_nothing() {
+        let account = Account::generate();
+        let full = chain(&account, 4);
+        let once = merge(vec![full[..2].to_vec(), full[2..].to_vec()]).unwrap();
+        let twice = merge(vec![once.transactions.clone(), full.clone()]).unwrap();
+        assert_eq!(hashes(&once), hashes(&twice));
+    }
+
+    #[test]
+    fn concurrent_appends_are_reported_as_a_fork() {
+        let (account, other) = (Account::generate(), Account::generate());
+        let shared = chain(&account, 2);
+        let mut a = shared.clone();
+        a.push(signed(&account, Some(&shared[1]), 2, "On replica A"));
+        let mut b = shared.clone();
+        b.push(signed(&other, Some(&shared[1]), 0, "On replica B"));
+        let merged = merge(vec![a, b]).unwrap();
+        assert_eq!(merged.forks.len(), 1);
+        assert_eq!(merged.forks[0].prev_hash, Some(shared[1].payload.hash_hex()));
+        assert!(merged.check_chain().is_err());
+    }
+
+    #[test]
+    fn two_geneses_are_a_fork() {
+        let merged = merge(vec![chain(&Account::generate(), 1), chain(&Account::generate(), 1)]).unwrap();
+        assert_eq!(merged.forks.len(), 1);
+        assert_eq!(merged.forks[0].prev_hash, None);
+    }
+
+    #[test]
+    fn one_sequence_signed_twice_is_a_conflict() {
+        let account = Account::generate();
+        let shared = chain(&account, 1);
+        let a = vec![shared[0].clone(), signed(&account, Some(&shared[0]), 1, "One")];
+        let b = vec![shared[0].clone(), signed(&account, Some(&shared[0]), 1, "Another")];
+        let merged = merge(vec![a, b]).unwrap();
+        assert_eq!(merged.conflicts.len(), 1);
+        assert_eq!((merged.conflicts[0].author.as_str(), merged.conflicts[0].sequence), (account.did.as_str(), 1));
+    }
+
+    #[test]
+    fn a_missing_predecessor_fails_the_chain_check() {
+        let account = Account::generate();
+        let full = chain(&account, 3);
+        let merged = merge(vec![vec![full[0].clone(), full[2].clone()]]).unwrap();
+        assert!(merged.forks.is_empty());
+        assert!(merged.check_chain().is_err());
+    }
+}
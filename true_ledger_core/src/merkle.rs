@@ -0,0 +1,503 @@
+//! Merkle Tree over a Journal
+//! The journal's transactions, in chain order, are the leaves of an RFC 6962
+//! (Certificate Transparency) tree: each leaf is a transaction's payload
+//! hash, leaves are hashed as SHA-256(0x00 || hash) and interior nodes as
+//! SHA-256(0x01 || left || right). The root commits to the whole journal,
+//! and an inclusion proof of log2(n) hashes shows one transaction is in it
+//! without handing over the rest. A consistency proof, also O(log n), shows
+//! a later root extends an earlier one: nothing before it was changed.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::LedgerError;
+
+pub type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// The leaf for a transaction's payload hash
+pub fn leaf_hash(tx_hash: &[u8]) -> Hash {
+    Sha256::new().chain_update([LEAF_PREFIX]).chain_update(tx_hash).finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    Sha256::new().chain_update([NODE_PREFIX]).chain_update(left).chain_update(right).finalize().into()
+}
+
+/// The largest power of two smaller than `n` (n > 1): where RFC 6962 splits a tree
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// The root over already-hashed leaves
+fn subtree_root(leaves: &[Hash]) -> Hash {
+    match leaves.len() {
+        0 => Sha256::digest([]).into(),
+        1 => leaves[0],
+        n => {
+            let k = split_point(n);
+            node_hash(&subtree_root(&leaves[..k]), &subtree_root(&leaves[k..]))
+        }
+    }
+}
+
+/// The sibling hashes from leaf `index` up to the root, lowest first
+fn audit_path(index: usize, leaves: &[Hash]) -> Vec<Hash> {
+    if leaves.len() <= 1 {
+        return Vec::new();
+    }
+    let k = split_point(leaves.len());
+    let (mut path, sibling) = if index < k {
+        (audit_path(index, &leaves[..k]), subtree_root(&leaves[k..]))
+    } else {
+        (audit_path(index - k, &leaves[k..]), subtree_root(&leaves[..k]))
+    };
+    path.push(sibling);
+    path
+}
+
+/// RFC 6962 SUBPROOF: the hashes that show the first `m` leaves are a prefix
+/// of `leaves`. `complete` is whether the old tree's own root may be left out.
+fn consistency_path(m: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+    let n = leaves.len();
+    if m == n {
+        return if complete { Vec::new() } else { vec![subtree_root(leaves)] };
+    }
+    let k = split_point(n);
+    let (mut path, sibling) = if m <= k {
+        (consistency_path(m, &leaves[..k], complete), subtree_root(&leaves[k..]))
+    } else {
+        (consistency_path(m - k, &leaves[k..], false), subtree_root(&leaves[..k]))
+    };
+    path.push(sibling);
+    path
+}
+
+/// A tree built from payload hashes in chain order
+#[derive(Debug, Clone, Default)]
+pub struct MerkleTree {
+    tx_hashes: Vec<Vec<u8>>,
+    leaves: Vec<Hash>,
+}
+
+impl MerkleTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the next transaction's payload hash
+    pub fn push(&mut self, tx_hash: Vec<u8>) {
+        self.leaves.push(leaf_hash(&tx_hash));
+        self.tx_hashes.push(tx_hash);
+    }
+
+    /// Number of transactions in the tree
+    pub fn size(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn root(&self) -> Hash {
+        subtree_root(&self.leaves)
+    }
+
+    /// The root the tree had when it held its first `size` transactions
+    pub fn root_at(&self, size: usize) -> Option<Hash> {
+        self.leaves.get(..size).map(subtree_root)
+    }
+
+    /// Position of the transaction with this hex payload hash
+    pub fn position(&self, tx_hash_hex: &str) -> Option<usize> {
+        let tx_hash = hex::decode(tx_hash_hex.trim()).ok()?;
+        self.tx_hashes.iter().position(|h| *h == tx_hash)
+    }
+
+    /// Proves the transaction with this hex payload hash is in the tree
+    pub fn prove_inclusion(&self, tx_hash_hex: &str) -> Result<InclusionProof, LedgerError> {
+        let index = self.position(tx_hash_hex)
+            .ok_or_else(|| LedgerError::Chain(format!("Transaction {} is not in the journal", tx_hash_hex)))?;
+        self.prove_leaf(index, self.size())
+    }
+
+    /// Proves leaf `index` is in the tree of the first `size` transactions,
+    /// e.g. one a checkpoint covers
+    pub fn prove_leaf(&self, index: usize, size: usize) -> Result<InclusionProof, LedgerError> {
+        if index >= size || size > self.size() {
+            return Err(LedgerError::Chain(format!("Cannot prove leaf {} of {} in a journal of {}", index, size, self.size())));
+        }
+        let leaves = &self.leaves[..size];
+        Ok(InclusionProof {
+            tree_size: size as u64,
+            leaf_index: index as u64,
+            tx_hash: hex::encode(&self.tx_hashes[index]),
+            root: hex::encode(subtree_root(leaves)),
+            audit_path: audit_path(index, leaves).iter().map(hex::encode).collect(),
+        })
+    }
+
+    /// Proves the tree of the first `old_size` transactions is a prefix of
+    /// the tree of the first `new_size`
+    pub fn prove_consistency(&self, old_size: usize, new_size: usize) -> Result<ConsistencyProof, LedgerError> {
+        if old_size > new_size || new_size > self.size() {
+            return Err(LedgerError::Chain(format!("Cannot prove {} transactions consistent with {} in a journal of {}",
+                old_size, new_size, self.size())));
+        }
+        let leaves = &self.leaves[..new_size];
+        let path = if old_size == 0 { Vec::new() } else { consistency_path(old_size, leaves, true) };
+        Ok(ConsistencyProof {
+            old_size: old_size as u64,
+            new_size: new_size as u64,
+            old_root: hex::encode(subtree_root(&leaves[..old_size])),
+            new_root: hex::encode(subtree_root(leaves)),
+            path: path.iter().map(hex::encode).collect(),
+        })
+    }
+}
+
+/// Evidence that one transaction is leaf `leaf_index` of a tree of `tree_size`
+/// transactions with root `root`. All hashes are hex.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InclusionProof {
+    pub tree_size: u64,
+    pub leaf_index: u64,
+    pub tx_hash: String,         // Payload hash of the proven transaction
+    pub root: String,            // Root the proof was made against
+    pub audit_path: Vec<String>, // Sibling hashes, leaf level first
+}
+
+/// Decodes a hex SHA-256 hash
+pub fn parse_hash(hex_hash: &str) -> Result<Hash, LedgerError> {
+    hex::decode(hex_hash.trim()).ok()
+        .and_then(|bytes| Hash::try_from(bytes).ok())
+        .ok_or_else(|| LedgerError::Serialization(format!("{} is not a hex SHA-256 hash", hex_hash)))
+}
+
+impl InclusionProof {
+    /// Recomputes the root from the transaction hash and audit path (RFC 9162
+    /// section 2.1.3.2) and checks it equals `root`. Pass a root obtained
+    /// independently (e.g. from a checkpoint): the proof's own `root` field
+    /// only says which tree it was made against.
+    pub fn verify(&self, root: &Hash) -> Result<(), LedgerError> {
+        if self.leaf_index >= self.tree_size {
+            return Err(LedgerError::Chain(format!("Leaf {} is outside a tree of {}", self.leaf_index, self.tree_size)));
+        }
+        let mut index = self.leaf_index;
+        let mut last = self.tree_size - 1;
+        let mut hash = leaf_hash(&hex::decode(&self.tx_hash)
+            .map_err(|e| LedgerError::Serialization(format!("Invalid tx_hash: {}", e)))?);
+        for sibling in &self.audit_path {
+            if last == 0 {
+                return Err(LedgerError::Chain("Audit path is longer than the tree is deep".to_string()));
+            }
+            let sibling = parse_hash(sibling)?;
+            if index & 1 == 1 || index == last {
+                hash = node_hash(&sibling, &hash);
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                hash = node_hash(&hash, &sibling);
+            }
+            index >>= 1;
+            last >>= 1;
+        }
+        if last != 0 {
+            return Err(LedgerError::Chain("Audit path is shorter than the tree is deep".to_string()));
+        }
+        if hash != *root {
+            return Err(LedgerError::Chain(format!("Proof leads to root {} but the trusted root is {}",
+                hex::encode(hash), hex::encode(root))));
+        }
+        Ok(())
+    }
+}
+
+/// Evidence that the tree of `new_size` transactions still starts with the
+/// tree of `old_size`, unchanged: the journal was only appended to in between
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ConsistencyProof {
+    pub old_size: u64,
+    pub new_size: u64,
+    pub old_root: String,  // Roots the proof was made against (hex)
+    pub new_root: String,
+    pub path: Vec<String>, // Subtree hashes, lowest first
+}
+
+impl ConsistencyProof {
+    /// Checks the proof links `old_root` to `new_root` (RFC 9162 section
+    /// 2.1.4.2). As with inclusion proofs, pass roots obtained independently.
+    pub fn verify(&self, old_root: &Hash, new_root: &Hash) -> Result<(), LedgerError> {
+        let inconsistent = |message: &str| Err(LedgerError::Chain(format!("Consistency proof from {} to {} transactions: {}",
+            self.old_size, self.new_size, message)));
+        let mut path = self.path.iter().map(|hash| parse_hash(hash)).collect::<Result<Vec<_>, _>>()?;
+        if self.old_size > self.new_size {
+            return inconsistent("the old tree is larger");
+        }
+        if self.old_size == 0 {
+            // Every tree extends the empty one
+            return if path.is_empty() { Ok(()) } else { inconsistent("expected no hashes") };
+        }
+        if self.old_size == self.new_size {
+            if !path.is_empty() {
+                return inconsistent("expected no hashes between equal sizes");
+            }
+            return if old_root == new_root { Ok(()) } else { inconsistent("equal sizes but different roots") };
+        }
+        // When the old tree is a complete subtree its root is the first node of the path
+        if self.old_size.is_power_of_two() {
+            path.insert(0, *old_root);
+        }
+        let Some((first, rest)) = path.split_first() else {
+            return inconsistent("no hashes");
+        };
+
+        let mut index = self.old_size - 1;
+        let mut last = self.new_size - 1;
+        while index & 1 == 1 {
+            index >>= 1;
+            last >>= 1;
+        }
+        let (mut old_hash, mut new_hash) = (*first, *first);
+        for hash in rest {
+            if last == 0 {
+                return inconsistent("too many hashes");
+            }
+            if index & 1 == 1 || index == last {
+                old_hash = node_hash(hash, &old_hash);
+                new_hash = node_hash(hash, &new_hash);
+                while index & 1 == 0 && index != 0 {
+                    index >>= 1;
+                    last >>= 1;
+                }
+            } else {
+                new_hash = node_hash(&new_hash, hash);
+            }
+            index >>= 1;
+            last >>= 1;
+        }
+        if last != 0 {
+            return inconsistent("too few hashes");
+        }
+        if old_hash != *old_root {
+            return inconsistent(&format!("the old tree's root would be {}, not {}", hex::encode(old_hash), hex::encode(old_root)));
+        }
+        if new_hash != *new_root {
+            return inconsistent(&format!("the new tree's root would be {}, not {}", hex::encode(new_hash), hex::encode(new_root)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The leaves of the Certificate Transparency reference tests
+    /// (certificate-transparency/cpp/merkletree/merkle_tree_test.cc)
+    const LEAVES: [&str; 8] = ["", "00", "10", "2021", "3031", "40414243", "5051525354555657", "606162636465666768696a6b6c6d6e6f"];
+
+    /// The roots of the trees of the first 1..=8 leaves
+    const ROOTS: [&str; 8] = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+
+    fn tree() -> MerkleTree {
+        let mut tree = MerkleTree::new();
+        for leaf in LEAVES {
+            tree.push(hex::decode(leaf).unwrap());
+        }
+        tree
+    }
+
+    fn root(size: usize) -> Hash {
+        parse_hash(ROOTS[size - 1]).unwrap()
+    }
+
+    /// An inclusion proof for leaf `index` of the first `size` leaves
+    fn inclusion(index: usize, size: usize, audit_path: &[&str]) -> InclusionProof {
+        InclusionProof {
+            tree_size: size as u64,
+            leaf_index: index as u64,
+            tx_hash: LEAVES[index].to_string(),
+            root: ROOTS[size - 1].to_string(),
+            audit_path: audit_path.iter().map(|hash| hash.to_string()).collect(),
+        }
+    }
+
+    fn consistency(old_size: usize, new_size: usize, path: &[&str]) -> ConsistencyProof {
+        ConsistencyProof {
+            old_size: old_size as u64,
+            new_size: new_size as u64,
+            old_root: ROOTS[old_size - 1].to_string(),
+            new_root: ROOTS[new_size - 1].to_string(),
+            path: path.iter().map(|hash| hash.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn roots_match_the_reference() {
+        let tree = tree();
+        assert_eq!(hex::encode(MerkleTree::new().root()), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+        for size in 1..=8 {
+            assert_eq!(hex::encode(tree.root_at(size).unwrap()), ROOTS[size - 1], "size {}", size);
+        }
+        assert_eq!(tree.root_at(9), None);
+    }
+
+    #[test]
+    fn reference_inclusion_proofs() {
+        let cases: [(usize, usize, &[&str]); 5] = [
+            (0, 1, &[]),
+            (0, 8, &[
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ]),
+            (5, 8, &[
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ]),
+            (2, 3, &["fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125"]),
+            (1, 5, &[
+                "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            ]),
+        ];
+        for (index, size, audit_path) in cases {
+            let proof = inclusion(index, size, audit_path);
+            proof.verify(&root(size)).unwrap_or_else(|e| panic!("leaf {} of {}: {}", index, size, e));
+            let leaves: Vec<Hash> = LEAVES[..size].iter().map(|leaf| leaf_hash(&hex::decode(leaf).unwrap())).collect();
+            let ours: Vec<String> = super::audit_path(index, &leaves).iter().map(hex::encode).collect();
+            assert_eq!(ours, proof.audit_path, "leaf {} of {}", index, size);
+        }
+    }
+
+    #[test]
+    fn every_generated_inclusion_proof_verifies() {
+        let mut tree = MerkleTree::new();
+        for n in 0..20u8 {
+            tree.push(vec![n; 32]);
+            for leaf in 0..=n {
+                let proof = tree.prove_inclusion(&hex::encode([leaf; 32])).unwrap();
+                proof.verify(&tree.root()).unwrap_or_else(|e| panic!("leaf {} of {}: {}", leaf, n + 1, e));
+            }
+        }
+    }
+
+    #[test]
+    fn leaves_are_proven_against_an_earlier_root() {
+        let tree = tree();
+        for size in 1..=LEAVES.len() {
+            for index in 0..size {
+                let proof = tree.prove_leaf(index, size).unwrap();
+                assert_eq!(proof.root, ROOTS[size - 1]);
+                proof.verify(&root(size)).unwrap_or_else(|e| panic!("leaf {} of {}: {}", index, size, e));
+            }
+        }
+        assert!(tree.prove_leaf(3, 3).is_err());
+        assert!(tree.prove_leaf(0, LEAVES.len() + 1).is_err());
+    }
+
+    #[test]
+    fn tampered_inclusion_proofs_fail() {
+        let proof = tree().prove_inclusion("40414243").unwrap();
+        let root = root(8);
+        proof.verify(&root).unwrap();
+
+        let mut wrong_leaf = proof.clone();
+        wrong_leaf.tx_hash = "3031".to_string();
+        assert!(wrong_leaf.verify(&root).is_err());
+        let mut wrong_index = proof.clone();
+        wrong_index.leaf_index = 4;
+        assert!(wrong_index.verify(&root).is_err());
+        let mut outside = proof.clone();
+        outside.leaf_index = 8;
+        assert!(outside.verify(&root).is_err());
+        let mut short = proof.clone();
+        short.audit_path.pop();
+        assert!(short.verify(&root).is_err());
+        let mut long = proof.clone();
+        long.audit_path.push(ROOTS[0].to_string());
+        assert!(long.verify(&root).is_err());
+        let mut flipped = proof.clone();
+        flipped.audit_path[1] = ROOTS[1].to_string();
+        assert!(flipped.verify(&root).is_err());
+        assert!(proof.verify(&self::root(7)).is_err());
+        assert!(tree().prove_inclusion("ff").is_err());
+    }
+
+    #[test]
+    fn reference_consistency_proofs() {
+        let cases: [(usize, usize, &[&str]); 4] = [
+            (1, 1, &[]),
+            (1, 8, &[
+                "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+            ]),
+            (6, 8, &[
+                "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ]),
+            (2, 5, &[
+                "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+            ]),
+        ];
+        let tree = tree();
+        for (old_size, new_size, path) in cases {
+            let proof = consistency(old_size, new_size, path);
+            proof.verify(&root(old_size), &root(new_size)).unwrap_or_else(|e| panic!("{} to {}: {}", old_size, new_size, e));
+            assert_eq!(tree.prove_consistency(old_size, new_size).unwrap().path, proof.path, "{} to {}", old_size, new_size);
+        }
+    }
+
+    #[test]
+    fn every_generated_consistency_proof_verifies() {
+        let tree = tree();
+        for new_size in 1..=8 {
+            for old_size in 1..=new_size {
+                let proof = tree.prove_consistency(old_size, new_size).unwrap();
+                proof.verify(&root(old_size), &root(new_size)).unwrap_or_else(|e| panic!("{} to {}: {}", old_size, new_size, e));
+            }
+        }
+        assert!(tree.prove_consistency(0, 8).unwrap().verify(&MerkleTree::new().root(), &root(8)).is_ok());
+    }
+
+    #[test]
+    fn tampered_consistency_proofs_fail() {
+        let tree = tree();
+        let proof = tree.prove_consistency(6, 8).unwrap();
+        assert!(proof.verify(&root(5), &root(8)).is_err());
+        assert!(proof.verify(&root(6), &root(7)).is_err());
+        let mut short = proof.clone();
+        short.path.pop();
+        assert!(short.verify(&root(6), &root(8)).is_err());
+        let mut long = proof.clone();
+        long.path.push(ROOTS[0].to_string());
+        assert!(long.verify(&root(6), &root(8)).is_err());
+        let mut altered = proof.clone();
+        altered.path[0] = ROOTS[0].to_string();
+        assert!(altered.verify(&root(6), &root(8)).is_err());
+        assert!(consistency(3, 3, &[ROOTS[0]]).verify(&root(3), &root(3)).is_err());
+        assert!(consistency(3, 3, &[]).verify(&root(3), &root(4)).is_err());
+        assert!(tree.prove_consistency(5, 4).is_err());
+        assert!(tree.prove_consistency(4, 9).is_err());
+    }
+}
@@ -0,0 +1,98 @@
+//! Mnemonic Backups (BIP39 + SLIP-0010)
+//! A 24-word BIP39 phrase is a paper backup of an identity. The phrase is
+//! stretched into a 64-byte seed (BIP39, empty passphrase) and the Ed25519
+//! secret key is derived from that seed with SLIP-0010 along a fixed path,
+//! so the same words always give back the same did:key. The phrase, the
+//! seed and every intermediate key are wiped from memory when dropped.
+
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha512;
+use zeroize::Zeroizing;
+
+use crate::error::LedgerError;
+
+type HmacSha512 = Hmac<Sha512>;
+
+/// SLIP-0010 path for the signing key: m/0'. Ed25519 only supports hardened
+/// children, so every index here is hardened.
+pub const DERIVATION_PATH: &[u32] = &[0];
+
+const HARDENED: u32 = 0x8000_0000;
+
+/// Generates a fresh 24-word English phrase (256 bits of entropy)
+pub fn generate_mnemonic() -> Zeroizing<String> {
+    let mut entropy = Zeroizing::new([0u8; 32]);
+    OsRng.fill_bytes(entropy.as_mut());
+    let mnemonic = bip39::Mnemonic::from_entropy(entropy.as_ref())
+        .expect("32 bytes is a valid BIP39 entropy length");
+    Zeroizing::new(mnemonic.to_string())
+}
+
+/// Checks the phrase (word list, checksum, 24 words) and returns its BIP39 seed
+pub fn mnemonic_to_seed(phrase: &str) -> Result<Zeroizing<[u8; 64]>, LedgerError> {
+    let mnemonic = bip39::Mnemonic::parse(phrase)
+        .map_err(|e| LedgerError::Key(format!("Invalid recovery phrase: {}", e)))?;
+    if mnemonic.word_count() != 24 {
+        return Err(LedgerError::Key(format!("Recovery phrase must have 24 words, not {}", mnemonic.word_count())));
+    }
+    Ok(Zeroizing::new(mnemonic.to_seed("")))
+}
+
+/// One HMAC-SHA512 step: returns (key, chain code)
+fn hmac_split(key: &[u8], data: &[&[u8]]) -> (Zeroizing<[u8; 32]>, Zeroizing<[u8; 32]>) {
+    let mut mac = HmacSha512::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in data {
+        mac.update(part);
+    }
+    let output = Zeroizing::new(<[u8; 64]>::from(mac.finalize().into_bytes()));
+    let mut left = Zeroizing::new([0u8; 32]);
+    let mut right = Zeroizing::new([0u8; 32]);
+    left.copy_from_slice(&output[..32]);
+    right.copy_from_slice(&output[32..]);
+    (left, right)
+}
+
+/// SLIP-0010 Ed25519 derivation of the secret key at `path` (all hardened)
+pub fn derive_ed25519(seed: &[u8], path: &[u32]) -> Zeroizing<[u8; 32]> {
+    let (mut key, mut chain_code) = hmac_split(b"ed25519 seed", &[seed]);
+    for index in path {
+        let index = (index | HARDENED).to_be_bytes();
+        let (child_key, child_chain_code) = hmac_split(chain_code.as_ref(), &[&[0u8], key.as_ref(), &index]);
+        key = child_key;
+        chain_code = child_chain_code;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derivation_matches_the_slip10_test_vector() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        assert_eq!(hex::encode(derive_ed25519(&seed, &[])), "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7");
+        assert_eq!(hex::encode(derive_ed25519(&seed, DERIVATION_PATH)), "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3");
+    }
+
+    #[test]
+    fn a_phrase_always_gives_the_same_seed() {
+        let phrase = generate_mnemonic();
+        assert_eq!(phrase.split_whitespace().count(), 24);
+        assert_eq!(*mnemonic_to_seed(&phrase).unwrap(), *mnemonic_to_seed(&phrase).unwrap());
+        assert_ne!(*generate_mnemonic(), *phrase);
+    }
+
+    #[test]
+    fn only_valid_24_word_phrases_are_accepted() {
+        let twelve = format!("{} about", ["abandon"; 11].join(" "));
+        let short = mnemonic_to_seed(&twelve).err().unwrap().to_string();
+        assert!(short.contains("24 words"), "{}", short);
+        // Right length, wrong checksum
+        assert!(mnemonic_to_seed(&["abandon"; 24].join(" ")).is_err());
+        assert!(mnemonic_to_seed(&format!("{} notaword", ["abandon"; 23].join(" "))).is_err());
+        assert!(mnemonic_to_seed(&format!("{} art", ["abandon"; 23].join(" "))).is_ok());
+    }
+}
@@ -0,0 +1,186 @@
+//! Data Models (The Ledger Objects)
+//! These are the "structs" that define our accounting data.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::canonical::to_jcs;
+use crate::dag_cbor::{cid_v1, to_dag_cbor};
+use crate::error::LedgerError;
+use crate::hashing::{HashAlg, Hasher};
+use crate::key_events::KeyEvent;
+use crate::keys::SigAlg;
+use crate::approval::Preparation;
+use crate::capability::SignedCapability;
+use crate::multisig::{Cosignature, SigningPolicy};
+use crate::policy::Policy;
+use crate::private_memo::PrivateMemo;
+use crate::schema::check_signed_transaction;
+use crate::timestamp::Timestamp;
+use crate::versioning::{check_version, upgrade};
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct JournalEntry {
+    pub account_id: String, // e.g., "10100" (Assets:Cash)
+    pub debit: String,      // Amount as string for precision
+    pub credit: String,     // Amount as string
+    // ISO 4217 code, e.g. "EUR". Omitted (and so left out of the hash) on
+    // single-currency transactions, including every one signed before it existed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+/// How a payload is turned into the bytes that get hashed and signed
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Canonicalization {
+    /// RFC 8785 JSON Canonicalization Scheme
+    #[serde(rename = "JCS")]
+    Jcs,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Transaction {
+    pub timestamp: Timestamp, // Unix seconds, or RFC 3339 with a UTC offset
+    #[serde(default)] // May be left out of an unsigned transaction; the signer fills it in
+    pub author_did: String,         // The 'did:key' of the creator
+    pub entries: Vec<JournalEntry>, // The list of balanced entries
+    pub memo: String,               // Justification
+    // Chain position. Omitted from the JSON (and so from the hash) when absent,
+    // which keeps transactions signed before chaining verifiable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prev_hash: Option<String>, // Hex hash of the previous payload (None at genesis)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u64>,       // 0 for the genesis transaction
+    // Replay protection: the author's own counter, 0 for their first
+    // transaction in a ledger. Ledgers reject a repeated or skipped number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence: Option<u64>,
+    // Signing-input version. Absent on transactions signed before JCS, whose
+    // signing input is serde_json's output in field order.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub canonicalization: Option<Canonicalization>,
+    // Who else must approve (m-of-n); absent means the author's signature suffices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_policy: Option<SigningPolicy>,
+    // Rotates or revokes a key instead of posting entries (see key_events)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_event: Option<KeyEvent>,
+    // Hash the signature is over; absent means SHA-256 (see hashing)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash_alg: Option<HashAlg>,
+    // Payload format version (see versioning). Absent on payloads signed
+    // before it existed, whose version follows from their other fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    // Posted into a closed period by someone its close authorises (see period_close)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub adjusting: Option<bool>,
+    // Hex payload hash of the transaction this one undoes (see reversal)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reverses: Option<String>,
+    // DID of whoever prepared it, when an approver posts it (see approval)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepared_by: Option<String>,
+    // Hex SHA-256 of a salt and the memo's private text, which travels
+    // unsigned in the envelope's private_memo (see private_memo)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo_commitment: Option<String>,
+    // Adopts a posting policy instead of posting entries (see policy)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy: Option<Policy>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct SignedTransaction {
+    pub payload: Transaction, // The raw transaction data
+    pub signature: String,    // Multibase signature (older files: bare hex)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>, // Multibase multihash of the payload (not signed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sig_alg: Option<SigAlg>, // Signature algorithm (older files: absent, meaning Ed25519)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cosignatures: Vec<Cosignature>, // Approvals by other signers, over the same payload hash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preparation: Option<Preparation>, // The preparer's signature over the content (see approval)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub delegation: Vec<SignedCapability>, // The author's capability chain, root first (see capability)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private_memo: Option<PrivateMemo>, // Opening of the payload's memo_commitment (see private_memo)
+}
+
+impl Transaction {
+    /// The exact bytes that get hashed (and therefore signed)
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        match self.canonicalization {
+            Some(Canonicalization::Jcs) => to_jcs(&self),
+            None => serde_json::to_string(&self).map_err(|e| LedgerError::Serialization(e.to_string())),
+        }
+        .expect("Failed to serialize transaction for hashing")
+        .into_bytes()
+    }
+
+    /// Transactions are signed at the current payload version (JCS,
+    /// `version` set) unless they already name a later one; called by every
+    /// signer just before hashing
+    pub fn prepare_for_signing(self) -> Self {
+        upgrade(self).0
+    }
+
+    /// The algorithm `get_hash` uses
+    pub fn hash_alg(&self) -> HashAlg {
+        self.hash_alg.unwrap_or_default()
+    }
+
+    /// Creates a secure hash of the transaction data.
+    /// This hash is what gets signed.
+    pub fn get_hash(&self) -> Vec<u8> {
+        self.hash_alg().digest(&self.canonical_bytes())
+    }
+
+    /// Hex form of the payload hash: what the next transaction puts in `prev_hash`
+    pub fn hash_hex(&self) -> String {
+        hex::encode(self.get_hash())
+    }
+}
+
+impl SignedTransaction {
+    /// Reads our own envelope, checking it against the published schema
+    /// first so a malformed file names every offending field
+    pub fn from_json(json_data: &str) -> Result<Self, LedgerError> {
+        let parse_error = |e: serde_json::Error| LedgerError::Serialization(format!("Failed to parse transaction data: {}", e));
+        let document = serde_json::from_str(json_data).map_err(parse_error)?;
+        check_signed_transaction(&document)?;
+        let signed_tx: Self = serde_json::from_value(document).map_err(parse_error)?;
+        check_version(&signed_tx.payload)?;
+        Ok(signed_tx)
+    }
+
+    /// Wraps a payload with its signature, both encoded self-describingly:
+    /// the signature as multibase base58btc ('z...') and the payload hash as
+    /// a multibase multihash ('zQm...' for sha2-256).
+    pub fn new(payload: Transaction, sig_alg: SigAlg, signature: &[u8]) -> Self {
+        let multihash = payload.hash_alg().multihash(&payload.canonical_bytes());
+
+        SignedTransaction {
+            payload,
+            signature: multibase::encode(multibase::Base::Base58Btc, signature),
+            digest: Some(multibase::encode(multibase::Base::Base58Btc, multihash)),
+            sig_alg: Some(sig_alg),
+            cosignatures: Vec::new(),
+            preparation: None,
+            delegation: Vec::new(),
+            private_memo: None,
+        }
+    }
+
+    /// The whole signed transaction (payload, signature and metadata) as
+    /// deterministic DAG-CBOR
+    pub fn to_dag_cbor(&self) -> Vec<u8> {
+        to_dag_cbor(self).expect("Failed to serialize transaction as DAG-CBOR")
+    }
+
+    /// Content identifier: CIDv1 (dag-cbor, sha2-256) of [`Self::to_dag_cbor`]
+    pub fn cid(&self) -> String {
+        cid_v1(&self.to_dag_cbor())
+    }
+}
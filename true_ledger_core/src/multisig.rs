@@ -0,0 +1,202 @@
+//! Multi-Signature Approval (m-of-n)
+//! A transaction can name a signing policy: the DIDs allowed to approve it
+//! and how many of them must. The policy lives in the payload, so every
+//! signature covers it and it cannot be swapped for a weaker one. Approvals
+//! beyond the author's own signature travel in the envelope as cosignatures
+//! over the same payload hash.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::keys::SigAlg;
+use crate::model::{SignedTransaction, Transaction};
+use crate::signer::TransactionSigner;
+use crate::verify::{balance_totals, verify_signature_by};
+
+/// "`threshold` of these `signers` must sign"
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct SigningPolicy {
+    pub threshold: u32,
+    pub signers: Vec<String>, // DIDs allowed to approve (the author may be one of them)
+}
+
+/// An approval by someone other than the author
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct Cosignature {
+    pub signer_did: String,
+    pub signature: String, // Multibase, like the author's signature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sig_alg: Option<SigAlg>,
+}
+
+impl SigningPolicy {
+    /// Rejects policies that can never (or too easily) be met
+    pub fn validate(&self) -> Result<(), LedgerError> {
+        let distinct: BTreeSet<&String> = self.signers.iter().collect();
+        if distinct.len() != self.signers.len() {
+            return Err(LedgerError::Approval("Signing policy lists a signer more than once".to_string()));
+        }
+        if self.threshold == 0 || self.threshold as usize > self.signers.len() {
+            return Err(LedgerError::Approval(format!("Signing policy threshold {} must be between 1 and {} (the number of signers)",
+                self.threshold, self.signers.len())));
+        }
+        Ok(())
+    }
+}
+
+/// Adds `signer`'s approval to an already signed transaction. The payload is
+/// not changed, so earlier signatures stay valid.
+pub fn cosign(signer: &dyn TransactionSigner, signed_tx: &mut SignedTransaction) -> Result<(), LedgerError> {
+    let did = signer.did().to_string();
+    if let Some(policy) = &signed_tx.payload.signing_policy {
+        if !policy.signers.contains(&did) {
+            return Err(LedgerError::Approval(format!("{} is not a signer under this transaction's signing policy", did)));
+        }
+    }
+    if did == signed_tx.payload.author_did || signed_tx.cosignatures.iter().any(|c| c.signer_did == did) {
+        return Err(LedgerError::Approval(format!("{} has already signed this transaction", did)));
+    }
+
+    // The payload already names its canonicalization, so this signs the same hash
+    let approval = signer.sign_transaction(signed_tx.payload.clone())?;
+    if approval.payload.get_hash() != signed_tx.payload.get_hash() {
+        return Err(LedgerError::Approval("Cosigner signed different bytes than the author".to_string()));
+    }
+    signed_tx.cosignatures.push(Cosignature {
+        signer_did: did,
+        signature: approval.signature,
+        sig_alg: approval.sig_alg,
+    });
+    Ok(())
+}
+
+/// Verifies every cosignature and returns the distinct DIDs that validly
+/// signed, the author first. Assumes the author's signature was checked.
+pub fn approvers(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<Vec<String>, LedgerError> {
+    let tx_hash = signed_tx.payload.get_hash();
+    let mut approvers = vec![signed_tx.payload.author_did.clone()];
+    for cosignature in &signed_tx.cosignatures {
+        if approvers.contains(&cosignature.signer_did) {
+            return Err(LedgerError::Approval(format!("{} signed this transaction more than once", cosignature.signer_did)));
+        }
+        verify_signature_by(&cosignature.signer_did, cosignature.sig_alg, &cosignature.signature, &tx_hash, resolver)
+            .map_err(|e| LedgerError::Approval(format!("Cosignature by {}: {}", cosignature.signer_did, e)))?;
+        approvers.push(cosignature.signer_did.clone());
+    }
+    Ok(approvers)
+}
+
+/// Checks every cosignature and, if the payload names a signing policy, that
+/// enough of its signers approved. Returns the number of approvals counted.
+pub fn verify_quorum(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<usize, LedgerError> {
+    let approvers = approvers(signed_tx, resolver)?;
+    if let Some(policy) = &signed_tx.payload.signing_policy {
+        policy.validate()?;
+        if let Some(outsider) = signed_tx.cosignatures.iter().find(|c| !policy.signers.contains(&c.signer_did)) {
+            return Err(LedgerError::Approval(format!("Cosignature by {}, who is not a signer under the signing policy", outsider.signer_did)));
+        }
+    }
+    check_policy(&signed_tx.payload, &approvers)
+}
+
+/// Checks the payload's signing policy, if any, against the DIDs whose
+/// signatures verified. Returns the number of approvals counted.
+pub fn check_policy(tx: &Transaction, approvers: &[String]) -> Result<usize, LedgerError> {
+    let Some(policy) = &tx.signing_policy else {
+        return Ok(approvers.len());
+    };
+    policy.validate()?;
+    let approvals = approvers.iter().filter(|did| policy.signers.contains(did)).count();
+    if approvals < policy.threshold as usize {
+        return Err(LedgerError::Approval(format!("Signing policy requires {} of {} approvals, found {}",
+            policy.threshold, policy.signers.len(), approvals)));
+    }
+    Ok(approvals)
+}
+
+/// Organisational rule: transactions moving more than `limit_cents` must
+/// require at least two approvals
+pub fn require_dual_approval(tx: &Transaction, limit_cents: i64) -> Result<(), LedgerError> {
+    let (total_cents, _) = balance_totals(tx)?;
+    if total_cents <= limit_cents {
+        return Ok(());
+    }
+    match &tx.signing_policy {
+        Some(policy) if policy.threshold >= 2 => Ok(()),
+        _ => Err(LedgerError::Approval("Transaction is above the dual-approval limit but does not require two approvals".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+
+    fn payment(author: &Account, amount: &str, policy: Option<SigningPolicy>) -> Transaction {
+        let builder = TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .author(&author.did)
+            .entry("68100", amount, "0.00")
+            .entry("10100", "0.00", amount)
+            .memo("Payment")
+            .genesis();
+        match policy {
+            Some(policy) => builder.signing_policy(policy),
+            None => builder,
+        }.build().unwrap()
+    }
+
+    fn two_of(signers: &[&Account]) -> SigningPolicy {
+        SigningPolicy { threshold: 2, signers: signers.iter().map(|account| account.did.clone()).collect() }
+    }
+
+    #[test]
+    fn a_quorum_counts_only_the_policys_signers() {
+        let (author, approver, outsider) = (Account::generate(), Account::generate(), Account::generate());
+        let mut signed_tx = author.sign(payment(&author, "10.00", Some(two_of(&[&author, &approver]))));
+        assert!(verify_quorum(&signed_tx, &DidKeyResolver).is_err());
+        assert!(cosign(&outsider, &mut signed_tx).is_err());
+        assert!(cosign(&author, &mut signed_tx).is_err());
+        cosign(&approver, &mut signed_tx).unwrap();
+        assert_eq!(verify_quorum(&signed_tx, &DidKeyResolver).unwrap(), 2);
+        assert!(cosign(&approver, &mut signed_tx).is_err());
+
+        // A cosignature over other bytes does not count
+        signed_tx.cosignatures[0].signature = author.sign(payment(&author, "10.01", None)).signature;
+        assert!(verify_quorum(&signed_tx, &DidKeyResolver).is_err());
+    }
+
+    #[test]
+    fn policies_that_cannot_be_met_are_invalid() {
+        let account = Account::generate();
+        assert!(SigningPolicy { threshold: 0, signers: vec![account.did.clone()] }.validate().is_err());
+        assert!(SigningPolicy { threshold: 2, signers: vec![account.did.clone()] }.validate().is_err());
+        assert!(two_of(&[&account, &account]).validate().is_err());
+    }
+
+    #[test]
+    fn dual_approval_is_required_above_the_limit() {
+        let (author, approver) = (Account::generate(), Account::generate());
+        require_dual_approval(&payment(&author, "500.00", None), 50_000).unwrap();
+        assert!(matches!(require_dual_approval(&payment(&author, "500.01", None), 50_000), Err(LedgerError::Approval(_))));
+        require_dual_approval(&payment(&author, "500.01", Some(two_of(&[&author, &approver]))), 50_000).unwrap();
+    }
+
+    #[test]
+    fn negative_or_overflowing_debits_cannot_slip_under_the_limit() {
+        let author = Account::generate();
+        let mut negative = payment(&author, "1000.00", None);
+        negative.entries.push(negative.entries[0].clone());
+        negative.entries[2].debit = "-999.00".to_string();
+        assert!(matches!(require_dual_approval(&negative, 50_000), Err(LedgerError::Amount(_))));
+
+        let mut overflow = payment(&author, "92233720368547758.07", None);
+        overflow.entries[1] = overflow.entries[0].clone();
+        assert!(matches!(require_dual_approval(&overflow, 50_000), Err(LedgerError::Amount(_))));
+    }
+}
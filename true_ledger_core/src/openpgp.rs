@@ -0,0 +1,299 @@
+//! OpenPGP Envelope
+//! For organizations whose keys already live in GPG: the transaction's JCS
+//! form is signed with an OpenPGP Ed25519 key (v4 EdDSA, as GnuPG makes, or
+//! v6 Ed25519), and the envelope carries the armored detached signature
+//! beside the payload with a reference to the key, its fingerprint and
+//! creation time:
+//!
+//! ```json
+//! { "payload": { ... },
+//!   "openpgp": { "signature": "-----BEGIN PGP SIGNATURE-----...",
+//!                "fingerprint": "8F1B...", "key_created": 1700000000 } }
+//! ```
+//!
+//! The author is the did:key of that same Ed25519 key, so ACLs and signing
+//! policies name it like any other signer. A verifier rebuilds the key packet
+//! from the author's DID and the creation time, and accepts the signature
+//! only if the rebuilt key has the stated fingerprint: the one a GPG keyring
+//! or keyserver knows the key by.
+//!
+//! Built only with the `openpgp` feature.
+
+use std::io::Write;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ed25519_dalek::PublicKey;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use sequoia_openpgp as pgp;
+use pgp::armor;
+use pgp::crypto::mpi;
+use pgp::packet::key::{Key4, Key6, PublicParts, UnspecifiedRole};
+use pgp::packet::signature::SignatureBuilder;
+use pgp::packet::Key;
+use pgp::parse::Parse;
+use pgp::policy::{HashAlgoSecurity, Policy, StandardPolicy};
+use pgp::serialize::MarshalInto;
+use pgp::types::{Curve, SignatureType};
+use pgp::Packet;
+pub use pgp::crypto::Password;
+pub use pgp::Cert;
+
+use crate::canonical::to_jcs;
+use crate::did::{did_from_public_key, DidResolver};
+use crate::error::LedgerError;
+use crate::keys::VerifyingKey;
+use crate::model::Transaction;
+
+/// The key an OpenPGP signature was made with
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OpenPgpSignature {
+    pub signature: String,   // ASCII-armored detached signature over the payload's JCS form
+    pub fingerprint: String, // Upper-case hex: 40 digits for a v4 key, 64 for v6
+    pub key_created: u64,    // Unix seconds, part of what the fingerprint hashes
+}
+
+/// A transaction in the OpenPGP envelope
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenPgpTransaction {
+    pub payload: Transaction,
+    pub openpgp: OpenPgpSignature,
+}
+
+fn pgp_error(what: &str, e: impl std::fmt::Display) -> LedgerError {
+    LedgerError::Key(format!("{}: {}", what, e))
+}
+
+/// The Ed25519 point of an OpenPGP key, if it is one
+fn ed25519_bytes(key: &Key<PublicParts, UnspecifiedRole>) -> Option<[u8; 32]> {
+    match key.mpis() {
+        mpi::PublicKey::EdDSA { curve: Curve::Ed25519, q } => q.decode_point(&Curve::Ed25519).ok()?.0.try_into().ok(),
+        mpi::PublicKey::Ed25519 { a } => Some(*a),
+        _ => None,
+    }
+}
+
+/// The did:key of an OpenPGP Ed25519 key
+pub fn key_did(key: &Key<PublicParts, UnspecifiedRole>) -> Result<String, LedgerError> {
+    let bytes = ed25519_bytes(key)
+        .ok_or_else(|| LedgerError::Key(format!("OpenPGP key {} is not an Ed25519 key", key.fingerprint())))?;
+    let public = PublicKey::from_bytes(&bytes).map_err(|e| pgp_error("Invalid OpenPGP Ed25519 key", e))?;
+    Ok(did_from_public_key(&public))
+}
+
+/// The signing key of `cert` (a primary key or subkey valid for signing
+/// now), with its DID
+pub fn signing_key(cert: &Cert) -> Result<(Key<PublicParts, UnspecifiedRole>, String), LedgerError> {
+    let policy = StandardPolicy::new();
+    let key = cert.keys().with_policy(&policy, None).supported().alive().revoked(false).for_signing()
+        .map(|ka| ka.key().clone().role_into_unspecified())
+        .find(|key| ed25519_bytes(key).is_some())
+        .ok_or_else(|| LedgerError::Key(format!("OpenPGP certificate {} has no usable Ed25519 signing key", cert.fingerprint())))?;
+    let did = key_did(&key)?;
+    Ok((key, did))
+}
+
+/// Reads a certificate with its secret keys, armored or binary
+pub fn load_tsk(data: &[u8]) -> Result<Cert, LedgerError> {
+    let cert = Cert::from_bytes(data).map_err(|e| pgp_error("Not an OpenPGP key file", e))?;
+    if !cert.is_tsk() {
+        return Err(LedgerError::Key(format!("OpenPGP certificate {} holds no secret keys (export it with --export-secret-keys)",
+            cert.fingerprint())));
+    }
+    Ok(cert)
+}
+
+/// Whether the secret part of the signing key of `tsk` is passphrase-protected
+pub fn needs_password(tsk: &Cert) -> Result<bool, LedgerError> {
+    let (public, _) = signing_key(tsk)?;
+    Ok(tsk.keys().secret().key_handle(public.fingerprint()).next()
+        .is_some_and(|ka| !ka.key().has_unencrypted_secret()))
+}
+
+/// Signs `tx` with the signing key of `tsk`, a certificate with its secret
+/// keys (`gpg --export-secret-keys --armor`), decrypting the key with
+/// `password` if it is protected. The author must be that key's DID.
+pub fn sign_openpgp(tx: Transaction, tsk: &Cert, password: Option<&Password>) -> Result<OpenPgpTransaction, LedgerError> {
+    let tx = tx.prepare_for_signing();
+    let (public, did) = signing_key(tsk)?;
+    if tx.author_did != did {
+        return Err(LedgerError::Key(format!("Transaction names author {} but the OpenPGP signing key is {}", tx.author_did, did)));
+    }
+    let secret = tsk.keys().secret().key_handle(public.fingerprint()).next()
+        .ok_or_else(|| LedgerError::Key(format!("The secret part of OpenPGP key {} is not in the file", public.fingerprint())))?
+        .key().clone().role_into_unspecified();
+    let secret = match (secret.has_unencrypted_secret(), password) {
+        (true, _) => secret,
+        (false, Some(password)) => secret.decrypt_secret(password)
+            .map_err(|_| LedgerError::Key(format!("Wrong passphrase for OpenPGP key {}", public.fingerprint())))?,
+        (false, None) => return Err(LedgerError::Key(format!("OpenPGP key {} is passphrase-protected", public.fingerprint()))),
+    };
+    let mut keypair = secret.into_keypair().map_err(|e| pgp_error("Unusable OpenPGP key", e))?;
+
+    let signature = SignatureBuilder::new(SignatureType::Binary)
+        .sign_message(&mut keypair, to_jcs(&tx)?)
+        .map_err(|e| pgp_error("OpenPGP signing failed", e))?;
+    let mut armored = armor::Writer::new(Vec::new(), armor::Kind::Signature).map_err(|e| pgp_error("Armoring failed", e))?;
+    let packet = Packet::from(signature).to_vec().map_err(|e| pgp_error("Serializing the signature failed", e))?;
+    armored.write_all(&packet).map_err(|e| pgp_error("Armoring failed", e))?;
+    let armored = armored.finalize().map_err(|e| pgp_error("Armoring failed", e))?;
+
+    let key_created = public.creation_time().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    Ok(OpenPgpTransaction {
+        payload: tx,
+        openpgp: OpenPgpSignature {
+            signature: String::from_utf8_lossy(&armored).into_owned(),
+            fingerprint: public.fingerprint().to_hex(),
+            key_created,
+        },
+    })
+}
+
+impl OpenPgpTransaction {
+    pub fn from_value(document: Value) -> Result<Self, LedgerError> {
+        serde_json::from_value(document)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to parse OpenPGP transaction: {}", e)))
+    }
+
+    /// The author's key as an OpenPGP key packet created at `key_created`,
+    /// in the version the fingerprint's length names
+    fn rebuild_key(&self, key: &VerifyingKey) -> Result<Key<PublicParts, UnspecifiedRole>, LedgerError> {
+        let VerifyingKey::Ed25519(public) = key else {
+            return Err(LedgerError::Signature(format!("The author {} does not have an Ed25519 key, so cannot sign with OpenPGP",
+                self.payload.author_did)));
+        };
+        let created = UNIX_EPOCH + Duration::from_secs(self.openpgp.key_created);
+        let key: Key<PublicParts, UnspecifiedRole> = match self.openpgp.fingerprint.len() {
+            40 => Key4::import_public_ed25519(public.as_bytes(), created).map(Key::from),
+            64 => Key6::import_public_ed25519(public.as_bytes(), created).map(Key::from),
+            _ => return Err(LedgerError::Signature(format!("{} is not an OpenPGP v4 or v6 fingerprint", self.openpgp.fingerprint))),
+        }.map_err(|e| pgp_error("Rebuilding the OpenPGP key failed", e))?;
+        if !key.fingerprint().to_hex().eq_ignore_ascii_case(&self.openpgp.fingerprint) {
+            return Err(LedgerError::Signature(format!("OpenPGP key {} is not the author's key {}",
+                self.openpgp.fingerprint, self.payload.author_did)));
+        }
+        Ok(key)
+    }
+
+    /// Checks that the fingerprint names the author's key and the detached
+    /// signature is by it, over the payload
+    pub fn verify_signature(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let key = self.rebuild_key(&resolver.resolve_public_key(&self.payload.author_did)?)?;
+        let invalid = |e: pgp::anyhow::Error| LedgerError::Signature(format!("Invalid OpenPGP signature: {}", e));
+        let signature = match Packet::from_bytes(self.openpgp.signature.as_bytes()).map_err(invalid)? {
+            Packet::Signature(signature) => signature,
+            packet => return Err(LedgerError::Signature(format!("Expected an OpenPGP signature, found a {} packet", packet.tag()))),
+        };
+        if signature.typ() != SignatureType::Binary {
+            return Err(LedgerError::Signature(format!("An OpenPGP {} signature does not sign a transaction", signature.typ())));
+        }
+        if signature.get_issuers().iter().any(|issuer| !issuer.aliases(key.key_handle())) {
+            return Err(LedgerError::Signature(format!("The OpenPGP signature names an issuer other than {}", self.openpgp.fingerprint)));
+        }
+        StandardPolicy::new().signature(&signature, HashAlgoSecurity::CollisionResistance).map_err(invalid)?;
+        if signature.signature_creation_time().is_some_and(|t| t < key.creation_time() || t > SystemTime::now() + Duration::from_secs(300)) {
+            return Err(LedgerError::Signature("The OpenPGP signature is dated before its key or in the future".to_string()));
+        }
+        signature.verify_message(&key, to_jcs(&self.payload)?).map_err(invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::signed;
+    use pgp::cert::{CertBuilder, CipherSuite};
+    use pgp::Profile;
+
+    fn tsk(profile: Profile) -> Cert {
+        CertBuilder::new().set_cipher_suite(CipherSuite::Cv25519).set_profile(profile).unwrap()
+            .add_userid("Controller <controller@example.com>").add_signing_subkey()
+            .generate().unwrap().0
+    }
+
+    /// An unsigned transaction by the signing key of `cert`
+    fn transaction_for(cert: &Cert) -> Transaction {
+        let mut tx = signed(&Account::generate(), None, 0, "Opening").payload;
+        tx.author_did = signing_key(cert).unwrap().1;
+        tx
+    }
+
+    #[test]
+    fn v4_and_v6_keys_round_trip() {
+        for (profile, digits) in [(Profile::RFC4880, 40), (Profile::RFC9580, 64)] {
+            let cert = tsk(profile);
+            let signed = sign_openpgp(transaction_for(&cert), &cert, None).unwrap();
+            assert_eq!(signed.openpgp.fingerprint.len(), digits);
+            assert!(!needs_password(&cert).unwrap());
+            assert!(signed.openpgp.signature.starts_with("-----BEGIN PGP SIGNATURE-----"));
+            let parsed = OpenPgpTransaction::from_value(serde_json::to_value(&signed).unwrap()).unwrap();
+            parsed.verify_signature(&DidKeyResolver).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_protected_key_needs_its_passphrase() {
+        let password = Password::from("correct horse");
+        let cert = CertBuilder::new().set_cipher_suite(CipherSuite::Cv25519).set_password(Some(password.clone()))
+            .add_userid("Controller").add_signing_subkey().generate().unwrap().0;
+        assert!(needs_password(&cert).unwrap());
+        assert!(sign_openpgp(transaction_for(&cert), &cert, None).is_err());
+        assert!(sign_openpgp(transaction_for(&cert), &cert, Some(&Password::from("wrong"))).is_err());
+        sign_openpgp(transaction_for(&cert), &cert, Some(&password)).unwrap().verify_signature(&DidKeyResolver).unwrap();
+    }
+
+    #[test]
+    fn only_a_secret_key_file_is_loaded() {
+        let cert = tsk(Profile::RFC4880);
+        let armored = MarshalInto::to_vec(&cert.as_tsk().armored()).unwrap();
+        assert_eq!(load_tsk(&armored).unwrap().fingerprint(), cert.fingerprint());
+        assert!(load_tsk(&MarshalInto::to_vec(&cert.armored()).unwrap()).is_err()); // The public certificate alone
+        assert!(load_tsk(b"not a key").is_err());
+    }
+
+    #[test]
+    fn a_foreign_author_is_refused_at_signing() {
+        let tx = signed(&Account::generate(), None, 0, "Opening").payload;
+        assert!(matches!(sign_openpgp(tx, &tsk(Profile::RFC4880), None), Err(LedgerError::Key(_))));
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let cert = tsk(Profile::RFC4880);
+        let signed = sign_openpgp(transaction_for(&cert), &cert, None).unwrap();
+
+        let mut other = signed.clone();
+        other.payload.memo = "Opening balance".to_string();
+        assert!(matches!(other.verify_signature(&DidKeyResolver), Err(LedgerError::Signature(_))));
+
+        // The same key, claimed to be created at another time, has another fingerprint
+        let mut other = signed.clone();
+        other.openpgp.key_created += 1;
+        assert!(other.verify_signature(&DidKeyResolver).is_err());
+
+        // Another key's fingerprint and signature over the same payload
+        let stranger = tsk(Profile::RFC4880);
+        let mut forged = sign_openpgp(transaction_for(&stranger), &stranger, None).unwrap();
+        forged.payload = signed.payload.clone();
+        assert!(forged.verify_signature(&DidKeyResolver).is_err());
+        forged.openpgp.fingerprint = signed.openpgp.fingerprint.clone();
+        forged.openpgp.key_created = signed.openpgp.key_created;
+        assert!(forged.verify_signature(&DidKeyResolver).is_err());
+
+        let mut garbled = signed.clone();
+        garbled.openpgp.signature = "-----BEGIN PGP SIGNATURE-----\n\n-----END PGP SIGNATURE-----\n".to_string();
+        assert!(garbled.verify_signature(&DidKeyResolver).is_err());
+    }
+
+    #[test]
+    fn a_secp256k1_author_cannot_use_openpgp() {
+        let account = Account::generate_for(crate::keys::SigAlg::Secp256k1);
+        let cert = tsk(Profile::RFC4880);
+        let mut signed = sign_openpgp(transaction_for(&cert), &cert, None).unwrap();
+        signed.payload.author_did = account.did;
+        assert!(signed.verify_signature(&DidKeyResolver).err().unwrap().to_string().contains("Ed25519"));
+    }
+}
@@ -0,0 +1,191 @@
+//! Period Close
+//! Closing the books is a signed statement by whoever finalises them that
+//! nothing more is posted on or before `closed_through`. A verifier given
+//! the close rejects transactions dated in the closed period, so finalised
+//! books cannot be changed by back-dating. The only exception is an
+//! adjusting entry (`"adjusting": true`, which the author signs) by one of
+//! the close's `adjusters`, e.g. the auditor's year-end adjustments.
+//! Closing through period 12 of a fiscal year rather than the whole year
+//! leaves period 13 open: adjusting entries dated on the year's last day are
+//! still accepted from anyone (see fiscal.rs).
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::canonical::to_jcs;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::fiscal::Cutoff;
+use crate::model::Transaction;
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+use crate::validation::ValidationRule;
+use crate::verify::decode_signature;
+
+/// Prefixed to the signed bytes so a close signature can never be replayed
+/// as any other kind of signature
+const SIGNING_CONTEXT: &[u8] = b"true-ledger period close v1\n";
+
+/// What the closer signs
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PeriodClose {
+    pub closed_by: String,
+    pub closed_through: NaiveDate,   // Last day of the closed period, in each transaction's own time zone
+    #[serde(default)]
+    pub adjusters: Vec<String>,      // DIDs that may still post adjusting entries into it
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub adjusting_period_open: bool, // Period 13 (adjusting entries dated `closed_through`) is not closed
+    pub timestamp: Timestamp,        // When the books were closed
+}
+
+/// A period close and the closer's signature over it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedPeriodClose {
+    pub close: PeriodClose,
+    pub signature: String, // Multibase (base58btc)
+}
+
+impl PeriodClose {
+    /// A close of everything `through` includes
+    pub fn new(closed_by: &str, through: Cutoff, adjusters: Vec<String>, timestamp: Timestamp) -> Self {
+        PeriodClose {
+            closed_by: closed_by.to_string(),
+            closed_through: through.date,
+            adjusters,
+            adjusting_period_open: !through.adjustments,
+            timestamp,
+        }
+    }
+
+    /// The exact bytes the closer signs: a context string, then the JCS form
+    fn signing_input(&self) -> Result<Vec<u8>, LedgerError> {
+        let mut input = SIGNING_CONTEXT.to_vec();
+        input.extend_from_slice(to_jcs(self)?.as_bytes());
+        Ok(input)
+    }
+
+    /// Signs the close. `closed_by` must be the signer's own DID.
+    pub fn sign(self, signer: &dyn TransactionSigner) -> Result<SignedPeriodClose, LedgerError> {
+        if self.closed_by != signer.did() {
+            return Err(LedgerError::Key(format!("The close names {} but the signing key is {}", self.closed_by, signer.did())));
+        }
+        let signature = signer.sign_bytes(&self.signing_input()?)?;
+        Ok(SignedPeriodClose {
+            close: self,
+            signature: multibase::encode(multibase::Base::Base58Btc, signature),
+        })
+    }
+
+    /// Rejects a transaction dated in the closed period, unless it is an
+    /// adjusting entry by one of the adjusters
+    pub fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let date = tx.timestamp.local_date();
+        if date > self.closed_through {
+            return Ok(());
+        }
+        if tx.adjusting != Some(true) {
+            return Err(LedgerError::Timestamp(format!("Dated {}, but {} closed the books through {}",
+                date, self.closed_by, self.closed_through)));
+        }
+        if date == self.closed_through && self.adjusting_period_open {
+            return Ok(());
+        }
+        if !self.adjusters.contains(&tx.author_did) {
+            return Err(LedgerError::Approval(format!("{} may not post adjusting entries into the period closed through {}",
+                tx.author_did, self.closed_through)));
+        }
+        Ok(())
+    }
+}
+
+impl SignedPeriodClose {
+    /// Reads a signed close from a JSON file (its signature is not checked)
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read period close {}: {}", path, e)))?;
+        serde_json::from_str(&data).map_err(|e| LedgerError::Serialization(format!("Invalid period close {}: {}", path, e)))
+    }
+
+    /// Checks the closer's signature
+    pub fn verify(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let key = resolver.resolve_public_key(&self.close.closed_by)?;
+        key.verify_bytes(&self.close.signing_input()?, &decode_signature(&self.signature)?)
+    }
+}
+
+/// A verified close as a posting rule
+impl ValidationRule for SignedPeriodClose {
+    fn name(&self) -> &str {
+        "period_close"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        self.close.check(tx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+
+    fn year_end() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 12, 31).unwrap()
+    }
+
+    fn posting(author: &Account, date: &str, adjusting: bool) -> Transaction {
+        let builder = TransactionBuilder::new()
+            .timestamp(Timestamp::parse_rfc3339(date).unwrap())
+            .author(&author.did)
+            .entry("68100", "10.00", "0.00")
+            .entry("10100", "0.00", "10.00")
+            .memo("Posting")
+            .genesis();
+        if adjusting { builder.adjusting() } else { builder }.build().unwrap()
+    }
+
+    fn close(closer: &Account, adjustments: bool, adjusters: &[&Account]) -> PeriodClose {
+        let adjusters = adjusters.iter().map(|account| account.did.clone()).collect();
+        PeriodClose::new(&closer.did, Cutoff { date: year_end(), adjustments }, adjusters, Timestamp::Unix(1_736_000_000))
+    }
+
+    #[test]
+    fn the_closers_signature_covers_the_close() {
+        let (closer, other) = (Account::generate(), Account::generate());
+        assert!(close(&closer, true, &[]).sign(&other).is_err());
+        let signed = close(&closer, true, &[]).sign(&closer).unwrap();
+        signed.verify(&DidKeyResolver).unwrap();
+
+        let mut moved = signed.clone();
+        moved.close.closed_through = NaiveDate::from_ymd_opt(2024, 6, 30).unwrap();
+        assert!(moved.verify(&DidKeyResolver).is_err());
+        let mut widened = signed;
+        widened.close.adjusters.push(other.did.clone());
+        assert!(widened.verify(&DidKeyResolver).is_err());
+    }
+
+    #[test]
+    fn only_adjusters_post_into_a_closed_period() {
+        let (closer, auditor, clerk) = (Account::generate(), Account::generate(), Account::generate());
+        let close = close(&closer, true, &[&auditor]);
+        assert!(close.check(&posting(&clerk, "2025-01-01T00:00:00+01:00", false)).is_ok());
+        // The entry's own offset decides its day: still 31 December there
+        assert!(close.check(&posting(&clerk, "2025-01-01T02:00:00+05:00", false)).is_ok());
+        assert!(close.check(&posting(&clerk, "2024-12-31T23:00:00-05:00", false)).is_err());
+        assert!(close.check(&posting(&clerk, "2024-06-30T12:00:00Z", true)).is_err());
+        assert!(close.check(&posting(&auditor, "2024-06-30T12:00:00Z", false)).is_err());
+        assert!(close.check(&posting(&auditor, "2024-06-30T12:00:00Z", true)).is_ok());
+    }
+
+    #[test]
+    fn closing_through_period_12_leaves_year_end_adjustments_open() {
+        let (closer, clerk) = (Account::generate(), Account::generate());
+        let close = close(&closer, false, &[]);
+        assert!(close.adjusting_period_open);
+        assert!(close.check(&posting(&clerk, "2024-12-31T18:00:00Z", true)).is_ok());
+        assert!(close.check(&posting(&clerk, "2024-12-31T18:00:00Z", false)).is_err());
+        assert!(close.check(&posting(&clerk, "2024-12-30T18:00:00Z", true)).is_err());
+    }
+}
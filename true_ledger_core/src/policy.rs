@@ -0,0 +1,281 @@
+//! Posting Policy Documents
+//! A ledger's posting policy (who may post where, amount limits, approvals:
+//! a rules file, see validation.rs) can live in the ledger itself. A policy
+//! transaction is an ordinary signed transaction (no entries) whose payload
+//! carries a `policy`: the rules file's text, a version number and when it
+//! takes effect. It is chained, sequenced and stored like any posting, so
+//! the policy's history is exactly as tamper-evident as the books it governs.
+//! Replaying a ledger's policy transactions into a [`PolicyHistory`] gives
+//! every version ever adopted:
+//!
+//! - versions count up from 1, one at a time
+//! - version 1 may be adopted by anyone who may post; each later version
+//!   only by one of the `administrators` of the version before
+//! - a version takes effect no earlier than it was signed and no earlier
+//!   than the version before, so a policy never applies retroactively
+//!
+//! A policy is self-contained: its rules may not name a chart, fiscal
+//! calendar or script file, which the ledger could not vouch for.
+//!
+//! A transaction is judged by the version in force at its own timestamp,
+//! not the latest one (see [`HistoricalPolicy`]), so tightening the policy
+//! never makes an archived transaction fail re-verification.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::acl::expand_groups;
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::timestamp::Timestamp;
+use crate::validation::{RulesConfig, ValidationPipeline, ValidationRule};
+
+/// What a policy transaction adopts
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    pub version: u64,                // 1, then one more for each new version
+    pub effective_from: Timestamp,   // In force for transactions dated from then on
+    pub administrators: Vec<String>, // DIDs or the rules' group names that may adopt the next version
+    pub rules: String,               // The text of a rules file (see validation.rs)
+}
+
+impl Policy {
+    /// The rules, refusing references to files outside the ledger
+    pub fn rules_config(&self) -> Result<RulesConfig, LedgerError> {
+        let config = RulesConfig::parse(&self.rules)?;
+        let outside = [
+            config.chart.as_ref().map(|_| "chart"),
+            config.fiscal.as_ref().map(|_| "fiscal"),
+            config.script.first().map(|_| "[[script]]"),
+        ];
+        if let Some(key) = outside.into_iter().flatten().next() {
+            return Err(LedgerError::Config(format!("A policy must be self-contained, but its rules name a {} file", key)));
+        }
+        Ok(config)
+    }
+
+    /// The DIDs that may adopt the next version
+    pub fn administrator_dids(&self) -> Result<Vec<String>, LedgerError> {
+        expand_groups(&self.administrators, &self.rules_config()?.groups).map_err(|e| e.context("administrators"))
+    }
+}
+
+/// A version as adopted in the ledger
+#[derive(Debug, Clone)]
+pub struct AdoptedPolicy {
+    pub policy: Policy,
+    pub adopted_by: String,      // Author DID of the policy transaction
+    pub adopted_at: Timestamp,   // Its timestamp
+    pub tx_hash: String,         // Its payload hash
+    administrators: Vec<String>, // DIDs
+}
+
+impl AdoptedPolicy {
+    /// The version's rules, ready to run
+    pub fn pipeline(&self) -> Result<ValidationPipeline, LedgerError> {
+        ValidationPipeline::from_config(&self.policy.rules_config()?)
+    }
+}
+
+/// Every policy version a ledger adopted, oldest first, built from verified
+/// policy transactions
+#[derive(Debug, Default)]
+pub struct PolicyHistory {
+    versions: Vec<AdoptedPolicy>,
+}
+
+impl PolicyHistory {
+    pub fn new() -> Self {
+        PolicyHistory::default()
+    }
+
+    /// Builds a history from transactions that have already been verified,
+    /// in chain order; those without a policy are skipped
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I) -> Result<Self, LedgerError> {
+        let mut history = PolicyHistory::new();
+        for tx in txs {
+            history.apply(tx)?;
+        }
+        Ok(history)
+    }
+
+    pub fn versions(&self) -> &[AdoptedPolicy] {
+        &self.versions
+    }
+
+    /// The latest version adopted, whether or not it is in force yet
+    pub fn latest(&self) -> Option<&AdoptedPolicy> {
+        self.versions.last()
+    }
+
+    /// The version in force at `at` (Unix seconds): the latest to take
+    /// effect at or before it. None before the first takes effect.
+    pub fn in_force_at(&self, at: u64) -> Option<&AdoptedPolicy> {
+        self.versions.iter().rev().find(|adopted| adopted.policy.effective_from.unix() <= at)
+    }
+
+    /// Every version's rules, compiled, to judge transactions by the version of their day
+    pub fn compile(&self) -> Result<HistoricalPolicy, LedgerError> {
+        let versions = self.versions.iter()
+            .map(|adopted| Ok((adopted.policy.effective_from.unix(), adopted.policy.version, adopted.pipeline()?)))
+            .collect::<Result<_, LedgerError>>()?;
+        Ok(HistoricalPolicy { versions })
+    }
+
+    /// Records `tx`'s policy, if it has one. Its signature must already have
+    /// been verified; this checks the author was entitled to adopt it and
+    /// that its rules are valid.
+    pub fn apply(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
+        let Some(policy) = &tx.policy else { return Ok(()) };
+        if !tx.entries.is_empty() {
+            return Err(LedgerError::Config("A policy transaction must not post entries".to_string()));
+        }
+        let expected = self.latest().map_or(1, |latest| latest.policy.version + 1);
+        if policy.version != expected {
+            return Err(LedgerError::Config(format!("Policy version {} is out of turn: the next version is {}", policy.version, expected)));
+        }
+        if let Some(latest) = self.latest() {
+            if !latest.administrators.contains(&tx.author_did) {
+                return Err(LedgerError::Config(format!("{} may not adopt policy version {}: not an administrator of version {}",
+                    tx.author_did, policy.version, latest.policy.version)));
+            }
+            if policy.effective_from.unix() < latest.policy.effective_from.unix() {
+                return Err(LedgerError::Config(format!("Policy version {} would take effect at {}, before version {} does",
+                    policy.version, policy.effective_from, latest.policy.version)));
+            }
+        }
+        if policy.effective_from.unix() < tx.timestamp.unix() {
+            return Err(LedgerError::Config(format!("Policy version {} would take effect at {}, before it was signed at {}",
+                policy.version, policy.effective_from, tx.timestamp)));
+        }
+        let administrators = policy.administrator_dids()?;
+        ValidationPipeline::from_config(&policy.rules_config()?).map_err(|e| e.context(format!("Policy version {}", policy.version)))?;
+        self.versions.push(AdoptedPolicy {
+            policy: policy.clone(),
+            adopted_by: tx.author_did.clone(),
+            adopted_at: tx.timestamp.clone(),
+            tx_hash: tx.hash_hex(),
+            administrators,
+        });
+        Ok(())
+    }
+}
+
+/// Checks each transaction against the policy version in force at its
+/// timestamp. Transactions dated before the first version takes effect, and
+/// policy transactions themselves, pass.
+pub struct HistoricalPolicy {
+    versions: Vec<(u64, u64, ValidationPipeline)>, // Effective from (Unix seconds), version, its rules; oldest first
+}
+
+impl ValidationRule for HistoricalPolicy {
+    fn name(&self) -> &str {
+        "policy"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        if tx.policy.is_some() {
+            return Ok(());
+        }
+        let at = tx.timestamp.unix();
+        match self.versions.iter().rev().find(|(effective_from, _, _)| *effective_from <= at) {
+            Some((_, version, rules)) => rules.validate(tx).map_err(|e| e.context(format!("Policy version {}", version))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+
+    /// A policy transaction by `author` at `at` (the builder insists on entries)
+    fn adopt(author: &Account, at: u64, policy: Policy) -> Transaction {
+        let mut tx = TransactionBuilder::new()
+            .timestamp(at)
+            .entry("10100", "1.00", "0.00")
+            .entry("30000", "0.00", "1.00")
+            .memo("Posting policy")
+            .genesis()
+            .sign(author)
+            .unwrap()
+            .payload;
+        tx.entries.clear();
+        tx.policy = Some(policy);
+        tx
+    }
+
+    fn policy(version: u64, effective_from: u64, administrators: &[&str], rules: &str) -> Policy {
+        Policy {
+            version,
+            effective_from: effective_from.into(),
+            administrators: administrators.iter().map(|admin| admin.to_string()).collect(),
+            rules: rules.to_string(),
+        }
+    }
+
+    #[test]
+    fn versions_are_adopted_in_turn_by_the_administrators() {
+        let controller = Account::generate();
+        let clerk = Account::generate();
+        let admins = format!("[groups]\nfinance = [\"{}\"]\n", controller.did);
+        let mut history = PolicyHistory::new();
+        history.apply(&adopt(&clerk, 100, policy(1, 100, &["finance"], &admins))).unwrap();
+
+        let by_clerk = adopt(&clerk, 200, policy(2, 200, &["finance"], &admins));
+        assert!(history.apply(&by_clerk).unwrap_err().to_string().contains("not an administrator"));
+        let skipped = adopt(&controller, 200, policy(3, 200, &["finance"], &admins));
+        assert!(history.apply(&skipped).unwrap_err().to_string().contains("out of turn"));
+        let backdated = adopt(&controller, 200, policy(2, 150, &["finance"], &admins));
+        assert!(history.apply(&backdated).unwrap_err().to_string().contains("before it was signed"));
+
+        history.apply(&adopt(&controller, 200, policy(2, 300, &["finance"], &admins))).unwrap();
+        assert_eq!(history.versions().len(), 2);
+        assert_eq!(history.latest().unwrap().adopted_by, controller.did);
+    }
+
+    #[test]
+    fn transactions_are_judged_by_the_version_of_their_day() {
+        let controller = Account::generate();
+        let did = controller.did.clone();
+        let history = PolicyHistory::from_transactions(&[
+            adopt(&controller, 100, policy(1, 100, &[&did], "[[amount_limit]]\nmax = \"500.00\"")),
+            adopt(&controller, 200, policy(2, 300, &[&did], "[[amount_limit]]\nmax = \"100.00\"")),
+        ]).unwrap();
+        assert!(history.in_force_at(99).is_none());
+        assert_eq!(history.in_force_at(299).unwrap().policy.version, 1);
+        assert_eq!(history.in_force_at(300).unwrap().policy.version, 2);
+
+        let rule = history.compile().unwrap();
+        let posting = |at: u64| TransactionBuilder::new()
+            .timestamp(at)
+            .entry("60000", "250.00", "0.00")
+            .entry("10100", "0.00", "250.00")
+            .memo("Supplies")
+            .genesis()
+            .sign(&controller)
+            .unwrap()
+            .payload;
+        assert!(rule.check(&posting(50)).is_ok());
+        assert!(rule.check(&posting(250)).is_ok(), "archived under version 1, which allowed it");
+        assert!(rule.check(&posting(300)).unwrap_err().to_string().starts_with("Policy version 2"));
+    }
+
+    #[test]
+    fn a_policy_must_be_self_contained_and_valid() {
+        let author = Account::generate();
+        let did = author.did.clone();
+        let chart = adopt(&author, 100, policy(1, 100, &[&did], "chart = \"chart.toml\""));
+        assert!(PolicyHistory::new().apply(&chart).unwrap_err().to_string().contains("self-contained"));
+        let bad_limit = adopt(&author, 100, policy(1, 100, &[&did], "[[amount_limit]]\nmax = \"lots\""));
+        assert!(PolicyHistory::new().apply(&bad_limit).is_err());
+        let nobody = adopt(&author, 100, policy(1, 100, &["finance"], ""));
+        assert!(PolicyHistory::new().apply(&nobody).is_err());
+
+        let limit = adopt(&author, 100, policy(1, 100, &[&did], "[[amount_limit]]\nmax = \"100.00\""));
+        let history = PolicyHistory::from_transactions([&limit]).unwrap();
+        assert_eq!(history.latest().unwrap().pipeline().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,190 @@
+//! Verifiable Presentation of Financial Statements
+//! A statement (a trial balance, balance sheet or counterparty statement)
+//! leaves the ledger as a W3C Verifiable Presentation that standard VC
+//! verifiers can check: the preparer issues a `FinancialStatement`
+//! credential whose subject is the statement, the digest of the trial
+//! balance it was built from (see trial_balance.rs) and an inclusion proof
+//! for each underlying transaction in the journal's Merkle tree (see
+//! merkle.rs), then presents it as holder.
+//! Both proofs are eddsa-jcs-2022 (see data_integrity.rs): the credential's
+//! for `assertionMethod`, the presentation's for `authentication`, bound to
+//! the verifier's `challenge` when one was given.
+//!
+//! The proofs show the preparer vouched for exactly these figures over
+//! exactly these transactions. A recipient who trusts the tree's root (e.g.
+//! from a signed checkpoint) and holds the transactions can go further and
+//! rebuild the trial balance from them (see [`StatementSubject::check_books`]).
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::data_integrity::{add_proof, proof_signer, verify_document, CREDENTIALS_CONTEXT, PROOF_PURPOSE};
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::merkle::{parse_hash, Hash, InclusionProof, MerkleTree};
+use crate::model::Transaction;
+use crate::signer::TransactionSigner;
+use crate::trial_balance::TrialBalance;
+
+pub const STATEMENT_CREDENTIAL_TYPE: &str = "FinancialStatement";
+pub const AUTHENTICATION: &str = "authentication";
+
+/// What the statement credential asserts
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StatementSubject {
+    pub statement: String,                 // What `data` is, e.g. "TrialBalance" or "BalanceSheet"
+    pub data: Value,                       // The statement, as its JSON report
+    pub trial_balance: String,             // Hex digest of the trial balance of `transactions`
+    pub tree_size: u64,                    // The journal tree the proofs are against
+    pub root: String,                      // Its root (hex)
+    pub transactions: Vec<InclusionProof>, // One per underlying transaction, in journal order
+}
+
+impl StatementSubject {
+    /// Describes `statement`, built from `txs`, all of which must be leaves of `tree`
+    pub fn new<'a, S: Serialize, I: IntoIterator<Item = &'a Transaction>>(statement: &str, data: &S, txs: I, tree: &MerkleTree) -> Result<Self, LedgerError> {
+        let txs: Vec<&Transaction> = txs.into_iter().collect();
+        let transactions = txs.iter()
+            .map(|tx| tree.prove_inclusion(&tx.hash_hex()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(StatementSubject {
+            statement: statement.to_string(),
+            data: serde_json::to_value(data).map_err(|e| LedgerError::Serialization(format!("Could not serialize the statement: {}", e)))?,
+            trial_balance: TrialBalance::from_transactions(txs)?.digest()?,
+            tree_size: tree.size() as u64,
+            root: hex::encode(tree.root()),
+            transactions,
+        })
+    }
+
+    /// Checks every inclusion proof against `root`, the root the recipient
+    /// trusts, or the subject's own `root` if none
+    pub fn check_inclusion(&self, root: Option<&Hash>) -> Result<(), LedgerError> {
+        let root = match root {
+            Some(root) => *root,
+            None => parse_hash(&self.root)?,
+        };
+        for proof in &self.transactions {
+            if proof.tree_size != self.tree_size {
+                return Err(LedgerError::Chain(format!("The proof for {} is against a tree of {}, not {}", proof.tx_hash, proof.tree_size, self.tree_size)));
+            }
+            proof.verify(&root).map_err(|e| e.context(format!("Transaction {}", proof.tx_hash)))?;
+        }
+        Ok(())
+    }
+
+    /// Rebuilds the trial balance from the underlying transactions, found
+    /// in `journal` by hash, and checks it matches the one stated
+    pub fn check_books<'a, I: IntoIterator<Item = &'a Transaction>>(&self, journal: I) -> Result<(), LedgerError> {
+        let by_hash: std::collections::HashMap<String, &Transaction> = journal.into_iter().map(|tx| (tx.hash_hex(), tx)).collect();
+        let txs = self.transactions.iter()
+            .map(|proof| by_hash.get(&proof.tx_hash.to_lowercase()).copied()
+                .ok_or_else(|| LedgerError::Chain(format!("Transaction {} is not in the journal", proof.tx_hash))))
+            .collect::<Result<Vec<_>, _>>()?;
+        let books = TrialBalance::from_transactions(txs)?;
+        let digest = books.digest()?;
+        if digest != self.trial_balance {
+            return Err(LedgerError::Chain(format!("The transactions give trial balance {}, not the {} stated", digest, self.trial_balance)));
+        }
+        if self.statement == "TrialBalance" && serde_json::to_value(&books).ok().as_ref() != Some(&self.data) {
+            return Err(LedgerError::Chain("The stated trial balance is not the one its transactions give".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// A signed presentation of one statement
+#[derive(Debug, Clone)]
+pub struct StatementPresentation {
+    pub document: Value,           // The presentation exactly as signed, credential included
+    pub subject: StatementSubject, // The credential's subject
+}
+
+impl Serialize for StatementPresentation {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.document.serialize(serializer)
+    }
+}
+
+impl StatementPresentation {
+    /// Issues the statement credential as `signer` (the preparer) and
+    /// presents it, both proofs dated `created` (Unix seconds)
+    pub fn sign(subject: StatementSubject, signer: &dyn TransactionSigner, created: u64, challenge: Option<&str>) -> Result<Self, LedgerError> {
+        let credential = add_proof(json!({
+            "@context": [CREDENTIALS_CONTEXT],
+            "type": ["VerifiableCredential", STATEMENT_CREDENTIAL_TYPE],
+            "issuer": signer.did(),
+            "credentialSubject": subject,
+        }), signer, created, PROOF_PURPOSE, None)?;
+        let document = add_proof(json!({
+            "@context": [CREDENTIALS_CONTEXT],
+            "type": ["VerifiablePresentation"],
+            "holder": signer.did(),
+            "verifiableCredential": [credential],
+        }), signer, created, AUTHENTICATION, challenge)?;
+        Ok(StatementPresentation { document, subject })
+    }
+
+    /// Reads a presentation holding one statement credential
+    pub fn from_value(document: Value) -> Result<Self, LedgerError> {
+        let subject = serde_json::from_value(document["verifiableCredential"][0]["credentialSubject"].clone())
+            .map_err(|e| LedgerError::Serialization(format!("Not a statement presentation: {}", e)))?;
+        Ok(StatementPresentation { document, subject })
+    }
+
+    /// The preparer: the credential's issuer
+    pub fn preparer(&self) -> &str {
+        self.document["verifiableCredential"][0]["issuer"].as_str().unwrap_or_default()
+    }
+
+    /// Checks both proofs (the preparer must be both issuer and holder) and
+    /// that every inclusion proof leads to the subject's root
+    pub fn verify(&self, challenge: Option<&str>, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let credentials = self.document["verifiableCredential"].as_array().map_or(0, Vec::len);
+        if credentials != 1 {
+            return Err(LedgerError::Serialization(format!("A statement presentation holds one credential, not {}", credentials)));
+        }
+        let credential = &self.document["verifiableCredential"][0];
+        let holder = self.document["holder"].as_str().unwrap_or_default();
+        if self.preparer() != holder || proof_signer(credential) != holder || proof_signer(&self.document) != holder {
+            return Err(LedgerError::Signature(format!("Issuer {}, holder {} and both proof signers must be the same DID", self.preparer(), holder)));
+        }
+        verify_document(credential, PROOF_PURPOSE, None, resolver).map_err(|e| e.context("Statement credential"))?;
+        verify_document(&self.document, AUTHENTICATION, challenge, resolver).map_err(|e| e.context("Presentation"))?;
+        self.subject.check_inclusion(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::chain;
+
+    #[test]
+    fn a_presented_trial_balance_verifies_and_ties_to_its_transactions() {
+        let clerk = Account::generate();
+        let preparer = Account::generate();
+        let journal: Vec<Transaction> = chain(&clerk, 3).into_iter().map(|signed_tx| signed_tx.payload).collect();
+        let mut tree = MerkleTree::new();
+        for tx in &journal {
+            tree.push(tx.get_hash());
+        }
+        let books = TrialBalance::from_transactions(&journal[..2]).unwrap();
+        let subject = StatementSubject::new("TrialBalance", &books, &journal[..2], &tree).unwrap();
+        let presentation = StatementPresentation::sign(subject, &preparer, 1_701_400_000, Some("nonce-1")).unwrap();
+
+        let resolver = DidKeyResolver;
+        let received = StatementPresentation::from_value(serde_json::to_value(&presentation).unwrap()).unwrap();
+        received.verify(Some("nonce-1"), &resolver).unwrap();
+        received.subject.check_books(&journal).unwrap();
+        assert!(received.verify(Some("nonce-2"), &resolver).is_err(), "bound to the verifier's challenge");
+        assert!(received.subject.check_books(&journal[1..]).is_err(), "needs every underlying transaction");
+
+        let mut inflated = presentation.document.clone();
+        inflated["verifiableCredential"][0]["credentialSubject"]["data"]["transactions"] = json!(3);
+        assert!(StatementPresentation::from_value(inflated).unwrap().verify(Some("nonce-1"), &resolver).is_err());
+    }
+}
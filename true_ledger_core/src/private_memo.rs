@@ -0,0 +1,387 @@
+//! Private Memos
+//! A memo can have a private part the ledger should not keep forever, such
+//! as a customer's name. In commitment mode the signed payload holds only
+//! `memo_commitment`: the hex SHA-256 of a random 16-byte salt followed by
+//! the private text. The salt and text (the opening) travel beside the
+//! payload in the envelope's unsigned `private_memo`, so the signature covers
+//! the text without the ledger having to keep it: the opening can later be
+//! sealed, erased or redacted while the signature, the chain and every
+//! Merkle root stay as they were.
+//!
+//! A sealed opening is encrypted with AES-256-GCM under a key of its own,
+//! with the commitment as associated data. The keys are kept in a record key
+//! file ([`RecordKeys`]), apart from the ledger, each with the day its
+//! record must be kept until. Deleting the keys once that day has passed
+//! (`tlc memo erase-expired`) leaves the openings unreadable wherever copies
+//! of the ledger went, backups included.
+//!
+//! Redaction removes an opening on request, e.g. to honour a GDPR erasure:
+//! the private memo is replaced by a [`SignedRedaction`], in which whoever
+//! redacted it states when and why, over the payload hash and commitment.
+//! Only the commitment is left, and the transaction still verifies.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::Engine;
+use chrono::NaiveDate;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::canonical::to_jcs;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::model::{SignedTransaction, Transaction};
+use crate::signer::TransactionSigner;
+use crate::timestamp::Timestamp;
+use crate::verify::decode_signature;
+
+/// Prefixed to the signed bytes so a redaction signature can never be
+/// replayed as any other signature
+const SIGNING_CONTEXT: &[u8] = b"true-ledger memo redaction v1\n";
+
+/// The salt and text a memo commitment is over
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct MemoOpening {
+    pub salt: String, // Hex, 16 bytes
+    pub text: String,
+}
+
+/// An opening encrypted under a record key
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct SealedMemo {
+    pub key_id: String,     // Its key in the record key file
+    pub nonce: String,      // Hex, 12 bytes
+    pub ciphertext: String, // Base64: the opening's JSON + 16-byte tag
+}
+
+/// What whoever redacts a private memo signs
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct Redaction {
+    pub payload_hash: String, // Hex payload hash of the transaction
+    pub commitment: String,   // Its memo_commitment, which is all that is left
+    pub redacted_by: String,  // DID
+    pub timestamp: Timestamp,
+    pub reason: String,
+}
+
+/// A redaction and its signature
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct SignedRedaction {
+    pub redaction: Redaction,
+    pub signature: String, // Multibase (base58btc)
+}
+
+/// The private part of a memo, as the envelope carries it; tagged by
+/// `state` ("open", "sealed" or "redacted")
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(tag = "state", rename_all = "lowercase")]
+pub enum PrivateMemo {
+    Open(MemoOpening),
+    Sealed(SealedMemo),
+    Redacted(SignedRedaction),
+}
+
+/// One record's key, and how long the record must be kept
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordKey {
+    pub key: String, // Hex, 32 bytes
+    pub retain_until: NaiveDate,
+}
+
+/// The record key file: key id -> key
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct RecordKeys {
+    pub keys: BTreeMap<String, RecordKey>,
+}
+
+impl MemoOpening {
+    /// Commits `tx` to a private `text` under a fresh salt, and returns the
+    /// opening that goes beside the signed payload
+    pub fn commit(tx: &mut Transaction, text: &str) -> Result<Self, LedgerError> {
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let opening = MemoOpening { salt: hex::encode(salt), text: text.to_string() };
+        tx.memo_commitment = Some(opening.commitment()?);
+        Ok(opening)
+    }
+
+    /// Hex SHA-256 of the salt's bytes followed by the text
+    pub fn commitment(&self) -> Result<String, LedgerError> {
+        let salt = hex::decode(&self.salt).ok().filter(|salt| salt.len() == 16)
+            .ok_or_else(|| LedgerError::Serialization("The memo's salt is not 16 hex-encoded bytes".to_string()))?;
+        let mut hasher = Sha256::new();
+        hasher.update(&salt);
+        hasher.update(self.text.as_bytes());
+        Ok(hex::encode(hasher.finalize()))
+    }
+
+    /// Checks the opening is the one `commitment` was made over
+    pub fn check(&self, commitment: &str) -> Result<(), LedgerError> {
+        if self.commitment()? != commitment {
+            return Err(LedgerError::Signature("The private memo does not match the signed memo_commitment".to_string()));
+        }
+        Ok(())
+    }
+}
+
+fn cipher(key: &str) -> Result<Aes256Gcm, LedgerError> {
+    let key = Zeroizing::new(hex::decode(key).map_err(|e| LedgerError::Key(format!("Invalid record key: {:?}", e)))?);
+    Aes256Gcm::new_from_slice(&key).map_err(|e| LedgerError::Key(format!("Invalid record key: {}", e)))
+}
+
+impl RecordKeys {
+    /// Reads a record key file; a missing one has no keys yet
+    pub fn load(path: &Path) -> Result<Self, LedgerError> {
+        match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data)
+                .map_err(|e| LedgerError::Serialization(format!("Invalid record key file {}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(RecordKeys::default()),
+            Err(e) => Err(LedgerError::Io(format!("Could not read {}: {}", path.display(), e))),
+        }
+    }
+
+    /// Replaces the file in one rename, readable by its owner only
+    pub fn save(&self, path: &Path) -> Result<(), LedgerError> {
+        let data = Zeroizing::new(serde_json::to_string_pretty(self)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to serialize record keys: {}", e)))?);
+        let staging = path.with_extension("tmp");
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&staging)
+            .and_then(|mut file| std::io::Write::write_all(&mut file, data.as_bytes()))
+            .and_then(|_| fs::rename(&staging, path))
+            .map_err(|e| LedgerError::Io(format!("Could not write {}: {}", path.display(), e)))
+    }
+
+    /// Seals `opening` under a new key, kept until `retain_until`. The
+    /// opening's commitment is bound to the ciphertext.
+    pub fn seal(&mut self, opening: &MemoOpening, retain_until: NaiveDate) -> Result<SealedMemo, LedgerError> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        let mut id = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(key.as_mut());
+        OsRng.fill_bytes(&mut id);
+        OsRng.fill_bytes(&mut nonce);
+        let record_key = RecordKey { key: hex::encode(&key[..]), retain_until };
+
+        let plaintext = Zeroizing::new(serde_json::to_vec(opening).map_err(|e| LedgerError::Serialization(e.to_string()))?);
+        let ciphertext = cipher(&record_key.key)?
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: &plaintext, aad: opening.commitment()?.as_bytes() })
+            .map_err(|_| LedgerError::Key("Encryption failed".to_string()))?;
+        let key_id = hex::encode(id);
+        self.keys.insert(key_id.clone(), record_key);
+        Ok(SealedMemo { key_id, nonce: hex::encode(nonce), ciphertext: base64::engine::general_purpose::STANDARD.encode(ciphertext) })
+    }
+
+    /// Opens a sealed memo committed to as `commitment`, or None once its
+    /// key has been erased
+    pub fn open(&self, sealed: &SealedMemo, commitment: &str) -> Result<Option<MemoOpening>, LedgerError> {
+        let Some(record_key) = self.keys.get(&sealed.key_id) else {
+            return Ok(None);
+        };
+        let nonce = hex::decode(&sealed.nonce).ok().filter(|nonce| nonce.len() == 12)
+            .ok_or_else(|| LedgerError::Key("Invalid nonce: must be 12 hex-encoded bytes".to_string()))?;
+        let ciphertext = base64::engine::general_purpose::STANDARD.decode(&sealed.ciphertext)
+            .map_err(|e| LedgerError::Key(format!("Invalid ciphertext: {}", e)))?;
+        let plaintext = Zeroizing::new(cipher(&record_key.key)?
+            .decrypt(Nonce::from_slice(&nonce), Payload { msg: &ciphertext, aad: commitment.as_bytes() })
+            .map_err(|_| LedgerError::Key(format!("Could not open the private memo sealed under key {}", sealed.key_id)))?);
+        let opening: MemoOpening = serde_json::from_slice(&plaintext)
+            .map_err(|e| LedgerError::Serialization(format!("Invalid sealed memo: {}", e)))?;
+        opening.check(commitment)?;
+        Ok(Some(opening))
+    }
+
+    /// Deletes every key whose record need not be kept past `today`, and
+    /// returns their ids
+    pub fn erase_expired(&mut self, today: NaiveDate) -> Vec<String> {
+        let expired: Vec<String> = self.keys.iter()
+            .filter(|(_, record_key)| record_key.retain_until < today)
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            if let Some(mut record_key) = self.keys.remove(id) {
+                zeroize::Zeroize::zeroize(&mut record_key.key);
+            }
+        }
+        expired
+    }
+}
+
+impl Redaction {
+    /// The exact bytes the redactor signs: a context string, then the JCS form
+    fn signing_input(&self) -> Result<Vec<u8>, LedgerError> {
+        let mut input = SIGNING_CONTEXT.to_vec();
+        input.extend_from_slice(to_jcs(self)?.as_bytes());
+        Ok(input)
+    }
+}
+
+impl SignedRedaction {
+    /// Signs the redaction of `signed_tx`'s private memo as the signer's own DID
+    pub fn sign(signed_tx: &SignedTransaction, reason: &str, signer: &dyn TransactionSigner, timestamp: Timestamp) -> Result<Self, LedgerError> {
+        let commitment = signed_tx.payload.memo_commitment.clone().ok_or_else(|| LedgerError::Config(
+            "The transaction has no private memo to redact; its memo is signed in full".to_string()))?;
+        if reason.trim().is_empty() {
+            return Err(LedgerError::Config("A redaction needs a reason".to_string()));
+        }
+        let redaction = Redaction {
+            payload_hash: signed_tx.payload.hash_hex(),
+            commitment,
+            redacted_by: signer.did().to_string(),
+            timestamp,
+            reason: reason.to_string(),
+        };
+        let signature = signer.sign_bytes(&redaction.signing_input()?)?;
+        Ok(SignedRedaction { redaction, signature: multibase::encode(multibase::Base::Base58Btc, signature) })
+    }
+
+    /// Checks the redactor's signature, and that it redacts `signed_tx`
+    pub fn verify(&self, signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        let redaction = &self.redaction;
+        if redaction.payload_hash != signed_tx.payload.hash_hex() || signed_tx.payload.memo_commitment.as_ref() != Some(&redaction.commitment) {
+            return Err(LedgerError::Signature("The redaction is of another transaction's memo".to_string()));
+        }
+        let key = resolver.resolve_public_key(&redaction.redacted_by)?;
+        key.verify_bytes(&redaction.signing_input()?, &decode_signature(&self.signature)?)
+            .map_err(|e| e.context("The redaction's signature is invalid"))
+    }
+}
+
+/// Checks an envelope's private memo against its payload: an open one must
+/// match the commitment, a redacted one must carry a valid redaction of this
+/// transaction, and only a payload in commitment mode may have one. A sealed
+/// memo is checked when it is opened; an erased one not at all.
+pub fn check_private_memo(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+    match (&signed_tx.private_memo, &signed_tx.payload.memo_commitment) {
+        (None, _) => Ok(()),
+        (Some(_), None) => Err(LedgerError::Signature("The transaction has a private memo but no memo_commitment".to_string())),
+        (Some(PrivateMemo::Open(opening)), Some(commitment)) => opening.check(commitment),
+        (Some(PrivateMemo::Sealed(_)), Some(_)) => Ok(()),
+        (Some(PrivateMemo::Redacted(redaction)), Some(_)) => redaction.verify(signed_tx, resolver),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::scratch_dir;
+    use crate::verify::verify;
+
+    fn day(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn committed(account: &Account, text: &str) -> (SignedTransaction, MemoOpening) {
+        let mut tx = TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .author(&account.did)
+            .entry("60100", "250.00", "0.00")
+            .entry("10100", "0.00", "250.00")
+            .memo("Refund")
+            .build()
+            .unwrap();
+        let opening = MemoOpening::commit(&mut tx, text).unwrap();
+        (account.sign(tx), opening)
+    }
+
+    #[test]
+    fn an_open_memo_must_match_its_commitment() {
+        let account = Account::generate();
+        let (mut signed_tx, opening) = committed(&account, "Refund to Jane Doe");
+        signed_tx.private_memo = Some(PrivateMemo::Open(opening.clone()));
+        verify(&signed_tx).unwrap();
+
+        let json = serde_json::to_string(&signed_tx).unwrap();
+        assert_eq!(SignedTransaction::from_json(&json).unwrap().private_memo, signed_tx.private_memo);
+
+        signed_tx.private_memo = Some(PrivateMemo::Open(MemoOpening { text: "Refund to John Roe".to_string(), ..opening }));
+        assert!(verify(&signed_tx).unwrap_err().to_string().contains("does not match"));
+    }
+
+    #[test]
+    fn an_erased_memo_leaves_the_signature_valid() {
+        let account = Account::generate();
+        let (mut signed_tx, _) = committed(&account, "Refund to Jane Doe");
+        let hash = signed_tx.payload.hash_hex();
+        verify(&signed_tx).unwrap();
+        signed_tx.private_memo = None;
+        verify(&signed_tx).unwrap();
+        assert_eq!(signed_tx.payload.hash_hex(), hash);
+    }
+
+    #[test]
+    fn a_sealed_memo_opens_until_its_key_is_erased() {
+        let account = Account::generate();
+        let (signed_tx, opening) = committed(&account, "Refund to Jane Doe");
+        let commitment = signed_tx.payload.memo_commitment.clone().unwrap();
+        let path = scratch_dir("record-keys").join("record_keys.json");
+
+        let mut keys = RecordKeys::default();
+        let sealed = keys.seal(&opening, day("2030-12-31")).unwrap();
+        keys.save(&path).unwrap();
+        assert!(!sealed.ciphertext.contains("Jane"));
+
+        let mut keys = RecordKeys::load(&path).unwrap();
+        assert_eq!(keys.open(&sealed, &commitment).unwrap(), Some(opening));
+        assert!(keys.open(&sealed, &"0".repeat(64)).is_err(), "the ciphertext is bound to its commitment");
+
+        assert!(keys.erase_expired(day("2030-12-31")).is_empty());
+        assert_eq!(keys.erase_expired(day("2031-01-01")), vec![sealed.key_id.clone()]);
+        assert_eq!(keys.open(&sealed, &commitment).unwrap(), None);
+    }
+
+    #[test]
+    fn only_a_committed_payload_has_a_private_memo() {
+        let account = Account::generate();
+        let (signed_tx, opening) = committed(&account, "Refund to Jane Doe");
+        let mut plain = crate::test_util::signed(&account, None, 0, "Posting");
+        plain.private_memo = Some(PrivateMemo::Open(opening));
+        assert!(check_private_memo(&plain, &DidKeyResolver).is_err());
+        assert!(check_private_memo(&signed_tx, &DidKeyResolver).is_ok());
+    }
+
+    #[test]
+    fn a_redacted_memo_keeps_the_transaction_verifying() {
+        let account = Account::generate();
+        let operator = Account::generate();
+        let (mut signed_tx, opening) = committed(&account, "Refund to Jane Doe");
+        signed_tx.private_memo = Some(PrivateMemo::Open(opening));
+        let redaction = SignedRedaction::sign(&signed_tx, "GDPR Art. 17 request", &operator, 1_800_000_000.into()).unwrap();
+        signed_tx.private_memo = Some(PrivateMemo::Redacted(redaction.clone()));
+        verify(&signed_tx).unwrap();
+        assert!(!serde_json::to_string(&signed_tx).unwrap().contains("Jane"));
+
+        let mut reworded = redaction.clone();
+        reworded.redaction.reason = "No reason".to_string();
+        signed_tx.private_memo = Some(PrivateMemo::Redacted(reworded));
+        assert!(verify(&signed_tx).unwrap_err().to_string().contains("signature is invalid"));
+
+        let (mut other, _) = committed(&account, "Refund to John Roe");
+        other.private_memo = Some(PrivateMemo::Redacted(redaction));
+        assert!(verify(&other).unwrap_err().to_string().contains("another transaction"));
+    }
+
+    #[test]
+    fn only_a_committed_memo_can_be_redacted() {
+        let account = Account::generate();
+        let plain = crate::test_util::signed(&account, None, 0, "Posting");
+        assert!(SignedRedaction::sign(&plain, "GDPR", &account, 1_800_000_000.into()).is_err());
+    }
+}
@@ -0,0 +1,776 @@
+//! Raft Replication
+//! For deployments that need one canonical order of transactions, a cluster
+//! of verifier nodes can agree on it with Raft: one elected leader orders
+//! every submission, and a transaction is appended to the ledgers only once
+//! a majority of nodes hold it in their logs. If the leader fails, the
+//! others elect a new one after a randomized timeout and carry on.
+//!
+//! [`RaftNode`] is the protocol alone. It does no I/O besides its own log:
+//! the caller delivers messages with [`RaftNode::step`], calls
+//! [`RaftNode::tick`] at a steady rate, sends whatever
+//! [`RaftNode::take_messages`] returns, and appends
+//! [`RaftNode::committed`] entries to its ledger. The log and the node's
+//! vote live in a directory ([`RaftLog`]), so a restarted node rejoins with
+//! what it had promised. Members sign what they send ([`SealedEnvelope`]),
+//! so nobody else can cast votes or pose as the leader.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::LedgerError;
+use crate::keys::VerifyingKey;
+use crate::model::SignedTransaction;
+use crate::signer::TransactionSigner;
+use crate::verify::decode_signature;
+
+pub type NodeId = u64;
+
+/// Ticks without hearing from a leader before a follower stands for
+/// election; each node picks at random within this range
+const ELECTION_TICKS: (u32, u32) = (10, 20);
+
+/// Ticks between a leader's heartbeats (well under the election timeout)
+const HEARTBEAT_TICKS: u32 = 3;
+
+/// Most log entries sent in one AppendEntries message
+const MAX_ENTRIES: usize = 64;
+
+/// One slot in the replicated log
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Entry {
+    pub term: u64,
+    pub transaction: Option<SignedTransaction>, // None: the no-op a new leader commits first
+}
+
+/// The protocol's messages
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum Message {
+    RequestVote { last_log_index: u64, last_log_term: u64 },
+    Vote { granted: bool },
+    AppendEntries { prev_log_index: u64, prev_log_term: u64, entries: Vec<Entry>, leader_commit: u64 },
+    AppendResult { success: bool, match_index: u64 }, // On failure, the follower's last index (a hint)
+}
+
+/// A message between two nodes, stamped with the sender's term
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Envelope {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub term: u64,
+    pub message: Message,
+}
+
+/// Prefixed to an envelope before it is signed, so the signature cannot be
+/// passed off as one over anything else
+const SEAL_CONTEXT: &[u8] = b"true-ledger/raft/1\n";
+
+/// An envelope as it travels between members: its JSON, signed by the
+/// sender's key, so a receiver knows which member really sent it. Raft
+/// copes with messages that are repeated or arrive late, so a replayed
+/// message does no harm.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SealedEnvelope {
+    pub envelope: String,  // The Envelope's JSON, exactly as signed
+    pub signature: String, // Multibase (base58btc), over SEAL_CONTEXT + envelope
+}
+
+impl SealedEnvelope {
+    /// Signs `envelope` with the sending member's key
+    pub fn seal(envelope: &Envelope, signer: &dyn TransactionSigner) -> Result<Self, LedgerError> {
+        let envelope = serde_json::to_string(envelope).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        let signature = signer.sign_bytes(&[SEAL_CONTEXT, envelope.as_bytes()].concat())?;
+        Ok(SealedEnvelope { envelope, signature: multibase::encode(multibase::Base::Base58Btc, signature) })
+    }
+
+    /// The envelope, if it is addressed to `to` and signed by the key of the
+    /// member it says it is from
+    pub fn open(&self, keys: &BTreeMap<NodeId, VerifyingKey>, to: NodeId) -> Result<Envelope, LedgerError> {
+        let envelope: Envelope = serde_json::from_str(&self.envelope).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        let key = keys.get(&envelope.from)
+            .ok_or_else(|| LedgerError::Signature(format!("Message from {}, which is not a member", envelope.from)))?;
+        let signature = decode_signature(&self.signature)?;
+        key.verify_bytes(&[SEAL_CONTEXT, self.envelope.as_bytes()].concat(), &signature)
+            .map_err(|e| e.context(format!("Message claiming to be from member {}", envelope.from)))?;
+        if envelope.to != to {
+            return Err(LedgerError::Signature(format!("Message for member {} delivered to member {}", envelope.to, to)));
+        }
+        Ok(envelope)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Follower,
+    Candidate,
+    Leader,
+}
+
+/// What a node must not forget across restarts
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct HardState {
+    term: u64,
+    voted_for: Option<NodeId>,
+    applied: u64, // Entries already appended to the ledger
+}
+
+/// A node's durable state: `state.json` (term, vote, progress) and
+/// `log.ndjson` (one entry per line; index 1 is the first line)
+pub struct RaftLog {
+    dir: PathBuf,
+    state: HardState,
+    entries: Vec<Entry>,
+}
+
+fn io_error(path: &Path, e: impl std::fmt::Display) -> LedgerError {
+    LedgerError::Io(format!("Raft log {}: {}", path.display(), e))
+}
+
+impl RaftLog {
+    /// Opens the log in `dir`, creating the directory if needed
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, LedgerError> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir).map_err(|e| io_error(&dir, e))?;
+        let state_path = dir.join("state.json");
+        let state = match fs::read_to_string(&state_path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| LedgerError::Serialization(format!("{}: {}", state_path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HardState::default(),
+            Err(e) => return Err(io_error(&state_path, e)),
+        };
+        let log_path = dir.join("log.ndjson");
+        let mut entries = Vec::new();
+        match File::open(&log_path) {
+            Ok(file) => {
+                for (n, line) in BufReader::new(file).lines().enumerate() {
+                    let line = line.map_err(|e| io_error(&log_path, e))?;
+                    // A crash mid-write can only leave the last line partial;
+                    // it was never acknowledged, so it is dropped
+                    match serde_json::from_str(&line) {
+                        Ok(entry) => entries.push(entry),
+                        Err(_) => {
+                            let mut log = RaftLog { dir, state, entries };
+                            log.truncate(n as u64)?;
+                            return Ok(log);
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(io_error(&log_path, e)),
+        }
+        Ok(RaftLog { dir, state, entries })
+    }
+
+    fn save_state(&self) -> Result<(), LedgerError> {
+        let path = self.dir.join("state.json");
+        let temp = self.dir.join("state.json.tmp");
+        let data = serde_json::to_vec(&self.state).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+        File::create(&temp).and_then(|mut file| file.write_all(&data).and_then(|_| file.sync_all()))
+            .and_then(|_| fs::rename(&temp, &path))
+            .map_err(|e| io_error(&path, e))
+    }
+
+    fn write_lines(&self, file: &mut File, entries: &[Entry]) -> Result<(), LedgerError> {
+        let path = self.dir.join("log.ndjson");
+        let mut data = Vec::new();
+        for entry in entries {
+            serde_json::to_writer(&mut data, entry).map_err(|e| LedgerError::Serialization(e.to_string()))?;
+            data.push(b'\n');
+        }
+        file.write_all(&data).and_then(|_| file.sync_data()).map_err(|e| io_error(&path, e))
+    }
+
+    fn append(&mut self, entries: Vec<Entry>) -> Result<(), LedgerError> {
+        let path = self.dir.join("log.ndjson");
+        let mut file = OpenOptions::new().create(true).append(true).open(&path).map_err(|e| io_error(&path, e))?;
+        self.write_lines(&mut file, &entries)?;
+        self.entries.extend(entries);
+        Ok(())
+    }
+
+    /// Keeps the first `len` entries, rewriting the file
+    fn truncate(&mut self, len: u64) -> Result<(), LedgerError> {
+        self.entries.truncate(len as usize);
+        let path = self.dir.join("log.ndjson");
+        let temp = self.dir.join("log.ndjson.tmp");
+        let mut file = File::create(&temp).map_err(|e| io_error(&temp, e))?;
+        self.write_lines(&mut file, &self.entries)?;
+        fs::rename(&temp, &path).map_err(|e| io_error(&path, e))
+    }
+
+    pub fn last_index(&self) -> u64 {
+        self.entries.len() as u64
+    }
+
+    /// The term of entry `index`; 0 for index 0 (before the first entry)
+    fn term_at(&self, index: u64) -> Option<u64> {
+        match index {
+            0 => Some(0),
+            _ => self.entries.get(index as usize - 1).map(|entry| entry.term),
+        }
+    }
+
+    pub fn entry(&self, index: u64) -> Option<&Entry> {
+        index.checked_sub(1).and_then(|i| self.entries.get(i as usize))
+    }
+}
+
+/// One member of a Raft cluster
+pub struct RaftNode {
+    id: NodeId,
+    peers: Vec<NodeId>, // Every other member
+    log: RaftLog,
+    role: Role,
+    leader: Option<NodeId>,
+    commit_index: u64,
+    election_elapsed: u32,
+    election_timeout: u32,
+    heartbeat_elapsed: u32,
+    votes: BTreeSet<NodeId>,
+    next_index: BTreeMap<NodeId, u64>,  // Leader only: next entry to send each peer
+    match_index: BTreeMap<NodeId, u64>, // Leader only: highest entry each peer is known to hold
+    outbox: Vec<Envelope>,
+}
+
+fn random_timeout() -> u32 {
+    let (min, max) = ELECTION_TICKS;
+    min + OsRng.next_u32() % (max - min + 1)
+}
+
+impl RaftNode {
+    /// A follower that resumes from `log`; `members` lists the whole cluster
+    pub fn new(id: NodeId, members: &[NodeId], log: RaftLog) -> Result<Self, LedgerError> {
+        if !members.contains(&id) {
+            return Err(LedgerError::Config(format!("Node {} is not a member of the cluster", id)));
+        }
+        let peers = members.iter().copied().filter(|member| *member != id).collect::<BTreeSet<_>>().into_iter().collect();
+        let commit_index = log.state.applied.min(log.last_index()); // Applied entries were committed
+        Ok(RaftNode {
+            id,
+            peers,
+            log,
+            role: Role::Follower,
+            leader: None,
+            commit_index,
+            election_elapsed: 0,
+            election_timeout: random_timeout(),
+            heartbeat_elapsed: 0,
+            votes: BTreeSet::new(),
+            next_index: BTreeMap::new(),
+            match_index: BTreeMap::new(),
+            outbox: Vec::new(),
+        })
+    }
+
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    pub fn role(&self) -> Role {
+        self.role
+    }
+
+    pub fn term(&self) -> u64 {
+        self.log.state.term
+    }
+
+    pub fn leader(&self) -> Option<NodeId> {
+        self.leader
+    }
+
+    pub fn commit_index(&self) -> u64 {
+        self.commit_index
+    }
+
+    pub fn last_index(&self) -> u64 {
+        self.log.last_index()
+    }
+
+    pub fn applied(&self) -> u64 {
+        self.log.state.applied
+    }
+
+    fn quorum(&self) -> usize {
+        let members = self.peers.len() + 1;
+        members / 2 + 1
+    }
+
+    fn send(&mut self, to: NodeId, message: Message) {
+        self.outbox.push(Envelope { from: self.id, to, term: self.term(), message });
+    }
+
+    /// Messages to deliver since the last call
+    pub fn take_messages(&mut self) -> Vec<Envelope> {
+        std::mem::take(&mut self.outbox)
+    }
+
+    fn become_follower(&mut self, term: u64, leader: Option<NodeId>) -> Result<(), LedgerError> {
+        if term > self.term() {
+            self.log.state.term = term;
+            self.log.state.voted_for = None;
+            self.log.save_state()?;
+        }
+        self.role = Role::Follower;
+        self.leader = leader;
+        self.votes.clear();
+        self.election_elapsed = 0;
+        self.election_timeout = random_timeout();
+        Ok(())
+    }
+
+    fn start_election(&mut self) -> Result<(), LedgerError> {
+        self.log.state.term += 1;
+        self.log.state.voted_for = Some(self.id);
+        self.log.save_state()?;
+        self.role = Role::Candidate;
+        self.leader = None;
+        self.votes = BTreeSet::from([self.id]);
+        self.election_elapsed = 0;
+        self.election_timeout = random_timeout();
+        let (last_log_index, last_log_term) = (self.log.last_index(), self.log.term_at(self.log.last_index()).unwrap_or(0));
+        for peer in self.peers.clone() {
+            self.send(peer, Message::RequestVote { last_log_index, last_log_term });
+        }
+        self.check_votes()
+    }
+
+    fn check_votes(&mut self) -> Result<(), LedgerError> {
+        if self.role != Role::Candidate || self.votes.len() < self.quorum() {
+            return Ok(());
+        }
+        self.role = Role::Leader;
+        self.leader = Some(self.id);
+        self.heartbeat_elapsed = 0;
+        let next = self.log.last_index() + 1;
+        self.next_index = self.peers.iter().map(|peer| (*peer, next)).collect();
+        self.match_index = self.peers.iter().map(|peer| (*peer, 0)).collect();
+        // Entries from earlier terms only count as committed once one from
+        // this term is, so a new leader starts with an empty one
+        self.log.append(vec![Entry { term: self.term(), transaction: None }])?;
+        self.advance_commit();
+        self.broadcast_append();
+        Ok(())
+    }
+
+    fn send_append(&mut self, peer: NodeId) {
+        let next = self.next_index.get(&peer).copied().unwrap_or(1).max(1);
+        let prev_log_index = next - 1;
+        let prev_log_term = self.log.term_at(prev_log_index).unwrap_or(0);
+        let entries = self.log.entries.get(prev_log_index as usize..).unwrap_or_default().iter().take(MAX_ENTRIES).cloned().collect();
+        let leader_commit = self.commit_index;
+        self.send(peer, Message::AppendEntries { prev_log_index, prev_log_term, entries, leader_commit });
+    }
+
+    fn broadcast_append(&mut self) {
+        self.heartbeat_elapsed = 0;
+        for peer in self.peers.clone() {
+            self.send_append(peer);
+        }
+    }
+
+    /// Commits the highest entry of this term that a majority holds
+    fn advance_commit(&mut self) {
+        let mut held: Vec<u64> = self.match_index.values().copied().collect();
+        held.push(self.log.last_index());
+        held.sort_unstable_by(|a, b| b.cmp(a));
+        let majority_holds = held[self.quorum() - 1];
+        if majority_holds > self.commit_index && self.log.term_at(majority_holds) == Some(self.term()) {
+            self.commit_index = majority_holds;
+        }
+    }
+
+    /// Advances time by one tick
+    pub fn tick(&mut self) -> Result<(), LedgerError> {
+        if self.role == Role::Leader {
+            self.heartbeat_elapsed += 1;
+            if self.heartbeat_elapsed >= HEARTBEAT_TICKS {
+                self.broadcast_append();
+            }
+            return Ok(());
+        }
+        self.election_elapsed += 1;
+        if self.election_elapsed >= self.election_timeout {
+            self.start_election()?;
+        }
+        Ok(())
+    }
+
+    /// Handles a message from another node
+    pub fn step(&mut self, envelope: Envelope) -> Result<(), LedgerError> {
+        let Envelope { from, term, message, .. } = envelope;
+        if !self.peers.contains(&from) {
+            return Ok(()); // Not a member
+        }
+        if term > self.term() {
+            let leader = matches!(message, Message::AppendEntries { .. }).then_some(from);
+            self.become_follower(term, leader)?;
+        }
+        match message {
+            Message::RequestVote { last_log_index, last_log_term } => {
+                let my_last = self.log.last_index();
+                let my_last_term = self.log.term_at(my_last).unwrap_or(0);
+                let up_to_date = (last_log_term, last_log_index) >= (my_last_term, my_last);
+                let free = self.log.state.voted_for.is_none_or(|voted| voted == from);
+                let granted = term == self.term() && free && up_to_date;
+                if granted {
+                    self.log.state.voted_for = Some(from);
+                    self.log.save_state()?;
+                    self.election_elapsed = 0;
+                }
+                self.send(from, Message::Vote { granted });
+            }
+            Message::Vote { granted } => {
+                if granted && term == self.term() && self.role == Role::Candidate {
+                    self.votes.insert(from);
+                    self.check_votes()?;
+                }
+            }
+            Message::AppendEntries { prev_log_index, prev_log_term, entries, leader_commit } => {
+                if term < self.term() {
+                    let match_index = self.log.last_index();
+                    self.send(from, Message::AppendResult { success: false, match_index });
+                    return Ok(());
+                }
+                if self.role != Role::Follower || self.leader != Some(from) {
+                    self.become_follower(term, Some(from))?;
+                }
+                self.election_elapsed = 0;
+                if self.log.term_at(prev_log_index) != Some(prev_log_term) {
+                    let match_index = self.log.last_index().min(prev_log_index.saturating_sub(1));
+                    self.send(from, Message::AppendResult { success: false, match_index });
+                    return Ok(());
+                }
+                // Skip entries already held; drop ours from the first that differs
+                let mut index = prev_log_index;
+                let mut new = Vec::new();
+                for entry in entries {
+                    index += 1;
+                    if !new.is_empty() {
+                        new.push(entry);
+                        continue;
+                    }
+                    match self.log.term_at(index) {
+                        Some(held) if held == entry.term => {}
+                        Some(_) => {
+                            if index <= self.commit_index {
+                                return Err(LedgerError::Chain(format!("Leader {} contradicts committed entry {}", from, index)));
+                            }
+                            self.log.truncate(index - 1)?;
+                            new.push(entry);
+                        }
+                        None => new.push(entry),
+                    }
+                }
+                self.log.append(new)?;
+                if leader_commit > self.commit_index {
+                    self.commit_index = leader_commit.min(index);
+                }
+                self.send(from, Message::AppendResult { success: true, match_index: index });
+            }
+            Message::AppendResult { success, match_index } => {
+                if self.role != Role::Leader || term != self.term() {
+                    return Ok(());
+                }
+                if success {
+                    let held = self.match_index.entry(from).or_default();
+                    *held = (*held).max(match_index);
+                    self.next_index.insert(from, *held + 1);
+                    self.advance_commit();
+                    if self.next_index[&from] <= self.log.last_index() {
+                        self.send_append(from);
+                    }
+                } else {
+                    let next = self.next_index.get(&from).copied().unwrap_or(1);
+                    self.next_index.insert(from, (match_index + 1).min(next.saturating_sub(1)).max(1));
+                    self.send_append(from);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a transaction to the log (leader only); it is committed once a
+    /// majority holds it. Returns its log index.
+    pub fn propose(&mut self, transaction: SignedTransaction) -> Result<u64, LedgerError> {
+        if self.role != Role::Leader {
+            return Err(LedgerError::Config(match self.leader {
+                Some(leader) => format!("Node {} is not the leader; node {} is", self.id, leader),
+                None => format!("Node {} is not the leader, and no leader is known yet", self.id),
+            }));
+        }
+        self.log.append(vec![Entry { term: self.term(), transaction: Some(transaction) }])?;
+        self.advance_commit(); // A cluster of one commits at once
+        self.broadcast_append();
+        Ok(self.log.last_index())
+    }
+
+    /// Transactions in the log that have not been applied yet, committed or
+    /// not, in log order; a new proposal has to follow the last of them
+    pub fn unapplied(&self) -> impl Iterator<Item = &SignedTransaction> {
+        (self.applied() + 1..=self.log.last_index()).filter_map(|index| self.log.entry(index)?.transaction.as_ref())
+    }
+
+    /// Committed entries not yet applied, with their indexes
+    pub fn committed(&self) -> Vec<(u64, Entry)> {
+        (self.applied() + 1..=self.commit_index)
+            .filter_map(|index| self.log.entry(index).map(|entry| (index, entry.clone())))
+            .collect()
+    }
+
+    /// Records that entries up to `index` have been appended to the ledger
+    pub fn mark_applied(&mut self, index: u64) -> Result<(), LedgerError> {
+        self.log.state.applied = index.min(self.commit_index);
+        self.log.save_state()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+    use crate::test_util::{chain, scratch_dir};
+
+    /// Nodes whose messages are delivered at once, except to and from those `down`
+    struct Cluster {
+        nodes: BTreeMap<NodeId, RaftNode>,
+        dirs: BTreeMap<NodeId, PathBuf>,
+        down: BTreeSet<NodeId>,
+    }
+
+    impl Cluster {
+        fn new(name: &str, size: u64) -> Self {
+            let members: Vec<NodeId> = (1..=size).collect();
+            let dirs: BTreeMap<NodeId, PathBuf> = members.iter().map(|id| (*id, scratch_dir(&format!("raft-{}-{}", name, id)))).collect();
+            let nodes = members.iter().map(|id| (*id, RaftNode::new(*id, &members, RaftLog::open(&dirs[id]).unwrap()).unwrap())).collect();
+            Cluster { nodes, dirs, down: BTreeSet::new() }
+        }
+
+        fn deliver(&mut self) {
+            loop {
+                let mut envelopes = Vec::new();
+                for node in self.nodes.values_mut() {
+                    envelopes.extend(node.take_messages());
+                }
+                if envelopes.is_empty() {
+                    return;
+                }
+                for envelope in envelopes {
+                    if !self.down.contains(&envelope.from) && !self.down.contains(&envelope.to) {
+                        self.nodes.get_mut(&envelope.to).unwrap().step(envelope).unwrap();
+                    }
+                }
+            }
+        }
+
+        fn tick(&mut self, ticks: usize) {
+            for _ in 0..ticks {
+                for (id, node) in &mut self.nodes {
+                    if !self.down.contains(id) {
+                        node.tick().unwrap();
+                    }
+                }
+                self.deliver();
+            }
+        }
+
+        /// Ticks until the nodes that are up agree on a leader among them
+        fn elect(&mut self) -> NodeId {
+            for _ in 0..500 {
+                self.tick(1);
+                let up: Vec<&RaftNode> = self.nodes.values().filter(|node| !self.down.contains(&node.id())).collect();
+                let leaders: Vec<NodeId> = up.iter().filter(|node| node.role() == Role::Leader).map(|node| node.id()).collect();
+                if let [leader] = leaders[..] {
+                    if up.iter().all(|node| node.leader() == Some(leader)) {
+                        return leader;
+                    }
+                }
+            }
+            panic!("no leader was elected");
+        }
+
+        fn node(&mut self, id: NodeId) -> &mut RaftNode {
+            self.nodes.get_mut(&id).unwrap()
+        }
+
+        /// The transactions committed on `id`, by payload hash
+        fn committed(&mut self, id: NodeId) -> Vec<String> {
+            self.node(id).committed().into_iter().filter_map(|(_, entry)| entry.transaction).map(|tx| tx.payload.hash_hex()).collect()
+        }
+    }
+
+    fn hashes(transactions: &[SignedTransaction]) -> Vec<String> {
+        transactions.iter().map(|tx| tx.payload.hash_hex()).collect()
+    }
+
+    fn vote(from: NodeId, to: NodeId) -> Envelope {
+        Envelope { from, to, term: 3, message: Message::Vote { granted: true } }
+    }
+
+    fn member_keys(accounts: &[&Account]) -> BTreeMap<NodeId, VerifyingKey> {
+        accounts.iter().enumerate().map(|(i, account)| (i as NodeId + 1, account.public_key())).collect()
+    }
+
+    #[test]
+    fn a_sealed_envelope_opens_for_its_recipient() {
+        let (one, two) = (Account::generate(), Account::generate());
+        let sealed = SealedEnvelope::seal(&vote(1, 2), &one).unwrap();
+        let opened = sealed.open(&member_keys(&[&one, &two]), 2).unwrap();
+        assert_eq!((opened.from, opened.to, opened.term), (1, 2, 3));
+    }
+
+    #[test]
+    fn a_message_signed_by_another_member_is_refused() {
+        let (one, two) = (Account::generate(), Account::generate());
+        // Member 2 poses as member 1
+        let forged = SealedEnvelope::seal(&vote(1, 2), &two).unwrap();
+        assert!(forged.open(&member_keys(&[&one, &two]), 2).is_err());
+    }
+
+    #[test]
+    fn a_tampered_envelope_is_refused() {
+        let (one, two) = (Account::generate(), Account::generate());
+        let mut sealed = SealedEnvelope::seal(&vote(1, 2), &one).unwrap();
+        sealed.envelope = sealed.envelope.replace("\"term\":3", "\"term\":9");
+        assert!(sealed.open(&member_keys(&[&one, &two]), 2).is_err());
+    }
+
+    #[test]
+    fn strangers_and_misdelivered_messages_are_refused() {
+        let (one, two, stranger) = (Account::generate(), Account::generate(), Account::generate());
+        let keys = member_keys(&[&one, &two]);
+        assert!(SealedEnvelope::seal(&vote(7, 2), &stranger).unwrap().open(&keys, 2).is_err());
+        assert!(SealedEnvelope::seal(&vote(1, 2), &one).unwrap().open(&keys, 1).is_err());
+    }
+
+    #[test]
+    fn a_cluster_elects_one_leader() {
+        let mut cluster = Cluster::new("elect", 3);
+        let leader = cluster.elect();
+        let term = cluster.node(leader).term();
+        assert!(cluster.nodes.values().all(|node| node.term() == term));
+        assert_eq!(cluster.nodes.values().filter(|node| node.role() == Role::Leader).count(), 1);
+    }
+
+    #[test]
+    fn proposals_commit_on_every_member_in_order() {
+        let mut cluster = Cluster::new("commit", 3);
+        let leader = cluster.elect();
+        let transactions = chain(&Account::generate(), 3);
+        for tx in &transactions {
+            cluster.node(leader).propose(tx.clone()).unwrap();
+        }
+        // Followers learn the last commit with the next heartbeat
+        cluster.tick(HEARTBEAT_TICKS as usize);
+        for id in 1..=3 {
+            assert_eq!(cluster.committed(id), hashes(&transactions), "node {}", id);
+        }
+        let follower = (1..=3).find(|id| *id != leader).unwrap();
+        assert!(cluster.node(follower).propose(transactions[0].clone()).is_err());
+    }
+
+    #[test]
+    fn a_minority_cannot_commit() {
+        let mut cluster = Cluster::new("minority", 3);
+        let leader = cluster.elect();
+        cluster.down = (1..=3).filter(|id| *id != leader).collect();
+        let before = cluster.node(leader).commit_index();
+        let index = cluster.node(leader).propose(chain(&Account::generate(), 1).remove(0)).unwrap();
+        cluster.tick(30);
+        assert_eq!(cluster.node(leader).commit_index(), before);
+        assert!(index > before);
+        assert!(cluster.node(leader).unapplied().count() == 1);
+    }
+
+    #[test]
+    fn a_new_leader_keeps_what_was_committed() {
+        let mut cluster = Cluster::new("failover", 3);
+        let first = cluster.elect();
+        let transactions = chain(&Account::generate(), 2);
+        cluster.node(first).propose(transactions[0].clone()).unwrap();
+        cluster.deliver();
+
+        cluster.down.insert(first);
+        let second = cluster.elect();
+        assert_ne!(first, second);
+        assert!(cluster.node(second).term() > cluster.node(first).term());
+        cluster.node(second).propose(transactions[1].clone()).unwrap();
+        cluster.deliver();
+        assert_eq!(cluster.committed(second), hashes(&transactions));
+
+        // The old leader steps down and catches up when it returns
+        cluster.down.clear();
+        cluster.tick(10);
+        assert_eq!(cluster.node(first).role(), Role::Follower);
+        assert_eq!(cluster.committed(first), hashes(&transactions));
+    }
+
+    #[test]
+    fn a_cluster_of_one_commits_at_once() {
+        let mut cluster = Cluster::new("single", 1);
+        cluster.elect();
+        let tx = chain(&Account::generate(), 1).remove(0);
+        cluster.node(1).propose(tx.clone()).unwrap();
+        assert_eq!(cluster.committed(1), hashes(&[tx]));
+    }
+
+    #[test]
+    fn a_restarted_node_remembers_its_term_vote_and_progress() {
+        let mut cluster = Cluster::new("restart", 3);
+        let leader = cluster.elect();
+        cluster.node(leader).propose(chain(&Account::generate(), 1).remove(0)).unwrap();
+        cluster.deliver();
+        let (index, _) = cluster.node(leader).committed().pop().unwrap();
+        cluster.node(leader).mark_applied(index).unwrap();
+        let term = cluster.node(leader).term();
+
+        let restarted = RaftNode::new(leader, &[1, 2, 3], RaftLog::open(&cluster.dirs[&leader]).unwrap()).unwrap();
+        assert_eq!(restarted.term(), term);
+        assert_eq!(restarted.applied(), index);
+        assert_eq!(restarted.last_index(), index);
+        assert!(restarted.committed().is_empty());
+        assert_eq!(restarted.role(), Role::Follower);
+    }
+
+    #[test]
+    fn one_vote_per_term_and_only_for_an_up_to_date_log() {
+        let mut cluster = Cluster::new("votes", 3);
+        let request = |from, term, last_log_index, last_log_term| Envelope {
+            from, to: 1, term, message: Message::RequestVote { last_log_index, last_log_term },
+        };
+        let granted = |node: &mut RaftNode| match node.take_messages().pop().map(|envelope| envelope.message) {
+            Some(Message::Vote { granted }) => granted,
+            other => panic!("expected a vote, got {:?}", other),
+        };
+        let node = cluster.node(1);
+        node.step(request(2, 1, 0, 0)).unwrap();
+        assert!(granted(node));
+        node.step(request(3, 1, 0, 0)).unwrap();
+        assert!(!granted(node), "voted twice in term 1");
+        node.step(request(2, 1, 0, 0)).unwrap();
+        assert!(granted(node), "a repeated request gets the same answer");
+
+        // Give node 1 an entry from term 2, then ask with an older log
+        node.step(Envelope { from: 2, to: 1, term: 2, message: Message::AppendEntries {
+            prev_log_index: 0, prev_log_term: 0, entries: vec![Entry { term: 2, transaction: None }], leader_commit: 0,
+        } }).unwrap();
+        node.take_messages();
+        node.step(request(3, 3, 5, 1)).unwrap();
+        assert!(!granted(node), "voted for a log from an older term");
+        node.step(request(3, 4, 1, 2)).unwrap();
+        assert!(granted(node));
+    }
+
+    #[test]
+    fn messages_from_outside_the_cluster_are_ignored() {
+        let mut cluster = Cluster::new("outsider", 3);
+        let node = cluster.node(1);
+        node.step(Envelope { from: 9, to: 1, term: 50, message: Message::RequestVote { last_log_index: 0, last_log_term: 0 } }).unwrap();
+        assert_eq!(node.term(), 0);
+        assert!(node.take_messages().is_empty());
+        assert!(RaftNode::new(4, &[1, 2, 3], RaftLog::open(scratch_dir("raft-outsider-4")).unwrap()).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Recurring Transactions
+//! Rent, depreciation and subscriptions post the same entries every period.
+//! A template (YAML, TOML or JSON) describes them once:
+//!
+//! ```yaml
+//! name: rent
+//! memo: "Office rent for {month_name} {year}"
+//! every: month                # day, week, month, quarter or year
+//! vars:
+//!   amount: "1500.00"         # Default; a caller may override it
+//! entries:
+//!   - account_id: "60200"
+//!     debit: "{amount}"
+//!   - account_id: "10100"
+//!     credit: "{amount}"
+//! ```
+//!
+//! `{name}` in the memo, account ids and amounts is replaced by a variable,
+//! or by the occurrence's `{date}` (YYYY-MM-DD), `{year}`, `{month}`
+//! (YYYY-MM) or `{month_name}`. A placeholder with no value is an error, so
+//! nothing is signed half-filled. Occurrences are counted from the first
+//! date, so monthly from 31 January falls on the last day of shorter months
+//! and returns to the 31st when it can.
+
+use chrono::{Days, Months, NaiveDate};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::builder::TransactionBuilder;
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::timestamp::Timestamp;
+
+/// How often a template recurs
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Day,
+    Week,
+    #[default]
+    Month,
+    Quarter,
+    Year,
+}
+
+impl Frequency {
+    /// The `n`th occurrence after `first` (0 is `first` itself)
+    fn nth(self, first: NaiveDate, n: u32) -> Option<NaiveDate> {
+        match self {
+            Frequency::Day => first.checked_add_days(Days::new(n.into())),
+            Frequency::Week => first.checked_add_days(Days::new(u64::from(n) * 7)),
+            Frequency::Month => first.checked_add_months(Months::new(n)),
+            Frequency::Quarter => first.checked_add_months(Months::new(n.checked_mul(3)?)),
+            Frequency::Year => first.checked_add_months(Months::new(n.checked_mul(12)?)),
+        }
+    }
+}
+
+/// One entry of a template; amounts and the account may hold placeholders
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateEntry {
+    pub account_id: String,
+    #[serde(default)]
+    pub debit: String,
+    #[serde(default)]
+    pub credit: String,
+    pub currency: Option<String>,
+}
+
+/// A periodic transaction; see the module docs
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RecurringTemplate {
+    pub name: String,
+    pub memo: String,
+    #[serde(default)]
+    pub every: Frequency,
+    #[serde(default = "default_time")]
+    pub time: String,       // Time of day with UTC offset, e.g. "09:00:00+01:00"
+    #[serde(default)]
+    pub adjusting: bool,    // Post each occurrence as an adjusting entry (see period_close.rs)
+    #[serde(default)]
+    pub vars: BTreeMap<String, String>, // Placeholder defaults
+    pub entries: Vec<TemplateEntry>,
+}
+
+fn default_time() -> String {
+    "00:00:00Z".to_string()
+}
+
+impl RecurringTemplate {
+    /// Reads a template; the extension picks the format (.yaml/.yml, .toml, else JSON)
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read template {}: {}", path, e)))?;
+        let invalid = |e: &dyn std::fmt::Display| LedgerError::Config(format!("Invalid template {}: {}", path, e));
+        if path.ends_with(".yaml") || path.ends_with(".yml") {
+            serde_yaml::from_str(&data).map_err(|e| invalid(&e))
+        } else if path.ends_with(".toml") {
+            toml::from_str(&data).map_err(|e| invalid(&e))
+        } else {
+            serde_json::from_str(&data).map_err(|e| invalid(&e))
+        }
+    }
+
+    /// Every occurrence from `first` through `until` (YYYY-MM-DD; just
+    /// `first` without an `until`)
+    pub fn dates(&self, first: &str, until: Option<&str>) -> Result<Vec<NaiveDate>, LedgerError> {
+        let first = parse_day(first)?;
+        let until = until.map(parse_day).transpose()?.unwrap_or(first);
+        if until < first {
+            return Err(LedgerError::Config(format!("The last occurrence ({}) is before the first ({})", until, first)));
+        }
+        Ok((0..)
+            .map_while(|n| self.every.nth(first, n))
+            .take_while(|date| *date <= until)
+            .collect())
+    }
+
+    /// The unsigned transaction for the occurrence on `date`. `vars` take
+    /// precedence over the template's own.
+    pub fn instantiate(&self, date: NaiveDate, vars: &BTreeMap<String, String>) -> Result<Transaction, LedgerError> {
+        let mut values = self.vars.clone();
+        values.insert("date".to_string(), date.format("%Y-%m-%d").to_string());
+        values.insert("year".to_string(), date.format("%Y").to_string());
+        values.insert("month".to_string(), date.format("%Y-%m").to_string());
+        values.insert("month_name".to_string(), date.format("%B").to_string());
+        values.extend(vars.iter().map(|(name, value)| (name.clone(), value.clone())));
+
+        let in_template = |e: LedgerError| e.context(format!("Template '{}' on {}", self.name, date));
+        let timestamp = Timestamp::parse_rfc3339(&format!("{}T{}", date.format("%Y-%m-%d"), self.time)).map_err(in_template)?;
+        let mut builder = TransactionBuilder::new()
+            .timestamp(timestamp)
+            .memo(fill(&self.memo, &values).map_err(in_template)?);
+        for entry in &self.entries {
+            let account_id = fill(&entry.account_id, &values).map_err(in_template)?;
+            let debit = fill(&entry.debit, &values).map_err(in_template)?;
+            let credit = fill(&entry.credit, &values).map_err(in_template)?;
+            builder = match &entry.currency {
+                Some(currency) => builder.entry_in(currency, account_id, debit, credit),
+                None => builder.entry(account_id, debit, credit),
+            };
+        }
+        if self.adjusting {
+            builder = builder.adjusting();
+        }
+        builder.build().map_err(in_template)
+    }
+}
+
+fn parse_day(date: &str) -> Result<NaiveDate, LedgerError> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| LedgerError::Config(format!("'{}' is not a date (YYYY-MM-DD)", date)))
+}
+
+/// Replaces each `{name}` in `text` with its value
+fn fill(text: &str, values: &BTreeMap<String, String>) -> Result<String, LedgerError> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let close = rest[open..].find('}')
+            .ok_or_else(|| LedgerError::Config(format!("Unclosed placeholder in '{}'", text)))?;
+        let name = &rest[open + 1..open + close];
+        let value = values.get(name)
+            .ok_or_else(|| LedgerError::Config(format!("No value for placeholder {{{}}}", name)))?;
+        out.push_str(value);
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
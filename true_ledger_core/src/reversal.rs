@@ -0,0 +1,209 @@
+//! Reversals
+//! A posted transaction is never edited; it is undone by a reversal, which
+//! posts the same entries with debits and credits swapped and names the
+//! original's payload hash in `reverses`. Since `reverses` is signed, the
+//! link cannot be moved to another transaction afterwards. A verifier that
+//! can see the original checks that the reversal negates it exactly, and a
+//! journal may reverse each transaction only once.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::LedgerError;
+use crate::model::{JournalEntry, Transaction};
+use crate::storage::{Query, Storage};
+use crate::timestamp::Timestamp;
+use crate::validation::ValidationRule;
+use crate::verify::entry_cents;
+
+/// The unsigned reversal of `target`, dated `timestamp`. The author is left
+/// for the signer; chain position and sequence for the ledger.
+pub fn reversal_of(target: &Transaction, timestamp: Timestamp) -> Transaction {
+    let hash = target.hash_hex();
+    Transaction {
+        timestamp,
+        author_did: String::new(),
+        entries: target.entries.iter()
+            .map(|entry| JournalEntry {
+                account_id: entry.account_id.clone(),
+                debit: entry.credit.clone(),
+                credit: entry.debit.clone(),
+                currency: entry.currency.clone(),
+            })
+            .collect(),
+        memo: format!("Reversal of {}: {}", &hash[..16], target.memo),
+        prev_hash: None,
+        height: None,
+        sequence: None,
+        canonicalization: None,
+        signing_policy: None,
+        key_event: None,
+        hash_alg: target.hash_alg,
+        version: None,
+        adjusting: None,
+        reverses: Some(hash),
+        prepared_by: None,
+        memo_commitment: None,
+        policy: None,
+    }
+}
+
+/// Each entry as (account, currency, debits minus credits), in a fixed order
+fn net_entries(tx: &Transaction) -> Result<Vec<(String, Option<String>, i64)>, LedgerError> {
+    let mut nets = Vec::with_capacity(tx.entries.len());
+    for (i, entry) in tx.entries.iter().enumerate() {
+        let in_entry = |e: LedgerError| e.context(format!("Entry #{}", i));
+        // Neither side is negative, so neither this nor its negation overflows
+        let net = entry_cents(&entry.debit, "debit").map_err(in_entry)? - entry_cents(&entry.credit, "credit").map_err(in_entry)?;
+        nets.push((entry.account_id.clone(), entry.currency.clone(), net));
+    }
+    nets.sort();
+    Ok(nets)
+}
+
+/// Checks that `reversal` undoes `target`: it names it, is not dated before
+/// it, and posts exactly its entries with the sides swapped
+pub fn check_reversal(reversal: &Transaction, target: &Transaction) -> Result<(), LedgerError> {
+    let hash = target.hash_hex();
+    if reversal.reverses.as_deref() != Some(hash.as_str()) {
+        return Err(LedgerError::Chain(format!("The transaction does not reverse {}", hash)));
+    }
+    if reversal.timestamp.unix() < target.timestamp.unix() {
+        return Err(LedgerError::Timestamp(format!("The reversal ({}) is dated before the transaction it reverses ({})",
+            reversal.timestamp, target.timestamp)));
+    }
+    let mut negated: Vec<_> = net_entries(target)?.into_iter().map(|(account, currency, net)| (account, currency, -net)).collect();
+    negated.sort();
+    if net_entries(reversal)? != negated {
+        return Err(LedgerError::Imbalance(format!("The reversal's entries do not exactly negate those of {}", hash)));
+    }
+    Ok(())
+}
+
+/// Checks reversals while walking a journal in order. Only the transactions
+/// some reversal names (`targets`, found in a first pass) are kept.
+#[derive(Debug, Default)]
+pub struct ReversalTracker {
+    targets: HashSet<String>,
+    seen: HashMap<String, Transaction>, // Targets met so far, by hash
+    reversed: HashSet<String>,
+}
+
+impl ReversalTracker {
+    pub fn new<I: IntoIterator<Item = String>>(targets: I) -> Self {
+        ReversalTracker { targets: targets.into_iter().collect(), ..Self::default() }
+    }
+
+    /// Checks `tx` if it is a reversal, then remembers it if it will be reversed
+    pub fn check(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
+        if let Some(target) = &tx.reverses {
+            let original = self.seen.get(target).ok_or_else(|| LedgerError::Chain(format!(
+                "Reverses {}, which is not earlier in the journal", target)))?;
+            check_reversal(tx, original)?;
+            if !self.reversed.insert(target.clone()) {
+                return Err(LedgerError::Chain(format!("{} was already reversed", target)));
+            }
+        }
+        let hash = tx.hash_hex();
+        if self.targets.contains(&hash) {
+            self.seen.insert(hash, tx.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Checks a single reversal against the ledger it is posted to: the
+/// original must be there, and not reversed by anything else
+pub struct ReversalInLedger {
+    pub ledger: Box<dyn Storage>,
+}
+
+impl ValidationRule for ReversalInLedger {
+    fn name(&self) -> &str {
+        "reversal"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let Some(target) = &tx.reverses else { return Ok(()) };
+        let original = self.ledger.get_by_hash(target)?
+            .ok_or_else(|| LedgerError::Chain(format!("Reverses {}, which is not in the ledger", target)))?;
+        check_reversal(tx, &original.payload)?;
+        let hash = tx.hash_hex();
+        let other = self.ledger.query(&Query::default())?.into_iter()
+            .find(|stored| stored.payload.reverses.as_ref() == Some(target) && stored.payload.hash_hex() != hash);
+        match other {
+            Some(other) => Err(LedgerError::Chain(format!("{} was already reversed by {}", target, other.payload.hash_hex()))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+    use crate::journal::NdjsonJournal;
+    use crate::model::SignedTransaction;
+    use crate::test_util::{scratch_dir, signed};
+
+    /// The signed reversal of `original`, dated `timestamp` and linked after it
+    fn reversal(account: &Account, original: &SignedTransaction, timestamp: u64) -> SignedTransaction {
+        TransactionBuilder::new()
+            .timestamp(timestamp)
+            .entry("10100", "0.00", "10.00")
+            .entry("30100", "10.00", "0.00")
+            .memo("Reversal")
+            .reverses(&original.payload)
+            .follows(&original.payload)
+            .sequence(1)
+            .sign(account)
+            .unwrap()
+    }
+
+    #[test]
+    fn a_reversal_negates_its_original_exactly() {
+        let account = Account::generate();
+        let original = signed(&account, None, 0, "Sale");
+        check_reversal(&reversal_of(&original.payload, Timestamp::Unix(1_700_000_000)), &original.payload).unwrap();
+        check_reversal(&reversal(&account, &original, 1_700_000_100).payload, &original.payload).unwrap();
+
+        assert!(check_reversal(&reversal_of(&original.payload, Timestamp::Unix(1_699_999_999)), &original.payload).is_err());
+        let mut partial = reversal_of(&original.payload, Timestamp::Unix(1_700_000_100));
+        partial.entries[0].credit = "9.99".to_string();
+        assert!(check_reversal(&partial, &original.payload).is_err());
+        let other = signed(&account, None, 0, "Another sale");
+        assert!(check_reversal(&reversal_of(&other.payload, Timestamp::Unix(1_700_000_100)), &original.payload).is_err());
+    }
+
+    #[test]
+    fn negative_amounts_do_not_reverse() {
+        let mut original = signed(&Account::generate(), None, 0, "Sale").payload;
+        original.entries[0].debit = "-92233720368547758.07".to_string();
+        original.entries[0].credit = "92233720368547758.07".to_string();
+        let reversed = reversal_of(&original, Timestamp::Unix(1_700_000_100));
+        assert!(check_reversal(&reversed, &original).is_err());
+    }
+
+    #[test]
+    fn a_journal_reverses_each_transaction_once() {
+        let account = Account::generate();
+        let original = signed(&account, None, 0, "Sale");
+        let (first, second) = (reversal(&account, &original, 1_700_000_100), reversal(&account, &original, 1_700_000_200));
+
+        let mut tracker = ReversalTracker::new([original.payload.hash_hex()]);
+        assert!(tracker.check(&first.payload).is_err());
+        tracker.check(&original.payload).unwrap();
+        tracker.check(&first.payload).unwrap();
+        assert!(tracker.check(&second.payload).is_err());
+
+        let mut ledger = NdjsonJournal::open(scratch_dir("reversal").join("ledger.ndjson"));
+        let empty = ReversalInLedger { ledger: Box::new(NdjsonJournal::open(scratch_dir("reversal-empty").join("ledger.ndjson"))) };
+        assert!(empty.check(&first.payload).is_err());
+        ledger.append(&original).unwrap();
+        ledger.append(&first).unwrap();
+        let rule = ReversalInLedger { ledger: Box::new(ledger) };
+        rule.check(&first.payload).unwrap();
+        let refused = rule.check(&second.payload).unwrap_err().to_string();
+        assert!(refused.contains("already reversed"), "{}", refused);
+    }
+}
@@ -0,0 +1,348 @@
+//! Red-Flag Rules (Anomaly Screening)
+//! These rules never fail verification. They flag transactions that are
+//! valid but worth a second look by a reviewer. The built-in set is tuned
+//! from a TOML file; a missing table keeps that rule's defaults:
+//!
+//! ```toml
+//! [business_hours]            # Postings outside these hours, or at weekends
+//! start_hour = 8              # Inclusive, in the transaction's own time zone
+//! end_hour = 18               # Exclusive
+//!
+//! [round_sum]                 # Entries at or above `threshold`...
+//! threshold = "10000.00"
+//! round_to = "1000.00"        # ...that are a whole multiple of this
+//!
+//! [[unusual_pair]]            # Replaces the default pairs when any is given
+//! debit = "3"                 # Account code prefixes
+//! credit = "4"
+//! reason = "revenue recognised directly against equity"
+//!
+//! self_approval = true        # The author approving their own preparation or signing policy
+//! ```
+//!
+//! Amounts are summed in integer cents, like everything else in the ledger.
+
+use chrono::{Datelike, Timelike};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::amount::{format_cents, parse_cents};
+use crate::error::LedgerError;
+use crate::model::Transaction;
+
+/// Sum of all valid debits in cents, used to size whole-transaction findings.
+/// Invalid amounts are reported by balance_check, not here.
+pub fn transaction_total(tx: &Transaction) -> i64 {
+    tx.entries.iter()
+        .filter_map(|e| parse_cents(&e.debit).ok())
+        .filter(|cents| *cents > 0)
+        .fold(0, i64::saturating_add)
+}
+
+/// A single structured finding emitted by a red-flag rule
+#[derive(Serialize, Debug)]
+pub struct Finding {
+    pub rule: &'static str,
+    pub entry_index: Option<usize>, // Which entry triggered it (None = whole transaction)
+    pub amount_cents: i64,          // Amount at stake, used for materiality
+    pub message: String,
+}
+
+/// A red-flag rule inspects a transaction and reports anything suspicious
+pub trait RedFlagRule {
+    fn name(&self) -> &'static str;
+    fn check(&self, tx: &Transaction) -> Vec<Finding>;
+}
+
+/// Flags postings made outside business hours or on weekends, in the
+/// transaction's own time zone (UTC for Unix timestamps)
+pub struct OutsideBusinessHours {
+    pub start_hour: u32, // Inclusive
+    pub end_hour: u32,   // Exclusive
+}
+
+impl RedFlagRule for OutsideBusinessHours {
+    fn name(&self) -> &'static str {
+        "outside_business_hours"
+    }
+
+    fn check(&self, tx: &Transaction) -> Vec<Finding> {
+        let local = tx.timestamp.to_datetime();
+        let hour = local.hour();
+        let weekday = local.weekday().num_days_from_monday();
+        let zone = match local.offset().local_minus_utc() {
+            0 => "UTC".to_string(),
+            _ => local.offset().to_string(),
+        };
+
+        if weekday >= 5 {
+            vec![Finding {
+                rule: self.name(),
+                entry_index: None,
+                amount_cents: transaction_total(tx),
+                message: "Posted on a weekend.".to_string(),
+            }]
+        } else if hour < self.start_hour || hour >= self.end_hour {
+            vec![Finding {
+                rule: self.name(),
+                entry_index: None,
+                amount_cents: transaction_total(tx),
+                message: format!("Posted at {:02}:00 {}, outside {:02}:00-{:02}:00.", hour, zone, self.start_hour, self.end_hour),
+            }]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Flags suspiciously round amounts at or above a threshold (e.g. 10000.00)
+pub struct RoundSumEntries {
+    pub threshold_cents: i64,
+    pub round_to_cents: i64, // An amount is "round" if it is a whole multiple of this
+}
+
+impl RedFlagRule for RoundSumEntries {
+    fn name(&self) -> &'static str {
+        "round_sum_entry"
+    }
+
+    fn check(&self, tx: &Transaction) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for (i, entry) in tx.entries.iter().enumerate() {
+            for amount in [&entry.debit, &entry.credit] {
+                // Unparseable amounts are reported by balance_check, not here
+                let Ok(cents) = parse_cents(amount) else { continue };
+                if cents >= self.threshold_cents && cents % self.round_to_cents == 0 {
+                    findings.push(Finding {
+                        rule: self.name(),
+                        entry_index: Some(i),
+                        amount_cents: cents,
+                        message: format!("Round amount {} on account {}.", format_cents(cents), entry.account_id),
+                    });
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// Flags debit/credit account combinations that rarely occur legitimately.
+/// Accounts are matched by code prefix (e.g. "3" = all equity accounts).
+pub struct UnusualAccountCombination {
+    pub pairs: Vec<UnusualPair>,
+}
+
+impl RedFlagRule for UnusualAccountCombination {
+    fn name(&self) -> &'static str {
+        "unusual_account_combination"
+    }
+
+    fn check(&self, tx: &Transaction) -> Vec<Finding> {
+        let is_nonzero = |amount: &str| parse_cents(amount).is_ok_and(|cents| cents != 0);
+        let debited: Vec<&str> = tx.entries.iter()
+            .filter(|e| is_nonzero(&e.debit))
+            .map(|e| e.account_id.as_str())
+            .collect();
+        let credited: Vec<&str> = tx.entries.iter()
+            .filter(|e| is_nonzero(&e.credit))
+            .map(|e| e.account_id.as_str())
+            .collect();
+
+        let mut findings = Vec::new();
+        for pair in &self.pairs {
+            let debit_hit = debited.iter().find(|a| a.starts_with(&pair.debit));
+            let credit_hit = credited.iter().find(|a| a.starts_with(&pair.credit));
+            if let (Some(d), Some(c)) = (debit_hit, credit_hit) {
+                findings.push(Finding {
+                    rule: self.name(),
+                    entry_index: None,
+                    amount_cents: transaction_total(tx),
+                    message: format!("Debit {} against credit {}: {}.", d, c, pair.reason),
+                });
+            }
+        }
+        findings
+    }
+}
+
+/// Flags transactions their author approved alone: one the author also
+/// prepared, or whose signing policy the author's own signature satisfies
+pub struct SelfApproval;
+
+impl RedFlagRule for SelfApproval {
+    fn name(&self) -> &'static str {
+        "self_approval"
+    }
+
+    fn check(&self, tx: &Transaction) -> Vec<Finding> {
+        let finding = |message: String| Finding { rule: self.name(), entry_index: None, amount_cents: transaction_total(tx), message };
+        let mut findings = Vec::new();
+        if tx.prepared_by.as_ref() == Some(&tx.author_did) {
+            findings.push(finding(format!("Prepared and approved by the same DID, {}.", tx.author_did)));
+        }
+        if let Some(policy) = tx.signing_policy.as_ref().filter(|policy| policy.threshold <= 1 && policy.signers.contains(&tx.author_did)) {
+            findings.push(finding(format!("The signing policy ({} of {}) is met by the author's own signature.", policy.threshold, policy.signers.len())));
+        }
+        findings
+    }
+}
+
+/// The `[business_hours]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BusinessHoursConfig {
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+/// The `[round_sum]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RoundSumConfig {
+    pub threshold: String,
+    pub round_to: String,
+}
+
+/// One `[[unusual_pair]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct UnusualPair {
+    pub debit: String,  // Account code prefix
+    pub credit: String, // Account code prefix
+    pub reason: String,
+}
+
+/// A red-flags file
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct RedFlagConfig {
+    pub business_hours: BusinessHoursConfig,
+    pub round_sum: RoundSumConfig,
+    pub unusual_pair: Vec<UnusualPair>,
+    pub self_approval: bool,
+}
+
+impl Default for RedFlagConfig {
+    fn default() -> Self {
+        let pair = |debit: &str, credit: &str, reason: &str| UnusualPair { debit: debit.to_string(), credit: credit.to_string(), reason: reason.to_string() };
+        RedFlagConfig {
+            business_hours: BusinessHoursConfig { start_hour: 8, end_hour: 18 },
+            round_sum: RoundSumConfig { threshold: "10000.00".to_string(), round_to: "1000.00".to_string() },
+            unusual_pair: vec![
+                pair("3", "4", "revenue recognised directly against equity"),
+                pair("4", "1", "revenue reversed straight out of an asset account"),
+            ],
+            self_approval: true,
+        }
+    }
+}
+
+impl RedFlagConfig {
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read red-flags file {}: {}", path, e)))?;
+        toml::from_str(&data).map_err(|e| LedgerError::Config(format!("Invalid red-flags file {}: {}", path, e)))
+    }
+
+    /// The rules this file configures
+    pub fn rules(&self) -> Result<Vec<Box<dyn RedFlagRule>>, LedgerError> {
+        let hours = &self.business_hours;
+        if hours.start_hour >= hours.end_hour || hours.end_hour > 24 {
+            return Err(LedgerError::Config(format!("business_hours: {}-{} is not a span of hours within a day", hours.start_hour, hours.end_hour)));
+        }
+        let cents = |amount: &str, key: &str| parse_cents(amount).map_err(|e| e.context(format!("round_sum.{}", key)));
+        let (threshold_cents, round_to_cents) = (cents(&self.round_sum.threshold, "threshold")?, cents(&self.round_sum.round_to, "round_to")?);
+        if round_to_cents <= 0 {
+            return Err(LedgerError::Config("round_sum.round_to must be more than zero".to_string()));
+        }
+
+        let mut rules: Vec<Box<dyn RedFlagRule>> = vec![
+            Box::new(OutsideBusinessHours { start_hour: hours.start_hour, end_hour: hours.end_hour }),
+            Box::new(RoundSumEntries { threshold_cents, round_to_cents }),
+            Box::new(UnusualAccountCombination { pairs: self.unusual_pair.clone() }),
+        ];
+        if self.self_approval {
+            rules.push(Box::new(SelfApproval));
+        }
+        Ok(rules)
+    }
+}
+
+/// The default red-flag rule set
+pub fn default_rules() -> Vec<Box<dyn RedFlagRule>> {
+    RedFlagConfig::default().rules().expect("the default red-flag rules are valid")
+}
+
+/// Runs every rule against the transaction and collects the findings
+pub fn evaluate_rules(tx: &Transaction, rules: &[Box<dyn RedFlagRule>]) -> Vec<Finding> {
+    rules.iter().flat_map(|rule| rule.check(tx)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+    use crate::multisig::SigningPolicy;
+
+    // Wednesday 2023-11-15 10:00 UTC
+    const WEDNESDAY_10AM: u64 = 1_700_042_400;
+
+    fn posting(at: u64, debit: &str, credit: &str, amount: &str) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(at)
+            .author(Account::generate().did)
+            .entry(debit, amount, "0.00")
+            .entry(credit, "0.00", amount)
+            .memo("Posting")
+            .build()
+            .unwrap()
+    }
+
+    fn flagged(tx: &Transaction) -> Vec<&'static str> {
+        evaluate_rules(tx, &default_rules()).into_iter().map(|finding| finding.rule).collect()
+    }
+
+    #[test]
+    fn an_ordinary_posting_raises_no_flags() {
+        assert!(flagged(&posting(WEDNESDAY_10AM, "68100", "10100", "1234.56")).is_empty());
+    }
+
+    #[test]
+    fn the_default_rules_flag_each_anomaly() {
+        assert_eq!(flagged(&posting(WEDNESDAY_10AM + 10 * 3600, "68100", "10100", "1.00")), ["outside_business_hours"]);
+        assert_eq!(flagged(&posting(WEDNESDAY_10AM + 3 * 86_400, "68100", "10100", "1.00")), ["outside_business_hours"]);
+        assert_eq!(flagged(&posting(WEDNESDAY_10AM, "68100", "10100", "12000.00")), ["round_sum_entry", "round_sum_entry"]);
+        assert!(flagged(&posting(WEDNESDAY_10AM, "68100", "10100", "12000.01")).is_empty());
+        assert_eq!(flagged(&posting(WEDNESDAY_10AM, "30100", "40100", "5.00")), ["unusual_account_combination"]);
+
+        let mut prepared = posting(WEDNESDAY_10AM, "68100", "10100", "5.00");
+        prepared.prepared_by = Some(prepared.author_did.clone());
+        assert_eq!(flagged(&prepared), ["self_approval"]);
+        let mut one_of_two = posting(WEDNESDAY_10AM, "68100", "10100", "5.00");
+        one_of_two.signing_policy = Some(SigningPolicy { threshold: 1, signers: vec![one_of_two.author_did.clone(), Account::generate().did] });
+        assert_eq!(flagged(&one_of_two), ["self_approval"]);
+        one_of_two.signing_policy.as_mut().unwrap().threshold = 2;
+        assert!(flagged(&one_of_two).is_empty());
+    }
+
+    #[test]
+    fn thresholds_come_from_the_config() {
+        let config: RedFlagConfig = toml::from_str(
+            "self_approval = false\n[round_sum]\nthreshold = \"500.00\"\nround_to = \"100.00\"\n[business_hours]\nstart_hour = 6\nend_hour = 22\n",
+        ).unwrap();
+        assert_eq!(config.unusual_pair.len(), 2);
+        let rules = config.rules().unwrap();
+        let findings = evaluate_rules(&posting(WEDNESDAY_10AM + 10 * 3600, "68100", "10100", "600.00"), &rules);
+        assert_eq!(findings.iter().map(|finding| finding.rule).collect::<Vec<_>>(), ["round_sum_entry", "round_sum_entry"]);
+        assert_eq!(findings[0].amount_cents, 60_000);
+
+        let mut prepared = posting(WEDNESDAY_10AM, "68100", "10100", "5.00");
+        prepared.prepared_by = Some(prepared.author_did.clone());
+        assert!(evaluate_rules(&prepared, &rules).is_empty());
+
+        let zero: RedFlagConfig = toml::from_str("[round_sum]\nthreshold = \"1.00\"\nround_to = \"0\"\n").unwrap();
+        assert!(zero.rules().is_err());
+        let backwards: RedFlagConfig = toml::from_str("[business_hours]\nstart_hour = 18\nend_hour = 8\n").unwrap();
+        assert!(backwards.rules().is_err());
+    }
+}
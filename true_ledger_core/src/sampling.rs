@@ -0,0 +1,315 @@
+//! Audit Sampling
+//! An auditor tests a sample of the ledger, and must be able to show that
+//! nobody chose which transactions went into it. A sample is drawn from the
+//! transactions a signed checkpoint covers, seeded with the checkpoint's
+//! digest: the operator fixes the seed by publishing the checkpoint, before
+//! anyone can tell which transactions it will pick, and anyone holding the
+//! checkpoint can draw the same sample again. Each sampled transaction
+//! comes with an inclusion proof against the checkpoint's root.
+//!
+//! Draws are SHA-256(seed || counter), reduced by rejection so every value
+//! is equally likely. Two methods:
+//! - random: simple random sampling without replacement (Floyd's algorithm)
+//! - monetary-unit: every cent of the debits is equally likely to be picked,
+//!   so a transaction's chance grows with its amount (systematic selection
+//!   from a random start). A transaction larger than the sampling interval
+//!   is always picked, and only once, so the sample may come out smaller
+//!   than asked for.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+use crate::checkpoint::SignedCheckpoint;
+use crate::did::DidResolver;
+use crate::error::LedgerError;
+use crate::merkle::{Hash, InclusionProof, MerkleTree};
+use crate::model::SignedTransaction;
+use crate::verify::{balance_totals, verify_with};
+
+/// How the sample is drawn
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SampleMethod {
+    Random,
+    MonetaryUnit,
+}
+
+/// A deterministic stream of uniform draws from a seed
+pub struct Draws {
+    seed: Hash,
+    counter: u64,
+}
+
+impl Draws {
+    pub fn new(seed: Hash) -> Self {
+        Draws { seed, counter: 0 }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut hasher = Sha256::new();
+        hasher.update(self.seed);
+        hasher.update(self.counter.to_be_bytes());
+        self.counter += 1;
+        let digest = hasher.finalize();
+        u64::from_be_bytes(digest[..8].try_into().expect("8 bytes"))
+    }
+
+    /// A uniform draw from 0..n, for n > 0
+    pub fn below(&mut self, n: u64) -> u64 {
+        let limit = u64::MAX - u64::MAX % n; // A multiple of n: draws at or above it would favour small values
+        loop {
+            let draw = self.next_u64();
+            if draw < limit {
+                return draw % n;
+            }
+        }
+    }
+}
+
+/// `size` distinct indices from 0..population, in ascending order
+pub fn simple_random(seed: Hash, population: usize, size: usize) -> Result<Vec<usize>, LedgerError> {
+    if size == 0 || size > population {
+        return Err(LedgerError::Config(format!("Cannot sample {} of {} transactions", size, population)));
+    }
+    let mut draws = Draws::new(seed);
+    let mut picked = BTreeSet::new();
+    for j in population - size..population {
+        let t = draws.below(j as u64 + 1) as usize;
+        if !picked.insert(t) {
+            picked.insert(j);
+        }
+    }
+    Ok(picked.into_iter().collect())
+}
+
+/// The indices of the transactions holding `size` cents picked at a fixed
+/// interval from a random start, in ascending order. `amounts` are each
+/// transaction's debits in cents.
+pub fn monetary_unit(seed: Hash, amounts: &[i64], size: usize) -> Result<Vec<usize>, LedgerError> {
+    let mut ends = Vec::with_capacity(amounts.len()); // Cumulative totals: transaction i holds cents ends[i-1]..ends[i]
+    let mut total: u64 = 0;
+    for &amount in amounts {
+        let amount = u64::try_from(amount).map_err(|_| LedgerError::Amount(format!("A negative amount {} cannot be sampled", amount)))?;
+        total = total.checked_add(amount).ok_or_else(|| LedgerError::Amount("Amounts add up to more than a ledger can hold".to_string()))?;
+        ends.push(total);
+    }
+    if size == 0 || size as u64 > total {
+        return Err(LedgerError::Config(format!("Cannot sample {} of {} cents", size, total)));
+    }
+    let interval = total / size as u64;
+    let start = Draws::new(seed).below(interval);
+    let picked: BTreeSet<usize> = (0..size as u64)
+        .map(|k| start + k * interval)
+        .map(|cent| ends.partition_point(|&end| end <= cent))
+        .collect();
+    Ok(picked.into_iter().collect())
+}
+
+/// One sampled transaction and its proof against the checkpoint's root
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SampleItem {
+    pub transaction: SignedTransaction,
+    pub proof: InclusionProof,
+}
+
+/// A sample and everything needed to check it was drawn fairly
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AuditSample {
+    pub checkpoint: SignedCheckpoint,
+    pub seed: String, // Hex: the checkpoint's digest
+    pub method: SampleMethod,
+    pub size: u64, // Asked for; monetary-unit sampling may pick fewer
+    pub items: Vec<SampleItem>,
+}
+
+/// Each transaction's debits in cents, for monetary-unit sampling
+fn amounts(transactions: &[SignedTransaction]) -> Result<Vec<i64>, LedgerError> {
+    transactions.iter().map(|signed_tx| balance_totals(&signed_tx.payload).map(|(debits, _)| debits)).collect()
+}
+
+/// The indices `method` picks from the checkpointed `transactions`
+fn select(method: SampleMethod, seed: Hash, transactions: &[SignedTransaction], size: usize) -> Result<Vec<usize>, LedgerError> {
+    match method {
+        SampleMethod::Random => simple_random(seed, transactions.len(), size),
+        SampleMethod::MonetaryUnit => monetary_unit(seed, &amounts(transactions)?, size),
+    }
+}
+
+impl AuditSample {
+    /// Draws a sample from the first `tree_size` transactions of `journal`
+    /// (in chain order), which must still reproduce the checkpoint's root
+    pub fn draw(checkpoint: SignedCheckpoint, journal: &[SignedTransaction], method: SampleMethod, size: usize) -> Result<Self, LedgerError> {
+        let mut tree = MerkleTree::new();
+        for signed_tx in journal {
+            tree.push(signed_tx.payload.get_hash());
+        }
+        checkpoint.checkpoint.check_tree(&tree)?;
+        let tree_size = checkpoint.checkpoint.tree_size as usize;
+        let seed = checkpoint.digest()?;
+        let items = select(method, seed, &journal[..tree_size], size)?
+            .into_iter()
+            .map(|index| Ok(SampleItem { transaction: journal[index].clone(), proof: tree.prove_leaf(index, tree_size)? }))
+            .collect::<Result<_, LedgerError>>()?;
+        Ok(AuditSample { checkpoint, seed: hex::encode(seed), method, size: size as u64, items })
+    }
+
+    fn leaf_indices(&self) -> Vec<usize> {
+        self.items.iter().map(|item| item.proof.leaf_index as usize).collect()
+    }
+
+    /// Checks the checkpoint's signature and the seed, that every sampled
+    /// transaction verifies and is proven against the checkpoint's root, and
+    /// for a random sample that these are the transactions the seed picks.
+    /// A monetary-unit selection depends on every amount: see `check_selection`.
+    pub fn verify(&self, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+        self.checkpoint.verify(resolver).map_err(|e| e.context("The sample's checkpoint signature is invalid"))?;
+        let seed = self.checkpoint.digest()?;
+        if self.seed != hex::encode(seed) {
+            return Err(LedgerError::Chain(format!("The sample's seed {} is not its checkpoint's digest {}", self.seed, hex::encode(seed))));
+        }
+        let checkpoint = &self.checkpoint.checkpoint;
+        let root = checkpoint.root_hash()?;
+        for item in &self.items {
+            let hash = item.transaction.payload.hash_hex();
+            if item.proof.tx_hash != hash || item.proof.tree_size != checkpoint.tree_size {
+                return Err(LedgerError::Chain(format!("The proof for {} is not for that transaction in the checkpointed tree", hash)));
+            }
+            item.proof.verify(&root).map_err(|e| e.context(format!("Transaction {}", hash)))?;
+            verify_with(&item.transaction, resolver).map_err(|e| e.context(format!("Transaction {}", hash)))?;
+        }
+        if self.method == SampleMethod::Random {
+            let expected = simple_random(seed, checkpoint.tree_size as usize, self.size as usize)?;
+            if self.leaf_indices() != expected {
+                return Err(LedgerError::Chain(format!("The sample holds transactions {:?}, but the seed picks {:?}", self.leaf_indices(), expected)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws the sample again from the journal and checks it picked the same
+    /// transactions
+    pub fn check_selection(&self, journal: &[SignedTransaction]) -> Result<(), LedgerError> {
+        let redrawn = AuditSample::draw(self.checkpoint.clone(), journal, self.method, self.size as usize)?;
+        if self.leaf_indices() != redrawn.leaf_indices() {
+            return Err(LedgerError::Chain(format!("The sample holds transactions {:?}, but the journal and seed pick {:?}",
+                self.leaf_indices(), redrawn.leaf_indices())));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::checkpoint::Checkpoint;
+    use crate::did::DidKeyResolver;
+    use crate::identity::Account;
+    use crate::test_util::chain;
+
+    fn checkpoint(operator: &Account, journal: &[SignedTransaction], tree_size: usize) -> SignedCheckpoint {
+        let mut tree = MerkleTree::new();
+        for signed_tx in &journal[..tree_size] {
+            tree.push(signed_tx.payload.get_hash());
+        }
+        let height = journal[tree_size - 1].payload.height;
+        Checkpoint::new(&tree, height, &operator.did, 1_700_000_100.into()).sign(operator).unwrap()
+    }
+
+    #[test]
+    fn random_draws_are_distinct_and_in_range() {
+        for population in 1..30 {
+            for size in 1..=population {
+                let picked = simple_random([7; 32], population, size).unwrap();
+                assert_eq!(picked.len(), size);
+                assert!(picked.windows(2).all(|pair| pair[0] < pair[1]));
+                assert!(picked.iter().all(|&index| index < population));
+            }
+        }
+        assert_ne!(simple_random([1; 32], 1000, 5).unwrap(), simple_random([2; 32], 1000, 5).unwrap());
+        assert!(simple_random([7; 32], 3, 4).is_err());
+    }
+
+    #[test]
+    fn monetary_unit_sampling_favours_large_amounts() {
+        let amounts = [100, 1_000_000, 100, 0, 100];
+        for seed in 0..20u8 {
+            let picked = monetary_unit([seed; 32], &amounts, 2).unwrap();
+            assert!(picked.contains(&1), "the large transaction was not picked with seed {}", seed);
+            assert!(!picked.contains(&3), "a transaction with no amount was picked");
+        }
+        assert_eq!(monetary_unit([0; 32], &[5, 5], 10).unwrap(), vec![0, 1]);
+        assert!(monetary_unit([0; 32], &[5, -5], 1).is_err());
+    }
+
+    #[test]
+    fn a_sample_is_drawn_from_the_checkpoint_and_verifies() {
+        let operator = Account::generate();
+        let journal = chain(&Account::generate(), 8);
+        let sample = AuditSample::draw(checkpoint(&operator, &journal, 6), &journal, SampleMethod::Random, 3).unwrap();
+        assert_eq!(sample.items.len(), 3);
+        assert!(sample.items.iter().all(|item| item.proof.leaf_index < 6));
+        sample.verify(&DidKeyResolver).unwrap();
+        sample.check_selection(&journal).unwrap();
+
+        let again = AuditSample::draw(sample.checkpoint.clone(), &journal, SampleMethod::Random, 3).unwrap();
+        assert_eq!(again.leaf_indices(), sample.leaf_indices());
+    }
+
+    #[test]
+    fn a_hand_picked_sample_is_refused() {
+        let operator = Account::generate();
+        let journal = chain(&Account::generate(), 8);
+        let sample = AuditSample::draw(checkpoint(&operator, &journal, 8), &journal, SampleMethod::Random, 2).unwrap();
+
+        let mut swapped = sample.clone();
+        let other = (0..8).find(|index| !sample.leaf_indices().contains(index)).unwrap();
+        swapped.items[0] = SampleItem { transaction: journal[other].clone(), proof: sample.items[0].proof.clone() };
+        assert!(swapped.verify(&DidKeyResolver).is_err());
+
+        let mut reproven = sample.clone();
+        let mut tree = MerkleTree::new();
+        for signed_tx in &journal {
+            tree.push(signed_tx.payload.get_hash());
+        }
+        reproven.items[0] = SampleItem { transaction: journal[other].clone(), proof: tree.prove_leaf(other, 8).unwrap() };
+        assert!(reproven.verify(&DidKeyResolver).unwrap_err().to_string().contains("the seed picks"));
+
+        let mut dropped = sample.clone();
+        dropped.items.pop();
+        assert!(dropped.verify(&DidKeyResolver).is_err());
+
+        let mut reseeded = sample;
+        reseeded.seed = hex::encode([0u8; 32]);
+        assert!(reseeded.verify(&DidKeyResolver).unwrap_err().to_string().contains("not its checkpoint's digest"));
+    }
+
+    #[test]
+    fn a_monetary_unit_sample_is_checked_against_the_journal() {
+        let author = Account::generate();
+        let mut journal: Vec<SignedTransaction> = Vec::new();
+        for (sequence, amount) in ["1.00", "9000.00", "1.00", "2.00"].into_iter().enumerate() {
+            let builder = TransactionBuilder::new()
+                .timestamp(1_700_000_000 + sequence as u64)
+                .entry("10100", amount, "0.00")
+                .entry("30100", "0.00", amount)
+                .memo("Sale")
+                .sequence(sequence as u64);
+            let builder = match journal.last() {
+                Some(prev) => builder.follows(&prev.payload),
+                None => builder.genesis(),
+            };
+            journal.push(builder.sign(&author).unwrap());
+        }
+        let operator = Account::generate();
+        let sample = AuditSample::draw(checkpoint(&operator, &journal, 4), &journal, SampleMethod::MonetaryUnit, 2).unwrap();
+        assert!(sample.leaf_indices().contains(&1));
+        sample.verify(&DidKeyResolver).unwrap();
+        sample.check_selection(&journal).unwrap();
+
+        let mut picked_small = sample;
+        picked_small.items.retain(|item| item.proof.leaf_index != 1);
+        assert!(picked_small.check_selection(&journal).is_err());
+    }
+}
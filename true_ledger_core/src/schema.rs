@@ -0,0 +1,329 @@
+//! JSON Schema
+//! The published schema of a signed transaction file, generated from the
+//! model types so it cannot drift from what we actually read. Incoming files
+//! are checked against it before serde sees them: a malformed submission gets
+//! every problem at once, each at the JSON Pointer of the offending value,
+//! instead of serde's first complaint by line and column.
+//!
+//! The validator covers the draft-07 keywords schemars emits for our types;
+//! `format` is an annotation only (the timestamp parser checks date-times).
+
+use std::fmt;
+use std::sync::OnceLock;
+
+use serde_json::Value;
+
+use crate::canonical::MAX_SAFE_INTEGER;
+use crate::error::LedgerError;
+use crate::model::SignedTransaction;
+
+/// The JSON Schema (draft-07) of our own envelope
+pub fn signed_transaction_schema() -> &'static Value {
+    static SCHEMA: OnceLock<Value> = OnceLock::new();
+    SCHEMA.get_or_init(|| {
+        let mut schema = serde_json::to_value(schemars::schema_for!(SignedTransaction)).expect("schemas serialize");
+        schema["description"] = Value::from("A transaction payload, its author's signature and any cosignatures");
+        limit_integers(&mut schema);
+        schema
+    })
+}
+
+/// Caps every 64-bit integer at what JCS can represent exactly (see
+/// canonical.rs), so an out-of-range number is refused with the others
+fn limit_integers(schema: &mut Value) {
+    match schema {
+        Value::Object(fields) => {
+            let format = fields.get("format").and_then(Value::as_str).unwrap_or_default().to_string();
+            if matches!(format.as_str(), "uint64" | "int64" | "uint" | "int") {
+                fields.insert("maximum".to_string(), Value::from(MAX_SAFE_INTEGER));
+                if format.starts_with("int") {
+                    fields.insert("minimum".to_string(), Value::from(-(MAX_SAFE_INTEGER as i64)));
+                }
+            }
+            fields.values_mut().for_each(limit_integers);
+        }
+        Value::Array(items) => items.iter_mut().for_each(limit_integers),
+        _ => {}
+    }
+}
+
+/// Checks a parsed signed transaction file against the schema
+pub fn check_signed_transaction(instance: &Value) -> Result<(), LedgerError> {
+    let errors = validate(signed_transaction_schema(), instance);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    let lines: Vec<String> = errors.iter().map(|e| format!("  {}", e)).collect();
+    Err(LedgerError::Serialization(format!("Transaction does not match the signed-transaction schema:\n{}", lines.join("\n"))))
+}
+
+/// One way an instance breaks a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    pub path: String,    // JSON Pointer to the offending value ("" is the document)
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+/// Every error in `instance` against `schema`, whose `$ref`s point into its own `definitions`
+pub fn validate(schema: &Value, instance: &Value) -> Vec<SchemaError> {
+    let mut errors = Vec::new();
+    Validator { root: schema }.check(schema, instance, "", &mut errors);
+    errors
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+}
+
+impl Validator<'_> {
+    fn check(&self, schema: &Value, instance: &Value, path: &str, errors: &mut Vec<SchemaError>) {
+        let error = |errors: &mut Vec<SchemaError>, message: String| errors.push(SchemaError { path: path.to_string(), message });
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            match self.resolve(reference) {
+                Some(target) => self.check(target, instance, path, errors),
+                None => error(errors, format!("schema reference {} does not resolve", reference)),
+            }
+        }
+
+        if let Some(types) = schema.get("type") {
+            let allowed: Vec<&str> = match types {
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                other => other.as_str().into_iter().collect(),
+            };
+            if !allowed.iter().any(|t| has_type(instance, t)) {
+                // Nothing below applies to a value of the wrong type
+                return error(errors, format!("expected {}, found {}", allowed.join(" or "), describe(instance)));
+            }
+        }
+
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(instance) {
+                let options: Vec<String> = options.iter().map(Value::to_string).collect();
+                error(errors, format!("{} is not one of {}", instance, options.join(", ")));
+            }
+        }
+
+        if let (Some(minimum), Some(number)) = (schema.get("minimum").and_then(Value::as_f64), instance.as_f64()) {
+            if number < minimum {
+                error(errors, format!("{} is less than the minimum {}", instance, minimum));
+            }
+        }
+        if let (Some(maximum), Some(number)) = (schema.get("maximum").and_then(Value::as_f64), instance.as_f64()) {
+            if number > maximum {
+                error(errors, format!("{} is more than the maximum {}", instance, maximum));
+            }
+        }
+
+        if let Value::Object(fields) = instance {
+            for name in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+                if !fields.contains_key(name) {
+                    error(errors, format!("missing required field `{}`", name));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (name, value) in fields {
+                let field_path = format!("{}/{}", path, name.replace('~', "~0").replace('/', "~1"));
+                match (properties.and_then(|p| p.get(name)), schema.get("additionalProperties")) {
+                    (Some(property), _) => self.check(property, value, &field_path, errors),
+                    (None, Some(Value::Bool(false))) => errors.push(SchemaError { path: field_path, message: "field is not allowed".to_string() }),
+                    (None, Some(additional @ Value::Object(_))) => self.check(additional, value, &field_path, errors),
+                    (None, _) => {}
+                }
+            }
+        }
+
+        if let (Value::Array(items), Some(item_schema)) = (instance, schema.get("items")) {
+            for (index, item) in items.iter().enumerate() {
+                self.check(item_schema, item, &format!("{}/{}", path, index), errors);
+            }
+        }
+
+        for subschema in schema.get("allOf").and_then(Value::as_array).into_iter().flatten() {
+            self.check(subschema, instance, path, errors);
+        }
+        if let Some(options) = schema.get("anyOf").and_then(Value::as_array) {
+            self.check_alternatives(options, instance, path, errors, false);
+        }
+        if let Some(options) = schema.get("oneOf").and_then(Value::as_array) {
+            self.check_alternatives(options, instance, path, errors, true);
+        }
+    }
+
+    /// anyOf / oneOf. When nothing matches, the closest alternative's errors
+    /// (the fewest, earliest on a tie) say more than "matched none".
+    fn check_alternatives(&self, options: &[Value], instance: &Value, path: &str, errors: &mut Vec<SchemaError>, exactly_one: bool) {
+        let results: Vec<Vec<SchemaError>> = options.iter().map(|option| {
+            let mut option_errors = Vec::new();
+            self.check(option, instance, path, &mut option_errors);
+            option_errors
+        }).collect();
+        let matches = results.iter().filter(|r| r.is_empty()).count();
+        if matches == 0 {
+            if let Some(closest) = results.into_iter().min_by_key(Vec::len) {
+                errors.extend(closest);
+            }
+        } else if exactly_one && matches > 1 {
+            errors.push(SchemaError { path: path.to_string(), message: format!("matches {} alternatives where exactly one is allowed", matches) });
+        }
+    }
+
+    /// Resolves `#/definitions/Name` (or any other pointer into the root schema)
+    fn resolve(&self, reference: &str) -> Option<&Value> {
+        self.root.pointer(reference.strip_prefix('#')?)
+    }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "null" => instance.is_null(),
+        "boolean" => instance.is_boolean(),
+        "object" => instance.is_object(),
+        "array" => instance.is_array(),
+        "string" => instance.is_string(),
+        "number" => instance.is_number(),
+        "integer" => instance.is_i64() || instance.is_u64() || instance.as_f64().is_some_and(|n| n.fract() == 0.0),
+        _ => false,
+    }
+}
+
+fn describe(instance: &Value) -> String {
+    match instance {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => format!("boolean {}", b),
+        Value::Number(n) => format!("number {}", n),
+        Value::String(s) => format!("string {:?}", s),
+        Value::Array(_) => "an array".to_string(),
+        Value::Object(_) => "an object".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+    use crate::test_util::signed;
+
+    use serde_json::json;
+
+    fn paths(schema: &Value, instance: Value) -> Vec<String> {
+        validate(schema, &instance).into_iter().map(|e| e.path).collect()
+    }
+
+    fn signed_value() -> Value {
+        serde_json::to_value(signed(&Account::generate(), None, 0, "Opening")).unwrap()
+    }
+
+    #[test]
+    fn a_signed_transaction_matches() {
+        assert!(check_signed_transaction(&signed_value()).is_ok());
+    }
+
+    #[test]
+    fn integers_jcs_cannot_represent_are_refused() {
+        let mut instance = signed_value();
+        instance["payload"]["sequence"] = Value::from(MAX_SAFE_INTEGER);
+        assert!(check_signed_transaction(&instance).is_ok());
+        instance["payload"]["sequence"] = Value::from(MAX_SAFE_INTEGER + 1);
+        let errors = validate(signed_transaction_schema(), &instance);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].path, "/payload/sequence");
+    }
+
+    #[test]
+    fn required_fields_are_reported_at_their_object() {
+        let schema = json!({ "type": "object", "required": ["a", "b"], "properties": { "a": { "type": "string" } } });
+        let errors = validate(&schema, &json!({}));
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["missing required field `a`", "missing required field `b`"]);
+        assert!(errors.iter().all(|e| e.path.is_empty()));
+        assert!(validate(&schema, &json!({ "a": "x", "b": 1 })).is_empty());
+    }
+
+    #[test]
+    fn additional_properties() {
+        let closed = json!({ "type": "object", "properties": { "a": {} }, "additionalProperties": false });
+        assert_eq!(paths(&closed, json!({ "a": 1, "b": 2, "c/d": 3 })), ["/b", "/c~1d"]);
+        let typed = json!({ "type": "object", "additionalProperties": { "type": "integer" } });
+        assert!(validate(&typed, &json!({ "x": 1, "y": 2 })).is_empty());
+        assert_eq!(paths(&typed, json!({ "x": 1, "y": "two" })), ["/y"]);
+        let open = json!({ "type": "object", "properties": { "a": {} } });
+        assert!(validate(&open, &json!({ "b": 2 })).is_empty());
+    }
+
+    #[test]
+    fn any_of_needs_one_match_and_reports_the_closest_miss() {
+        let schema = json!({ "anyOf": [
+            { "type": "string" },
+            { "type": "object", "required": ["a", "b", "c"] },
+            { "type": "object", "required": ["a"] },
+        ] });
+        assert!(validate(&schema, &json!("text")).is_empty());
+        assert!(validate(&schema, &json!({ "a": 1 })).is_empty());
+        // The string alternative also fails only once, and comes first
+        let errors = validate(&schema, &json!({}));
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].message, "expected string, found an object");
+        let errors = validate(&json!({ "anyOf": schema["anyOf"].as_array().unwrap()[1..] }), &json!({ "b": 2 }));
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert_eq!(errors[0].message, "missing required field `a`");
+        assert!(!validate(&schema, &json!(5)).is_empty());
+    }
+
+    #[test]
+    fn one_of_refuses_several_matches() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "type": "number", "minimum": 10 }] });
+        assert!(validate(&schema, &json!(3)).is_empty());
+        assert!(validate(&schema, &json!(10.5)).is_empty());
+        let errors = validate(&schema, &json!(12));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "matches 2 alternatives where exactly one is allowed");
+        assert!(!validate(&schema, &json!("12")).is_empty());
+    }
+
+    #[test]
+    fn nested_references_resolve_against_the_root() {
+        let schema = json!({
+            "$ref": "#/definitions/Outer",
+            "definitions": {
+                "Outer": { "type": "object", "required": ["inner"], "properties": {
+                    "inner": { "type": "array", "items": { "$ref": "#/definitions/Inner" } },
+                } },
+                "Inner": { "type": "object", "additionalProperties": false, "properties": {
+                    "amount": { "anyOf": [{ "$ref": "#/definitions/Amount" }, { "type": "null" }] },
+                } },
+                "Amount": { "type": "string", "enum": ["1.00", "2.00"] },
+            },
+        });
+        assert!(validate(&schema, &json!({ "inner": [{ "amount": "1.00" }, { "amount": null }, {}] })).is_empty());
+        assert_eq!(paths(&schema, json!({ "inner": [{ "amount": "3.00" }, { "extra": 1 }] })), ["/inner/0/amount", "/inner/1/extra"]);
+        let broken = json!({ "$ref": "#/definitions/Missing" });
+        assert_eq!(validate(&broken, &json!(1))[0].message, "schema reference #/definitions/Missing does not resolve");
+    }
+
+    #[test]
+    fn a_wrong_type_stops_further_checks() {
+        let schema = json!({ "type": "object", "required": ["a"] });
+        let errors = validate(&schema, &json!([1]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "expected object, found an array");
+    }
+
+    #[test]
+    fn every_problem_in_a_transaction_is_reported() {
+        let mut instance = signed_value();
+        instance["payload"]["entries"][0]["debit"] = json!(10);
+        instance["payload"]["surprise"] = json!(true);
+        instance.as_object_mut().unwrap().remove("signature");
+        let errors = check_signed_transaction(&instance).unwrap_err().to_string();
+        assert!(errors.contains("/payload/entries/0/debit"), "{}", errors);
+        assert!(errors.contains("missing required field `signature`"), "{}", errors);
+    }
+}
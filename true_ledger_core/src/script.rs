@@ -0,0 +1,137 @@
+//! Scripted Posting Rules
+//! A posting rule can be a Rhai script, for policies the built-in rules do
+//! not cover. The script sees the transaction as `tx` and the rules file's
+//! DID groups as `groups`:
+//!
+//! ```rhai
+//! for e in tx.entries {
+//!     if e.account_id.starts_with("50") && e.debit > 0.0 && !(tx.author_did in groups.finance) {
+//!         return `${e.account_id} may only be debited by finance`;
+//!     }
+//! }
+//! ```
+//!
+//! A script passes by finishing with `()` or `true`; it rejects the
+//! transaction by returning a message (or `false`), or with `throw`.
+//!
+//! Scripts are compiled once at startup and run sandboxed: no modules, no
+//! `eval`, no output, bounded operations, nesting and sizes, and a
+//! wall-clock limit per transaction.
+
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map, Scope, AST};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::amount::parse_cents;
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::validation::ValidationRule;
+
+/// Default wall-clock limit for one script run
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(100);
+
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// A posting rule written in Rhai
+pub struct ScriptRule {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    groups: Dynamic,              // Group name -> array of DIDs
+    timeout: Duration,
+    deadline: Rc<Cell<Instant>>, // Read by the engine's progress callback
+}
+
+impl ScriptRule {
+    /// Loads and compiles a script file
+    pub fn load(name: &str, path: &str, groups: &BTreeMap<String, Vec<String>>, timeout: Duration) -> Result<Self, LedgerError> {
+        let source = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read rule script {}: {}", path, e)))?;
+        Self::compile(name, &source, groups, timeout).map_err(|e| e.context(path))
+    }
+
+    pub fn compile(name: &str, source: &str, groups: &BTreeMap<String, Vec<String>>, timeout: Duration) -> Result<Self, LedgerError> {
+        let deadline = Rc::new(Cell::new(Instant::now()));
+        let engine = sandboxed_engine(deadline.clone());
+        let ast = engine.compile(source).map_err(|e| LedgerError::Config(format!("Rule script {} does not compile: {}", name, e)))?;
+        let groups: Map = groups.iter()
+            .map(|(group, dids)| (group.into(), Dynamic::from_array(dids.iter().cloned().map(Dynamic::from).collect())))
+            .collect();
+        Ok(ScriptRule { name: name.to_string(), engine, ast, groups: Dynamic::from_map(groups), timeout, deadline })
+    }
+}
+
+fn sandboxed_engine(deadline: Rc<Cell<Instant>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_module_resolver(DummyModuleResolver::new());
+    engine.disable_symbol("eval");
+    engine.on_print(|_| {});
+    engine.on_debug(|_, _, _| {});
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(10_000);
+    engine.on_progress(move |_| (Instant::now() > deadline.get()).then(|| Dynamic::from("timeout")));
+    engine
+}
+
+/// The transaction as the script sees it. Amounts come both as decimals
+/// (`debit`) and as exact integer cents (`debit_cents`).
+fn script_view(tx: &Transaction) -> Result<Dynamic, LedgerError> {
+    let mut entries = Array::new();
+    for entry in &tx.entries {
+        let (debit, credit) = (parse_cents(&entry.debit)?, parse_cents(&entry.credit)?);
+        let mut map = Map::new();
+        map.insert("account_id".into(), entry.account_id.clone().into());
+        map.insert("debit".into(), (debit as f64 / 100.0).into());
+        map.insert("credit".into(), (credit as f64 / 100.0).into());
+        map.insert("debit_cents".into(), debit.into());
+        map.insert("credit_cents".into(), credit.into());
+        map.insert("currency".into(), entry.currency.clone().map_or(Dynamic::UNIT, Dynamic::from));
+        entries.push(map.into());
+    }
+    let optional = |value: Option<u64>| value.map_or(Dynamic::UNIT, |v| Dynamic::from(v as i64));
+    let mut map = Map::new();
+    map.insert("author_did".into(), tx.author_did.clone().into());
+    map.insert("memo".into(), tx.memo.clone().into());
+    map.insert("timestamp".into(), (tx.timestamp.unix() as i64).into());
+    map.insert("date".into(), tx.timestamp.local_date().format("%Y-%m-%d").to_string().into());
+    map.insert("height".into(), optional(tx.height));
+    map.insert("sequence".into(), optional(tx.sequence));
+    map.insert("entries".into(), entries.into());
+    Ok(map.into())
+}
+
+impl ValidationRule for ScriptRule {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let mut scope = Scope::new();
+        scope.push_constant("tx", script_view(tx)?);
+        scope.push_constant("groups", self.groups.clone());
+        self.deadline.set(Instant::now() + self.timeout);
+
+        let rejected = |message: String| Err(LedgerError::Approval(message));
+        match self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast) {
+            Ok(result) if result.is_unit() => Ok(()),
+            Ok(result) => match result.as_bool() {
+                Ok(true) => Ok(()),
+                Ok(false) => rejected(format!("Rejected by {}", self.name)),
+                Err(_) => rejected(result.to_string()),
+            },
+            Err(error) => match *error {
+                EvalAltResult::ErrorRuntime(thrown, _) => rejected(thrown.to_string()),
+                EvalAltResult::ErrorTerminated(..) => Err(LedgerError::Config(format!(
+                    "Rule script {} ran longer than {} ms", self.name, self.timeout.as_millis()))),
+                other => Err(LedgerError::Config(format!("Rule script {} failed: {}", self.name, other))),
+            },
+        }
+    }
+}
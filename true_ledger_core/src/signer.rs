@@ -0,0 +1,32 @@
+//! Signing Backends
+//! A transaction can be signed by a locally held Account or by a key that
+//! lives elsewhere (e.g. the user's ssh-agent). Every backend produces a plain
+//! Ed25519 signature over the transaction hash, so verification is identical.
+
+use crate::error::LedgerError;
+use crate::identity::Account;
+use crate::model::{SignedTransaction, Transaction};
+
+/// Something that can sign transactions on behalf of a did:key
+pub trait TransactionSigner {
+    fn did(&self) -> &str;
+    fn sign_transaction(&self, tx: Transaction) -> Result<SignedTransaction, LedgerError>;
+
+    /// Signs arbitrary bytes, for envelopes other than our own: Ed25519 over
+    /// the message itself, ES256K over its SHA-256
+    fn sign_bytes(&self, message: &[u8]) -> Result<Vec<u8>, LedgerError>;
+}
+
+impl TransactionSigner for Account {
+    fn did(&self) -> &str {
+        &self.did
+    }
+
+    fn sign_transaction(&self, tx: Transaction) -> Result<SignedTransaction, LedgerError> {
+        Ok(self.sign(tx))
+    }
+
+    fn sign_bytes(&self, message: &[u8]) -> Result<Vec<u8>, LedgerError> {
+        Ok(Account::sign_bytes(self, message))
+    }
+}
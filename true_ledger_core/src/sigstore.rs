@@ -0,0 +1,606 @@
+//! Sigstore Keyless Envelope (experimental)
+//! Authorship bound to a corporate SSO identity rather than a long-lived
+//! key. The signer presents an OIDC identity token to Fulcio, which issues a
+//! certificate for an ephemeral P-256 key that lasts minutes; the
+//! transaction's JCS form is signed with that key, the key is thrown away,
+//! and the signature is recorded in the Rekor transparency log, whose signed
+//! entry timestamp proves it was made while the certificate was valid:
+//!
+//! ```json
+//! { "payload": { "author_did": "mailto:alice@corp.example", ... },
+//!   "sigstore": { "chain": ["-----BEGIN CERTIFICATE-----..."],
+//!                 "signature": "MEUCIQ...",
+//!                 "rekor": { "uuid": "...", "log_id": "...", "log_index": 1234,
+//!                            "integrated_time": 1700000000, "body": "...",
+//!                            "signed_entry_timestamp": "MEQCI..." } } }
+//! ```
+//!
+//! The author is not a DID but the certificate's identity as a URI:
+//! `mailto:` and the address for a person, the workflow URI for CI. ACLs and
+//! signing policies name it like any other signer.
+//!
+//! Verification is offline, against a trust root: the certificates Fulcio
+//! chains end in, the Rekor log's public keys and, optionally, the only OIDC
+//! issuers to accept (a company's own SSO). It is read from
+//! `$TLC_SIGSTORE_TRUST`, otherwise `~/.tlc/sigstore/trust.toml`:
+//!
+//! ```toml
+//! fulcio = "fulcio.pem"   # curl https://fulcio.sigstore.dev/api/v1/rootCert
+//! rekor = "rekor.pem"     # curl https://rekor.sigstore.dev/api/v1/log/publicKey
+//! oidc_issuers = ["https://sso.corp.example"]
+//! ```
+//!
+//! The log's inclusion proof is not checked, only its signed promise to
+//! include the entry (as `cosign verify` does offline).
+//!
+//! Built only with the `sigstore` feature.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use p256::ecdsa::signature::{Signer, Verifier};
+use p256::ecdsa::{Signature, SigningKey, VerifyingKey};
+use p256::pkcs8::{DecodePublicKey, EncodePublicKey, LineEnding};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use x509_parser::der_parser::der::parse_der_utf8string;
+use x509_parser::extensions::GeneralName;
+use x509_parser::oid_registry::Oid;
+use x509_parser::pem::Pem;
+use x509_parser::prelude::X509Certificate;
+use zeroize::Zeroizing;
+
+use crate::canonical::to_jcs;
+use crate::error::LedgerError;
+use crate::model::Transaction;
+
+/// Fulcio's OIDC issuer extension: a DER UTF8String, and its older raw form
+const ISSUER_OID: &str = "1.3.6.1.4.1.57264.1.8";
+const ISSUER_V1_OID: &str = "1.3.6.1.4.1.57264.1.1";
+
+/// A hashedrekord entry as Rekor integrated it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RekorEntry {
+    pub uuid: String,
+    pub log_id: String, // Hex SHA-256 of the log's public key (DER)
+    pub log_index: u64,
+    pub integrated_time: i64,           // Unix seconds
+    pub body: String,                   // Base64 of the entry as logged
+    pub signed_entry_timestamp: String, // Base64 DER ECDSA by the log's key
+}
+
+/// The keyless signature over a payload
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SigstoreSignature {
+    pub chain: Vec<String>, // PEM certificates, Fulcio's leaf first
+    pub signature: String,  // Base64 DER ECDSA P-256 over the payload's JCS form
+    pub rekor: RekorEntry,
+}
+
+/// A transaction in the Sigstore envelope
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SigstoreTransaction {
+    pub payload: Transaction,
+    pub sigstore: SigstoreSignature,
+}
+
+/// What keyless signatures are checked against
+pub struct SigstoreTrust {
+    pub fulcio_roots: Vec<Vec<u8>>, // DER certificates a chain may end in
+    pub rekor_keys: Vec<VerifyingKey>,
+    pub oidc_issuers: Vec<String>, // Empty: any issuer Fulcio vouches for
+}
+
+/// The trust file: PEM paths relative to it
+#[derive(Deserialize)]
+struct TrustFile {
+    fulcio: PathBuf,
+    rekor: PathBuf,
+    #[serde(default)]
+    oidc_issuers: Vec<String>,
+}
+
+fn invalid(what: &str) -> LedgerError {
+    LedgerError::Signature(format!("Invalid Sigstore signature: {}", what))
+}
+
+fn pem_blocks(pem: &[u8], what: &str) -> Result<Vec<Pem>, LedgerError> {
+    let blocks: Vec<Pem> = Pem::iter_from_buffer(pem).collect::<Result<_, _>>()
+        .map_err(|e| LedgerError::Serialization(format!("Invalid PEM in {}: {}", what, e)))?;
+    match blocks.is_empty() {
+        true => Err(LedgerError::Serialization(format!("No PEM blocks in {}", what))),
+        false => Ok(blocks),
+    }
+}
+
+fn parse_certificate<'a>(pem: &'a Pem, what: &str) -> Result<X509Certificate<'a>, LedgerError> {
+    pem.parse_x509().map_err(|e| LedgerError::Serialization(format!("Invalid certificate in {}: {}", what, e)))
+}
+
+impl SigstoreTrust {
+    /// From PEM: Fulcio's root (and intermediate) certificates and Rekor's public keys
+    pub fn from_pem(fulcio: &[u8], rekor: &[u8], oidc_issuers: Vec<String>) -> Result<Self, LedgerError> {
+        let fulcio_roots = pem_blocks(fulcio, "the Fulcio roots")?.into_iter()
+            .map(|pem| parse_certificate(&pem, "the Fulcio roots").map(|_| pem.contents.clone()))
+            .collect::<Result<_, _>>()?;
+        let rekor_keys = pem_blocks(rekor, "the Rekor keys")?.iter()
+            .map(|pem| VerifyingKey::from_public_key_der(&pem.contents)
+                .map_err(|e| LedgerError::Key(format!("Rekor keys must be P-256 public keys: {}", e))))
+            .collect::<Result<_, _>>()?;
+        Ok(SigstoreTrust { fulcio_roots, rekor_keys, oidc_issuers })
+    }
+
+    /// Reads a trust file (see the module docs)
+    pub fn load(path: &Path) -> Result<Self, LedgerError> {
+        let read = |path: &Path| fs::read(path).map_err(|e| LedgerError::Io(format!("Could not read {}: {}", path.display(), e)));
+        let file: TrustFile = toml::from_str(&String::from_utf8_lossy(&read(path)?))
+            .map_err(|e| LedgerError::Serialization(format!("Invalid Sigstore trust file {}: {}", path.display(), e)))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::from_pem(&read(&dir.join(file.fulcio))?, &read(&dir.join(file.rekor))?, file.oidc_issuers)
+    }
+
+    /// `$TLC_SIGSTORE_TRUST` if set, otherwise `~/.tlc/sigstore/trust.toml`
+    pub fn default_path() -> PathBuf {
+        match std::env::var_os("TLC_SIGSTORE_TRUST") {
+            Some(path) => PathBuf::from(path),
+            None => crate::tlc_home().join("sigstore").join("trust.toml"),
+        }
+    }
+}
+
+/// Fulcio and Rekor, as the signer reaches them
+pub trait SigstoreClient {
+    /// A certificate chain (PEM, leaf first) for `public_key_pem`, given an
+    /// identity token and the key's signature over the token's subject
+    fn signing_certificate(&self, id_token: &str, public_key_pem: &str, proof: &[u8]) -> Result<Vec<String>, LedgerError>;
+
+    /// Logs a hashedrekord entry, returning it as integrated
+    fn log_entry(&self, entry: &Value) -> Result<RekorEntry, LedgerError>;
+}
+
+/// A Fulcio and a Rekor reached over HTTPS
+pub struct SigstoreServers {
+    pub fulcio_url: String,
+    pub rekor_url: String,
+}
+
+impl SigstoreServers {
+    /// The Sigstore project's public instances
+    pub fn public_good() -> Self {
+        SigstoreServers {
+            fulcio_url: "https://fulcio.sigstore.dev".to_string(),
+            rekor_url: "https://rekor.sigstore.dev".to_string(),
+        }
+    }
+
+    fn post(&self, url: &str, body: Value) -> Result<Value, LedgerError> {
+        let agent = ureq::AgentBuilder::new().timeout(std::time::Duration::from_secs(30)).build();
+        let reply = agent.post(url).set("Content-Type", "application/json").send_string(&body.to_string())
+            .map_err(|e| LedgerError::Io(format!("{}: {}", url, e)))?
+            .into_string()
+            .map_err(|e| LedgerError::Io(format!("Could not read the reply from {}: {}", url, e)))?;
+        serde_json::from_str(&reply).map_err(|e| LedgerError::Serialization(format!("{} did not reply with JSON: {}", url, e)))
+    }
+}
+
+impl SigstoreClient for SigstoreServers {
+    fn signing_certificate(&self, id_token: &str, public_key_pem: &str, proof: &[u8]) -> Result<Vec<String>, LedgerError> {
+        let url = format!("{}/api/v2/signingCert", self.fulcio_url.trim_end_matches('/'));
+        let reply = self.post(&url, json!({
+            "credentials": { "oidcIdentityToken": id_token },
+            "publicKeyRequest": {
+                "publicKey": { "algorithm": "ECDSA", "content": public_key_pem },
+                "proofOfPossession": STANDARD.encode(proof),
+            },
+        }))?;
+        let signed = match reply.get("signedCertificateEmbeddedSct") {
+            Some(signed) => signed,
+            None => &reply["signedCertificateDetachedSct"],
+        };
+        signed["chain"]["certificates"].as_array()
+            .and_then(|chain| chain.iter().map(|pem| pem.as_str().map(str::to_string)).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| LedgerError::Serialization(format!("{} did not return a certificate chain", url)))
+    }
+
+    fn log_entry(&self, entry: &Value) -> Result<RekorEntry, LedgerError> {
+        let url = format!("{}/api/v1/log/entries", self.rekor_url.trim_end_matches('/'));
+        let reply = self.post(&url, entry.clone())?;
+        let malformed = || LedgerError::Serialization(format!("{} returned a malformed log entry", url));
+        let (uuid, logged) = reply.as_object().and_then(|entries| entries.iter().next()).ok_or_else(malformed)?;
+        Ok(RekorEntry {
+            uuid: uuid.clone(),
+            log_id: logged["logID"].as_str().ok_or_else(malformed)?.to_string(),
+            log_index: logged["logIndex"].as_u64().ok_or_else(malformed)?,
+            integrated_time: logged["integratedTime"].as_i64().ok_or_else(malformed)?,
+            body: logged["body"].as_str().ok_or_else(malformed)?.to_string(),
+            signed_entry_timestamp: logged["verification"]["signedEntryTimestamp"].as_str().ok_or_else(malformed)?.to_string(),
+        })
+    }
+}
+
+/// The subject Fulcio expects a proof of possession over: the token's
+/// `email` claim if it has one, otherwise its `sub`. The token itself is
+/// checked by Fulcio.
+fn token_subject(id_token: &str) -> Result<String, LedgerError> {
+    let claims = id_token.split('.').nth(1)
+        .and_then(|claims| URL_SAFE_NO_PAD.decode(claims.trim_end_matches('=')).ok())
+        .and_then(|claims| serde_json::from_slice::<Value>(&claims).ok())
+        .ok_or_else(|| LedgerError::Key("The OIDC identity token is not a JWT".to_string()))?;
+    claims["email"].as_str().or(claims["sub"].as_str()).map(str::to_string)
+        .ok_or_else(|| LedgerError::Key("The OIDC identity token has neither an email nor a sub claim".to_string()))
+}
+
+fn ephemeral_key() -> SigningKey {
+    loop {
+        // Almost every 32-byte string is a valid scalar; retry on the rare miss
+        let mut secret = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(&mut secret[..]);
+        if let Ok(key) = SigningKey::from_slice(&secret[..]) {
+            break key;
+        }
+    }
+}
+
+/// The hashedrekord entry logging `signature` over the payload with `digest`
+fn hashedrekord(signature: &str, leaf_pem: &str, digest: &[u8]) -> Value {
+    json!({
+        "apiVersion": "0.0.1",
+        "kind": "hashedrekord",
+        "spec": {
+            "signature": { "content": signature, "publicKey": { "content": STANDARD.encode(leaf_pem) } },
+            "data": { "hash": { "algorithm": "sha256", "value": hex::encode(digest) } },
+        },
+    })
+}
+
+/// The identity a Fulcio certificate was issued to, as a URI
+fn certificate_identity(cert: &X509Certificate) -> Result<String, LedgerError> {
+    let san = cert.subject_alternative_name().ok().flatten()
+        .ok_or_else(|| invalid("the certificate names no identity"))?;
+    san.value.general_names.iter().find_map(|name| match name {
+        GeneralName::RFC822Name(email) => Some(format!("mailto:{}", email)),
+        GeneralName::URI(uri) => Some(uri.to_string()),
+        _ => None,
+    }).ok_or_else(|| invalid("the certificate names neither an email address nor a URI"))
+}
+
+/// The OIDC issuer that vouched for the certificate's identity
+fn certificate_issuer(cert: &X509Certificate) -> Result<String, LedgerError> {
+    let oid = |oid: &str| Oid::from(&oid.split('.').map(|arc| arc.parse().unwrap_or(0)).collect::<Vec<u64>>()[..]).ok();
+    let extensions = cert.extensions();
+    if let Some(ext) = oid(ISSUER_OID).and_then(|oid| extensions.iter().find(|ext| ext.oid == oid)) {
+        let (_, issuer) = parse_der_utf8string(ext.value).map_err(|_| invalid("unreadable OIDC issuer"))?;
+        return issuer.as_str().map(str::to_string).map_err(|_| invalid("unreadable OIDC issuer"));
+    }
+    oid(ISSUER_V1_OID).and_then(|oid| extensions.iter().find(|ext| ext.oid == oid))
+        .and_then(|ext| String::from_utf8(ext.value.to_vec()).ok())
+        .ok_or_else(|| invalid("the certificate names no OIDC issuer"))
+}
+
+/// Signs `tx` keyless: `id_token` gets a certificate from Fulcio for a key
+/// used once, and the signature is logged in Rekor. An empty `author_did`
+/// is filled in with the certificate's identity; any other value must be
+/// it. The result is checked against `trust` before it is returned.
+pub fn sign_keyless(tx: Transaction, id_token: &str, client: &dyn SigstoreClient, trust: &SigstoreTrust)
+    -> Result<SigstoreTransaction, LedgerError> {
+    let mut tx = tx.prepare_for_signing();
+    let key = ephemeral_key();
+    let public_key_pem = key.verifying_key().to_public_key_pem(LineEnding::LF)
+        .map_err(|e| LedgerError::Key(format!("Could not encode the ephemeral key: {}", e)))?;
+    let proof: Signature = key.sign(token_subject(id_token)?.as_bytes());
+    let chain = client.signing_certificate(id_token, &public_key_pem, proof.to_der().as_bytes())?;
+
+    let leaf_pem = chain.first().ok_or_else(|| LedgerError::Key("Fulcio returned an empty certificate chain".to_string()))?;
+    let leaf = pem_blocks(leaf_pem.as_bytes(), "Fulcio's certificate")?.swap_remove(0);
+    let identity = certificate_identity(&parse_certificate(&leaf, "Fulcio's certificate")?)?;
+    if tx.author_did.is_empty() {
+        tx.author_did = identity;
+    } else if tx.author_did != identity {
+        return Err(LedgerError::Key(format!("Transaction names author {} but the certificate was issued to {}", tx.author_did, identity)));
+    }
+
+    let message = to_jcs(&tx)?;
+    let signature: Signature = key.sign(message.as_bytes());
+    let signature = STANDARD.encode(signature.to_der().as_bytes());
+    let rekor = client.log_entry(&hashedrekord(&signature, leaf_pem, &Sha256::digest(message.as_bytes())))?;
+    let signed = SigstoreTransaction { payload: tx, sigstore: SigstoreSignature { chain, signature, rekor } };
+    signed.verify_with(trust)?;
+    Ok(signed)
+}
+
+impl SigstoreTransaction {
+    pub fn from_value(document: Value) -> Result<Self, LedgerError> {
+        serde_json::from_value(document)
+            .map_err(|e| LedgerError::Serialization(format!("Failed to parse Sigstore transaction: {}", e)))
+    }
+
+    /// The signer's identity and the OIDC issuer that vouched for it
+    pub fn identity(&self) -> Result<(String, String), LedgerError> {
+        let pems = self.chain_pems()?;
+        let leaf = parse_certificate(&pems[0], "the chain")?;
+        Ok((certificate_identity(&leaf)?, certificate_issuer(&leaf)?))
+    }
+
+    fn chain_pems(&self) -> Result<Vec<Pem>, LedgerError> {
+        let pems = self.sigstore.chain.iter()
+            .map(|pem| pem_blocks(pem.as_bytes(), "the chain").map(|mut blocks| blocks.swap_remove(0)))
+            .collect::<Result<Vec<_>, _>>()?;
+        match pems.is_empty() {
+            true => Err(invalid("no certificate")),
+            false => Ok(pems),
+        }
+    }
+
+    /// Checks against the trust root configured for this machine
+    pub fn verify_signature(&self) -> Result<(), LedgerError> {
+        let path = SigstoreTrust::default_path();
+        if !path.exists() {
+            return Err(LedgerError::Signature(format!(
+                "Sigstore transactions are checked against a trust root, and there is none at {} (see TLC_SIGSTORE_TRUST)",
+                path.display())));
+        }
+        self.verify_with(&SigstoreTrust::load(&path)?)
+    }
+
+    /// Checks that the chain ends in a trusted Fulcio root, the leaf was
+    /// issued to the author by an accepted issuer, the signature over the
+    /// payload is the leaf's, and a trusted Rekor log integrated exactly this
+    /// signature while the leaf was valid
+    pub fn verify_with(&self, trust: &SigstoreTrust) -> Result<(), LedgerError> {
+        let pems = self.chain_pems()?;
+        let chain = pems.iter().map(|pem| parse_certificate(pem, "the chain")).collect::<Result<Vec<_>, _>>()?;
+        let leaf = &chain[0];
+        let at = self.sigstore.rekor.integrated_time;
+
+        // The certificate chain, valid when the log integrated the entry
+        for cert in &chain {
+            if at < cert.validity().not_before.timestamp() || at > cert.validity().not_after.timestamp() {
+                return Err(invalid("a certificate was not valid when the signature was logged"));
+            }
+        }
+        for pair in chain.windows(2) {
+            if !pair[1].is_ca() || pair[0].verify_signature(Some(pair[1].public_key())).is_err() {
+                return Err(invalid("the certificate chain is broken"));
+            }
+        }
+        let last = chain.last().expect("chain is not empty");
+        let anchored = trust.fulcio_roots.iter().any(|root| {
+            root == pems.last().expect("chain is not empty").contents.as_slice()
+                || x509_parser::parse_x509_certificate(root).is_ok_and(|(_, root)| root.is_ca()
+                    && root.subject() == last.issuer() && last.verify_signature(Some(root.public_key())).is_ok())
+        });
+        if !anchored {
+            return Err(invalid("the certificate chain does not end in a trusted Fulcio root"));
+        }
+        if !leaf.extended_key_usage().ok().flatten().is_some_and(|eku| eku.value.code_signing) {
+            return Err(invalid("the certificate is not for code signing"));
+        }
+
+        // Issued to the author, by an issuer we accept
+        let identity = certificate_identity(leaf)?;
+        if identity != self.payload.author_did {
+            return Err(LedgerError::Signature(format!("The certificate was issued to {}, not the author {}", identity, self.payload.author_did)));
+        }
+        let issuer = certificate_issuer(leaf)?;
+        if !trust.oidc_issuers.is_empty() && !trust.oidc_issuers.contains(&issuer) {
+            return Err(LedgerError::Signature(format!("The identity {} was vouched for by {}, which is not an accepted OIDC issuer",
+                identity, issuer)));
+        }
+
+        // The leaf's key signed the payload
+        let key = VerifyingKey::from_sec1_bytes(&leaf.public_key().subject_public_key.data)
+            .map_err(|_| invalid("the certificate's key is not a P-256 key"))?;
+        let signature = STANDARD.decode(&self.sigstore.signature).ok()
+            .and_then(|der| Signature::from_der(&der).ok())
+            .ok_or_else(|| invalid("unreadable signature"))?;
+        let message = to_jcs(&self.payload)?;
+        key.verify(message.as_bytes(), &signature).map_err(|_| invalid("the signature does not match the payload"))?;
+
+        self.verify_log_entry(trust, &pems[0], message.as_bytes())
+    }
+
+    /// The log entry records this signature by this certificate over this
+    /// payload, and its signed entry timestamp is by a trusted log
+    fn verify_log_entry(&self, trust: &SigstoreTrust, leaf: &Pem, message: &[u8]) -> Result<(), LedgerError> {
+        let rekor = &self.sigstore.rekor;
+        let log_key = trust.rekor_keys.iter().find(|key| key.to_public_key_der()
+                .is_ok_and(|der| hex::encode(Sha256::digest(der.as_bytes())) == rekor.log_id))
+            .ok_or_else(|| invalid("the entry is in a Rekor log that is not trusted"))?;
+        let promise = to_jcs(&json!({
+            "body": rekor.body,
+            "integratedTime": rekor.integrated_time,
+            "logID": rekor.log_id,
+            "logIndex": rekor.log_index,
+        }))?;
+        let timestamp = STANDARD.decode(&rekor.signed_entry_timestamp).ok()
+            .and_then(|der| Signature::from_der(&der).ok())
+            .ok_or_else(|| invalid("unreadable signed entry timestamp"))?;
+        log_key.verify(promise.as_bytes(), &timestamp).map_err(|_| invalid("the signed entry timestamp is not the log's"))?;
+
+        let body: Value = STANDARD.decode(&rekor.body).ok()
+            .and_then(|body| serde_json::from_slice(&body).ok())
+            .ok_or_else(|| invalid("unreadable log entry"))?;
+        let spec = &body["spec"];
+        let logged_leaf = spec["signature"]["publicKey"]["content"].as_str()
+            .and_then(|pem| STANDARD.decode(pem).ok())
+            .and_then(|pem| pem_blocks(&pem, "the log entry").ok())
+            .map(|blocks| blocks[0].contents.clone());
+        if body["kind"] != "hashedrekord"
+            || spec["data"]["hash"]["algorithm"] != "sha256"
+            || spec["data"]["hash"]["value"] != hex::encode(Sha256::digest(message))
+            || spec["signature"]["content"] != self.sigstore.signature
+            || logged_leaf.as_deref() != Some(leaf.contents.as_slice()) {
+            return Err(invalid("the log entry is for another signature"));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+    use crate::test_util::signed;
+    use rcgen::{BasicConstraints, CertificateParams, CustomExtension, ExtendedKeyUsagePurpose, IsCa, KeyPair, SanType};
+    use std::cell::RefCell;
+
+    const ISSUER: &str = "https://sso.corp.example";
+
+    /// A Fulcio CA and a Rekor log in memory, issuing to `email` certificates
+    /// valid on 2023-11-14 and logging at `now`, that day
+    struct FakeSigstore {
+        ca: rcgen::Certificate,
+        ca_key: KeyPair,
+        log_key: SigningKey,
+        email: String,
+        issuer: String,
+        now: i64,
+        logged: RefCell<u64>,
+    }
+
+    impl FakeSigstore {
+        fn new(email: &str) -> Self {
+            let ca_key = KeyPair::generate().unwrap();
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            params.distinguished_name.push(rcgen::DnType::CommonName, "sigstore-test");
+            let ca = params.self_signed(&ca_key).unwrap();
+            FakeSigstore { ca, ca_key, log_key: ephemeral_key(), email: email.to_string(), issuer: ISSUER.to_string(),
+                now: 1_700_000_000, logged: RefCell::new(0) }
+        }
+
+        fn trust(&self) -> SigstoreTrust {
+            let rekor = self.log_key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap();
+            SigstoreTrust::from_pem(self.ca.pem().as_bytes(), rekor.as_bytes(), Vec::new()).unwrap()
+        }
+
+        fn token(&self) -> String {
+            let claims = URL_SAFE_NO_PAD.encode(json!({ "email": self.email, "iss": self.issuer }).to_string());
+            format!("e30.{}.sig", claims)
+        }
+    }
+
+    impl SigstoreClient for FakeSigstore {
+        fn signing_certificate(&self, id_token: &str, public_key_pem: &str, proof: &[u8]) -> Result<Vec<String>, LedgerError> {
+            let key = VerifyingKey::from_public_key_pem(public_key_pem).unwrap();
+            key.verify(token_subject(id_token)?.as_bytes(), &Signature::from_der(proof).unwrap())
+                .map_err(|_| LedgerError::Key("bad proof of possession".to_string()))?;
+
+            let mut params = CertificateParams::new(Vec::new()).unwrap();
+            params.subject_alt_names = vec![SanType::Rfc822Name(self.email.clone().try_into().unwrap())];
+            params.extended_key_usages = vec![ExtendedKeyUsagePurpose::CodeSigning];
+            let issuer = [&[0x0c, self.issuer.len() as u8][..], self.issuer.as_bytes()].concat(); // DER UTF8String
+            params.custom_extensions = vec![CustomExtension::from_oid_content(&[1, 3, 6, 1, 4, 1, 57264, 1, 8], issuer)];
+            (params.not_before, params.not_after) = (rcgen::date_time_ymd(2023, 11, 14), rcgen::date_time_ymd(2023, 11, 15));
+            let leaf_key = rcgen::SubjectPublicKeyInfo::from_pem(public_key_pem).unwrap();
+            let leaf = params.signed_by(&leaf_key, &self.ca, &self.ca_key).unwrap();
+            Ok(vec![leaf.pem(), self.ca.pem()])
+        }
+
+        fn log_entry(&self, entry: &Value) -> Result<RekorEntry, LedgerError> {
+            let body = STANDARD.encode(entry.to_string());
+            let log_id = hex::encode(Sha256::digest(self.log_key.verifying_key().to_public_key_der().unwrap().as_bytes()));
+            let log_index = *self.logged.borrow();
+            *self.logged.borrow_mut() += 1;
+            let promise = to_jcs(&json!({ "body": body, "integratedTime": self.now, "logID": log_id, "logIndex": log_index }))?;
+            let timestamp: Signature = self.log_key.sign(promise.as_bytes());
+            Ok(RekorEntry { uuid: format!("entry-{}", log_index), log_id, log_index, integrated_time: self.now, body,
+                signed_entry_timestamp: STANDARD.encode(timestamp.to_der().as_bytes()) })
+        }
+    }
+
+    fn unsigned() -> Transaction {
+        let mut tx = signed(&Account::generate(), None, 0, "Opening").payload;
+        tx.author_did.clear();
+        tx
+    }
+
+    #[test]
+    fn a_keyless_signature_names_its_sso_identity() {
+        let sigstore = FakeSigstore::new("alice@corp.example");
+        let signed = sign_keyless(unsigned(), &sigstore.token(), &sigstore, &sigstore.trust()).unwrap();
+        assert_eq!(signed.payload.author_did, "mailto:alice@corp.example");
+        assert_eq!(signed.identity().unwrap(), ("mailto:alice@corp.example".to_string(), ISSUER.to_string()));
+        let parsed = SigstoreTransaction::from_value(serde_json::to_value(&signed).unwrap()).unwrap();
+        parsed.verify_with(&sigstore.trust()).unwrap();
+
+        let mut other = unsigned();
+        other.author_did = "mailto:bob@corp.example".to_string();
+        assert!(matches!(sign_keyless(other, &sigstore.token(), &sigstore, &sigstore.trust()), Err(LedgerError::Key(_))));
+    }
+
+    #[test]
+    fn only_trusted_roots_logs_and_issuers_are_accepted() {
+        let sigstore = FakeSigstore::new("alice@corp.example");
+        let signed = sign_keyless(unsigned(), &sigstore.token(), &sigstore, &sigstore.trust()).unwrap();
+        let elsewhere = FakeSigstore::new("alice@corp.example");
+        assert!(sign_keyless(unsigned(), &sigstore.token(), &sigstore, &elsewhere.trust()).is_err());
+
+        let mut trust = sigstore.trust();
+        trust.fulcio_roots = elsewhere.trust().fulcio_roots;
+        assert!(signed.verify_with(&trust).is_err());
+        let mut trust = sigstore.trust();
+        trust.rekor_keys = elsewhere.trust().rekor_keys;
+        assert!(signed.verify_with(&trust).is_err());
+        let mut trust = sigstore.trust();
+        trust.oidc_issuers = vec!["https://accounts.google.com".to_string()];
+        assert!(signed.verify_with(&trust).is_err());
+        trust.oidc_issuers.push(ISSUER.to_string());
+        signed.verify_with(&trust).unwrap();
+    }
+
+    #[test]
+    fn tampering_is_detected() {
+        let sigstore = FakeSigstore::new("alice@corp.example");
+        let trust = sigstore.trust();
+        let signed = sign_keyless(unsigned(), &sigstore.token(), &sigstore, &trust).unwrap();
+
+        let mut other = signed.clone();
+        other.payload.memo = "Opening balance".to_string();
+        assert!(other.verify_with(&trust).is_err());
+
+        let mut other = signed.clone();
+        other.payload.author_did = "mailto:bob@corp.example".to_string();
+        assert!(other.verify_with(&trust).is_err());
+
+        // Logged after the certificate expired
+        let mut other = signed.clone();
+        other.sigstore.rekor.integrated_time += 86_400;
+        assert!(other.verify_with(&trust).is_err());
+
+        // Another signature's log entry
+        let second = sign_keyless(unsigned(), &sigstore.token(), &sigstore, &trust).unwrap();
+        let mut other = signed.clone();
+        other.sigstore.rekor = second.sigstore.rekor.clone();
+        assert!(other.verify_with(&trust).is_err());
+
+        // Another certificate from the same CA, for another key
+        let mut other = signed.clone();
+        other.sigstore.chain[0] = second.sigstore.chain[0].clone();
+        assert!(other.verify_with(&trust).is_err());
+        let mut other = signed.clone();
+        other.sigstore.chain.truncate(1);
+        signed.verify_with(&trust).unwrap();
+        other.verify_with(&trust).unwrap(); // The leaf alone, signed by a trusted root
+        other.sigstore.chain.clear();
+        assert!(other.verify_with(&trust).is_err());
+    }
+
+    #[test]
+    fn the_trust_file_names_pem_files_beside_it() {
+        let sigstore = FakeSigstore::new("alice@corp.example");
+        let dir = crate::test_util::scratch_dir("sigstore-trust");
+        fs::write(dir.join("fulcio.pem"), sigstore.ca.pem()).unwrap();
+        fs::write(dir.join("rekor.pem"), sigstore.log_key.verifying_key().to_public_key_pem(LineEnding::LF).unwrap()).unwrap();
+        fs::write(dir.join("trust.toml"), "fulcio = \"fulcio.pem\"\nrekor = \"rekor.pem\"\noidc_issuers = [\"https://sso.corp.example\"]\n").unwrap();
+        let trust = SigstoreTrust::load(&dir.join("trust.toml")).unwrap();
+        assert_eq!(trust.oidc_issuers, vec![ISSUER.to_string()]);
+        sign_keyless(unsigned(), &sigstore.token(), &sigstore, &trust).unwrap();
+        assert!(SigstoreTrust::from_pem(b"", b"", Vec::new()).is_err());
+    }
+}
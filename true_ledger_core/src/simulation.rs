@@ -0,0 +1,186 @@
+//! Replay and Simulation
+//! Previews what a batch of proposed postings would do before anyone signs
+//! it. The verified journal is replayed into an in-memory projection (the
+//! trial balance and the policy history), then each proposed transaction is
+//! judged as the ledger would judge it on posting and, if nothing refuses
+//! it, posted to the projection. Nothing is signed, chained or stored.
+//!
+//! A proposal must balance and pass the ledger's own policy version in
+//! force at its timestamp (see policy.rs), plus any rules the caller adds
+//! (a rules file, period closes). A proposal that adopts a new policy
+//! version is checked and, if accepted, judges the proposals after it. A
+//! refused proposal is left out of the resulting balances, as the ledger
+//! would leave it out of the books.
+//!
+//! The outcome depends only on the journal, the proposals and their order:
+//! no clock is read, so the same inputs always give the same report.
+
+use serde::Serialize;
+
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::policy::{HistoricalPolicy, PolicyHistory};
+use crate::trial_balance::TrialBalance;
+use crate::validation::{Balanced, ValidationPipeline, ValidationRule};
+
+/// A hypothetical transaction, and what to call it in the report
+#[derive(Debug, Clone)]
+pub struct Proposal {
+    pub label: String, // e.g. the draft id or file it came from
+    pub tx: Transaction,
+}
+
+/// A rule the proposal breaks
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct ProposalOutcome {
+    pub label: String,
+    pub memo: String,
+    pub accepted: bool,
+    pub violations: Vec<Violation>,
+}
+
+/// An account the accepted proposals move
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub account_id: String,
+    pub before_cents: i64, // Debit minus credit
+    pub after_cents: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct SimulationReport {
+    pub replayed: usize, // Journal transactions in the projection
+    pub proposals: Vec<ProposalOutcome>,
+    pub changes: Vec<BalanceChange>,
+    pub after: TrialBalance,
+}
+
+impl SimulationReport {
+    pub fn refused(&self) -> usize {
+        self.proposals.iter().filter(|outcome| !outcome.accepted).count()
+    }
+}
+
+/// The journal's projection, ready to take proposals
+pub struct Simulation {
+    books: TrialBalance,
+    policies: PolicyHistory,
+    policy: HistoricalPolicy,
+    rules: ValidationPipeline, // The caller's, on top of the ledger's policy
+}
+
+impl Simulation {
+    /// Replays verified transactions, in chain order. Policy transactions
+    /// their author was not entitled to adopt are ignored, as on replay of
+    /// the ledger itself.
+    pub fn replay<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I, rules: ValidationPipeline) -> Result<Self, LedgerError> {
+        let mut books = TrialBalance::new();
+        let mut policies = PolicyHistory::new();
+        for tx in txs {
+            books.post(tx)?;
+            let _ = policies.apply(tx);
+        }
+        let policy = policies.compile()?;
+        Ok(Simulation { books, policies, policy, rules })
+    }
+
+    /// Everything `tx` breaks, in rule order
+    fn violations(&self, tx: &Transaction) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut record = |rule: &str, result: Result<(), LedgerError>| {
+            if let Err(e) = result {
+                violations.push(Violation { rule: rule.to_string(), message: e.to_string() });
+            }
+        };
+        record(Balanced.name(), Balanced.check(tx));
+        record(self.policy.name(), self.policy.check(tx));
+        for (rule, result) in self.rules.check(tx) {
+            record(rule, result);
+        }
+        violations
+    }
+
+    /// Applies the proposals in order and reports the outcome
+    pub fn run(mut self, proposals: &[Proposal]) -> Result<SimulationReport, LedgerError> {
+        let before = self.books.clone();
+        let replayed = before.transactions;
+        let mut outcomes = Vec::with_capacity(proposals.len());
+        for proposal in proposals {
+            let mut violations = self.violations(&proposal.tx);
+            if violations.is_empty() && proposal.tx.policy.is_some() {
+                match self.policies.apply(&proposal.tx).and_then(|_| self.policies.compile()) {
+                    Ok(policy) => self.policy = policy,
+                    Err(e) => violations.push(Violation { rule: "policy_adoption".to_string(), message: e.to_string() }),
+                }
+            }
+            let accepted = violations.is_empty();
+            if accepted {
+                self.books.post(&proposal.tx).map_err(|e| e.context(format!("Proposal {}", proposal.label)))?;
+            }
+            outcomes.push(ProposalOutcome { label: proposal.label.clone(), memo: proposal.tx.memo.clone(), accepted, violations });
+        }
+
+        let net = |books: &TrialBalance, account_id: &str| books.accounts.get(account_id).map_or(0, |totals| totals.net_cents());
+        let changes = self.books.accounts.keys()
+            .map(|account_id| BalanceChange { account_id: account_id.clone(), before_cents: net(&before, account_id), after_cents: net(&self.books, account_id) })
+            .filter(|change| change.before_cents != change.after_cents)
+            .collect();
+        Ok(SimulationReport { replayed, proposals: outcomes, changes, after: self.books })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+    use crate::identity::Account;
+    use crate::policy::Policy;
+    use crate::test_util::chain;
+
+    fn proposal(label: &str, at: u64, debit: &str, credit: &str, amount: &str) -> Proposal {
+        let tx = TransactionBuilder::new()
+            .timestamp(at)
+            .entry(debit, amount, "0.00")
+            .entry(credit, "0.00", amount)
+            .memo(label)
+            .build()
+            .unwrap();
+        Proposal { label: label.to_string(), tx }
+    }
+
+    #[test]
+    fn proposals_are_judged_by_the_policy_and_only_accepted_ones_move_balances() {
+        let clerk = Account::generate();
+        let mut journal: Vec<Transaction> = chain(&clerk, 2).into_iter().map(|signed_tx| signed_tx.payload).collect();
+        let mut adoption = journal[0].clone();
+        adoption.entries.clear();
+        adoption.policy = Some(Policy {
+            version: 1,
+            effective_from: adoption.timestamp.clone(),
+            administrators: vec![clerk.did.clone()],
+            rules: "[[amount_limit]]\naccount = \"68100\"\nmax = \"500.00\"\n".to_string(),
+        });
+        journal.push(adoption);
+
+        let small = proposal("small", 1_800_000_000, "68100", "10100", "200.00");
+        let large = proposal("large", 1_800_000_000, "68100", "10100", "900.00");
+        let mut unbalanced = proposal("unbalanced", 1_800_000_000, "68100", "10100", "10.00");
+        unbalanced.tx.entries[1].credit = "9.00".to_string();
+
+        let simulation = Simulation::replay(&journal, ValidationPipeline::new()).unwrap();
+        let report = simulation.run(&[small, large, unbalanced]).unwrap();
+        assert_eq!(report.proposals.iter().map(|outcome| outcome.accepted).collect::<Vec<_>>(), [true, false, false]);
+        assert_eq!(report.proposals[1].violations[0].rule, "policy");
+        assert_eq!(report.proposals[2].violations[0].rule, "balance");
+        assert_eq!(report.refused(), 2);
+        let expenses = report.changes.iter().find(|change| change.account_id == "68100").unwrap();
+        assert_eq!(expenses.after_cents - expenses.before_cents, 20_000);
+        report.after.check().unwrap();
+    }
+}
@@ -0,0 +1,150 @@
+//! SSH-Agent Signer
+//! Signs with an Ed25519 key held by the user's ssh-agent. The private key
+//! never leaves the agent; the did:key is derived from the agent's public key.
+
+use ed25519_dalek::PublicKey;
+
+use crate::did::did_from_public_key;
+use crate::error::LedgerError;
+use crate::keys::SigAlg;
+use crate::model::{SignedTransaction, Transaction};
+use crate::signer::TransactionSigner;
+
+// SSH agent protocol message numbers (draft-miller-ssh-agent)
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH_AGENT_SIGN_RESPONSE: u8 = 14;
+
+/// Appends an SSH wire-format string (u32 length + bytes)
+fn put_ssh_string(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// Reads an SSH wire-format string from `buf` at `*pos`, advancing it
+fn take_ssh_string<'a>(buf: &'a [u8], pos: &mut usize) -> Result<&'a [u8], LedgerError> {
+    let len_bytes = buf.get(*pos..*pos + 4).ok_or_else(|| LedgerError::Key("Truncated ssh-agent message".to_string()))?;
+    let len = u32::from_be_bytes([len_bytes[0], len_bytes[1], len_bytes[2], len_bytes[3]]) as usize;
+    let data = buf.get(*pos + 4..*pos + 4 + len).ok_or_else(|| LedgerError::Key("Truncated ssh-agent message".to_string()))?;
+    *pos += 4 + len;
+    Ok(data)
+}
+
+/// Signs via an Ed25519 key held by the ssh-agent at $SSH_AUTH_SOCK.
+/// The private key never leaves the agent.
+pub struct SshAgentSigner {
+    socket_path: String,
+    key_blob: Vec<u8>, // The agent's "ssh-ed25519" public key blob
+    did: String,
+}
+
+impl SshAgentSigner {
+    /// Connects to the agent and picks the first Ed25519 key,
+    /// or the one whose comment matches `comment` if given
+    pub fn connect(comment: Option<&str>) -> Result<Self, LedgerError> {
+        let socket_path = std::env::var("SSH_AUTH_SOCK")
+            .map_err(|_| LedgerError::Key("SSH_AUTH_SOCK is not set; is ssh-agent running?".to_string()))?;
+
+        let reply = ssh_agent_request(&socket_path, &[SSH_AGENTC_REQUEST_IDENTITIES])?;
+        if reply.first() != Some(&SSH_AGENT_IDENTITIES_ANSWER) {
+            return Err(LedgerError::Key("ssh-agent refused to list identities".to_string()));
+        }
+
+        let count_bytes = reply.get(1..5).ok_or_else(|| LedgerError::Key("Truncated ssh-agent message".to_string()))?;
+        let count = u32::from_be_bytes([count_bytes[0], count_bytes[1], count_bytes[2], count_bytes[3]]);
+        let mut pos = 5; // Past the message type and key count
+        for _ in 0..count {
+            let key_blob = take_ssh_string(&reply, &mut pos)?;
+            let key_comment = take_ssh_string(&reply, &mut pos)?;
+
+            let mut blob_pos = 0;
+            let key_type = take_ssh_string(key_blob, &mut blob_pos)?;
+            if key_type != b"ssh-ed25519" {
+                continue;
+            }
+            if let Some(wanted) = comment {
+                if key_comment != wanted.as_bytes() {
+                    continue;
+                }
+            }
+
+            let public_bytes = take_ssh_string(key_blob, &mut blob_pos)?;
+            let public = PublicKey::from_bytes(public_bytes)
+                .map_err(|e| LedgerError::Key(format!("ssh-agent returned an invalid Ed25519 key: {:?}", e)))?;
+            return Ok(SshAgentSigner {
+                socket_path,
+                key_blob: key_blob.to_vec(),
+                did: did_from_public_key(&public),
+            });
+        }
+
+        match comment {
+            Some(wanted) => Err(LedgerError::Key(format!("No Ed25519 key with comment '{}' in ssh-agent", wanted))),
+            None => Err(LedgerError::Key("No Ed25519 keys in ssh-agent (try `ssh-add`)".to_string())),
+        }
+    }
+}
+
+impl TransactionSigner for SshAgentSigner {
+    fn did(&self) -> &str {
+        &self.did
+    }
+
+    fn sign_transaction(&self, tx: Transaction) -> Result<SignedTransaction, LedgerError> {
+        let tx = tx.prepare_for_signing();
+        let signature = self.sign_bytes(&tx.get_hash())?;
+        Ok(SignedTransaction::new(tx, SigAlg::Ed25519, &signature))
+    }
+
+    fn sign_bytes(&self, message: &[u8]) -> Result<Vec<u8>, LedgerError> {
+        let mut request = vec![SSH_AGENTC_SIGN_REQUEST];
+        put_ssh_string(&mut request, &self.key_blob);
+        put_ssh_string(&mut request, message);
+        request.extend_from_slice(&0u32.to_be_bytes()); // No flags
+
+        let reply = ssh_agent_request(&self.socket_path, &request)?;
+        match reply.first() {
+            Some(&SSH_AGENT_SIGN_RESPONSE) => {}
+            Some(&SSH_AGENT_FAILURE) => return Err(LedgerError::Key("ssh-agent refused to sign (key locked or confirmation denied?)".to_string())),
+            _ => return Err(LedgerError::Key("Unexpected reply from ssh-agent".to_string())),
+        }
+
+        // The reply wraps the signature as string("ssh-ed25519") + string(64 bytes)
+        let mut pos = 1;
+        let sig_blob = take_ssh_string(&reply, &mut pos)?;
+        let mut sig_pos = 0;
+        let _sig_type = take_ssh_string(sig_blob, &mut sig_pos)?;
+        let signature = take_ssh_string(sig_blob, &mut sig_pos)?;
+        if signature.len() != 64 {
+            return Err(LedgerError::Key("ssh-agent returned a malformed Ed25519 signature".to_string()));
+        }
+
+        Ok(signature.to_vec())
+    }
+}
+
+/// Sends one framed request to the agent and returns the reply body
+#[cfg(unix)]
+fn ssh_agent_request(socket_path: &str, body: &[u8]) -> Result<Vec<u8>, LedgerError> {
+    use std::io::{Read, Write};
+
+    let mut stream = std::os::unix::net::UnixStream::connect(socket_path)
+        .map_err(|e| LedgerError::Io(format!("Could not connect to ssh-agent at {}: {}", socket_path, e)))?;
+
+    let mut framed = (body.len() as u32).to_be_bytes().to_vec();
+    framed.extend_from_slice(body);
+    stream.write_all(&framed).map_err(|e| LedgerError::Io(format!("ssh-agent write failed: {}", e)))?;
+
+    let mut len_bytes = [0u8; 4];
+    stream.read_exact(&mut len_bytes).map_err(|e| LedgerError::Io(format!("ssh-agent read failed: {}", e)))?;
+    let mut reply = vec![0u8; u32::from_be_bytes(len_bytes) as usize];
+    stream.read_exact(&mut reply).map_err(|e| LedgerError::Io(format!("ssh-agent read failed: {}", e)))?;
+    Ok(reply)
+}
+
+#[cfg(not(unix))]
+fn ssh_agent_request(_socket_path: &str, _body: &[u8]) -> Result<Vec<u8>, LedgerError> {
+    Err(LedgerError::Key("ssh-agent signing is only supported on Unix platforms".to_string()))
+}
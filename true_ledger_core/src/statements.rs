@@ -0,0 +1,250 @@
+//! Financial Statements
+//! Statements drawn from verified transactions and the chart of accounts.
+//! Every account must be in the chart, since its type decides where it is
+//! reported. Balances are shown on each account's normal side, so a
+//! positive amount is an ordinary asset, liability or equity balance, and
+//! parents carry their children's balances (see chart.rs).
+//!
+//! The cash flow statement uses the indirect method: it starts from net
+//! income and adds back the change in every non-cash account, each under the
+//! activity the chart maps it to. The activities must add up to the change
+//! in the cash accounts themselves.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::amount::format_cents;
+use crate::chart::{AccountType, CashFlow, ChartOfAccounts, NormalBalance};
+use crate::error::LedgerError;
+use crate::fiscal::Cutoff;
+use crate::model::Transaction;
+use crate::trial_balance::TrialBalance;
+
+/// One account on a statement
+#[derive(Serialize, Debug, Clone)]
+pub struct StatementLine {
+    pub account_id: String,
+    pub name: Option<String>, // None for parents that are only implied by their children's codes
+    pub depth: usize,         // 0 for accounts that roll up into nothing
+    pub balance_cents: i64,   // On the account's normal side, its children included
+}
+
+/// The accounts of one type, rolled up
+#[derive(Serialize, Debug, Clone)]
+pub struct StatementSection {
+    pub account_type: AccountType,
+    pub lines: Vec<StatementLine>,
+    pub total_cents: i64,
+}
+
+impl StatementSection {
+    /// The section for every account of `account_type` in the trial balance
+    fn new(trial_balance: &TrialBalance, chart: &ChartOfAccounts, account_type: AccountType) -> Result<Self, LedgerError> {
+        let mut of_type = TrialBalance::new();
+        of_type.accounts = trial_balance.accounts.iter()
+            .filter(|(code, _)| chart.get(code).is_some_and(|account| account.account_type == account_type))
+            .map(|(code, totals)| (code.clone(), totals.clone()))
+            .collect();
+        let (debits, credits) = of_type.totals()?;
+        let normal = |net: i64| match account_type.normal_balance() {
+            NormalBalance::Debit => net,
+            NormalBalance::Credit => -net,
+        };
+        let lines = of_type.rollup(chart)?.into_iter()
+            .map(|line| StatementLine {
+                name: chart.get(&line.account_id).map(|account| account.name.clone()),
+                depth: line.depth,
+                balance_cents: normal(line.totals.net_cents()),
+                account_id: line.account_id,
+            })
+            .collect();
+        Ok(StatementSection { account_type, lines, total_cents: normal(debits - credits) })
+    }
+}
+
+/// Every account must be classified before it can be reported
+fn check_classified(trial_balance: &TrialBalance, chart: &ChartOfAccounts) -> Result<(), LedgerError> {
+    let unknown: Vec<&str> = trial_balance.accounts.keys().filter(|code| chart.get(code).is_none()).map(String::as_str).collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(LedgerError::Config(format!("Not in the chart of accounts, so not classified: {}", unknown.join(", "))))
+    }
+}
+
+/// Assets = Liabilities + Equity, at the close of a day
+#[derive(Serialize, Debug, Clone)]
+pub struct BalanceSheet {
+    pub as_of: NaiveDate,
+    pub adjustments: bool, // Whether year-end adjusting entries dated `as_of` are included
+    pub assets: StatementSection,
+    pub liabilities: StatementSection,
+    pub equity: StatementSection,
+    pub net_income_cents: i64, // Income less expenses not yet closed to equity
+    pub transactions: usize,   // How many transactions are included
+}
+
+impl BalanceSheet {
+    /// Builds the balance sheet from the verified transactions `as_of`
+    /// includes (see [`FiscalCalendar::cutoff`](crate::fiscal::FiscalCalendar::cutoff))
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I, chart: &ChartOfAccounts, as_of: Cutoff) -> Result<Self, LedgerError> {
+        let trial_balance = TrialBalance::from_transactions(txs.into_iter().filter(|tx| as_of.includes(tx)))?;
+        check_classified(&trial_balance, chart)?;
+
+        let income = StatementSection::new(&trial_balance, chart, AccountType::Income)?.total_cents;
+        let expenses = StatementSection::new(&trial_balance, chart, AccountType::Expense)?.total_cents;
+        let net_income_cents = income.checked_sub(expenses)
+            .ok_or_else(|| LedgerError::Amount("Net income is more than a ledger can hold".to_string()))?;
+        Ok(BalanceSheet {
+            as_of: as_of.date,
+            adjustments: as_of.adjustments,
+            assets: StatementSection::new(&trial_balance, chart, AccountType::Asset)?,
+            liabilities: StatementSection::new(&trial_balance, chart, AccountType::Liability)?,
+            equity: StatementSection::new(&trial_balance, chart, AccountType::Equity)?,
+            net_income_cents,
+            transactions: trial_balance.transactions,
+        })
+    }
+
+    /// Equity including the period's net income
+    pub fn total_equity_cents(&self) -> i64 {
+        self.equity.total_cents + self.net_income_cents
+    }
+
+    /// The sheet balances when assets equal liabilities plus equity
+    pub fn check(&self) -> Result<(), LedgerError> {
+        let claims = self.liabilities.total_cents + self.total_equity_cents();
+        if self.assets.total_cents == claims {
+            Ok(())
+        } else {
+            Err(LedgerError::Imbalance(format!("Balance sheet does not balance: Assets ({}) != Liabilities + Equity ({})",
+                format_cents(self.assets.total_cents), format_cents(claims))))
+        }
+    }
+}
+
+/// One account's effect on cash over the period
+#[derive(Serialize, Debug, Clone)]
+pub struct CashFlowLine {
+    pub account_id: String,
+    pub name: String,
+    pub amount_cents: i64, // Positive when the account's change brought cash in
+}
+
+/// Cash in and out over a period, by activity
+#[derive(Serialize, Debug, Clone)]
+pub struct CashFlowStatement {
+    pub from: Option<NaiveDate>, // None: from the first transaction
+    pub to: NaiveDate,
+    pub adjustments: bool, // Whether year-end adjusting entries dated `to` are included
+    pub net_income_cents: i64,
+    pub operating: Vec<CashFlowLine>, // Adjustments to net income
+    pub investing: Vec<CashFlowLine>,
+    pub financing: Vec<CashFlowLine>,
+    pub opening_cash_cents: i64,
+    pub closing_cash_cents: i64,
+    pub transactions: usize, // How many transactions fall in the period
+}
+
+impl CashFlowStatement {
+    /// Builds the statement from verified transactions dated from `from`
+    /// (or the start of the books) through the `to` cutoff
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I, chart: &ChartOfAccounts, from: Option<NaiveDate>, to: Cutoff) -> Result<Self, LedgerError> {
+        if from.is_some_and(|from| from > to.date) {
+            return Err(LedgerError::Config(format!("The period ends ({}) before it starts", to.date)));
+        }
+        if !chart.accounts().any(|account| account.cash_flow() == CashFlow::Cash) {
+            return Err(LedgerError::Config("No account in the chart is marked cash_flow = \"cash\"".to_string()));
+        }
+
+        let mut opening = TrialBalance::new();
+        let mut period = TrialBalance::new();
+        for tx in txs {
+            let date = tx.timestamp.local_date();
+            if from.is_some_and(|from| date < from) {
+                opening.post(tx)?;
+            } else if to.includes(tx) {
+                period.post(tx)?;
+            }
+        }
+        check_classified(&opening, chart)?;
+        check_classified(&period, chart)?;
+
+        let cash = |trial_balance: &TrialBalance| trial_balance.accounts.iter()
+            .filter(|(code, _)| chart.get(code).is_some_and(|account| account.cash_flow() == CashFlow::Cash))
+            .map(|(_, totals)| totals.net_cents())
+            .sum::<i64>();
+        let mut statement = CashFlowStatement {
+            from,
+            to: to.date,
+            adjustments: to.adjustments,
+            net_income_cents: 0,
+            operating: Vec::new(),
+            investing: Vec::new(),
+            financing: Vec::new(),
+            opening_cash_cents: cash(&opening),
+            closing_cash_cents: cash(&opening) + cash(&period),
+            transactions: period.transactions,
+        };
+
+        // A non-cash account's change is cash flowing the other way: a credit
+        // (a liability raised, an asset sold, income earned) brings cash in
+        for (code, totals) in &period.accounts {
+            let Some(account) = chart.get(code) else { continue };
+            let effect = -totals.net_cents();
+            if effect == 0 || account.cash_flow() == CashFlow::Cash {
+                continue;
+            }
+            let line = |amount_cents: i64| CashFlowLine { account_id: code.clone(), name: account.name.clone(), amount_cents };
+            let is_income_statement = matches!(account.account_type, AccountType::Income | AccountType::Expense);
+            if is_income_statement {
+                statement.net_income_cents += effect;
+            }
+            match account.cash_flow() {
+                CashFlow::Operating if is_income_statement => {} // Already in net income
+                CashFlow::Operating => statement.operating.push(line(effect)),
+                activity => {
+                    // Income or expense from another activity (e.g. a gain on
+                    // disposal) comes out of operating and is shown where it belongs
+                    if is_income_statement {
+                        statement.operating.push(line(-effect));
+                    }
+                    match activity {
+                        CashFlow::Investing => statement.investing.push(line(effect)),
+                        _ => statement.financing.push(line(effect)),
+                    }
+                }
+            }
+        }
+        Ok(statement)
+    }
+
+    /// Net income plus its adjustments
+    pub fn operating_cents(&self) -> i64 {
+        self.net_income_cents + self.operating.iter().map(|line| line.amount_cents).sum::<i64>()
+    }
+
+    pub fn investing_cents(&self) -> i64 {
+        self.investing.iter().map(|line| line.amount_cents).sum()
+    }
+
+    pub fn financing_cents(&self) -> i64 {
+        self.financing.iter().map(|line| line.amount_cents).sum()
+    }
+
+    /// The three activities together
+    pub fn net_change_cents(&self) -> i64 {
+        self.operating_cents() + self.investing_cents() + self.financing_cents()
+    }
+
+    /// The activities must explain the change in the cash accounts exactly
+    pub fn check(&self) -> Result<(), LedgerError> {
+        let change = self.closing_cash_cents - self.opening_cash_cents;
+        if self.net_change_cents() == change {
+            Ok(())
+        } else {
+            Err(LedgerError::Imbalance(format!("Cash flow does not reconcile: activities ({}) != change in cash ({})",
+                format_cents(self.net_change_cents()), format_cents(change))))
+        }
+    }
+}
@@ -0,0 +1,518 @@
+//! Ledger Storage
+//! One JSON file per transaction is easy to inspect but slow to search. A
+//! `Storage` keeps signed transactions indexed by hash, author, account and
+//! timestamp, and searchable by the words of their memos. `SqliteStorage`
+//! keeps a whole ledger in a single file, with an inverted index of memo
+//! words; `journal::NdjsonJournal` is the plain-text, append-only
+//! alternative, searched by reading it through. Both also keep the drafts
+//! waiting to be posted, outside the chain (see lifecycle.rs).
+
+#[cfg(feature = "sqlite")]
+use rusqlite::types::Value;
+#[cfg(feature = "sqlite")]
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::chain::{check_sequence, verify_link};
+use crate::error::LedgerError;
+use crate::journal::NdjsonJournal;
+use crate::key_events::KeyRegistry;
+use crate::lifecycle::Draft;
+use crate::model::SignedTransaction;
+use crate::private_memo::PrivateMemo;
+
+/// Which transactions to return. Every field narrows the result; the
+/// default matches everything. Timestamps are inclusive.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    pub author: Option<String>,  // author_did
+    pub account: Option<String>, // Any entry posts to this account
+    pub from: Option<u64>,
+    pub until: Option<u64>,
+    pub memo: Option<String>,    // Every word of this appears in the memo, in any order and case
+}
+
+/// The words of a memo as the search index keeps them: lowercased runs of
+/// letters and digits
+pub fn memo_terms(text: &str) -> BTreeSet<String> {
+    text.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).map(str::to_lowercase).collect()
+}
+
+impl Query {
+    /// Whether `signed_tx` satisfies every condition
+    pub fn matches(&self, signed_tx: &SignedTransaction) -> bool {
+        let tx = &signed_tx.payload;
+        self.author.as_ref().is_none_or(|author| tx.author_did == *author)
+            && self.account.as_ref().is_none_or(|account| tx.entries.iter().any(|e| e.account_id == *account))
+            && self.from.is_none_or(|from| tx.timestamp.unix() >= from)
+            && self.until.is_none_or(|until| tx.timestamp.unix() <= until)
+            && self.memo.as_ref().is_none_or(|text| memo_terms(text).is_subset(&memo_terms(&tx.memo)))
+    }
+}
+
+/// A store of signed transactions, keyed by payload hash (the hex SHA-256
+/// that `prev_hash` refers to). Stores can be handed to another thread.
+pub trait Storage: Send {
+    /// Stores a transaction and returns its hash. Storing the same
+    /// transaction twice, one that does not link to the head, or a sequence
+    /// number out of turn, is an error (see [`check_append`]).
+    fn append(&mut self, signed_tx: &SignedTransaction) -> Result<String, LedgerError>;
+
+    fn get_by_hash(&self, hash: &str) -> Result<Option<SignedTransaction>, LedgerError>;
+
+    /// Matching transactions in chain order (height, then timestamp)
+    fn query(&self, query: &Query) -> Result<Vec<SignedTransaction>, LedgerError>;
+
+    /// The transaction with the greatest height, which the next one links to
+    fn head(&self) -> Result<Option<SignedTransaction>, LedgerError>;
+
+    /// The sequence number `author_did`'s next transaction must carry
+    fn next_sequence(&self, author_did: &str) -> Result<u64, LedgerError> {
+        let by_author = self.query(&Query { author: Some(author_did.to_string()), ..Query::default() })?;
+        Ok(by_author.iter().filter_map(|signed_tx| signed_tx.payload.sequence).max().map_or(0, |last| last + 1))
+    }
+
+    /// The rotations and revocations recorded by the stored key events, in chain order
+    fn key_registry(&self) -> Result<KeyRegistry, LedgerError> {
+        let stored = self.query(&Query::default())?;
+        KeyRegistry::from_transactions(stored.iter().map(|signed_tx| &signed_tx.payload))
+    }
+
+    /// Drafts and pending transactions, oldest first (see lifecycle.rs)
+    fn drafts(&self) -> Result<Vec<Draft>, LedgerError>;
+
+    /// Stores a draft as given, replacing any with the same id; callers
+    /// use [`Storage::save_draft`], which checks the transition first
+    fn put_draft(&mut self, draft: &Draft) -> Result<(), LedgerError>;
+
+    fn delete_draft(&mut self, id: &str) -> Result<(), LedgerError>;
+
+    /// Replaces the unsigned private memo stored with transaction `hash`
+    /// (see private_memo). Its payload, and so its place in the chain, stay
+    /// as they are.
+    fn replace_private_memo(&mut self, hash: &str, private_memo: Option<PrivateMemo>) -> Result<(), LedgerError>;
+
+    fn draft(&self, id: &str) -> Result<Option<Draft>, LedgerError> {
+        Ok(self.drafts()?.into_iter().find(|draft| draft.id == id))
+    }
+
+    /// Saves a new or changed draft, if its state may change that way
+    fn save_draft(&mut self, draft: &Draft) -> Result<(), LedgerError> {
+        Draft::check_transition(self.draft(&draft.id)?.as_ref(), draft)?;
+        self.put_draft(draft)
+    }
+
+    /// Appends the reviewer's signed `signed_tx` in place of pending draft
+    /// `id`, and returns its hash
+    fn post_draft(&mut self, id: &str, signed_tx: &SignedTransaction) -> Result<String, LedgerError> {
+        let draft = self.draft(id)?.ok_or_else(|| LedgerError::Storage(format!("No draft {}", id)))?;
+        draft.check_posting(&signed_tx.payload)?;
+        let hash = self.append(signed_tx)?;
+        self.delete_draft(id)?;
+        Ok(hash)
+    }
+}
+
+/// Checks that `signed_tx` may be appended to `storage`: not already
+/// stored, linked to the current head (or a genesis transaction for an
+/// empty ledger), its author's next sequence number, and signed by keys
+/// the stored key events have not revoked or rotated away by its timestamp
+/// (a key event must itself be one the registry accepts). Every backend runs
+/// this before writing, so no writer can fork the chain.
+pub fn check_append(storage: &(impl Storage + ?Sized), signed_tx: &SignedTransaction) -> Result<String, LedgerError> {
+    let payload = &signed_tx.payload;
+    let hash = payload.hash_hex();
+    if storage.get_by_hash(&hash)?.is_some() {
+        return Err(LedgerError::Storage(format!("Transaction {} is already stored", hash)));
+    }
+    verify_link(storage.head()?.as_ref().map(|head| &head.payload), payload)?;
+    check_sequence(payload, storage.next_sequence(&payload.author_did)?)?;
+    check_keys(storage, signed_tx)?;
+    Ok(hash)
+}
+
+/// Checks `signed_tx`'s signers against the key events stored so far: a
+/// key event must be one its author may still make, and anything else must
+/// be signed by keys not revoked or rotated away by its timestamp
+pub fn check_keys(storage: &(impl Storage + ?Sized), signed_tx: &SignedTransaction) -> Result<(), LedgerError> {
+    let mut registry = storage.key_registry()?;
+    if signed_tx.payload.key_event.is_some() {
+        registry.apply(&signed_tx.payload)
+    } else {
+        registry.check(signed_tx)
+    }
+}
+
+/// Whether `path` names an NDJSON journal (*.ndjson or *.jsonl) rather than
+/// an SQLite database
+pub fn is_journal_path(path: &Path) -> bool {
+    matches!(path.extension().and_then(|ext| ext.to_str()), Some("ndjson") | Some("jsonl"))
+}
+
+/// Opens the ledger at `path`, picking the backend by file extension
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn Storage>, LedgerError> {
+    let path = path.as_ref();
+    if is_journal_path(path) {
+        return Ok(Box::new(NdjsonJournal::open(path)));
+    }
+    #[cfg(feature = "sqlite")]
+    return Ok(Box::new(SqliteStorage::open(path)?));
+    #[cfg(not(feature = "sqlite"))]
+    Err(LedgerError::Storage(format!("{}: built without SQLite support; use an .ndjson journal", path.display())))
+}
+
+#[cfg(feature = "sqlite")]
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS transactions (
+        hash       TEXT PRIMARY KEY,
+        author_did TEXT NOT NULL,
+        timestamp  INTEGER NOT NULL,
+        height     INTEGER,
+        cid        TEXT NOT NULL,
+        body       TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS transactions_author ON transactions (author_did, timestamp);
+    CREATE INDEX IF NOT EXISTS transactions_timestamp ON transactions (timestamp);
+    CREATE INDEX IF NOT EXISTS transactions_height ON transactions (height);
+    CREATE TABLE IF NOT EXISTS postings (
+        hash       TEXT NOT NULL REFERENCES transactions (hash),
+        account_id TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS postings_account ON postings (account_id, hash);
+    CREATE TABLE IF NOT EXISTS memo_terms (
+        term TEXT NOT NULL,
+        hash TEXT NOT NULL REFERENCES transactions (hash),
+        PRIMARY KEY (term, hash)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS drafts (
+        id      TEXT PRIMARY KEY,
+        state   TEXT NOT NULL,
+        updated INTEGER NOT NULL,
+        body    TEXT NOT NULL
+    );
+";
+
+/// Bumped when an index is added; older databases are backfilled on open
+#[cfg(feature = "sqlite")]
+const SCHEMA_VERSION: i64 = 1;
+
+#[cfg(feature = "sqlite")]
+/// A ledger in one SQLite database. Each row keeps the signed transaction's
+/// JSON exactly as it would be written to a file, next to the indexed columns.
+pub struct SqliteStorage {
+    conn: Connection,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    /// Opens (or creates) the database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, LedgerError> {
+        let path = path.as_ref();
+        let conn = Connection::open(path).map_err(|e| LedgerError::Io(format!("Could not open ledger {}: {}", path.display(), e)))?;
+        Self::init(conn)
+    }
+
+    /// A throwaway database that lives only in memory
+    pub fn open_in_memory() -> Result<Self, LedgerError> {
+        Self::init(Connection::open_in_memory().map_err(db_error)?)
+    }
+
+    fn init(mut conn: Connection) -> Result<Self, LedgerError> {
+        conn.execute_batch(SCHEMA).map_err(db_error)?;
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0)).map_err(db_error)?;
+        if version < SCHEMA_VERSION {
+            // Ledgers written before the memo index existed
+            let db = conn.transaction().map_err(db_error)?;
+            let stored: Vec<(String, String)> = {
+                let mut statement = db.prepare("SELECT hash, body FROM transactions").map_err(db_error)?;
+                let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?))).map_err(db_error)?;
+                rows.collect::<Result<_, _>>().map_err(db_error)?
+            };
+            for (hash, body) in stored {
+                index_memo(&db, &hash, &parse_body(&body)?.payload.memo)?;
+            }
+            db.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION)).map_err(db_error)?;
+            db.commit().map_err(db_error)?;
+        }
+        Ok(SqliteStorage { conn })
+    }
+
+    /// Runs a `SELECT body ...` and parses every row
+    fn select(&self, sql: &str, values: Vec<Value>) -> Result<Vec<SignedTransaction>, LedgerError> {
+        let mut statement = self.conn.prepare(sql).map_err(db_error)?;
+        let bodies = statement.query_map(params_from_iter(values), |row| row.get::<_, String>(0))
+            .map_err(db_error)?;
+        let mut transactions = Vec::new();
+        for body in bodies {
+            transactions.push(parse_body(&body.map_err(db_error)?)?);
+        }
+        Ok(transactions)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn append(&mut self, signed_tx: &SignedTransaction) -> Result<String, LedgerError> {
+        let payload = &signed_tx.payload;
+        let hash = check_append(self, signed_tx)?;
+        let body = serde_json::to_string_pretty(signed_tx).map_err(|e| LedgerError::Serialization(format!("Failed to serialize transaction: {}", e)))?;
+
+        // The row and its postings are written together or not at all
+        let db = self.conn.transaction().map_err(db_error)?;
+        db.execute(
+            "INSERT INTO transactions (hash, author_did, timestamp, height, cid, body) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![hash, payload.author_did, payload.timestamp.unix() as i64, payload.height.map(|h| h as i64),
+                signed_tx.cid(), body],
+        ).map_err(db_error)?;
+        for entry in &payload.entries {
+            db.execute("INSERT INTO postings (hash, account_id) VALUES (?1, ?2)", params![hash, entry.account_id])
+                .map_err(db_error)?;
+        }
+        index_memo(&db, &hash, &payload.memo)?;
+        db.commit().map_err(db_error)?;
+        Ok(hash)
+    }
+
+    fn get_by_hash(&self, hash: &str) -> Result<Option<SignedTransaction>, LedgerError> {
+        let body: Option<String> = self.conn
+            .query_row("SELECT body FROM transactions WHERE hash = ?1", params![hash], |row| row.get(0))
+            .optional()
+            .map_err(db_error)?;
+        body.map(|body| parse_body(&body)).transpose()
+    }
+
+    fn query(&self, query: &Query) -> Result<Vec<SignedTransaction>, LedgerError> {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if let Some(author) = &query.author {
+            conditions.push("author_did = ?");
+            values.push(Value::Text(author.clone()));
+        }
+        if let Some(account) = &query.account {
+            conditions.push("hash IN (SELECT hash FROM postings WHERE account_id = ?)");
+            values.push(Value::Text(account.clone()));
+        }
+        if let Some(from) = query.from {
+            conditions.push("timestamp >= ?");
+            values.push(Value::Integer(from as i64));
+        }
+        if let Some(until) = query.until {
+            conditions.push("timestamp <= ?");
+            values.push(Value::Integer(until as i64));
+        }
+        let terms = query.memo.as_deref().map(memo_terms).unwrap_or_default();
+        let memo_condition = format!("hash IN (SELECT hash FROM memo_terms WHERE term IN ({}) GROUP BY hash HAVING COUNT(*) = {})",
+            vec!["?"; terms.len()].join(", "), terms.len());
+        if !terms.is_empty() {
+            conditions.push(&memo_condition);
+            values.extend(terms.into_iter().map(Value::Text));
+        }
+
+        let mut sql = "SELECT body FROM transactions".to_string();
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        // Unchained rows (NULL height) sort first, as in chain::load_dir
+        sql.push_str(" ORDER BY height, timestamp, hash");
+        self.select(&sql, values)
+    }
+
+    fn key_registry(&self) -> Result<KeyRegistry, LedgerError> {
+        // Only key events carry the field; a memo quoting it is skipped by the registry
+        let stored = self.select("SELECT body FROM transactions WHERE body LIKE '%\"key_event\"%' ORDER BY height, timestamp, hash", Vec::new())?;
+        KeyRegistry::from_transactions(stored.iter().map(|signed_tx| &signed_tx.payload))
+    }
+
+    fn head(&self) -> Result<Option<SignedTransaction>, LedgerError> {
+        let mut head = self.select("SELECT body FROM transactions WHERE height IS NOT NULL ORDER BY height DESC LIMIT 1", Vec::new())?;
+        Ok(head.pop())
+    }
+
+    fn drafts(&self) -> Result<Vec<Draft>, LedgerError> {
+        let mut statement = self.conn.prepare("SELECT body FROM drafts ORDER BY updated, id").map_err(db_error)?;
+        let bodies = statement.query_map([], |row| row.get::<_, String>(0)).map_err(db_error)?;
+        let mut drafts = Vec::new();
+        for body in bodies {
+            let body = body.map_err(db_error)?;
+            drafts.push(serde_json::from_str(&body).map_err(|e| LedgerError::Serialization(format!("Stored draft is corrupt: {}", e)))?);
+        }
+        Ok(drafts)
+    }
+
+    fn put_draft(&mut self, draft: &Draft) -> Result<(), LedgerError> {
+        let body = serde_json::to_string_pretty(draft).map_err(|e| LedgerError::Serialization(format!("Failed to serialize draft: {}", e)))?;
+        self.conn.execute("INSERT OR REPLACE INTO drafts (id, state, updated, body) VALUES (?1, ?2, ?3, ?4)",
+            params![draft.id, draft.state.to_string(), draft.updated.unix() as i64, body]).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn delete_draft(&mut self, id: &str) -> Result<(), LedgerError> {
+        self.conn.execute("DELETE FROM drafts WHERE id = ?1", params![id]).map_err(db_error)?;
+        Ok(())
+    }
+
+    fn replace_private_memo(&mut self, hash: &str, private_memo: Option<PrivateMemo>) -> Result<(), LedgerError> {
+        let mut signed_tx = self.get_by_hash(hash)?.ok_or_else(|| LedgerError::Storage(format!("No transaction {}", hash)))?;
+        signed_tx.private_memo = private_memo;
+        let body = serde_json::to_string_pretty(&signed_tx).map_err(|e| LedgerError::Serialization(format!("Failed to serialize transaction: {}", e)))?;
+        // The envelope's CID changes with it; the payload hash does not
+        self.conn.execute("UPDATE transactions SET body = ?1, cid = ?2 WHERE hash = ?3", params![body, signed_tx.cid(), hash])
+            .map_err(db_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn index_memo(db: &Connection, hash: &str, memo: &str) -> Result<(), LedgerError> {
+    for term in memo_terms(memo) {
+        db.execute("INSERT OR IGNORE INTO memo_terms (term, hash) VALUES (?1, ?2)", params![term, hash]).map_err(db_error)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "sqlite")]
+fn parse_body(body: &str) -> Result<SignedTransaction, LedgerError> {
+    serde_json::from_str(body).map_err(|e| LedgerError::Storage(format!("Stored transaction is corrupt: {}", e)))
+}
+
+#[cfg(feature = "sqlite")]
+fn db_error(e: rusqlite::Error) -> LedgerError {
+    LedgerError::Storage(format!("Ledger database error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+    use crate::key_events::KeyEvent;
+    use crate::private_memo::MemoOpening;
+    use crate::test_util::{chain, scratch_dir, signed};
+
+    /// Every backend, empty
+    fn backends(name: &str) -> Vec<Box<dyn Storage>> {
+        let journal = NdjsonJournal::open(scratch_dir(name).join("ledger.ndjson"));
+        #[cfg(feature = "sqlite")]
+        return vec![Box::new(journal), Box::new(SqliteStorage::open_in_memory().unwrap())];
+        #[cfg(not(feature = "sqlite"))]
+        vec![Box::new(journal)]
+    }
+
+    #[test]
+    fn a_linked_chain_appends() {
+        let account = Account::generate();
+        for mut ledger in backends("linked") {
+            for signed_tx in chain(&account, 3) {
+                ledger.append(&signed_tx).unwrap();
+            }
+            assert_eq!(ledger.head().unwrap().unwrap().payload.height, Some(2));
+            assert_eq!(ledger.next_sequence(&account.did).unwrap(), 3);
+        }
+    }
+
+    #[test]
+    fn a_second_genesis_is_rejected() {
+        let account = Account::generate();
+        let other = Account::generate();
+        for mut ledger in backends("second-genesis") {
+            ledger.append(&signed(&account, None, 0, "First")).unwrap();
+            let error = ledger.append(&signed(&other, None, 0, "Second")).unwrap_err();
+            assert!(matches!(error, LedgerError::Chain(_)), "{}", error);
+            assert_eq!(ledger.query(&Query::default()).unwrap().len(), 1);
+        }
+    }
+
+    #[test]
+    fn a_stale_prev_hash_is_rejected() {
+        let account = Account::generate();
+        let other = Account::generate();
+        for mut ledger in backends("stale") {
+            let transactions = chain(&account, 2);
+            for signed_tx in &transactions {
+                ledger.append(signed_tx).unwrap();
+            }
+            // Links to the genesis, which is no longer the head
+            let fork = signed(&other, Some(&transactions[0]), 0, "Fork");
+            assert!(matches!(ledger.append(&fork), Err(LedgerError::Chain(_))));
+        }
+    }
+
+    #[test]
+    fn an_unchained_transaction_is_rejected() {
+        let account = Account::generate();
+        for mut ledger in backends("unchained") {
+            let mut tx = signed(&account, None, 0, "Unchained").payload;
+            tx.height = None;
+            assert!(matches!(ledger.append(&account.sign(tx)), Err(LedgerError::Chain(_))));
+        }
+    }
+
+    #[test]
+    fn duplicates_and_replays_are_rejected() {
+        let account = Account::generate();
+        for mut ledger in backends("replay") {
+            let transactions = chain(&account, 2);
+            ledger.append(&transactions[0]).unwrap();
+            assert!(matches!(ledger.append(&transactions[0]), Err(LedgerError::Storage(_))));
+            ledger.append(&transactions[1]).unwrap();
+            // Linked correctly, but sequence 0 again
+            let replay = signed(&account, Some(&transactions[1]), 0, "Replay");
+            assert!(matches!(ledger.append(&replay), Err(LedgerError::Chain(_))));
+        }
+    }
+
+    /// `tx` turned into a key event, re-signed by `account`
+    fn key_event(account: &Account, signed_tx: SignedTransaction, event: KeyEvent) -> SignedTransaction {
+        let mut tx = signed_tx.payload;
+        tx.entries.clear();
+        tx.key_event = Some(event);
+        account.sign(tx)
+    }
+
+    #[test]
+    fn a_revoked_key_appends_nothing_more() {
+        let account = Account::generate();
+        let thief = Account::generate();
+        for mut ledger in backends("revoked") {
+            let genesis = signed(&account, None, 0, "First");
+            ledger.append(&genesis).unwrap();
+            let revoke = KeyEvent::Revoke { did: account.did.clone(), revoked_at: Some(1_700_000_005.into()) };
+            let revocation = key_event(&account, signed(&account, Some(&genesis), 1, "Revoke"), revoke);
+            ledger.append(&revocation).unwrap();
+
+            // Stamped before the cut-off, yet a revoked key makes no more key events
+            let rotate = KeyEvent::Rotate { new_did: thief.did.clone() };
+            let rotation = key_event(&account, signed(&account, Some(&revocation), 2, "Rotate"), rotate);
+            assert!(matches!(ledger.append(&rotation), Err(LedgerError::Key(_))));
+
+            let mut late = signed(&account, Some(&revocation), 2, "After the cut-off").payload;
+            late.timestamp = 1_700_000_005.into();
+            assert!(matches!(ledger.append(&account.sign(late)), Err(LedgerError::Key(_))));
+            ledger.append(&signed(&account, Some(&revocation), 2, "Before the cut-off")).unwrap();
+        }
+    }
+
+    #[test]
+    fn a_private_memo_is_replaced_in_place() {
+        let account = Account::generate();
+        for mut ledger in backends("private-memo") {
+            let transactions = chain(&account, 3);
+            for signed_tx in &transactions[..2] {
+                ledger.append(signed_tx).unwrap();
+            }
+            let hash = transactions[0].payload.hash_hex();
+            let opening = MemoOpening { salt: "00".repeat(16), text: "A longer line than before".to_string() };
+            ledger.replace_private_memo(&hash, Some(PrivateMemo::Open(opening.clone()))).unwrap();
+            assert_eq!(ledger.get_by_hash(&hash).unwrap().unwrap().private_memo, Some(PrivateMemo::Open(opening)));
+            // The lines after it moved, and are still found
+            assert_eq!(ledger.head().unwrap().unwrap().payload.hash_hex(), transactions[1].payload.hash_hex());
+            ledger.append(&transactions[2]).unwrap();
+
+            ledger.replace_private_memo(&hash, None).unwrap();
+            assert_eq!(ledger.get_by_hash(&hash).unwrap().unwrap().private_memo, None);
+            assert_eq!(ledger.query(&Query::default()).unwrap().len(), 3);
+            assert!(ledger.replace_private_memo(&"0".repeat(64), None).is_err());
+        }
+    }
+}
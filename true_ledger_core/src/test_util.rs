@@ -0,0 +1,43 @@
+//! Test Helpers
+//! Signed transactions and scratch directories shared by the unit tests.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::builder::TransactionBuilder;
+use crate::identity::Account;
+use crate::model::SignedTransaction;
+
+/// A balanced transaction by `account`, chained onto `prev` (or a genesis)
+pub fn signed(account: &Account, prev: Option<&SignedTransaction>, sequence: u64, memo: &str) -> SignedTransaction {
+    let builder = TransactionBuilder::new()
+        .timestamp(1_700_000_000 + sequence)
+        .entry("10100", "10.00", "0.00")
+        .entry("30100", "0.00", "10.00")
+        .memo(memo)
+        .sequence(sequence);
+    let builder = match prev {
+        Some(prev) => builder.follows(&prev.payload),
+        None => builder.genesis(),
+    };
+    builder.sign(account).expect("a valid transaction")
+}
+
+/// `len` linked transactions by one author, genesis first
+pub fn chain(account: &Account, len: usize) -> Vec<SignedTransaction> {
+    let mut transactions: Vec<SignedTransaction> = Vec::new();
+    for sequence in 0..len {
+        let next = signed(account, transactions.last(), sequence as u64, &format!("Posting {}", sequence));
+        transactions.push(next);
+    }
+    transactions
+}
+
+/// A fresh, empty directory under the system temp dir
+pub fn scratch_dir(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!("tlc-test-{}-{}-{}", std::process::id(), name, COUNTER.fetch_add(1, Ordering::SeqCst)));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).expect("a scratch directory");
+    dir
+}
@@ -0,0 +1,236 @@
+//! Timestamps
+//! A transaction's `timestamp` is either Unix seconds (every older file) or
+//! an RFC 3339 string with its UTC offset, e.g. "2024-11-05T14:27:22+01:00".
+//! Either way it is written back exactly as it was read, so the signed bytes
+//! never change; the accessors convert it for date arithmetic.
+//!
+//! A signature proves who wrote a timestamp, not that it is plausible.
+//! [`TimestampPolicy`] catches transactions dated in the future or back into a
+//! period that is already closed, including an unset `timestamp: 0`.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use schemars::gen::SchemaGenerator;
+use schemars::schema::{InstanceType, NumberValidation, Schema, SchemaObject, SubschemaValidation};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::LedgerError;
+use crate::fx::format_date;
+use crate::model::Transaction;
+
+/// When a transaction happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Timestamp {
+    /// Seconds since 1970-01-01T00:00:00Z
+    Unix(u64),
+    /// RFC 3339 text (validated), kept verbatim
+    Rfc3339(String),
+}
+
+impl Timestamp {
+    /// Validates RFC 3339 text such as "2024-11-05T14:27:22+01:00"
+    pub fn parse_rfc3339(text: &str) -> Result<Self, LedgerError> {
+        let parsed = DateTime::parse_from_rfc3339(text)
+            .map_err(|e| LedgerError::Timestamp(format!("'{}' is not an RFC 3339 timestamp: {}", text, e)))?;
+        if parsed.timestamp() < 0 {
+            return Err(LedgerError::Timestamp(format!("'{}' is before 1970", text)));
+        }
+        Ok(Timestamp::Rfc3339(text.to_string()))
+    }
+
+    /// The instant with its own UTC offset (+00:00 for Unix seconds)
+    pub fn to_datetime(&self) -> DateTime<FixedOffset> {
+        match self {
+            Timestamp::Unix(seconds) => i64::try_from(*seconds).ok()
+                .and_then(|seconds| Utc.timestamp_opt(seconds, 0).single())
+                .unwrap_or(DateTime::<Utc>::MAX_UTC) // Absurd values stay absurd (and in the future)
+                .fixed_offset(),
+            Timestamp::Rfc3339(text) => DateTime::parse_from_rfc3339(text).expect("validated when parsed"),
+        }
+    }
+
+    /// The instant in UTC
+    pub fn utc(&self) -> DateTime<Utc> {
+        self.to_datetime().with_timezone(&Utc)
+    }
+
+    /// Seconds since 1970-01-01T00:00:00Z
+    pub fn unix(&self) -> u64 {
+        match self {
+            Timestamp::Unix(seconds) => *seconds,
+            Timestamp::Rfc3339(_) => self.to_datetime().timestamp() as u64,
+        }
+    }
+
+    /// The calendar day where the transaction was recorded (its own offset)
+    pub fn local_date(&self) -> NaiveDate {
+        self.to_datetime().date_naive()
+    }
+
+    /// The calendar day in another time zone, e.g. the entity's fiscal zone
+    pub fn date_in<Tz: TimeZone>(&self, zone: &Tz) -> NaiveDate {
+        self.to_datetime().with_timezone(zone).date_naive()
+    }
+}
+
+impl From<u64> for Timestamp {
+    fn from(seconds: u64) -> Self {
+        Timestamp::Unix(seconds)
+    }
+}
+
+/// Unix seconds if the text is all digits, otherwise RFC 3339
+impl FromStr for Timestamp {
+    type Err = LedgerError;
+
+    fn from_str(text: &str) -> Result<Self, LedgerError> {
+        match text.parse::<u64>() {
+            Ok(seconds) => Ok(Timestamp::Unix(seconds)),
+            Err(_) => Timestamp::parse_rfc3339(text),
+        }
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timestamp::Unix(seconds) => seconds.fmt(f),
+            Timestamp::Rfc3339(text) => text.fmt(f),
+        }
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Timestamp::Unix(seconds) => serializer.serialize_u64(*seconds),
+            Timestamp::Rfc3339(text) => serializer.serialize_str(text),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Timestamp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Wire {
+            Unix(u64),
+            Text(String),
+        }
+        match Wire::deserialize(deserializer)? {
+            Wire::Unix(seconds) => Ok(Timestamp::Unix(seconds)),
+            Wire::Text(text) => Timestamp::parse_rfc3339(&text).map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl JsonSchema for Timestamp {
+    fn schema_name() -> String {
+        "Timestamp".to_string()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        let unix = SchemaObject {
+            instance_type: Some(InstanceType::Integer.into()),
+            format: Some("uint64".to_string()),
+            number: Some(Box::new(NumberValidation { minimum: Some(0.0), ..Default::default() })),
+            ..Default::default()
+        };
+        let rfc3339 = SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            format: Some("date-time".to_string()),
+            ..Default::default()
+        };
+        SchemaObject {
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![unix.into(), rfc3339.into()]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }.into()
+    }
+}
+
+/// How far a timestamp may stray. The current time is passed in by the
+/// caller, so the same check runs where there is no system clock (wasm32).
+#[derive(Debug, Clone, Copy)]
+pub struct TimestampPolicy {
+    pub max_clock_skew: u64,            // Seconds a timestamp may be ahead of now
+    pub open_period_start: Option<u64>, // Nothing may be dated before this (None: no lower bound)
+}
+
+impl Default for TimestampPolicy {
+    fn default() -> Self {
+        TimestampPolicy { max_clock_skew: 300, open_period_start: None }
+    }
+}
+
+impl TimestampPolicy {
+    /// Checks `tx.timestamp` against `now` (Unix seconds)
+    pub fn check(&self, tx: &Transaction, now: u64) -> Result<(), LedgerError> {
+        let timestamp = tx.timestamp.unix();
+        if timestamp == 0 {
+            return Err(LedgerError::Timestamp("Transaction has no timestamp (0)".to_string()));
+        }
+        if timestamp > now.saturating_add(self.max_clock_skew) {
+            return Err(LedgerError::Timestamp(format!("Timestamp {} ({}) is {} s in the future (allowed clock skew: {} s)",
+                timestamp, format_date(timestamp), timestamp - now, self.max_clock_skew)));
+        }
+        match self.open_period_start {
+            Some(start) if timestamp < start => Err(LedgerError::Timestamp(format!(
+                "Timestamp {} ({}) is before the open accounting period, which starts {}",
+                timestamp, format_date(timestamp), format_date(start)))),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+
+    fn dated(timestamp: impl Into<Timestamp>) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(timestamp)
+            .entry("10100", "10.00", "0.00")
+            .entry("30100", "0.00", "10.00")
+            .memo("Dated")
+            .genesis()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn timestamps_are_written_back_as_they_were_read() {
+        let text: Timestamp = "2024-11-05T14:27:22+01:00".parse().unwrap();
+        assert_eq!(serde_json::to_string(&text).unwrap(), "\"2024-11-05T14:27:22+01:00\"");
+        assert_eq!(serde_json::from_str::<Timestamp>("1700000000").unwrap(), Timestamp::Unix(1_700_000_000));
+        assert_eq!(text.unix(), 1_730_813_242);
+        assert_eq!(text.local_date(), NaiveDate::from_ymd_opt(2024, 11, 5).unwrap());
+        assert_eq!(text.date_in(&FixedOffset::west_opt(15 * 3600).unwrap()), NaiveDate::from_ymd_opt(2024, 11, 4).unwrap());
+
+        for invalid in ["\"2024-11-05\"", "\"1969-12-31T23:59:59Z\"", "-1", "\"yesterday\""] {
+            assert!(serde_json::from_str::<Timestamp>(invalid).is_err(), "{}", invalid);
+        }
+        // An absurd Unix timestamp stays in the future rather than wrapping
+        assert_eq!(Timestamp::Unix(u64::MAX).utc(), DateTime::<Utc>::MAX_UTC);
+    }
+
+    #[test]
+    fn the_policy_refuses_unset_future_and_closed_period_timestamps() {
+        let now = 1_700_000_000;
+        let policy = TimestampPolicy { max_clock_skew: 300, open_period_start: Some(now - 86_400) };
+        assert!(policy.check(&dated(now), now).is_ok());
+        assert!(policy.check(&dated(now + 300), now).is_ok());
+        let mut unset = dated(now);
+        unset.timestamp = Timestamp::Unix(0);
+        assert!(policy.check(&unset, now).is_err());
+        assert!(policy.check(&dated(now + 301), now).is_err());
+        assert!(policy.check(&dated(now - 86_401), now).is_err());
+        assert!(policy.check(&dated(u64::MAX), now).is_err());
+        assert!(TimestampPolicy::default().check(&dated(u64::MAX), u64::MAX).is_ok());
+    }
+}
@@ -0,0 +1,215 @@
+//! Trial Balance
+//! Per-account debit and credit totals over a set of verified transactions.
+//! Amounts are summed in integer cents, so the grand totals are exact; a
+//! sum too large for them is an error, never a wrapped total.
+//! [`TrialBalance::rollup`] adds each account into its parents (see chart.rs)
+//! for statement presentation.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use sha2::{Digest, Sha256};
+
+use crate::amount::format_cents;
+use crate::canonical::to_jcs;
+use crate::chart::ChartOfAccounts;
+use crate::error::LedgerError;
+use crate::model::Transaction;
+use crate::verify::entry_cents;
+
+/// Totals for one account
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct AccountTotals {
+    pub debit_cents: i64,
+    pub credit_cents: i64,
+}
+
+impl AccountTotals {
+    /// Debits minus credits: positive for a debit balance, negative for a credit balance
+    pub fn net_cents(&self) -> i64 {
+        self.debit_cents - self.credit_cents
+    }
+
+    fn add(&mut self, debit_cents: i64, credit_cents: i64) -> Result<(), LedgerError> {
+        let overflow = || LedgerError::Amount("Totals add up to more than a ledger can hold".to_string());
+        self.debit_cents = self.debit_cents.checked_add(debit_cents).ok_or_else(overflow)?;
+        self.credit_cents = self.credit_cents.checked_add(credit_cents).ok_or_else(overflow)?;
+        Ok(())
+    }
+}
+
+/// One line of a rolled-up report
+#[derive(Serialize, Debug, Clone)]
+pub struct RollupLine {
+    pub account_id: String,
+    pub depth: usize,          // 0 for accounts that roll up into nothing
+    pub totals: AccountTotals, // The account and everything under it
+}
+
+/// Running trial balance, keyed by account code
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct TrialBalance {
+    pub accounts: BTreeMap<String, AccountTotals>,
+    pub transactions: usize, // How many transactions were posted
+}
+
+impl TrialBalance {
+    pub fn new() -> Self {
+        TrialBalance::default()
+    }
+
+    /// Posts every entry of a transaction. Nothing is posted if any amount is
+    /// negative or not a valid decimal with at most 2 places, or if a total
+    /// would overflow.
+    pub fn post(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
+        let mut posted: BTreeMap<&str, AccountTotals> = BTreeMap::new();
+        for (i, entry) in tx.entries.iter().enumerate() {
+            let in_entry = |e: LedgerError| e.context(format!("Entry #{} ({})", i, entry.account_id));
+            let debit = entry_cents(&entry.debit, "debit").map_err(in_entry)?;
+            let credit = entry_cents(&entry.credit, "credit").map_err(in_entry)?;
+            let totals = posted.entry(entry.account_id.as_str())
+                .or_insert_with(|| self.accounts.get(&entry.account_id).cloned().unwrap_or_default());
+            totals.add(debit, credit).map_err(in_entry)?;
+        }
+        for (account_id, totals) in posted {
+            self.accounts.insert(account_id.to_string(), totals);
+        }
+        self.transactions += 1;
+        Ok(())
+    }
+
+    /// Builds a trial balance from transactions that have already been verified
+    pub fn from_transactions<'a, I: IntoIterator<Item = &'a Transaction>>(txs: I) -> Result<Self, LedgerError> {
+        let mut trial_balance = TrialBalance::new();
+        for tx in txs {
+            trial_balance.post(tx)?;
+        }
+        Ok(trial_balance)
+    }
+
+    /// Grand (debit, credit) totals across all accounts
+    pub fn totals(&self) -> Result<(i64, i64), LedgerError> {
+        let mut grand = AccountTotals::default();
+        for totals in self.accounts.values() {
+            grand.add(totals.debit_cents, totals.credit_cents)?;
+        }
+        Ok((grand.debit_cents, grand.credit_cents))
+    }
+
+    /// Hex SHA-256 of the JCS form: every account's totals and the number of
+    /// transactions. Any change to the books it covers changes it.
+    pub fn digest(&self) -> Result<String, LedgerError> {
+        Ok(hex::encode(Sha256::digest(to_jcs(self)?.as_bytes())))
+    }
+
+    /// The books balance when total debits equal total credits
+    pub fn check(&self) -> Result<(), LedgerError> {
+        let (debits, credits) = self.totals()?;
+        if debits == credits {
+            Ok(())
+        } else {
+            Err(LedgerError::Imbalance(format!("Trial balance does not balance: Debits ({}) != Credits ({})",
+                format_cents(debits), format_cents(credits))))
+        }
+    }
+
+    /// Every account with its descendants' totals added in, each parent
+    /// followed by its children (in code order). Parents are listed even if
+    /// nothing was posted to them directly. Grand totals still come from
+    /// [`totals`](Self::totals), since a rolled-up line counts its children again.
+    pub fn rollup(&self, chart: &ChartOfAccounts) -> Result<Vec<RollupLine>, LedgerError> {
+        let mut rolled: BTreeMap<String, AccountTotals> = BTreeMap::new();
+        for (account_id, totals) in &self.accounts {
+            for code in std::iter::once(account_id.clone()).chain(chart.ancestors(account_id)) {
+                rolled.entry(code).or_default().add(totals.debit_cents, totals.credit_cents)?;
+            }
+        }
+
+        let mut children: BTreeMap<Option<String>, BTreeSet<String>> = BTreeMap::new();
+        for code in rolled.keys() {
+            children.entry(chart.parent(code)).or_default().insert(code.clone());
+        }
+        let mut lines = Vec::with_capacity(rolled.len());
+        let mut stack: Vec<(String, usize)> = children.get(&None).into_iter().flatten().rev().map(|code| (code.clone(), 0)).collect();
+        while let Some((code, depth)) = stack.pop() {
+            if let Some(under) = children.get(&Some(code.clone())) {
+                stack.extend(under.iter().rev().map(|child| (child.clone(), depth + 1)));
+            }
+            let totals = rolled.remove(&code).unwrap_or_default();
+            lines.push(RollupLine { account_id: code, depth, totals });
+        }
+        Ok(lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+
+    fn payment(amount: &str) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .entry("68100", amount, "0.00")
+            .entry("10100", "0.00", amount)
+            .memo("Payment")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn postings_are_totalled_per_account() {
+        let books = TrialBalance::from_transactions(&[payment("10.00"), payment("2.50")]).unwrap();
+        assert_eq!(books.accounts["68100"].net_cents(), 1_250);
+        assert_eq!(books.accounts["10100"].net_cents(), -1_250);
+        assert_eq!(books.totals().unwrap(), (1_250, 1_250));
+        assert_eq!(books.transactions, 2);
+        books.check().unwrap();
+    }
+
+    #[test]
+    fn a_negative_amount_posts_nothing() {
+        let mut books = TrialBalance::from_transactions(&[payment("10.00")]).unwrap();
+        let mut negative = payment("5.00");
+        negative.entries[1].credit = "-5.00".to_string();
+        assert!(matches!(books.post(&negative), Err(LedgerError::Amount(_))));
+        assert_eq!(books.accounts["68100"].debit_cents, 1_000);
+        assert_eq!(books.transactions, 1);
+    }
+
+    #[test]
+    fn totals_that_would_overflow_are_errors() {
+        let max = "92233720368547758.07";
+        let mut books = TrialBalance::from_transactions(&[payment(max)]).unwrap();
+        assert!(matches!(books.post(&payment("0.01")), Err(LedgerError::Amount(_))));
+        assert_eq!(books.transactions, 1);
+
+        let mut other = payment(max);
+        other.entries[0].account_id = "68200".to_string();
+        other.entries[1].account_id = "10200".to_string();
+        books.post(&other).unwrap();
+        assert!(matches!(books.totals(), Err(LedgerError::Amount(_))));
+        assert!(books.check().is_err());
+    }
+
+    #[test]
+    fn rollups_add_children_into_parents_without_overflowing() {
+        let mut books = TrialBalance::new();
+        let mut rent = payment("10.00");
+        rent.entries[0].account_id = "6:rent".to_string();
+        let mut power = payment("2.50");
+        power.entries[0].account_id = "6:power".to_string();
+        books.post(&rent).unwrap();
+        books.post(&power).unwrap();
+        let lines = books.rollup(&ChartOfAccounts::default()).unwrap();
+        let parent = lines.iter().find(|line| line.account_id == "6").unwrap();
+        assert_eq!((parent.depth, parent.totals.debit_cents), (0, 1_250));
+        assert_eq!(lines.iter().find(|line| line.account_id == "6:rent").unwrap().depth, 1);
+
+        let mut huge = payment("92233720368547758.07");
+        huge.entries[0].account_id = "6:rates".to_string();
+        huge.entries[1].account_id = "10200".to_string();
+        books.post(&huge).unwrap();
+        assert!(matches!(books.rollup(&ChartOfAccounts::default()), Err(LedgerError::Amount(_))));
+    }
+}
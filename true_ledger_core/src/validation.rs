@@ -0,0 +1,479 @@
+//! Posting Rules
+//! Unlike red flags, these fail verification: they are the organisation's
+//! own posting policy on top of what every ledger checks. A
+//! [`ValidationPipeline`] runs its rules in order and reports each one; the
+//! built-in rules are configured from a TOML file, and callers add their own
+//! by implementing [`ValidationRule`]:
+//!
+//! ```toml
+//! chart = "chart.toml"          # Every account must be in the chart
+//! period_lock = "2024-12-31"    # Nothing dated on or before this day...
+//! fiscal = "fiscal.toml"        # ...or in this fiscal period, e.g. "FY2025-P12" (see fiscal.rs)
+//!
+//! [[amount_limit]]
+//! account = "6*"                # Each entry on 6xxxx accounts...
+//! max = "5000.00"               # ...is at most this much
+//!
+//! [[amount_limit]]
+//! max = "250000.00"             # Without an account: the transaction's total debits
+//!
+//! [[retired]]                   # Closed accounts (see chart.rs)...
+//! accounts = ["61500", "62*"]   # ...codes, prefixes ending in '*', or ranges...
+//! from = "2025-07-01"           # ...take no postings dated on or after this day
+//!
+//! [[pairing]]                   # Accumulated depreciation...
+//! accounts = ["15900"]
+//! with = ["68100", "79*"]       # ...only moves against depreciation expense or disposals
+//!
+//! authors = ["finance"]         # Only these DIDs or groups may author (see acl.rs)
+//!
+//! [groups]                      # Named sets of DIDs, for scripts, ACLs and approvers
+//! finance = ["did:key:z6Mk..."]
+//!
+//! [[acl]]                       # Only finance may debit 5xxxx accounts (see acl.rs)
+//! accounts = ["5*"]
+//! side = "debit"
+//! allow = ["finance"]
+//!
+//! [approval]                    # Preparer and approver must both sign (see approval.rs)
+//! approvers = ["finance"]
+//!
+//! [delegation]                  # Authors need a capability from a root (see capability.rs)
+//! roots = ["finance"]
+//!
+//! [[script]]                    # A Rhai script (see script.rs)
+//! path = "finance_only.rhai"
+//! timeout_ms = 50
+//! ```
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::acl::{AccountAcl, AccountPattern, AclConfig, AuthorRegistry};
+use crate::amount::{format_cents, parse_cents};
+use crate::approval::ApprovalWorkflow;
+use crate::capability::DelegationPolicy;
+use crate::chart::ChartOfAccounts;
+use crate::error::LedgerError;
+use crate::fiscal::FiscalCalendar;
+use crate::model::Transaction;
+use crate::verify::{balance_check, balance_totals, entry_cents};
+
+/// A posting policy a transaction must satisfy
+pub trait ValidationRule {
+    fn name(&self) -> &str;
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError>;
+}
+
+/// Debits equal credits in every currency
+pub struct Balanced;
+
+impl ValidationRule for Balanced {
+    fn name(&self) -> &str {
+        "balance"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        balance_check(tx)
+    }
+}
+
+/// Every entry posts to an account in the chart (on its normal-balance side, if `strict`)
+pub struct AccountsExist {
+    pub chart: ChartOfAccounts,
+    pub strict: bool,
+}
+
+impl ValidationRule for AccountsExist {
+    fn name(&self) -> &str {
+        "accounts_exist"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let problems = self.chart.validate(tx, self.strict);
+        if problems.is_empty() { Ok(()) } else { Err(LedgerError::Config(problems.join("; "))) }
+    }
+}
+
+/// Books are closed through `locked_through`: nothing may be dated on or
+/// before it (in the transaction's own time zone), except year-end adjusting
+/// entries on that day while the adjusting period is open
+pub struct PeriodLock {
+    pub locked_through: NaiveDate,
+    pub adjusting_period_open: bool,
+}
+
+impl ValidationRule for PeriodLock {
+    fn name(&self) -> &str {
+        "period_lock"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let date = tx.timestamp.local_date();
+        let adjustment = date == self.locked_through && self.adjusting_period_open && tx.adjusting == Some(true);
+        if date <= self.locked_through && !adjustment {
+            return Err(LedgerError::Timestamp(format!("Dated {}, but the books are locked through {}", date, self.locked_through)));
+        }
+        Ok(())
+    }
+}
+
+/// Caps each entry on matching accounts, or without `account` the transaction's total debits
+pub struct AmountLimit {
+    pub account: Option<String>, // An account code, or a prefix ending in '*'
+    pub max_cents: i64,
+}
+
+impl AmountLimit {
+    fn matches(&self, account_id: &str) -> bool {
+        match self.account.as_deref() {
+            Some(pattern) => match pattern.strip_suffix('*') {
+                Some(prefix) => account_id.starts_with(prefix),
+                None => account_id == pattern,
+            },
+            None => true,
+        }
+    }
+}
+
+impl ValidationRule for AmountLimit {
+    fn name(&self) -> &str {
+        "amount_limit"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let over = |amount: i64, what: String| Err(LedgerError::Amount(format!("{} is {}, over the limit of {}",
+            what, format_cents(amount), format_cents(self.max_cents))));
+        if self.account.is_none() {
+            let (total, _) = balance_totals(tx)?;
+            return if total > self.max_cents { over(total, "The total debit".to_string()) } else { Ok(()) };
+        }
+        for (i, entry) in tx.entries.iter().enumerate().filter(|(_, e)| self.matches(&e.account_id)) {
+            let amount = entry_cents(&entry.debit, "debit")?.max(entry_cents(&entry.credit, "credit")?);
+            if amount > self.max_cents {
+                return over(amount, format!("Entry #{} on {}", i, entry.account_id));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Accounts closed to postings dated on or after `from` (in the
+/// transaction's own time zone); earlier postings to them still verify
+pub struct RetiredAccounts {
+    pub accounts: Vec<AccountPattern>,
+    pub from: NaiveDate,
+}
+
+impl ValidationRule for RetiredAccounts {
+    fn name(&self) -> &str {
+        "retired"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        if tx.timestamp.local_date() < self.from {
+            return Ok(());
+        }
+        match tx.entries.iter().enumerate().find(|(_, entry)| self.accounts.iter().any(|pattern| pattern.matches(&entry.account_id))) {
+            Some((i, entry)) => Err(LedgerError::Config(format!("Entry #{}: account {} is retired from {}", i, entry.account_id, self.from))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Entries on `accounts` may only be offset, on the other side of the same
+/// transaction, by entries on `with` (or on `accounts` themselves), e.g. a
+/// contra account that only moves against its own expense
+pub struct AllowedPairing {
+    pub accounts: Vec<AccountPattern>,
+    pub with: Vec<AccountPattern>,
+}
+
+impl AllowedPairing {
+    fn covers(patterns: &[AccountPattern], account_id: &str) -> bool {
+        patterns.iter().any(|pattern| pattern.matches(account_id))
+    }
+}
+
+impl ValidationRule for AllowedPairing {
+    fn name(&self) -> &str {
+        "pairing"
+    }
+
+    fn check(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        let mut sides = Vec::with_capacity(tx.entries.len()); // (debited, credited) per entry
+        for entry in &tx.entries {
+            sides.push((parse_cents(&entry.debit)? != 0, parse_cents(&entry.credit)? != 0));
+        }
+        for (i, entry) in tx.entries.iter().enumerate().filter(|(_, entry)| Self::covers(&self.accounts, &entry.account_id)) {
+            let (debited, credited) = sides[i];
+            let against = tx.entries.iter().enumerate()
+                .filter(|(j, _)| (debited && sides[*j].1) || (credited && sides[*j].0))
+                .find(|(_, other)| !Self::covers(&self.accounts, &other.account_id) && !Self::covers(&self.with, &other.account_id));
+            if let Some((j, other)) = against {
+                return Err(LedgerError::Config(format!("Entry #{}: {} may not move against {} (entry #{})", i, entry.account_id, other.account_id, j)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One `[[pairing]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PairingConfig {
+    pub accounts: Vec<String>,
+    pub with: Vec<String>,
+}
+
+/// One `[[retired]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RetiredConfig {
+    pub accounts: Vec<String>,
+    pub from: NaiveDate,
+}
+
+/// One `[[amount_limit]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AmountLimitConfig {
+    pub account: Option<String>,
+    pub max: String,
+}
+
+/// One `[[script]]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptConfig {
+    pub path: String,            // Relative to the rules file
+    pub name: Option<String>,    // Default: the file name without .rhai
+    pub timeout_ms: Option<u64>, // Wall-clock limit per transaction
+}
+
+impl ScriptConfig {
+    pub fn name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            Path::new(&self.path).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_else(|| self.path.clone())
+        })
+    }
+}
+
+/// The `[approval]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ApprovalConfig {
+    pub approvers: Vec<String>, // DIDs or group names
+}
+
+/// The `[delegation]` table
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DelegationConfig {
+    pub roots: Vec<String>, // DIDs or group names
+}
+
+/// A rules file
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RulesConfig {
+    pub chart: Option<String>,       // Chart of accounts (relative to the rules file)
+    #[serde(default)]
+    pub strict_chart: bool,          // Also reject postings against an account's normal balance
+    pub period_lock: Option<String>, // YYYY-MM-DD or a fiscal period, inclusive
+    pub fiscal: Option<String>,      // Fiscal calendar for period_lock (relative to the rules file)
+    #[serde(default)]
+    pub amount_limit: Vec<AmountLimitConfig>,
+    #[serde(default)]
+    pub retired: Vec<RetiredConfig>,
+    #[serde(default)]
+    pub pairing: Vec<PairingConfig>,
+    #[serde(default)]
+    pub authors: Vec<String>,                  // DIDs or group names; empty: anyone
+    #[serde(default)]
+    pub groups: BTreeMap<String, Vec<String>>, // Group name -> DIDs
+    #[serde(default)]
+    pub script: Vec<ScriptConfig>,
+    #[serde(default)]
+    pub acl: Vec<AclConfig>,
+    pub approval: Option<ApprovalConfig>,
+    pub delegation: Option<DelegationConfig>,
+}
+
+impl RulesConfig {
+    /// A rules file's text, with any paths in it left as written
+    pub fn parse(text: &str) -> Result<Self, LedgerError> {
+        toml::from_str(text).map_err(|e| LedgerError::Config(format!("Invalid rules: {}", e)))
+    }
+
+    pub fn load(path: &str) -> Result<Self, LedgerError> {
+        let data = fs::read_to_string(path).map_err(|e| LedgerError::Io(format!("Could not read rules file {}: {}", path, e)))?;
+        let mut config: RulesConfig = toml::from_str(&data)
+            .map_err(|e| LedgerError::Config(format!("Invalid rules file {}: {}", path, e)))?;
+        // Paths inside the file are relative to it, not to the working directory
+        if let Some(dir) = Path::new(path).parent() {
+            let relative = |file: &str| dir.join(file).to_string_lossy().into_owned();
+            config.chart = config.chart.as_deref().map(relative);
+            config.fiscal = config.fiscal.as_deref().map(relative);
+            for script in &mut config.script {
+                script.path = relative(&script.path);
+            }
+        }
+        Ok(config)
+    }
+
+    /// The two-role workflow, if the file has an `[approval]` table
+    pub fn approval_workflow(&self) -> Result<Option<ApprovalWorkflow>, LedgerError> {
+        self.approval.as_ref().map(|approval| ApprovalWorkflow::new(&approval.approvers, &self.groups)).transpose()
+    }
+
+    /// The registry of trusted authors, if the file lists `authors`
+    pub fn author_registry(&self) -> Result<Option<AuthorRegistry>, LedgerError> {
+        if self.authors.is_empty() {
+            return Ok(None);
+        }
+        AuthorRegistry::new(&self.authors, &self.groups).map(Some)
+    }
+
+    /// The trusted roots of delegated posting rights, if the file has a `[delegation]` table
+    pub fn delegation_policy(&self) -> Result<Option<DelegationPolicy>, LedgerError> {
+        self.delegation.as_ref().map(|delegation| DelegationPolicy::new(&delegation.roots, &self.groups)).transpose()
+    }
+}
+
+/// Rules run in order; each reports on its own
+#[derive(Default)]
+pub struct ValidationPipeline {
+    rules: Vec<Box<dyn ValidationRule>>,
+}
+
+impl ValidationPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rules a rules file turns on, scripts compiled
+    pub fn from_config(config: &RulesConfig) -> Result<Self, LedgerError> {
+        let mut pipeline = Self::new();
+        if let Some(registry) = config.author_registry()? {
+            pipeline.push(registry);
+        }
+        if let Some(chart) = &config.chart {
+            pipeline.push(AccountsExist { chart: ChartOfAccounts::load(chart)?, strict: config.strict_chart });
+        }
+        if let Some(period) = &config.period_lock {
+            let calendar = config.fiscal.as_deref().map(FiscalCalendar::load).transpose()?.unwrap_or_default();
+            let through = calendar.cutoff(period).map_err(|e| e.context("period_lock"))?;
+            pipeline.push(PeriodLock { locked_through: through.date, adjusting_period_open: !through.adjustments });
+        }
+        for limit in &config.amount_limit {
+            let max_cents = parse_cents(&limit.max).map_err(|e| e.context("amount_limit"))?;
+            pipeline.push(AmountLimit { account: limit.account.clone(), max_cents });
+        }
+        for retired in &config.retired {
+            if retired.accounts.is_empty() {
+                return Err(LedgerError::Config("retired: no accounts listed".to_string()));
+            }
+            let accounts = retired.accounts.iter().map(|pattern| AccountPattern::parse(pattern)).collect::<Result<_, _>>()
+                .map_err(|e| e.context("retired"))?;
+            pipeline.push(RetiredAccounts { accounts, from: retired.from });
+        }
+        for pairing in &config.pairing {
+            let patterns = |patterns: &[String], key: &str| {
+                if patterns.is_empty() {
+                    return Err(LedgerError::Config(format!("pairing: no {} listed", key)));
+                }
+                patterns.iter().map(|pattern| AccountPattern::parse(pattern)).collect::<Result<Vec<_>, _>>().map_err(|e| e.context("pairing"))
+            };
+            pipeline.push(AllowedPairing { accounts: patterns(&pairing.accounts, "accounts")?, with: patterns(&pairing.with, "with")? });
+        }
+        for acl in &config.acl {
+            pipeline.push(AccountAcl::from_config(acl, &config.groups)?);
+        }
+        #[cfg(feature = "scripting")]
+        for script in &config.script {
+            pipeline.push(crate::script::ScriptRule::load(&script.name(), &script.path, &config.groups,
+                script.timeout_ms.map_or(crate::script::DEFAULT_TIMEOUT, std::time::Duration::from_millis))?);
+        }
+        #[cfg(not(feature = "scripting"))]
+        if let Some(script) = config.script.first() {
+            return Err(LedgerError::Config(format!("Rule script {} needs a build with the scripting feature", script.path)));
+        }
+        Ok(pipeline)
+    }
+
+    pub fn push(&mut self, rule: impl ValidationRule + 'static) {
+        self.rules.push(Box::new(rule));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Every rule's outcome, by rule name
+    pub fn check(&self, tx: &Transaction) -> Vec<(&str, Result<(), LedgerError>)> {
+        self.rules.iter().map(|rule| (rule.name(), rule.check(tx))).collect()
+    }
+
+    /// The first rule that fails
+    pub fn validate(&self, tx: &Transaction) -> Result<(), LedgerError> {
+        for rule in &self.rules {
+            rule.check(tx).map_err(|e| e.context(format!("Rule {}", rule.name())))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::TransactionBuilder;
+
+    fn payment(amount: &str) -> Transaction {
+        TransactionBuilder::new()
+            .timestamp(1_700_000_000)
+            .entry("68100", amount, "0.00")
+            .entry("10100", "0.00", amount)
+            .memo("Payment")
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn amount_limits_cap_the_total_or_one_account() {
+        let total = AmountLimit { account: None, max_cents: 50_000 };
+        total.check(&payment("500.00")).unwrap();
+        assert!(total.check(&payment("500.01")).is_err());
+
+        let expenses = AmountLimit { account: Some("68*".to_string()), max_cents: 10_000 };
+        expenses.check(&payment("100.00")).unwrap();
+        assert!(expenses.check(&payment("100.01")).is_err());
+        let cash = AmountLimit { account: Some("10100".to_string()), max_cents: 10_000 };
+        assert!(cash.check(&payment("100.01")).is_err());
+    }
+
+    #[test]
+    fn negative_or_overflowing_amounts_cannot_slip_under_a_limit() {
+        let total = AmountLimit { account: None, max_cents: 50_000 };
+        let mut negative = payment("1000.00");
+        negative.entries.push(negative.entries[0].clone());
+        negative.entries[2].debit = "-999.00".to_string();
+        assert!(matches!(total.check(&negative), Err(LedgerError::Amount(_))));
+
+        let mut overflow = payment("92233720368547758.07");
+        overflow.entries[1] = overflow.entries[0].clone();
+        assert!(matches!(total.check(&overflow), Err(LedgerError::Amount(_))));
+
+        let expenses = AmountLimit { account: Some("68100".to_string()), max_cents: 10_000 };
+        let mut negative_entry = payment("1.00");
+        negative_entry.entries[0].debit = "-1000.00".to_string();
+        assert!(matches!(expenses.check(&negative_entry), Err(LedgerError::Amount(_))));
+    }
+}
@@ -0,0 +1,276 @@
+//! Core Verification Functions
+//! The cryptographic check (who signed it, and was it changed?) and the
+//! financial check (do debits equal credits?).
+
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::amount::{format_cents, parse_cents};
+use crate::approval::verify_preparation;
+use crate::did::{DidKeyResolver, DidResolver};
+use crate::error::LedgerError;
+use crate::hashing::HashAlg;
+use crate::keys::SigAlg;
+use crate::model::{SignedTransaction, Transaction};
+use crate::multisig::verify_quorum;
+use crate::private_memo::check_private_memo;
+
+/// Decodes a signature string. Legacy files store exactly 128 hex characters;
+/// anything else is treated as multibase (e.g. 'z...' for base58btc).
+pub fn decode_signature(encoded: &str) -> Result<Vec<u8>, LedgerError> {
+    if encoded.len() == 128 && encoded.chars().all(|c| c.is_ascii_hexdigit()) {
+        return hex::decode(encoded).map_err(|e| LedgerError::Signature(format!("Invalid hex signature: {:?}", e)));
+    }
+    multibase::decode(encoded)
+        .map(|(_, bytes)| bytes)
+        .map_err(|e| LedgerError::Signature(format!("Invalid multibase signature: {:?}", e)))
+}
+
+/// Checks that an envelope digest (multibase multihash) matches our own hash.
+/// Only called once the signature is valid, so a mismatch means the sender
+/// recorded a digest of different bytes, or under a different algorithm,
+/// than the ones they signed.
+pub fn verify_digest(encoded: &str, hash_alg: HashAlg, tx_hash: &[u8]) -> Result<(), LedgerError> {
+    let (_, multihash) = multibase::decode(encoded)
+        .map_err(|e| LedgerError::Signature(format!("Invalid multibase digest: {:?}", e)))?;
+    match multihash.as_slice() {
+        [code, len, digest @ ..] if *len as usize == digest.len() => {
+            let digest_alg = HashAlg::from_multihash_code(*code)
+                .ok_or_else(|| LedgerError::Signature(format!("Unsupported multihash code 0x{:02x} in digest", code)))?;
+            if digest_alg != hash_alg {
+                Err(LedgerError::Signature(format!("Envelope digest is {} but the payload is signed over {}", digest_alg, hash_alg)))
+            } else if digest == tx_hash {
+                Ok(())
+            } else {
+                Err(LedgerError::Signature(format!("Envelope digest does not match the signed payload's {}.", hash_alg)))
+            }
+        }
+        [_, ..] => Err(LedgerError::Signature("Malformed multihash in digest".to_string())),
+        [] => Err(LedgerError::Signature("Empty digest".to_string())),
+    }
+}
+
+/// Verifies the cryptographic signature against the transaction hash (did:key authors)
+pub fn verify_signature(signed_tx: &SignedTransaction) -> Result<(), LedgerError> {
+    verify_signature_with(signed_tx, &DidKeyResolver)
+}
+
+/// Verifies one signature by `did` over a payload hash. `declared` is the
+/// sig_alg recorded next to the signature, if any.
+pub fn verify_signature_by(did: &str, declared: Option<SigAlg>, signature: &str, tx_hash: &[u8], resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+    // 1. Get the Public Key from the DID (Authentication)
+    let public_key = resolver.resolve_public_key(did)?;
+
+    // 2. The key decides the algorithm; a declared sig_alg must agree with it
+    let sig_alg = public_key.sig_alg();
+    if let Some(declared) = declared {
+        if declared != sig_alg {
+            return Err(LedgerError::Signature(format!("Envelope sig_alg {:?} does not match the signer's {:?} key", declared, sig_alg)));
+        }
+    }
+
+    // 3. Verify the Signature against the Hash of the payload (Integrity)
+    let signature_bytes = decode_signature(signature)?;
+    public_key.verify(tx_hash, &signature_bytes)
+}
+
+/// Verifies the author's signature, resolving their key with `resolver`
+pub fn verify_signature_with(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+    let tx_hash = signed_tx.payload.get_hash();
+    verify_signature_by(&signed_tx.payload.author_did, signed_tx.sig_alg, &signed_tx.signature, &tx_hash, resolver)?;
+
+    // If the envelope carries a digest, it must agree with what was signed
+    if let Some(digest) = &signed_tx.digest {
+        verify_digest(digest, signed_tx.payload.hash_alg(), &tx_hash)?;
+    }
+    verify_preparation(signed_tx, resolver)
+}
+
+/// Parses one side of an entry into cents. Amounts are decimal strings with
+/// at most two decimals; "NaN", "inf" and negative amounts are rejected.
+pub fn entry_cents(amount: &str, side: &str) -> Result<i64, LedgerError> {
+    let cents = parse_cents(amount)
+        .map_err(|e| LedgerError::Amount(format!("Invalid {} amount: {}", side, e)))?;
+    if cents < 0 {
+        return Err(LedgerError::Amount(format!("Invalid {} amount '{}': amounts are never negative", side, amount)));
+    }
+    Ok(cents)
+}
+
+pub(crate) fn add_cents(total: &mut i64, cents: i64) -> Result<(), LedgerError> {
+    *total = total.checked_add(cents).ok_or_else(|| LedgerError::Amount("Amounts add up to more than a ledger can hold".to_string()))?;
+    Ok(())
+}
+
+/// Sums every entry's debits and credits in cents, failing on invalid amounts
+pub fn balance_totals(tx: &Transaction) -> Result<(i64, i64), LedgerError> {
+    let mut total_debits = 0;
+    let mut total_credits = 0;
+    for entry in &tx.entries {
+        add_cents(&mut total_debits, entry_cents(&entry.debit, "debit")?)?;
+        add_cents(&mut total_credits, entry_cents(&entry.credit, "credit")?)?;
+    }
+    Ok((total_debits, total_credits))
+}
+
+/// Checks an ISO 4217 alphabetic code: three uppercase letters
+pub fn check_currency(code: &str) -> Result<(), LedgerError> {
+    if code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase()) {
+        Ok(())
+    } else {
+        Err(LedgerError::Amount(format!("'{}' is not an ISO 4217 currency code", code)))
+    }
+}
+
+/// Debits and credits in cents, summed separately for each currency.
+/// Entries without a currency are grouped under None.
+pub fn currency_totals(tx: &Transaction) -> Result<BTreeMap<Option<&str>, (i64, i64)>, LedgerError> {
+    let mut totals = BTreeMap::new();
+    for entry in &tx.entries {
+        if let Some(currency) = &entry.currency {
+            check_currency(currency)?;
+        }
+        let (debits, credits) = totals.entry(entry.currency.as_deref()).or_insert((0, 0));
+        add_cents(debits, entry_cents(&entry.debit, "debit")?)?;
+        add_cents(credits, entry_cents(&entry.credit, "credit")?)?;
+    }
+    Ok(totals)
+}
+
+/// IFRS/Accounting Check: Ensures total debits equal total credits in every
+/// currency, so a EUR debit cannot be balanced by a USD credit
+pub fn balance_check(tx: &Transaction) -> Result<(), LedgerError> {
+    for (currency, (total_debits, total_credits)) in currency_totals(tx)? {
+        // Exact: amounts are compared in integer cents
+        if total_debits != total_credits {
+            let scope = currency.map(|c| format!(" in {}", c)).unwrap_or_default();
+            return Err(LedgerError::Imbalance(format!("Financial imbalance detected{}: Debits ({}) != Credits ({})",
+                scope, format_cents(total_debits), format_cents(total_credits))));
+        }
+    }
+    Ok(())
+}
+
+/// Full verification: a valid signature by the author, any approvals its
+/// signing policy requires, a balanced payload, and a private memo that
+/// matches its commitment or was validly redacted
+pub fn verify(signed_tx: &SignedTransaction) -> Result<(), LedgerError> {
+    verify_with(signed_tx, &DidKeyResolver)
+}
+
+/// Full verification, resolving the author's key with `resolver`
+pub fn verify_with(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Result<(), LedgerError> {
+    verify_signature_with(signed_tx, resolver)?;
+    verify_quorum(signed_tx, resolver)?;
+    balance_check(&signed_tx.payload)?;
+    check_private_memo(signed_tx, resolver)
+}
+
+/// The outcome of one check, for machine-readable reports
+#[derive(Serialize, Debug, Clone)]
+pub struct CheckResult {
+    pub check: String, // "signature", "approvals", "balance" or "private_memo"
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<u16>, // LedgerError::code of the failure
+}
+
+/// Every check's outcome for one transaction. Unlike [`verify_with`], all
+/// checks run even after one fails, so a caller sees every problem at once.
+#[derive(Serialize, Debug, Clone)]
+pub struct Verdict {
+    pub valid: bool,
+    pub hash: String, // Hex payload hash (what prev_hash refers to)
+    pub cid: String,
+    pub checks: Vec<CheckResult>,
+}
+
+/// Runs the same checks as [`verify_with`] and reports each one
+pub fn verdict_with(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> Verdict {
+    let results = [
+        ("signature", verify_signature_with(signed_tx, resolver)),
+        ("approvals", verify_quorum(signed_tx, resolver).map(|_| ())),
+        ("balance", balance_check(&signed_tx.payload)),
+        ("private_memo", check_private_memo(signed_tx, resolver)),
+    ];
+    let checks: Vec<CheckResult> = results.into_iter()
+        .map(|(check, result)| CheckResult {
+            check: check.to_string(),
+            ok: result.is_ok(),
+            code: result.as_ref().err().map(LedgerError::code),
+            error: result.err().map(|e| e.to_string()),
+        })
+        .collect();
+    Verdict {
+        valid: checks.iter().all(|c| c.ok),
+        hash: signed_tx.payload.hash_hex(),
+        cid: signed_tx.cid(),
+        checks,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::Account;
+
+    fn payload(entries: serde_json::Value) -> Transaction {
+        serde_json::from_value(serde_json::json!({
+            "timestamp": 1700000000,
+            "author_did": "",
+            "entries": entries,
+            "memo": "test",
+        }))
+        .expect("a valid payload")
+    }
+
+    fn entry(debit: &str, credit: &str) -> serde_json::Value {
+        serde_json::json!({ "account_id": "1000", "debit": debit, "credit": credit })
+    }
+
+    #[test]
+    fn balanced_amounts_pass() {
+        let tx = payload(serde_json::json!([entry("10.10", "0"), entry("0.1", "10.20")]));
+        assert!(balance_check(&tx).is_ok());
+    }
+
+    #[test]
+    fn imbalance_of_one_cent_fails() {
+        let tx = payload(serde_json::json!([entry("10.01", "0"), entry("0", "10.00")]));
+        assert!(matches!(balance_check(&tx), Err(LedgerError::Imbalance(_))));
+    }
+
+    #[test]
+    fn non_finite_amounts_are_rejected() {
+        for amount in ["NaN", "nan", "inf", "-inf", "infinity", "1e3"] {
+            let tx = payload(serde_json::json!([entry(amount, "0"), entry("0", amount)]));
+            assert!(matches!(balance_check(&tx), Err(LedgerError::Amount(_))), "{} was accepted", amount);
+        }
+    }
+
+    #[test]
+    fn negative_amounts_are_rejected() {
+        let tx = payload(serde_json::json!([entry("-5.00", "0"), entry("0", "-5.00")]));
+        assert!(matches!(balance_check(&tx), Err(LedgerError::Amount(_))));
+    }
+
+    #[test]
+    fn overflowing_totals_are_rejected() {
+        let tx = payload(serde_json::json!([entry("90000000000000000", "0"), entry("90000000000000000", "0")]));
+        assert!(matches!(balance_check(&tx), Err(LedgerError::Amount(_))));
+    }
+
+    #[test]
+    fn signed_nan_transaction_fails_verification() {
+        let account = Account::generate();
+        let mut tx = payload(serde_json::json!([entry("NaN", "0"), entry("0", "NaN")]));
+        tx.author_did = account.did.clone();
+        let signed_tx = account.sign(tx);
+        assert!(verify_signature(&signed_tx).is_ok());
+        assert!(verify(&signed_tx).is_err());
+        assert!(!verdict_with(&signed_tx, &DidKeyResolver).valid);
+    }
+}
@@ -0,0 +1,95 @@
+//! Payload Versions
+//! Every change to what a payload's signature covers gets a version number.
+//! Payloads signed before the `version` field existed are dated by their
+//! other fields: no `canonicalization` is version 0, JCS without `version`
+//! is version 1. [`VERSIONS`] is the registry of readers: what a payload of
+//! each version must look like, and how to lift one from the version before.
+//!
+//! Every version stays readable, so existing ledgers keep verifying. Lifting
+//! a payload changes its hash, so only its author can do that
+//! (`tlc migrate --resign`); everything else about a file can be rewritten
+//! around the original signature.
+
+use crate::error::LedgerError;
+use crate::model::{Canonicalization, Transaction};
+
+/// The version every new payload is signed at
+pub const CURRENT_VERSION: u32 = 2;
+
+/// The first version written out in the `version` field
+const FIRST_EXPLICIT_VERSION: u32 = 2;
+
+/// One payload format
+pub struct PayloadVersion {
+    pub version: u32,
+    pub summary: &'static str,
+    check: fn(&Transaction) -> Result<(), String>, // What a payload of this version must look like
+    upgrade: fn(&mut Transaction),                 // Lifts a payload from the version before
+}
+
+/// Every version this release reads, oldest first
+pub static VERSIONS: &[PayloadVersion] = &[
+    PayloadVersion {
+        version: 0,
+        summary: "signed over serde_json's output, in field order",
+        check: |_| Ok(()),
+        upgrade: |_| {},
+    },
+    PayloadVersion {
+        version: 1,
+        summary: "signed over the RFC 8785 (JCS) canonical form",
+        check: requires_jcs,
+        upgrade: |tx| tx.canonicalization = Some(Canonicalization::Jcs),
+    },
+    PayloadVersion {
+        version: 2,
+        summary: "names its version in the signed `version` field",
+        check: requires_jcs,
+        upgrade: |tx| tx.version = Some(2),
+    },
+];
+
+fn requires_jcs(tx: &Transaction) -> Result<(), String> {
+    match tx.canonicalization {
+        Some(Canonicalization::Jcs) => Ok(()),
+        None => Err("it has no canonicalization (JCS is required)".to_string()),
+    }
+}
+
+/// The payload's version: its `version` field, or read off the fields it has
+pub fn version_of(tx: &Transaction) -> u32 {
+    match (tx.version, tx.canonicalization) {
+        (Some(version), _) => version,
+        (None, Some(_)) => 1,
+        (None, None) => 0,
+    }
+}
+
+/// The reader for a version, or why this release cannot read it
+pub fn reader(version: u32) -> Result<&'static PayloadVersion, LedgerError> {
+    VERSIONS.iter().find(|v| v.version == version).ok_or_else(|| LedgerError::Serialization(format!(
+        "Payload version {} is newer than this release reads (up to {}); upgrade true-ledger to read it",
+        version, CURRENT_VERSION)))
+}
+
+/// Checks a payload is a well-formed instance of its version
+pub fn check_version(tx: &Transaction) -> Result<(), LedgerError> {
+    if let Some(version) = tx.version.filter(|v| *v < FIRST_EXPLICIT_VERSION) {
+        return Err(LedgerError::Serialization(format!(
+            "Payload names version {}, which is only ever implied by its fields", version)));
+    }
+    let version = version_of(tx);
+    (reader(version)?.check)(tx)
+        .map_err(|reason| LedgerError::Serialization(format!("Not a valid version {} payload: {}", version, reason)))
+}
+
+/// Lifts a payload to the current version, returning the versions it passed
+/// through. Payloads already there (or later) come back unchanged.
+pub fn upgrade(mut tx: Transaction) -> (Transaction, Vec<&'static PayloadVersion>) {
+    let from = version_of(&tx);
+    let steps: Vec<&'static PayloadVersion> = VERSIONS.iter().filter(|v| v.version > from).collect();
+    for step in &steps {
+        (step.upgrade)(&mut tx);
+    }
+    (tx, steps)
+}
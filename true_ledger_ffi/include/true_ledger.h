@@ -0,0 +1,74 @@
+/*
+ * True Ledger Core C API
+ *
+ * Sign and verify ledger transactions from Swift, Kotlin (via JNI) or any
+ * language with a C FFI. Link libtrue_ledger_ffi.a (iOS) or
+ * libtrue_ledger_ffi.so (Android).
+ *
+ * Strings in and out are NUL-terminated UTF-8. Transactions are JSON in the
+ * same format the `tlc` command line reads and writes. Every function returns
+ * a status code below; when it is not TL_OK, tl_last_error() explains why and
+ * tl_last_error_code() says what kind of failure it was.
+ * Strings returned through char ** out-parameters belong to the caller and
+ * must be released with tl_string_free().
+ */
+
+#ifndef TRUE_LEDGER_H
+#define TRUE_LEDGER_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define TL_OK 0           /* Success; for tl_verify*: the transaction is valid */
+#define TL_INVALID 1      /* The transaction was read but failed verification */
+#define TL_ERR_ARGUMENT 2 /* A NULL pointer, or a string that is not UTF-8 */
+#define TL_ERR_PARSE 3    /* The JSON is not a (signed) transaction */
+#define TL_ERR_KEY 4      /* The secret key or signature algorithm was rejected */
+#define TL_ERR_INTERNAL 5 /* A bug in the library; nothing was returned */
+
+/* Message for the last failed call on this thread, or NULL. Owned by the
+ * library; valid until the next call on the same thread. */
+const char *tl_last_error(void);
+
+/* The core error code behind the last failed call on this thread, or 0 if
+ * the call succeeded or failed before reaching the core. Codes are stable:
+ *   1 serialization   2 I/O        10 DID          11 key
+ *  20 signature      21 approval   30 imbalance    31 amount
+ *  40 chain          41 timestamp  50 storage    60 configuration */
+uint16_t tl_last_error_code(void);
+
+/* Releases a string returned through an out-parameter. NULL is ignored. */
+void tl_string_free(char *s);
+
+/* Runs every check (signature, approvals, balance) and writes the verdict
+ * as JSON to *verdict_json_out:
+ *   {"valid":true,"hash":"...","cid":"bafy...","checks":[{"check":"signature","ok":true},...]}
+ * Failed checks also carry "error" (the message) and "code" (as for
+ * tl_last_error_code). Returns TL_OK if valid, TL_INVALID if any check
+ * failed; both write a verdict. */
+int32_t tl_verify(const char *signed_json, char **verdict_json_out);
+
+/* TL_OK if the author's signature (and any digest) matches the payload */
+int32_t tl_verify_signature(const char *signed_json);
+
+/* TL_OK if the payload's debits equal its credits */
+int32_t tl_verify_balance(const char *signed_json);
+
+/* The did:key for a 32-byte secret key. sig_alg is "Ed25519" (the default
+ * when NULL) or "ES256K". */
+int32_t tl_did_from_secret_key(const uint8_t *secret_key, const char *sig_alg, char **did_out);
+
+/* Signs an unsigned transaction and writes the signed transaction JSON to
+ * *signed_json_out. An empty author_did is filled in with the key's DID; any
+ * other author must match it. */
+int32_t tl_sign(const char *transaction_json, const uint8_t *secret_key, const char *sig_alg,
+                char **signed_json_out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TRUE_LEDGER_H */
@@ -0,0 +1,238 @@
+//! C ABI
+//! Signing and verification for apps that cannot link Rust directly (Swift on
+//! iOS, Kotlin/JNI on Android). include/true_ledger.h declares everything
+//! here; that header is the stable contract.
+//!
+//! Conventions, the same for every function:
+//! - Strings in and out are NUL-terminated UTF-8; transactions are JSON in
+//!   the same format `tlc` reads and writes.
+//! - Every function returns a status code (`TL_OK`, ...). On failure,
+//!   `tl_last_error()` describes what went wrong on the calling thread, and
+//!   `tl_last_error_code()` gives the core's finer-grained error code.
+//! - Strings handed back through `char **` out-parameters belong to the
+//!   caller and must be released with `tl_string_free`.
+
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use true_ledger_core::did_web::DidWebResolver;
+use true_ledger_core::keys::SigAlg;
+use true_ledger_core::verify::{balance_check, verdict_with, verify_signature_with};
+use true_ledger_core::{Account, LedgerError, SignedTransaction, Transaction};
+
+/// Success (for the verify functions: the transaction is valid)
+pub const TL_OK: i32 = 0;
+/// The transaction was read but failed verification
+pub const TL_INVALID: i32 = 1;
+/// A NULL pointer, or a string that is not UTF-8
+pub const TL_ERR_ARGUMENT: i32 = 2;
+/// The JSON is not a (signed) transaction
+pub const TL_ERR_PARSE: i32 = 3;
+/// The secret key or signature algorithm was rejected
+pub const TL_ERR_KEY: i32 = 4;
+/// A bug in the library; nothing was returned
+pub const TL_ERR_INTERNAL: i32 = 5;
+
+/// A failed call: its status code and message
+type Failure = (i32, String);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+    static LAST_ERROR_CODE: Cell<u16> = const { Cell::new(0) };
+}
+
+/// A core error as a failure with the given status; its code is kept for
+/// `tl_last_error_code`
+fn core_failure(status: i32, error: LedgerError) -> Failure {
+    LAST_ERROR_CODE.with(|code| code.set(error.code()));
+    (status, error.to_string())
+}
+
+fn set_last_error(message: Option<String>) {
+    let message = message.map(|m| CString::new(m.replace('\0', " ")).expect("NULs were replaced"));
+    LAST_ERROR.with(|last| *last.borrow_mut() = message);
+}
+
+/// Runs one call: records its error (or clears the last one) and never lets
+/// a panic unwind into the caller's language
+fn run(call: impl FnOnce() -> Result<i32, Failure>) -> i32 {
+    LAST_ERROR_CODE.with(|code| code.set(0));
+    match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(code)) => {
+            set_last_error(None);
+            code
+        }
+        Ok(Err((code, message))) => {
+            set_last_error(Some(message));
+            code
+        }
+        Err(_) => {
+            set_last_error(Some("Internal error (panic) in true_ledger".to_string()));
+            TL_ERR_INTERNAL
+        }
+    }
+}
+
+/// # Safety
+/// `ptr` must be NULL or point to a NUL-terminated string
+unsafe fn read_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, Failure> {
+    if ptr.is_null() {
+        return Err((TL_ERR_ARGUMENT, format!("{} is NULL", name)));
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| (TL_ERR_ARGUMENT, format!("{} is not valid UTF-8", name)))
+}
+
+/// # Safety
+/// `out` must be NULL or point to writable storage for one pointer
+unsafe fn write_str(out: *mut *mut c_char, value: String) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err((TL_ERR_ARGUMENT, "Output pointer is NULL".to_string()));
+    }
+    let value = CString::new(value).map_err(|e| (TL_ERR_INTERNAL, e.to_string()))?;
+    *out = value.into_raw();
+    Ok(())
+}
+
+fn parse_signed(json: &str) -> Result<SignedTransaction, Failure> {
+    SignedTransaction::from_json(json).map_err(|e| (TL_ERR_PARSE, e.message().to_string()))
+}
+
+/// did:key authors resolve locally; did:web documents are fetched over HTTPS
+fn resolver() -> DidWebResolver {
+    DidWebResolver::new(None, true)
+}
+
+/// # Safety
+/// `secret_key` must be NULL or point to 32 readable bytes; `sig_alg` must be
+/// NULL or a NUL-terminated string
+unsafe fn read_account(secret_key: *const u8, sig_alg: *const c_char) -> Result<Account, Failure> {
+    if secret_key.is_null() {
+        return Err((TL_ERR_ARGUMENT, "secret_key is NULL".to_string()));
+    }
+    let sig_alg = if sig_alg.is_null() {
+        SigAlg::Ed25519
+    } else {
+        let name = read_str(sig_alg, "sig_alg")?;
+        serde_json::from_value(serde_json::Value::String(name.to_string()))
+            .map_err(|_| (TL_ERR_KEY, format!("Unknown sig_alg '{}' (expected Ed25519 or ES256K)", name)))?
+    };
+    let bytes: [u8; 32] = std::slice::from_raw_parts(secret_key, 32).try_into().expect("slice is 32 bytes");
+    Account::from_secret_bytes_with(sig_alg, &bytes).map_err(|e| core_failure(TL_ERR_KEY, e))
+}
+
+/// The message for the last failed call on this thread, or NULL. Owned by
+/// the library; valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn tl_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |message| message.as_ptr()))
+}
+
+/// The core error code (`LedgerError::code`) behind the last failed call on
+/// this thread, e.g. 20 for a bad signature or 30 for an imbalance; 0 if the
+/// call succeeded or failed before reaching the core
+#[no_mangle]
+pub extern "C" fn tl_last_error_code() -> u16 {
+    LAST_ERROR_CODE.with(Cell::get)
+}
+
+/// Releases a string returned through an out-parameter. NULL is ignored.
+///
+/// # Safety
+/// `s` must be NULL or a string from this library not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn tl_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Runs every check and writes the verdict as JSON to `*verdict_json_out`
+/// (the same shape `tlc serve` returns from /verify). Returns `TL_OK` if the
+/// transaction is valid and `TL_INVALID` if any check failed; both write a verdict.
+///
+/// # Safety
+/// `signed_json` must be a NUL-terminated string; `verdict_json_out` must
+/// point to writable storage for one pointer
+#[no_mangle]
+pub unsafe extern "C" fn tl_verify(signed_json: *const c_char, verdict_json_out: *mut *mut c_char) -> i32 {
+    run(|| {
+        let signed_tx = parse_signed(read_str(signed_json, "signed_json")?)?;
+        let verdict = verdict_with(&signed_tx, &resolver());
+        let json = serde_json::to_string(&verdict).map_err(|e| (TL_ERR_INTERNAL, e.to_string()))?;
+        write_str(verdict_json_out, json)?;
+        Ok(if verdict.valid { TL_OK } else { TL_INVALID })
+    })
+}
+
+/// `TL_OK` if the author's signature (and any digest) matches the payload
+///
+/// # Safety
+/// `signed_json` must be a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn tl_verify_signature(signed_json: *const c_char) -> i32 {
+    run(|| {
+        let signed_tx = parse_signed(read_str(signed_json, "signed_json")?)?;
+        verify_signature_with(&signed_tx, &resolver()).map_err(|e| core_failure(TL_INVALID, e))?;
+        Ok(TL_OK)
+    })
+}
+
+/// `TL_OK` if the payload's debits equal its credits
+///
+/// # Safety
+/// `signed_json` must be a NUL-terminated string
+#[no_mangle]
+pub unsafe extern "C" fn tl_verify_balance(signed_json: *const c_char) -> i32 {
+    run(|| {
+        let signed_tx = parse_signed(read_str(signed_json, "signed_json")?)?;
+        balance_check(&signed_tx.payload).map_err(|e| core_failure(TL_INVALID, e))?;
+        Ok(TL_OK)
+    })
+}
+
+/// The did:key for a 32-byte secret key. `sig_alg` is "Ed25519" (the
+/// default when NULL) or "ES256K".
+///
+/// # Safety
+/// `secret_key` must point to 32 readable bytes; `sig_alg` must be NULL or a
+/// NUL-terminated string; `did_out` must point to writable storage for one pointer
+#[no_mangle]
+pub unsafe extern "C" fn tl_did_from_secret_key(secret_key: *const u8, sig_alg: *const c_char, did_out: *mut *mut c_char) -> i32 {
+    run(|| {
+        let account = read_account(secret_key, sig_alg)?;
+        write_str(did_out, account.did.clone())?;
+        Ok(TL_OK)
+    })
+}
+
+/// Signs an unsigned transaction and writes the signed transaction JSON to
+/// `*signed_json_out`. An empty `author_did` is filled in with the key's DID;
+/// any other author must match it.
+///
+/// # Safety
+/// `transaction_json` must be a NUL-terminated string; `secret_key` must
+/// point to 32 readable bytes; `sig_alg` must be NULL or a NUL-terminated
+/// string; `signed_json_out` must point to writable storage for one pointer
+#[no_mangle]
+pub unsafe extern "C" fn tl_sign(
+    transaction_json: *const c_char,
+    secret_key: *const u8,
+    sig_alg: *const c_char,
+    signed_json_out: *mut *mut c_char,
+) -> i32 {
+    run(|| {
+        let mut tx: Transaction = serde_json::from_str(read_str(transaction_json, "transaction_json")?)
+            .map_err(|e| (TL_ERR_PARSE, format!("Failed to parse transaction data: {}", e)))?;
+        let account = read_account(secret_key, sig_alg)?;
+        if tx.author_did.is_empty() {
+            tx.author_did = account.did.clone();
+        } else if tx.author_did != account.did {
+            return Err((TL_ERR_KEY, format!("Transaction names author {} but the signing key is {}", tx.author_did, account.did)));
+        }
+        let json = serde_json::to_string_pretty(&account.sign(tx)).map_err(|e| (TL_ERR_INTERNAL, e.to_string()))?;
+        write_str(signed_json_out, json)?;
+        Ok(TL_OK)
+    })
+}
@@ -0,0 +1,182 @@
+//! Python Bindings
+//! `import true_ledger_py` for scripts that sign transactions, check them and
+//! total them up. Build and install into the active virtualenv with:
+//!
+//!   maturin develop -m true_ledger_py/Cargo.toml
+//!
+//! Transactions are passed as dicts (or JSON text) in the same format as the
+//! files `tlc` reads and writes, and come back as dicts. Every failure raises
+//! a subclass of `true_ledger_py.LedgerError`.
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use true_ledger_core::amount::format_cents;
+use true_ledger_core::did::DidResolver;
+use true_ledger_core::did_web::DidWebResolver;
+use true_ledger_core::keys::SigAlg;
+use true_ledger_core::trial_balance::TrialBalance;
+use true_ledger_core::verify::verdict_with;
+use true_ledger_core::{Account, SignedTransaction, Transaction};
+use true_ledger_core::LedgerError as CoreError;
+
+create_exception!(true_ledger_py, LedgerError, PyException, "Base class of every true_ledger_py error");
+create_exception!(true_ledger_py, ParseError, LedgerError, "The data is not a (signed) transaction");
+create_exception!(true_ledger_py, SigningKeyError, LedgerError, "The secret key or signature algorithm was rejected");
+create_exception!(true_ledger_py, DidError, LedgerError, "A malformed DID, or one whose key could not be resolved");
+create_exception!(true_ledger_py, SignatureError, LedgerError, "The signature does not match the payload or its author");
+create_exception!(true_ledger_py, ApprovalError, LedgerError, "The signing policy's quorum of approvals is not met");
+create_exception!(true_ledger_py, BalanceError, LedgerError, "Debits do not equal credits");
+
+/// The exception for a core error code (LedgerError::code)
+fn exception(code: Option<u16>, message: String) -> PyErr {
+    match code {
+        Some(CoreError::SERIALIZATION | CoreError::AMOUNT) => ParseError::new_err(message),
+        Some(CoreError::KEY) => SigningKeyError::new_err(message),
+        Some(CoreError::DID) => DidError::new_err(message),
+        Some(CoreError::SIGNATURE) => SignatureError::new_err(message),
+        Some(CoreError::APPROVAL) => ApprovalError::new_err(message),
+        Some(CoreError::IMBALANCE) => BalanceError::new_err(message),
+        _ => LedgerError::new_err(message),
+    }
+}
+
+fn core_error(error: CoreError) -> PyErr {
+    exception(Some(error.code()), error.to_string())
+}
+
+/// A dict, list or JSON string as a model type
+fn from_python<T: DeserializeOwned>(value: &Bound<'_, PyAny>) -> PyResult<T> {
+    let text = match value.extract::<String>() {
+        Ok(text) => text,
+        Err(_) => value.py().import("json")?.call_method1("dumps", (value,))?.extract()?,
+    };
+    serde_json::from_str(&text).map_err(|e| ParseError::new_err(format!("Failed to parse transaction data: {}", e)))
+}
+
+/// A model type as plain dicts and lists
+fn to_python<'py, T: Serialize>(py: Python<'py>, value: &T) -> PyResult<Bound<'py, PyAny>> {
+    let text = serde_json::to_string(value).map_err(|e| LedgerError::new_err(e.to_string()))?;
+    py.import("json")?.call_method1("loads", (text,))
+}
+
+/// did:key resolves locally; did:web through the same cache `tlc` uses
+fn resolver(offline: bool) -> DidWebResolver {
+    DidWebResolver::new(Some(DidWebResolver::default_cache_dir()), !offline)
+}
+
+/// Raises the exception for the first failed check
+fn check(signed_tx: &SignedTransaction, resolver: &dyn DidResolver) -> PyResult<true_ledger_core::verify::Verdict> {
+    let verdict = verdict_with(signed_tx, resolver);
+    if let Some(failed) = verdict.checks.iter().find(|c| !c.ok) {
+        return Err(exception(failed.code, failed.error.clone().unwrap_or_default()));
+    }
+    Ok(verdict)
+}
+
+/// sign_transaction(transaction, secret_key, sig_alg="Ed25519") -> dict
+///
+/// Signs an unsigned transaction with a 32-byte secret key. An empty
+/// author_did is filled in with the key's did:key; any other author must match it.
+#[pyfunction]
+#[pyo3(signature = (transaction, secret_key, sig_alg = "Ed25519"))]
+fn sign_transaction<'py>(py: Python<'py>, transaction: &Bound<'py, PyAny>, secret_key: &[u8], sig_alg: &str) -> PyResult<Bound<'py, PyAny>> {
+    let mut tx: Transaction = from_python(transaction)?;
+    let sig_alg: SigAlg = serde_json::from_value(serde_json::Value::String(sig_alg.to_string()))
+        .map_err(|_| SigningKeyError::new_err(format!("Unknown sig_alg '{}' (expected Ed25519 or ES256K)", sig_alg)))?;
+    let secret_key: [u8; 32] = secret_key.try_into()
+        .map_err(|_| SigningKeyError::new_err(format!("secret_key must be 32 bytes, got {}", secret_key.len())))?;
+    let account = Account::from_secret_bytes_with(sig_alg, &secret_key).map_err(core_error)?;
+
+    if tx.author_did.is_empty() {
+        tx.author_did = account.did.clone();
+    } else if tx.author_did != account.did {
+        return Err(SigningKeyError::new_err(format!("Transaction names author {} but the signing key is {}", tx.author_did, account.did)));
+    }
+    to_python(py, &account.sign(tx))
+}
+
+/// verify_signed_transaction(signed_transaction, offline=False) -> dict
+///
+/// Checks the signature, any required approvals and the balance. Returns the
+/// verdict (valid, hash, cid, checks) or raises DidError, SignatureError,
+/// ApprovalError or BalanceError for the first check that fails. With
+/// offline=True, did:web authors are resolved from the DID cache only.
+#[pyfunction]
+#[pyo3(signature = (signed_transaction, offline = false))]
+fn verify_signed_transaction<'py>(py: Python<'py>, signed_transaction: &Bound<'py, PyAny>, offline: bool) -> PyResult<Bound<'py, PyAny>> {
+    let signed_tx: SignedTransaction = from_python(signed_transaction)?;
+    let verdict = check(&signed_tx, &resolver(offline))?;
+    to_python(py, &verdict)
+}
+
+/// trial_balance(signed_transactions, offline=False) -> dict
+///
+/// Verifies every transaction, then totals debits and credits per account:
+/// {"accounts": {id: {"debit", "credit", "net"}}, "debit", "credit", "transactions"},
+/// with amounts as decimal.Decimal. Raises on the first transaction that fails.
+#[pyfunction]
+#[pyo3(signature = (signed_transactions, offline = false))]
+fn trial_balance<'py>(py: Python<'py>, signed_transactions: &Bound<'py, PyAny>, offline: bool) -> PyResult<Bound<'py, PyDict>> {
+    let signed_txs: Vec<SignedTransaction> = from_python(signed_transactions)?;
+    let resolver = resolver(offline);
+    let mut trial_balance = TrialBalance::new();
+    for (i, signed_tx) in signed_txs.iter().enumerate() {
+        // Only verified transactions belong in the books
+        check(signed_tx, &resolver).and_then(|_| trial_balance.post(&signed_tx.payload).map_err(core_error))
+            .map_err(|e| {
+                let message = format!("Transaction #{}: {}", i, e.value(py));
+                PyErr::from_type(e.get_type(py), message)
+            })?;
+    }
+    trial_balance.check().map_err(core_error)?;
+
+    let decimal = py.import("decimal")?.getattr("Decimal")?;
+    let amount = |cents: i64| decimal.call1((format_cents(cents),));
+    let accounts = PyDict::new(py);
+    for (account_id, totals) in &trial_balance.accounts {
+        let row = PyDict::new(py);
+        row.set_item("debit", amount(totals.debit_cents)?)?;
+        row.set_item("credit", amount(totals.credit_cents)?)?;
+        row.set_item("net", amount(totals.net_cents())?)?;
+        accounts.set_item(account_id, row)?;
+    }
+    let (debits, credits) = trial_balance.totals().map_err(core_error)?;
+    let result = PyDict::new(py);
+    result.set_item("accounts", accounts)?;
+    result.set_item("debit", amount(debits)?)?;
+    result.set_item("credit", amount(credits)?)?;
+    result.set_item("transactions", trial_balance.transactions)?;
+    Ok(result)
+}
+
+/// resolve_did(did, offline=False) -> dict
+///
+/// The W3C DID Document of a did:key or did:web, or raises DidError.
+#[pyfunction]
+#[pyo3(signature = (did, offline = false))]
+fn resolve_did<'py>(py: Python<'py>, did: &str, offline: bool) -> PyResult<Bound<'py, PyAny>> {
+    let document = resolver(offline).resolve_document(did).map_err(core_error)?;
+    to_python(py, &document)
+}
+
+#[pymodule]
+fn true_ledger_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = m.py();
+    m.add_function(wrap_pyfunction!(sign_transaction, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_signed_transaction, m)?)?;
+    m.add_function(wrap_pyfunction!(trial_balance, m)?)?;
+    m.add_function(wrap_pyfunction!(resolve_did, m)?)?;
+    m.add("LedgerError", py.get_type::<LedgerError>())?;
+    m.add("ParseError", py.get_type::<ParseError>())?;
+    m.add("SigningKeyError", py.get_type::<SigningKeyError>())?;
+    m.add("DidError", py.get_type::<DidError>())?;
+    m.add("SignatureError", py.get_type::<SignatureError>())?;
+    m.add("ApprovalError", py.get_type::<ApprovalError>())?;
+    m.add("BalanceError", py.get_type::<BalanceError>())?;
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! WebAssembly Bindings
+//! The verification path for browsers and other JavaScript hosts, so a
+//! client can check a signed transaction itself instead of trusting the
+//! server that handed it over. Build with:
+//!
+//!   wasm-pack build true_ledger_wasm --target web
+//!
+//! Transactions cross the boundary as JSON text, in the same format as the
+//! files `tlc` reads and writes. did:key authors resolve locally; for did:web
+//! authors the caller fetches the DID Documents and passes them in.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use true_ledger_core::did::{self, DidKeyResolver, DidResolver};
+use true_ledger_core::did_web::assertion_key_from_document;
+use true_ledger_core::keys::VerifyingKey;
+use true_ledger_core::verify::{self, verdict_with};
+use true_ledger_core::{LedgerError, SignedTransaction};
+use wasm_bindgen::prelude::*;
+
+/// Resolves did:web from documents the caller already fetched, did:key locally
+struct DocumentResolver {
+    documents: HashMap<String, Value>,
+}
+
+impl DocumentResolver {
+    /// `documents_json` is an object mapping each DID to its DID Document
+    fn from_json(documents_json: Option<String>) -> Result<Self, LedgerError> {
+        let documents = match documents_json {
+            Some(json) => serde_json::from_str(&json).map_err(|e| LedgerError::Serialization(format!("Failed to parse DID Documents: {}", e)))?,
+            None => HashMap::new(),
+        };
+        Ok(DocumentResolver { documents })
+    }
+}
+
+impl DidResolver for DocumentResolver {
+    fn resolve_public_key(&self, did: &str) -> Result<VerifyingKey, LedgerError> {
+        match self.documents.get(did) {
+            Some(document) => assertion_key_from_document(document, did),
+            None if did.starts_with("did:key:") => DidKeyResolver.resolve_public_key(did),
+            None => Err(LedgerError::Did(format!("No DID Document supplied for {}", did))),
+        }
+    }
+}
+
+fn parse_signed(signed_json: &str) -> Result<SignedTransaction, JsError> {
+    SignedTransaction::from_json(signed_json).map_err(|e| JsError::new(e.message()))
+}
+
+/// The raw public key a did:key encodes (32 bytes for Ed25519, 33 for secp256k1)
+#[wasm_bindgen(js_name = didToPublicKey)]
+pub fn did_to_public_key(did: &str) -> Result<Vec<u8>, JsError> {
+    did::did_to_verifying_key(did).map(|key| key.to_bytes()).map_err(JsError::from)
+}
+
+/// Throws unless the author's signature (and any digest) matches the payload
+#[wasm_bindgen(js_name = verifySignature)]
+pub fn verify_signature(signed_json: &str, documents_json: Option<String>) -> Result<(), JsError> {
+    let signed_tx = parse_signed(signed_json)?;
+    let resolver = DocumentResolver::from_json(documents_json)?;
+    verify::verify_signature_with(&signed_tx, &resolver).map_err(JsError::from)
+}
+
+/// Throws unless the payload's debits equal its credits
+#[wasm_bindgen(js_name = verifyBalance)]
+pub fn verify_balance(signed_json: &str) -> Result<(), JsError> {
+    verify::balance_check(&parse_signed(signed_json)?.payload).map_err(JsError::from)
+}
+
+/// Every check's outcome as JSON, in the shape `tlc serve` returns from /verify
+#[wasm_bindgen]
+pub fn verdict(signed_json: &str, documents_json: Option<String>) -> Result<String, JsError> {
+    let signed_tx = parse_signed(signed_json)?;
+    let resolver = DocumentResolver::from_json(documents_json)?;
+    serde_json::to_string(&verdict_with(&signed_tx, &resolver)).map_err(|e| JsError::new(&e.to_string()))
+}